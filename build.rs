@@ -0,0 +1,80 @@
+//! Generates the bindings for the **embree** feature from the
+//! installed Embree 4 headers (see src/accelerators/embree.rs). Set
+//! `EMBREE_DIR` to the directory containing `include/embree4` and
+//! `lib` if Embree isn't installed in a system location.
+
+fn main() {
+    #[cfg(feature = "embree")]
+    embree::generate_bindings();
+}
+
+#[cfg(feature = "embree")]
+mod embree {
+    use std::path::{Path, PathBuf};
+
+    /// The Embree major version the accelerator is written for.
+    const RTC_VERSION_MAJOR: u32 = 4;
+
+    pub fn generate_bindings() {
+        println!("cargo:rerun-if-env-changed=EMBREE_DIR");
+        let mut include_dirs: Vec<PathBuf> = Vec::new();
+        if let Some(dir) = std::env::var_os("EMBREE_DIR") {
+            let dir: PathBuf = PathBuf::from(dir);
+            println!(
+                "cargo:rustc-link-search=native={}",
+                dir.join("lib").display()
+            );
+            include_dirs.push(dir.join("include"));
+        }
+        include_dirs.push(PathBuf::from("/usr/local/include"));
+        include_dirs.push(PathBuf::from("/usr/include"));
+        println!("cargo:rustc-link-lib=embree4");
+        let config: PathBuf = include_dirs
+            .iter()
+            .map(|dir| dir.join("embree4").join("rtcore_config.h"))
+            .find(|config| config.exists())
+            .expect("embree4/rtcore_config.h not found, set EMBREE_DIR");
+        check_version(&config);
+        let header: PathBuf = config.with_file_name("rtcore.h");
+        println!("cargo:rerun-if-changed={}", header.display());
+        let bindings = bindgen::Builder::default()
+            .header(header.to_str().unwrap())
+            .clang_arg(format!(
+                "-I{}",
+                config.parent().unwrap().parent().unwrap().display()
+            ))
+            .allowlist_function("rtc.*")
+            .allowlist_type("RTC.*")
+            .allowlist_var("RTC_.*")
+            // plain integer constants named like in the C API, e.g.
+            // RTC_GEOMETRY_TYPE_TRIANGLE
+            .default_enum_style(bindgen::EnumVariation::Consts)
+            .prepend_enum_name(false)
+            .derive_default(true)
+            .layout_tests(false)
+            .generate()
+            .expect("Unable to generate Embree bindings");
+        let out_dir: PathBuf = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
+        bindings
+            .write_to_file(out_dir.join("embree4.rs"))
+            .expect("Unable to write Embree bindings");
+    }
+
+    /// Makes sure the headers are the ones of the supported major
+    /// version, the API (and ABI) changed between Embree 3 and 4.
+    fn check_version(config: &Path) {
+        let text: String = std::fs::read_to_string(config).unwrap();
+        let major: Option<u32> = text
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("#define RTC_VERSION_MAJOR"))
+            .find_map(|value| value.trim().parse().ok());
+        match major {
+            Some(RTC_VERSION_MAJOR) => {}
+            Some(major) => panic!(
+                "Embree {} found in {:?}, the embree feature needs Embree {}",
+                major, config, RTC_VERSION_MAJOR
+            ),
+            None => panic!("RTC_VERSION_MAJOR missing in {:?}", config),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Builds the accelerators over two scenes, a tessellated sphere
+//! (about one million triangles by default) and a grid of 32768
+//! spheres, and measures the build time and the ray throughput of
+//! intersect() and intersect_p(). The BVH is built with each split
+//! method. Run in release mode:
+//!
+//! cargo run --release --example accelerators_bvh_benchmark [n_rings]
+//!
+//! With the embree feature (and the Embree 4 library installed) the
+//! Embree backend is measured as well, with native triangles for the
+//! first scene and user geometry for the second:
+//!
+//! cargo run --release --features embree --example accelerators_bvh_benchmark
+//!
+//! The results are printed as a Markdown table.
+
+// std
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::time::Instant;
+// pbrt
+use pbrt::accelerators::bvh::{BVHAccel, SplitMethod};
+#[cfg(feature = "embree")]
+use pbrt::accelerators::embree::EmbreeAccel;
+use pbrt::core::geometry::{Point3f, Ray, Vector3f};
+use pbrt::core::pbrt::Float;
+use pbrt::core::primitive::{GeometricPrimitive, Primitive};
+use pbrt::core::rng::Rng;
+use pbrt::core::shape::Shape;
+use pbrt::core::transform::Transform;
+use pbrt::shapes::sphere::Sphere;
+use pbrt::shapes::triangle::{Triangle, TriangleMesh};
+
+fn make_sphere_mesh(n_rings: u32) -> Vec<Arc<Primitive>> {
+    // slightly bumpy sphere, so the triangles differ in size
+    let n_segments: u32 = 2 * n_rings;
+    let mut p: Vec<Point3f> = Vec::new();
+    for ring in 0..=n_rings {
+        let theta: Float = PI * ring as Float / n_rings as Float;
+        for segment in 0..n_segments {
+            let phi: Float = 2.0 as Float * PI * segment as Float / n_segments as Float;
+            let r: Float = 1.0 as Float + 0.05 as Float * (7.0 as Float * phi).sin() * theta.sin();
+            p.push(Point3f {
+                x: r * theta.sin() * phi.cos(),
+                y: r * theta.sin() * phi.sin(),
+                z: r * theta.cos(),
+            });
+        }
+    }
+    let mut vertex_indices: Vec<u32> = Vec::new();
+    for ring in 0..n_rings {
+        for segment in 0..n_segments {
+            let next: u32 = (segment + 1) % n_segments;
+            let v00: u32 = ring * n_segments + segment;
+            let v01: u32 = ring * n_segments + next;
+            let v10: u32 = (ring + 1) * n_segments + segment;
+            let v11: u32 = (ring + 1) * n_segments + next;
+            vertex_indices.extend_from_slice(&[v00, v10, v11, v00, v11, v01]);
+        }
+    }
+    let n_vertices: u32 = p.len() as u32;
+    let n_triangles: u32 = vertex_indices.len() as u32 / 3;
+    let mesh: Arc<TriangleMesh> = Arc::new(TriangleMesh::new(
+        Transform::default(),
+        Transform::default(),
+        false,
+        n_triangles,
+        vertex_indices,
+        n_vertices,
+        p,
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+        None,
+        None,
+    ));
+    let mut prims: Vec<Arc<Primitive>> = Vec::with_capacity(n_triangles as usize);
+    for id in 0..n_triangles {
+        let triangle: Arc<Shape> = Arc::new(Shape::Trngl(Triangle::new(mesh.clone(), id)));
+        prims.push(Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+            triangle, None, None, None,
+        ))));
+    }
+    prims
+}
+
+/// A grid of _n_ x _n_ x _n_ small spheres filling the cube from -1
+/// to 1.
+fn make_sphere_grid(n: u32) -> Vec<Arc<Primitive>> {
+    let spacing: Float = 2.0 as Float / n as Float;
+    let mut prims: Vec<Arc<Primitive>> = Vec::with_capacity((n * n * n) as usize);
+    for i in 0..n * n * n {
+        let center: Vector3f = Vector3f {
+            x: -1.0 as Float + spacing * ((i % n) as Float + 0.5 as Float),
+            y: -1.0 as Float + spacing * ((i / n % n) as Float + 0.5 as Float),
+            z: -1.0 as Float + spacing * ((i / (n * n)) as Float + 0.5 as Float),
+        };
+        let object_to_world: Transform = Transform::translate(&center);
+        let world_to_object: Transform = Transform::inverse(&object_to_world);
+        let radius: Float = 0.4 as Float * spacing;
+        let sphere: Arc<Shape> = Arc::new(Shape::Sphr(Sphere::new(
+            Arc::new(object_to_world),
+            Arc::new(world_to_object),
+            false,
+            radius,
+            -radius,
+            radius,
+            360.0 as Float,
+        )));
+        prims.push(Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+            sphere, None, None, None,
+        ))));
+    }
+    prims
+}
+
+fn random_point(rng: &mut Rng, extent: Float) -> Point3f {
+    Point3f {
+        x: extent * (2.0 as Float * rng.uniform_float() - 1.0 as Float),
+        y: extent * (2.0 as Float * rng.uniform_float() - 1.0 as Float),
+        z: extent * (2.0 as Float * rng.uniform_float() - 1.0 as Float),
+    }
+}
+
+fn main() {
+    let n_rings: u32 = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("n_rings has to be an integer"))
+        .unwrap_or(500_u32);
+    // rays from points around the scene towards points within it
+    let n_rays: usize = 1_000_000;
+    let mut rng: Rng = Rng::new();
+    let rays: Vec<(Point3f, Vector3f)> = (0..n_rays)
+        .map(|_| {
+            let o: Point3f = random_point(&mut rng, 3.0 as Float);
+            let target: Point3f = random_point(&mut rng, 0.5 as Float);
+            (o, target - o)
+        })
+        .collect();
+    println!("| scene | accelerator | build | intersect | intersect_p | hits |");
+    println!("|-------|-------------|-------|-----------|-------------|------|");
+    for (scene, prims) in &[
+        (
+            format!("sphere mesh ({} triangles)", 4 * n_rings * n_rings),
+            make_sphere_mesh(n_rings),
+        ),
+        (String::from("32768 spheres"), make_sphere_grid(32)),
+    ] {
+        for (name, split_method) in &[
+            ("bvh (sah)", SplitMethod::SAH),
+            ("bvh (hlbvh)", SplitMethod::HLBVH),
+            ("bvh (middle)", SplitMethod::Middle),
+            ("bvh (equal)", SplitMethod::EqualCounts),
+        ] {
+            let start = Instant::now();
+            let bvh: BVHAccel = BVHAccel::new(prims.clone(), 4, split_method.clone());
+            let build_time: Float = start.elapsed().as_secs_f64() as Float;
+            measure(scene, name, &Primitive::BVH(bvh), build_time, &rays);
+        }
+        #[cfg(feature = "embree")]
+        {
+            let start = Instant::now();
+            let embree: EmbreeAccel = EmbreeAccel::new(prims.clone());
+            let build_time: Float = start.elapsed().as_secs_f64() as Float;
+            measure(
+                scene,
+                "embree",
+                &Primitive::Embree(embree),
+                build_time,
+                &rays,
+            );
+        }
+    }
+}
+
+fn measure(
+    scene: &str,
+    name: &str,
+    accelerator: &Primitive,
+    build_time: Float,
+    rays: &[(Point3f, Vector3f)],
+) {
+    let n_rays: usize = rays.len();
+    let start = Instant::now();
+    let mut n_hits: usize = 0;
+    for (o, d) in rays {
+        let mut ray: Ray = Ray {
+            o: *o,
+            d: *d,
+            t_max: std::f32::INFINITY,
+            time: 0.0,
+            medium: None,
+            differential: None,
+        };
+        if accelerator.intersect(&mut ray).is_some() {
+            n_hits += 1;
+        }
+    }
+    let intersect_time: Float = start.elapsed().as_secs_f64() as Float;
+    let start = Instant::now();
+    let mut n_occluded: usize = 0;
+    for (o, d) in rays {
+        let ray: Ray = Ray {
+            o: *o,
+            d: *d,
+            t_max: std::f32::INFINITY,
+            time: 0.0,
+            medium: None,
+            differential: None,
+        };
+        if accelerator.intersect_p(&ray) {
+            n_occluded += 1;
+        }
+    }
+    let intersect_p_time: Float = start.elapsed().as_secs_f64() as Float;
+    assert_eq!(n_hits, n_occluded);
+    println!(
+        "| {} | {} | {:.2} s | {:.2} Mrays/s | {:.2} Mrays/s | {} |",
+        scene,
+        name,
+        build_time,
+        n_rays as Float / intersect_time / 1.0e6,
+        n_rays as Float / intersect_p_time / 1.0e6,
+        n_hits
+    );
+}
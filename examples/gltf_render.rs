@@ -0,0 +1,59 @@
+//! Renders a glTF 2.0 scene (e.g. the "DamagedHelmet" sample model)
+//! under an environment light:
+//!
+//! cargo run --release --features gltf --example gltf_render DamagedHelmet.gltf [envmap]
+//!
+//! Without a camera in the glTF file, the scene is viewed from +z.
+//! The image is written to gltf_render.png.
+
+// std
+use std::path::Path;
+// pbrt
+use pbrt::core::api::{
+    pbrt_camera, pbrt_cleanup, pbrt_film, pbrt_init, pbrt_integrator, pbrt_light_source,
+    pbrt_look_at, pbrt_sampler, pbrt_world_begin,
+};
+use pbrt::core::gltfimport::GltfImport;
+use pbrt::core::paramset::ParamSet;
+
+fn params(name: &str) -> ParamSet {
+    let mut params = ParamSet::default();
+    params.name = String::from(name);
+    params
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        println!("usage: {} <file.gltf> [envmap]", args[0]);
+        return;
+    }
+    let gltf: GltfImport = match GltfImport::open(Path::new(&args[1])) {
+        Ok(gltf) => gltf,
+        Err(e) => panic!("Error reading \"{}\": {}", args[1], e),
+    };
+    let (mut api_state, mut bsdf_state) = pbrt_init(num_cpus::get() as u8);
+    let mut film = params("image");
+    film.add_int(String::from("xresolution"), 800);
+    film.add_int(String::from("yresolution"), 800);
+    film.add_string(String::from("filename"), String::from("gltf_render.png"));
+    pbrt_film(&mut api_state, film);
+    let mut sampler = params("sobol");
+    sampler.add_int(String::from("pixelsamples"), 64);
+    pbrt_sampler(&mut api_state, sampler);
+    pbrt_integrator(&mut api_state, params("path"));
+    if !gltf.make_camera(&mut api_state) {
+        pbrt_look_at(&mut api_state, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0);
+        let mut camera = params("perspective");
+        camera.add_float(String::from("fov"), 45.0);
+        pbrt_camera(&mut api_state, camera);
+    }
+    pbrt_world_begin(&mut api_state);
+    let mut environment = params("infinite");
+    if args.len() > 2 {
+        environment.add_string(String::from("mapname"), args[2].clone());
+    }
+    pbrt_light_source(&mut api_state, environment);
+    gltf.make_world(&mut api_state, &mut bsdf_state);
+    pbrt_cleanup(&api_state);
+}
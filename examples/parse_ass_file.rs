@@ -171,7 +171,14 @@ pub fn make_perspective_camera(
             let camera_name: String = String::from("perspective");
             let mut camera_params: ParamSet = ParamSet::default();
             camera_params.add_float(String::from("fov"), fov);
-            some_camera = make_camera(&camera_name, &camera_params, animated_cam_to_world, film);
+            some_camera = make_camera(
+                &camera_name,
+                &camera_params,
+                animated_cam_to_world,
+                film,
+                None,
+                None,
+            );
         }
     }
     some_camera
@@ -1085,14 +1092,9 @@ fn main() {
                                                     None,
                                                 ));
                                                 for id in 0..mesh.n_triangles {
-                                                    let triangle =
-                                                        Arc::new(Shape::Trngl(Triangle::new(
-                                                            mesh.object_to_world,
-                                                            mesh.world_to_object,
-                                                            mesh.reverse_orientation,
-                                                            mesh.clone(),
-                                                            id,
-                                                        )));
+                                                    let triangle = Arc::new(Shape::Trngl(
+                                                        Triangle::new(mesh.clone(), id),
+                                                    ));
                                                     shapes.push(triangle.clone());
                                                 }
                                                 let mi: MediumInterface =
@@ -1121,8 +1123,8 @@ fn main() {
                                             } else if node_type == "disk" {
                                                 let mut shapes: Vec<Arc<Shape>> = Vec::new();
                                                 let disk = Arc::new(Shape::Dsk(Disk::new(
-                                                    obj_to_world,
-                                                    world_to_obj,
+                                                    Arc::new(obj_to_world),
+                                                    Arc::new(world_to_obj),
                                                     false,
                                                     0.0 as Float, // height
                                                     radius,
@@ -1155,8 +1157,8 @@ fn main() {
                                             } else if node_type == "sphere" {
                                                 let mut shapes: Vec<Arc<Shape>> = Vec::new();
                                                 let sphere = Arc::new(Shape::Sphr(Sphere::new(
-                                                    obj_to_world,
-                                                    world_to_obj,
+                                                    Arc::new(obj_to_world),
+                                                    Arc::new(world_to_obj),
                                                     false,
                                                     radius,
                                                     -radius,        // z_min
@@ -1191,8 +1193,8 @@ fn main() {
                                                 // TODO: assumption about z_min and z_max
                                                 let cylinder =
                                                     Arc::new(Shape::Clndr(Cylinder::new(
-                                                        obj_to_world,
-                                                        world_to_obj,
+                                                        Arc::new(obj_to_world),
+                                                        Arc::new(world_to_obj),
                                                         false,
                                                         radius,
                                                         0.0 as Float,   // z_min
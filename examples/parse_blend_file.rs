@@ -63,7 +63,7 @@ use pbrt::shapes::sphere::Sphere;
 use pbrt::shapes::triangle::{Triangle, TriangleMesh};
 use pbrt::textures::constant::ConstantTexture;
 use pbrt::textures::imagemap::convert_to_spectrum;
-use pbrt::textures::imagemap::ImageTexture;
+use pbrt::textures::imagemap::{ImageEncoding, ImageTexture};
 
 pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
@@ -269,7 +269,7 @@ impl SceneDescriptionBuilder {
         let triangle_mesh = Arc::new(TriangleMesh::new(
             object_to_world,
             world_to_object,
-            false,
+            object_to_world.swaps_handedness(),
             n_triangles.try_into().unwrap(),
             vertex_indices.try_into().unwrap(),
             n_vertices,
@@ -296,8 +296,8 @@ impl SceneDescriptionBuilder {
     ) -> &mut SceneDescriptionBuilder {
         self.cylinder_names.push(base_name);
         let cylinder = Arc::new(Shape::Clndr(Cylinder::new(
-            object_to_world,
-            world_to_object,
+            Arc::new(object_to_world),
+            Arc::new(world_to_object),
             false,
             radius,
             z_min,
@@ -319,8 +319,8 @@ impl SceneDescriptionBuilder {
     ) -> &mut SceneDescriptionBuilder {
         self.disk_names.push(base_name);
         let disk = Arc::new(Shape::Dsk(Disk::new(
-            object_to_world,
-            world_to_object,
+            Arc::new(object_to_world),
+            Arc::new(world_to_object),
             false,
             height,
             radius,
@@ -342,8 +342,8 @@ impl SceneDescriptionBuilder {
     ) -> &mut SceneDescriptionBuilder {
         self.sphere_names.push(base_name);
         let sphere = Arc::new(Shape::Sphr(Sphere::new(
-            object_to_world,
-            world_to_object,
+            Arc::new(object_to_world),
+            Arc::new(world_to_object),
             false,
             radius,
             z_min,
@@ -579,7 +579,7 @@ impl RenderOptions {
                             let max_aniso: Float = 8.0;
                             let wrap_mode: ImageWrap = ImageWrap::Repeat;
                             let scale: Float = 1.0;
-                            let gamma: bool = true;
+                            let encoding: ImageEncoding = ImageEncoding::Srgb;
                             kd = Arc::new(ImageTexture::new(
                                 mapping,
                                 filename,
@@ -587,7 +587,7 @@ impl RenderOptions {
                                 max_aniso,
                                 wrap_mode,
                                 scale,
-                                gamma,
+                                encoding,
                                 convert_to_spectrum,
                             ));
                         } else {
@@ -626,7 +626,7 @@ impl RenderOptions {
                                 let max_aniso: Float = 8.0;
                                 let wrap_mode: ImageWrap = ImageWrap::Repeat;
                                 let scale: Float = 1.0;
-                                let gamma: bool = true;
+                                let encoding: ImageEncoding = ImageEncoding::Srgb;
                                 kd = Arc::new(ImageTexture::new(
                                     mapping,
                                     filename,
@@ -634,7 +634,7 @@ impl RenderOptions {
                                     max_aniso,
                                     wrap_mode,
                                     scale,
-                                    gamma,
+                                    encoding,
                                     convert_to_spectrum,
                                 ));
                             }
@@ -765,7 +765,7 @@ impl RenderOptions {
                             let max_aniso: Float = 8.0;
                             let wrap_mode: ImageWrap = ImageWrap::Repeat;
                             let scale: Float = 1.0;
-                            let gamma: bool = true;
+                            let encoding: ImageEncoding = ImageEncoding::Srgb;
                             kd = Arc::new(ImageTexture::new(
                                 mapping,
                                 filename,
@@ -773,7 +773,7 @@ impl RenderOptions {
                                 max_aniso,
                                 wrap_mode,
                                 scale,
-                                gamma,
+                                encoding,
                                 convert_to_spectrum,
                             ));
                         } else {
@@ -812,7 +812,7 @@ impl RenderOptions {
                                 let max_aniso: Float = 8.0;
                                 let wrap_mode: ImageWrap = ImageWrap::Repeat;
                                 let scale: Float = 1.0;
-                                let gamma: bool = true;
+                                let encoding: ImageEncoding = ImageEncoding::Srgb;
                                 kd = Arc::new(ImageTexture::new(
                                     mapping,
                                     filename,
@@ -820,7 +820,7 @@ impl RenderOptions {
                                     max_aniso,
                                     wrap_mode,
                                     scale,
-                                    gamma,
+                                    encoding,
                                     convert_to_spectrum,
                                 ));
                             }
@@ -951,7 +951,7 @@ impl RenderOptions {
                             let max_aniso: Float = 8.0;
                             let wrap_mode: ImageWrap = ImageWrap::Repeat;
                             let scale: Float = 1.0;
-                            let gamma: bool = true;
+                            let encoding: ImageEncoding = ImageEncoding::Srgb;
                             kd = Arc::new(ImageTexture::new(
                                 mapping,
                                 filename,
@@ -959,7 +959,7 @@ impl RenderOptions {
                                 max_aniso,
                                 wrap_mode,
                                 scale,
-                                gamma,
+                                encoding,
                                 convert_to_spectrum,
                             ));
                         } else {
@@ -998,7 +998,7 @@ impl RenderOptions {
                                 let max_aniso: Float = 8.0;
                                 let wrap_mode: ImageWrap = ImageWrap::Repeat;
                                 let scale: Float = 1.0;
-                                let gamma: bool = true;
+                                let encoding: ImageEncoding = ImageEncoding::Srgb;
                                 kd = Arc::new(ImageTexture::new(
                                     mapping,
                                     filename,
@@ -1006,7 +1006,7 @@ impl RenderOptions {
                                     max_aniso,
                                     wrap_mode,
                                     scale,
-                                    gamma,
+                                    encoding,
                                     convert_to_spectrum,
                                 ));
                             }
@@ -1029,13 +1029,7 @@ impl RenderOptions {
             // create individual triangles
             let mut triangles: Vec<Arc<Shape>> = Vec::new();
             for id in 0..mesh.n_triangles {
-                let triangle = Arc::new(Shape::Trngl(Triangle::new(
-                    mesh.object_to_world,
-                    mesh.world_to_object,
-                    mesh.transform_swaps_handedness,
-                    mesh.clone(),
-                    id,
-                )));
+                let triangle = Arc::new(Shape::Trngl(Triangle::new(mesh.clone(), id)));
                 triangles.push(triangle.clone());
                 shapes.push(triangle.clone());
             }
@@ -1156,7 +1150,7 @@ impl RenderOptions {
                             let max_aniso: Float = 8.0;
                             let wrap_mode: ImageWrap = ImageWrap::Repeat;
                             let scale: Float = 1.0;
-                            let gamma: bool = true;
+                            let encoding: ImageEncoding = ImageEncoding::Srgb;
                             kd = Arc::new(ImageTexture::new(
                                 mapping,
                                 filename,
@@ -1164,7 +1158,7 @@ impl RenderOptions {
                                 max_aniso,
                                 wrap_mode,
                                 scale,
-                                gamma,
+                                encoding,
                                 convert_to_spectrum,
                             ));
                         } else {
@@ -1203,7 +1197,7 @@ impl RenderOptions {
                                 let max_aniso: Float = 8.0;
                                 let wrap_mode: ImageWrap = ImageWrap::Repeat;
                                 let scale: Float = 1.0;
-                                let gamma: bool = true;
+                                let encoding: ImageEncoding = ImageEncoding::Srgb;
                                 kd = Arc::new(ImageTexture::new(
                                     mapping,
                                     filename,
@@ -1211,7 +1205,7 @@ impl RenderOptions {
                                     max_aniso,
                                     wrap_mode,
                                     scale,
-                                    gamma,
+                                    encoding,
                                     convert_to_spectrum,
                                 ));
                             }
@@ -1660,7 +1654,14 @@ pub fn make_perspective_camera(
             let camera_name: String = String::from("perspective");
             let mut camera_params: ParamSet = ParamSet::default();
             camera_params.add_float(String::from("fov"), fov);
-            some_camera = make_camera(&camera_name, &camera_params, animated_cam_to_world, film);
+            some_camera = make_camera(
+                &camera_name,
+                &camera_params,
+                animated_cam_to_world,
+                film,
+                None,
+                None,
+            );
         }
     }
     some_camera
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use pbrt::core::geometry::Vector3f;
 use pbrt::core::pbrt::Float;
 use pbrt::core::transform::Transform;
@@ -14,7 +16,15 @@ fn main() {
     let z_min: Float = 0.0;
     let z_max: Float = 1.0;
     let phi_max: Float = 360.0;
-    let cylinder = Cylinder::new(translate, inverse, false, radius, z_min, z_max, phi_max);
+    let cylinder = Cylinder::new(
+        Arc::new(translate),
+        Arc::new(inverse),
+        false,
+        radius,
+        z_min,
+        z_max,
+        phi_max,
+    );
     println!("translate = {:?}", translate);
     println!("inverse = {:?}", inverse);
     println!("cylinder.radius = {:?}", cylinder.radius);
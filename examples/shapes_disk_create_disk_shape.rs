@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use pbrt::core::geometry::Vector3f;
 use pbrt::core::pbrt::Float;
 use pbrt::core::transform::Transform;
@@ -15,8 +17,8 @@ fn main() {
     let inner_radius: Float = 0.0;
     let phi_max: Float = 360.0;
     let disk = Disk::new(
-        translate,
-        inverse,
+        Arc::new(translate),
+        Arc::new(inverse),
         false,
         height,
         radius,
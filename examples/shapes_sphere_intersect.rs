@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use pbrt::core::geometry::{Point3f, Ray, Vector3f};
 use pbrt::core::pbrt::Float;
 use pbrt::core::shape::Shape;
@@ -17,7 +19,13 @@ fn main() {
     });
     let inverse: Transform = Transform::inverse(&translate);
     let sphere: Shape = Shape::Sphr(Sphere::new(
-        translate, inverse, false, radius, z_min, z_max, phi_max,
+        Arc::new(translate),
+        Arc::new(inverse),
+        false,
+        radius,
+        z_min,
+        z_max,
+        phi_max,
     ));
     // see Sphere::Intersect() in sphere.cpp
     let o: Point3f = Point3f {
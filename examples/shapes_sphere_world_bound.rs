@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use pbrt::core::geometry::{Bounds3f, Vector3f};
 use pbrt::core::pbrt::Float;
 use pbrt::core::transform::Transform;
@@ -14,7 +16,15 @@ fn main() {
         z: 0.0,
     });
     let inverse: Transform = Transform::inverse(&translate);
-    let sphere: Sphere = Sphere::new(translate, inverse, false, radius, z_min, z_max, phi_max);
+    let sphere: Sphere = Sphere::new(
+        Arc::new(translate),
+        Arc::new(inverse),
+        false,
+        radius,
+        z_min,
+        z_max,
+        phi_max,
+    );
     let world_bound: Bounds3f = sphere.world_bound(); // Primitive
                                                       // println!("sphere() = {:?}", sphere);
     println!("world_bound() = {:?}", world_bound);
@@ -24,7 +34,15 @@ fn main() {
         z: 0.0,
     });
     let inverse: Transform = Transform::inverse(&translate);
-    let sphere: Sphere = Sphere::new(translate, inverse, false, radius, z_min, z_max, phi_max);
+    let sphere: Sphere = Sphere::new(
+        Arc::new(translate),
+        Arc::new(inverse),
+        false,
+        radius,
+        z_min,
+        z_max,
+        phi_max,
+    );
     let world_bound: Bounds3f = sphere.world_bound(); // Primitive
                                                       // println!("sphere() = {:?}", sphere);
     println!("world_bound() = {:?}", world_bound);
@@ -0,0 +1,216 @@
+//! Measures how well tiles are balanced between threads for the scene
+//! of assets/scenes/tiles-hotspot.pbrt, where the glass spheres in one
+//! corner take most of the render time. The render time of each 8x8
+//! pixel block is measured on a single thread (by rendering it with a
+//! crop window), then the wall-clock time for several thread counts
+//! and tile sizes is computed for two schedules:
+//!
+//! - static: the tiles (in the order of the block queue) are split
+//!   into one contiguous chunk per thread
+//! - shared counter: each thread takes the next tile from the block
+//!   queue once it is done with the previous one, like the render loop
+//!
+//! This works without a machine with many cores, but leaves out
+//! effects like memory bandwidth or the threads merging film tiles.
+//! Run in release mode:
+//!
+//! cargo run --release --example tiles_schedule_benchmark [pixelsamples]
+
+// std
+use std::time::Instant;
+// pbrt
+use pbrt::blockqueue::BlockQueue;
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::geometry::Point3f;
+use pbrt::core::paramset::ParamSet;
+use pbrt::core::pbrt::Float;
+
+const RESOLUTION: u32 = 256;
+const BLOCK: u32 = 8;
+
+fn params(name: &str) -> ParamSet {
+    let mut params = ParamSet::default();
+    params.name = String::from(name);
+    params
+}
+
+fn glass_sphere(builder: &mut SceneBuilder, x: Float, y: Float, z: Float, radius: Float) {
+    let mut sphere = params("sphere");
+    sphere.add_float(String::from("radius"), radius);
+    builder
+        .attribute_begin()
+        .translate(x, y, z)
+        .shape(sphere)
+        .attribute_end();
+}
+
+/// Renders the block (_bx_, _by_) of the image on a single thread and
+/// returns the time it took in seconds.
+fn render_block(bx: u32, by: u32, pixel_samples: i32) -> f64 {
+    let mut builder = SceneBuilder::new(1);
+    let mut film = params("image");
+    film.add_int(String::from("xresolution"), RESOLUTION as i32);
+    film.add_int(String::from("yresolution"), RESOLUTION as i32);
+    let r: Float = RESOLUTION as Float;
+    film.add_floats(
+        String::from("cropwindow"),
+        vec![
+            (bx * BLOCK) as Float / r,
+            ((bx + 1) * BLOCK) as Float / r,
+            (by * BLOCK) as Float / r,
+            ((by + 1) * BLOCK) as Float / r,
+        ],
+    );
+    let path = std::env::temp_dir().join("pbrt_tiles_schedule_benchmark.png");
+    film.add_string(String::from("filename"), path.to_str().unwrap().to_string());
+    let mut sampler = params("sobol");
+    sampler.add_int(String::from("pixelsamples"), pixel_samples);
+    let mut integrator = params("path");
+    integrator.add_int(String::from("maxdepth"), 32);
+    let mut camera = params("perspective");
+    camera.add_float(String::from("fov"), 45.0);
+    let mut sky = params("infinite");
+    sky.add_rgb(String::from("L"), [0.2; 3]);
+    let mut spot = params("spot");
+    spot.add_float(String::from("coneangle"), 15.0);
+    spot.add_point3f(
+        String::from("from"),
+        Point3f {
+            x: -1.5,
+            y: 6.0,
+            z: 1.0,
+        },
+    );
+    spot.add_point3f(
+        String::from("to"),
+        Point3f {
+            x: -1.5,
+            y: 0.0,
+            z: -1.0,
+        },
+    );
+    spot.add_rgb(String::from("I"), [40.0; 3]);
+    let mut matte = params("matte");
+    matte.add_rgb(String::from("Kd"), [0.5; 3]);
+    let mut floor = params("trianglemesh");
+    floor.add_ints(String::from("indices"), vec![0, 1, 2, 0, 2, 3]);
+    floor.add_point3fs(
+        String::from("P"),
+        vec![
+            -10.0, -1.0, -10.0, 10.0, -1.0, -10.0, 10.0, -1.0, 10.0, -10.0, -1.0, 10.0,
+        ],
+    );
+    let mut glass = params("glass");
+    glass.add_float(String::from("index"), 1.5);
+    builder
+        .film(film)
+        .sampler(sampler)
+        .integrator(integrator)
+        .look_at(0.0, 3.0, -6.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+        .camera(camera)
+        .world_begin()
+        .light_source(sky)
+        .light_source(spot)
+        .attribute_begin()
+        .material(matte)
+        .shape(floor)
+        .attribute_end()
+        .attribute_begin()
+        .material(glass);
+    glass_sphere(&mut builder, -1.5, -0.5, -1.0, 0.5);
+    glass_sphere(&mut builder, -2.1, -0.7, -1.6, 0.3);
+    glass_sphere(&mut builder, -0.9, -0.7, -1.6, 0.3);
+    builder.attribute_end();
+    let (scene, render_options) = builder.build();
+    let mut integrator = render_options.make_integrator(None).unwrap();
+    let start = Instant::now();
+    integrator.render(&scene, 1);
+    let seconds: f64 = start.elapsed().as_secs_f64();
+    std::fs::remove_file(&path).unwrap();
+    seconds
+}
+
+/// Render times of the tiles of _tile_size_ pixels, in the order the
+/// block queue hands them out.
+fn tile_times(block_times: &[f64], tile_size: u32) -> Vec<f64> {
+    let queue = BlockQueue::new((RESOLUTION, RESOLUTION), (tile_size, tile_size), (0, 0));
+    let blocks_per_tile: u32 = tile_size / BLOCK;
+    let blocks_per_row: u32 = RESOLUTION / BLOCK;
+    let mut times: Vec<f64> = Vec::with_capacity(queue.len());
+    while let Some((tx, ty)) = queue.next() {
+        let mut time: f64 = 0.0;
+        for by in ty * blocks_per_tile..(ty + 1) * blocks_per_tile {
+            for bx in tx * blocks_per_tile..(tx + 1) * blocks_per_tile {
+                time += block_times[(by * blocks_per_row + bx) as usize];
+            }
+        }
+        times.push(time);
+    }
+    times
+}
+
+/// Wall-clock time if each of _n_threads_ threads renders a contiguous
+/// chunk of the tiles.
+fn static_schedule(times: &[f64], n_threads: usize) -> f64 {
+    let chunk_size: usize = (times.len() + n_threads - 1) / n_threads;
+    times
+        .chunks(chunk_size)
+        .map(|chunk| chunk.iter().sum::<f64>())
+        .fold(0.0, f64::max)
+}
+
+/// Wall-clock time if the next tile goes to the first thread which is
+/// done with its previous one.
+fn shared_counter_schedule(times: &[f64], n_threads: usize) -> f64 {
+    let mut busy_until: Vec<f64> = vec![0.0; n_threads];
+    for time in times {
+        let first_idle: &mut f64 = busy_until
+            .iter_mut()
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap();
+        *first_idle += time;
+    }
+    busy_until.into_iter().fold(0.0, f64::max)
+}
+
+fn main() {
+    let pixel_samples: i32 = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("pixelsamples has to be an integer"))
+        .unwrap_or(64_i32);
+    let blocks_per_row: u32 = RESOLUTION / BLOCK;
+    let mut block_times: Vec<f64> = Vec::new();
+    for by in 0..blocks_per_row {
+        for bx in 0..blocks_per_row {
+            block_times.push(render_block(bx, by, pixel_samples));
+        }
+    }
+    let total: f64 = block_times.iter().sum();
+    let slowest: f64 = block_times.iter().cloned().fold(0.0, f64::max);
+    println!(
+        "{} blocks of {}x{} pixels: {:.2} s in total, slowest {:.3} s, mean {:.3} s",
+        block_times.len(),
+        BLOCK,
+        BLOCK,
+        total,
+        slowest,
+        total / block_times.len() as f64
+    );
+    println!(
+        "{:>8} {:>10} {:>12} {:>16} {:>10}",
+        "threads", "tile size", "static [s]", "shared ctr [s]", "ideal [s]"
+    );
+    for n_threads in &[2_usize, 4, 8, 16, 32] {
+        for tile_size in &[8_u32, 16, 32, 64] {
+            let times: Vec<f64> = tile_times(&block_times, *tile_size);
+            println!(
+                "{:>8} {:>10} {:>12.2} {:>16.2} {:>10.2}",
+                n_threads,
+                tile_size,
+                static_schedule(&times, *n_threads),
+                shared_counter_schedule(&times, *n_threads),
+                total / *n_threads as f64
+            );
+        }
+    }
+}
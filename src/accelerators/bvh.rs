@@ -1,7 +1,9 @@
 // std
 use std;
+use std::cmp::Ordering;
 use std::sync::Arc;
 // others
+use rayon::prelude::*;
 // use time::PreciseTime;
 use typed_arena::Arena;
 // pbrt
@@ -16,6 +18,12 @@ use crate::core::primitive::Primitive;
 
 // see bvh.h
 
+crate::stat_memory!("Memory/BVH tree", TREE_BYTES);
+crate::stat_counter!("Intersections/BVH nodes visited", BVH_NODE_VISITS);
+
+/// Leaf nodes store the number of their primitives in 16 bits.
+const MAX_LEAF_PRIMITIVES: usize = std::u16::MAX as usize;
+
 #[derive(Debug, Clone)]
 pub enum SplitMethod {
     SAH,
@@ -113,6 +121,97 @@ pub struct LinearBVHNode {
 }
 
 // BVHAccel -> Aggregate -> Primitive
+
+/// Bounding volume hierarchy, flattened into an array of nodes in
+/// depth-first order. Whatever the split method, it finds the same
+/// intersections as testing all primitives:
+///
+/// ```rust
+/// use pbrt::accelerators::bvh::{BVHAccel, SplitMethod};
+/// use pbrt::core::geometry::{Point3f, Ray};
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::primitive::{GeometricPrimitive, Primitive};
+/// use pbrt::core::rng::Rng;
+/// use pbrt::core::shape::Shape;
+/// use pbrt::core::transform::Transform;
+/// use pbrt::shapes::triangle::{Triangle, TriangleMesh};
+/// use std::sync::Arc;
+///
+/// let mut rng: Rng = Rng::new();
+/// let mut random_point = |extent: Float| Point3f {
+///     x: extent * (2.0 * rng.uniform_float() - 1.0),
+///     y: extent * (2.0 * rng.uniform_float() - 1.0),
+///     z: extent * (2.0 * rng.uniform_float() - 1.0),
+/// };
+/// // small random triangles, some of them stacked on top of each other
+/// let n_triangles: u32 = 200;
+/// let mut p: Vec<Point3f> = Vec::new();
+/// for i in 0..n_triangles {
+///     let center: Point3f = if i % 10 == 0 {
+///         Point3f::default()
+///     } else {
+///         random_point(1.0)
+///     };
+///     for _ in 0..3 {
+///         p.push(center + (random_point(0.1) - Point3f::default()));
+///     }
+/// }
+/// let mesh = Arc::new(TriangleMesh::new(
+///     Transform::default(),
+///     Transform::default(),
+///     false,
+///     n_triangles,
+///     (0..3 * n_triangles).collect(),
+///     3 * n_triangles,
+///     p,
+///     Vec::new(),
+///     Vec::new(),
+///     Vec::new(),
+///     None,
+///     None,
+/// ));
+/// let prims: Vec<Arc<Primitive>> = (0..n_triangles)
+///     .map(|id| {
+///         let shape = Arc::new(Shape::Trngl(Triangle::new(mesh.clone(), id)));
+///         Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+///             shape, None, None, None,
+///         )))
+///     })
+///     .collect();
+/// let rays: Vec<Ray> = (0..10_000)
+///     .map(|_| {
+///         let o: Point3f = random_point(2.0);
+///         Ray {
+///             o,
+///             d: random_point(1.0) - o,
+///             t_max: std::f32::INFINITY,
+///             time: 0.0,
+///             medium: None,
+///             differential: None,
+///         }
+///     })
+///     .collect();
+/// for split_method in vec![
+///     SplitMethod::SAH,
+///     SplitMethod::HLBVH,
+///     SplitMethod::Middle,
+///     SplitMethod::EqualCounts,
+/// ] {
+///     let bvh: BVHAccel = BVHAccel::new(prims.clone(), 4, split_method);
+///     for ray in &rays {
+///         // brute force
+///         let mut expected: Ray = ray.clone();
+///         let mut hit: bool = false;
+///         for prim in &prims {
+///             hit |= prim.intersect(&mut expected).is_some();
+///         }
+///         let mut r: Ray = ray.clone();
+///         assert_eq!(bvh.intersect(&mut r).is_some(), hit);
+///         assert_eq!(r.t_max, expected.t_max);
+///         assert_eq!(bvh.intersect_p(ray), hit);
+///     }
+/// }
+/// ```
 pub struct BVHAccel {
     max_prims_in_node: usize,
     split_method: SplitMethod,
@@ -142,21 +241,33 @@ impl BVHAccel {
             let world_bound = bvh.primitives[i].world_bound();
             primitive_info[i] = BVHPrimitiveInfo::new(i, world_bound);
         }
-        // TODO: if (splitMethod == SplitMethod::HLBVH)
         let mut arena: Arena<BVHBuildNode> = Arena::with_capacity(1024 * 1024);
+        // HLBVH builds its treelets in parallel, each into its own arena
+        let mut treelet_arenas: Vec<Arena<BVHBuildNode>> = Vec::new();
         let mut total_nodes: usize = 0;
         let mut ordered_prims: Vec<Arc<Primitive>> = Vec::with_capacity(num_prims);
         // println!("BVHAccel::recursive_build(..., {}, ...)", num_prims);
         // let start = PreciseTime::now();
-        let root = BVHAccel::recursive_build(
-            bvh.clone(), // instead of self
-            &mut arena,
-            &mut primitive_info,
-            0,
-            num_prims,
-            &mut total_nodes,
-            &mut ordered_prims,
-        );
+        let root = if let SplitMethod::HLBVH = split_method {
+            BVHAccel::hlbvh_build(
+                bvh.clone(),
+                &arena,
+                &mut treelet_arenas,
+                &primitive_info,
+                &mut total_nodes,
+                &mut ordered_prims,
+            )
+        } else {
+            BVHAccel::recursive_build(
+                bvh.clone(), // instead of self
+                &mut arena,
+                &mut primitive_info,
+                0,
+                num_prims,
+                &mut total_nodes,
+                &mut ordered_prims,
+            )
+        };
         // let end = PreciseTime::now();
         // println!("{} seconds for building BVH ...", start.to(end));
         // flatten first
@@ -168,6 +279,10 @@ impl BVHAccel {
         // let end = PreciseTime::now();
         // println!("{} seconds for flattening BVH ...", start.to(end));
         assert!(nodes.len() == total_nodes);
+        crate::stat_add!(
+            TREE_BYTES,
+            total_nodes * std::mem::size_of::<LinearBVHNode>()
+        );
         // primitives.swap(orderedPrims);
         let bvh_ordered_prims = Arc::new(BVHAccel {
             max_prims_in_node: std::cmp::min(max_prims_in_node, 255),
@@ -239,7 +354,8 @@ impl BVHAccel {
             let dim: u8 = centroid_bounds.maximum_extent();
             // partition primitives into two sets and build children
             let mut mid: usize = (start + end) / 2_usize;
-            if centroid_bounds.p_max[dim] == centroid_bounds.p_min[dim] {
+            let centroids_coincide: bool = centroid_bounds.p_max[dim] == centroid_bounds.p_min[dim];
+            if centroids_coincide && n_primitives <= MAX_LEAF_PRIMITIVES {
                 // create leaf _BVHBuildNode_
                 let first_prim_offset: usize = ordered_prims.len();
                 for i in start..end {
@@ -249,43 +365,63 @@ impl BVHAccel {
                 node.init_leaf(first_prim_offset, n_primitives, &bounds);
                 return node;
             } else {
+                // too many primitives with coinciding centroids for a
+                // single leaf can only be split into equal counts
+                let split_method: SplitMethod = if centroids_coincide {
+                    SplitMethod::EqualCounts
+                } else {
+                    bvh.split_method.clone()
+                };
+                let mut equal_counts: bool = false;
                 // partition primitives based on _splitMethod_
-                match bvh.split_method {
+                match split_method {
                     SplitMethod::Middle => {
-                        // TODO
+                        // partition primitives through node's midpoint
+                        let p_mid: Float =
+                            (centroid_bounds.p_min[dim] + centroid_bounds.p_max[dim]) / 2.0;
+                        mid = start
+                            + partition_primitive_info(&mut primitive_info[start..end], |pi| {
+                                pi.centroid[dim] < p_mid
+                            });
+                        // for lots of prims with large overlapping
+                        // bounding boxes, this may fail to partition
+                        if mid == start || mid == end {
+                            equal_counts = true;
+                        }
                     }
                     SplitMethod::EqualCounts => {
-                        // TODO
+                        equal_counts = true;
                     }
                     SplitMethod::SAH | SplitMethod::HLBVH => {
                         if n_primitives <= 2 {
-                            mid = (start + end) / 2;
-                            if start != end - 1 {
-                                if primitive_info[end - 1].centroid[dim]
-                                    < primitive_info[start].centroid[dim]
-                                {
-                                    primitive_info.swap(start, end - 1);
-                                }
-                            }
+                            // partition primitives into equally-sized subsets
+                            equal_counts = true;
                         } else {
                             // allocate _BucketInfo_ for SAH partition buckets
                             let n_buckets: usize = 12;
                             let mut buckets: [BucketInfo; 12] = [BucketInfo::default(); 12];
+                            let bucket = |pi: &BVHPrimitiveInfo| -> usize {
+                                let b: usize = (n_buckets as Float
+                                    * centroid_bounds.offset(&pi.centroid)[dim])
+                                    as usize;
+                                std::cmp::min(b, n_buckets - 1)
+                            };
                             // initialize _BucketInfo_ for SAH partition buckets
                             for i in start..end {
-                                let mut b: usize = (n_buckets as Float
-                                    * centroid_bounds.offset(&primitive_info[i].centroid)[dim])
-                                    as usize;
-                                if b == n_buckets {
-                                    b = n_buckets - 1;
-                                }
-                                // assert!(b >= 0_usize, "b >= 0");
-                                assert!(b < n_buckets, "b < {}", n_buckets);
+                                let b: usize = bucket(&primitive_info[i]);
                                 buckets[b].count += 1;
                                 buckets[b].bounds =
                                     bnd3_union_bnd3(&buckets[b].bounds, &primitive_info[i].bounds);
                             }
-                            // compute costs for splitting after each bucket
+                            // compute costs for splitting after each
+                            // bucket, relative to the node's area (which
+                            // is zero if all primitives lie on a line)
+                            let area: Float = bounds.surface_area();
+                            let inv_area: Float = if area > 0.0 as Float {
+                                1.0 as Float / area
+                            } else {
+                                0.0 as Float
+                            };
                             let mut cost: [Float; 11] = [0.0; 11];
                             for i in 0..(n_buckets - 1) {
                                 let mut b0: Bounds3f = Bounds3f::default();
@@ -303,7 +439,7 @@ impl BVHAccel {
                                 cost[i] = 1.0
                                     + (count0 as Float * b0.surface_area()
                                         + count1 as Float * b1.surface_area())
-                                        / bounds.surface_area();
+                                        * inv_area;
                             }
                             // find bucket to split at that minimizes SAH metric
                             let mut min_cost: Float = cost[0];
@@ -318,30 +454,13 @@ impl BVHAccel {
                             // at selected SAH bucket
                             let leaf_cost: Float = n_primitives as Float;
                             if n_primitives > bvh.max_prims_in_node || min_cost < leaf_cost {
-                                let (mut left, mut right): (
-                                    Vec<BVHPrimitiveInfo>,
-                                    Vec<BVHPrimitiveInfo>,
-                                ) = primitive_info[start..end].into_iter().partition(|&pi| {
-                                    let mut b: usize = (n_buckets as Float
-                                        * centroid_bounds.offset(&pi.centroid)[dim])
-                                        as usize;
-                                    if b == n_buckets {
-                                        b = n_buckets - 1;
-                                    }
-                                    // assert!(b >= 0_usize, "b >= 0");
-                                    assert!(b < n_buckets, "b < {}", n_buckets);
-                                    b <= min_cost_split_bucket
-                                });
-                                mid = start + left.len();
-                                let combined_len = left.len() + right.len();
-                                if combined_len == primitive_info.len() {
-                                    primitive_info.clear();
-                                    primitive_info.append(&mut left);
-                                    primitive_info.append(&mut right);
-                                } else {
-                                    primitive_info.splice(start..mid, left.iter().cloned());
-                                    primitive_info.splice(mid..end, right.iter().cloned());
-                                }
+                                // the first and the last bucket are never
+                                // empty, so both sides get primitives
+                                mid = start
+                                    + partition_primitive_info(
+                                        &mut primitive_info[start..end],
+                                        |pi| bucket(pi) <= min_cost_split_bucket,
+                                    );
                             } else {
                                 // create leaf _BVHBuildNode_
                                 let first_prim_offset: usize = ordered_prims.len();
@@ -355,6 +474,15 @@ impl BVHAccel {
                         }
                     }
                 }
+                if equal_counts {
+                    // partition primitives into equally-sized subsets
+                    mid = (start + end) / 2;
+                    primitive_info[start..end].select_nth_unstable_by(mid - start, |a, b| {
+                        a.centroid[dim]
+                            .partial_cmp(&b.centroid[dim])
+                            .unwrap_or(Ordering::Equal)
+                    });
+                }
                 // make sure we get result for c1 before c0
                 let c1 = BVHAccel::recursive_build(
                     bvh.clone(),
@@ -379,6 +507,96 @@ impl BVHAccel {
         }
         return node;
     }
+    /// Linear BVH construction (see "HLBVH: Hierarchical LBVH
+    /// Construction for Real-Time Ray Tracing of Dynamic Geometry"):
+    /// primitives are sorted along a Morton curve through their
+    /// centroids, clusters of primitives sharing the upper 12 bits of
+    /// their Morton codes are turned into treelets in parallel, and the
+    /// upper levels of the tree are built over the treelets with SAH.
+    /// The primitives end up in Morton order.
+    pub fn hlbvh_build<'a>(
+        bvh: Arc<BVHAccel>,
+        arena: &'a Arena<BVHBuildNode<'a>>,
+        treelet_arenas: &'a mut Vec<Arena<BVHBuildNode<'a>>>,
+        primitive_info: &[BVHPrimitiveInfo],
+        total_nodes: &mut usize,
+        ordered_prims: &mut Vec<Arc<Primitive>>,
+    ) -> &'a mut BVHBuildNode<'a> {
+        // compute bounding box of all primitive centroids
+        let mut bounds: Bounds3f = Bounds3f::default();
+        for pi in primitive_info {
+            bounds = bnd3_union_pnt3(&bounds, &pi.centroid);
+        }
+        // compute Morton indices of primitives
+        let morton_bits: u32 = 10;
+        let morton_scale: Float = (1 << morton_bits) as Float;
+        let mut morton_prims: Vec<MortonPrimitive> = primitive_info
+            .par_iter()
+            .enumerate()
+            .map(|(i, pi)| {
+                let centroid_offset: Vector3f = bounds.offset(&pi.centroid);
+                MortonPrimitive {
+                    primitive_index: i,
+                    morton_code: encode_morton_3(&(centroid_offset * morton_scale)),
+                }
+            })
+            .collect();
+        // radix sort primitive Morton indices
+        radix_sort(&mut morton_prims);
+        // create LBVH treelets at bottom of BVH
+
+        // find intervals of primitives for each treelet
+        let mut treelets_to_build: Vec<(usize, usize)> = Vec::new();
+        let mut start: usize = 0;
+        for end in 1..=morton_prims.len() {
+            let mask: u32 = 0b0011_1111_1111_1100_0000_0000_0000_0000;
+            if end == morton_prims.len()
+                || (morton_prims[start].morton_code & mask)
+                    != (morton_prims[end].morton_code & mask)
+            {
+                // add entry to _treeletsToBuild_ for this treelet
+                treelets_to_build.push((start, end - start));
+                start = end;
+            }
+        }
+        // create LBVHs for treelets in parallel, the leaves refer to
+        // the primitives by their position in Morton order
+        let first_bit_index: i32 = 29 - 12;
+        treelet_arenas.resize_with(treelets_to_build.len(), Arena::new);
+        let treelet_arenas: &'a mut [Arena<BVHBuildNode<'a>>] = treelet_arenas.as_mut_slice();
+        let max_prims_in_node: usize = bvh.max_prims_in_node;
+        let morton_prims_ref: &[MortonPrimitive] = &morton_prims;
+        let treelets: Vec<(&'a mut BVHBuildNode<'a>, usize)> = treelet_arenas
+            .par_iter_mut()
+            .zip(treelets_to_build.par_iter())
+            .map(|(treelet_arena, &(start_index, n_primitives))| {
+                let treelet_arena: &'a Arena<BVHBuildNode<'a>> = treelet_arena;
+                let mut nodes_created: usize = 0;
+                let root = emit_lbvh(
+                    treelet_arena,
+                    primitive_info,
+                    morton_prims_ref,
+                    start_index,
+                    n_primitives,
+                    max_prims_in_node,
+                    &mut nodes_created,
+                    first_bit_index,
+                );
+                (root, nodes_created)
+            })
+            .collect();
+        for mp in &morton_prims {
+            ordered_prims.push(bvh.primitives[mp.primitive_index].clone());
+        }
+        // create and return SAH BVH from LBVH treelets
+        let mut finished_treelets: Vec<Option<&'a mut BVHBuildNode<'a>>> =
+            Vec::with_capacity(treelets.len());
+        for (root, nodes_created) in treelets {
+            *total_nodes += nodes_created;
+            finished_treelets.push(Some(root));
+        }
+        build_upper_sah(arena, &mut finished_treelets, total_nodes)
+    }
     pub fn flatten_bvh_tree<'a>(
         node: &mut BVHBuildNode<'a>,
         nodes: &mut Vec<LinearBVHNode>,
@@ -426,27 +644,29 @@ impl BVHAccel {
         if self.nodes.len() == 0 {
             return None;
         }
-        // TODO: ProfilePhase p(Prof::AccelIntersect);
         let mut hit: bool = false;
         let inv_dir: Vector3f = Vector3f {
             x: 1.0 / ray.d.x,
             y: 1.0 / ray.d.y,
             z: 1.0 / ray.d.z,
         };
-        let dir_is_neg: [u8; 3] = [
-            (inv_dir.x < 0.0) as u8,
-            (inv_dir.y < 0.0) as u8,
-            (inv_dir.z < 0.0) as u8,
+        let dir_is_neg: [usize; 3] = [
+            (inv_dir.x < 0.0) as usize,
+            (inv_dir.y < 0.0) as usize,
+            (inv_dir.z < 0.0) as usize,
         ];
         // follow ray through BVH nodes to find primitive intersections
         let mut to_visit_offset: u32 = 0;
         let mut current_node_index: u32 = 0;
         let mut nodes_to_visit: [u32; 64] = [0_u32; 64];
         let mut si: SurfaceInteraction = SurfaceInteraction::default();
+        // counted locally, the thread-local counter is updated once
+        let mut n_nodes_visited: i64 = 0;
         loop {
+            n_nodes_visited += 1;
             let node: &LinearBVHNode = &self.nodes[current_node_index as usize];
             // check ray against BVH node
-            let intersects: bool = node.bounds.intersect_p(ray, &inv_dir, dir_is_neg);
+            let intersects: bool = node.bounds.intersect_p_precomp(ray, &inv_dir, dir_is_neg);
             if intersects {
                 if node.n_primitives > 0 {
                     // intersect ray with primitives in leaf BVH node
@@ -468,7 +688,7 @@ impl BVHAccel {
                 } else {
                     // put far BVH node on _nodesToVisit_ stack,
                     // advance to near node
-                    if dir_is_neg[node.axis as usize] == 1_u8 {
+                    if dir_is_neg[node.axis as usize] == 1 {
                         nodes_to_visit[to_visit_offset as usize] = current_node_index + 1_u32;
                         to_visit_offset += 1_u32;
                         current_node_index = node.offset as u32;
@@ -486,6 +706,7 @@ impl BVHAccel {
                 current_node_index = nodes_to_visit[to_visit_offset as usize];
             }
         }
+        crate::stat_add!(BVH_NODE_VISITS, n_nodes_visited);
         if hit {
             Some(si)
         } else {
@@ -496,28 +717,30 @@ impl BVHAccel {
         if self.nodes.len() == 0 {
             return false;
         }
-        // TODO: ProfilePhase p(Prof::AccelIntersectP);
         let inv_dir: Vector3f = Vector3f {
             x: 1.0 / ray.d.x,
             y: 1.0 / ray.d.y,
             z: 1.0 / ray.d.z,
         };
-        let dir_is_neg: [u8; 3] = [
-            (inv_dir.x < 0.0) as u8,
-            (inv_dir.y < 0.0) as u8,
-            (inv_dir.z < 0.0) as u8,
+        let dir_is_neg: [usize; 3] = [
+            (inv_dir.x < 0.0) as usize,
+            (inv_dir.y < 0.0) as usize,
+            (inv_dir.z < 0.0) as usize,
         ];
         let mut to_visit_offset: u32 = 0;
         let mut current_node_index: u32 = 0;
         let mut nodes_to_visit: [u32; 64] = [0_u32; 64];
+        let mut n_nodes_visited: i64 = 0;
         loop {
+            n_nodes_visited += 1;
             let node: &LinearBVHNode = &self.nodes[current_node_index as usize];
-            let intersects: bool = node.bounds.intersect_p(ray, &inv_dir, dir_is_neg);
+            let intersects: bool = node.bounds.intersect_p_precomp(ray, &inv_dir, dir_is_neg);
             if intersects {
                 // process BVH node _node_ for traversal
                 if node.n_primitives > 0 {
                     for i in 0..node.n_primitives {
                         if self.primitives[node.offset as usize + i as usize].intersect_p(ray) {
+                            crate::stat_add!(BVH_NODE_VISITS, n_nodes_visited);
                             return true;
                         }
                     }
@@ -527,7 +750,7 @@ impl BVHAccel {
                     to_visit_offset -= 1_u32;
                     current_node_index = nodes_to_visit[to_visit_offset as usize];
                 } else {
-                    if dir_is_neg[node.axis as usize] == 1_u8 {
+                    if dir_is_neg[node.axis as usize] == 1 {
                         nodes_to_visit[to_visit_offset as usize] = current_node_index + 1_u32;
                         to_visit_offset += 1_u32;
                         current_node_index = node.offset as u32;
@@ -545,6 +768,7 @@ impl BVHAccel {
                 current_node_index = nodes_to_visit[to_visit_offset as usize];
             }
         }
+        crate::stat_add!(BVH_NODE_VISITS, n_nodes_visited);
         false
     }
     pub fn get_material(&self) -> Option<Arc<Material>> {
@@ -554,3 +778,276 @@ impl BVHAccel {
         None
     }
 }
+
+/// Moves the primitives for which _pred_ is true to the front and
+/// returns their number (like std::partition in C++).
+fn partition_primitive_info<P>(primitive_info: &mut [BVHPrimitiveInfo], pred: P) -> usize
+where
+    P: Fn(&BVHPrimitiveInfo) -> bool,
+{
+    let mut first: usize = 0;
+    for i in 0..primitive_info.len() {
+        if pred(&primitive_info[i]) {
+            primitive_info.swap(first, i);
+            first += 1;
+        }
+    }
+    first
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct MortonPrimitive {
+    primitive_index: usize,
+    morton_code: u32,
+}
+
+/// Spreads the lower 10 bits of _x_ out to every third bit.
+fn left_shift_3(x: u32) -> u32 {
+    let mut x: u32 = x;
+    assert!(x <= (1 << 10));
+    if x == (1 << 10) {
+        x -= 1;
+    }
+    x = (x | (x << 16)) & 0b0000_0011_0000_0000_0000_0000_1111_1111;
+    // x = ---- --98 ---- ---- ---- ---- 7654 3210
+    x = (x | (x << 8)) & 0b0000_0011_0000_0000_1111_0000_0000_1111;
+    // x = ---- --98 ---- ---- 7654 ---- ---- 3210
+    x = (x | (x << 4)) & 0b0000_0011_0000_1100_0011_0000_1100_0011;
+    // x = ---- --98 ---- 76-- --54 ---- 32-- --10
+    x = (x | (x << 2)) & 0b0000_1001_0010_0100_1001_0010_0100_1001;
+    // x = ---- 9--8 --7- -6-- 5--4 --3- -2-- 1--0
+    x
+}
+
+/// Interleaves the bits of the (scaled) coordinates of _v_, which have
+/// to lie within [0, 1024].
+fn encode_morton_3(v: &Vector3f) -> u32 {
+    assert!(v.x >= 0.0 as Float);
+    assert!(v.y >= 0.0 as Float);
+    assert!(v.z >= 0.0 as Float);
+    (left_shift_3(v.z as u32) << 2) | (left_shift_3(v.y as u32) << 1) | left_shift_3(v.x as u32)
+}
+
+/// Sorts the primitives by their 30-bit Morton codes, using 6 bits
+/// per pass.
+fn radix_sort(v: &mut Vec<MortonPrimitive>) {
+    let mut temp_vector: Vec<MortonPrimitive> = vec![MortonPrimitive::default(); v.len()];
+    const BITS_PER_PASS: u32 = 6;
+    const N_BITS: u32 = 30;
+    const N_PASSES: u32 = N_BITS / BITS_PER_PASS;
+    for pass in 0..N_PASSES {
+        // perform one pass of radix sort, sorting _BITS_PER_PASS_ bits
+        let low_bit: u32 = pass * BITS_PER_PASS;
+        // set in and out vector references for radix sort pass
+        let (v_in, v_out): (&Vec<MortonPrimitive>, &mut Vec<MortonPrimitive>) = if pass & 1 == 1 {
+            (&temp_vector, v)
+        } else {
+            (v, &mut temp_vector)
+        };
+        // count number of zero bits in array for current radix sort bit
+        const N_BUCKETS: usize = 1 << BITS_PER_PASS;
+        let mut bucket_count: [usize; N_BUCKETS] = [0_usize; N_BUCKETS];
+        let bit_mask: u32 = (1 << BITS_PER_PASS) - 1;
+        for mp in v_in.iter() {
+            let bucket: usize = ((mp.morton_code >> low_bit) & bit_mask) as usize;
+            bucket_count[bucket] += 1;
+        }
+        // compute starting index in output array for each bucket
+        let mut out_index: [usize; N_BUCKETS] = [0_usize; N_BUCKETS];
+        for i in 1..N_BUCKETS {
+            out_index[i] = out_index[i - 1] + bucket_count[i - 1];
+        }
+        // store sorted values in output array
+        for mp in v_in.iter() {
+            let bucket: usize = ((mp.morton_code >> low_bit) & bit_mask) as usize;
+            v_out[out_index[bucket]] = *mp;
+            out_index[bucket] += 1;
+        }
+    }
+    // copy final result from _temp_vector_, if needed
+    if N_PASSES & 1 == 1 {
+        std::mem::swap(v, &mut temp_vector);
+    }
+}
+
+/// Recursively splits the Morton ordered primitives
+/// _morton_prims[start..start + n_primitives]_ where their codes
+/// differ in bit _bit_index_ (or a lower one).
+fn emit_lbvh<'a>(
+    arena: &'a Arena<BVHBuildNode<'a>>,
+    primitive_info: &[BVHPrimitiveInfo],
+    morton_prims: &[MortonPrimitive],
+    start: usize,
+    n_primitives: usize,
+    max_prims_in_node: usize,
+    total_nodes: &mut usize,
+    bit_index: i32,
+) -> &'a mut BVHBuildNode<'a> {
+    assert!(n_primitives > 0);
+    if (bit_index == -1 && n_primitives <= MAX_LEAF_PRIMITIVES) || n_primitives < max_prims_in_node
+    {
+        // create and return leaf node of LBVH treelet
+        *total_nodes += 1;
+        let node: &mut BVHBuildNode<'a> = arena.alloc(BVHBuildNode::default());
+        let mut bounds: Bounds3f = Bounds3f::default();
+        for mp in &morton_prims[start..start + n_primitives] {
+            bounds = bnd3_union_bnd3(&bounds, &primitive_info[mp.primitive_index].bounds);
+        }
+        node.init_leaf(start, n_primitives, &bounds);
+        node
+    } else {
+        let split_offset: usize = if bit_index == -1 {
+            // too many primitives with the same Morton code for one leaf
+            n_primitives / 2
+        } else {
+            let mask: u32 = 1 << bit_index;
+            // advance to next subtree level if there's no LBVH split for this bit
+            if (morton_prims[start].morton_code & mask)
+                == (morton_prims[start + n_primitives - 1].morton_code & mask)
+            {
+                return emit_lbvh(
+                    arena,
+                    primitive_info,
+                    morton_prims,
+                    start,
+                    n_primitives,
+                    max_prims_in_node,
+                    total_nodes,
+                    bit_index - 1,
+                );
+            }
+            // find LBVH split point for this dimension
+            let mut search_start: usize = 0;
+            let mut search_end: usize = n_primitives - 1;
+            while search_start + 1 != search_end {
+                let mid: usize = (search_start + search_end) / 2;
+                if (morton_prims[start + search_start].morton_code & mask)
+                    == (morton_prims[start + mid].morton_code & mask)
+                {
+                    search_start = mid;
+                } else {
+                    search_end = mid;
+                }
+            }
+            search_end
+        };
+        // create and return interior LBVH node
+        *total_nodes += 1;
+        let node: &mut BVHBuildNode<'a> = arena.alloc(BVHBuildNode::default());
+        let c0 = emit_lbvh(
+            arena,
+            primitive_info,
+            morton_prims,
+            start,
+            split_offset,
+            max_prims_in_node,
+            total_nodes,
+            bit_index - 1,
+        );
+        let c1 = emit_lbvh(
+            arena,
+            primitive_info,
+            morton_prims,
+            start + split_offset,
+            n_primitives - split_offset,
+            max_prims_in_node,
+            total_nodes,
+            bit_index - 1,
+        );
+        let axis: u8 = std::cmp::max(bit_index, 0) as u8 % 3;
+        node.init_interior(axis, c0, c1);
+        node
+    }
+}
+
+/// Builds the upper levels of the HLBVH over the treelet roots with
+/// SAH, taking the roots out of _treelet_roots_.
+fn build_upper_sah<'a>(
+    arena: &'a Arena<BVHBuildNode<'a>>,
+    treelet_roots: &mut [Option<&'a mut BVHBuildNode<'a>>],
+    total_nodes: &mut usize,
+) -> &'a mut BVHBuildNode<'a> {
+    assert!(!treelet_roots.is_empty());
+    let n_nodes: usize = treelet_roots.len();
+    if n_nodes == 1 {
+        return treelet_roots[0].take().unwrap();
+    }
+    *total_nodes += 1;
+    let node: &mut BVHBuildNode<'a> = arena.alloc(BVHBuildNode::default());
+    // compute bounds of all nodes under this HLBVH node
+    let root_bounds =
+        |root: &Option<&'a mut BVHBuildNode<'a>>| -> Bounds3f { root.as_ref().unwrap().bounds };
+    let root_centroid = |root: &Option<&'a mut BVHBuildNode<'a>>| -> Point3f {
+        let b: Bounds3f = root_bounds(root);
+        b.p_min * 0.5 + b.p_max * 0.5
+    };
+    let mut bounds: Bounds3f = Bounds3f::default();
+    let mut centroid_bounds: Bounds3f = Bounds3f::default();
+    for root in treelet_roots.iter() {
+        bounds = bnd3_union_bnd3(&bounds, &root_bounds(root));
+        centroid_bounds = bnd3_union_pnt3(&centroid_bounds, &root_centroid(root));
+    }
+    let dim: u8 = centroid_bounds.maximum_extent();
+    let mut mid: usize = n_nodes / 2;
+    // treelets have distinct Morton code prefixes, but their centroids
+    // can still coincide
+    if centroid_bounds.p_max[dim] != centroid_bounds.p_min[dim] {
+        // allocate _BucketInfo_ for SAH partition buckets
+        let n_buckets: usize = 12;
+        let mut buckets: [BucketInfo; 12] = [BucketInfo::default(); 12];
+        let bucket = |root: &Option<&'a mut BVHBuildNode<'a>>| -> usize {
+            let b: usize =
+                (n_buckets as Float * centroid_bounds.offset(&root_centroid(root))[dim]) as usize;
+            std::cmp::min(b, n_buckets - 1)
+        };
+        // initialize _BucketInfo_ for HLBVH SAH partition buckets
+        for root in treelet_roots.iter() {
+            let b: usize = bucket(root);
+            buckets[b].count += 1;
+            buckets[b].bounds = bnd3_union_bnd3(&buckets[b].bounds, &root_bounds(root));
+        }
+        // compute costs for splitting after each bucket
+        let mut cost: [Float; 11] = [0.0; 11];
+        for i in 0..(n_buckets - 1) {
+            let mut b0: Bounds3f = Bounds3f::default();
+            let mut b1: Bounds3f = Bounds3f::default();
+            let mut count0: usize = 0;
+            let mut count1: usize = 0;
+            for j in 0..(i + 1) {
+                b0 = bnd3_union_bnd3(&b0, &buckets[j].bounds);
+                count0 += buckets[j].count;
+            }
+            for j in (i + 1)..n_buckets {
+                b1 = bnd3_union_bnd3(&b1, &buckets[j].bounds);
+                count1 += buckets[j].count;
+            }
+            cost[i] = 0.125
+                + (count0 as Float * b0.surface_area() + count1 as Float * b1.surface_area())
+                    / bounds.surface_area();
+        }
+        // find bucket to split at that minimizes SAH metric
+        let mut min_cost: Float = cost[0];
+        let mut min_cost_split_bucket: usize = 0;
+        for i in 1..(n_buckets - 1) {
+            if cost[i] < min_cost {
+                min_cost = cost[i];
+                min_cost_split_bucket = i;
+            }
+        }
+        // split nodes and create interior HLBVH SAH node
+        let mut first: usize = 0;
+        for i in 0..n_nodes {
+            if bucket(&treelet_roots[i]) <= min_cost_split_bucket {
+                treelet_roots.swap(first, i);
+                first += 1;
+            }
+        }
+        mid = first;
+    }
+    assert!(mid > 0 && mid < n_nodes);
+    let (left, right) = treelet_roots.split_at_mut(mid);
+    let c0 = build_upper_sah(arena, left, total_nodes);
+    let c1 = build_upper_sah(arena, right, total_nodes);
+    node.init_interior(dim, c0, c1);
+    node
+}
@@ -0,0 +1,417 @@
+//! Acceleration structure using Intel's Embree ray tracing kernels
+//! (enabled with the **embree** cargo feature, which links against
+//! the Embree 4 library, see https://www.embree.org). The bindings
+//! are generated from the installed headers by build.rs, which
+//! rejects other major versions, and **EmbreeAccel::new()** checks
+//! that the library isn't older than the headers.
+//!
+//! Static triangle meshes are handed to Embree as native triangle
+//! geometry. All other primitives (curves, quadrics, animated meshes,
+//! object instances, ...) become user geometry, whose callbacks call
+//! the existing **Primitive::intersect()** and
+//! **Primitive::intersect_p()** methods. Triangles with alpha masks
+//! get a filter function which rejects hits on the masked parts. The
+//! **SurfaceInteraction** of a triangle hit is filled in at the
+//! barycentric coordinates Embree found, user geometry computes it
+//! with its own intersect() method again, so shading is the same as
+//! for the other accelerators.
+//!
+//! Select it in a scene file with:
+//!
+//! ```text
+//! Accelerator "embree"
+//! ```
+
+// std
+use std::collections::HashMap;
+use std::os::raw::{c_uint, c_void};
+use std::ptr;
+use std::sync::Arc;
+// pbrt
+use crate::core::geometry::{bnd3_union_bnd3, Bounds3f, Point3f, Ray, Vector3f};
+use crate::core::interaction::SurfaceInteraction;
+use crate::core::light::Light;
+use crate::core::material::Material;
+use crate::core::paramset::ParamSet;
+use crate::core::pbrt::Float;
+use crate::core::primitive::{GeometricPrimitive, Primitive};
+use crate::core::shape::Shape;
+use crate::shapes::triangle::{Triangle, TriangleMesh};
+
+/// Bindings generated by build.rs from the installed Embree headers.
+#[allow(
+    non_camel_case_types,
+    non_snake_case,
+    non_upper_case_globals,
+    dead_code,
+    clippy::all
+)]
+mod sys {
+    include!(concat!(env!("OUT_DIR"), "/embree4.rs"));
+}
+use sys::*;
+
+// defined with a cast in rtcore_common.h, which bindgen can't translate
+const RTC_INVALID_GEOMETRY_ID: c_uint = c_uint::MAX;
+
+/// The primitives behind one Embree geometry, indexed by Embree's
+/// primitive ID. Embree keeps a pointer to it as geometry user data.
+struct GeometryData {
+    primitives: Vec<Arc<Primitive>>,
+}
+
+pub struct EmbreeAccel {
+    device: RTCDevice,
+    scene: RTCScene,
+    /// indexed by Embree's geometry ID
+    geometries: Vec<Box<GeometryData>>,
+    bounds: Bounds3f,
+}
+
+// Embree scenes can be traversed by several threads at once after
+// rtcCommitScene(), and the callbacks only read the primitives.
+unsafe impl Send for EmbreeAccel {}
+unsafe impl Sync for EmbreeAccel {}
+
+impl EmbreeAccel {
+    pub fn new(p: Vec<Arc<Primitive>>) -> Self {
+        let device: RTCDevice = unsafe { rtcNewDevice(ptr::null()) };
+        assert!(!device.is_null(), "Unable to create Embree device");
+        // the library has to be at least as new as the headers the
+        // bindings were generated from
+        let version: u32 =
+            unsafe { rtcGetDeviceProperty(device, RTC_DEVICE_PROPERTY_VERSION) } as u32;
+        assert!(
+            version / 10000 == RTC_VERSION_MAJOR && version >= RTC_VERSION,
+            "Embree library version {} doesn't match the headers (version {})",
+            version,
+            RTC_VERSION
+        );
+        let scene: RTCScene = unsafe { rtcNewScene(device) };
+        let mut bounds: Bounds3f = Bounds3f::default();
+        // static triangle meshes become native triangle geometry, one
+        // per mesh, all other primitives are user geometry
+        let mut mesh_indices: HashMap<*const TriangleMesh, usize> = HashMap::new();
+        let mut meshes: Vec<(Arc<TriangleMesh>, Vec<u32>, GeometryData)> = Vec::new();
+        let mut others: GeometryData = GeometryData {
+            primitives: Vec::new(),
+        };
+        for (i, prim) in p.iter().enumerate() {
+            let prim_bounds: Bounds3f = prim.world_bound();
+            bounds = if i == 0 {
+                prim_bounds
+            } else {
+                bnd3_union_bnd3(&bounds, &prim_bounds)
+            };
+            if let Primitive::Geometric(primitive) = &**prim {
+                if let Shape::Trngl(triangle) = &*primitive.shape {
+                    let mesh: Arc<TriangleMesh> = triangle.get_mesh();
+                    if mesh.animated_object_to_world.is_none() {
+                        let key: *const TriangleMesh = Arc::as_ptr(&mesh);
+                        let index: usize = *mesh_indices.entry(key).or_insert_with(|| {
+                            meshes.push((
+                                mesh.clone(),
+                                Vec::new(),
+                                GeometryData {
+                                    primitives: Vec::new(),
+                                },
+                            ));
+                            meshes.len() - 1
+                        });
+                        meshes[index].1.push(triangle.id);
+                        meshes[index].2.primitives.push(prim.clone());
+                        continue;
+                    }
+                }
+            }
+            others.primitives.push(prim.clone());
+        }
+        let mut geometries: Vec<Box<GeometryData>> = Vec::with_capacity(meshes.len() + 1);
+        for (mesh, triangle_ids, data) in meshes {
+            let data: Box<GeometryData> = Box::new(data);
+            let has_alpha: bool = mesh.alpha_mask.is_some() || mesh.shadow_alpha_mask.is_some();
+            unsafe {
+                let geometry: RTCGeometry = rtcNewGeometry(device, RTC_GEOMETRY_TYPE_TRIANGLE);
+                // the mesh vertices are already in world space
+                let vertices = rtcSetNewGeometryBuffer(
+                    geometry,
+                    RTC_BUFFER_TYPE_VERTEX,
+                    0,
+                    RTC_FORMAT_FLOAT3,
+                    3 * std::mem::size_of::<f32>(),
+                    mesh.p.len(),
+                ) as *mut f32;
+                let vertices: &mut [f32] =
+                    std::slice::from_raw_parts_mut(vertices, 3 * mesh.p.len());
+                for (v, p) in mesh.p.iter().enumerate() {
+                    vertices[3 * v] = p.x as f32;
+                    vertices[3 * v + 1] = p.y as f32;
+                    vertices[3 * v + 2] = p.z as f32;
+                }
+                let indices = rtcSetNewGeometryBuffer(
+                    geometry,
+                    RTC_BUFFER_TYPE_INDEX,
+                    0,
+                    RTC_FORMAT_UINT3,
+                    3 * std::mem::size_of::<u32>(),
+                    triangle_ids.len(),
+                ) as *mut u32;
+                let indices: &mut [u32] =
+                    std::slice::from_raw_parts_mut(indices, 3 * triangle_ids.len());
+                for (t, id) in triangle_ids.iter().enumerate() {
+                    let first: usize = 3 * *id as usize;
+                    indices[3 * t..3 * t + 3]
+                        .copy_from_slice(&mesh.vertex_indices[first..first + 3]);
+                }
+                if has_alpha {
+                    rtcSetGeometryIntersectFilterFunction(geometry, Some(alpha_intersect_filter));
+                    rtcSetGeometryOccludedFilterFunction(geometry, Some(alpha_occluded_filter));
+                }
+                attach_geometry(scene, geometry, data, &mut geometries);
+            }
+        }
+        if !others.primitives.is_empty() {
+            let data: Box<GeometryData> = Box::new(others);
+            unsafe {
+                let geometry: RTCGeometry = rtcNewGeometry(device, RTC_GEOMETRY_TYPE_USER);
+                rtcSetGeometryUserPrimitiveCount(geometry, data.primitives.len() as c_uint);
+                rtcSetGeometryBoundsFunction(geometry, Some(user_bounds), ptr::null_mut());
+                rtcSetGeometryIntersectFunction(geometry, Some(user_intersect));
+                rtcSetGeometryOccludedFunction(geometry, Some(user_occluded));
+                attach_geometry(scene, geometry, data, &mut geometries);
+            }
+        }
+        unsafe {
+            rtcCommitScene(scene);
+            let error: RTCError = rtcGetDeviceError(device);
+            assert_eq!(error, RTC_ERROR_NONE, "Embree error {}", error);
+        }
+        EmbreeAccel {
+            device,
+            scene,
+            geometries,
+            bounds,
+        }
+    }
+    pub fn create(prims: Vec<Arc<Primitive>>, _ps: &ParamSet) -> Primitive {
+        Primitive::Embree(EmbreeAccel::new(prims))
+    }
+    pub fn world_bound(&self) -> Bounds3f {
+        self.bounds
+    }
+    pub fn intersect(&self, ray: &mut Ray) -> Option<SurfaceInteraction<'_>> {
+        let mut rayhit: RTCRayHit = RTCRayHit {
+            ray: rtc_ray(ray),
+            hit: RTCHit {
+                primID: RTC_INVALID_GEOMETRY_ID,
+                geomID: RTC_INVALID_GEOMETRY_ID,
+                instID: [RTC_INVALID_GEOMETRY_ID; RTC_MAX_INSTANCE_LEVEL_COUNT as usize],
+                ..Default::default()
+            },
+        };
+        unsafe {
+            rtcIntersect1(self.scene, &mut rayhit, ptr::null_mut());
+        }
+        if rayhit.hit.geomID == RTC_INVALID_GEOMETRY_ID {
+            return None;
+        }
+        let primitive: &Arc<Primitive> =
+            &self.geometries[rayhit.hit.geomID as usize].primitives[rayhit.hit.primID as usize];
+        if let Some((geometric, triangle)) = native_triangle(primitive) {
+            // fill in the surface interaction at Embree's barycentric
+            // coordinates instead of intersecting the triangle again,
+            // which could miss hits close to the edges
+            let b1: Float = rayhit.hit.u as Float;
+            let b2: Float = rayhit.hit.v as Float;
+            let mut isect: SurfaceInteraction =
+                triangle.interaction_at(ray, 1.0 as Float - b1 - b2, b1, b2)?;
+            isect = geometric.complete_interaction(ray, isect, rayhit.ray.tfar as Float);
+            isect.primitive = Some(&**primitive);
+            return Some(isect);
+        }
+        // user geometry: the closest primitive computes the surface
+        // interaction (and updates _t_max_) as it did in
+        // user_intersect(), for the same ray
+        primitive.intersect(ray)
+    }
+    pub fn intersect_p(&self, ray: &Ray) -> bool {
+        let mut rtc: RTCRay = rtc_ray(ray);
+        unsafe {
+            rtcOccluded1(self.scene, &mut rtc, ptr::null_mut());
+        }
+        // Embree sets tfar to -inf for occluded rays
+        rtc.tfar < 0.0
+    }
+    pub fn get_material(&self) -> Option<Arc<Material>> {
+        None
+    }
+    pub fn get_area_light(&self) -> Option<Arc<Light>> {
+        None
+    }
+}
+
+impl Drop for EmbreeAccel {
+    fn drop(&mut self) {
+        unsafe {
+            rtcReleaseScene(self.scene);
+            rtcReleaseDevice(self.device);
+        }
+    }
+}
+
+unsafe fn attach_geometry(
+    scene: RTCScene,
+    geometry: RTCGeometry,
+    data: Box<GeometryData>,
+    geometries: &mut Vec<Box<GeometryData>>,
+) {
+    // the boxed data doesn't move when the vector grows
+    rtcSetGeometryUserData(geometry, &*data as *const GeometryData as *mut c_void);
+    rtcCommitGeometry(geometry);
+    let geom_id: c_uint = rtcAttachGeometry(scene, geometry);
+    assert_eq!(geom_id as usize, geometries.len());
+    rtcReleaseGeometry(geometry);
+    geometries.push(data);
+}
+
+fn rtc_ray(ray: &Ray) -> RTCRay {
+    RTCRay {
+        org_x: ray.o.x as f32,
+        org_y: ray.o.y as f32,
+        org_z: ray.o.z as f32,
+        tnear: 0.0,
+        dir_x: ray.d.x as f32,
+        dir_y: ray.d.y as f32,
+        dir_z: ray.d.z as f32,
+        time: ray.time as f32,
+        tfar: ray.t_max as f32,
+        mask: c_uint::MAX,
+        id: 0,
+        flags: 0,
+    }
+}
+
+/// Rebuilds the ray passed to a callback. Only what affects the
+/// distance of the hit point is kept (e.g. no medium).
+fn pbrt_ray(rtc: &RTCRay, t_max: Float) -> Ray {
+    Ray {
+        o: Point3f {
+            x: rtc.org_x as Float,
+            y: rtc.org_y as Float,
+            z: rtc.org_z as Float,
+        },
+        d: Vector3f {
+            x: rtc.dir_x as Float,
+            y: rtc.dir_y as Float,
+            z: rtc.dir_z as Float,
+        },
+        t_max,
+        time: rtc.time as Float,
+        medium: None,
+        differential: None,
+    }
+}
+
+unsafe extern "C" fn user_bounds(args: *const RTCBoundsFunctionArguments) {
+    let args: &RTCBoundsFunctionArguments = &*args;
+    let data: &GeometryData = &*(args.geometryUserPtr as *const GeometryData);
+    let b: Bounds3f = data.primitives[args.primID as usize].world_bound();
+    *args.bounds_o = RTCBounds {
+        lower_x: b.p_min.x as f32,
+        lower_y: b.p_min.y as f32,
+        lower_z: b.p_min.z as f32,
+        align0: 0.0,
+        upper_x: b.p_max.x as f32,
+        upper_y: b.p_max.y as f32,
+        upper_z: b.p_max.z as f32,
+        align1: 0.0,
+    };
+}
+
+unsafe extern "C" fn user_intersect(args: *const RTCIntersectFunctionNArguments) {
+    // rtcIntersect1() passes a single ray
+    let args: &RTCIntersectFunctionNArguments = &*args;
+    if *args.valid == 0 {
+        return;
+    }
+    let data: &GeometryData = &*(args.geometryUserPtr as *const GeometryData);
+    let rayhit: &mut RTCRayHit = &mut *(args.rayhit as *mut RTCRayHit);
+    let mut ray: Ray = pbrt_ray(&rayhit.ray, rayhit.ray.tfar as Float);
+    if let Some(isect) = data.primitives[args.primID as usize].intersect(&mut ray) {
+        rayhit.ray.tfar = ray.t_max as f32;
+        rayhit.hit.Ng_x = isect.n.x as f32;
+        rayhit.hit.Ng_y = isect.n.y as f32;
+        rayhit.hit.Ng_z = isect.n.z as f32;
+        rayhit.hit.u = isect.uv.x as f32;
+        rayhit.hit.v = isect.uv.y as f32;
+        rayhit.hit.primID = args.primID;
+        rayhit.hit.geomID = args.geomID;
+        rayhit.hit.instID[0] = RTC_INVALID_GEOMETRY_ID;
+    }
+}
+
+unsafe extern "C" fn user_occluded(args: *const RTCOccludedFunctionNArguments) {
+    // rtcOccluded1() passes a single ray
+    let args: &RTCOccludedFunctionNArguments = &*args;
+    if *args.valid == 0 {
+        return;
+    }
+    let data: &GeometryData = &*(args.geometryUserPtr as *const GeometryData);
+    let rtc: &mut RTCRay = &mut *(args.ray as *mut RTCRay);
+    let ray: Ray = pbrt_ray(rtc, rtc.tfar as Float);
+    if data.primitives[args.primID as usize].intersect_p(&ray) {
+        rtc.tfar = -std::f32::INFINITY;
+    }
+}
+
+/// The primitive of a native Embree triangle geometry (a static
+/// triangle, see EmbreeAccel::new()) and its triangle.
+fn native_triangle(primitive: &Primitive) -> Option<(&GeometricPrimitive, &Triangle)> {
+    if let Primitive::Geometric(geometric) = primitive {
+        if let Shape::Trngl(triangle) = &*geometric.shape {
+            if triangle.get_mesh().animated_object_to_world.is_none() {
+                return Some((geometric, triangle));
+            }
+        }
+    }
+    None
+}
+
+/// Rejects hits on triangles which the alpha mask cuts away, by
+/// evaluating the mask at the hit's barycentric coordinates.
+unsafe extern "C" fn alpha_intersect_filter(args: *const RTCFilterFunctionNArguments) {
+    let args: &RTCFilterFunctionNArguments = &*args;
+    if *args.valid == 0 {
+        return;
+    }
+    let data: &GeometryData = &*(args.geometryUserPtr as *const GeometryData);
+    // called for single rays, see user_intersect()
+    let hit: &RTCHit = &*(args.hit as *const RTCHit);
+    let ray: Ray = pbrt_ray(&*(args.ray as *const RTCRay), std::f32::INFINITY);
+    if let Some((_geometric, triangle)) = native_triangle(&data.primitives[hit.primID as usize]) {
+        let b1: Float = hit.u as Float;
+        let b2: Float = hit.v as Float;
+        if triangle
+            .interaction_at(&ray, 1.0 as Float - b1 - b2, b1, b2)
+            .is_none()
+        {
+            *args.valid = 0;
+        }
+    }
+}
+
+/// Same as alpha_intersect_filter(), but for shadow rays, which also
+/// respect the shadow alpha mask.
+unsafe extern "C" fn alpha_occluded_filter(args: *const RTCFilterFunctionNArguments) {
+    let args: &RTCFilterFunctionNArguments = &*args;
+    if *args.valid == 0 {
+        return;
+    }
+    let data: &GeometryData = &*(args.geometryUserPtr as *const GeometryData);
+    // called for single rays, see user_intersect()
+    let hit: &RTCHit = &*(args.hit as *const RTCHit);
+    let ray: Ray = pbrt_ray(&*(args.ray as *const RTCRay), std::f32::INFINITY);
+    if !data.primitives[hit.primID as usize].intersect_p(&ray) {
+        *args.valid = 0;
+    }
+}
@@ -479,17 +479,22 @@ impl KdTreeAccel {
             return None;
         }
         // compute initial parametric range of ray inside kd-tree extent
-        let mut t_min: Float = 0.0;
-        let mut t_max: Float = 0.0;
-        if !self.bounds.intersect_b(&ray, &mut t_min, &mut t_max) {
-            return None;
-        }
-        // prepare to traverse kd-tree for ray
         let inv_dir: Vector3f = Vector3f {
             x: 1.0 / ray.d.x,
             y: 1.0 / ray.d.y,
             z: 1.0 / ray.d.z,
         };
+        let dir_is_neg: [usize; 3] = [
+            (inv_dir.x < 0.0) as usize,
+            (inv_dir.y < 0.0) as usize,
+            (inv_dir.z < 0.0) as usize,
+        ];
+        let (mut t_min, mut t_max): (Float, Float) =
+            match self.bounds.intersect_b_precomp(&ray, &inv_dir, dir_is_neg) {
+                Some(range) => range,
+                None => return None,
+            };
+        // prepare to traverse kd-tree for ray
         let mut todo: [KdToDo; MAX_TODO] = [KdToDo::default(); MAX_TODO];
         let mut todo_pos: usize = 0;
         // traverse kd-tree nodes in order for ray
@@ -604,17 +609,22 @@ impl KdTreeAccel {
             return false;
         }
         // compute initial parametric range of ray inside kd-tree extent
-        let mut t_min: Float = 0.0;
-        let mut t_max: Float = 0.0;
-        if !self.bounds.intersect_b(&ray, &mut t_min, &mut t_max) {
-            return false;
-        }
-        // prepare to traverse kd-tree for ray
         let inv_dir: Vector3f = Vector3f {
             x: 1.0 / ray.d.x,
             y: 1.0 / ray.d.y,
             z: 1.0 / ray.d.z,
         };
+        let dir_is_neg: [usize; 3] = [
+            (inv_dir.x < 0.0) as usize,
+            (inv_dir.y < 0.0) as usize,
+            (inv_dir.z < 0.0) as usize,
+        ];
+        let (mut t_min, mut t_max): (Float, Float) =
+            match self.bounds.intersect_b_precomp(&ray, &inv_dir, dir_is_neg) {
+                Some(range) => range,
+                None => return false,
+            };
+        // prepare to traverse kd-tree for ray
         let mut todo: [KdToDo; MAX_TODO] = [KdToDo::default(); MAX_TODO];
         let mut todo_pos: usize = 0;
         let mut node_idx: usize = 0;
@@ -7,6 +7,9 @@
 //!
 //! - BVHAccel
 //! - KdTreeAccel
+//! - EmbreeAccel (optional, see the **embree** feature)
 
 pub mod bvh;
+#[cfg(feature = "embree")]
+pub mod embree;
 pub mod kdtreeaccel;
@@ -1,6 +1,10 @@
 use pest_derive::*;
 
 pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+/// exit code for errors in (or while loading) the scene description
+pub const EXIT_PARSE_ERROR: i32 = 1;
+/// exit code for errors after WorldEnd
+pub const EXIT_RENDER_ERROR: i32 = 2;
 
 #[derive(Parser)]
 #[grammar = "../examples/pbrt.pest"]
@@ -12,29 +16,30 @@ use pest::Parser;
 // getopts
 use getopts::Options;
 // pbrt
-use pbrt::core::api::{
-    pbrt_accelerator, pbrt_active_transform_all, pbrt_active_transform_end_time,
-    pbrt_active_transform_start_time, pbrt_area_light_source, pbrt_attribute_begin,
-    pbrt_attribute_end, pbrt_camera, pbrt_cleanup, pbrt_concat_transform, pbrt_coord_sys_transform,
-    pbrt_film, pbrt_init, pbrt_integrator, pbrt_light_source, pbrt_look_at,
-    pbrt_make_named_material, pbrt_make_named_medium, pbrt_material, pbrt_medium_interface,
-    pbrt_named_material, pbrt_object_begin, pbrt_object_end, pbrt_object_instance,
-    pbrt_pixel_filter, pbrt_reverse_orientation, pbrt_rotate, pbrt_sampler, pbrt_scale, pbrt_shape,
-    pbrt_texture, pbrt_transform, pbrt_transform_begin, pbrt_transform_end, pbrt_translate,
-    pbrt_world_begin,
-};
-use pbrt::core::api::{ApiState, BsdfState};
-use pbrt::core::geometry::{Normal3f, Point2f, Point3f, Vector3f};
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::film::FilmSnapshot;
+use pbrt::core::geometry::{Normal3f, Point2f, Point3f, Vector2f, Vector3f};
+use pbrt::core::integrator::RenderCheckpoint;
 use pbrt::core::paramset::ParamSet;
-use pbrt::core::pbrt::{Float, Spectrum};
+use pbrt::core::pbrt::Float;
+#[cfg(feature = "serde")]
+use pbrt::core::scenecache::SceneCache;
+use pbrt::core::stats::print_stats;
 use pbrt::core::transform::Transform;
 // std
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
 use std::io::Read;
+use std::io::Write;
+use std::panic;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// set once WorldEnd starts the rendering
+static RENDERING: AtomicBool = AtomicBool::new(false);
 
 fn print_usage(program: &str, opts: Options) {
     let brief = format!("Usage: {} [options]", program);
@@ -200,7 +205,26 @@ fn pbrt_texture_parameter(pairs: &mut pest::iterators::Pairs<Rule>) -> (String,
     (string1, string2)
 }
 
-fn extract_params(key_word: String, pairs: pest::iterators::Pair<Rule>) -> ParamSet {
+/// Checks that a point, normal or vector parameter comes with a
+/// multiple of _n_ values.
+fn check_coordinates(location: &Location, kind: &str, name: &str, values: &[Float], n: usize) {
+    if values.len() % n != 0 {
+        panic!(
+            "{}: \"{} {}\" needs a multiple of {} values ({} found)",
+            location,
+            kind,
+            name,
+            n,
+            values.len()
+        );
+    }
+}
+
+fn extract_params(
+    key_word: String,
+    pairs: pest::iterators::Pair<Rule>,
+    location: &Location,
+) -> ParamSet {
     let mut params: ParamSet = ParamSet::default();
     params.key_word = key_word;
     let mut counter: u8 = 0_u8;
@@ -298,6 +322,7 @@ fn extract_params(key_word: String, pairs: pest::iterators::Pair<Rule>) -> Param
                                 pbrt_float_parameter(&mut parameter_pair.into_inner());
                             let string: String = tuple.0;
                             let floats: Vec<Float> = tuple.1;
+                            check_coordinates(location, "point", &string, &floats, 3);
                             if floats.len() == 3 {
                                 params.add_point3f(
                                     string,
@@ -316,6 +341,7 @@ fn extract_params(key_word: String, pairs: pest::iterators::Pair<Rule>) -> Param
                                 pbrt_float_parameter(&mut parameter_pair.into_inner());
                             let string: String = tuple.0;
                             let floats: Vec<Float> = tuple.1;
+                            check_coordinates(location, "point2", &string, &floats, 2);
                             if floats.len() == 2 {
                                 params.add_point2f(
                                     string,
@@ -333,6 +359,7 @@ fn extract_params(key_word: String, pairs: pest::iterators::Pair<Rule>) -> Param
                                 pbrt_float_parameter(&mut parameter_pair.into_inner());
                             let string: String = tuple.0;
                             let floats: Vec<Float> = tuple.1;
+                            check_coordinates(location, "normal", &string, &floats, 3);
                             if floats.len() == 3 {
                                 params.add_normal3f(
                                     string,
@@ -351,12 +378,7 @@ fn extract_params(key_word: String, pairs: pest::iterators::Pair<Rule>) -> Param
                                 pbrt_float_parameter(&mut parameter_pair.into_inner());
                             let string: String = tuple.0;
                             let floats: Vec<Float> = tuple.1;
-                            params.add_rgb_spectrum(
-                                string,
-                                Spectrum {
-                                    c: [floats[0], floats[1], floats[2]],
-                                },
-                            );
+                            params.add_rgb(string, [floats[0], floats[1], floats[2]]);
                         }
                         Rule::spectrum_param => {
                             // TODO: "spectrum Kd" [ 300 .3  400 .6   410 .65  415 .8  500 .2  600 .1 ]
@@ -394,11 +416,30 @@ fn extract_params(key_word: String, pairs: pest::iterators::Pair<Rule>) -> Param
                             let string2: String = tuple.1;
                             params.add_texture(string1, string2);
                         }
+                        Rule::vector2_param => {
+                            let tuple: (String, Vec<Float>) =
+                                pbrt_float_parameter(&mut parameter_pair.into_inner());
+                            let string: String = tuple.0;
+                            let floats: Vec<Float> = tuple.1;
+                            check_coordinates(location, "vector2", &string, &floats, 2);
+                            if floats.len() == 2 {
+                                params.add_vector2f(
+                                    string,
+                                    Vector2f {
+                                        x: floats[0],
+                                        y: floats[1],
+                                    },
+                                );
+                            } else {
+                                params.add_vector2fs(string, floats);
+                            }
+                        }
                         Rule::vector_param => {
                             let tuple: (String, Vec<Float>) =
                                 pbrt_float_parameter(&mut parameter_pair.into_inner());
                             let string: String = tuple.0;
                             let floats: Vec<Float> = tuple.1;
+                            check_coordinates(location, "vector", &string, &floats, 3);
                             if floats.len() == 3 {
                                 params.add_vector3f(
                                     string,
@@ -423,52 +464,68 @@ fn extract_params(key_word: String, pairs: pest::iterators::Pair<Rule>) -> Param
     params
 }
 
-fn parse_line(
-    api_state: &mut ApiState,
-    bsdf_state: &mut BsdfState,
-    identifier: &str,
-    str_buf: String,
-) {
+/// Where a statement was found: file and line, and (for included
+/// files) where the file was included from.
+struct Location<'a> {
+    filename: &'a str,
+    line: usize,
+    included_from: &'a str,
+}
+
+impl<'a> fmt::Display for Location<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.filename, self.line)?;
+        if self.included_from != "" {
+            write!(f, ", included from {}", self.included_from)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_line(builder: &mut SceneBuilder, identifier: &str, str_buf: String, location: &Location) {
     if str_buf == "" {
         // no additional arguments
         match identifier {
             "AttributeBegin" => {
                 // AttributeBegin
                 // println!("{} {}", identifier, str_buf);
-                pbrt_attribute_begin(api_state);
+                builder.attribute_begin();
             }
             "AttributeEnd" => {
                 // AttributeEnd
                 // println!("{} {}", identifier, str_buf);
-                pbrt_attribute_end(api_state);
+                builder.attribute_end();
             }
             "ObjectEnd" => {
                 // ObjectEnd
                 // println!("{} {}", identifier, str_buf);
-                pbrt_object_end(api_state);
+                builder.object_end();
             }
             "ReverseOrientation" => {
                 // ReverseOrientation
                 // println!("{} {}", identifier, str_buf);
-                pbrt_reverse_orientation(api_state);
+                builder.reverse_orientation();
             }
             "TransformBegin" => {
                 // TransformBegin
-                pbrt_transform_begin(api_state);
+                builder.transform_begin();
             }
             "TransformEnd" => {
                 // TransformEnd
-                pbrt_transform_end(api_state);
+                builder.transform_end();
             }
             "WorldBegin" => {
                 // WorldBegin
                 // println!("{} {}", identifier, str_buf);
-                pbrt_world_begin(api_state);
+                builder.world_begin();
             }
             "WorldEnd" => {
                 // WorldEnd
                 // println!("{} {}", identifier, str_buf);
-                pbrt_cleanup(api_state);
+                #[cfg(feature = "serde")]
+                write_scene_cache(builder);
+                RENDERING.store(true, Ordering::SeqCst);
+                builder.world_end();
             }
             _ => println!("{} {:?}", identifier, str_buf),
         }
@@ -476,7 +533,7 @@ fn parse_line(
         let statement = String::from(identifier) + " " + &str_buf;
         // println!("DEBUG: {:?}", &statement);
         let pairs = PbrtParser::parse(Rule::name_and_or_params, &statement)
-            .expect("unsuccessful parse")
+            .unwrap_or_else(|e| panic!("{}: unsuccessful parse of {}: {}", location, identifier, e))
             .next()
             .unwrap();
         for inner_pair in pairs.into_inner() {
@@ -486,89 +543,92 @@ fn parse_line(
                     // identifier "type" parameter-list
                     let for_printing = inner_pair.as_str();
                     // println!("DEBUG: {}", for_printing);
-                    let params = extract_params(String::from(identifier), inner_pair);
+                    let params = extract_params(String::from(identifier), inner_pair, location);
                     match identifier {
                         "Accelerator" => {
                             // Accelerator
-                            pbrt_accelerator(api_state, params);
+                            builder.accelerator(params);
                         }
                         "AreaLightSource" => {
                             // AreaLightSource
-                            pbrt_area_light_source(api_state, params);
+                            builder.area_light_source(params);
                         }
                         "Camera" => {
                             // Camera
-                            pbrt_camera(api_state, params);
+                            builder.camera(params);
+                        }
+                        "CoordinateSystem" => {
+                            // CoordinateSystem
+                            builder.coordinate_system(params);
                         }
                         "CoordSysTransform" => {
                             // CoordSysTransform
-                            pbrt_coord_sys_transform(api_state, params);
+                            builder.coord_sys_transform(params);
                         }
                         "Film" => {
                             // Film
-                            pbrt_film(api_state, params);
+                            builder.film(params);
                         }
                         "Include" => {
-                            // Include
+                            // Include (relative to the including file)
                             let mut include_file: String = params.name.clone();
-                            if let Some(ref search_directory) = api_state.search_directory {
-                                let mut path_buf: PathBuf = PathBuf::from("/");
-                                path_buf.push(search_directory.as_ref());
-                                path_buf.push(params.name);
-                                include_file = String::from(path_buf.to_str().unwrap());
-                                // println!("DEBUG: {:?}", include_file);
+                            if Path::new(&params.name).is_relative() {
+                                if let Some(parent) = Path::new(location.filename).parent() {
+                                    let path_buf: PathBuf = parent.join(&params.name);
+                                    include_file = String::from(path_buf.to_str().unwrap());
+                                }
                             }
                             let todo: Vec<&str> = for_printing.splitn(3, '"').collect();
                             println!("Include {:?}", include_file);
-                            parse_file(include_file, api_state, bsdf_state, todo[2]);
+                            parse_file(include_file, builder, todo[2], &location.to_string());
                         }
                         "Integrator" => {
                             // Integrator
-                            pbrt_integrator(api_state, params);
+                            builder.integrator(params);
                         }
                         "LightSource" => {
                             // LightSource
-                            pbrt_light_source(api_state, params);
+                            builder.light_source(params);
                         }
                         "MakeNamedMaterial" => {
                             // MakeNamedMaterial
-                            pbrt_make_named_material(api_state, bsdf_state, params);
+                            builder.make_named_material(params);
                         }
                         "MakeNamedMedium" => {
                             // MakeNamedMedium
-                            pbrt_make_named_medium(api_state, params);
+                            builder.make_named_medium(params);
                         }
                         "Material" => {
                             // Material
-                            pbrt_material(api_state, params);
+                            builder.material(params);
                         }
                         "NamedMaterial" => {
                             // NamedMaterial
-                            pbrt_named_material(api_state, params);
+                            builder.named_material(params);
                         }
                         "ObjectBegin" => {
                             // ObjectBegin
-                            pbrt_object_begin(api_state, params);
+                            builder.object_begin(params);
                         }
                         "ObjectInstance" => {
                             // ObjectInstance
-                            pbrt_object_instance(api_state, params);
+                            builder.object_instance(params);
                         }
                         "PixelFilter" => {
                             // PixelFilter
-                            pbrt_pixel_filter(api_state, params);
+                            builder.pixel_filter(params);
                         }
                         "Sampler" => {
                             // Sampler
-                            pbrt_sampler(api_state, params);
+                            builder.sampler(params);
                         }
                         "Shape" => {
                             // Shape
-                            pbrt_shape(api_state, bsdf_state, params);
+                            builder.shape(params);
                         }
                         "Texture" => {
                             // Texture
-                            pbrt_texture(api_state, params);
+                            builder.texture(params);
                         }
                         _ => println!("> {}", for_printing),
                     }
@@ -578,13 +638,13 @@ fn parse_line(
                     for rule_pair in inner_pair.into_inner() {
                         match rule_pair.as_rule() {
                             Rule::all => {
-                                pbrt_active_transform_all(api_state);
+                                builder.active_transform_all();
                             }
                             Rule::start_time => {
-                                pbrt_active_transform_start_time(api_state);
+                                builder.active_transform_start_time();
                             }
                             Rule::end_time => {
-                                pbrt_active_transform_end_time(api_state);
+                                builder.active_transform_end_time();
                             }
                             _ => unreachable!(),
                         }
@@ -623,7 +683,7 @@ fn parse_line(
                         m00, m10, m20, m30, m01, m11, m21, m31, m02, m12, m22, m32, m03, m13, m23,
                         m33,
                     );
-                    pbrt_concat_transform(api_state, &tr);
+                    builder.concat_transform(&tr);
                 }
                 Rule::look_at => {
                     // LookAt eye_x eye_y eye_z look_x look_y look_z up_x up_y up_z
@@ -637,9 +697,7 @@ fn parse_line(
                     //     "LookAt {} {} {} {} {} {} {} {} {}",
                     //     v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7], v[8],
                     // );
-                    pbrt_look_at(
-                        api_state, v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7], v[8],
-                    );
+                    builder.look_at(v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7], v[8]);
                 }
                 Rule::medium_interface => {
                     // MediumInterface
@@ -664,7 +722,7 @@ fn parse_line(
                         "ERROR: expected two strings, found {:?}",
                         strings.len()
                     );
-                    pbrt_medium_interface(api_state, &strings[0], &strings[1]);
+                    builder.medium_interface(&strings[0], &strings[1]);
                 }
                 Rule::rotate => {
                     // Rotate angle x y z
@@ -675,7 +733,7 @@ fn parse_line(
                         v.push(number);
                     }
                     // println!("Rotate {} {} {} {}", v[0], v[1], v[2], v[3]);
-                    pbrt_rotate(api_state, v[0], v[1], v[2], v[3]);
+                    builder.rotate(v[0], v[1], v[2], v[3]);
                 }
                 Rule::scale => {
                     // Scale x y z
@@ -686,7 +744,7 @@ fn parse_line(
                         v.push(number);
                     }
                     // println!("Scale {} {} {}", v[0], v[1], v[2]);
-                    pbrt_scale(api_state, v[0], v[1], v[2]);
+                    builder.scale(v[0], v[1], v[2]);
                 }
                 Rule::transform => {
                     // Transform m00 .. m33
@@ -721,7 +779,7 @@ fn parse_line(
                         m00, m10, m20, m30, m01, m11, m21, m31, m02, m12, m22, m32, m03, m13, m23,
                         m33,
                     );
-                    pbrt_transform(api_state, &tr);
+                    builder.transform(&tr);
                 }
                 Rule::translate => {
                     // Translate x y z
@@ -732,7 +790,7 @@ fn parse_line(
                         v.push(number);
                     }
                     // println!("Translate {} {} {}", v[0], v[1], v[2]);
-                    pbrt_translate(api_state, v[0], v[1], v[2]);
+                    builder.translate(v[0], v[1], v[2]);
                 }
                 Rule::remaining_line => {
                     // predetermined number of arguments of predetermined type
@@ -745,21 +803,31 @@ fn parse_line(
     }
 }
 
-fn parse_file(
-    filename: String,
-    api_state: &mut ApiState,
-    bsdf_state: &mut BsdfState,
-    append: &str,
-) {
+/// Parses a scene file. Files are included (see the Include
+/// directive) relative to the including file, _included_from_ is the
+/// location of the Include statement (empty for the main scene file)
+/// and gets reported with parse errors.
+fn parse_file(filename: String, builder: &mut SceneBuilder, append: &str, included_from: &str) {
     // println!("FILE = {}", x);
-    let f = File::open(filename.clone()).unwrap();
-    let ip: &Path = Path::new(filename.as_str());
-    if ip.is_relative() {
+    let f = match File::open(filename.clone()) {
+        Ok(f) => f,
+        Err(e) => {
+            if included_from != "" {
+                panic!("{}: unable to include {:?}: {}", included_from, filename, e);
+            } else {
+                panic!("Unable to open {:?}: {}", filename, e);
+            }
+        }
+    };
+    builder.record_source(Path::new(&filename));
+    if included_from == "" {
+        // textures, meshes, etc. are found relative to the main scene file
+        let ip: &Path = Path::new(filename.as_str());
         let cp: PathBuf = env::current_dir().unwrap();
         let pb: PathBuf = cp.join(ip);
         let search_directory: &Path = pb.as_path().parent().unwrap();
         // println!("search_directory is {}", search_directory.display());
-        api_state.search_directory = Some(Box::new(PathBuf::from(search_directory)));
+        builder.search_directory(PathBuf::from(search_directory));
     }
     let mut reader = BufReader::new(f);
     let mut str_buf: String = String::default();
@@ -773,10 +841,22 @@ fn parse_file(
         str_buf += "\n";
     }
     let pairs = PbrtParser::parse(Rule::pbrt, &str_buf)
-        .expect("unsuccessful parse")
+        .unwrap_or_else(|e| {
+            let line: usize = match e.line_col {
+                pest::error::LineColLocation::Pos((line, _col)) => line,
+                pest::error::LineColLocation::Span((line, _col), _) => line,
+            };
+            let location = Location {
+                filename: &filename,
+                line,
+                included_from,
+            };
+            panic!("{}: unsuccessful parse: {}", location, e)
+        })
         .next()
         .unwrap();
     let mut identifier: &str = "";
+    let mut identifier_line: usize = 0;
     // let mut comment_count: u64 = 0;
     // let mut empty_count: u64 = 0;
     // let mut todo_count: u64 = 0;
@@ -793,9 +873,15 @@ fn parse_file(
                     match statement_pair.as_rule() {
                         Rule::identifier => {
                             if identifier != "" {
-                                parse_line(api_state, bsdf_state, identifier, parse_again.clone());
+                                let location = Location {
+                                    filename: &filename,
+                                    line: identifier_line,
+                                    included_from,
+                                };
+                                parse_line(builder, identifier, parse_again.clone(), &location);
                             }
                             identifier = statement_pair.as_str();
+                            identifier_line = statement_pair.as_span().start_pos().line_col().0;
                             parse_again = String::default();
                         }
                         Rule::remaining_line => {
@@ -840,7 +926,14 @@ fn parse_file(
                     }
                 }
             }
-            Rule::EOI => parse_line(api_state, bsdf_state, identifier, parse_again.clone()),
+            Rule::EOI => {
+                let location = Location {
+                    filename: &filename,
+                    line: identifier_line,
+                    included_from,
+                };
+                parse_line(builder, identifier, parse_again.clone(), &location)
+            }
             _ => unreachable!(),
         }
     }
@@ -849,6 +942,121 @@ fn parse_file(
     // println!("Number of empty line(s):     {}", empty_count);
 }
 
+/// 64-bit FNV-1a hash, stable across builds (unlike the hashers of
+/// the standard library), used to match checkpoints to scene files.
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Parses "WxH" (e.g. "640x480").
+/// Parses "HOST:PORT" (the port defaults to 14158, tev's default).
+fn parse_tev_address(address: &str) -> Option<(String, u16)> {
+    match address.rfind(':') {
+        Some(i) => Some((
+            String::from(&address[..i]),
+            address[i + 1..].parse::<u16>().ok()?,
+        )),
+        None => Some((String::from(address), 14158_u16)),
+    }
+}
+
+fn parse_resolution(resolution: &str) -> Option<(i32, i32)> {
+    let mut parts = resolution.splitn(2, 'x');
+    let xres: i32 = parts.next()?.parse::<i32>().ok()?;
+    let yres: i32 = parts.next()?.parse::<i32>().ok()?;
+    if xres > 0 && yres > 0 {
+        Some((xres, yres))
+    } else {
+        None
+    }
+}
+
+/// Redirects stdout to stderr (so all messages go there) and returns
+/// the original stdout, to write an image to it.
+#[cfg(unix)]
+fn take_stdout() -> Box<dyn Write> {
+    use std::os::unix::io::FromRawFd;
+    unsafe {
+        let fd: libc::c_int = libc::dup(1);
+        assert!(fd >= 0, "ERROR: unable to duplicate stdout");
+        libc::dup2(2, 1);
+        Box::new(File::from_raw_fd(fd))
+    }
+}
+
+/// On other platforms messages and the image share stdout.
+#[cfg(not(unix))]
+fn take_stdout() -> Box<dyn Write> {
+    Box::new(std::io::stdout())
+}
+
+/// The cache file for a scene file, e.g. scene.pbrt.bin for
+/// scene.pbrt.
+#[cfg(feature = "serde")]
+fn scene_cache_file(scene_file: &Path) -> PathBuf {
+    let mut cache_file = scene_file.as_os_str().to_owned();
+    cache_file.push(".bin");
+    PathBuf::from(cache_file)
+}
+
+/// Replays (and renders) the cached scene description, if the cache
+/// file exists and none of the scene files changed since it was
+/// written.
+#[cfg(feature = "serde")]
+fn load_scene_cache(scene_file: &str, builder: &mut SceneBuilder) -> bool {
+    let scene_path: PathBuf = match std::fs::canonicalize(scene_file) {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+    let cache_file: PathBuf = scene_cache_file(&scene_path);
+    if !cache_file.exists() {
+        return false;
+    }
+    let cache: SceneCache = match SceneCache::read(&cache_file) {
+        Ok(cache) => cache,
+        Err(e) => {
+            println!("WARNING: unable to read {:?}: {}", cache_file, e);
+            return false;
+        }
+    };
+    if cache.sources[0].path != scene_path || !cache.is_up_to_date() {
+        println!("{:?} is out of date", cache_file);
+        return false;
+    }
+    println!("Reading scene description from {:?}", cache_file);
+    cache.replay(builder);
+    RENDERING.store(true, Ordering::SeqCst);
+    builder.world_end();
+    true
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_scene_cache(_scene_file: &str, _builder: &mut SceneBuilder) -> bool {
+    println!("WARNING: rs_pbrt was built without the serde feature, --cache is ignored");
+    false
+}
+
+/// Writes the statements recorded so far next to the (main) scene
+/// file, see **load_scene_cache()**.
+#[cfg(feature = "serde")]
+fn write_scene_cache(builder: &mut SceneBuilder) {
+    if let Some(cache) = builder.take_recording() {
+        if cache.sources.is_empty() {
+            return;
+        }
+        let cache_file: PathBuf = scene_cache_file(&cache.sources[0].path);
+        match cache.write(&cache_file) {
+            Ok(()) => println!("Wrote scene description to {:?}", cache_file),
+            Err(e) => println!("WARNING: unable to write {:?}: {}", cache_file, e),
+        }
+    }
+}
+
 fn main() {
     // handle command line options
     let args: Vec<String> = env::args().collect();
@@ -856,13 +1064,75 @@ fn main() {
     let mut opts = Options::new();
     opts.optflag("h", "help", "print this help menu");
     opts.optopt("i", "", "parse an input file", "FILE");
+    opts.optflag(
+        "p",
+        "progressive",
+        "render one sample per pass and write pass_NNNN.png after each",
+    );
     opts.optopt(
         "t",
         "nthreads",
         "use specified number of threads for rendering",
         "NUM",
     );
+    opts.optflag("q", "quiet", "don't warn about unused parameters");
+    opts.optopt(
+        "",
+        "spp",
+        "override the samples per pixel of the Sampler directive",
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "resolution",
+        "override the resolution of the Film directive",
+        "WxH",
+    );
+    opts.optopt(
+        "",
+        "outfile",
+        "override the filename of the Film directive (\"-\" writes a PNG to stdout)",
+        "FILE",
+    );
+    opts.optflag(
+        "",
+        "quick",
+        "render a preview (a 16th of the samples at half the resolution)",
+    );
+    opts.optopt(
+        "",
+        "tev",
+        "show the image in the tev viewer while it renders (requires the tev feature)",
+        "HOST:PORT",
+    );
+    opts.optflag(
+        "",
+        "cache",
+        "read the scene from (or write it to) FILE.bin, if FILE didn't change",
+    );
     opts.optflag("v", "version", "print version number");
+    opts.optopt(
+        "",
+        "seed",
+        "seed for the random samplers (same seed, same image)",
+        "NUM",
+    );
+    opts.optflag(
+        "",
+        "stats",
+        "print statistics (counters and timings) after rendering",
+    );
+    opts.optopt(
+        "",
+        "checkpoint-interval",
+        "write FILE.checkpoint every SECS seconds while rendering",
+        "SECS",
+    );
+    opts.optflag(
+        "",
+        "resume",
+        "continue rendering from FILE.checkpoint (written by --checkpoint-interval)",
+    );
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
         Err(f) => panic!(f.to_string()),
@@ -871,6 +1141,11 @@ fn main() {
         print_usage(&program, opts);
         return;
     } else if matches.opt_present("i") {
+        // keep stdout clean for the image
+        let mut image_stdout: Option<Box<dyn Write>> = None;
+        if matches.opt_str("outfile") == Some(String::from("-")) {
+            image_stdout = Some(take_stdout());
+        }
         let mut number_of_threads: u8 = 0_u8;
         if matches.opt_present("t") {
             let nthreads = matches.opt_str("t");
@@ -897,8 +1172,116 @@ fn main() {
                 println!(
                     "Rust code based on C++ code by Matt Pharr, Greg Humphreys, and Wenzel Jakob."
                 );
-                let (mut api_state, mut bsdf_state) = pbrt_init(number_of_threads);
-                parse_file(x, &mut api_state, &mut bsdf_state, "");
+                let mut builder = SceneBuilder::new(number_of_threads);
+                builder.api_state.quiet = matches.opt_present("q");
+                if let Some(seed) = matches.opt_str("seed") {
+                    builder.api_state.render_options.seed = seed
+                        .parse::<u64>()
+                        .expect("ERROR: unsigned integer seed expected");
+                }
+                if matches.opt_present("p") {
+                    builder.api_state.render_options.progressive = true;
+                    builder.api_state.render_options.on_pass_complete =
+                        Some(Box::new(|snapshot: &FilmSnapshot| {
+                            let filename: String =
+                                format!("pass_{:04}.png", snapshot.samples_per_pixel);
+                            if let Err(e) = snapshot.write_png(&filename) {
+                                println!("ERROR: writing {:?} failed: {}", filename, e);
+                            }
+                        }));
+                }
+                if matches.opt_present("checkpoint-interval") || matches.opt_present("resume") {
+                    // default: every five minutes
+                    let interval: Float = match matches.opt_str("checkpoint-interval") {
+                        Some(secs) => secs
+                            .parse::<Float>()
+                            .expect("ERROR: number of seconds expected"),
+                        None => 300.0 as Float,
+                    };
+                    let scene_bytes: Vec<u8> = std::fs::read(&x)
+                        .unwrap_or_else(|e| panic!("Unable to read {:?}: {}", x, e));
+                    builder.api_state.render_options.checkpoint = Some(RenderCheckpoint {
+                        filename: format!("{}.checkpoint", x),
+                        interval,
+                        scene_hash: fnv1a_hash(&scene_bytes),
+                        resume: matches.opt_present("resume"),
+                    });
+                }
+                // bad overrides are errors in the scene setup, reported
+                // before anything gets parsed or rendered
+                if let Some(spp) = matches.opt_str("spp") {
+                    match spp.parse::<i32>() {
+                        Ok(spp) if spp > 0 => {
+                            builder.api_state.render_options.pixel_samples = Some(spp);
+                        }
+                        _ => {
+                            println!(
+                                "ERROR: --spp {:?}: at least one sample per pixel expected",
+                                spp
+                            );
+                            std::process::exit(EXIT_PARSE_ERROR);
+                        }
+                    }
+                }
+                if let Some(resolution) = matches.opt_str("resolution") {
+                    match parse_resolution(&resolution) {
+                        Some(resolution) => {
+                            builder.api_state.render_options.resolution = Some(resolution);
+                        }
+                        None => {
+                            println!(
+                                "ERROR: --resolution {:?}: resolution like \"640x480\" expected",
+                                resolution
+                            );
+                            std::process::exit(EXIT_PARSE_ERROR);
+                        }
+                    }
+                }
+                if let Some(address) = matches.opt_str("tev") {
+                    builder.api_state.render_options.tev = Some(
+                        parse_tev_address(&address)
+                            .expect("ERROR: address like \"127.0.0.1:14158\" expected"),
+                    );
+                }
+                // the film writes a PNG file, which gets copied to stdout
+                let stdout_png: PathBuf =
+                    env::temp_dir().join(format!("rs_pbrt_{}.png", std::process::id()));
+                if let Some(outfile) = matches.opt_str("outfile") {
+                    builder.api_state.render_options.image_file = if outfile == "-" {
+                        Some(String::from(stdout_png.to_str().unwrap()))
+                    } else {
+                        Some(outfile)
+                    };
+                }
+                builder.api_state.render_options.quick_render = matches.opt_present("quick");
+                let use_cache: bool = matches.opt_present("cache");
+                let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                    if !(use_cache && load_scene_cache(&x, &mut builder)) {
+                        if use_cache && cfg!(feature = "serde") {
+                            builder.start_recording();
+                        }
+                        parse_file(x, &mut builder, "", "");
+                    }
+                }));
+                if result.is_err() {
+                    // the panic message was printed already
+                    if RENDERING.load(Ordering::SeqCst) {
+                        std::process::exit(EXIT_RENDER_ERROR);
+                    } else {
+                        std::process::exit(EXIT_PARSE_ERROR);
+                    }
+                }
+                if matches.opt_present("stats") {
+                    print_stats(&mut std::io::stdout()).expect("Failed to print statistics");
+                }
+                if let Some(mut image_stdout) = image_stdout {
+                    let mut png: File = File::open(&stdout_png)
+                        .unwrap_or_else(|e| panic!("Unable to open {:?}: {}", stdout_png, e));
+                    std::io::copy(&mut png, &mut image_stdout)
+                        .expect("ERROR: unable to write the image to stdout");
+                    image_stdout.flush().unwrap();
+                    std::fs::remove_file(&stdout_png).unwrap();
+                }
             }
             None => panic!("No input file name."),
         }
@@ -61,11 +61,16 @@ impl BlockQueue {
     }
     /// Get the next block in the queue or None if the queue is finished
     pub fn next(&self) -> Option<(u32, u32)> {
+        self.next_indexed().map(|(_, block)| block)
+    }
+    /// Like `next`, but also returns the position of the block in the
+    /// queue, e.g. to merge results in a fixed order
+    pub fn next_indexed(&self) -> Option<(usize, (u32, u32))> {
         let i = self.next.fetch_add(1, Ordering::AcqRel);
         if i >= self.blocks.len() {
             None
         } else {
-            Some(self.blocks[i])
+            Some((i, self.blocks[i]))
         }
     }
     /// Get the length of the queue
@@ -74,7 +79,7 @@ impl BlockQueue {
     }
     /// Check if the queue is empty
     pub fn is_empty(&self) -> bool {
-        self.next.load(Ordering::AcqRel) >= self.blocks.len()
+        self.next.load(Ordering::Acquire) >= self.blocks.len()
     }
 }
 
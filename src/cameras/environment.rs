@@ -5,7 +5,7 @@ use std::sync::Arc;
 // pbrt
 use crate::core::camera::{Camera, CameraSample};
 use crate::core::film::Film;
-use crate::core::geometry::{Bounds2f, Point2f, Point3f, Ray, Vector3f};
+use crate::core::geometry::{Bounds2f, Point2f, Point3f, Ray, RayDifferential, Vector3f};
 use crate::core::interaction::InteractionCommon;
 use crate::core::light::VisibilityTester;
 use crate::core::medium::Medium;
@@ -89,8 +89,9 @@ impl EnvironmentCamera {
         )));
         camera
     }
-    // Camera
-    pub fn generate_ray_differential(&self, sample: &CameraSample, ray: &mut Ray) -> Float {
+    pub fn generate_ray(&self, sample: &CameraSample, ray: &mut Ray) -> Float {
+        // TODO: ProfilePhase prof(Prof::GenerateCameraRay);
+        // compute environment camera ray direction
         let theta: Float = PI * sample.p_film.y / self.film.full_resolution.y as Float;
         let phi: Float = 2.0 as Float * PI * sample.p_film.x / self.film.full_resolution.x as Float;
         let dir: Vector3f = Vector3f {
@@ -115,6 +116,37 @@ impl EnvironmentCamera {
         *ray = self.camera_to_world.transform_ray(&in_ray);
         1.0
     }
+    // Camera
+    pub fn generate_ray_differential(&self, sample: &CameraSample, ray: &mut Ray) -> Float {
+        // see Camera::GenerateRayDifferential() in camera.cpp
+        let wt: Float = self.generate_ray(sample, ray);
+        if wt == 0.0 as Float {
+            return 0.0 as Float;
+        }
+        // find camera ray after shifting one pixel in the $x$ direction
+        let mut sshift: CameraSample = *sample;
+        sshift.p_film.x += 1.0 as Float;
+        let mut rx: Ray = Ray::default();
+        let wtx: Float = self.generate_ray(&sshift, &mut rx);
+        if wtx == 0.0 as Float {
+            return 0.0 as Float;
+        }
+        // find camera ray after shifting one pixel in the $y$ direction
+        sshift.p_film.x = sample.p_film.x;
+        sshift.p_film.y += 1.0 as Float;
+        let mut ry: Ray = Ray::default();
+        let wty: Float = self.generate_ray(&sshift, &mut ry);
+        if wty == 0.0 as Float {
+            return 0.0 as Float;
+        }
+        ray.differential = Some(RayDifferential {
+            rx_origin: rx.o,
+            ry_origin: ry.o,
+            rx_direction: rx.d,
+            ry_direction: ry.d,
+        });
+        wt
+    }
     pub fn we(&self, _ray: &Ray, _p_raster2: Option<&mut Point2f>) -> Spectrum {
         panic!("camera::we() is not implemented!");
         // Spectrum::default()
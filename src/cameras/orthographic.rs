@@ -16,6 +16,73 @@ use crate::core::transform::{AnimatedTransform, Transform};
 
 // see orthographic.h
 
+/// Camera based on the orthographic projection: all rays travel
+/// along the camera's +z axis, so objects keep their size
+/// regardless of their distance to the camera:
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use pbrt::cameras::orthographic::OrthographicCamera;
+/// use pbrt::core::camera::CameraSample;
+/// use pbrt::core::film::Film;
+/// use pbrt::core::filter::Filter;
+/// use pbrt::core::geometry::{Bounds2f, Bounds3f, Point2f, Point2i, Point3f, Ray, Vector2f, Vector3f};
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::transform::{AnimatedTransform, Transform};
+/// use pbrt::filters::boxfilter::BoxFilter;
+///
+/// fn main() {
+///     let res: i32 = 32;
+///     let filter = Box::new(Filter::Bx(BoxFilter {
+///         radius: Vector2f { x: 0.5, y: 0.5 },
+///         inv_radius: Vector2f { x: 2.0, y: 2.0 },
+///     }));
+///     let crop = Bounds2f {
+///         p_min: Point2f { x: 0.0, y: 0.0 },
+///         p_max: Point2f { x: 1.0, y: 1.0 },
+///     };
+///     let film = Arc::new(Film::new(
+///         Point2i { x: res, y: res }, crop, filter, 35.0, String::new(), 1.0, Float::INFINITY,
+///     ));
+///     let screen = Bounds2f {
+///         p_min: Point2f { x: -1.0, y: -1.0 },
+///         p_max: Point2f { x: 1.0, y: 1.0 },
+///     };
+///     // axis-aligned unit cube around the origin
+///     let cube = Bounds3f {
+///         p_min: Point3f { x: -0.5, y: -0.5, z: -0.5 },
+///         p_max: Point3f { x: 0.5, y: 0.5, z: 0.5 },
+///     };
+///     for distance in &[2.0 as Float, 10.0, 1000.0] {
+///         let world_to_camera = Transform::look_at(
+///             &Point3f { x: 0.0, y: 0.0, z: -distance },
+///             &Point3f::default(),
+///             &Vector3f { x: 0.0, y: 1.0, z: 0.0 },
+///         );
+///         let camera_to_world = Transform::inverse(&world_to_camera);
+///         let camera = OrthographicCamera::new(
+///             AnimatedTransform::new(&camera_to_world, 0.0, &camera_to_world, 1.0),
+///             screen, 0.0, 1.0, 0.0, 1e6, film.clone(), None,
+///         );
+///         // the silhouette covers exactly the center 16x16 pixels
+///         for y in 0..res {
+///             for x in 0..res {
+///                 let sample = CameraSample {
+///                     p_film: Point2f { x: x as Float + 0.5, y: y as Float + 0.5 },
+///                     p_lens: Point2f::default(),
+///                     time: 0.0,
+///                 };
+///                 let mut ray = Ray::default();
+///                 camera.generate_ray(&sample, &mut ray);
+///                 let (mut t0, mut t1): (Float, Float) = (0.0, 0.0);
+///                 let hit: bool = cube.intersect_b(&ray, &mut t0, &mut t1);
+///                 let inside = |i: i32| i >= res / 4 && i < 3 * res / 4;
+///                 assert_eq!(hit, inside(x) && inside(y));
+///             }
+///         }
+///     }
+/// }
+/// ```
 pub struct OrthographicCamera {
     // inherited from Camera (see camera.h)
     pub camera_to_world: AnimatedTransform,
@@ -102,10 +169,15 @@ impl OrthographicCamera {
         film: Arc<Film>,
         medium: Option<Arc<Medium>>,
     ) -> Arc<Camera> {
-        let shutteropen: Float = params.find_one_float("shutteropen", 0.0);
-        let shutterclose: Float = params.find_one_float("shutterclose", 1.0);
-        // TODO: std::swap(shutterclose, shutteropen);
-        assert!(shutterclose >= shutteropen);
+        let mut shutteropen: Float = params.find_one_float("shutteropen", 0.0);
+        let mut shutterclose: Float = params.find_one_float("shutterclose", 1.0);
+        if shutterclose < shutteropen {
+            println!(
+                "WARNING: Shutter close time [{}] < shutter open [{}].  Swapping them.",
+                shutterclose, shutteropen
+            );
+            std::mem::swap(&mut shutterclose, &mut shutteropen);
+        }
         let lensradius: Float = params.find_one_float("lensradius", 0.0);
         let focaldistance: Float = params.find_one_float("focaldistance", 1e6);
         let frame: Float = params.find_one_float(
@@ -147,9 +219,9 @@ impl OrthographicCamera {
         )));
         camera
     }
-    // Camera
-    pub fn generate_ray_differential(&self, sample: &CameraSample, ray: &mut Ray) -> Float {
-        // TODO: ProfilePhase prof(Prof::GenerateCameraRay);
+    /// Computes the camera space ray for a sample (including depth of
+    /// field) and the unperturbed point on the near plane it starts at.
+    fn generate_camera_space_ray(&self, sample: &CameraSample) -> (Ray, Point3f) {
         // compute raster and camera sample positions
         let p_film: Point3f = Point3f {
             x: sample.p_film.x,
@@ -157,7 +229,7 @@ impl OrthographicCamera {
             z: 0.0,
         };
         let p_camera: Point3f = self.raster_to_camera.transform_point(&p_film);
-        *ray = Ray {
+        let mut in_ray: Ray = Ray {
             o: p_camera,
             d: Vector3f {
                 x: 0.0,
@@ -174,76 +246,89 @@ impl OrthographicCamera {
             // sample point on lens
             let p_lens: Point2f = concentric_sample_disk(&sample.p_lens) * self.lens_radius;
             // compute point on plane of focus
-            let ft: Float = self.focal_distance / ray.d.z;
-            let p_focus: Point3f = ray.position(ft);
+            let ft: Float = self.focal_distance / in_ray.d.z;
+            let p_focus: Point3f = in_ray.position(ft);
             // update ray for effect of lens
-            ray.o = Point3f {
+            in_ray.o = Point3f {
                 x: p_lens.x,
                 y: p_lens.y,
                 z: 0.0 as Float,
             };
-            ray.d = (p_focus - ray.o).normalize();
+            in_ray.d = (p_focus - in_ray.o).normalize();
         }
+        // ray->medium = medium;
+        if let Some(ref medium_arc) = self.medium {
+            in_ray.medium = Some(medium_arc.clone());
+        } else {
+            in_ray.medium = None;
+        }
+        (in_ray, p_camera)
+    }
+    pub fn generate_ray(&self, sample: &CameraSample, ray: &mut Ray) -> Float {
+        // TODO: ProfilePhase prof(Prof::GenerateCameraRay);
+        let (in_ray, _p_camera) = self.generate_camera_space_ray(sample);
+        *ray = self.camera_to_world.transform_ray(&in_ray);
+        1.0
+    }
+    // Camera
+    pub fn generate_ray_differential(&self, sample: &CameraSample, ray: &mut Ray) -> Float {
+        // TODO: ProfilePhase prof(Prof::GenerateCameraRay);
+        let (mut in_ray, p_camera) = self.generate_camera_space_ray(sample);
         // compute offset rays for _OrthographicCamera_ ray differentials
         if self.lens_radius > 0.0 as Float {
             // compute _OrthographicCamera_ ray differentials accounting for lens
 
             // sample point on lens
             let p_lens: Point2f = concentric_sample_disk(&sample.p_lens) * self.lens_radius;
-            let ft: Float = self.focal_distance / ray.d.z;
-            let p_focus: Point3f = p_camera
-                + self.dx_camera
-                + (Vector3f {
-                    x: 0.0 as Float,
-                    y: 0.0 as Float,
-                    z: 1.0 as Float,
-                } * ft);
-            let rx_origin = Point3f {
-                x: p_lens.x,
-                y: p_lens.y,
-                z: 0.0 as Float,
-            };
-            let ry_origin = Point3f {
+            // all unperturbed rays travel along +z, so the plane of
+            // focus is always _focal_distance_ away
+            let ft: Float = self.focal_distance;
+            let forward: Vector3f = Vector3f {
+                x: 0.0 as Float,
+                y: 0.0 as Float,
+                z: 1.0 as Float,
+            } * ft;
+            let lens_origin = Point3f {
                 x: p_lens.x,
                 y: p_lens.y,
                 z: 0.0 as Float,
             };
+            let p_focus_x: Point3f = p_camera + self.dx_camera + forward;
+            let p_focus_y: Point3f = p_camera + self.dy_camera + forward;
             let diff = RayDifferential {
-                rx_origin: rx_origin,
-                rx_direction: (p_focus - rx_origin).normalize(),
-                ry_origin: ry_origin,
-                ry_direction: (p_focus - ry_origin).normalize(),
+                rx_origin: lens_origin,
+                rx_direction: (p_focus_x - lens_origin).normalize(),
+                ry_origin: lens_origin,
+                ry_direction: (p_focus_y - lens_origin).normalize(),
             };
             // replace differential
-            ray.differential = Some(diff);
+            in_ray.differential = Some(diff);
         } else {
             let diff: RayDifferential = RayDifferential {
-                rx_origin: ray.o + self.dx_camera,
-                ry_origin: ray.o + self.dy_camera,
-                rx_direction: ray.d,
-                ry_direction: ray.d,
+                rx_origin: in_ray.o + self.dx_camera,
+                ry_origin: in_ray.o + self.dy_camera,
+                rx_direction: in_ray.d,
+                ry_direction: in_ray.d,
             };
-            ray.differential = Some(diff);
+            in_ray.differential = Some(diff);
         }
-        // ray->medium = medium;
-        if let Some(ref medium_arc) = self.medium {
-            ray.medium = Some(medium_arc.clone());
-        } else {
-            ray.medium = None;
-        }
-        *ray = self.camera_to_world.transform_ray(ray);
+        *ray = self.camera_to_world.transform_ray(&in_ray);
         1.0
     }
+    /// Importance emitted by the camera is only defined for the
+    /// perspective camera (like in the C++ version). Bidirectional
+    /// integrators (BDPT, MLT) can't be used with an orthographic
+    /// camera.
     pub fn we(&self, _ray: &Ray, _p_raster2: Option<&mut Point2f>) -> Spectrum {
-        panic!("camera::we() is not implemented!");
-        // Spectrum::default()
+        panic!("OrthographicCamera::we() is not supported (use a perspective camera for BDPT/MLT)");
     }
+    /// See we(); not supported for orthographic cameras.
     pub fn pdf_we(&self, _ray: &Ray) -> (Float, Float) {
-        // let mut pdf_pos: Float = 0.0;
-        // let mut pdf_dir: Float = 0.0;
-        panic!("camera::pdf_we() is not implemented!");
-        // (pdf_pos, pdf_dir)
+        panic!(
+            "OrthographicCamera::pdf_we() is not supported (use a perspective camera for BDPT/MLT)"
+        );
     }
+    /// See we(); not supported for orthographic cameras.
     pub fn sample_wi(
         &self,
         _iref: &InteractionCommon,
@@ -253,8 +338,9 @@ impl OrthographicCamera {
         _p_raster: &mut Point2f,
         _vis: &mut VisibilityTester,
     ) -> Spectrum {
-        panic!("camera::sample_wi() is not implemented!");
-        // Spectrum::default()
+        panic!(
+            "OrthographicCamera::sample_wi() is not supported (use a perspective camera for BDPT/MLT)"
+        );
     }
     pub fn get_shutter_open(&self) -> Float {
         self.shutter_open
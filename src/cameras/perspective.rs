@@ -1,7 +1,10 @@
 // std
 use std;
 use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+// others
+use image::{DynamicImage, ImageResult};
 // pbrt
 use crate::core::camera::{Camera, CameraSample};
 use crate::core::film::Film;
@@ -12,14 +15,207 @@ use crate::core::geometry::{
 use crate::core::interaction::InteractionCommon;
 use crate::core::light::VisibilityTester;
 use crate::core::medium::{Medium, MediumInterface};
+use crate::core::mipmap::{ImageWrap, MipMap};
 use crate::core::paramset::ParamSet;
-use crate::core::pbrt::lerp;
+use crate::core::pbrt::{clamp_t, lerp};
 use crate::core::pbrt::{Float, Spectrum};
-use crate::core::sampling::concentric_sample_disk;
+use crate::core::rng::FLOAT_ONE_MINUS_EPSILON;
+use crate::core::sampling::{concentric_sample_disk, uniform_sample_triangle, Distribution2D};
 use crate::core::transform::{AnimatedTransform, Transform};
 
 // see perspective.h
 
+/// Shape of the aperture of a **PerspectiveCamera**. Out-of-focus
+/// highlights (bokeh) take the shape of the aperture.
+pub enum ApertureShape {
+    /// The thin lens disk
+    Circle,
+    /// Regular polygon with the given number of corners, inscribed in
+    /// the lens disk (with a corner at the top)
+    NGon(u32),
+    /// Grayscale image covering the square around the lens disk, its
+    /// values weight the lens positions
+    Image(MipMap<Float>),
+}
+
+/// The aperture shape together with what's needed to sample it.
+pub struct Aperture {
+    pub shape: ApertureShape,
+    /// Area for a lens radius of one (image apertures count non-zero
+    /// texels fully)
+    pub area: Float,
+    distribution: Option<Distribution2D>,
+}
+
+impl Aperture {
+    pub fn new(shape: ApertureShape) -> Self {
+        match shape {
+            ApertureShape::Circle => Aperture {
+                shape,
+                area: PI,
+                distribution: None,
+            },
+            ApertureShape::NGon(n) => {
+                assert!(n >= 3, "Aperture polygons need at least three corners");
+                let area: Float =
+                    0.5 as Float * n as Float * (2.0 as Float * PI / n as Float).sin();
+                Aperture {
+                    shape,
+                    area,
+                    distribution: None,
+                }
+            }
+            ApertureShape::Image(mipmap) => {
+                let width: i32 = mipmap.width();
+                let height: i32 = mipmap.height();
+                let mut func: Vec<Float> = Vec::with_capacity((width * height) as usize);
+                for t in 0..height {
+                    for s in 0..width {
+                        func.push(mipmap.texel(0, s as isize, t as isize).max(0.0 as Float));
+                    }
+                }
+                let n_non_zero: usize = func.iter().filter(|v| **v > 0.0 as Float).count();
+                if n_non_zero == 0 {
+                    println!("WARNING: Aperture image is black, using \"circle\".");
+                    return Aperture::new(ApertureShape::Circle);
+                }
+                let area: Float = 4.0 as Float * n_non_zero as Float / func.len() as Float;
+                let distribution: Distribution2D = Distribution2D::new(func, width, height);
+                Aperture {
+                    shape: ApertureShape::Image(mipmap),
+                    area,
+                    distribution: Some(distribution),
+                }
+            }
+        }
+    }
+    /// Maps a uniform sample to a point on the aperture (for a lens
+    /// radius of one). The circle is sampled like the thin lens always
+    /// was, polygons are sampled uniformly by picking one of the
+    /// triangles around the center and remapping the sample onto it,
+    /// so no samples get rejected:
+    ///
+    /// ```rust
+    /// use pbrt::cameras::perspective::{Aperture, ApertureShape};
+    /// use pbrt::core::geometry::Point2f;
+    /// use pbrt::core::mipmap::{ImageWrap, MipMap};
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::sampling::concentric_sample_disk;
+    /// use std::f32::consts::PI;
+    ///
+    /// fn main() {
+    ///     let n: usize = 64;
+    ///     let mut samples: Vec<Point2f> = Vec::new();
+    ///     for i in 0..n {
+    ///         for j in 0..n {
+    ///             samples.push(Point2f {
+    ///                 x: (i as Float + 0.5) / n as Float,
+    ///                 y: (j as Float + 0.5) / n as Float,
+    ///             });
+    ///         }
+    ///     }
+    ///     // the circle matches the thin lens
+    ///     let circle = Aperture::new(ApertureShape::Circle);
+    ///     for u in &samples {
+    ///         let (p, q) = (circle.sample(u), concentric_sample_disk(u));
+    ///         assert_eq!((p.x, p.y), (q.x, q.y));
+    ///     }
+    ///     // all samples lie within the hexagon (and cover it uniformly)
+    ///     let sides: u32 = 6;
+    ///     let hexagon = Aperture::new(ApertureShape::NGon(sides));
+    ///     assert!((hexagon.area - 1.5 * (3.0 as Float).sqrt()).abs() < 1e-5);
+    ///     let apothem: Float = (PI / sides as Float).cos();
+    ///     let mut n_inner: usize = 0;
+    ///     let mut max_radius: Float = 0.0;
+    ///     for u in &samples {
+    ///         let p = hexagon.sample(u);
+    ///         for k in 0..sides {
+    ///             // outward normal of the edge from corner k to k + 1
+    ///             let phi: Float = PI / 2.0 + 2.0 * PI * (k as Float + 0.5) / sides as Float;
+    ///             assert!(p.x * phi.cos() + p.y * phi.sin() <= apothem + 1e-5);
+    ///         }
+    ///         let r: Float = (p.x * p.x + p.y * p.y).sqrt();
+    ///         if r < 0.5 {
+    ///             n_inner += 1;
+    ///         }
+    ///         max_radius = max_radius.max(r);
+    ///     }
+    ///     let expected: Float = PI * 0.25 / hexagon.area;
+    ///     assert!((n_inner as Float / samples.len() as Float - expected).abs() < 0.01);
+    ///     assert!(max_radius > 0.95);
+    ///     // an image with only the upper right texel set
+    ///     let res = pbrt::core::geometry::Point2i { x: 2, y: 2 };
+    ///     let texels: Vec<Float> = vec![0.0, 0.0, 0.0, 1.0];
+    ///     let mipmap = MipMap::new(&res, &texels, false, 8.0, ImageWrap::Black);
+    ///     let image = Aperture::new(ApertureShape::Image(mipmap));
+    ///     assert_eq!(image.area, 1.0);
+    ///     for u in &samples {
+    ///         let p = image.sample(u);
+    ///         assert!(p.x >= 0.0 && p.x <= 1.0 && p.y >= 0.0 && p.y <= 1.0);
+    ///     }
+    /// }
+    /// ```
+    pub fn sample(&self, u: &Point2f) -> Point2f {
+        match self.shape {
+            ApertureShape::Circle => concentric_sample_disk(u),
+            ApertureShape::NGon(n) => {
+                // pick a triangle, reuse the rest of _u[0]_ within it
+                let k: u32 = std::cmp::min((u[0] * n as Float) as u32, n - 1);
+                let u0: Float = u[0] * n as Float - k as Float;
+                let b: Point2f = uniform_sample_triangle(&Point2f {
+                    x: u0.min(FLOAT_ONE_MINUS_EPSILON),
+                    y: u[1],
+                });
+                let phi0: Float = PI / 2.0 as Float + 2.0 as Float * PI * k as Float / n as Float;
+                let phi1: Float =
+                    PI / 2.0 as Float + 2.0 as Float * PI * (k + 1) as Float / n as Float;
+                // the third corner is the center
+                Point2f {
+                    x: b.x * phi0.cos() + b.y * phi1.cos(),
+                    y: b.x * phi0.sin() + b.y * phi1.sin(),
+                }
+            }
+            ApertureShape::Image(_) => {
+                let mut pdf: Float = 0.0 as Float;
+                let st: Point2f = self
+                    .distribution
+                    .as_ref()
+                    .unwrap()
+                    .sample_continuous(u, &mut pdf);
+                Point2f {
+                    x: 2.0 as Float * st.x - 1.0 as Float,
+                    y: 2.0 as Float * st.y - 1.0 as Float,
+                }
+            }
+        }
+    }
+}
+
+/// Reads an aperture image (as luminance, with the top row of the
+/// image at the top of the lens).
+fn read_aperture_map(filename: &str) -> MipMap<Float> {
+    let img_result: ImageResult<DynamicImage> = image::open(Path::new(filename));
+    if !img_result.is_ok() {
+        panic!("Error reading \"{}\"", filename);
+    }
+    let rgb = img_result.unwrap().to_rgb();
+    let res: Point2i = Point2i {
+        x: rgb.width() as i32,
+        y: rgb.height() as i32,
+    };
+    let mut texels: Vec<Float> = Vec::with_capacity((res.x * res.y) as usize);
+    for t in 0..res.y {
+        for s in 0..res.x {
+            let p = rgb.get_pixel(s as u32, (res.y - 1 - t) as u32);
+            let r = Float::from(p[0]) / 255.0;
+            let g = Float::from(p[1]) / 255.0;
+            let b = Float::from(p[2]) / 255.0;
+            texels.push(Spectrum::rgb(r, g, b).y());
+        }
+    }
+    MipMap::new(&res, &texels[..], false, 8.0 as Float, ImageWrap::Black)
+}
+
 pub struct PerspectiveCamera {
     // inherited from Camera (see camera.h)
     pub camera_to_world: AnimatedTransform,
@@ -34,10 +230,23 @@ pub struct PerspectiveCamera {
     // raster_to_screen: Transform,
     pub lens_radius: Float,
     pub focal_distance: Float,
+    /// Shape of the lens samples (and of out-of-focus highlights)
+    pub aperture: Aperture,
     // private data (see perspective.h)
     pub dx_camera: Vector3f,
     pub dy_camera: Vector3f,
     pub a: Float,
+    // camera realism options
+    /// Natural vignetting: scale the ray weight by $\cos^4 \theta$
+    pub vignetting: bool,
+    /// Ratio of light let through by the shutter compared to an ideal
+    /// (instantaneous) one, in [0.5, 1]. Values below 1 give the
+    /// shutter a trapezoidal profile with opening and closing ramps.
+    pub shutter_efficiency: Float,
+    /// Fraction of the shutter interval used to read out the
+    /// scanlines from top to bottom, in [0, 1]. Each scanline is
+    /// exposed for the rest of the interval; 0 is a global shutter.
+    pub rolling_shutter: Float,
 }
 
 impl PerspectiveCamera {
@@ -123,9 +332,13 @@ impl PerspectiveCamera {
             // raster_to_screen,
             lens_radius,
             focal_distance,
+            aperture: Aperture::new(ApertureShape::Circle),
             dx_camera,
             dy_camera,
             a,
+            vignetting: false,
+            shutter_efficiency: 1.0 as Float,
+            rolling_shutter: 0.0 as Float,
         }
     }
     pub fn create(
@@ -133,11 +346,17 @@ impl PerspectiveCamera {
         cam2world: AnimatedTransform,
         film: Arc<Film>,
         medium: Option<Arc<Medium>>,
+        search_directory: Option<&Box<PathBuf>>,
     ) -> Arc<Camera> {
-        let shutteropen: Float = params.find_one_float("shutteropen", 0.0);
-        let shutterclose: Float = params.find_one_float("shutterclose", 1.0);
-        // TODO: std::swap(shutterclose, shutteropen);
-        assert!(shutterclose >= shutteropen);
+        let mut shutteropen: Float = params.find_one_float("shutteropen", 0.0);
+        let mut shutterclose: Float = params.find_one_float("shutterclose", 1.0);
+        if shutterclose < shutteropen {
+            println!(
+                "WARNING: Shutter close time [{}] < shutter open [{}].  Swapping them.",
+                shutterclose, shutteropen
+            );
+            std::mem::swap(&mut shutterclose, &mut shutteropen);
+        }
         let lensradius: Float = params.find_one_float("lensradius", 0.0);
         let focaldistance: Float = params.find_one_float("focaldistance", 1e6);
         let frame: Float = params.find_one_float(
@@ -172,7 +391,7 @@ impl PerspectiveCamera {
         //     params.find_one_float(String::from("halffov"), -1.0);
         // TODO: if (halffov > 0.f)
         // TODO: let perspective_camera: Arc<Camera + Sync + Send> =
-        let camera = Arc::new(Camera::Perspective(PerspectiveCamera::new(
+        let mut camera = PerspectiveCamera::new(
             cam2world,
             screen,
             shutteropen,
@@ -182,8 +401,86 @@ impl PerspectiveCamera {
             fov,
             film,
             medium,
-        )));
-        camera
+        );
+        // camera realism options
+        camera.vignetting = params.find_one_bool("vignetting", false);
+        let efficiency: Float = params.find_one_float("shutterefficiency", 1.0);
+        if efficiency < 0.5 as Float || efficiency > 1.0 as Float {
+            println!(
+                "WARNING: \"shutterefficiency\" {} outside of [0.5, 1], clamping it.",
+                efficiency
+            );
+        }
+        camera.shutter_efficiency = clamp_t(efficiency, 0.5 as Float, 1.0 as Float);
+        let readout: Float = params.find_one_float("rollingshutter", 0.0);
+        if readout < 0.0 as Float || readout > 1.0 as Float {
+            println!(
+                "WARNING: \"rollingshutter\" {} outside of [0, 1], clamping it.",
+                readout
+            );
+        }
+        camera.rolling_shutter = clamp_t(readout, 0.0 as Float, 1.0 as Float);
+        // aperture shape
+        let mut aperture_map: String = params.find_one_filename("aperturemap", String::new());
+        if aperture_map != "" {
+            if let Some(ref search_directory) = search_directory {
+                let mut path_buf: PathBuf = PathBuf::from("/");
+                path_buf.push(search_directory.as_ref());
+                path_buf.push(aperture_map);
+                aperture_map = String::from(path_buf.to_str().unwrap());
+            }
+            camera.aperture = Aperture::new(ApertureShape::Image(read_aperture_map(&aperture_map)));
+        } else {
+            let aperture: String = params.find_one_string("aperture", String::from("circle"));
+            if aperture == "ngon" {
+                let mut sides: i32 = params.find_one_int("aperturesides", 6);
+                if sides < 3 {
+                    println!(
+                        "WARNING: \"aperturesides\" {} less than 3, using a triangle.",
+                        sides
+                    );
+                    sides = 3;
+                }
+                camera.aperture = Aperture::new(ApertureShape::NGon(sides as u32));
+            } else if aperture != "circle" {
+                println!(
+                    "WARNING: Aperture \"{}\" unknown, using \"circle\".",
+                    aperture
+                );
+            }
+        }
+        Arc::new(Camera::Perspective(camera))
+    }
+    /// Computes the time of a camera ray from the time sample _u_ and
+    /// the raster position _p_film_, taking the shutter profile (see
+    /// _shutter_efficiency_) and a rolling shutter into account.
+    fn sample_shutter_time(&self, u: Float, p_film: &Point2f) -> Float {
+        // warp _u_ to the trapezoidal shutter profile with ramps of
+        // width _r_ (relative to the exposure)
+        let r: Float = 1.0 as Float - self.shutter_efficiency;
+        let mut s: Float = u;
+        if r > 0.0 as Float {
+            // height of the trapezoid, area of one ramp
+            let h: Float = 1.0 as Float / (1.0 as Float - r);
+            let ramp: Float = 0.5 as Float * h * r;
+            if u < ramp {
+                s = (2.0 as Float * r * u / h).sqrt();
+            } else if u > 1.0 as Float - ramp {
+                s = 1.0 as Float - (2.0 as Float * r * (1.0 as Float - u) / h).sqrt();
+            } else {
+                s = u / h + 0.5 as Float * r;
+            }
+        }
+        // each scanline starts its exposure a little later
+        if self.rolling_shutter > 0.0 as Float {
+            let row: Float = clamp_t(
+                p_film.y / self.film.full_resolution.y as Float,
+                0.0 as Float,
+                1.0 as Float,
+            );
+            s = row * self.rolling_shutter + s * (1.0 as Float - self.rolling_shutter);
+        }
+        lerp(s, self.shutter_open, self.shutter_close)
     }
     // Camera
     pub fn generate_ray_differential(&self, sample: &CameraSample, ray: &mut Ray) -> Float {
@@ -222,14 +519,14 @@ impl PerspectiveCamera {
             o: Point3f::default(),
             d: dir,
             t_max: std::f32::INFINITY,
-            time: lerp(sample.time, self.shutter_open, self.shutter_close),
+            time: self.sample_shutter_time(sample.time, &sample.p_film),
             medium: None,
             differential: Some(diff),
         };
         // modify ray for depth of field
         if self.lens_radius > 0.0 as Float {
             // sample point on lens
-            let p_lens: Point2f = concentric_sample_disk(&sample.p_lens) * self.lens_radius;
+            let p_lens: Point2f = self.aperture.sample(&sample.p_lens) * self.lens_radius;
             // compute point on plane of focus
             let ft: Float = self.focal_distance / in_ray.d.z;
             let p_focus: Point3f = in_ray.position(ft);
@@ -246,7 +543,7 @@ impl PerspectiveCamera {
             // compute _PerspectiveCamera_ ray differentials accounting for lens
 
             // sample point on lens
-            let p_lens: Point2f = concentric_sample_disk(&sample.p_lens) * self.lens_radius;
+            let p_lens: Point2f = self.aperture.sample(&sample.p_lens) * self.lens_radius;
             let dx: Vector3f = Vector3f::from(p_camera + self.dx_camera).normalize();
             let ft: Float = self.focal_distance / dx.z;
             let p_focus: Point3f = Point3f::default() + (dx * ft);
@@ -275,7 +572,13 @@ impl PerspectiveCamera {
             in_ray.medium = None;
         }
         *ray = self.camera_to_world.transform_ray(&in_ray);
-        1.0
+        if self.vignetting {
+            // natural vignetting, $\cos^4 \theta$ falloff
+            let cos_2_theta: Float = dir.z * dir.z;
+            cos_2_theta * cos_2_theta
+        } else {
+            1.0
+        }
     }
     pub fn we(&self, ray: &Ray, p_raster2: Option<&mut Point2f>) -> Spectrum {
         // interpolate camera matrix and check if $\w{}$ is forward-facing
@@ -320,13 +623,17 @@ impl PerspectiveCamera {
         // compute lens area of perspective camera
         let lens_area: Float;
         if self.lens_radius != 0.0 as Float {
-            lens_area = PI * self.lens_radius * self.lens_radius;
+            lens_area = self.aperture.area * self.lens_radius * self.lens_radius;
         } else {
             lens_area = 1.0 as Float;
         }
         // return importance for point on image plane
         let cos_2_theta: Float = cos_theta * cos_theta;
-        Spectrum::new(1.0 as Float / (self.a * lens_area * cos_2_theta * cos_2_theta))
+        let mut we: Float = 1.0 as Float / (self.a * lens_area * cos_2_theta * cos_2_theta);
+        if self.vignetting {
+            we *= cos_2_theta * cos_2_theta;
+        }
+        Spectrum::new(we)
     }
     pub fn pdf_we(&self, ray: &Ray) -> (Float, Float) {
         let mut pdf_pos: Float = 0.0;
@@ -368,10 +675,9 @@ impl PerspectiveCamera {
             return (pdf_pos, pdf_dir);
         }
         // compute lens area of perspective camera
-        // Float lens_area = self.lens_radius != 0 ? (Pi * self.lens_radius * self.lens_radius) : 1;
         let lens_area: Float;
         if self.lens_radius != 0.0 as Float {
-            lens_area = PI * self.lens_radius * self.lens_radius;
+            lens_area = self.aperture.area * self.lens_radius * self.lens_radius;
         } else {
             lens_area = 1.0 as Float;
         }
@@ -388,8 +694,8 @@ impl PerspectiveCamera {
         p_raster: &mut Point2f,
         vis: &mut VisibilityTester,
     ) -> Spectrum {
-        // uniformly sample a lens interaction _lensIntr_
-        let p_lens: Point2f = concentric_sample_disk(u) * self.lens_radius;
+        // sample a lens interaction _lensIntr_
+        let p_lens: Point2f = self.aperture.sample(u) * self.lens_radius;
         let p_lens_world: Point3f = self.camera_to_world.transform_point(
             iref.time,
             &Point3f {
@@ -430,7 +736,7 @@ impl PerspectiveCamera {
         // compute lens area of perspective camera
         let mut lens_area: Float = 1.0 as Float;
         if self.lens_radius != 0.0 as Float {
-            lens_area = PI * self.lens_radius * self.lens_radius;
+            lens_area = self.aperture.area * self.lens_radius * self.lens_radius;
         }
         *pdf = (dist * dist) / (nrm_abs_dot_vec3(&lens_intr.n, wi) * lens_area);
         self.we(&lens_intr.spawn_ray(&-*wi), Some(p_raster))
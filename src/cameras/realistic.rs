@@ -98,18 +98,20 @@ impl RealisticCamera {
         let mut exit_pupil_bounds: Vec<Bounds2f> = Vec::new();
         exit_pupil_bounds.resize(n_samples, Bounds2f::default());
         let num_cores: usize = num_cpus::get();
-        let chunk_size: usize = n_samples / num_cores;
+        // at most one band per core (the last band may be shorter)
+        let chunk_size: usize = ((n_samples + num_cores - 1) / num_cores).max(1);
         {
             let bands: Vec<&mut [Bounds2f]> = exit_pupil_bounds.chunks_mut(chunk_size).collect();
+            let n_bands: usize = bands.len();
             let camera = &camera;
             let film = &film;
             crossbeam::scope(|scope| {
-                let (band_tx, band_rx) = crossbeam_channel::bounded(num_cores);
+                let (band_tx, band_rx) = crossbeam_channel::bounded(n_bands);
                 // spawn worker threads
                 for (b, band) in bands.into_iter().enumerate() {
                     let band_tx = band_tx.clone();
                     scope.spawn(move |_| {
-                        for (index, bound) in band.into_iter().enumerate() {
+                        for (index, bound) in band.iter_mut().enumerate() {
                             let i: usize = (b * chunk_size) + index;
                             let r0: Float =
                                 i as Float / n_samples as Float * film.diagonal / 2.0 as Float;
@@ -117,13 +119,15 @@ impl RealisticCamera {
                                 / 2.0 as Float;
                             *bound = camera.bound_exit_pupil(r0, r1);
                         }
+                        // send progress through the channel to main thread
+                        band_tx
+                            .send(b)
+                            .unwrap_or_else(|_| panic!("Failed to send progress"));
                     });
-                    // send progress through the channel to main thread
-                    band_tx.send(b).expect(&format!("Failed to send progress"));
                 }
                 // spawn thread to report progress
                 scope.spawn(move |_| {
-                    for _ in pbr::PbIter::new(0..num_cores) {
+                    for _ in pbr::PbIter::new(0..n_bands) {
                         band_rx.recv().unwrap();
                     }
                 });
@@ -158,23 +162,21 @@ impl RealisticCamera {
             }
         }
         if lens_file == "" {
-            println!("ERROR: No lens description file supplied!");
-        } else {
-            println!("lens_file = {:?}", lens_file);
+            panic!("ERROR: No lens description file supplied!");
         }
         let aperture_diameter: Float = params.find_one_float("aperturediameter", 1.0);
         let focus_distance: Float = params.find_one_float("focusdistance", 10.0);
         let simple_weighting: bool = params.find_one_bool("simpleweighting", true);
         let mut lens_data: Vec<Float> = Vec::new();
         if !read_float_file(&lens_file, &mut lens_data) {
-            println!(
+            panic!(
                 "ERROR: Error reading lens specification file {:?}.",
                 lens_file
             );
         }
-        if lens_data.len() % 4_usize != 0_usize {
-            println!("ERROR: Excess values in lens specification file {:?}; must be multiple-of-four values, read {}.",
-                     lens_file, lens_data.len());
+        if lens_data.len() % 4_usize != 0_usize || lens_data.is_empty() {
+            panic!("ERROR: Excess values in lens specification file {:?}; must be multiple-of-four values, read {}.",
+                   lens_file, lens_data.len());
         }
         // println!("lens_data = {:?}", lens_data);
         let camera = Arc::new(Camera::Realistic(RealisticCamera::new(
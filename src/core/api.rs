@@ -4,23 +4,29 @@
 
 // std
 use std;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::convert::TryInto;
-use std::path::PathBuf;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 // pbrt
 use crate::accelerators::bvh::{BVHAccel, SplitMethod};
+#[cfg(feature = "embree")]
+use crate::accelerators::embree::EmbreeAccel;
 use crate::accelerators::kdtreeaccel::KdTreeAccel;
 use crate::cameras::environment::EnvironmentCamera;
 use crate::cameras::orthographic::OrthographicCamera;
 use crate::cameras::perspective::PerspectiveCamera;
 use crate::cameras::realistic::RealisticCamera;
 use crate::core::camera::Camera;
-use crate::core::film::Film;
+use crate::core::film::{Film, FilmSnapshot};
 use crate::core::filter::Filter;
-use crate::core::geometry::{vec3_coordinate_system, vec3_cross_vec3};
+use crate::core::geometry::{bnd2_intersect_bnd2, vec3_coordinate_system, vec3_cross_vec3};
 use crate::core::geometry::{Bounds2i, Normal3f, Point2f, Point2i, Point3f, Vector3f};
-use crate::core::integrator::{Integrator, SamplerIntegrator};
+use crate::core::integrator::{
+    render_progressive, render_with_checkpoints, Integrator, RenderCheckpoint, SamplerIntegrator,
+};
 use crate::core::light::Light;
 use crate::core::material::Material;
 use crate::core::medium::get_medium_scattering_properties;
@@ -33,11 +39,13 @@ use crate::core::primitive::{GeometricPrimitive, Primitive, TransformedPrimitive
 use crate::core::reflection::FourierBSDFTable;
 use crate::core::sampler::Sampler;
 use crate::core::scene::Scene;
+use crate::core::scenecache::{SceneCache, Statement};
 use crate::core::shape::Shape;
-use crate::core::texture::{
-    CylindricalMapping2D, IdentityMapping3D, PlanarMapping2D, SphericalMapping2D, Texture,
-    TextureMapping2D, TextureMapping3D, UVMapping2D,
-};
+use crate::core::spectrum::SpectrumType;
+use crate::core::stats::{stat_name_id, Prof, ProfilePhase};
+#[cfg(feature = "tev")]
+use crate::core::tev::TevDisplay;
+use crate::core::texture::{IdentityMapping3D, Texture, TextureMapping2D, TextureMapping3D};
 use crate::core::transform::{AnimatedTransform, Matrix4x4, Transform};
 use crate::filters::boxfilter::BoxFilter;
 use crate::filters::gaussian::GaussianFilter;
@@ -49,13 +57,15 @@ use crate::integrators::bdpt::BDPTIntegrator;
 use crate::integrators::directlighting::{DirectLightingIntegrator, LightStrategy};
 use crate::integrators::mlt::MLTIntegrator;
 use crate::integrators::path::PathIntegrator;
+use crate::integrators::restir::ReSTIRDirectIntegrator;
 use crate::integrators::sppm::SPPMIntegrator;
 use crate::integrators::volpath::VolPathIntegrator;
 use crate::integrators::whitted::WhittedIntegrator;
 use crate::lights::diffuse::DiffuseAreaLight;
 use crate::lights::distant::DistantLight;
 use crate::lights::goniometric::GonioPhotometricLight;
-use crate::lights::infinite::InfiniteAreaLight;
+use crate::lights::ies::IESLight;
+use crate::lights::infinite::{ConstantInfiniteLight, InfiniteAreaLight};
 use crate::lights::point::PointLight;
 use crate::lights::projection::ProjectionLight;
 use crate::lights::spot::SpotLight;
@@ -63,6 +73,7 @@ use crate::materials::disney::DisneyMaterial;
 use crate::materials::fourier::FourierMaterial;
 use crate::materials::glass::GlassMaterial;
 use crate::materials::hair::HairMaterial;
+use crate::materials::kdsubsurface::KdSubsurfaceMaterial;
 use crate::materials::matte::MatteMaterial;
 use crate::materials::metal::MetalMaterial;
 use crate::materials::mirror::MirrorMaterial;
@@ -74,6 +85,8 @@ use crate::materials::translucent::TranslucentMaterial;
 use crate::materials::uber::UberMaterial;
 use crate::media::grid::GridDensityMedium;
 use crate::media::homogeneous::HomogeneousMedium;
+#[cfg(feature = "vdb")]
+use crate::media::nanovdb::NanoVdbGrid;
 use crate::samplers::halton::HaltonSampler;
 use crate::samplers::maxmin::MaxMinDistSampler;
 use crate::samplers::random::RandomSampler;
@@ -86,23 +99,27 @@ use crate::shapes::disk::Disk;
 use crate::shapes::loopsubdiv::loop_subdivide;
 use crate::shapes::nurbs::nurbs_evaluate_surface;
 use crate::shapes::nurbs::Homogeneous3;
-use crate::shapes::plymesh::create_ply_mesh;
+use crate::shapes::objmesh::load_obj;
+use crate::shapes::plymesh::{create_ply_mesh, inline_ply_mesh, ply_file_name};
 use crate::shapes::sphere::Sphere;
 use crate::shapes::triangle::{Triangle, TriangleMesh};
-use crate::textures::checkerboard::Checkerboard2DTexture;
+use crate::textures::checkerboard::{AAMethod, Checkerboard2DTexture, Checkerboard3DTexture};
 use crate::textures::constant::ConstantTexture;
 use crate::textures::dots::DotsTexture;
 use crate::textures::fbm::FBmTexture;
-use crate::textures::imagemap::ImageTexture;
-use crate::textures::imagemap::{convert_to_float, convert_to_spectrum};
+use crate::textures::imagemap::{channel_to_float, convert_to_float, convert_to_spectrum};
+use crate::textures::imagemap::{ImageEncoding, ImageTexture};
 use crate::textures::marble::MarbleTexture;
 use crate::textures::mix::MixTexture;
-use crate::textures::scale::ScaleTexture;
+use crate::textures::scale::{ScaleTexture, ScaleTextureSF};
 use crate::textures::windy::WindyTexture;
 use crate::textures::wrinkled::WrinkledTexture;
 
 // see api.cpp
 
+crate::stat_counter!("Scene/Object instances used", N_OBJECT_INSTANCES_USED);
+crate::stat_memory!("Memory/Animated transforms", ANIMATED_TRANSFORM_BYTES);
+
 pub struct BsdfState {
     pub loaded_bsdfs: HashMap<String, Arc<FourierBSDFTable>>,
 }
@@ -118,10 +135,12 @@ impl Default for BsdfState {
 pub struct ApiState {
     number_of_threads: u8,
     pub search_directory: Option<Box<PathBuf>>,
+    /// don't warn about unused parameters
+    pub quiet: bool,
     cur_transform: TransformSet,
     active_transform_bits: u8,
-    named_coordinate_systems: HashMap<&'static str, TransformSet>,
-    render_options: RenderOptions,
+    named_coordinate_systems: HashMap<String, TransformSet>,
+    pub render_options: RenderOptions,
     graphics_state: GraphicsState,
     pushed_graphics_states: Vec<GraphicsState>,
     pushed_transforms: Vec<TransformSet>,
@@ -134,6 +153,7 @@ impl Default for ApiState {
         ApiState {
             number_of_threads: 0_u8,
             search_directory: None,
+            quiet: false,
             cur_transform: TransformSet {
                 t: [Transform {
                     m: Matrix4x4 {
@@ -201,27 +221,171 @@ pub struct RenderOptions {
     pub integrator_params: ParamSet,
     pub camera_name: String, // "perspective";
     pub camera_params: ParamSet,
+    /// A camera made in code (see **SceneBuilder::set_camera()**),
+    /// used instead of _camera_name_ and _camera_params_
+    pub camera: Option<Arc<Camera>>,
     pub camera_to_world: TransformSet,
+    /// The named medium the camera is in (the outside medium when the
+    /// Camera directive was given)
+    pub camera_medium: String,
     pub named_media: HashMap<String, Arc<Medium>>,
     pub lights: Vec<Arc<Light>>,
     pub primitives: Vec<Arc<Primitive>>,
     pub instances: HashMap<String, Vec<Arc<Primitive>>>,
     pub current_instance: String,
     pub have_scattering_media: bool, // false
+    /// Seed for the random samplers (see Sampler::set_seed()), an
+    /// "integer seed" parameter of the Sampler directive overrides it
+    pub seed: u64, // 0
+    /// Render one sample per pixel per pass (see render_progressive())
+    pub progressive: bool, // false
+    /// Called with a snapshot of the film after each progressive pass
+    pub on_pass_complete: Option<Box<dyn Fn(&FilmSnapshot) + Send + Sync>>,
+    /// Save (and resume) the render state periodically
+    pub checkpoint: Option<RenderCheckpoint>,
+    /// Overrides the samples per pixel of the Sampler directive
+    pub pixel_samples: Option<i32>,
+    /// Overrides "xresolution" and "yresolution" of the Film directive
+    pub resolution: Option<(i32, i32)>,
+    /// Overrides "filename" of the Film directive
+    pub image_file: Option<String>,
+    /// A 16th of the samples at half the resolution (for previews)
+    pub quick_render: bool, // false
+    /// Host and port of a tev image viewer showing the image while
+    /// it renders (requires the "tev" feature)
+    pub tev: Option<(String, u16)>,
+    // shared instance (and animated shape) transforms, by hash
+    animated_transforms: HashMap<u64, Vec<Arc<AnimatedTransform>>>,
+    // shared shape transforms, by hash
+    transforms: HashMap<u64, Vec<Arc<Transform>>>,
+}
+
+fn hash_transform(transform: &Transform, hasher: &mut DefaultHasher) {
+    for row in &transform.m.m {
+        for v in row {
+            v.to_bits().hash(hasher);
+        }
+    }
 }
 
 impl RenderOptions {
-    pub fn make_integrator(&self) -> Option<Box<Integrator>> {
+    /// Returns the **AnimatedTransform** for a CTM pair (and the current
+    /// transform times), sharing one copy between all object instances
+    /// placed with identical transforms.
+    pub fn animated_transform(&mut self, transform_set: &TransformSet) -> Arc<AnimatedTransform> {
+        let animated_transform: AnimatedTransform = AnimatedTransform::new(
+            &transform_set.t[0],
+            self.transform_start_time,
+            &transform_set.t[1],
+            self.transform_end_time,
+        );
+        let mut hasher = DefaultHasher::new();
+        for t in &transform_set.t {
+            hash_transform(t, &mut hasher);
+        }
+        self.transform_start_time.to_bits().hash(&mut hasher);
+        self.transform_end_time.to_bits().hash(&mut hasher);
+        let bucket = self
+            .animated_transforms
+            .entry(hasher.finish())
+            .or_insert_with(Vec::new);
+        if let Some(shared) = bucket.iter().find(|at| ***at == animated_transform) {
+            return shared.clone();
+        }
+        crate::stat_add!(
+            ANIMATED_TRANSFORM_BYTES,
+            std::mem::size_of::<AnimatedTransform>()
+        );
+        let shared: Arc<AnimatedTransform> = Arc::new(animated_transform);
+        bucket.push(shared.clone());
+        shared
+    }
+    /// Returns a shared copy of _transform_ (like pbrt's
+    /// TransformCache), so shapes with identical transformations, e.g.
+    /// all segments of all curves in a hair model, reference a single
+    /// **Transform**. Shapes only keep such references, which keeps
+    /// every **Shape** small:
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::api::RenderOptions;
+    /// use pbrt::core::geometry::Vector3f;
+    /// use pbrt::core::shape::Shape;
+    /// use pbrt::core::transform::Transform;
+    ///
+    /// let mut render_options: RenderOptions = RenderOptions::default();
+    /// let up: Transform = Transform::translate(&Vector3f { x: 0.0, y: 1.0, z: 0.0 });
+    /// let a: Arc<Transform> = render_options.transform(&up);
+    /// let b: Arc<Transform> = render_options.transform(&up.clone());
+    /// assert!(Arc::ptr_eq(&a, &b));
+    /// let c: Arc<Transform> = render_options.transform(&Transform::default());
+    /// assert!(!Arc::ptr_eq(&a, &c));
+    /// assert!(std::mem::size_of::<Shape>() <= 64);
+    /// ```
+    pub fn transform(&mut self, transform: &Transform) -> Arc<Transform> {
+        let mut hasher = DefaultHasher::new();
+        hash_transform(transform, &mut hasher);
+        let bucket = self
+            .transforms
+            .entry(hasher.finish())
+            .or_insert_with(Vec::new);
+        if let Some(shared) = bucket.iter().find(|t| ***t == *transform) {
+            return shared.clone();
+        }
+        let shared: Arc<Transform> = Arc::new(*transform);
+        bucket.push(shared.clone());
+        shared
+    }
+    /// The film's sample bounds (the crop window expanded by the filter
+    /// radius), restricted to the integrator's optional "pixelbounds".
+    fn get_pixel_bounds(&self, camera: &Arc<Camera>) -> Bounds2i {
+        let mut pixel_bounds: Bounds2i = camera.get_film().get_sample_bounds();
+        let pb: Vec<i32> = self.integrator_params.find_int("pixelbounds");
+        let np: usize = pb.len();
+        if np > 0 as usize {
+            if np != 4 as usize {
+                panic!(
+                    "Expected four values for \"pixelbounds\" parameter. Got {}.",
+                    np
+                );
+            } else {
+                pixel_bounds = bnd2_intersect_bnd2(
+                    &pixel_bounds,
+                    &Bounds2i {
+                        p_min: Point2i { x: pb[0], y: pb[2] },
+                        p_max: Point2i { x: pb[1], y: pb[3] },
+                    },
+                );
+                if pixel_bounds.area() <= 0 {
+                    println!("ERROR: Degenerate \"pixelbounds\" specified.");
+                }
+            }
+        }
+        pixel_bounds
+    }
+    pub fn make_integrator(
+        &self,
+        search_directory: Option<&Box<PathBuf>>,
+    ) -> Option<Box<Integrator>> {
         let mut some_integrator: Option<Box<Integrator>> = None;
-        let some_camera: Option<Arc<Camera>> = self.make_camera();
+        let some_camera: Option<Arc<Camera>> = self.make_camera(search_directory);
         if let Some(camera) = some_camera {
-            let some_sampler: Option<Box<Sampler>> =
-                make_sampler(&self.sampler_name, &self.sampler_params, camera.get_film());
-            if let Some(sampler) = some_sampler {
+            let some_sampler: Option<Box<Sampler>> = make_sampler(
+                &self.sampler_name,
+                &self.sampler_params_with_overrides(),
+                camera.get_film(),
+            );
+            if let Some(mut sampler) = some_sampler {
+                let seed: i32 = self.sampler_params.find_one_int("seed", -1);
+                if seed >= 0 {
+                    sampler.set_seed(seed as u64);
+                } else {
+                    sampler.set_seed(self.seed);
+                }
                 if self.integrator_name == "whitted" {
                     // CreateWhittedIntegrator
                     let max_depth: i32 = self.integrator_params.find_one_int("maxdepth", 5);
-                    let pixel_bounds: Bounds2i = camera.get_film().get_sample_bounds();
+                    let pixel_bounds: Bounds2i = self.get_pixel_bounds(&camera);
                     let integrator = Box::new(Integrator::Sampler(SamplerIntegrator::Whitted(
                         WhittedIntegrator::new(max_depth as u32, camera, sampler, pixel_bounds),
                     )));
@@ -240,13 +404,7 @@ impl RenderOptions {
                     } else {
                         panic!("Strategy \"{}\" for direct lighting unknown.", st);
                     }
-                    // TODO: const int *pb = params.FindInt("pixelbounds", &np);
-                    let xres: i32 = self.film_params.find_one_int("xresolution", 1280);
-                    let yres: i32 = self.film_params.find_one_int("yresolution", 720);
-                    let pixel_bounds: Bounds2i = Bounds2i {
-                        p_min: Point2i { x: 0, y: 0 },
-                        p_max: Point2i { x: xres, y: yres },
-                    };
+                    let pixel_bounds: Bounds2i = self.get_pixel_bounds(&camera);
                     let integrator = Box::new(Integrator::Sampler(
                         SamplerIntegrator::DirectLighting(DirectLightingIntegrator::new(
                             strategy,
@@ -257,26 +415,35 @@ impl RenderOptions {
                         )),
                     ));
                     some_integrator = Some(integrator);
+                } else if self.integrator_name == "restir" {
+                    // CreateReSTIRDirectIntegrator
+                    let max_depth: i32 = self.integrator_params.find_one_int("maxdepth", 5);
+                    let n_candidates: i32 = self.integrator_params.find_one_int("candidates", 32);
+                    let n_spatial: i32 = self.integrator_params.find_one_int("spatialsamples", 5);
+                    let spatial_radius: Float = self
+                        .integrator_params
+                        .find_one_float("spatialradius", 10.0 as Float);
+                    let temporal: bool = self.integrator_params.find_one_bool("temporal", true);
+                    let light_strategy: String = self
+                        .integrator_params
+                        .find_one_string("lightsamplestrategy", String::from("power"));
+                    let pixel_bounds: Bounds2i = self.get_pixel_bounds(&camera);
+                    let integrator = Box::new(Integrator::ReSTIR(ReSTIRDirectIntegrator::new(
+                        camera,
+                        sampler,
+                        pixel_bounds,
+                        max_depth as u32,
+                        std::cmp::max(n_candidates, 1) as u32,
+                        std::cmp::max(n_spatial, 0) as u32,
+                        spatial_radius,
+                        temporal,
+                        light_strategy,
+                    )));
+                    some_integrator = Some(integrator);
                 } else if self.integrator_name == "path" {
                     // CreatePathIntegrator
                     let max_depth: i32 = self.integrator_params.find_one_int("maxdepth", 5);
-                    let pb: Vec<i32> = self.integrator_params.find_int("pixelbounds");
-                    let np: usize = pb.len();
-                    let pixel_bounds: Bounds2i = camera.get_film().get_sample_bounds();
-                    if np > 0 as usize {
-                        if np != 4 as usize {
-                            panic!(
-                                "Expected four values for \"pixelbounds\" parameter. Got {}.",
-                                np
-                            );
-                        } else {
-                            println!("TODO: pixelBounds = Intersect(...)");
-                            // pixelBounds = Intersect(pixelBounds,
-                            //                         Bounds2i{{pb[0], pb[2]}, {pb[1], pb[3]}});
-                            // if (pixelBounds.Area() == 0)
-                            //     Error("Degenerate \"pixelbounds\" specified.");
-                        }
-                    }
+                    let pixel_bounds: Bounds2i = self.get_pixel_bounds(&camera);
                     let rr_threshold: Float = self
                         .integrator_params
                         .find_one_float("rrthreshold", 1.0 as Float);
@@ -297,23 +464,7 @@ impl RenderOptions {
                 } else if self.integrator_name == "volpath" {
                     // CreateVolPathIntegrator
                     let max_depth: i32 = self.integrator_params.find_one_int("maxdepth", 5);
-                    let pb: Vec<i32> = self.integrator_params.find_int("pixelbounds");
-                    let np: usize = pb.len();
-                    let pixel_bounds: Bounds2i = camera.get_film().get_sample_bounds();
-                    if np > 0 as usize {
-                        if np != 4 as usize {
-                            panic!(
-                                "Expected four values for \"pixelbounds\" parameter. Got {}.",
-                                np
-                            );
-                        } else {
-                            println!("TODO: pixelBounds = Intersect(...)");
-                            // pixelBounds = Intersect(pixelBounds,
-                            //                         Bounds2i{{pb[0], pb[2]}, {pb[1], pb[3]}});
-                            // if (pixelBounds.Area() == 0)
-                            //     Error("Degenerate \"pixelbounds\" specified.");
-                        }
-                    }
+                    let pixel_bounds: Bounds2i = self.get_pixel_bounds(&camera);
                     let rr_threshold: Float = self
                         .integrator_params
                         .find_one_float("rrthreshold", 1.0 as Float);
@@ -345,7 +496,7 @@ impl RenderOptions {
                         println!(" limiting maxdepth to 5");
                         max_depth = 5;
                     }
-                    let pixel_bounds: Bounds2i = camera.get_film().get_sample_bounds();
+                    let pixel_bounds: Bounds2i = self.get_pixel_bounds(&camera);
                     let light_strategy: String = self
                         .integrator_params
                         .find_one_string("lightsamplestrategy", String::from("power"));
@@ -385,23 +536,7 @@ impl RenderOptions {
                     some_integrator = Some(integrator);
                 } else if self.integrator_name == "ambientocclusion" {
                     // CreateAOIntegrator
-                    let pb: Vec<i32> = self.integrator_params.find_int("pixelbounds");
-                    let np: usize = pb.len();
-                    let pixel_bounds: Bounds2i = camera.get_film().get_sample_bounds();
-                    if np > 0 as usize {
-                        if np != 4 as usize {
-                            panic!(
-                                "Expected four values for \"pixelbounds\" parameter. Got {}.",
-                                np
-                            );
-                        } else {
-                            println!("TODO: pixelBounds = Intersect(...)");
-                            // pixelBounds = Intersect(pixelBounds,
-                            //                         Bounds2i{{pb[0], pb[2]}, {pb[1], pb[3]}});
-                            // if (pixelBounds.Area() == 0)
-                            //     Error("Degenerate \"pixelbounds\" specified.");
-                        }
-                    }
+                    let pixel_bounds: Bounds2i = self.get_pixel_bounds(&camera);
                     let cos_sample: bool = self.integrator_params.find_one_bool("cossample", true);
                     let n_samples: i32 = self.integrator_params.find_one_int("nsamples", 64 as i32);
                     let integrator = Box::new(Integrator::Sampler(SamplerIntegrator::AO(
@@ -425,7 +560,9 @@ impl RenderOptions {
                     let radius: Float = self
                         .integrator_params
                         .find_one_float("radius", 1.0 as Float);
-                    // TODO: if (PbrtOptions.quickRender) nIterations = std::max(1, nIterations / 16);
+                    if self.quick_render {
+                        n_iterations = std::cmp::max(1, n_iterations / 16);
+                    }
                     let integrator = Box::new(Integrator::SPPM(SPPMIntegrator::new(
                         camera.clone(),
                         n_iterations,
@@ -453,17 +590,34 @@ impl RenderOptions {
             &self.accelerator_params,
         );
         if let Some(accelerator) = some_accelerator {
-            return Scene::new(accelerator, self.lights.clone());
+            let mut scene: Scene = Scene::new(accelerator, self.lights.clone());
+            // limit intersections per path (for debugging)?
+            let max_intersections: i32 = self.integrator_params.find_one_int("maxintersections", 0);
+            scene.set_max_intersections(max_intersections.max(0) as u64);
+            // bend shading normals into the visible hemisphere?
+            scene.set_clamp_shading_normals(
+                self.integrator_params
+                    .find_one_bool("clampshadingnormals", false),
+            );
+            // reject light-leaking directions in Bsdf::f() and Bsdf::sample_f()?
+            scene.set_check_geometric_normal(
+                self.integrator_params
+                    .find_one_bool("checkgeometricnormal", true),
+            );
+            return scene;
         } else {
             panic!("Unable to create accelerator.");
         }
     }
-    pub fn make_camera(&self) -> Option<Arc<Camera>> {
+    pub fn make_camera(&self, search_directory: Option<&Box<PathBuf>>) -> Option<Arc<Camera>> {
+        if let Some(ref camera) = self.camera {
+            return Some(camera.clone());
+        }
         let mut some_camera: Option<Arc<Camera>> = None;
         let some_filter = make_filter(&self.filter_name, &self.filter_params);
         if let Some(filter) = some_filter {
             let some_film: Option<Arc<Film>> =
-                make_film(&self.film_name, &self.film_params, filter);
+                make_film(&self.film_name, &self.film_params_with_overrides(), filter);
             if let Some(film) = some_film {
                 let animated_cam_to_world: AnimatedTransform = AnimatedTransform::new(
                     &self.camera_to_world.t[0],
@@ -471,16 +625,92 @@ impl RenderOptions {
                     &self.camera_to_world.t[1],
                     self.transform_end_time,
                 );
+                let medium: Option<Arc<Medium>> = if self.camera_medium.is_empty() {
+                    None
+                } else {
+                    match self.named_media.get(&self.camera_medium) {
+                        Some(medium) => Some(medium.clone()),
+                        None => panic!("ERROR: Named medium \"{}\" undefined.", self.camera_medium),
+                    }
+                };
                 some_camera = make_camera(
                     &self.camera_name,
                     &self.camera_params,
                     animated_cam_to_world,
                     film,
+                    medium,
+                    search_directory,
                 );
             }
         }
         some_camera
     }
+    /// The Film parameters with _resolution_, _image_file_, and
+    /// _quick_render_ applied.
+    fn film_params_with_overrides(&self) -> ParamSet {
+        let mut params: ParamSet = ParamSet::default();
+        params.copy_from(&self.film_params);
+        let (mut xres, mut yres): (i32, i32) = match self.resolution {
+            Some(resolution) => resolution,
+            None => (
+                params.find_one_int("xresolution", 1280),
+                params.find_one_int("yresolution", 720),
+            ),
+        };
+        if self.quick_render {
+            xres = std::cmp::max(1, xres / 2);
+            yres = std::cmp::max(1, yres / 2);
+        }
+        params.erase_int("xresolution");
+        params.erase_int("yresolution");
+        params.add_int(String::from("xresolution"), xres);
+        params.add_int(String::from("yresolution"), yres);
+        if let Some(ref image_file) = self.image_file {
+            params.erase_string("filename");
+            params.add_string(String::from("filename"), image_file.clone());
+        }
+        params
+    }
+    /// The Sampler parameters with _pixel_samples_ and _quick_render_
+    /// applied.
+    fn sampler_params_with_overrides(&self) -> ParamSet {
+        let mut params: ParamSet = ParamSet::default();
+        params.copy_from(&self.sampler_params);
+        if self.sampler_name == "stratified" {
+            let (mut xsamp, mut ysamp): (i32, i32) = (
+                params.find_one_int("xsamples", 4),
+                params.find_one_int("ysamples", 4),
+            );
+            if let Some(spp) = self.pixel_samples {
+                // as square as possible
+                xsamp = std::cmp::max(1, (spp as Float).sqrt() as i32);
+                while spp % xsamp != 0 {
+                    xsamp -= 1;
+                }
+                ysamp = spp / xsamp;
+            }
+            if self.quick_render {
+                xsamp = std::cmp::max(1, xsamp / 4);
+                ysamp = std::cmp::max(1, ysamp / 4);
+            }
+            params.erase_int("xsamples");
+            params.erase_int("ysamples");
+            params.add_int(String::from("xsamples"), xsamp);
+            params.add_int(String::from("ysamples"), ysamp);
+        } else {
+            let default_spp: i32 = if self.sampler_name == "random" { 4 } else { 16 };
+            let mut spp: i32 = match self.pixel_samples {
+                Some(spp) => spp,
+                None => params.find_one_int("pixelsamples", default_spp),
+            };
+            if self.quick_render {
+                spp = std::cmp::max(1, spp / 16);
+            }
+            params.erase_int("pixelsamples");
+            params.add_int(String::from("pixelsamples"), spp);
+        }
+        params
+    }
 }
 
 impl Default for RenderOptions {
@@ -500,6 +730,7 @@ impl Default for RenderOptions {
             integrator_params: ParamSet::default(),
             camera_name: String::from("perspective"),
             camera_params: ParamSet::default(),
+            camera: None,
             camera_to_world: TransformSet {
                 t: [Transform {
                     m: Matrix4x4 {
@@ -520,12 +751,24 @@ impl Default for RenderOptions {
                     },
                 }; 2],
             },
+            camera_medium: String::from(""),
             named_media: HashMap::new(),
             lights: Vec::new(),
             primitives: Vec::new(),
             instances: HashMap::new(),
             current_instance: String::from(""),
             have_scattering_media: false,
+            seed: 0_u64,
+            progressive: false,
+            on_pass_complete: None,
+            checkpoint: None,
+            pixel_samples: None,
+            resolution: None,
+            image_file: None,
+            quick_render: false,
+            tev: None,
+            animated_transforms: HashMap::new(),
+            transforms: HashMap::new(),
         }
     }
 }
@@ -624,21 +867,22 @@ fn create_material(api_state: &ApiState, bsdf_state: &mut BsdfState) -> Option<A
         }
     } else {
         // MakeMaterial
-        if api_state.graphics_state.material == "" || api_state.graphics_state.material == "none" {
-            return None;
-        } else if api_state.graphics_state.material == "matte" {
-            return Some(MatteMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "plastic" {
-            return Some(PlasticMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "translucent" {
-            return Some(TranslucentMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "glass" {
-            return Some(GlassMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "mirror" {
-            return Some(MirrorMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "hair" {
-            return Some(HairMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "mix" {
+        let material_type: &str = api_state.graphics_state.material.as_str();
+        let material: Option<Arc<Material>> = if material_type == "" || material_type == "none" {
+            None
+        } else if material_type == "matte" {
+            Some(MatteMaterial::create(&mut mp))
+        } else if material_type == "plastic" {
+            Some(PlasticMaterial::create(&mut mp))
+        } else if material_type == "translucent" {
+            Some(TranslucentMaterial::create(&mut mp))
+        } else if material_type == "glass" {
+            Some(GlassMaterial::create(&mut mp))
+        } else if material_type == "mirror" {
+            Some(MirrorMaterial::create(&mut mp))
+        } else if material_type == "hair" {
+            Some(HairMaterial::create(&mut mp))
+        } else if material_type == "mix" {
             let m1: String = mp.find_string("namedmaterial1", String::from(""));
             let m2: String = mp.find_string("namedmaterial2", String::from(""));
             let mat1 = match api_state.graphics_state.named_materials.get(&m1) {
@@ -655,37 +899,40 @@ fn create_material(api_state: &ApiState, bsdf_state: &mut BsdfState) -> Option<A
             };
             let scale: Arc<dyn Texture<Spectrum> + Send + Sync> =
                 mp.get_spectrum_texture("amount", Spectrum::new(0.5));
-            if let Some(m1) = mat1 {
-                if let Some(m2) = mat2 {
-                    let mix = Arc::new(Material::Mix(MixMaterial::new(
-                        m1.clone(),
-                        m2.clone(),
-                        scale,
-                    )));
-                    return Some(mix);
-                }
+            if let (Some(m1), Some(m2)) = (mat1, mat2) {
+                Some(Arc::new(Material::Mix(MixMaterial::new(
+                    m1.clone(),
+                    m2.clone(),
+                    scale,
+                ))))
+            } else {
+                None
             }
-            return None;
-        } else if api_state.graphics_state.material == "metal" {
-            return Some(MetalMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "substrate" {
-            return Some(SubstrateMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "uber" {
-            return Some(UberMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "subsurface" {
-            return Some(SubsurfaceMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "kdsubsurface" {
-            println!("TODO: CreateKdsubsurfaceMaterial");
-        } else if api_state.graphics_state.material == "fourier" {
-            return Some(FourierMaterial::create(&mut mp, bsdf_state));
-        } else if api_state.graphics_state.material == "disney" {
-            return Some(DisneyMaterial::create(&mut mp));
+        } else if material_type == "metal" {
+            Some(MetalMaterial::create(&mut mp))
+        } else if material_type == "substrate" {
+            Some(SubstrateMaterial::create(&mut mp))
+        } else if material_type == "uber" {
+            Some(UberMaterial::create(&mut mp))
+        } else if material_type == "subsurface" {
+            Some(SubsurfaceMaterial::create(&mut mp))
+        } else if material_type == "kdsubsurface" {
+            Some(KdSubsurfaceMaterial::create(&mut mp))
+        } else if material_type == "fourier" {
+            Some(FourierMaterial::create(&mut mp, bsdf_state))
+        } else if material_type == "disney" {
+            Some(DisneyMaterial::create(&mut mp))
         } else {
-            panic!(
-                "Material \"{}\" unknown.",
-                api_state.graphics_state.material
-            );
-        }
+            panic!("Material \"{}\" unknown.", material_type);
+        };
+        report_unused(
+            api_state,
+            &format!("Material \"{}\"", material_type),
+            mp.report_unused(),
+        );
+        // materials get created for each shape, warn only once
+        api_state.graphics_state.material_params.mark_looked_up();
+        return material;
     }
     let kd = Arc::new(ConstantTexture::new(Spectrum::new(0.5)));
     let sigma = Arc::new(ConstantTexture::new(0.0 as Float));
@@ -729,15 +976,35 @@ fn create_medium_interface(api_state: &ApiState) -> MediumInterface {
     m
 }
 
+/// Lights may specify a color _temperature_ (in Kelvin): their
+/// emission gets tinted like a blackbody at that temperature, without
+/// changing its luminance, so _I_ (or _L_) and _scale_ still control
+/// the brightness. The tint is relative to white light, so a white _I_
+/// (or _L_) gets the spectrum of the blackbody.
+pub fn find_light_tint(params: &ParamSet) -> Spectrum {
+    let temperature: Float = params.find_one_float("temperature", 0.0 as Float);
+    if temperature > 0.0 as Float {
+        let blackbody: Spectrum = Spectrum::blackbody(temperature, 1.0 as Float);
+        // the spectrum of RGB white light isn't constant for a
+        // SampledSpectrum
+        let white: Spectrum = Spectrum::from_rgb_type(&[1.0 as Float; 3], SpectrumType::Illuminant);
+        blackbody / white * (white.y() / blackbody.y())
+    } else {
+        Spectrum::new(1.0 as Float)
+    }
+}
+
 fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
     // MakeLight (api.cpp:591)
     if api_state.param_set.name == "point" {
-        let i: Spectrum = api_state
-            .param_set
-            .find_one_spectrum("I", Spectrum::new(1.0 as Float));
+        let i: Spectrum = api_state.param_set.find_one_illuminant(
+            "I",
+            Spectrum::from_rgb_type(&[1.0 as Float; 3], SpectrumType::Illuminant),
+        );
         let sc: Spectrum = api_state
             .param_set
-            .find_one_spectrum("scale", Spectrum::new(1.0 as Float));
+            .find_one_spectrum("scale", Spectrum::new(1.0 as Float))
+            * find_light_tint(&api_state.param_set);
         let p: Point3f = api_state
             .param_set
             .find_one_point3f("from", Point3f::default());
@@ -754,12 +1021,14 @@ fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
         api_state.render_options.lights.push(point_light);
     } else if api_state.param_set.name == "spot" {
         // CreateSpotLight
-        let i: Spectrum = api_state
-            .param_set
-            .find_one_spectrum("I", Spectrum::new(1.0 as Float));
+        let i: Spectrum = api_state.param_set.find_one_illuminant(
+            "I",
+            Spectrum::from_rgb_type(&[1.0 as Float; 3], SpectrumType::Illuminant),
+        );
         let sc: Spectrum = api_state
             .param_set
-            .find_one_spectrum("scale", Spectrum::new(1.0 as Float));
+            .find_one_spectrum("scale", Spectrum::new(1.0 as Float))
+            * find_light_tint(&api_state.param_set);
         let coneangle: Float = api_state
             .param_set
             .find_one_float("coneangle", 30.0 as Float);
@@ -808,12 +1077,14 @@ fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
         api_state.render_options.lights.push(spot_light);
     } else if api_state.param_set.name == "goniometric" {
         // CreateGoniometricLight
-        let i: Spectrum = api_state
-            .param_set
-            .find_one_spectrum("I", Spectrum::new(1.0 as Float));
+        let i: Spectrum = api_state.param_set.find_one_illuminant(
+            "I",
+            Spectrum::from_rgb_type(&[1.0 as Float; 3], SpectrumType::Illuminant),
+        );
         let sc: Spectrum = api_state
             .param_set
-            .find_one_spectrum("scale", Spectrum::new(1.0 as Float));
+            .find_one_spectrum("scale", Spectrum::new(1.0 as Float))
+            * find_light_tint(&api_state.param_set);
         let texname: String = api_state
             .param_set
             .find_one_filename("mapname", String::from(""));
@@ -824,14 +1095,33 @@ fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
             texname,
         )));
         api_state.render_options.lights.push(projection_light);
+    } else if api_state.param_set.name == "ies" {
+        // CreateIESLight
+        let search_directory: Option<&Path> = match api_state.search_directory {
+            Some(ref search_directory) => Some(search_directory.as_path()),
+            None => None,
+        };
+        if let Some(ies_light) = IESLight::create(
+            &api_state.param_set,
+            &api_state.cur_transform.t[0],
+            medium_interface,
+            search_directory,
+        ) {
+            api_state
+                .render_options
+                .lights
+                .push(Arc::new(Light::IES(ies_light)));
+        }
     } else if api_state.param_set.name == "projection" {
         // CreateProjectionLight
-        let i: Spectrum = api_state
-            .param_set
-            .find_one_spectrum("I", Spectrum::new(1.0 as Float));
+        let i: Spectrum = api_state.param_set.find_one_illuminant(
+            "I",
+            Spectrum::from_rgb_type(&[1.0 as Float; 3], SpectrumType::Illuminant),
+        );
         let sc: Spectrum = api_state
             .param_set
-            .find_one_spectrum("scale", Spectrum::new(1.0 as Float));
+            .find_one_spectrum("scale", Spectrum::new(1.0 as Float))
+            * find_light_tint(&api_state.param_set);
         let fov: Float = api_state.param_set.find_one_float("fov", 45.0 as Float);
         let texname: String = api_state
             .param_set
@@ -846,12 +1136,14 @@ fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
         api_state.render_options.lights.push(projection_light);
     } else if api_state.param_set.name == "distant" {
         // CreateDistantLight
-        let l: Spectrum = api_state
-            .param_set
-            .find_one_spectrum("L", Spectrum::new(1.0 as Float));
+        let l: Spectrum = api_state.param_set.find_one_illuminant(
+            "L",
+            Spectrum::from_rgb_type(&[1.0 as Float; 3], SpectrumType::Illuminant),
+        );
         let sc: Spectrum = api_state
             .param_set
-            .find_one_spectrum("scale", Spectrum::new(1.0 as Float));
+            .find_one_spectrum("scale", Spectrum::new(1.0 as Float))
+            * find_light_tint(&api_state.param_set);
         let from: Point3f = api_state.param_set.find_one_point3f(
             "from",
             Point3f {
@@ -877,12 +1169,14 @@ fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
         )));
         api_state.render_options.lights.push(distant_light);
     } else if api_state.param_set.name == "infinite" || api_state.param_set.name == "exinfinite" {
-        let l: Spectrum = api_state
-            .param_set
-            .find_one_spectrum("L", Spectrum::new(1.0 as Float));
+        let l: Spectrum = api_state.param_set.find_one_illuminant(
+            "L",
+            Spectrum::from_rgb_type(&[1.0 as Float; 3], SpectrumType::Illuminant),
+        );
         let sc: Spectrum = api_state
             .param_set
-            .find_one_spectrum("scale", Spectrum::new(1.0 as Float));
+            .find_one_spectrum("scale", Spectrum::new(1.0 as Float))
+            * find_light_tint(&api_state.param_set);
         let mut texmap: String = api_state
             .param_set
             .find_one_filename("mapname", String::from(""));
@@ -895,16 +1189,31 @@ fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
                 texmap = String::from(path_buf.to_str().unwrap());
             }
         }
-        let n_samples: i32 = api_state.param_set.find_one_int("nsamples", 1 as i32);
-        // TODO: if (PbrtOptions.quickRender) nSamples = std::max(1, nSamples / 4);
+        let mut n_samples: i32 = api_state.param_set.find_one_int("nsamples", 1 as i32);
+        if api_state.render_options.quick_render {
+            n_samples = std::cmp::max(1, n_samples / 4);
+        }
 
         // return std::make_shared<InfiniteAreaLight>(light2world, L * sc, nSamples, texmap);
-        let infinte_light = Arc::new(Light::InfiniteArea(InfiniteAreaLight::new(
-            &api_state.cur_transform.t[0],
-            &(l * sc),
-            n_samples,
-            texmap,
-        )));
+        let infinte_light = if texmap == "" {
+            // no environment map, same radiance from all directions
+            Arc::new(Light::ConstantInfinite(ConstantInfiniteLight::new(
+                &(l * sc),
+                n_samples,
+            )))
+        } else {
+            // the texels are illuminants already, _L_ only scales them
+            // (relative to white light, which isn't a constant
+            // spectrum for a SampledSpectrum)
+            let white: Spectrum =
+                Spectrum::from_rgb_type(&[1.0 as Float; 3], SpectrumType::Illuminant);
+            Arc::new(Light::InfiniteArea(InfiniteAreaLight::new(
+                &api_state.cur_transform.t[0],
+                &(l / white * sc),
+                n_samples,
+                texmap,
+            )))
+        };
         api_state.render_options.lights.push(infinte_light);
     } else {
         panic!("MakeLight: unknown name {}", api_state.param_set.name);
@@ -986,6 +1295,8 @@ fn make_medium(api_state: &mut ApiState) {
                 ))));
             }
         }
+    } else if medium_type == "nanovdb" {
+        some_medium = make_nanovdb_medium(api_state, &sig_a, &sig_s, g);
     } else {
         panic!("MakeMedium: unknown name {}", medium_type);
     }
@@ -997,6 +1308,58 @@ fn make_medium(api_state: &mut ApiState) {
     }
 }
 
+/// A **GridDensityMedium** from the grid _gridname_ (default
+/// "density") of the NanoVDB file _filename_.
+#[cfg(feature = "vdb")]
+fn make_nanovdb_medium(
+    api_state: &ApiState,
+    sig_a: &Spectrum,
+    sig_s: &Spectrum,
+    g: Float,
+) -> Option<Arc<Medium>> {
+    let filename: String = api_state
+        .param_set
+        .find_one_string("filename", String::new());
+    let grid_name: String = api_state
+        .param_set
+        .find_one_string("gridname", String::from("density"));
+    let mut path_buf: PathBuf = PathBuf::from(&filename);
+    if path_buf.is_relative() {
+        if let Some(ref search_directory) = api_state.search_directory {
+            path_buf = search_directory.join(&filename);
+        }
+    }
+    match NanoVdbGrid::read(&path_buf) {
+        Ok(grids) => match grids.iter().find(|grid| grid.name == grid_name) {
+            Some(grid) => Some(Arc::new(Medium::GridDensity(grid.to_medium(
+                sig_a,
+                sig_s,
+                g,
+                &api_state.cur_transform.t[0],
+            )))),
+            None => {
+                println!("ERROR: No grid {:?} in {:?}", grid_name, path_buf);
+                None
+            }
+        },
+        Err(e) => {
+            println!("ERROR: Unable to read {:?}: {}", path_buf, e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "vdb"))]
+fn make_nanovdb_medium(
+    _api_state: &ApiState,
+    _sig_a: &Spectrum,
+    _sig_s: &Spectrum,
+    _g: Float,
+) -> Option<Arc<Medium>> {
+    println!("ERROR: rs_pbrt was built without the vdb feature, can't read NanoVDB files");
+    None
+}
+
 fn make_texture(api_state: &mut ApiState) {
     // pbrtTexture (api.cpp:1049)
     let mut geom_params: ParamSet = ParamSet::default();
@@ -1047,53 +1410,8 @@ fn make_texture(api_state: &mut ApiState) {
             println!("TODO: CreateBilerpFloatTexture");
         } else if api_state.param_set.tex_name == "imagemap" {
             // CreateImageFloatTexture
-            let map: Option<Box<TextureMapping2D>>;
-            let mapping: String = tp.find_string("mapping", String::from("uv"));
-            if mapping == "uv" {
-                let su: Float = tp.find_float("uscale", 1.0);
-                let sv: Float = tp.find_float("vscale", 1.0);
-                let du: Float = tp.find_float("udelta", 0.0);
-                let dv: Float = tp.find_float("vdelta", 0.0);
-                map = Some(Box::new(TextureMapping2D::UV(UVMapping2D {
-                    su,
-                    sv,
-                    du,
-                    dv,
-                })));
-            } else if mapping == "spherical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Spherical(
-                    SphericalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "cylindrical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Cylindrical(
-                    CylindricalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "planar" {
-                map = Some(Box::new(TextureMapping2D::Planar(PlanarMapping2D {
-                    vs: tp.find_vector3f(
-                        "v1",
-                        Vector3f {
-                            x: 1.0,
-                            y: 0.0,
-                            z: 0.0,
-                        },
-                    ),
-                    vt: tp.find_vector3f(
-                        "v2",
-                        Vector3f {
-                            x: 0.0,
-                            y: 1.0,
-                            z: 0.0,
-                        },
-                    ),
-                    ds: tp.find_float("udelta", 0.0),
-                    dt: tp.find_float("vdelta", 0.0),
-                })));
-            } else {
-                panic!("2D texture mapping \"{}\" unknown", mapping);
-            }
+            let tex_2_world: Transform = api_state.cur_transform.t[0];
+            let mapping: Box<TextureMapping2D> = TextureMapping2D::create(&tex_2_world, &mut tp);
             // initialize _ImageTexture_ parameters
             let max_aniso: Float = tp.find_float("maxanisotropy", 8.0);
             let do_trilinear: bool = tp.find_bool("trilinear", false);
@@ -1113,89 +1431,89 @@ fn make_texture(api_state: &mut ApiState) {
                 path_buf.push(filename);
                 filename = String::from(path_buf.to_str().unwrap());
             }
-            // TODO: default depends on:
-            // HasExtension(filename,
-            // ".tga") ||
-            // HasExtension(filename,
-            // ".png"));
-            let gamma: bool = tp.find_bool("gamma", true);
-
-            if let Some(mapping) = map {
-                let ft = Arc::new(ImageTexture::new(
-                    mapping,
-                    filename,
-                    do_trilinear,
-                    max_aniso,
-                    wrap_mode,
-                    scale,
-                    gamma,
-                    convert_to_float,
-                ));
-                Arc::make_mut(&mut api_state.graphics_state.float_textures)
-                    .insert(api_state.param_set.name.clone(), ft);
-            }
+            // float textures usually hold data (roughness, bump maps, ...)
+            let encoding: ImageEncoding = ImageEncoding::create(&mut tp, ImageEncoding::Linear);
+            let channel: String = tp.find_string("channel", String::from("y"));
+            let convert: fn(&Spectrum) -> Float = match channel_to_float(&channel) {
+                Some(convert) => convert,
+                None => {
+                    println!(
+                        "WARNING: Image channel \"{}\" unknown. Using luminance.",
+                        channel
+                    );
+                    convert_to_float
+                }
+            };
+
+            let ft = Arc::new(ImageTexture::new(
+                mapping,
+                filename,
+                do_trilinear,
+                max_aniso,
+                wrap_mode,
+                scale,
+                encoding,
+                convert,
+            ));
+            Arc::make_mut(&mut api_state.graphics_state.float_textures)
+                .insert(api_state.param_set.name.clone(), ft);
         } else if api_state.param_set.tex_name == "uv" {
             println!("TODO: CreateUVFloatTexture");
         } else if api_state.param_set.tex_name == "checkerboard" {
-            println!("TODO: CreateCheckerboardFloatTexture");
-        } else if api_state.param_set.tex_name == "dots" {
-            // CreateDotsFloatTexture
-            let map: Option<Box<TextureMapping2D>>;
-            let mapping: String = tp.find_string("mapping", String::from("uv"));
-            if mapping == "uv" {
-                let su: Float = tp.find_float("uscale", 1.0);
-                let sv: Float = tp.find_float("vscale", 1.0);
-                let du: Float = tp.find_float("udelta", 0.0);
-                let dv: Float = tp.find_float("vdelta", 0.0);
-                map = Some(Box::new(TextureMapping2D::UV(UVMapping2D {
-                    su,
-                    sv,
-                    du,
-                    dv,
-                })));
-            } else if mapping == "spherical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Spherical(
-                    SphericalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "cylindrical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Cylindrical(
-                    CylindricalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "planar" {
-                map = Some(Box::new(TextureMapping2D::Planar(PlanarMapping2D {
-                    vs: tp.find_vector3f(
-                        "v1",
-                        Vector3f {
-                            x: 1.0,
-                            y: 0.0,
-                            z: 0.0,
-                        },
-                    ),
-                    vt: tp.find_vector3f(
-                        "v2",
-                        Vector3f {
-                            x: 0.0,
-                            y: 1.0,
-                            z: 0.0,
-                        },
-                    ),
-                    ds: tp.find_float("udelta", 0.0),
-                    dt: tp.find_float("vdelta", 0.0),
-                })));
-            } else {
-                panic!("2D texture mapping \"{}\" unknown", mapping);
+            // CreateCheckerboardFloatTexture
+            let dim: i32 = tp.find_int("dimension", 2);
+            if dim != 2 && dim != 3 {
+                panic!("{} dimensional checkerboard texture not supported", dim);
             }
-            if let Some(mapping) = map {
-                let dt = Arc::new(DotsTexture::new(
-                    mapping,
-                    tp.get_float_texture("inside", 1.0 as Float),
-                    tp.get_float_texture("outside", 0.0 as Float),
+            let tex1: Arc<dyn Texture<Float> + Send + Sync> =
+                tp.get_float_texture("tex1", 1.0 as Float);
+            let tex2: Arc<dyn Texture<Float> + Send + Sync> =
+                tp.get_float_texture("tex2", 0.0 as Float);
+            if dim == 2 {
+                let tex_2_world: Transform = api_state.cur_transform.t[0];
+                let mapping: Box<TextureMapping2D> =
+                    TextureMapping2D::create(&tex_2_world, &mut tp);
+                // initialize _Checkerboard2DTexture_ parameters
+                let aa: String = tp.find_string("aamode", String::from("closedform"));
+                let aa_method: AAMethod;
+                if aa == "none" {
+                    aa_method = AAMethod::None;
+                } else if aa == "closedform" {
+                    aa_method = AAMethod::ClosedForm;
+                } else {
+                    println!(
+                        "WARNING: Antialiasing mode \"{}\" not understood by Checkerboard2DTexture; using \"closedform\"",
+                        aa
+                    );
+                    aa_method = AAMethod::ClosedForm;
+                }
+                let ft = Arc::new(Checkerboard2DTexture::new(mapping, tex1, tex2, aa_method));
+                Arc::make_mut(&mut api_state.graphics_state.float_textures)
+                    .insert(api_state.param_set.name.clone(), ft);
+            } else {
+                // dim == 3
+                let tex_2_world: Transform = Transform {
+                    m: api_state.cur_transform.t[0].m,
+                    m_inv: api_state.cur_transform.t[0].m_inv,
+                };
+                let map: Box<TextureMapping3D> = Box::new(TextureMapping3D::Identity(
+                    IdentityMapping3D::new(tex_2_world),
                 ));
+                let ft = Arc::new(Checkerboard3DTexture::new(map, tex1, tex2));
                 Arc::make_mut(&mut api_state.graphics_state.float_textures)
-                    .insert(api_state.param_set.name.clone(), dt);
+                    .insert(api_state.param_set.name.clone(), ft);
             }
+        } else if api_state.param_set.tex_name == "dots" {
+            // CreateDotsFloatTexture
+            let tex_2_world: Transform = api_state.cur_transform.t[0];
+            let mapping: Box<TextureMapping2D> = TextureMapping2D::create(&tex_2_world, &mut tp);
+            let dt = Arc::new(DotsTexture::new(
+                mapping,
+                tp.get_float_texture("inside", 1.0 as Float),
+                tp.get_float_texture("outside", 0.0 as Float),
+            ));
+            Arc::make_mut(&mut api_state.graphics_state.float_textures)
+                .insert(api_state.param_set.name.clone(), dt);
         } else if api_state.param_set.tex_name == "fbm" {
             // CreateFBmFloatTexture
             let tex_2_world: Transform = Transform {
@@ -1216,12 +1534,7 @@ fn make_texture(api_state: &mut ApiState) {
                 m: api_state.cur_transform.t[0].m,
                 m_inv: api_state.cur_transform.t[0].m_inv,
             };
-            let map: Box<TextureMapping3D> = Box::new(TextureMapping3D::Identity(
-                IdentityMapping3D::new(tex_2_world),
-            ));
-            let octaves: i32 = tp.find_int("octaves", 8_i32);
-            let roughness: Float = tp.find_float("roughness", 0.5 as Float);
-            let ft = Arc::new(WrinkledTexture::new(map, octaves, roughness));
+            let ft = Arc::new(WrinkledTexture::create(&tex_2_world, &mut tp));
             Arc::make_mut(&mut api_state.graphics_state.float_textures)
                 .insert(api_state.param_set.name.clone(), ft);
         } else if api_state.param_set.tex_name == "marble" {
@@ -1232,10 +1545,7 @@ fn make_texture(api_state: &mut ApiState) {
                 m: api_state.cur_transform.t[0].m,
                 m_inv: api_state.cur_transform.t[0].m_inv,
             };
-            let map: Box<TextureMapping3D> = Box::new(TextureMapping3D::Identity(
-                IdentityMapping3D::new(tex_2_world),
-            ));
-            let ft = Arc::new(WindyTexture::new(map));
+            let ft = Arc::new(WindyTexture::create(&tex_2_world, &mut tp));
             Arc::make_mut(&mut api_state.graphics_state.float_textures)
                 .insert(api_state.param_set.name.clone(), ft);
         } else if api_state.param_set.tex_name == "ptex" {
@@ -1269,11 +1579,20 @@ fn make_texture(api_state: &mut ApiState) {
         } else if api_state.param_set.tex_name == "scale" {
             let tex1: Arc<dyn Texture<Spectrum> + Send + Sync> =
                 tp.get_spectrum_texture("tex1", Spectrum::new(1.0));
-            let tex2: Arc<dyn Texture<Spectrum> + Send + Sync> =
-                tp.get_spectrum_texture("tex2", Spectrum::new(0.0));
-            let st = Arc::new(ScaleTexture::<Spectrum>::new(tex1, tex2));
-            Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
-                .insert(api_state.param_set.name.clone(), st);
+            if tp.is_float_texture("tex2") {
+                // a float _tex2_ scales all channels of _tex1_
+                let tex2: Arc<dyn Texture<Float> + Send + Sync> =
+                    tp.get_float_texture("tex2", 0.0 as Float);
+                let st = Arc::new(ScaleTextureSF::new(tex1, tex2));
+                Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                    .insert(api_state.param_set.name.clone(), st);
+            } else {
+                let tex2: Arc<dyn Texture<Spectrum> + Send + Sync> =
+                    tp.get_spectrum_texture("tex2", Spectrum::new(0.0));
+                let st = Arc::new(ScaleTexture::<Spectrum>::new(tex1, tex2));
+                Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                    .insert(api_state.param_set.name.clone(), st);
+            }
         } else if api_state.param_set.tex_name == "mix" {
             let mt = Arc::new(MixTexture::<Spectrum>::new(
                 tp.get_spectrum_texture("tex1", Spectrum::new(0.0)),
@@ -1286,53 +1605,8 @@ fn make_texture(api_state: &mut ApiState) {
             println!("TODO: CreateBilerpSpectrumTexture");
         } else if api_state.param_set.tex_name == "imagemap" {
             // CreateImageSpectrumTexture
-            let map: Option<Box<TextureMapping2D>>;
-            let mapping: String = tp.find_string("mapping", String::from("uv"));
-            if mapping == "uv" {
-                let su: Float = tp.find_float("uscale", 1.0);
-                let sv: Float = tp.find_float("vscale", 1.0);
-                let du: Float = tp.find_float("udelta", 0.0);
-                let dv: Float = tp.find_float("vdelta", 0.0);
-                map = Some(Box::new(TextureMapping2D::UV(UVMapping2D {
-                    su,
-                    sv,
-                    du,
-                    dv,
-                })));
-            } else if mapping == "spherical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Spherical(
-                    SphericalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "cylindrical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Cylindrical(
-                    CylindricalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "planar" {
-                map = Some(Box::new(TextureMapping2D::Planar(PlanarMapping2D {
-                    vs: tp.find_vector3f(
-                        "v1",
-                        Vector3f {
-                            x: 1.0,
-                            y: 0.0,
-                            z: 0.0,
-                        },
-                    ),
-                    vt: tp.find_vector3f(
-                        "v2",
-                        Vector3f {
-                            x: 0.0,
-                            y: 1.0,
-                            z: 0.0,
-                        },
-                    ),
-                    ds: tp.find_float("udelta", 0.0),
-                    dt: tp.find_float("vdelta", 0.0),
-                })));
-            } else {
-                panic!("2D texture mapping \"{}\" unknown", mapping);
-            }
+            let tex_2_world: Transform = api_state.cur_transform.t[0];
+            let mapping: Box<TextureMapping2D> = TextureMapping2D::create(&tex_2_world, &mut tp);
             // initialize _ImageTexture_ parameters
             let max_aniso: Float = tp.find_float("maxanisotropy", 8.0);
             let do_trilinear: bool = tp.find_bool("trilinear", false);
@@ -1352,27 +1626,20 @@ fn make_texture(api_state: &mut ApiState) {
                 path_buf.push(filename);
                 filename = String::from(path_buf.to_str().unwrap());
             }
-            // TODO: default depends on:
-            // HasExtension(filename,
-            // ".tga") ||
-            // HasExtension(filename,
-            // ".png"));
-            let gamma: bool = tp.find_bool("gamma", true);
-
-            if let Some(mapping) = map {
-                let st = Arc::new(ImageTexture::new(
-                    mapping,
-                    filename,
-                    do_trilinear,
-                    max_aniso,
-                    wrap_mode,
-                    scale,
-                    gamma,
-                    convert_to_spectrum,
-                ));
-                Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
-                    .insert(api_state.param_set.name.clone(), st);
-            }
+            let encoding: ImageEncoding = ImageEncoding::create(&mut tp, ImageEncoding::Srgb);
+
+            let st = Arc::new(ImageTexture::new(
+                mapping,
+                filename,
+                do_trilinear,
+                max_aniso,
+                wrap_mode,
+                scale,
+                encoding,
+                convert_to_spectrum,
+            ));
+            Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                .insert(api_state.param_set.name.clone(), st);
         } else if api_state.param_set.tex_name == "uv" {
             println!("TODO: CreateUVSpectrumTexture");
         } else if api_state.param_set.tex_name == "checkerboard" {
@@ -1386,121 +1653,50 @@ fn make_texture(api_state: &mut ApiState) {
             let tex2: Arc<dyn Texture<Spectrum> + Send + Sync> =
                 tp.get_spectrum_texture("tex2", Spectrum::new(0.0));
             if dim == 2 {
-                let map: Option<Box<TextureMapping2D>>;
-                let mapping: String = tp.find_string("mapping", String::from("uv"));
-                if mapping == "uv" {
-                    let su: Float = tp.find_float("uscale", 1.0);
-                    let sv: Float = tp.find_float("vscale", 1.0);
-                    let du: Float = tp.find_float("udelta", 0.0);
-                    let dv: Float = tp.find_float("vdelta", 0.0);
-                    map = Some(Box::new(TextureMapping2D::UV(UVMapping2D {
-                        su,
-                        sv,
-                        du,
-                        dv,
-                    })));
-                } else if mapping == "spherical" {
-                    let tex_2_world = api_state.cur_transform.t[0];
-                    map = Some(Box::new(TextureMapping2D::Spherical(
-                        SphericalMapping2D::new(tex_2_world),
-                    )));
-                } else if mapping == "cylindrical" {
-                    let tex_2_world = api_state.cur_transform.t[0];
-                    map = Some(Box::new(TextureMapping2D::Cylindrical(
-                        CylindricalMapping2D::new(tex_2_world),
-                    )));
-                } else if mapping == "planar" {
-                    map = Some(Box::new(TextureMapping2D::Planar(PlanarMapping2D {
-                        vs: tp.find_vector3f(
-                            "v1",
-                            Vector3f {
-                                x: 1.0,
-                                y: 0.0,
-                                z: 0.0,
-                            },
-                        ),
-                        vt: tp.find_vector3f(
-                            "v2",
-                            Vector3f {
-                                x: 0.0,
-                                y: 1.0,
-                                z: 0.0,
-                            },
-                        ),
-                        ds: tp.find_float("udelta", 0.0),
-                        dt: tp.find_float("vdelta", 0.0),
-                    })));
+                let tex_2_world: Transform = api_state.cur_transform.t[0];
+                let mapping: Box<TextureMapping2D> =
+                    TextureMapping2D::create(&tex_2_world, &mut tp);
+                // initialize _Checkerboard2DTexture_ parameters
+                let aa: String = tp.find_string("aamode", String::from("closedform"));
+                let aa_method: AAMethod;
+                if aa == "none" {
+                    aa_method = AAMethod::None;
+                } else if aa == "closedform" {
+                    aa_method = AAMethod::ClosedForm;
                 } else {
-                    panic!("2D texture mapping \"{}\" unknown", mapping);
-                }
-                // TODO: aamode
-                if let Some(mapping) = map {
-                    let st = Arc::new(Checkerboard2DTexture::new(mapping, tex1, tex2));
-                    Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
-                        .insert(api_state.param_set.name.clone(), st);
+                    println!(
+                        "WARNING: Antialiasing mode \"{}\" not understood by Checkerboard2DTexture; using \"closedform\"",
+                        aa
+                    );
+                    aa_method = AAMethod::ClosedForm;
                 }
+                let st = Arc::new(Checkerboard2DTexture::new(mapping, tex1, tex2, aa_method));
+                Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                    .insert(api_state.param_set.name.clone(), st);
             } else {
                 // dim == 3
-                println!("TODO: TextureMapping3D");
+                let tex_2_world: Transform = Transform {
+                    m: api_state.cur_transform.t[0].m,
+                    m_inv: api_state.cur_transform.t[0].m_inv,
+                };
+                let map: Box<TextureMapping3D> = Box::new(TextureMapping3D::Identity(
+                    IdentityMapping3D::new(tex_2_world),
+                ));
+                let st = Arc::new(Checkerboard3DTexture::new(map, tex1, tex2));
+                Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                    .insert(api_state.param_set.name.clone(), st);
             }
         } else if api_state.param_set.tex_name == "dots" {
             // CreateDotsSpectrumTexture
-            let map: Option<Box<TextureMapping2D>>;
-            let mapping: String = tp.find_string("mapping", String::from("uv"));
-            if mapping == "uv" {
-                let su: Float = tp.find_float("uscale", 1.0);
-                let sv: Float = tp.find_float("vscale", 1.0);
-                let du: Float = tp.find_float("udelta", 0.0);
-                let dv: Float = tp.find_float("vdelta", 0.0);
-                map = Some(Box::new(TextureMapping2D::UV(UVMapping2D {
-                    su,
-                    sv,
-                    du,
-                    dv,
-                })));
-            } else if mapping == "spherical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Spherical(
-                    SphericalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "cylindrical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Cylindrical(
-                    CylindricalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "planar" {
-                map = Some(Box::new(TextureMapping2D::Planar(PlanarMapping2D {
-                    vs: tp.find_vector3f(
-                        "v1",
-                        Vector3f {
-                            x: 1.0,
-                            y: 0.0,
-                            z: 0.0,
-                        },
-                    ),
-                    vt: tp.find_vector3f(
-                        "v2",
-                        Vector3f {
-                            x: 0.0,
-                            y: 1.0,
-                            z: 0.0,
-                        },
-                    ),
-                    ds: tp.find_float("udelta", 0.0),
-                    dt: tp.find_float("vdelta", 0.0),
-                })));
-            } else {
-                panic!("2D texture mapping \"{}\" unknown", mapping);
-            }
+            let tex_2_world: Transform = api_state.cur_transform.t[0];
+            let mapping: Box<TextureMapping2D> = TextureMapping2D::create(&tex_2_world, &mut tp);
             let inside: Arc<dyn Texture<Spectrum> + Send + Sync> =
                 tp.get_spectrum_texture("inside", Spectrum::new(1.0));
             let outside: Arc<dyn Texture<Spectrum> + Send + Sync> =
                 tp.get_spectrum_texture("outside", Spectrum::new(0.0));
-            if let Some(mapping) = map {
-                let dt = Arc::new(DotsTexture::new(mapping, inside, outside));
-                Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
-                    .insert(api_state.param_set.name.clone(), dt);
-            }
+            let dt = Arc::new(DotsTexture::new(mapping, inside, outside));
+            Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                .insert(api_state.param_set.name.clone(), dt);
         } else if api_state.param_set.tex_name == "fbm" {
             // CreateFBmSpectrumTexture
             let tex_2_world: Transform = Transform {
@@ -1521,12 +1717,7 @@ fn make_texture(api_state: &mut ApiState) {
                 m: api_state.cur_transform.t[0].m,
                 m_inv: api_state.cur_transform.t[0].m_inv,
             };
-            let map: Box<TextureMapping3D> = Box::new(TextureMapping3D::Identity(
-                IdentityMapping3D::new(tex_2_world),
-            ));
-            let octaves: i32 = tp.find_int("octaves", 8_i32);
-            let roughness: Float = tp.find_float("roughness", 0.5 as Float);
-            let ft = Arc::new(WrinkledTexture::new(map, octaves, roughness));
+            let ft = Arc::new(WrinkledTexture::create(&tex_2_world, &mut tp));
             Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
                 .insert(api_state.param_set.name.clone(), ft);
         } else if api_state.param_set.tex_name == "marble" {
@@ -1552,10 +1743,7 @@ fn make_texture(api_state: &mut ApiState) {
                 m: api_state.cur_transform.t[0].m,
                 m_inv: api_state.cur_transform.t[0].m_inv,
             };
-            let map: Box<TextureMapping3D> = Box::new(TextureMapping3D::Identity(
-                IdentityMapping3D::new(tex_2_world),
-            ));
-            let ft = Arc::new(WindyTexture::new(map));
+            let ft = Arc::new(WindyTexture::create(&tex_2_world, &mut tp));
             Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
                 .insert(api_state.param_set.name.clone(), ft);
         } else {
@@ -1577,6 +1765,7 @@ pub fn make_accelerator(
     primitives: &Vec<Arc<Primitive>>,
     accelerator_params: &ParamSet,
 ) -> Option<Arc<Primitive>> {
+    let _phase = ProfilePhase::new(Prof::AccelConstruction);
     let mut some_accelerator: Option<Arc<Primitive>> = None;
     if accelerator_name == "bvh" {
         // CreateBVHAccelerator
@@ -1590,6 +1779,25 @@ pub fn make_accelerator(
             primitives.clone(),
             accelerator_params,
         )));
+    } else if accelerator_name == "embree" {
+        #[cfg(feature = "embree")]
+        {
+            // CreateEmbreeAccelerator
+            some_accelerator = Some(Arc::new(EmbreeAccel::create(
+                primitives.clone(),
+                accelerator_params,
+            )));
+        }
+        #[cfg(not(feature = "embree"))]
+        {
+            println!(
+                "WARNING: Accelerator \"embree\" needs the \"embree\" feature.  Using \"bvh\"."
+            );
+            some_accelerator = Some(Arc::new(BVHAccel::create(
+                primitives.clone(),
+                accelerator_params,
+            )));
+        }
     }
     some_accelerator
 }
@@ -1599,54 +1807,37 @@ pub fn make_camera(
     camera_params: &ParamSet,
     animated_cam_to_world: AnimatedTransform,
     film: Arc<Film>,
+    medium: Option<Arc<Medium>>,
+    search_directory: Option<&Box<PathBuf>>,
 ) -> Option<Arc<Camera>> {
     let mut some_camera: Option<Arc<Camera>> = None;
-    let medium_interface: MediumInterface = MediumInterface::default();
     if camera_name == "perspective" {
-        let camera: Arc<Camera> = PerspectiveCamera::create(
-            &camera_params,
-            animated_cam_to_world,
-            film,
-            medium_interface.outside,
-        );
+        let camera: Arc<Camera> =
+            PerspectiveCamera::create(
+                &camera_params,
+                animated_cam_to_world,
+                film,
+                medium,
+                search_directory,
+            );
         some_camera = Some(camera);
     } else if camera_name == "orthographic" {
-        let camera: Arc<Camera> = OrthographicCamera::create(
-            &camera_params,
-            animated_cam_to_world,
-            film,
-            medium_interface.outside,
-        );
+        let camera: Arc<Camera> =
+            OrthographicCamera::create(&camera_params, animated_cam_to_world, film, medium);
         some_camera = Some(camera);
     } else if camera_name == "realistic" {
-        // if let Some(ref search_directory) = api_state.search_directory {
-        //     let camera: Arc<Camera> = RealisticCamera::create(
-        //         &camera_params,
-        //         animated_cam_to_world,
-        //         film,
-        //         medium_interface.outside,
-        //         // additional parameters:
-        //         Some(search_directory),
-        //     );
-        //     some_camera = Some(camera);
-        // } else {
         let camera: Arc<Camera> = RealisticCamera::create(
             &camera_params,
             animated_cam_to_world,
             film,
-            medium_interface.outside,
+            medium,
             // additional parameters:
-            None,
+            search_directory,
         );
         some_camera = Some(camera);
-    // }
     } else if camera_name == "environment" {
-        let camera: Arc<Camera> = EnvironmentCamera::create(
-            &camera_params,
-            animated_cam_to_world,
-            film,
-            medium_interface.outside,
-        );
+        let camera: Arc<Camera> =
+            EnvironmentCamera::create(&camera_params, animated_cam_to_world, film, medium);
         some_camera = Some(camera);
     } else {
         println!("Camera \"{}\" unknown.", camera_name);
@@ -1666,11 +1857,11 @@ pub fn make_sampler(name: &String, param_set: &ParamSet, film: Arc<Film>) -> Opt
         some_sampler = Some(sampler);
     } else if name == "halton" {
         // CreateHaltonSampler
-        let sampler = HaltonSampler::create(param_set, &film.get_sample_bounds());
+        let sampler = HaltonSampler::create(param_set, &film.get_full_sample_bounds());
         some_sampler = Some(sampler);
     } else if name == "sobol" {
         // CreateSobolSampler
-        let sampler = SobolSampler::create(param_set, &film.get_sample_bounds());
+        let sampler = SobolSampler::create(param_set, &film.get_full_sample_bounds());
         some_sampler = Some(sampler);
     } else if name == "random" {
         // CreateRandomSampler
@@ -1714,8 +1905,32 @@ pub fn make_film(name: &String, param_set: &ParamSet, filter: Box<Filter>) -> Op
     some_film
 }
 
+/// The name of a shape for statistics: its "name" parameter, or the
+/// name of the object (ObjectBegin) it belongs to.
+fn shape_name(api_state: &ApiState) -> Option<String> {
+    let name: String = api_state.param_set.find_one_string("name", String::new());
+    if name != "" {
+        Some(name)
+    } else if api_state.render_options.current_instance != "" {
+        Some(api_state.render_options.current_instance.clone())
+    } else {
+        None
+    }
+}
+
+/// The statistics id (see **stat_name_id()**) of the current
+/// material: its name (MakeNamedMaterial), or the material type in
+/// parentheses for anonymous materials.
+fn material_stats_id(api_state: &ApiState) -> u32 {
+    if api_state.graphics_state.current_material != "" {
+        stat_name_id(Some(&api_state.graphics_state.current_material))
+    } else {
+        stat_name_id(Some(&format!("({})", api_state.graphics_state.material)))
+    }
+}
+
 fn get_shapes_and_materials(
-    api_state: &ApiState,
+    api_state: &mut ApiState,
     bsdf_state: &mut BsdfState,
 ) -> (Vec<Arc<Shape>>, Vec<Option<Arc<Material>>>) {
     if shape_may_set_material_parameters(&api_state.param_set) {
@@ -1728,7 +1943,6 @@ fn get_shapes_and_materials(
     let mut materials: Vec<Option<Arc<Material>>> = Vec::new();
     // pbrtShape (api.cpp:1153)
     // TODO: if (!curTransform.IsAnimated()) { ... }
-    // TODO: transformCache.Lookup(curTransform[0], &ObjToWorld, &WorldToObj);
     let mut obj_to_world: Transform = Transform {
         m: api_state.cur_transform.t[0].m,
         m_inv: api_state.cur_transform.t[0].m_inv,
@@ -1745,6 +1959,9 @@ fn get_shapes_and_materials(
         obj_to_world = Transform::default();
         world_to_obj = Transform::default();
     }
+    let obj_to_world: Arc<Transform> = api_state.render_options.transform(&obj_to_world);
+    let world_to_obj: Arc<Transform> = api_state.render_options.transform(&world_to_obj);
+    let mesh_name: Option<String> = shape_name(api_state);
     // MakeShapes (api.cpp:296)
     if api_state.param_set.name == "sphere" {
         // CreateSphereShape
@@ -1753,9 +1970,9 @@ fn get_shapes_and_materials(
         let z_max: Float = api_state.param_set.find_one_float("zmax", radius);
         let phi_max: Float = api_state.param_set.find_one_float("phimax", 360.0 as Float);
         let sphere = Arc::new(Shape::Sphr(Sphere::new(
-            obj_to_world,
-            world_to_obj,
-            false,
+            obj_to_world.clone(),
+            world_to_obj.clone(),
+            api_state.graphics_state.reverse_orientation,
             radius,
             z_min,
             z_max,
@@ -1770,9 +1987,9 @@ fn get_shapes_and_materials(
         let z_max: Float = api_state.param_set.find_one_float("zmax", radius);
         let phi_max: Float = api_state.param_set.find_one_float("phimax", 360.0 as Float);
         let cylinder = Arc::new(Shape::Clndr(Cylinder::new(
-            obj_to_world,
-            world_to_obj,
-            false,
+            obj_to_world.clone(),
+            world_to_obj.clone(),
+            api_state.graphics_state.reverse_orientation,
             radius,
             z_min,
             z_max,
@@ -1787,8 +2004,8 @@ fn get_shapes_and_materials(
         let inner_radius: Float = api_state.param_set.find_one_float("innerradius", 0.0);
         let phi_max: Float = api_state.param_set.find_one_float("phimax", 360.0);
         let disk = Arc::new(Shape::Dsk(Disk::new(
-            obj_to_world,
-            world_to_obj,
+            obj_to_world.clone(),
+            world_to_obj.clone(),
             false,
             height,
             radius,
@@ -1809,7 +2026,7 @@ fn get_shapes_and_materials(
         let curve_shapes: Vec<Arc<Shape>> = create_curve_shape(
             &obj_to_world,
             &world_to_obj,
-            false, // reverse_orientation
+            api_state.graphics_state.reverse_orientation,
             &api_state.param_set,
         );
         for shape in curve_shapes {
@@ -1890,9 +2107,9 @@ fn get_shapes_and_materials(
         for i in 0..vi.len() {
             vertex_indices.push(vi[i] as u32);
         }
-        let mesh = Arc::new(TriangleMesh::new(
-            obj_to_world,
-            world_to_obj,
+        let mut mesh: TriangleMesh = TriangleMesh::new(
+            *obj_to_world,
+            *world_to_obj,
             api_state.graphics_state.reverse_orientation,
             (vi.len() / 3).try_into().unwrap(), // n_triangles
             vertex_indices,
@@ -1903,13 +2120,15 @@ fn get_shapes_and_materials(
             uvs,
             None,
             None,
-        ));
+        );
+        mesh.set_name(mesh_name);
+        let mesh = Arc::new(mesh);
         let mtl: Option<Arc<Material>> = create_material(&api_state, bsdf_state);
         for id in 0..mesh.n_triangles {
+            if mesh.is_degenerate_triangle(id) {
+                continue;
+            }
             let triangle = Arc::new(Shape::Trngl(Triangle::new(
-                mesh.object_to_world,
-                mesh.world_to_object,
-                mesh.reverse_orientation,
                 mesh.clone(),
                 id.try_into().unwrap(),
             )));
@@ -1922,11 +2141,12 @@ fn get_shapes_and_materials(
             let ply_shapes: Vec<Arc<Shape>> = create_ply_mesh(
                 &obj_to_world,
                 &world_to_obj,
-                false, // reverse_orientation
+                api_state.graphics_state.reverse_orientation,
                 &api_state.param_set,
                 api_state.graphics_state.float_textures.clone(),
                 // additional parameters:
                 Some(search_directory),
+                mesh_name,
             );
             for shape in ply_shapes {
                 shapes.push(shape.clone());
@@ -1935,6 +2155,34 @@ fn get_shapes_and_materials(
         } else {
             panic!("No search directory for plymesh.");
         }
+    } else if api_state.param_set.name == "objmesh" {
+        let filename: String = api_state
+            .param_set
+            .find_one_string("filename", String::new());
+        let mut path_buf: PathBuf = PathBuf::from(&filename);
+        if path_buf.is_relative() {
+            if let Some(ref search_directory) = api_state.search_directory {
+                path_buf = search_directory.join(&filename);
+            }
+        }
+        match load_obj(
+            &path_buf,
+            &obj_to_world,
+            api_state.graphics_state.reverse_orientation,
+        ) {
+            Ok(meshes) => {
+                let mtl: Option<Arc<Material>> = create_material(&api_state, bsdf_state);
+                for (_mesh, triangles, obj_mtl) in meshes {
+                    // materials from the OBJ file take precedence
+                    let mtl: Option<Arc<Material>> = obj_mtl.or_else(|| mtl.clone());
+                    for triangle in triangles {
+                        shapes.push(triangle);
+                        materials.push(mtl.clone());
+                    }
+                }
+            }
+            Err(e) => panic!("Couldn't read OBJ file {:?}: {}", path_buf, e),
+        }
     } else if api_state.param_set.name == "heightfield" {
         println!("TODO: CreateHeightfield");
     } else if api_state.param_set.name == "loopsubdiv" {
@@ -1962,13 +2210,14 @@ fn get_shapes_and_materials(
             n_levels,
             &vertex_indices,
             &p,
+            mesh_name,
         );
         let mtl: Option<Arc<Material>> = create_material(&api_state, bsdf_state);
         for id in 0..mesh.n_triangles {
+            if mesh.is_degenerate_triangle(id) {
+                continue;
+            }
             let triangle = Arc::new(Shape::Trngl(Triangle::new(
-                mesh.object_to_world,
-                mesh.world_to_object,
-                mesh.reverse_orientation,
                 mesh.clone(),
                 id.try_into().unwrap(),
             )));
@@ -2136,9 +2385,9 @@ fn get_shapes_and_materials(
         for i in 0..n_normals {
             n_ws.push(obj_to_world.transform_normal(&eval_ns[i]));
         }
-        let mesh = Arc::new(TriangleMesh::new(
-            obj_to_world,
-            world_to_obj,
+        let mut mesh: TriangleMesh = TriangleMesh::new(
+            *obj_to_world,
+            *world_to_obj,
             api_state.graphics_state.reverse_orientation,
             n_tris.try_into().unwrap(), // n_triangles
             vertices,
@@ -2149,13 +2398,15 @@ fn get_shapes_and_materials(
             uvs,
             None,
             None,
-        ));
+        );
+        mesh.set_name(mesh_name);
+        let mesh = Arc::new(mesh);
         let mtl: Option<Arc<Material>> = create_material(&api_state, bsdf_state);
         for id in 0..mesh.n_triangles {
+            if mesh.is_degenerate_triangle(id) {
+                continue;
+            }
             let triangle = Arc::new(Shape::Trngl(Triangle::new(
-                mesh.object_to_world,
-                mesh.world_to_object,
-                mesh.reverse_orientation,
                 mesh.clone(),
                 id.try_into().unwrap(),
             )));
@@ -2245,10 +2496,9 @@ fn print_params(params: &ParamSet) {
     }
     for p in &params.spectra {
         if p.n_values == 1_usize {
-            println!(
-                "  \"rgb {}\" [{} {} {}]",
-                p.name, p.values[0].c[0], p.values[0].c[1], p.values[0].c[2]
-            );
+            let mut rgb: [Float; 3] = [0.0 as Float; 3];
+            p.values[0].to_rgb(&mut rgb);
+            println!("  \"rgb {}\" [{} {} {}]", p.name, rgb[0], rgb[1], rgb[2]);
         }
     }
     for p in &params.textures {
@@ -2258,6 +2508,49 @@ fn print_params(params: &ParamSet) {
     }
 }
 
+/// Warnings about the parameters of a statement which were never
+/// looked up (see **ParamSet::report_unused()**):
+///
+/// ```rust
+/// use pbrt::core::api::unused_parameter_warnings;
+/// use pbrt::core::paramset::{ParamSet, TextureParams};
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::materials::metal::MetalMaterial;
+/// use std::collections::HashMap;
+/// use std::sync::Arc;
+///
+/// // Material "metal" "float roughnes" [ 0.2 ]
+/// let mut material_params = ParamSet::default();
+/// material_params.add_float(String::from("roughnes"), 0.2 as Float);
+/// let mut mp = TextureParams::new(
+///     ParamSet::default(),
+///     material_params,
+///     Arc::new(HashMap::new()),
+///     Arc::new(HashMap::new()),
+/// );
+/// let _metal = MetalMaterial::create(&mut mp);
+/// assert_eq!(
+///     unused_parameter_warnings("Material \"metal\"", &mp.report_unused()),
+///     vec![String::from(
+///         "WARNING: Material \"metal\": parameter \"roughnes\" not used"
+///     )]
+/// );
+/// ```
+pub fn unused_parameter_warnings(statement: &str, unused: &[String]) -> Vec<String> {
+    unused
+        .iter()
+        .map(|name| format!("WARNING: {}: parameter \"{}\" not used", statement, name))
+        .collect()
+}
+
+fn report_unused(api_state: &ApiState, statement: &str, unused: Vec<String>) {
+    if !api_state.quiet {
+        for warning in unused_parameter_warnings(statement, &unused) {
+            println!("{}", warning);
+        }
+    }
+}
+
 pub fn pbrt_init(number_of_threads: u8) -> (ApiState, BsdfState) {
     let mut api_state: ApiState = ApiState::default();
     let bsdf_state: BsdfState = BsdfState::default();
@@ -2276,16 +2569,68 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
         "Missing end to pbrtTransformBegin()"
     );
     // MakeIntegrator
-    let some_integrator: Option<Box<Integrator>> = api_state.render_options.make_integrator();
+    let some_integrator: Option<Box<Integrator>> = api_state
+        .render_options
+        .make_integrator(api_state.search_directory.as_ref());
     if let Some(mut integrator) = some_integrator {
         let scene = api_state.render_options.make_scene();
         let num_threads: u8 = api_state.number_of_threads;
-        integrator.render(&scene, num_threads);
+        #[cfg(feature = "tev")]
+        let tev: Option<TevDisplay> = open_tev(&api_state.render_options, &integrator);
+        #[cfg(not(feature = "tev"))]
+        {
+            if api_state.render_options.tev.is_some() {
+                println!("WARNING: rs_pbrt was built without the tev feature, no live preview");
+            }
+        }
+        let _phase = ProfilePhase::new(Prof::Rendering);
+        if api_state.render_options.progressive {
+            let on_pass_complete = &api_state.render_options.on_pass_complete;
+            render_progressive(&scene, &mut integrator, num_threads, |film, passes| {
+                if let Some(on_pass_complete) = on_pass_complete {
+                    on_pass_complete(&film.snapshot(passes));
+                }
+            });
+        } else if let Some(checkpoint) = &api_state.render_options.checkpoint {
+            render_with_checkpoints(&scene, &mut integrator, num_threads, checkpoint);
+        } else {
+            integrator.render(&scene, num_threads);
+        }
+        #[cfg(feature = "tev")]
+        {
+            if let Some(display) = tev {
+                if let Err(e) = display.close() {
+                    println!("WARNING: closing connection to tev failed: {}", e);
+                }
+            }
+        }
     } else {
         panic!("Unable to create integrator.");
     }
 }
 
+/// Connects the film to the tev image viewer (if requested), see
+/// **Film::open_tev()**.
+#[cfg(feature = "tev")]
+fn open_tev(render_options: &RenderOptions, integrator: &Integrator) -> Option<TevDisplay> {
+    if let Some((ref host, port)) = render_options.tev {
+        let film: Arc<Film> = integrator.get_camera().get_film();
+        let name: String = if film.filename.is_empty() {
+            String::from("pbrt")
+        } else {
+            film.filename.clone()
+        };
+        match film.open_tev(host, port, &name) {
+            Ok(display) => return Some(display),
+            Err(e) => println!(
+                "WARNING: unable to connect to tev at {}:{}: {}",
+                host, port, e
+            ),
+        }
+    }
+    None
+}
+
 pub fn pbrt_translate(api_state: &mut ApiState, dx: Float, dy: Float, dz: Float) {
     // println!("Translate {} {} {}", dx, dy, dz);
     let translate: Transform = Transform::translate(&Vector3f {
@@ -2402,6 +2747,17 @@ pub fn pbrt_look_at(
     }
 }
 
+/// Remembers the current transformation (both of them, for animated
+/// transformations) under the given name, so it can be restored later
+/// by **pbrt_coord_sys_transform()**.
+pub fn pbrt_coordinate_system(api_state: &mut ApiState, params: ParamSet) {
+    // println!("CoordinateSystem \"{}\"", params.name);
+    api_state.param_set = params;
+    api_state
+        .named_coordinate_systems
+        .insert(api_state.param_set.name.clone(), api_state.cur_transform);
+}
+
 pub fn pbrt_coord_sys_transform(api_state: &mut ApiState, params: ParamSet) {
     // println!("CoordSysTransform \"{}\"", params.name);
     api_state.param_set = params;
@@ -2502,13 +2858,15 @@ pub fn pbrt_camera(api_state: &mut ApiState, params: ParamSet) {
     // println!("Camera \"{}\"", params.name);
     // print_params(&params);
     api_state.render_options.camera_name = params.name.clone();
+    api_state.render_options.camera_medium =
+        api_state.graphics_state.current_outside_medium.clone();
     api_state.param_set = params;
     api_state.render_options.camera_to_world.t[0] =
         Transform::inverse(&api_state.cur_transform.t[0]);
     api_state.render_options.camera_to_world.t[1] =
         Transform::inverse(&api_state.cur_transform.t[1]);
     api_state.named_coordinate_systems.insert(
-        "camera",
+        String::from("camera"),
         TransformSet {
             t: [
                 api_state.render_options.camera_to_world.t[0],
@@ -2546,7 +2904,7 @@ pub fn pbrt_world_begin(api_state: &mut ApiState) {
     api_state.cur_transform.t[1] = Transform::default();
     api_state.active_transform_bits = 3_u8; // 0x11
     api_state.named_coordinate_systems.insert(
-        "world",
+        String::from("world"),
         TransformSet {
             t: [Transform::default(); 2],
         },
@@ -2589,6 +2947,85 @@ pub fn pbrt_attribute_begin(api_state: &mut ApiState) {
         .push(api_state.active_transform_bits);
 }
 
+/// Restores the graphics state (materials, named materials, area
+/// light, media, and the ReverseOrientation flag) and the current
+/// transformation saved by the matching **pbrt_attribute_begin()**.
+/// Transformations set within the block by **pbrt_coord_sys_transform()**
+/// are undone as well.
+///
+/// ```rust
+/// use pbrt::core::api::{
+///     pbrt_attribute_begin, pbrt_attribute_end, pbrt_coord_sys_transform,
+///     pbrt_coordinate_system, pbrt_init, pbrt_make_named_material, pbrt_material,
+///     pbrt_named_material, pbrt_reverse_orientation, pbrt_shape, pbrt_translate,
+///     pbrt_world_begin,
+/// };
+/// use pbrt::core::material::Material;
+/// use pbrt::core::paramset::ParamSet;
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::primitive::Primitive;
+/// use pbrt::core::shape::Shape;
+///
+/// fn params(name: &str) -> ParamSet {
+///     let mut params = ParamSet::default();
+///     params.name = String::from(name);
+///     params
+/// }
+///
+/// let (mut api_state, mut bsdf_state) = pbrt_init(1);
+/// pbrt_world_begin(&mut api_state);
+/// pbrt_material(&mut api_state, params("mirror"));
+/// let mut shiny = params("shiny");
+/// shiny.add_string(String::from("type"), String::from("plastic"));
+/// pbrt_make_named_material(&mut api_state, &mut bsdf_state, shiny);
+/// pbrt_attribute_begin(&mut api_state);
+/// pbrt_translate(&mut api_state, 1.0, 0.0, 0.0);
+/// pbrt_coordinate_system(&mut api_state, params("shifted"));
+/// pbrt_reverse_orientation(&mut api_state);
+/// pbrt_named_material(&mut api_state, params("shiny"));
+/// pbrt_attribute_begin(&mut api_state);
+/// pbrt_translate(&mut api_state, 0.0, 2.0, 0.0);
+/// pbrt_coord_sys_transform(&mut api_state, params("world"));
+/// pbrt_shape(&mut api_state, &mut bsdf_state, params("sphere"));
+/// pbrt_attribute_end(&mut api_state);
+/// pbrt_shape(&mut api_state, &mut bsdf_state, params("sphere"));
+/// pbrt_attribute_end(&mut api_state);
+/// pbrt_shape(&mut api_state, &mut bsdf_state, params("sphere"));
+/// pbrt_coord_sys_transform(&mut api_state, params("shifted"));
+/// pbrt_shape(&mut api_state, &mut bsdf_state, params("sphere"));
+/// // (translation in x, reverse orientation, plastic?) for each sphere
+/// let expected: [(Float, bool, bool); 4] = [
+///     (0.0, true, true),
+///     (1.0, true, true),
+///     // MakeNamedMaterial didn't replace the current (mirror) material
+///     (0.0, false, false),
+///     (1.0, false, false),
+/// ];
+/// assert_eq!(api_state.render_options.primitives.len(), expected.len());
+/// for (prim, (tx, reversed, plastic)) in api_state
+///     .render_options
+///     .primitives
+///     .iter()
+///     .zip(expected.iter())
+/// {
+///     if let Primitive::Geometric(geo_prim) = &**prim {
+///         if let Shape::Sphr(sphere) = &*geo_prim.shape {
+///             assert_eq!(sphere.object_to_world.m.m[0][3], *tx);
+///             assert_eq!(sphere.object_to_world.m.m[1][3], 0.0);
+///             assert_eq!(sphere.reverse_orientation, *reversed);
+///         } else {
+///             unreachable!();
+///         }
+///         match geo_prim.material.as_ref().map(|m| &**m) {
+///             Some(Material::Plastic(_)) => assert!(*plastic),
+///             Some(Material::Mirror(_)) => assert!(!*plastic),
+///             _ => unreachable!(),
+///         }
+///     } else {
+///         unreachable!();
+///     }
+/// }
+/// ```
 pub fn pbrt_attribute_end(api_state: &mut ApiState) {
     // println!("AttributeEnd");
     if !(api_state.pushed_graphics_states.len() >= 1_usize) {
@@ -2664,22 +3101,30 @@ pub fn pbrt_make_named_material(
     if mat_type == "" {
         panic!("No parameter string \"type\" found in MakeNamedMaterial");
     }
-    api_state.graphics_state.material = mat_type.clone();
-    api_state
-        .graphics_state
-        .material_params
-        .copy_from(&api_state.param_set);
-    api_state.graphics_state.current_material = String::new();
+    // the material is created with the textures defined so far, but
+    // the current material stays untouched
+    let mut material_params: ParamSet = ParamSet::default();
+    material_params.copy_from(&api_state.param_set);
+    let saved_material: String =
+        std::mem::replace(&mut api_state.graphics_state.material, mat_type);
+    let saved_material_params: ParamSet = std::mem::replace(
+        &mut api_state.graphics_state.material_params,
+        material_params,
+    );
+    let saved_current_material: String = std::mem::replace(
+        &mut api_state.graphics_state.current_material,
+        String::new(),
+    );
     let mtl: Option<Arc<Material>> = create_material(&api_state, bsdf_state);
-    match api_state
+    api_state.graphics_state.material = saved_material;
+    api_state.graphics_state.material_params = saved_material_params;
+    api_state.graphics_state.current_material = saved_current_material;
+    if api_state
         .graphics_state
         .named_materials
-        .get(api_state.param_set.name.as_str())
+        .contains_key(api_state.param_set.name.as_str())
     {
-        Some(_named_material) => {
-            println!("Named material \"{}\" redefined", mat_type);
-        }
-        None => {}
+        println!("Named material \"{}\" redefined", api_state.param_set.name);
     }
     Arc::make_mut(&mut api_state.graphics_state.named_materials)
         .insert(api_state.param_set.name.clone(), mtl);
@@ -2697,6 +3142,11 @@ pub fn pbrt_light_source(api_state: &mut ApiState, params: ParamSet) {
     api_state.param_set = params;
     let mi: MediumInterface = create_medium_interface(&api_state);
     make_light(api_state, &mi);
+    report_unused(
+        api_state,
+        &format!("LightSource \"{}\"", api_state.param_set.name),
+        api_state.param_set.report_unused(),
+    );
 }
 
 pub fn pbrt_area_light_source(api_state: &mut ApiState, params: ParamSet) {
@@ -2724,7 +3174,8 @@ pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params:
             || api_state.graphics_state.area_light == "diffuse"
         {
             // first create the shape
-            let (shapes, materials) = get_shapes_and_materials(&api_state, bsdf_state);
+            let (shapes, materials) = get_shapes_and_materials(api_state, bsdf_state);
+            let material_stats_id: u32 = material_stats_id(api_state);
             assert_eq!(shapes.len(), materials.len());
             // MediumInterface
             let mi: MediumInterface = create_medium_interface(&api_state);
@@ -2736,22 +3187,28 @@ pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params:
                 let l: Spectrum = api_state
                     .graphics_state
                     .area_light_params
-                    .find_one_spectrum("L", Spectrum::new(1.0));
+                    .find_one_illuminant(
+                        "L",
+                        Spectrum::from_rgb_type(&[1.0 as Float; 3], SpectrumType::Illuminant),
+                    );
                 let sc: Spectrum = api_state
                     .graphics_state
                     .area_light_params
-                    .find_one_spectrum("scale", Spectrum::new(1.0));
+                    .find_one_spectrum("scale", Spectrum::new(1.0))
+                    * find_light_tint(&api_state.graphics_state.area_light_params);
                 let n_samples: i32 = // try "nsamples" first
                     api_state.graphics_state.area_light_params.find_one_int("nsamples",
                                                                   1);
-                let n_samples: i32 = // try "samples"next
+                let mut n_samples: i32 = // try "samples"next
                     api_state.graphics_state.area_light_params.find_one_int("samples",
                                                                   n_samples);
                 let two_sided: bool = api_state
                     .graphics_state
                     .area_light_params
                     .find_one_bool("twosided", false);
-                // TODO: if (PbrtOptions.quickRender) nSamples = std::max(1, nSamples / 4);
+                if api_state.render_options.quick_render {
+                    n_samples = std::cmp::max(1, n_samples / 4);
+                }
                 let l_emit: Spectrum = l * sc;
                 let area_light: Arc<Light> = Arc::new(Light::DiffuseArea(DiffuseAreaLight::new(
                     &light_to_world,
@@ -2762,40 +3219,52 @@ pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params:
                     two_sided,
                 )));
                 area_lights.push(area_light.clone());
-                let geo_prim = Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+                let mut geo_prim: GeometricPrimitive = GeometricPrimitive::new(
                     shape.clone(),
                     material.clone(),
                     Some(area_light.clone()),
                     Some(Arc::new(mi.clone())),
-                )));
+                );
+                geo_prim.material_stats_id = material_stats_id;
+                let geo_prim = Arc::new(Primitive::Geometric(geo_prim));
                 prims.push(geo_prim.clone());
             }
+            report_unused(
+                api_state,
+                &format!(
+                    "AreaLightSource \"{}\"",
+                    api_state.graphics_state.area_light
+                ),
+                api_state.graphics_state.area_light_params.report_unused(),
+            );
+            // area lights get created for each shape, warn only once
+            api_state.graphics_state.area_light_params.mark_looked_up();
         }
     } else {
         // continue with shape itself
-        let (shapes, materials) = get_shapes_and_materials(&api_state, bsdf_state);
+        let (shapes, materials) = get_shapes_and_materials(api_state, bsdf_state);
+        let material_stats_id: u32 = material_stats_id(api_state);
         assert_eq!(shapes.len(), materials.len());
         // MediumInterface
         let mi: MediumInterface = create_medium_interface(&api_state);
         for i in 0..shapes.len() {
             let shape = &shapes[i];
             let material = &materials[i];
-            let geo_prim = Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+            let mut geo_prim: GeometricPrimitive = GeometricPrimitive::new(
                 shape.clone(),
                 material.clone(),
                 None,
                 Some(Arc::new(mi.clone())),
-            )));
+            );
+            geo_prim.material_stats_id = material_stats_id;
+            let geo_prim = Arc::new(Primitive::Geometric(geo_prim));
             prims.push(geo_prim.clone());
         }
         // animated?
         if api_state.cur_transform.is_animated() {
-            let animated_object_to_world: AnimatedTransform = AnimatedTransform::new(
-                &api_state.cur_transform.t[0],
-                api_state.render_options.transform_start_time,
-                &api_state.cur_transform.t[1],
-                api_state.render_options.transform_end_time,
-            );
+            let cur_transform: TransformSet = api_state.cur_transform;
+            let animated_object_to_world: Arc<AnimatedTransform> =
+                api_state.render_options.animated_transform(&cur_transform);
             if prims.len() > 1 {
                 let bvh: Arc<Primitive> = Arc::new(Primitive::BVH(BVHAccel::new(
                     prims.clone(),
@@ -2838,6 +3307,11 @@ pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params:
             }
         }
     }
+    report_unused(
+        api_state,
+        &format!("Shape \"{}\"", api_state.param_set.name),
+        api_state.param_set.report_unused(),
+    );
 }
 
 // Attempt to determine if the ParamSet for a shape may provide a value for
@@ -2954,6 +3428,7 @@ pub fn pbrt_object_instance(api_state: &mut ApiState, params: ParamSet) {
         println!("ERROR: ObjectInstance can't be called inside instance definition");
         return;
     }
+    let instance: Arc<Primitive>;
     if let Some(instance_vec) = api_state
         .render_options
         .instances
@@ -2962,50 +3437,15 @@ pub fn pbrt_object_instance(api_state: &mut ApiState, params: ParamSet) {
         if instance_vec.is_empty() {
             return;
         }
-        // TODO: ++nObjectInstancesUsed;
+        crate::stat_inc!(N_OBJECT_INSTANCES_USED);
         if instance_vec.len() > 1_usize {
-            // create aggregate for instance _Primitive_s
-            if api_state.render_options.accelerator_name == "bvh" {
-                //  CreateBVHAccelerator
-                let split_method_name: String = api_state
-                    .render_options
-                    .accelerator_params
-                    .find_one_string("splitmethod", String::from("sah"));
-                let split_method;
-                if split_method_name == "sah" {
-                    split_method = SplitMethod::SAH;
-                } else if split_method_name == "hlbvh" {
-                    split_method = SplitMethod::HLBVH;
-                } else if split_method_name == "middle" {
-                    split_method = SplitMethod::Middle;
-                } else if split_method_name == "equal" {
-                    split_method = SplitMethod::EqualCounts;
-                } else {
-                    println!(
-                        "WARNING: BVH split method \"{}\" unknown.  Using \"sah\".",
-                        split_method_name
-                    );
-                    split_method = SplitMethod::SAH;
-                }
-                let max_prims_in_node: i32 = api_state
-                    .render_options
-                    .accelerator_params
-                    .find_one_int("maxnodeprims", 4);
-                let accelerator: Arc<Primitive> = Arc::new(Primitive::BVH(BVHAccel::new(
-                    instance_vec.clone(),
-                    max_prims_in_node as usize,
-                    split_method,
-                )));
-                instance_vec.clear();
-                instance_vec.push(accelerator);
-            } else if api_state.render_options.accelerator_name == "kdtree" {
-                // println!("TODO: CreateKdTreeAccelerator");
-                // WARNING: Use BVHAccel for now !!!
-                let accelerator: Arc<Primitive> = Arc::new(Primitive::BVH(BVHAccel::new(
-                    instance_vec.clone(),
-                    4,
-                    SplitMethod::SAH,
-                )));
+            // create aggregate for instance _Primitive_s (only once,
+            // all instances share it)
+            if let Some(accelerator) = make_accelerator(
+                &api_state.render_options.accelerator_name,
+                instance_vec,
+                &api_state.render_options.accelerator_params,
+            ) {
                 instance_vec.clear();
                 instance_vec.push(accelerator);
             } else {
@@ -3015,18 +3455,7 @@ pub fn pbrt_object_instance(api_state: &mut ApiState, params: ParamSet) {
                 );
             }
         }
-        // create _animatedInstanceToWorld_ transform for instance
-        let animated_instance_to_world: AnimatedTransform = AnimatedTransform::new(
-            &api_state.cur_transform.t[0],
-            api_state.render_options.transform_start_time,
-            &api_state.cur_transform.t[1],
-            api_state.render_options.transform_end_time,
-        );
-        let prim: Arc<Primitive> = Arc::new(Primitive::Transformed(TransformedPrimitive::new(
-            instance_vec[0].clone(),
-            animated_instance_to_world,
-        )));
-        api_state.render_options.primitives.push(prim.clone());
+        instance = instance_vec[0].clone();
     } else {
         println!(
             "ERROR: Unable to find instance named {:?}",
@@ -3034,4 +3463,630 @@ pub fn pbrt_object_instance(api_state: &mut ApiState, params: ParamSet) {
         );
         return;
     }
+    // create _animatedInstanceToWorld_ transform for instance
+    let cur_transform: TransformSet = api_state.cur_transform;
+    let animated_instance_to_world: Arc<AnimatedTransform> =
+        api_state.render_options.animated_transform(&cur_transform);
+    let prim: Arc<Primitive> = Arc::new(Primitive::Transformed(TransformedPrimitive::new(
+        instance,
+        animated_instance_to_world,
+    )));
+    api_state.render_options.primitives.push(prim.clone());
+}
+
+/// Builds scenes from code. There is one method for each statement of
+/// the scene file format (and each **pbrt_...()** function), and the
+/// scene file parser of **rs_pbrt** uses the same methods, so scenes
+/// built either way behave the same. Methods return the builder, so
+/// calls can be chained.
+///
+/// A Cornell box, without any files involved:
+///
+/// ```rust
+/// use pbrt::core::api::SceneBuilder;
+/// use pbrt::core::geometry::Point3f;
+/// use pbrt::core::integrator::{Integrator, SamplerRender};
+/// use pbrt::core::paramset::ParamSet;
+/// use pbrt::core::pbrt::Float;
+///
+/// fn params(name: &str) -> ParamSet {
+///     let mut params = ParamSet::default();
+///     params.name = String::from(name);
+///     params
+/// }
+///
+/// fn matte(r: Float, g: Float, b: Float) -> ParamSet {
+///     let mut material = params("matte");
+///     material.add_rgb(String::from("Kd"), [r, g, b]);
+///     material
+/// }
+///
+/// fn quad(corners: [Point3f; 4]) -> ParamSet {
+///     let mut mesh = params("trianglemesh");
+///     mesh.add_ints(String::from("indices"), vec![0, 1, 2, 0, 2, 3]);
+///     let mut p: Vec<Float> = Vec::new();
+///     for c in &corners {
+///         p.extend_from_slice(&[c.x, c.y, c.z]);
+///     }
+///     mesh.add_point3fs(String::from("P"), p);
+///     mesh
+/// }
+///
+/// fn pnt(x: Float, y: Float, z: Float) -> Point3f {
+///     Point3f { x, y, z }
+/// }
+///
+/// let (xres, yres): (i32, i32) = (16, 16);
+/// let mut builder = SceneBuilder::new(1);
+/// let mut film = params("image");
+/// film.add_int(String::from("xresolution"), xres);
+/// film.add_int(String::from("yresolution"), yres);
+/// let path = std::env::temp_dir().join("pbrt_cornell_box.exr");
+/// film.add_string(String::from("filename"), path.to_str().unwrap().to_string());
+/// let mut sampler = params("random");
+/// sampler.add_int(String::from("pixelsamples"), 16);
+/// let mut camera = params("perspective");
+/// camera.add_float(String::from("fov"), 50.0);
+/// let mut light = params("diffuse");
+/// light.add_rgb(String::from("L"), [8.0, 8.0, 8.0]);
+/// light.add_bool(String::from("twosided"), true);
+/// builder
+///     .film(film)
+///     .sampler(sampler)
+///     .integrator(params("path"))
+///     .look_at(0.5, 0.5, -1.0, 0.5, 0.5, 0.5, 0.0, 1.0, 0.0)
+///     .camera(camera)
+///     .world_begin()
+///     // red left wall, green right wall
+///     .attribute_begin()
+///     .material(matte(0.6, 0.05, 0.05))
+///     .shape(quad([pnt(0.0, 0.0, 0.0), pnt(0.0, 0.0, 1.0), pnt(0.0, 1.0, 1.0), pnt(0.0, 1.0, 0.0)]))
+///     .material(matte(0.05, 0.6, 0.05))
+///     .shape(quad([pnt(1.0, 0.0, 0.0), pnt(1.0, 1.0, 0.0), pnt(1.0, 1.0, 1.0), pnt(1.0, 0.0, 1.0)]))
+///     .attribute_end()
+///     // white floor, ceiling and back wall
+///     .material(matte(0.6, 0.6, 0.6))
+///     .shape(quad([pnt(0.0, 0.0, 0.0), pnt(1.0, 0.0, 0.0), pnt(1.0, 0.0, 1.0), pnt(0.0, 0.0, 1.0)]))
+///     .shape(quad([pnt(0.0, 1.0, 0.0), pnt(0.0, 1.0, 1.0), pnt(1.0, 1.0, 1.0), pnt(1.0, 1.0, 0.0)]))
+///     .shape(quad([pnt(0.0, 0.0, 1.0), pnt(1.0, 0.0, 1.0), pnt(1.0, 1.0, 1.0), pnt(0.0, 1.0, 1.0)]))
+///     // a light just below the ceiling
+///     .attribute_begin()
+///     .area_light_source(light)
+///     .shape(quad([pnt(0.35, 0.99, 0.35), pnt(0.65, 0.99, 0.35), pnt(0.65, 0.99, 0.65), pnt(0.35, 0.99, 0.65)]))
+///     .attribute_end();
+/// let (scene, render_options) = builder.build();
+/// let mut integrator = render_options.make_integrator(None).unwrap();
+/// integrator.render(&scene, 1);
+/// let rgb: Vec<Float> = match *integrator {
+///     Integrator::Sampler(ref integrator) => integrator.get_camera().get_film().get_rgb(1.0),
+///     _ => unreachable!(),
+/// };
+/// // sum up the leftmost and rightmost columns
+/// let (mut left, mut right) = ([0.0 as Float; 3], [0.0 as Float; 3]);
+/// for y in 0..yres {
+///     for c in 0..3 {
+///         left[c] += rgb[3 * (y * xres) as usize + c];
+///         right[c] += rgb[3 * (y * xres + xres - 1) as usize + c];
+///     }
+/// }
+/// assert!(left[0] > 2.0 * left[1] && left[0] > 2.0 * left[2]);
+/// assert!(right[1] > 2.0 * right[0] && right[1] > 2.0 * right[2]);
+/// ```
+pub struct SceneBuilder {
+    pub api_state: ApiState,
+    pub bsdf_state: BsdfState,
+    recording: Option<SceneCache>,
+}
+
+impl SceneBuilder {
+    pub fn new(number_of_threads: u8) -> Self {
+        let (api_state, bsdf_state) = pbrt_init(number_of_threads);
+        SceneBuilder {
+            api_state,
+            bsdf_state,
+            recording: None,
+        }
+    }
+    /// Records all following statements (but not the objects made in
+    /// code, like **add_mesh()**) until **take_recording()** gets
+    /// called, see **SceneCache::replay()**.
+    pub fn start_recording(&mut self) -> &mut Self {
+        self.recording = Some(SceneCache::default());
+        self
+    }
+    /// Stops recording and returns the statements recorded so far.
+    pub fn take_recording(&mut self) -> Option<SceneCache> {
+        self.recording.take()
+    }
+    /// Remembers (while recording) that the statements were read from
+    /// _path_, so a cached recording can be checked against changes.
+    pub fn record_source(&mut self, path: &Path) -> &mut Self {
+        if let Some(recording) = &mut self.recording {
+            if let Err(e) = recording.add_source(path) {
+                println!("WARNING: can't hash {:?}: {}", path, e);
+            }
+        }
+        self
+    }
+    fn record<F>(&mut self, statement: F)
+    where
+        F: FnOnce() -> Statement,
+    {
+        if let Some(recording) = &mut self.recording {
+            recording.statements.push(statement());
+        }
+    }
+    /// Directory relative file names (textures, meshes, ...) are
+    /// resolved against.
+    pub fn search_directory(&mut self, search_directory: PathBuf) -> &mut Self {
+        self.record(|| Statement::SearchDirectory(search_directory.clone()));
+        self.api_state.search_directory = Some(Box::new(search_directory));
+        self
+    }
+    // transformations
+    pub fn translate(&mut self, dx: Float, dy: Float, dz: Float) -> &mut Self {
+        self.record(|| Statement::Translate(dx, dy, dz));
+        pbrt_translate(&mut self.api_state, dx, dy, dz);
+        self
+    }
+    pub fn transform(&mut self, tr: &Transform) -> &mut Self {
+        self.record(|| Statement::Transform(*tr));
+        pbrt_transform(&mut self.api_state, tr);
+        self
+    }
+    pub fn concat_transform(&mut self, tr: &Transform) -> &mut Self {
+        self.record(|| Statement::ConcatTransform(*tr));
+        pbrt_concat_transform(&mut self.api_state, tr);
+        self
+    }
+    pub fn rotate(&mut self, angle: Float, dx: Float, dy: Float, dz: Float) -> &mut Self {
+        self.record(|| Statement::Rotate(angle, dx, dy, dz));
+        pbrt_rotate(&mut self.api_state, angle, dx, dy, dz);
+        self
+    }
+    pub fn scale(&mut self, sx: Float, sy: Float, sz: Float) -> &mut Self {
+        self.record(|| Statement::Scale(sx, sy, sz));
+        pbrt_scale(&mut self.api_state, sx, sy, sz);
+        self
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn look_at(
+        &mut self,
+        ex: Float,
+        ey: Float,
+        ez: Float,
+        lx: Float,
+        ly: Float,
+        lz: Float,
+        ux: Float,
+        uy: Float,
+        uz: Float,
+    ) -> &mut Self {
+        self.record(|| Statement::LookAt([ex, ey, ez, lx, ly, lz, ux, uy, uz]));
+        pbrt_look_at(&mut self.api_state, ex, ey, ez, lx, ly, lz, ux, uy, uz);
+        self
+    }
+    pub fn coordinate_system(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::CoordinateSystem(params.clone()));
+        pbrt_coordinate_system(&mut self.api_state, params);
+        self
+    }
+    pub fn coord_sys_transform(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::CoordSysTransform(params.clone()));
+        pbrt_coord_sys_transform(&mut self.api_state, params);
+        self
+    }
+    pub fn active_transform_all(&mut self) -> &mut Self {
+        self.record(|| Statement::ActiveTransformAll);
+        pbrt_active_transform_all(&mut self.api_state);
+        self
+    }
+    pub fn active_transform_end_time(&mut self) -> &mut Self {
+        self.record(|| Statement::ActiveTransformEndTime);
+        pbrt_active_transform_end_time(&mut self.api_state);
+        self
+    }
+    pub fn active_transform_start_time(&mut self) -> &mut Self {
+        self.record(|| Statement::ActiveTransformStartTime);
+        pbrt_active_transform_start_time(&mut self.api_state);
+        self
+    }
+    pub fn transform_times(&mut self, start: Float, end: Float) -> &mut Self {
+        self.record(|| Statement::TransformTimes(start, end));
+        pbrt_transform_times(&mut self.api_state, start, end);
+        self
+    }
+    // rendering options
+    pub fn pixel_filter(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::PixelFilter(params.clone()));
+        pbrt_pixel_filter(&mut self.api_state, params);
+        self
+    }
+    pub fn film(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::Film(params.clone()));
+        pbrt_film(&mut self.api_state, params);
+        self
+    }
+    pub fn sampler(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::Sampler(params.clone()));
+        pbrt_sampler(&mut self.api_state, params);
+        self
+    }
+    pub fn accelerator(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::Accelerator(params.clone()));
+        pbrt_accelerator(&mut self.api_state, params);
+        self
+    }
+    pub fn integrator(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::Integrator(params.clone()));
+        pbrt_integrator(&mut self.api_state, params);
+        self
+    }
+    pub fn camera(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::Camera(params.clone()));
+        pbrt_camera(&mut self.api_state, params);
+        self
+    }
+    pub fn make_named_medium(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::MakeNamedMedium(params.clone()));
+        pbrt_make_named_medium(&mut self.api_state, params);
+        self
+    }
+    pub fn medium_interface(&mut self, inside_name: &str, outside_name: &str) -> &mut Self {
+        self.record(|| {
+            Statement::MediumInterface(String::from(inside_name), String::from(outside_name))
+        });
+        pbrt_medium_interface(
+            &mut self.api_state,
+            &String::from(inside_name),
+            &String::from(outside_name),
+        );
+        self
+    }
+    // scene description
+    pub fn world_begin(&mut self) -> &mut Self {
+        self.record(|| Statement::WorldBegin);
+        pbrt_world_begin(&mut self.api_state);
+        self
+    }
+    pub fn attribute_begin(&mut self) -> &mut Self {
+        self.record(|| Statement::AttributeBegin);
+        pbrt_attribute_begin(&mut self.api_state);
+        self
+    }
+    pub fn attribute_end(&mut self) -> &mut Self {
+        self.record(|| Statement::AttributeEnd);
+        pbrt_attribute_end(&mut self.api_state);
+        self
+    }
+    pub fn transform_begin(&mut self) -> &mut Self {
+        self.record(|| Statement::TransformBegin);
+        pbrt_transform_begin(&mut self.api_state);
+        self
+    }
+    pub fn transform_end(&mut self) -> &mut Self {
+        self.record(|| Statement::TransformEnd);
+        pbrt_transform_end(&mut self.api_state);
+        self
+    }
+    pub fn texture(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::Texture(params.clone()));
+        pbrt_texture(&mut self.api_state, params);
+        self
+    }
+    pub fn material(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::Material(params.clone()));
+        pbrt_material(&mut self.api_state, params);
+        self
+    }
+    pub fn make_named_material(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::MakeNamedMaterial(params.clone()));
+        pbrt_make_named_material(&mut self.api_state, &mut self.bsdf_state, params);
+        self
+    }
+    pub fn named_material(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::NamedMaterial(params.clone()));
+        pbrt_named_material(&mut self.api_state, params);
+        self
+    }
+    pub fn light_source(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::LightSource(params.clone()));
+        pbrt_light_source(&mut self.api_state, params);
+        self
+    }
+    pub fn area_light_source(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::AreaLightSource(params.clone()));
+        pbrt_area_light_source(&mut self.api_state, params);
+        self
+    }
+    pub fn shape(&mut self, params: ParamSet) -> &mut Self {
+        // record the contents of PLY files, so replaying doesn't read them
+        let params: ParamSet = if self.recording.is_some()
+            && params.name == "plymesh"
+            && params.find_point3f("P").is_empty()
+        {
+            let filename: String = ply_file_name(&params, self.api_state.search_directory.as_deref());
+            self.record_source(Path::new(&filename));
+            inline_ply_mesh(&params, &filename)
+        } else {
+            params
+        };
+        self.record(|| Statement::Shape(params.clone()));
+        pbrt_shape(&mut self.api_state, &mut self.bsdf_state, params);
+        self
+    }
+    pub fn reverse_orientation(&mut self) -> &mut Self {
+        self.record(|| Statement::ReverseOrientation);
+        pbrt_reverse_orientation(&mut self.api_state);
+        self
+    }
+    pub fn object_begin(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::ObjectBegin(params.clone()));
+        pbrt_object_begin(&mut self.api_state, params);
+        self
+    }
+    pub fn object_end(&mut self) -> &mut Self {
+        self.record(|| Statement::ObjectEnd);
+        pbrt_object_end(&mut self.api_state);
+        self
+    }
+    pub fn object_instance(&mut self, params: ParamSet) -> &mut Self {
+        self.record(|| Statement::ObjectInstance(params.clone()));
+        pbrt_object_instance(&mut self.api_state, params);
+        self
+    }
+    // made in code
+    /// Adds the triangles of _mesh_ (with vertices in world space),
+    /// e.g. a single triangle lit by a point light:
+    ///
+    /// ```rust
+    /// use pbrt::cameras::perspective::PerspectiveCamera;
+    /// use pbrt::core::api::SceneBuilder;
+    /// use pbrt::core::film::Film;
+    /// use pbrt::core::geometry::{Point3f, Vector3f};
+    /// use pbrt::core::integrator::{Integrator, SamplerRender};
+    /// use pbrt::core::light::Light;
+    /// use pbrt::core::material::Material;
+    /// use pbrt::core::medium::MediumInterface;
+    /// use pbrt::core::paramset::ParamSet;
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::transform::{AnimatedTransform, Transform};
+    /// use pbrt::filters::boxfilter::BoxFilter;
+    /// use pbrt::lights::point::PointLight;
+    /// use pbrt::materials::matte::MatteMaterial;
+    /// use pbrt::shapes::triangle::TriangleMesh;
+    /// use pbrt::textures::constant::ConstantTexture;
+    /// use std::sync::Arc;
+    ///
+    /// let (xres, yres): (i32, i32) = (8, 8);
+    /// let mut film_params = ParamSet::default();
+    /// film_params.add_int(String::from("xresolution"), xres);
+    /// film_params.add_int(String::from("yresolution"), yres);
+    /// let path = std::env::temp_dir().join("pbrt_single_triangle.exr");
+    /// film_params.add_string(String::from("filename"), path.to_str().unwrap().to_string());
+    /// let film = Film::create(&film_params, BoxFilter::create(&ParamSet::default()));
+    /// // at the origin, looking down the z axis
+    /// let camera_to_world = AnimatedTransform::new(
+    ///     &Transform::default(),
+    ///     0.0 as Float,
+    ///     &Transform::default(),
+    ///     1.0 as Float,
+    /// );
+    /// let camera = PerspectiveCamera::create(&ParamSet::default(), camera_to_world, film, None, None);
+    /// // one triangle, covering the center of the image
+    /// let p: Vec<Point3f> = vec![
+    ///     Point3f { x: -2.0, y: -2.0, z: 2.0 },
+    ///     Point3f { x: 2.0, y: -2.0, z: 2.0 },
+    ///     Point3f { x: 0.0, y: 2.0, z: 2.0 },
+    /// ];
+    /// let mesh = Arc::new(TriangleMesh::new(
+    ///     Transform::default(),
+    ///     Transform::default(),
+    ///     false,
+    ///     1,
+    ///     vec![0, 1, 2],
+    ///     3,
+    ///     p,
+    ///     Vec::new(),
+    ///     Vec::new(),
+    ///     Vec::new(),
+    ///     None,
+    ///     None,
+    /// ));
+    /// let matte = Arc::new(Material::Matte(MatteMaterial::new(
+    ///     Arc::new(ConstantTexture::new(Spectrum::new(0.5 as Float))),
+    ///     Arc::new(ConstantTexture::new(0.0 as Float)),
+    ///     None,
+    /// )));
+    /// let light = Arc::new(Light::Point(PointLight::new(
+    ///     &Transform::translate(&Vector3f { x: 0.0, y: 0.0, z: 1.0 }),
+    ///     &MediumInterface::default(),
+    ///     &Spectrum::new(10.0 as Float),
+    /// )));
+    /// let mut integrator = ParamSet::default();
+    /// integrator.add_int(String::from("maxdepth"), 1);
+    /// let mut sampler = ParamSet::default();
+    /// sampler.name = String::from("random");
+    /// sampler.add_int(String::from("pixelsamples"), 4);
+    /// let mut builder = SceneBuilder::new(1);
+    /// builder
+    ///     .sampler(sampler)
+    ///     .set_camera(camera)
+    ///     .set_integrator("path", integrator)
+    ///     .add_mesh(mesh, Some(matte))
+    ///     .add_light(light);
+    /// let (scene, render_options) = builder.build();
+    /// let mut integrator = render_options.make_integrator(None).unwrap();
+    /// integrator.render(&scene, 1);
+    /// let rgb: Vec<Float> = match *integrator {
+    ///     Integrator::Sampler(ref integrator) => integrator.get_camera().get_film().get_rgb(1.0),
+    ///     _ => unreachable!(),
+    /// };
+    /// // lit triangle in the center, black background in the corners
+    /// let center: usize = 3 * ((yres / 2) * xres + xres / 2) as usize;
+    /// assert!(rgb[center] > 0.0);
+    /// assert_eq!(rgb[0], 0.0);
+    /// ```
+    pub fn add_mesh(
+        &mut self,
+        mesh: Arc<TriangleMesh>,
+        material: Option<Arc<Material>>,
+    ) -> &mut Self {
+        for id in 0..mesh.n_triangles {
+            if mesh.is_degenerate_triangle(id) {
+                continue;
+            }
+            let triangle = Arc::new(Shape::Trngl(Triangle::new(mesh.clone(), id)));
+            self.add_primitive(Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+                triangle,
+                material.clone(),
+                None,
+                None,
+            ))));
+        }
+        self
+    }
+    /// Adds the meshes of an OBJ file (see **load_obj()**) placed by
+    /// _object_to_world_. Faces without a material from the MTL file
+    /// get the current material.
+    ///
+    /// A cube with a different material on each pair of opposite
+    /// faces, one of them textured (*map_Kd*):
+    ///
+    /// ```rust
+    /// use pbrt::core::api::SceneBuilder;
+    /// use pbrt::core::material::Material;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::shapes::objmesh::load_obj;
+    ///
+    /// let directory = std::env::temp_dir().join("pbrt_add_obj");
+    /// std::fs::create_dir_all(&directory).unwrap();
+    /// image::RgbImage::from_pixel(2, 2, image::Rgb([255, 128, 0]))
+    ///     .save(directory.join("orange.png"))
+    ///     .unwrap();
+    /// std::fs::write(
+    ///     directory.join("cube.mtl"),
+    ///     "newmtl red\nKd 0.8 0.1 0.1\n\
+    ///      newmtl shiny\nKd 0.1 0.1 0.8\nKs 0.5 0.5 0.5\nNs 100\n\
+    ///      newmtl orange\nmap_Kd orange.png\n",
+    /// )
+    /// .unwrap();
+    /// std::fs::write(
+    ///     directory.join("cube.obj"),
+    ///     "mtllib cube.mtl\n\
+    ///      v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+    ///      v 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+    ///      vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+    ///      usemtl red\nf 1/1 4/4 3/3 2/2\n\
+    ///      usemtl shiny\nf 1/1 2/2 6/3 5/4\n\
+    ///      usemtl orange\nf 1/1 5/2 8/3 4/4\n\
+    ///      usemtl red\nf 5/1 6/2 7/3 8/4\n\
+    ///      usemtl shiny\nf 4/1 8/2 7/3 3/4\n\
+    ///      usemtl orange\nf 2/1 3/2 7/3 6/4\n",
+    /// )
+    /// .unwrap();
+    /// let path = directory.join("cube.obj");
+    /// // one mesh per material, with two triangles per face
+    /// let meshes = load_obj(&path, &Transform::default(), false).unwrap();
+    /// assert_eq!(meshes.len(), 3);
+    /// for (mesh, triangles, _material) in meshes.iter() {
+    ///     assert_eq!((mesh.n_triangles, triangles.len()), (4, 4));
+    /// }
+    /// let materials: Vec<&Material> = meshes.iter().map(|m| &**m.2.as_ref().unwrap()).collect();
+    /// match (materials[0], materials[1], materials[2]) {
+    ///     (Material::Matte(_), Material::Plastic(_), Material::Matte(_)) => {}
+    ///     _ => panic!("unexpected materials"),
+    /// }
+    /// let mut builder = SceneBuilder::new(1);
+    /// builder.world_begin();
+    /// builder.add_obj(&path, &Transform::scale(2.0, 2.0, 2.0)).unwrap();
+    /// std::fs::remove_dir_all(&directory).unwrap();
+    /// let (scene, _) = builder.build();
+    /// assert_eq!(scene.world_bound().p_max.z, 2.0);
+    /// ```
+    pub fn add_obj(
+        &mut self,
+        path: &Path,
+        object_to_world: &Transform,
+    ) -> std::io::Result<&mut Self> {
+        let meshes = load_obj(
+            path,
+            object_to_world,
+            self.api_state.graphics_state.reverse_orientation,
+        )?;
+        let material: Option<Arc<Material>> =
+            create_material(&self.api_state, &mut self.bsdf_state);
+        for (mesh, _triangles, obj_material) in meshes {
+            self.add_mesh(mesh, obj_material.or_else(|| material.clone()));
+        }
+        Ok(self)
+    }
+    /// Adds _prim_ placed by _primitive_to_world_, e.g. to use the
+    /// same (acceleration structure of a) mesh more than once.
+    pub fn add_instance(
+        &mut self,
+        prim: Arc<Primitive>,
+        primitive_to_world: &Transform,
+    ) -> &mut Self {
+        let animated_primitive_to_world: Arc<AnimatedTransform> = Arc::new(AnimatedTransform::new(
+            primitive_to_world,
+            self.api_state.render_options.transform_start_time,
+            primitive_to_world,
+            self.api_state.render_options.transform_end_time,
+        ));
+        self.add_primitive(Arc::new(Primitive::Transformed(TransformedPrimitive::new(
+            prim,
+            animated_primitive_to_world,
+        ))));
+        self
+    }
+    pub fn add_light(&mut self, light: Arc<Light>) -> &mut Self {
+        self.api_state.render_options.lights.push(light);
+        self
+    }
+    /// Uses _camera_ (and its film) instead of the Camera and Film
+    /// statements.
+    pub fn set_camera(&mut self, camera: Arc<Camera>) -> &mut Self {
+        self.api_state.render_options.camera = Some(camera);
+        self
+    }
+    /// Like **integrator()**, with the name of the integrator given
+    /// separately.
+    pub fn set_integrator(&mut self, name: &str, mut params: ParamSet) -> &mut Self {
+        params.name = String::from(name);
+        self.integrator(params)
+    }
+    fn add_primitive(&mut self, prim: Arc<Primitive>) {
+        let render_options: &mut RenderOptions = &mut self.api_state.render_options;
+        if render_options.current_instance != "" {
+            if let Some(instance_vec) = render_options
+                .instances
+                .get_mut(&render_options.current_instance)
+            {
+                instance_vec.push(prim);
+            }
+        } else {
+            render_options.primitives.push(prim);
+        }
+    }
+    /// Renders the scene (and writes the image), like the WorldEnd
+    /// statement.
+    pub fn world_end(&mut self) {
+        pbrt_cleanup(&self.api_state);
+    }
+    /// Creates the scene instead of rendering it. The returned
+    /// **RenderOptions** make the integrator (with camera, film and
+    /// sampler).
+    pub fn build(self) -> (Scene, RenderOptions) {
+        assert!(
+            self.api_state.pushed_graphics_states.is_empty(),
+            "Missing end to pbrtAttributeBegin()"
+        );
+        assert!(
+            self.api_state.pushed_transforms.is_empty(),
+            "Missing end to pbrtTransformBegin()"
+        );
+        let scene: Scene = self.api_state.render_options.make_scene();
+        (scene, self.api_state.render_options)
+    }
 }
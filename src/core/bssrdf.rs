@@ -13,7 +13,7 @@ use crate::core::geometry::{
 use crate::core::geometry::{Normal3f, Point2f, Point3f, Ray, Vector3f};
 use crate::core::interaction::{InteractionCommon, SurfaceInteraction};
 use crate::core::interpolation::{
-    catmull_rom_weights, integrate_catmull_rom, sample_catmull_rom_2d,
+    catmull_rom_weights, integrate_catmull_rom, invert_catmull_rom, sample_catmull_rom_2d,
 };
 use crate::core::material::{Material, TransportMode};
 use crate::core::medium::phase_hg;
@@ -54,7 +54,7 @@ impl TabulatedBssrdf {
     ) -> Self {
         let sigma_t: Spectrum = *sigma_a + *sigma_s;
         let mut rho: Spectrum = Spectrum::new(0.0 as Float);
-        for c in 0..3 {
+        for c in 0..Spectrum::N_SAMPLES {
             if sigma_t[c] != 0.0 as Float {
                 rho.c[c] = sigma_s[c] / sigma_t[c];
             } else {
@@ -113,9 +113,9 @@ impl TabulatedBssrdf {
         // return combined probability from all BSSRDF sampling strategies
         let mut pdf: Float = 0.0;
         let axis_prob: [Float; 3] = [0.25 as Float, 0.25 as Float, 0.5 as Float];
-        let ch_prob: Float = 1.0 as Float / 3.0 as Float;
+        let ch_prob: Float = 1.0 as Float / Spectrum::N_SAMPLES as Float;
         for axis in 0..3_usize {
-            for ch in 0..3_usize {
+            for ch in 0..Spectrum::N_SAMPLES {
                 pdf += self.pdf_sr(ch, r_proj[axis])
                     * n_local[axis as u8].abs()
                     * ch_prob
@@ -158,8 +158,12 @@ impl TabulatedBssrdf {
             u1 = (u1 - 0.75 as Float) * 4.0 as Float;
         }
         // choose spectral channel for BSSRDF sampling
-        let ch: usize = clamp_t((u1 * 3.0 as Float) as usize, 0_usize, 2_usize);
-        u1 = u1 * 3.0 as Float - ch as Float;
+        let ch: usize = clamp_t(
+            (u1 * Spectrum::N_SAMPLES as Float) as usize,
+            0_usize,
+            Spectrum::N_SAMPLES - 1,
+        );
+        u1 = u1 * Spectrum::N_SAMPLES as Float - ch as Float;
         // sample BSSRDF profile in polar coordinates
         let r: Float = self.sample_sr(ch, u2.x);
         if r < 0.0 as Float {
@@ -219,6 +223,7 @@ impl TabulatedBssrdf {
                             si_eval.p_error = si.p_error.clone();
                             si_eval.wo = si.wo.clone();
                             si_eval.n = si.n.clone();
+                            si_eval.check_geometric_normal = si.check_geometric_normal;
                             if let Some(medium_interface) = &si.medium_interface {
                                 Some(Arc::new(medium_interface.clone()));
                             } else {
@@ -331,7 +336,7 @@ impl TabulatedBssrdf {
     }
     pub fn sr(&self, r: Float) -> Spectrum {
         let mut sr: Spectrum = Spectrum::default();
-        for ch in 0..3_usize {
+        for ch in 0..Spectrum::N_SAMPLES {
             // convert $r$ into unitless optical radius $r_{\roman{optical}}$
             let r_optical: Float = r * self.sigma_t.c[ch];
             // compute spline weights to interpolate BSSRDF on channel _ch_
@@ -717,3 +722,55 @@ pub fn compute_beam_diffusion_bssrdf(g: Float, eta: Float, t: &mut BssrdfTable)
     }
     // }, t.n_rho_samples);
 }
+
+/// Inverts the diffusion approximation: finds the scattering and
+/// absorption coefficients which give the effective albedo _rho_eff_
+/// (the diffuse reflectance) for a mean free path _mfp_.
+///
+/// The albedo only depends on the reflectance, a longer mean free
+/// path makes the material more translucent:
+///
+/// ```rust
+/// use pbrt::core::bssrdf::{compute_beam_diffusion_bssrdf, subsurface_from_diffuse, BssrdfTable};
+/// use pbrt::core::pbrt::{Float, Spectrum};
+///
+/// let mut table = BssrdfTable::new(100, 64);
+/// compute_beam_diffusion_bssrdf(0.0, 1.33, &mut table);
+/// let coefficients = |kd: Float, mfp: Float| -> (Spectrum, Spectrum) {
+///     let mut sigma_a = Spectrum::default();
+///     let mut sigma_s = Spectrum::default();
+///     subsurface_from_diffuse(
+///         &table,
+///         &Spectrum::new(kd),
+///         &Spectrum::new(mfp),
+///         &mut sigma_a,
+///         &mut sigma_s,
+///     );
+///     (sigma_a, sigma_s)
+/// };
+/// let (sigma_a, sigma_s) = coefficients(0.5, 0.5);
+/// let (sigma_a_far, sigma_s_far) = coefficients(0.5, 2.0);
+/// let (sigma_a_bright, sigma_s_bright) = coefficients(0.8, 0.5);
+/// for c in 0..Spectrum::N_SAMPLES {
+///     let sigma_t: Float = sigma_a[c] + sigma_s[c];
+///     let sigma_t_far: Float = sigma_a_far[c] + sigma_s_far[c];
+///     assert!((1.0 / sigma_t - 0.5).abs() < 1e-4);
+///     assert!((1.0 / sigma_t_far - 2.0).abs() < 1e-4);
+///     assert!(sigma_a_far[c] < sigma_a[c] && sigma_s_far[c] < sigma_s[c]);
+///     assert!((sigma_s[c] / sigma_t - sigma_s_far[c] / sigma_t_far).abs() < 1e-5);
+///     assert!(sigma_s_bright[c] > sigma_s[c] && sigma_a_bright[c] < sigma_a[c]);
+/// }
+/// ```
+pub fn subsurface_from_diffuse(
+    t: &BssrdfTable,
+    rho_eff: &Spectrum,
+    mfp: &Spectrum,
+    sigma_a: &mut Spectrum,
+    sigma_s: &mut Spectrum,
+) {
+    for c in 0..Spectrum::N_SAMPLES {
+        let rho: Float = invert_catmull_rom(&t.rho_samples, &t.rho_eff, rho_eff[c]);
+        sigma_s[c] = rho / mfp[c];
+        sigma_a[c] = (1.0 as Float - rho) / mfp[c];
+    }
+}
@@ -13,6 +13,7 @@ use crate::core::film::Film;
 use crate::core::geometry::{Point2f, Ray, Vector3f};
 use crate::core::interaction::InteractionCommon;
 use crate::core::light::VisibilityTester;
+use crate::core::pbrt::lerp;
 use crate::core::pbrt::{Float, Spectrum};
 
 // see camera.h
@@ -65,6 +66,12 @@ impl Camera {
             Camera::Realistic(camera) => camera.sample_wi(iref, u, wi, pdf, p_raster, vis),
         }
     }
+    /// Maps a sample value _u_ in [0, 1) to a time within the shutter
+    /// interval. Primary rays get their time this way from the
+    /// sampler's _CameraSample::time_ (see generate_ray_differential()).
+    pub fn sample_time(&self, u: Float) -> Float {
+        lerp(u, self.get_shutter_open(), self.get_shutter_close())
+    }
     pub fn get_shutter_open(&self) -> Float {
         match self {
             Camera::Environment(camera) => camera.get_shutter_open(),
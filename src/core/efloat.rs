@@ -5,7 +5,7 @@
 
 // std
 use std;
-use std::ops::{Add, Div, Mul, Sub};
+use std::ops::{Add, Div, Mul, Neg, Sub};
 // pbrt
 use crate::core::pbrt::MACHINE_EPSILON;
 use crate::core::pbrt::{next_float_down, next_float_up};
@@ -14,6 +14,49 @@ use crate::core::pbrt::{next_float_down, next_float_up};
 
 /// Find solution(s) of the quadratic equation at<sup>2</sup> + bt + c = 0 using
 /// *EFloat* instead of *Float* for error bounds.
+///
+/// It finds solutions in exactly the same cases as the scalar
+/// **quadratic()**, and the intervals of _t0_ and _t1_ contain the
+/// exact roots:
+///
+/// ```rust
+/// use pbrt::core::efloat::{quadratic_efloat, EFloat};
+/// use pbrt::core::pbrt::quadratic;
+/// use pbrt::core::rng::Rng;
+///
+/// let mut rng: Rng = Rng::new();
+/// let mut n_solved: usize = 0;
+/// for _ in 0..10000 {
+///     let a: f32 = rng.uniform_float() * 10.0 + 0.1;
+///     let b: f32 = (rng.uniform_float() - 0.5) * 20.0;
+///     let c: f32 = (rng.uniform_float() - 0.5) * 20.0;
+///     let mut t0: EFloat = EFloat::default();
+///     let mut t1: EFloat = EFloat::default();
+///     let solved: bool = quadratic_efloat(
+///         EFloat::new(a, 0.0),
+///         EFloat::new(b, 0.0),
+///         EFloat::new(c, 0.0),
+///         &mut t0,
+///         &mut t1,
+///     );
+///     let (mut s0, mut s1): (f32, f32) = (0.0, 0.0);
+///     assert_eq!(solved, quadratic(a, b, c, &mut s0, &mut s1));
+///     if solved {
+///         n_solved += 1;
+///         // roots computed in double precision
+///         let (a, b, c): (f64, f64, f64) = (a as f64, b as f64, c as f64);
+///         let root_discrim: f64 = (b * b - 4.0 * a * c).sqrt();
+///         let q: f64 = -0.5 * (b + b.signum() * root_discrim);
+///         let (r0, r1): (f64, f64) = ((q / a).min(c / q), (q / a).max(c / q));
+///         assert!(t0.lower_bound() as f64 <= r0 && r0 <= t0.upper_bound() as f64);
+///         assert!(t1.lower_bound() as f64 <= r1 && r1 <= t1.upper_bound() as f64);
+///         assert!(t0.lower_bound() <= s0 && s0 <= t0.upper_bound());
+///         assert!(t1.lower_bound() <= s1 && s1 <= t1.upper_bound());
+///     }
+/// }
+/// // both cases (real roots or none) were covered
+/// assert!(n_solved > 1000 && n_solved < 9000);
+/// ```
 pub fn quadratic_efloat(a: EFloat, b: EFloat, c: EFloat, t0: &mut EFloat, t1: &mut EFloat) -> bool {
     let discrim: f64 = b.v as f64 * b.v as f64 - 4.0f64 * a.v as f64 * c.v as f64;
     if discrim < 0.0 {
@@ -44,31 +87,140 @@ pub fn quadratic_efloat(a: EFloat, b: EFloat, c: EFloat, t0: &mut EFloat, t1: &m
 
 /// **EFloat** keeps track of an interval that describes the
 /// uncertainty of a value of interest.
+///
+/// Every operation rounds the bounds of the result outwards, so the
+/// interval always contains the value an exact computation would have
+/// produced. In debug builds this is checked against a more precise
+/// value, carried along in double precision:
+///
+/// ```rust
+/// use pbrt::core::efloat::EFloat;
+/// use pbrt::core::rng::Rng;
+///
+/// let mut rng: Rng = Rng::new();
+/// let mut random = || -> f32 { (rng.uniform_float() - 0.5) * 2.0e3 };
+/// for _ in 0..10000 {
+///     let (a, b, c, d) = (random(), random(), random(), random());
+///     let (ea, eb, ec, ed) = (
+///         EFloat::new(a, 0.0),
+///         EFloat::new(b, 0.0),
+///         EFloat::new(c, 0.0),
+///         EFloat::new(d, 0.0),
+///     );
+///     let (a, b, c, d) = (a as f64, b as f64, c as f64, d as f64);
+///     let checks: [(EFloat, f64); 6] = [
+///         (ea + eb * ec, a + b * c),
+///         ((ea - eb) / ec, (a - b) / c),
+///         ((ea * ea + eb * eb).sqrt(), (a * a + b * b).sqrt()),
+///         ((ea - ed).abs() * ec, (a - d).abs() * c),
+///         (-(ea * eb) + ec * ed, -(a * b) + c * d),
+///         ((ea * eb - ec * ed) * 2.0 / (ed * ed), (a * b - c * d) * 2.0 / (d * d)),
+///     ];
+///     for (e, exact) in checks.iter() {
+///         assert!(e.lower_bound() as f64 <= *exact && *exact <= e.upper_bound() as f64);
+///         assert!((e.v as f64 - exact).abs() <= e.get_absolute_error() as f64);
+///     }
+/// }
+/// ```
 #[derive(Debug, Default, Copy, Clone)]
 pub struct EFloat {
     pub v: f32,
     pub low: f32,
     pub high: f32,
+    #[cfg(debug_assertions)]
+    pub v_precise: f64,
 }
 
 impl EFloat {
     pub fn new(v: f32, err: f32) -> Self {
         if err == 0.0 {
-            EFloat { v, low: v, high: v }
+            EFloat::from_bounds(v, v, v, v as f64)
         } else {
-            EFloat {
+            EFloat::from_bounds(
                 v,
-                low: next_float_down(v - err),
-                high: next_float_up(v + err),
+                next_float_down(v - err),
+                next_float_up(v + err),
+                v as f64,
+            )
+        }
+    }
+    // the precise value is only stored (and checked) in debug builds
+    #[allow(unused_variables)]
+    fn from_bounds(v: f32, low: f32, high: f32, v_precise: f64) -> Self {
+        let r: EFloat = EFloat {
+            v,
+            low,
+            high,
+            #[cfg(debug_assertions)]
+            v_precise,
+        };
+        r.check();
+        r
+    }
+    fn check(&self) {
+        if self.low.is_finite() && self.high.is_finite() {
+            debug_assert!(self.low <= self.high);
+        }
+        #[cfg(debug_assertions)]
+        {
+            if self.v.is_finite() && !self.low.is_nan() && !self.high.is_nan() {
+                debug_assert!(self.low as f64 <= self.v_precise);
+                debug_assert!(self.v_precise <= self.high as f64);
             }
         }
     }
+    fn precise(&self) -> f64 {
+        #[cfg(debug_assertions)]
+        {
+            self.v_precise
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            self.v as f64
+        }
+    }
     pub fn lower_bound(&self) -> f32 {
         self.low
     }
     pub fn upper_bound(&self) -> f32 {
         self.high
     }
+    /// Upper bound of the distance between the value and the exact
+    /// result.
+    pub fn get_absolute_error(&self) -> f32 {
+        next_float_up((self.high - self.v).abs().max((self.v - self.low).abs()))
+    }
+    /// Relative error of the value compared to the more precise value
+    /// (only available in debug builds).
+    #[cfg(debug_assertions)]
+    pub fn get_relative_error(&self) -> f64 {
+        ((self.v_precise - self.v as f64) / self.v_precise).abs()
+    }
+    pub fn sqrt(self) -> EFloat {
+        EFloat::from_bounds(
+            self.v.sqrt(),
+            next_float_down(self.low.sqrt()),
+            next_float_up(self.high.sqrt()),
+            self.precise().sqrt(),
+        )
+    }
+    pub fn abs(self) -> EFloat {
+        if self.low >= 0.0 {
+            // the entire interval is greater than zero, so we're all set
+            self
+        } else if self.high <= 0.0 {
+            // the entire interval is less than zero
+            -self
+        } else {
+            // the interval straddles zero
+            EFloat::from_bounds(
+                self.v.abs(),
+                0.0,
+                (-self.low).max(self.high),
+                self.precise().abs(),
+            )
+        }
+    }
 }
 
 impl PartialEq for EFloat {
@@ -77,27 +229,40 @@ impl PartialEq for EFloat {
     }
 }
 
+impl From<EFloat> for f32 {
+    fn from(e: EFloat) -> f32 {
+        e.v
+    }
+}
+
+impl Neg for EFloat {
+    type Output = EFloat;
+    fn neg(self) -> EFloat {
+        EFloat::from_bounds(-self.v, -self.high, -self.low, -self.precise())
+    }
+}
+
 impl Add for EFloat {
     type Output = EFloat;
     fn add(self, rhs: EFloat) -> EFloat {
-        // TODO: r.Check();
-        EFloat {
-            v: self.v + rhs.v,
-            low: next_float_down(self.lower_bound() + rhs.lower_bound()),
-            high: next_float_up(self.upper_bound() + rhs.upper_bound()),
-        }
+        EFloat::from_bounds(
+            self.v + rhs.v,
+            next_float_down(self.lower_bound() + rhs.lower_bound()),
+            next_float_up(self.upper_bound() + rhs.upper_bound()),
+            self.precise() + rhs.precise(),
+        )
     }
 }
 
 impl Sub for EFloat {
     type Output = EFloat;
     fn sub(self, rhs: EFloat) -> EFloat {
-        // TODO: r.Check();
-        EFloat {
-            v: self.v - rhs.v,
-            low: next_float_down(self.lower_bound() - rhs.upper_bound()),
-            high: next_float_up(self.upper_bound() - rhs.lower_bound()),
-        }
+        EFloat::from_bounds(
+            self.v - rhs.v,
+            next_float_down(self.lower_bound() - rhs.upper_bound()),
+            next_float_up(self.upper_bound() - rhs.lower_bound()),
+            self.precise() - rhs.precise(),
+        )
     }
 }
 
@@ -110,12 +275,12 @@ impl Mul for EFloat {
             self.lower_bound() * rhs.upper_bound(),
             self.upper_bound() * rhs.upper_bound(),
         ];
-        // TODO: r.Check();
-        EFloat {
-            v: self.v * rhs.v,
-            low: next_float_down(prod[0].min(prod[1]).min(prod[2].min(prod[3]))),
-            high: next_float_up(prod[0].max(prod[1]).max(prod[2].max(prod[3]))),
-        }
+        EFloat::from_bounds(
+            self.v * rhs.v,
+            next_float_down(prod[0].min(prod[1]).min(prod[2].min(prod[3]))),
+            next_float_up(prod[0].max(prod[1]).max(prod[2].max(prod[3]))),
+            self.precise() * rhs.precise(),
+        )
     }
 }
 
@@ -135,21 +300,22 @@ impl Div for EFloat {
             self.lower_bound() / rhs.upper_bound(),
             self.upper_bound() / rhs.upper_bound(),
         ];
-        // TODO: r.Check();
         if rhs.low < 0.0 && rhs.high > 0.0 {
             // the interval we're dividing by straddles zero, so just
             // return an interval of everything
-            EFloat {
-                v: self.v / rhs.v,
-                low: -std::f32::INFINITY,
-                high: std::f32::INFINITY,
-            }
+            EFloat::from_bounds(
+                self.v / rhs.v,
+                -std::f32::INFINITY,
+                std::f32::INFINITY,
+                self.precise() / rhs.precise(),
+            )
         } else {
-            EFloat {
-                v: self.v / rhs.v,
-                low: next_float_down(div[0].min(div[1]).min(div[2].min(div[3]))),
-                high: next_float_up(div[0].max(div[1]).max(div[2].max(div[3]))),
-            }
+            EFloat::from_bounds(
+                self.v / rhs.v,
+                next_float_down(div[0].min(div[1]).min(div[2].min(div[3]))),
+                next_float_up(div[0].max(div[1]).max(div[2].max(div[3]))),
+                self.precise() / rhs.precise(),
+            )
         }
     }
 }
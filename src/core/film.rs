@@ -10,38 +10,52 @@
 //!
 
 // std
-#[cfg(feature = "openexr")]
-use std;
-use std::ops::{DerefMut, Index};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
-use std::sync::{Arc, RwLock, RwLockWriteGuard};
+#[cfg(feature = "tev")]
+use std::sync::Mutex;
+use std::sync::{Arc, RwLock};
 
 // others
 use image;
-#[cfg(feature = "openexr")]
-use openexr::{FrameBuffer, Header, PixelType, ScanlineOutputFile};
 use smallvec::SmallVec;
 // pbrt
 use crate::core::filter::Filter;
 use crate::core::geometry::{
     bnd2_intersect_bnd2, pnt2_ceil, pnt2_floor, pnt2_inside_exclusive, pnt2_max_pnt2, pnt2_min_pnt2,
 };
-use crate::core::geometry::{Bounds2f, Bounds2i, Point2f, Point2i, Vector2f};
+use crate::core::geometry::{Bounds2f, Bounds2i, Normal3f, Point2f, Point2i, Vector2f};
+use crate::core::imageio::write_exr;
+use crate::core::integrator::AOVSample;
+use crate::core::parallel::AtomicFloat;
 use crate::core::paramset::ParamSet;
 use crate::core::pbrt::{clamp_t, gamma_correct};
 use crate::core::pbrt::{Float, Spectrum};
+use crate::core::progressreporter::CancellationToken;
 use crate::core::spectrum::xyz_to_rgb;
+#[cfg(feature = "tev")]
+use crate::core::tev::TevDisplay;
 
 // see film.h
 
 const FILTER_TABLE_WIDTH: usize = 16;
+const CHECKPOINT_MAGIC: &[u8; 8] = b"PBRTCKPT";
+const CHECKPOINT_VERSION: u32 = 3;
 
 #[derive(Debug, Clone)]
 pub struct Pixel {
     xyz: [Float; 3],
     filter_weight_sum: Float,
-    splat_xyz: [Float; 3],
-    pad: Float,
+    // unfiltered auxiliary features (AOVs) of the first hit
+    aov_albedo: [Float; 3],
+    aov_normal: [Float; 3],
+    aov_depth: Float,
+    aov_n_samples: u32,
+    aov_n_hits: u32,
+    // camera samples whose path exceeded the scene's intersection limit
+    n_aborted: u32,
 }
 
 impl Default for Pixel {
@@ -49,8 +63,12 @@ impl Default for Pixel {
         Pixel {
             xyz: [0.0 as Float; 3],
             filter_weight_sum: 0.0 as Float,
-            splat_xyz: [Float::default(), Float::default(), Float::default()],
-            pad: 0.0 as Float,
+            aov_albedo: [0.0 as Float; 3],
+            aov_normal: [0.0 as Float; 3],
+            aov_depth: 0.0 as Float,
+            aov_n_samples: 0_u32,
+            aov_n_hits: 0_u32,
+            n_aborted: 0_u32,
         }
     }
 }
@@ -59,6 +77,21 @@ impl Default for Pixel {
 pub struct FilmTilePixel {
     contrib_sum: Spectrum,
     filter_weight_sum: Float,
+    aov_albedo: Spectrum,
+    aov_normal: Normal3f,
+    aov_depth: Float,
+    aov_n_samples: u32,
+    aov_n_hits: u32,
+    n_aborted: u32,
+}
+
+/// Unfiltered luminance statistics of a pixel, only kept for adaptive
+/// sampling (films and tiles allocate them with the first sample).
+#[derive(Debug, Default, Copy, Clone)]
+struct SampleStats {
+    lum_sum: Float,
+    lum_sq_sum: Float,
+    n_samples: u32,
 }
 
 pub struct FilmTile<'a> {
@@ -68,7 +101,9 @@ pub struct FilmTile<'a> {
     filter_table: &'a [Float; FILTER_TABLE_WIDTH * FILTER_TABLE_WIDTH],
     filter_table_size: usize,
     pixels: Vec<FilmTilePixel>,
+    sample_stats: Vec<SampleStats>,
     max_sample_luminance: Float,
+    splats: Vec<(Point2f, Spectrum)>,
 }
 
 impl<'a> FilmTile<'a> {
@@ -90,9 +125,17 @@ impl<'a> FilmTile<'a> {
             filter_table_size,
             // TODO: pixels = std::vector<FilmTilePixel>(std::max(0, pixelBounds.Area()));
             pixels: vec![FilmTilePixel::default(); pixel_bounds.area() as usize],
+            sample_stats: Vec::new(),
             max_sample_luminance,
+            splats: Vec::new(),
         }
     }
+    /// Keeps a splat (see **Film::add_splat()**) until the tile gets
+    /// merged, so splats from several threads are added in a fixed
+    /// order.
+    pub fn add_splat(&mut self, p: &Point2f, v: &Spectrum) {
+        self.splats.push((*p, *v));
+    }
     pub fn add_sample(&mut self, p_film: &Point2f, l: &mut Spectrum, sample_weight: Float) {
         // TODO: ProfilePhase _(Prof::AddFilmSample);
         if l.y() > self.max_sample_luminance {
@@ -149,6 +192,48 @@ impl<'a> FilmTile<'a> {
             }
         }
     }
+    /// Records the (unfiltered) luminance of a sample taken for
+    /// pixel _p_, used to estimate the pixel's variance for adaptive
+    /// sampling.
+    pub fn add_sample_luminance(&mut self, p: &Point2i, l: &Spectrum) {
+        if !pnt2_inside_exclusive(p, &self.pixel_bounds) {
+            return;
+        }
+        if self.sample_stats.is_empty() {
+            self.sample_stats = vec![SampleStats::default(); self.pixels.len()];
+        }
+        let y: Float = l.y();
+        let idx = self.get_pixel_index(p.x, p.y);
+        let ref mut stats = self.sample_stats[idx];
+        stats.lum_sum += y;
+        stats.lum_sq_sum += y * y;
+        stats.n_samples += 1;
+    }
+    /// Records the auxiliary features of the first surface hit by a
+    /// camera ray of pixel _p_ (unfiltered, averaged per pixel).
+    pub fn add_aov_sample(&mut self, p: &Point2i, aov: &AOVSample) {
+        if !pnt2_inside_exclusive(p, &self.pixel_bounds) {
+            return;
+        }
+        let idx = self.get_pixel_index(p.x, p.y);
+        let ref mut pixel = self.pixels[idx];
+        pixel.aov_n_samples += 1;
+        if aov.hit {
+            pixel.aov_albedo += aov.albedo;
+            pixel.aov_normal = pixel.aov_normal + aov.normal;
+            pixel.aov_depth += aov.depth;
+            pixel.aov_n_hits += 1;
+        }
+    }
+    /// Flags a camera sample of pixel _p_ whose path was aborted (see
+    /// **Scene::set_max_intersections()**).
+    pub fn add_aborted_sample(&mut self, p: &Point2i) {
+        if !pnt2_inside_exclusive(p, &self.pixel_bounds) {
+            return;
+        }
+        let idx = self.get_pixel_index(p.x, p.y);
+        self.pixels[idx].n_aborted += 1;
+    }
     fn get_pixel_index(&self, x: i32, y: i32) -> usize {
         let width: i32 = self.pixel_bounds.p_max.x - self.pixel_bounds.p_min.x;
         let pidx = (y - self.pixel_bounds.p_min.y) * width + (x - self.pixel_bounds.p_min.x);
@@ -156,6 +241,230 @@ impl<'a> FilmTile<'a> {
     }
 }
 
+/// Tone curves to map HDR values to the displayable range [0, 1].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ToneMap {
+    Clamp,
+    Reinhard,
+    Aces,
+}
+
+impl ToneMap {
+    pub fn apply(&self, v: Float) -> Float {
+        match self {
+            ToneMap::Clamp => clamp_t(v, 0.0 as Float, 1.0 as Float),
+            ToneMap::Reinhard => {
+                let v: Float = v.max(0.0 as Float);
+                v / (1.0 as Float + v)
+            }
+            ToneMap::Aces => aces_filmic(v),
+        }
+    }
+}
+
+/// Auxiliary feature images (AOVs) recorded along with the radiance
+/// if the film's "aovs" parameter is true. _Aborted_ is a diagnostic
+/// recorded whenever the scene limits the intersections per path.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Aov {
+    /// RGB reflectance of the first surface seen
+    Albedo,
+    /// world space shading normal of the first surface seen
+    Normal,
+    /// distance from the camera to the first surface seen
+    Depth,
+    /// number of camera samples whose path exceeded the scene's
+    /// maximum number of intersections (see
+    /// **Scene::set_max_intersections()**)
+    Aborted,
+}
+
+impl Aov {
+    /// Channel names used for EXR layers.
+    pub fn channel_names(&self) -> &'static [&'static str] {
+        match self {
+            Aov::Albedo => &["albedo.R", "albedo.G", "albedo.B"],
+            Aov::Normal => &["N.X", "N.Y", "N.Z"],
+            Aov::Depth => &["Z"],
+            Aov::Aborted => &["aborted"],
+        }
+    }
+    pub fn n_channels(&self) -> usize {
+        self.channel_names().len()
+    }
+}
+
+/// Per pixel values of one AOV within the cropped pixel bounds of a
+/// film, in scanline order with interleaved channels.
+#[derive(Debug, Clone)]
+pub struct AovBuffer {
+    pub aov: Aov,
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<Float>,
+}
+
+impl AovBuffer {
+    /// Values of a single channel (e.g. for planar image formats).
+    pub fn channel(&self, c: usize) -> Vec<Float> {
+        self.values
+            .iter()
+            .skip(c)
+            .step_by(self.aov.n_channels())
+            .cloned()
+            .collect()
+    }
+}
+
+/// Encodes linear values as 8-bit sRGB (see WriteImage(...) in
+/// imageio.cpp): gamma corrected, rounded, and clamped to [0, 255].
+fn encode_srgb_8bit(values: &[Float]) -> Vec<u8> {
+    values
+        .iter()
+        .map(|value| {
+            clamp_t(
+                255.0 as Float * gamma_correct(*value) + 0.5,
+                0.0 as Float,
+                255.0 as Float,
+            ) as u8
+        })
+        .collect()
+}
+
+/// Krzysztof Narkowicz's fit of the ACES filmic tone curve.
+///
+/// ```rust
+/// use pbrt::core::film::aces_filmic;
+///
+/// assert_eq!(aces_filmic(0.0), 0.0);
+/// assert!((aces_filmic(0.18) - 0.2669).abs() < 1e-4);
+/// assert!((aces_filmic(1.0) - 0.8038).abs() < 1e-4);
+/// assert!((aces_filmic(4.0) - 0.9734).abs() < 1e-4);
+/// // saturates instead of exceeding white
+/// assert_eq!(aces_filmic(100.0), 1.0);
+/// ```
+pub fn aces_filmic(v: Float) -> Float {
+    let v: Float = v.max(0.0 as Float);
+    let a: Float = 2.51;
+    let b: Float = 0.03;
+    let c: Float = 2.43;
+    let d: Float = 0.59;
+    let e: Float = 0.14;
+    clamp_t(
+        (v * (a * v + b)) / (v * (c * v + d) + e),
+        0.0 as Float,
+        1.0 as Float,
+    )
+}
+
+/// Separable Gaussian blur of interleaved RGB values (standard
+/// deviation of a third of _radius_ pixels), clamping at the borders.
+fn gaussian_blur_rgb(rgb: &[Float], width: usize, height: usize, radius: Float) -> Vec<Float> {
+    let half_width: i32 = radius.ceil() as i32;
+    let sigma: Float = (radius / 3.0 as Float).max(0.5 as Float);
+    let mut weights: Vec<Float> = Vec::with_capacity((2 * half_width + 1) as usize);
+    for i in -half_width..=half_width {
+        weights.push((-((i * i) as Float) / (2.0 as Float * sigma * sigma)).exp());
+    }
+    let weight_sum: Float = weights.iter().sum();
+    for w in weights.iter_mut() {
+        *w /= weight_sum;
+    }
+    let mut tmp: Vec<Float> = vec![0.0 as Float; rgb.len()];
+    let mut blurred: Vec<Float> = vec![0.0 as Float; rgb.len()];
+    // horizontal pass
+    for y in 0..height {
+        for x in 0..width {
+            for (k, w) in weights.iter().enumerate() {
+                let sx: usize =
+                    clamp_t(x as i32 + k as i32 - half_width, 0_i32, width as i32 - 1) as usize;
+                for c in 0..3 {
+                    tmp[3 * (y * width + x) + c] += w * rgb[3 * (y * width + sx) + c];
+                }
+            }
+        }
+    }
+    // vertical pass
+    for y in 0..height {
+        for x in 0..width {
+            for (k, w) in weights.iter().enumerate() {
+                let sy: usize =
+                    clamp_t(y as i32 + k as i32 - half_width, 0_i32, height as i32 - 1) as usize;
+                for c in 0..3 {
+                    blurred[3 * (y * width + x) + c] += w * tmp[3 * (sy * width + x) + c];
+                }
+            }
+        }
+    }
+    blurred
+}
+
+fn write_u32(writer: &mut dyn Write, value: u32) -> std::io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_u64(writer: &mut dyn Write, value: u64) -> std::io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_floats(writer: &mut dyn Write, values: &[Float]) -> std::io::Result<()> {
+    for value in values {
+        write_u32(writer, value.to_bits())?;
+    }
+    Ok(())
+}
+
+fn read_u32(reader: &mut dyn Read) -> std::io::Result<u32> {
+    let mut bytes: [u8; 4] = [0_u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut dyn Read) -> std::io::Result<u64> {
+    let mut bytes: [u8; 8] = [0_u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_floats(reader: &mut dyn Read, values: &mut [Float]) -> std::io::Result<()> {
+    for value in values.iter_mut() {
+        *value = Float::from_bits(read_u32(reader)?);
+    }
+    Ok(())
+}
+
+fn invalid_checkpoint(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// A copy of the (normalized) film contents taken during a progressive
+/// render, e.g. to show a preview after each pass.
+#[derive(Debug, Clone)]
+pub struct FilmSnapshot {
+    /// Resolution of the cropped image in pixels
+    pub resolution: Point2i,
+    /// Linear RGB values (three per pixel, row by row), already divided by
+    /// the filter weights of the samples taken so far
+    pub rgb: Vec<Float>,
+    /// Number of finished passes (samples per pixel)
+    pub samples_per_pixel: usize,
+}
+
+impl FilmSnapshot {
+    /// Writes the snapshot as 8-bit sRGB PNG file (without the exposure,
+    /// bloom and tone mapping settings of the film).
+    pub fn write_png(&self, path: &str) -> std::io::Result<()> {
+        let buffer: Vec<u8> = encode_srgb_8bit(&self.rgb);
+        image::save_buffer(
+            &Path::new(path),
+            &buffer,
+            self.resolution.x as u32,
+            self.resolution.y as u32,
+            image::RGB(8),
+        )
+    }
+}
+
 pub struct Film {
     // Film Public Data
     /// The overall resolution of the image in pixels
@@ -168,12 +477,48 @@ pub struct Film {
     pub filename: String,
     /// A crop window that may specify a subset of the image to render
     pub cropped_pixel_bounds: Bounds2i,
+    /// Adaptive sampling: number of samples per pixel taken in each pass
+    pub min_samples: i32,
+    /// Adaptive sampling: maximum number of samples per pixel (0 disables adaptive sampling)
+    pub max_samples: i32,
+    /// Adaptive sampling: relative error a pixel has to exceed to get more samples
+    pub error_threshold: Float,
+    /// Write an additional image showing the samples taken per pixel
+    pub sample_heatmap: bool,
+    /// Store EXR channels as 16-bit instead of 32-bit floats
+    pub half_float: bool,
+    /// Accumulate and write albedo, normal, and depth AOVs (for denoising)
+    pub write_aovs: bool,
+    /// Additionally write a denoised image (requires the "denoise" feature)
+    pub denoise: bool,
+    /// Width and height (in pixels) of the tiles handed out to the
+    /// rendering threads
+    pub tile_size: i32,
+    /// PNG output: exposure adjustment in stops
+    pub exposure: Float,
+    /// PNG output: tone curve applied per channel
+    pub tone_map: ToneMap,
+    /// PNG output: radius (in pixels) of the bloom blur (0 disables bloom)
+    pub bloom_radius: Float,
+    /// PNG output: weight of the blurred bright pixels added back
+    pub bloom_weight: Float,
+    /// PNG output: values above this threshold contribute to bloom
+    pub bloom_threshold: Float,
 
     // Film Private Data
     pub pixels: RwLock<Vec<Pixel>>,
+    /// Splatted XYZ values (one per pixel), accumulated atomically and
+    /// kept apart from the filtered samples in _pixels_
+    splats: Vec<[AtomicFloat; 3]>,
+    /// Luminance statistics per pixel, empty unless the film was
+    /// sampled adaptively
+    sample_stats: RwLock<Vec<SampleStats>>,
     filter_table: [Float; FILTER_TABLE_WIDTH * FILTER_TABLE_WIDTH],
     scale: Float,
     max_sample_luminance: Float,
+    /// Live preview, see **open_tev()**
+    #[cfg(feature = "tev")]
+    tev: Mutex<Option<TevDisplay>>,
 }
 
 impl Film {
@@ -219,10 +564,27 @@ impl Film {
             filter,
             filename,
             cropped_pixel_bounds,
+            min_samples: 0_i32,
+            max_samples: 0_i32,
+            error_threshold: 0.0 as Float,
+            sample_heatmap: false,
+            half_float: false,
+            write_aovs: false,
+            denoise: false,
+            tile_size: 16_i32,
+            exposure: 0.0 as Float,
+            tone_map: ToneMap::Clamp,
+            bloom_radius: 0.0 as Float,
+            bloom_weight: 0.1 as Float,
+            bloom_threshold: 1.0 as Float,
             pixels: RwLock::new(vec![Pixel::default(); cropped_pixel_bounds.area() as usize]),
+            splats: vec![<[AtomicFloat; 3]>::default(); cropped_pixel_bounds.area() as usize],
+            sample_stats: RwLock::new(Vec::new()),
             filter_table,
             scale,
             max_sample_luminance,
+            #[cfg(feature = "tev")]
+            tev: Mutex::new(None),
         }
     }
     pub fn create(params: &ParamSet, filter: Box<Filter>) -> Arc<Film> {
@@ -252,7 +614,7 @@ impl Film {
         let diagonal: Float = params.find_one_float("diagonal", 35.0);
         let max_sample_luminance: Float =
             params.find_one_float("maxsampleluminance", std::f32::INFINITY);
-        let film = Arc::new(Film::new(
+        let mut film = Film::new(
             resolution,
             crop,
             filter,
@@ -260,24 +622,115 @@ impl Film {
             filename,
             scale,
             max_sample_luminance,
-        ));
-        film
+        );
+        // adaptive sampling
+        film.max_samples = params.find_one_int("maxsamples", 0);
+        film.min_samples = params.find_one_int("minsamples", 16).max(1);
+        film.error_threshold = params.find_one_float("errorthreshold", 0.05);
+        film.sample_heatmap = params.find_one_bool("sampleheatmap", false);
+        film.half_float = params.find_one_bool("halffloat", false);
+        film.write_aovs = params.find_one_bool("aovs", false);
+        film.denoise = params.find_one_bool("denoise", false);
+        film.tile_size = params.find_one_int("tilesize", 16);
+        if film.tile_size < 1 {
+            panic!("\"tilesize\" should be at least 1, got {}", film.tile_size);
+        }
+        // post-processing (PNG output only)
+        film.exposure = params.find_one_float("exposure", 0.0);
+        let tone_map: String = params.find_one_string("tonemap", String::from("clamp"));
+        if tone_map == "clamp" {
+            film.tone_map = ToneMap::Clamp;
+        } else if tone_map == "reinhard" {
+            film.tone_map = ToneMap::Reinhard;
+        } else if tone_map == "aces" {
+            film.tone_map = ToneMap::Aces;
+        } else {
+            println!(
+                "WARNING: Tone map \"{}\" unknown. Using \"clamp\".",
+                tone_map
+            );
+        }
+        film.bloom_radius = params.find_one_float("bloomradius", 0.0).max(0.0);
+        film.bloom_weight = params.find_one_float("bloomweight", 0.1);
+        film.bloom_threshold = params.find_one_float("bloomthreshold", 1.0);
+        Arc::new(film)
+    }
+    pub fn is_adaptive(&self) -> bool {
+        self.max_samples > 0_i32 && self.error_threshold > 0.0 as Float
+    }
+    /// Relative standard error of the mean luminance of a pixel, based
+    /// on the samples taken so far.
+    pub fn get_pixel_relative_error(&self, p: &Point2i) -> Float {
+        let stats: SampleStats = self.get_sample_stats(p);
+        if stats.n_samples < 2 {
+            return std::f32::INFINITY;
+        }
+        let n: Float = stats.n_samples as Float;
+        let mean: Float = stats.lum_sum / n;
+        let variance: Float =
+            ((stats.lum_sq_sum - stats.lum_sum * mean) / (n - 1.0 as Float)).max(0.0 as Float);
+        (variance / n).sqrt() / mean.abs().max(1.0e-3 as Float)
+    }
+    pub fn get_pixel_sample_count(&self, p: &Point2i) -> u32 {
+        self.get_sample_stats(p).n_samples
+    }
+    /// Adaptive sampling: does pixel _p_ need more samples?
+    pub fn needs_more_samples(&self, p: &Point2i) -> bool {
+        if !pnt2_inside_exclusive(p, &self.cropped_pixel_bounds) {
+            return false;
+        }
+        if self.get_sample_stats(p).n_samples >= self.max_samples as u32 {
+            return false;
+        }
+        self.get_pixel_relative_error(p) > self.error_threshold
+    }
+    fn get_sample_stats(&self, p: &Point2i) -> SampleStats {
+        assert!(pnt2_inside_exclusive(p, &self.cropped_pixel_bounds));
+        let width: i32 = self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x;
+        let offset: i32 = (p.x - self.cropped_pixel_bounds.p_min.x)
+            + (p.y - self.cropped_pixel_bounds.p_min.y) * width;
+        let sample_stats = self.sample_stats.read().unwrap();
+        if sample_stats.is_empty() {
+            SampleStats::default()
+        } else {
+            sample_stats[offset as usize]
+        }
     }
     pub fn get_cropped_pixel_bounds(&self) -> Bounds2i {
         self.cropped_pixel_bounds.clone()
     }
+    fn cropped_resolution(&self) -> Point2i {
+        Point2i {
+            x: self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x,
+            y: self.cropped_pixel_bounds.p_max.y - self.cropped_pixel_bounds.p_min.y,
+        }
+    }
     pub fn get_sample_bounds(&self) -> Bounds2i {
+        self.expand_by_filter_radius(&self.cropped_pixel_bounds)
+    }
+    /// Sample bounds of the whole image, ignoring the crop window.
+    /// Samplers use these, so a crop window doesn't change the samples
+    /// taken for the pixels within it.
+    pub fn get_full_sample_bounds(&self) -> Bounds2i {
+        self.expand_by_filter_radius(&Bounds2i {
+            p_min: Point2i { x: 0, y: 0 },
+            p_max: self.full_resolution,
+        })
+    }
+    /// Pixels whose samples contribute (through the filter) to the
+    /// given pixel bounds.
+    fn expand_by_filter_radius(&self, pixel_bounds: &Bounds2i) -> Bounds2i {
         let f: Point2f = pnt2_floor(
             &(Point2f {
-                x: self.cropped_pixel_bounds.p_min.x as Float,
-                y: self.cropped_pixel_bounds.p_min.y as Float,
+                x: pixel_bounds.p_min.x as Float,
+                y: pixel_bounds.p_min.y as Float,
             } + Vector2f { x: 0.5, y: 0.5 }
                 - self.filter.get_radius()),
         );
         let c: Point2f = pnt2_ceil(
             &(Point2f {
-                x: self.cropped_pixel_bounds.p_max.x as Float,
-                y: self.cropped_pixel_bounds.p_max.y as Float,
+                x: pixel_bounds.p_max.x as Float,
+                y: pixel_bounds.p_max.y as Float,
             } - Vector2f { x: 0.5, y: 0.5 }
                 + self.filter.get_radius()),
         );
@@ -350,6 +803,22 @@ impl Film {
         // TODO: ProfilePhase p(Prof::MergeFilmTile);
         // println!("Merging film tile {:?}", tile.pixel_bounds);
         // TODO: std::lock_guard<std::mutex> lock(mutex);
+        if !tile.sample_stats.is_empty() {
+            let mut sample_stats = self.sample_stats.write().unwrap();
+            if sample_stats.is_empty() {
+                *sample_stats = vec![SampleStats::default(); self.pixels.read().unwrap().len()];
+            }
+            let width: i32 = self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x;
+            for pixel in &tile.pixel_bounds {
+                let ref tile_stats = tile.sample_stats[tile.get_pixel_index(pixel.x, pixel.y)];
+                let offset: i32 = (pixel.x - self.cropped_pixel_bounds.p_min.x)
+                    + (pixel.y - self.cropped_pixel_bounds.p_min.y) * width;
+                let ref mut merge_stats = sample_stats[offset as usize];
+                merge_stats.lum_sum += tile_stats.lum_sum;
+                merge_stats.lum_sq_sum += tile_stats.lum_sq_sum;
+                merge_stats.n_samples += tile_stats.n_samples;
+            }
+        }
         for pixel in &tile.pixel_bounds {
             // merge _pixel_ into _Film::pixels_
             let idx = tile.get_pixel_index(pixel.x, pixel.y);
@@ -368,9 +837,81 @@ impl Film {
                 merge_pixel.xyz[i] += xyz[i];
             }
             merge_pixel.filter_weight_sum += tile_pixel.filter_weight_sum;
+            let mut albedo: [Float; 3] = [0.0; 3];
+            tile_pixel.aov_albedo.to_rgb(&mut albedo);
+            for i in 0..3 {
+                merge_pixel.aov_albedo[i] += albedo[i];
+                merge_pixel.aov_normal[i] += tile_pixel.aov_normal[i as u8];
+            }
+            merge_pixel.aov_depth += tile_pixel.aov_depth;
+            merge_pixel.aov_n_samples += tile_pixel.aov_n_samples;
+            merge_pixel.aov_n_hits += tile_pixel.aov_n_hits;
+            merge_pixel.n_aborted += tile_pixel.n_aborted;
             // write pixel back
             // pixels_write[offset as usize] = *merge_pixel;
         }
+        for (p, v) in &tile.splats {
+            self.add_splat(p, v);
+        }
+    }
+    /// Connects to the tev image viewer at _host_:_port_ and creates an
+    /// image called _name_ there. From now on the render loop sends
+    /// the pixels of each merged tile (see **update_tev()**), until the
+    /// returned handle gets closed.
+    #[cfg(feature = "tev")]
+    pub fn open_tev(&self, host: &str, port: u16, name: &str) -> std::io::Result<TevDisplay> {
+        let display: TevDisplay = TevDisplay::connect(host, port, name, self.cropped_pixel_bounds)?;
+        *self.tev.lock().unwrap() = Some(display.clone());
+        Ok(display)
+    }
+    /// Sends the pixels covered by a merged _tile_ to tev (if
+    /// connected, see **open_tev()**).
+    #[cfg(feature = "tev")]
+    pub fn update_tev(&self, tile: &FilmTile) {
+        let mut tev = self.tev.lock().unwrap();
+        if let Some(ref display) = *tev {
+            if let Err(e) = display.update_tile(self, tile) {
+                println!("WARNING: connection to tev lost: {}", e);
+                *tev = None;
+            }
+        }
+    }
+    #[cfg(not(feature = "tev"))]
+    pub fn update_tev(&self, _tile: &FilmTile) {}
+    /// Merges tiles arriving in any order (e.g. from worker threads)
+    /// in the order of their index, so pixels covered by several tiles
+    /// sum up the same way regardless of the number of threads.
+    /// *on_merged* is called with the number of tiles merged so far.
+    /// Once *cancel* is set no further tiles are merged, tiles finished
+    /// after that are dropped.
+    pub fn merge_film_tiles_in_order<'b, I>(
+        &self,
+        tiles: I,
+        cancel: &CancellationToken,
+        on_merged: &mut dyn FnMut(usize),
+    ) where
+        I: IntoIterator<Item = (usize, FilmTile<'b>)>,
+    {
+        let mut pending: BTreeMap<usize, FilmTile> = BTreeMap::new();
+        let mut next: usize = 0;
+        let mut merged: usize = 0;
+        for (index, tile) in tiles {
+            // keep receiving, so the workers don't block on sending
+            if cancel.is_cancelled() {
+                continue;
+            }
+            pending.insert(index, tile);
+            while let Some(tile) = pending.remove(&next) {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                self.merge_film_tile(&tile);
+                self.update_tev(&tile);
+                next += 1;
+                merged += 1;
+                on_merged(merged);
+            }
+        }
     }
     pub fn set_image(&self, img: &[Spectrum]) {
         let n_pixels: i32 = self.cropped_pixel_bounds.area();
@@ -383,11 +924,76 @@ impl Film {
                 merge_pixel.xyz[i] = xyz[i];
             }
             merge_pixel.filter_weight_sum = 1.0 as Float;
-            merge_pixel.splat_xyz[0] = 0.0;
-            merge_pixel.splat_xyz[1] = 0.0;
-            merge_pixel.splat_xyz[2] = 0.0;
+            for c in 0..3 {
+                self.splats[i][c].store(0.0 as Float);
+            }
         }
     }
+    /// Adds the contribution _v_ at film position _p_ without any
+    /// filtering (e.g. for light paths in BDPT or MLT). Can be called
+    /// concurrently from several threads, splats outside of the cropped
+    /// pixel bounds are ignored. Splats are scaled by the _splat_scale_
+    /// passed to write_image():
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use pbrt::core::film::Film;
+    /// use pbrt::core::filter::Filter;
+    /// use pbrt::core::geometry::{Bounds2f, Point2f, Point2i, Vector2f};
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::spectrum::SpectrumType;
+    /// use pbrt::filters::boxfilter::BoxFilter;
+    ///
+    /// fn main() {
+    ///     let filter = Box::new(Filter::Bx(BoxFilter {
+    ///         radius: Vector2f { x: 0.5, y: 0.5 },
+    ///         inv_radius: Vector2f { x: 2.0, y: 2.0 },
+    ///     }));
+    ///     let crop = Bounds2f {
+    ///         p_min: Point2f { x: 0.0, y: 0.0 },
+    ///         p_max: Point2f { x: 1.0, y: 1.0 },
+    ///     };
+    ///     let resolution = Point2i { x: 4, y: 4 };
+    ///     let film = Arc::new(Film::new(
+    ///         resolution, crop, filter, 35.0, String::new(), 1.0, Float::INFINITY,
+    ///     ));
+    ///     // every thread splats 1 + (x + 4 * y) / 16 into each pixel 100 times
+    ///     let n_threads: usize = 8;
+    ///     let handles: Vec<_> = (0..n_threads)
+    ///         .map(|_| {
+    ///             let film = film.clone();
+    ///             thread::spawn(move || {
+    ///                 for _ in 0..100 {
+    ///                     for i in 0..16 {
+    ///                         let p = Point2f {
+    ///                             x: (i % 4) as Float + 0.5,
+    ///                             y: (i / 4) as Float + 0.5,
+    ///                         };
+    ///                         let v = Spectrum::from_rgb_type(
+    ///                             &[1.0 + i as Float / 16.0; 3],
+    ///                             SpectrumType::Illuminant,
+    ///                         );
+    ///                         film.add_splat(&p, &v);
+    ///                     }
+    ///                     // ignored
+    ///                     film.add_splat(&Point2f { x: -0.5, y: 0.5 }, &Spectrum::new(1.0));
+    ///                 }
+    ///             })
+    ///         })
+    ///         .collect();
+    ///     for handle in handles {
+    ///         handle.join().unwrap();
+    ///     }
+    ///     let rgb: Vec<Float> = film.get_rgb(1.0 / (100 * n_threads) as Float);
+    ///     for i in 0..16 {
+    ///         let expected: Float = 1.0 + i as Float / 16.0;
+    ///         for c in 0..3 {
+    ///             assert!((rgb[3 * i + c] - expected).abs() < 1e-3);
+    ///         }
+    ///     }
+    /// }
+    /// ```
     pub fn add_splat(&self, p: &Point2f, v: &Spectrum) {
         let mut v: Spectrum = *v;
         // TODO: ProfilePhase pp(Prof::SplatFilm);
@@ -414,8 +1020,8 @@ impl Film {
         }
 
         let pi: Point2i = Point2i {
-            x: p.x as i32,
-            y: p.y as i32,
+            x: p.x.floor() as i32,
+            y: p.y.floor() as i32,
         };
         if !pnt2_inside_exclusive(&pi, &self.cropped_pixel_bounds) {
             return;
@@ -428,21 +1034,250 @@ impl Film {
         let width: i32 = self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x;
         let offset: i32 = (pi.x - self.cropped_pixel_bounds.p_min.x)
             + (pi.y - self.cropped_pixel_bounds.p_min.y) * width;
-        let mut pixels_write: RwLockWriteGuard<Vec<Pixel>> = self.pixels.write().unwrap();
-        let pixel_vec: &mut Vec<Pixel> = pixels_write.deref_mut();
-        let pixel: &mut Pixel = &mut pixel_vec[offset as usize];
-
-        let splat_xyz: &mut [Float; 3] = &mut pixel.splat_xyz;
-        splat_xyz[0] += xyz[0];
-        splat_xyz[1] += xyz[1];
-        splat_xyz[2] += xyz[2];
+        let splat_xyz: &[AtomicFloat; 3] = &self.splats[offset as usize];
+        for c in 0..3 {
+            splat_xyz[c].add(xyz[c]);
+        }
     }
-    #[cfg(not(feature = "openexr"))]
     pub fn write_image(&self, splat_scale: Float) {
-        let mut rgb: Vec<Float> =
-            vec![0.0 as Float; (3 * self.cropped_pixel_bounds.area()) as usize];
+        let rgb: Vec<Float> = self.get_rgb(splat_scale);
+        self.write_rgb(&rgb, "", self.write_aovs);
+        if self.denoise {
+            if let Some(denoised) = self.denoise(&rgb) {
+                self.write_rgb(&denoised, "_denoised", false);
+            }
+        }
+        self.write_aborted_image();
+    }
+    /// Number of pixels with at least one camera sample whose path
+    /// was aborted (see **Scene::set_max_intersections()**).
+    pub fn n_aborted_pixels(&self) -> usize {
+        let pixels = self.pixels.read().unwrap();
+        pixels.iter().filter(|pixel| pixel.n_aborted > 0).count()
+    }
+    /// If paths were aborted, writes an image with the suffix
+    /// "_aborted" (as EXR with the number of aborted samples per
+    /// pixel, or as PNG where those pixels are white).
+    fn write_aborted_image(&self) {
+        let n_pixels: usize = self.n_aborted_pixels();
+        if n_pixels == 0 {
+            return;
+        }
+        let lowercase: String = self.filename.to_lowercase();
+        let (stem, extension) = if lowercase.ends_with(".exr") || lowercase.ends_with(".png") {
+            self.filename.split_at(self.filename.len() - 4)
+        } else {
+            ("pbrt", ".png")
+        };
+        let filename: String = format!("{}_aborted{}", stem, extension);
+        println!(
+            "WARNING: {} pixel(s) exceeded the maximum number of intersections per path, see {:?}",
+            n_pixels, filename
+        );
+        let aborted: AovBuffer = self.get_aov_buffer(Aov::Aborted);
+        let result: std::io::Result<()> = if extension.to_lowercase() == ".exr" {
+            let channels: Vec<(&str, &[Float])> = vec![("Y", aborted.values.as_slice())];
+            write_exr(
+                &filename,
+                &channels,
+                &self.cropped_pixel_bounds,
+                &self.full_resolution,
+                self.half_float,
+            )
+        } else {
+            let buffer: Vec<u8> = aborted
+                .values
+                .iter()
+                .map(|n| if *n > 0.0 as Float { 255_u8 } else { 0_u8 })
+                .collect();
+            image::save_buffer(
+                &Path::new(&filename),
+                &buffer,
+                aborted.width as u32,
+                aborted.height as u32,
+                image::Gray(8),
+            )
+        };
+        if let Err(e) = result {
+            println!("ERROR: Unable to write {:?}: {}", filename, e);
+        }
+    }
+    /// Saves everything accumulated so far (filtered sums, filter
+    /// weights, sample statistics, AOVs and splats) together with the
+    /// number of finished _passes_, so an interrupted render can be
+    /// continued with read_checkpoint(). The file is written under a
+    /// temporary name first and renamed afterwards, so a crash while
+    /// writing leaves the previous checkpoint intact.
+    pub fn write_checkpoint(
+        &self,
+        path: &str,
+        scene_hash: u64,
+        passes: usize,
+    ) -> std::io::Result<()> {
+        let tmp_path: String = format!("{}.tmp", path);
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            writer.write_all(CHECKPOINT_MAGIC)?;
+            write_u32(&mut writer, CHECKPOINT_VERSION)?;
+            write_u64(&mut writer, scene_hash)?;
+            write_u64(&mut writer, passes as u64)?;
+            let resolution: Point2i = self.cropped_resolution();
+            write_u32(&mut writer, resolution.x as u32)?;
+            write_u32(&mut writer, resolution.y as u32)?;
+            let pixels = self.pixels.read().unwrap();
+            let sample_stats = self.sample_stats.read().unwrap();
+            for (i, pixel) in pixels.iter().enumerate() {
+                // films without adaptive sampling store zero statistics
+                let stats: SampleStats = sample_stats.get(i).copied().unwrap_or_default();
+                write_floats(&mut writer, &pixel.xyz)?;
+                write_floats(
+                    &mut writer,
+                    &[pixel.filter_weight_sum, stats.lum_sum, stats.lum_sq_sum],
+                )?;
+                write_u32(&mut writer, stats.n_samples)?;
+                write_floats(&mut writer, &pixel.aov_albedo)?;
+                write_floats(&mut writer, &pixel.aov_normal)?;
+                write_floats(&mut writer, &[pixel.aov_depth])?;
+                write_u32(&mut writer, pixel.aov_n_samples)?;
+                write_u32(&mut writer, pixel.aov_n_hits)?;
+                write_u32(&mut writer, pixel.n_aborted)?;
+            }
+            for splat_xyz in &self.splats {
+                for value in splat_xyz {
+                    write_floats(&mut writer, &[Float::from(value)])?;
+                }
+            }
+            writer.flush()?;
+        }
+        std::fs::rename(&tmp_path, path)
+    }
+    /// Restores the film from a file written by write_checkpoint() and
+    /// returns the number of finished passes. Fails if the checkpoint
+    /// was written by another version, for a film of another
+    /// resolution, or for another scene (_scene_hash_ differs).
+    ///
+    /// ```rust
+    /// use pbrt::core::film::Film;
+    /// use pbrt::core::filter::Filter;
+    /// use pbrt::core::geometry::{Bounds2f, Bounds2i, Point2f, Point2i, Vector2f};
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::filters::boxfilter::BoxFilter;
+    ///
+    /// let new_film = |x: i32, y: i32| {
+    ///     let filter = Box::new(Filter::Bx(BoxFilter {
+    ///         radius: Vector2f { x: 0.5, y: 0.5 },
+    ///         inv_radius: Vector2f { x: 2.0, y: 2.0 },
+    ///     }));
+    ///     let crop = Bounds2f {
+    ///         p_min: Point2f { x: 0.0, y: 0.0 },
+    ///         p_max: Point2f { x: 1.0, y: 1.0 },
+    ///     };
+    ///     Film::new(
+    ///         Point2i { x, y }, crop, filter, 35.0, String::new(), 1.0, Float::INFINITY,
+    ///     )
+    /// };
+    /// let film = new_film(3, 2);
+    /// let sample_bounds: Bounds2i = film.get_sample_bounds();
+    /// let mut tile = film.get_film_tile(&sample_bounds);
+    /// for p in &sample_bounds {
+    ///     let p_film = Point2f { x: p.x as Float + 0.5, y: p.y as Float + 0.5 };
+    ///     let mut l = Spectrum::new(0.1 * (p.x + 3 * p.y) as Float);
+    ///     tile.add_sample(&p_film, &mut l, 1.0);
+    /// }
+    /// film.merge_film_tile(&tile);
+    /// film.add_splat(&Point2f { x: 1.5, y: 0.5 }, &Spectrum::new(0.25));
+    /// let path = std::env::temp_dir().join("pbrt_checkpoint_doctest.ckpt");
+    /// let path = path.to_str().unwrap();
+    /// film.write_checkpoint(path, 42, 7).unwrap();
+    /// // a fresh film continues with identical contents ...
+    /// let resumed = new_film(3, 2);
+    /// assert_eq!(resumed.read_checkpoint(path, 42).unwrap(), 7);
+    /// assert_eq!(resumed.get_rgb(1.0), film.get_rgb(1.0));
+    /// // ... but a checkpoint of another scene or resolution is rejected
+    /// assert!(new_film(3, 2).read_checkpoint(path, 43).is_err());
+    /// assert!(new_film(2, 3).read_checkpoint(path, 42).is_err());
+    /// std::fs::remove_file(path).unwrap();
+    /// ```
+    pub fn read_checkpoint(&self, path: &str, scene_hash: u64) -> std::io::Result<usize> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic: [u8; 8] = [0_u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(invalid_checkpoint(format!("{:?} is no checkpoint", path)));
+        }
+        let version: u32 = read_u32(&mut reader)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(invalid_checkpoint(format!(
+                "checkpoint version {} (expected {})",
+                version, CHECKPOINT_VERSION
+            )));
+        }
+        if read_u64(&mut reader)? != scene_hash {
+            return Err(invalid_checkpoint(String::from(
+                "checkpoint was written for another scene",
+            )));
+        }
+        let passes: u64 = read_u64(&mut reader)?;
+        let resolution: Point2i = self.cropped_resolution();
+        let width: u32 = read_u32(&mut reader)?;
+        let height: u32 = read_u32(&mut reader)?;
+        if width != resolution.x as u32 || height != resolution.y as u32 {
+            return Err(invalid_checkpoint(format!(
+                "checkpoint was written for a {}x{} film (expected {}x{})",
+                width, height, resolution.x, resolution.y
+            )));
+        }
+        let mut pixels = self.pixels.write().unwrap();
+        let mut sample_stats: Vec<SampleStats> = vec![SampleStats::default(); pixels.len()];
+        for (pixel, stats) in pixels.iter_mut().zip(sample_stats.iter_mut()) {
+            let mut sums: [Float; 3] = [0.0 as Float; 3];
+            read_floats(&mut reader, &mut pixel.xyz)?;
+            read_floats(&mut reader, &mut sums)?;
+            pixel.filter_weight_sum = sums[0];
+            stats.lum_sum = sums[1];
+            stats.lum_sq_sum = sums[2];
+            stats.n_samples = read_u32(&mut reader)?;
+            read_floats(&mut reader, &mut pixel.aov_albedo)?;
+            read_floats(&mut reader, &mut pixel.aov_normal)?;
+            pixel.aov_depth = Float::from_bits(read_u32(&mut reader)?);
+            pixel.aov_n_samples = read_u32(&mut reader)?;
+            pixel.aov_n_hits = read_u32(&mut reader)?;
+            pixel.n_aborted = read_u32(&mut reader)?;
+        }
+        for splat_xyz in &self.splats {
+            for value in splat_xyz {
+                value.store(Float::from_bits(read_u32(&mut reader)?));
+            }
+        }
+        // only keep the statistics of an adaptively sampled film
+        if sample_stats.iter().any(|stats| stats.n_samples > 0) {
+            *self.sample_stats.write().unwrap() = sample_stats;
+        } else {
+            self.sample_stats.write().unwrap().clear();
+        }
+        Ok(passes as usize)
+    }
+    /// Copies the current film contents (see get_rgb()) into a
+    /// **FilmSnapshot**, splats are not included.
+    pub fn snapshot(&self, samples_per_pixel: usize) -> FilmSnapshot {
+        FilmSnapshot {
+            resolution: self.cropped_resolution(),
+            rgb: self.get_rgb(0.0 as Float),
+            samples_per_pixel,
+        }
+    }
+    /// Final (linear) RGB values of all pixels within the cropped pixel
+    /// bounds, including splats scaled by _splat_scale_.
+    pub fn get_rgb(&self, splat_scale: Float) -> Vec<Float> {
+        self.get_rgb_in(&self.cropped_pixel_bounds, splat_scale)
+    }
+    /// Like **get_rgb()**, but only for the pixels within _bounds_
+    /// (which have to be inside the cropped pixel bounds).
+    pub fn get_rgb_in(&self, bounds: &Bounds2i, splat_scale: Float) -> Vec<Float> {
+        let mut rgb: Vec<Float> = vec![0.0 as Float; (3 * bounds.area()) as usize];
         let mut offset;
-        for p in &self.cropped_pixel_bounds {
+        let mut start: usize = 0;
+        for p in bounds {
             // convert pixel XYZ color to RGB
             assert!(pnt2_inside_exclusive(&p, &self.cropped_pixel_bounds));
             let width: i32 = self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x;
@@ -450,7 +1285,6 @@ impl Film {
                 + (p.y - self.cropped_pixel_bounds.p_min.y) * width) as usize;
             let pixel: &Pixel = &self.pixels.read().unwrap()[offset];
 
-            let start: usize = 3 * offset;
             let mut rgb_array: [Float; 3] = [0.0 as Float; 3];
             xyz_to_rgb(&pixel.xyz, &mut rgb_array); // TODO: Use 'rgb' directly.
             rgb[start + 0] = rgb_array[0];
@@ -460,17 +1294,22 @@ impl Film {
             let filter_weight_sum: Float = pixel.filter_weight_sum;
             if filter_weight_sum != 0.0 as Float {
                 let inv_wt: Float = 1.0 as Float / filter_weight_sum;
-                rgb[start + 0] = (rgb[start + 0] * inv_wt).max(0.0 as Float);
-                rgb[start + 1] = (rgb[start + 1] * inv_wt).max(0.0 as Float);
-                rgb[start + 2] = (rgb[start + 2] * inv_wt).max(0.0 as Float);
+                rgb[start + 0] *= inv_wt;
+                rgb[start + 1] *= inv_wt;
+                rgb[start + 2] *= inv_wt;
             }
+            // filters with negative lobes (Mitchell, sinc) can produce
+            // negative values
+            rgb[start + 0] = rgb[start + 0].max(0.0 as Float);
+            rgb[start + 1] = rgb[start + 1].max(0.0 as Float);
+            rgb[start + 2] = rgb[start + 2].max(0.0 as Float);
             // add splat value at pixel
             let mut splat_rgb: [Float; 3] = [0.0 as Float; 3];
-            let pixel_splat_xyz: &[Float; 3] = &pixel.splat_xyz;
+            let pixel_splat_xyz: &[AtomicFloat; 3] = &self.splats[offset];
             let splat_xyz: [Float; 3] = [
-                *pixel_splat_xyz.index(0),
-                *pixel_splat_xyz.index(1),
-                *pixel_splat_xyz.index(2),
+                Float::from(&pixel_splat_xyz[0]),
+                Float::from(&pixel_splat_xyz[1]),
+                Float::from(&pixel_splat_xyz[2]),
             ];
             xyz_to_rgb(&splat_xyz, &mut splat_rgb);
             rgb[start + 0] += splat_scale * splat_rgb[0];
@@ -480,171 +1319,327 @@ impl Film {
             rgb[start + 0] *= self.scale;
             rgb[start + 1] *= self.scale;
             rgb[start + 2] *= self.scale;
+            start += 3;
+        }
+        rgb
+    }
+    /// Writes RGB values (see get_rgb()) either as EXR (if the film's
+    /// filename ends in ".exr") or as PNG (to "pbrt.png" unless the
+    /// filename ends in ".png"), inserting _suffix_ into the filename. AOVs are added if _with_aovs_ is true.
+    fn write_rgb(&self, rgb: &[Float], suffix: &str, with_aovs: bool) {
+        if self.filename.to_lowercase().ends_with(".exr") {
+            let (stem, extension) = self.filename.split_at(self.filename.len() - 4);
+            let filename: String = format!("{}{}{}", stem, suffix, extension);
+            println!(
+                "Writing image {:?} with bounds {:?}",
+                filename, self.cropped_pixel_bounds
+            );
+            let aovs: &[Aov] = if with_aovs {
+                &[Aov::Albedo, Aov::Normal, Aov::Depth]
+            } else {
+                &[]
+            };
+            if let Err(e) = self.write_exr_rgb(&filename, rgb, aovs) {
+                println!("ERROR: Unable to write {:?}: {}", filename, e);
+            }
+            return;
         }
-        let filename = "pbrt.png";
+        let stem: &str = self.png_stem();
+        let filename: String = format!("{}{}.png", stem, suffix);
         println!(
             "Writing image {:?} with bounds {:?}",
-            filename, // TODO: self.filename,
-            self.cropped_pixel_bounds
+            filename, self.cropped_pixel_bounds
         );
-        // TODO: pbrt::WriteImage(filename, &rgb[0], croppedPixelBounds, fullResolution);
-        let mut buffer: Vec<u8> = vec![0.0 as u8; (3 * self.cropped_pixel_bounds.area()) as usize];
-        // 8-bit format; apply gamma (see WriteImage(...) in imageio.cpp)
+        self.write_png_rgb(&filename, rgb).unwrap();
+        if with_aovs {
+            self.write_aov_images(stem);
+        }
+    }
+    /// Writes the film as OpenEXR file with linear float RGB values
+    /// (including unscaled splats) and the given AOVs as additional
+    /// layers, independent of the film's filename. The AOVs are only
+    /// recorded if the film's "aovs" parameter is true, otherwise they
+    /// are written as zeros.
+    pub fn write_exr(&self, path: &str, channels: &[Aov]) -> std::io::Result<()> {
+        let rgb: Vec<Float> = self.get_rgb(1.0 as Float);
+        self.write_exr_rgb(path, &rgb, channels)
+    }
+    /// Writes RGB values (see get_rgb()) and AOVs as OpenEXR file.
+    fn write_exr_rgb(&self, filename: &str, rgb: &[Float], aovs: &[Aov]) -> std::io::Result<()> {
+        // linear RGB values (no gamma) as separate channels
+        let mut planes: Vec<(&str, Vec<Float>)> = Vec::new();
+        for (c, name) in ["R", "G", "B"].iter().enumerate() {
+            planes.push((name, rgb.iter().skip(c).step_by(3).cloned().collect()));
+        }
+        // AOVs as additional layers
+        for aov in aovs {
+            let buffer: AovBuffer = self.get_aov_buffer(*aov);
+            for (c, name) in aov.channel_names().iter().enumerate() {
+                planes.push((name, buffer.channel(c)));
+            }
+        }
+        let channels: Vec<(&str, &[Float])> = planes
+            .iter()
+            .map(|(name, values)| (*name, values.as_slice()))
+            .collect();
+        write_exr(
+            filename,
+            &channels,
+            &self.cropped_pixel_bounds,
+            &self.full_resolution,
+            self.half_float,
+        )
+    }
+    /// Writes the film as 8-bit sRGB PNG file to _path_, independent of
+    /// the film's filename. Like write_image() it includes splats
+    /// scaled by _splat_scale_ and uses the display pipeline (exposure,
+    /// bloom, and tone curve, where the default "clamp" tone map clamps
+    /// the linear values to [0, 1]). Only the pixels within the crop
+    /// window are written, so the image size is the one of the
+    /// cropped pixel bounds.
+    ///
+    /// A constant radiance of 0.2 ends up as sRGB value 124:
+    ///
+    /// ```rust
+    /// use pbrt::core::film::Film;
+    /// use pbrt::core::filter::Filter;
+    /// use pbrt::core::geometry::{Bounds2f, Bounds2i, Point2f, Point2i, Vector2f};
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::spectrum::SpectrumType;
+    /// use pbrt::filters::boxfilter::BoxFilter;
+    ///
+    /// let filter = Box::new(Filter::Bx(BoxFilter {
+    ///     radius: Vector2f { x: 0.5, y: 0.5 },
+    ///     inv_radius: Vector2f { x: 2.0, y: 2.0 },
+    /// }));
+    /// // 4x4 pixels, but only the lower right 2x2 are within the crop window
+    /// let film = Film::new(
+    ///     Point2i { x: 4, y: 4 },
+    ///     Bounds2f {
+    ///         p_min: Point2f { x: 0.5, y: 0.5 },
+    ///         p_max: Point2f { x: 1.0, y: 1.0 },
+    ///     },
+    ///     filter,
+    ///     35.0,
+    ///     String::from("tiny.png"),
+    ///     1.0,
+    ///     std::f32::INFINITY,
+    /// );
+    /// let sample_bounds: Bounds2i = film.get_sample_bounds();
+    /// let mut tile = film.get_film_tile(&sample_bounds);
+    /// for p in &sample_bounds {
+    ///     let p_film = Point2f {
+    ///         x: p.x as Float + 0.5,
+    ///         y: p.y as Float + 0.5,
+    ///     };
+    ///     let mut l = Spectrum::from_rgb_type(&[0.2; 3], SpectrumType::Illuminant);
+    ///     tile.add_sample(&p_film, &mut l, 1.0);
+    /// }
+    /// film.merge_film_tile(&tile);
+    /// let path = std::env::temp_dir().join("pbrt_write_png_doctest.png");
+    /// film.write_png(path.to_str().unwrap(), 1.0).unwrap();
+    /// let png = image::open(&path).unwrap().to_rgb();
+    /// std::fs::remove_file(&path).unwrap();
+    /// assert_eq!(png.dimensions(), (2, 2));
+    /// assert!(png.pixels().all(|p| p.0 == [124, 124, 124]));
+    /// ```
+    pub fn write_png(&self, path: &str, splat_scale: Float) -> std::io::Result<()> {
+        let rgb: Vec<Float> = self.get_rgb(splat_scale);
+        self.write_png_rgb(path, &rgb)
+    }
+    /// Applies the display pipeline to RGB values (see get_rgb()) and
+    /// writes them as 8-bit sRGB PNG file.
+    fn write_png_rgb(&self, filename: &str, rgb: &[Float]) -> std::io::Result<()> {
         let width: u32 =
             (self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x) as u32;
         let height: u32 =
             (self.cropped_pixel_bounds.p_max.y - self.cropped_pixel_bounds.p_min.y) as u32;
-        for y in 0..height {
-            for x in 0..width {
-                // red
-                let index: usize = (3 * (y * width + x) + 0) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // green
-                let index: usize = (3 * (y * width + x) + 1) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // blue
-                let index: usize = (3 * (y * width + x) + 2) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
+        let rgb: Vec<Float> = self.apply_display_pipeline(rgb, width as usize, height as usize);
+        let buffer: Vec<u8> = encode_srgb_8bit(&rgb);
+        image::save_buffer(&Path::new(filename), &buffer, width, height, image::RGB(8))
+    }
+    /// Prepares linear RGB values for 8-bit output: scales by the
+    /// exposure, adds bloom around bright pixels, and applies the tone
+    /// curve. The result is still linear (sRGB encoding happens
+    /// afterwards) and is only used for PNG output, never for EXR.
+    fn apply_display_pipeline(&self, rgb: &[Float], width: usize, height: usize) -> Vec<Float> {
+        let exposure_scale: Float = (2.0 as Float).powf(self.exposure);
+        let mut display: Vec<Float> = rgb.iter().map(|v| *v * exposure_scale).collect();
+        if self.bloom_radius > 0.0 as Float && self.bloom_weight > 0.0 as Float {
+            // blur the part of each pixel above the threshold
+            let bright: Vec<Float> = display
+                .iter()
+                .map(|v| (*v - self.bloom_threshold).max(0.0 as Float))
+                .collect();
+            let blurred: Vec<Float> = gaussian_blur_rgb(&bright, width, height, self.bloom_radius);
+            for i in 0..display.len() {
+                display[i] += self.bloom_weight * blurred[i];
             }
         }
-        // write "pbrt.png" to disk
-        image::save_buffer(
-            &Path::new("pbrt.png"),
-            &buffer,
-            width,
-            height,
-            image::RGB(8),
-        )
-        .unwrap();
+        for v in display.iter_mut() {
+            *v = self.tone_map.apply(*v);
+        }
+        display
     }
-    #[cfg(feature = "openexr")]
-    pub fn write_image(&self, splat_scale: Float) {
-        let mut rgb: Vec<Float> =
-            vec![0.0 as Float; (3 * self.cropped_pixel_bounds.area()) as usize];
-        let mut exr: Vec<(Float, Float, Float)> = // copy data for OpenEXR image
-            vec![(0.0_f32, 0.0_f32, 0.0_f32); self.cropped_pixel_bounds.area() as usize];
-        let mut offset;
-        for p in &self.cropped_pixel_bounds {
-            // convert pixel XYZ color to RGB
-            assert!(pnt2_inside_exclusive(&p, &self.cropped_pixel_bounds));
-            let width: i32 = self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x;
-            offset = ((p.x - self.cropped_pixel_bounds.p_min.x)
-                + (p.y - self.cropped_pixel_bounds.p_min.y) * width) as usize;
-            let pixel: &Pixel = &self.pixels.read().unwrap()[offset];
-            let start = 3 * offset;
-            let mut rgb_array: [Float; 3] = [0.0 as Float; 3];
-            xyz_to_rgb(&pixel.xyz, &mut rgb_array); // TODO: Use 'rgb' directly.
-            rgb[start + 0] = rgb_array[0];
-            rgb[start + 1] = rgb_array[1];
-            rgb[start + 2] = rgb_array[2];
-            // normalize pixel with weight sum
-            let filter_weight_sum: Float = pixel.filter_weight_sum;
-            if filter_weight_sum != 0.0 as Float {
-                let inv_wt: Float = 1.0 as Float / filter_weight_sum;
-                rgb[start + 0] = (rgb[start + 0] * inv_wt).max(0.0 as Float);
-                rgb[start + 1] = (rgb[start + 1] * inv_wt).max(0.0 as Float);
-                rgb[start + 2] = (rgb[start + 2] * inv_wt).max(0.0 as Float);
+    /// Runs Intel Open Image Denoise on the (HDR) RGB values, guided by
+    /// the albedo and normal AOVs if they were recorded. Returns
+    /// _None_ (after a warning) if denoising isn't available, e.g.
+    /// because the OIDN library can't be loaded.
+    #[cfg(feature = "denoise")]
+    pub fn denoise(&self, rgb: &[Float]) -> Option<Vec<Float>> {
+        let (albedo, normal): (Option<AovBuffer>, Option<AovBuffer>) = if self.write_aovs {
+            (
+                Some(self.get_aov_buffer(Aov::Albedo)),
+                Some(self.get_aov_buffer(Aov::Normal)),
+            )
+        } else {
+            (None, None)
+        };
+        match oidn_filter(self, rgb, albedo.as_ref(), normal.as_ref()) {
+            Ok(output) => Some(output),
+            Err(e) => {
+                println!("WARNING: Denoising failed: {}", e);
+                None
             }
-            // add splat value at pixel
-            let mut splat_rgb: [Float; 3] = [0.0 as Float; 3];
-            let pixel_splat_xyz: &[Float; 3] = &pixel.splat_xyz;
-            let splat_xyz: [Float; 3] = [
-                *pixel_splat_xyz.index(0),
-                *pixel_splat_xyz.index(1),
-                *pixel_splat_xyz.index(2),
-            ];
-            xyz_to_rgb(&splat_xyz, &mut splat_rgb);
-            rgb[start + 0] += splat_scale * splat_rgb[0];
-            rgb[start + 1] += splat_scale * splat_rgb[1];
-            rgb[start + 2] += splat_scale * splat_rgb[2];
-            // scale pixel value by _scale_
-            rgb[start + 0] *= self.scale;
-            rgb[start + 1] *= self.scale;
-            rgb[start + 2] *= self.scale;
-            // copy data for OpenEXR image
-            exr[offset].0 = rgb[start + 0];
-            exr[offset].1 = rgb[start + 1];
-            exr[offset].2 = rgb[start + 2];
         }
-        let filename = "pbrt.png";
+    }
+    #[cfg(not(feature = "denoise"))]
+    pub fn denoise(&self, _rgb: &[Float]) -> Option<Vec<Float>> {
         println!(
-            "Writing image {:?} with bounds {:?}",
-            filename, // TODO: self.filename,
-            self.cropped_pixel_bounds
+            "WARNING: Denoising requested, but rs_pbrt was built without the \"denoise\" feature"
         );
-        // TODO: pbrt::WriteImage(filename, &rgb[0], croppedPixelBounds, fullResolution);
-        let mut buffer: Vec<u8> = vec![0.0 as u8; (3 * self.cropped_pixel_bounds.area()) as usize];
-        // 8-bit format; apply gamma (see WriteImage(...) in imageio.cpp)
+        None
+    }
+    /// Returns the per pixel averages of an AOV. Albedo and normal are
+    /// averaged over all camera samples (rays missing the scene count
+    /// as black/zero), the normal is renormalized, and the depth is
+    /// averaged over the hits only.
+    pub fn get_aov_buffer(&self, aov: Aov) -> AovBuffer {
+        let pixels = self.pixels.read().unwrap();
+        let mut values: Vec<Float> = Vec::with_capacity(aov.n_channels() * pixels.len());
+        for pixel in pixels.iter() {
+            match aov {
+                Aov::Albedo => {
+                    let inv_n: Float = if pixel.aov_n_samples > 0 {
+                        1.0 as Float / pixel.aov_n_samples as Float
+                    } else {
+                        0.0 as Float
+                    };
+                    for i in 0..3 {
+                        values.push(pixel.aov_albedo[i] * inv_n);
+                    }
+                }
+                Aov::Normal => {
+                    let n: Normal3f = Normal3f {
+                        x: pixel.aov_normal[0],
+                        y: pixel.aov_normal[1],
+                        z: pixel.aov_normal[2],
+                    };
+                    let len: Float = n.length();
+                    for i in 0..3 {
+                        if len > 0.0 as Float {
+                            values.push(pixel.aov_normal[i] / len);
+                        } else {
+                            values.push(0.0 as Float);
+                        }
+                    }
+                }
+                Aov::Depth => {
+                    if pixel.aov_n_hits > 0 {
+                        values.push(pixel.aov_depth / pixel.aov_n_hits as Float);
+                    } else {
+                        values.push(0.0 as Float);
+                    }
+                }
+                Aov::Aborted => values.push(pixel.n_aborted as Float),
+            }
+        }
+        AovBuffer {
+            aov,
+            width: (self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x) as usize,
+            height: (self.cropped_pixel_bounds.p_max.y - self.cropped_pixel_bounds.p_min.y)
+                as usize,
+            values,
+        }
+    }
+    /// Writes the AOVs as separate 8-bit images with the suffixes
+    /// "_albedo", "_normal" (mapped from [-1, 1] to [0, 1]), and
+    /// "_depth" (normalized by the maximum depth).
+    fn write_aov_images(&self, stem: &str) {
         let width: u32 =
             (self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x) as u32;
         let height: u32 =
             (self.cropped_pixel_bounds.p_max.y - self.cropped_pixel_bounds.p_min.y) as u32;
-        // OpenEXR
-        let filename = "pbrt_rust.exr";
-        println!(
-            "Writing image {:?} with bounds {:?}",
-            filename, // TODO: self.filename,
-            self.cropped_pixel_bounds
-        );
-        let mut file = std::fs::File::create("pbrt_rust.exr").unwrap();
-        let mut output_file = ScanlineOutputFile::new(
-            &mut file,
-            Header::new()
-                .set_resolution(width, height)
-                .add_channel("R", PixelType::FLOAT)
-                .add_channel("G", PixelType::FLOAT)
-                .add_channel("B", PixelType::FLOAT),
-        )
-        .unwrap();
-        let mut fb = FrameBuffer::new(width as u32, height as u32);
-        fb.insert_channels(&["R", "G", "B"], &exr);
-        output_file.write_pixels(&fb).unwrap();
-
-        // OpenEXR
-        for y in 0..height {
-            for x in 0..width {
-                // red
-                let index: usize = (3 * (y * width + x) + 0) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // green
-                let index: usize = (3 * (y * width + x) + 1) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // blue
-                let index: usize = (3 * (y * width + x) + 2) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
+        let albedo: Vec<Float> = self.get_aov_buffer(Aov::Albedo).values;
+        let normal: Vec<Float> = self.get_aov_buffer(Aov::Normal).values;
+        let depth: Vec<Float> = self.get_aov_buffer(Aov::Depth).values;
+        let to_u8 =
+            |v: Float| clamp_t(255.0 as Float * v + 0.5, 0.0 as Float, 255.0 as Float) as u8;
+        let albedo_buffer: Vec<u8> = encode_srgb_8bit(&albedo);
+        let normal_buffer: Vec<u8> = normal
+            .iter()
+            .map(|v| to_u8(0.5 as Float * *v + 0.5 as Float))
+            .collect();
+        let max_depth: Float = depth.iter().cloned().fold(0.0 as Float, Float::max);
+        let inv_max_depth: Float = if max_depth > 0.0 as Float {
+            1.0 as Float / max_depth
+        } else {
+            0.0 as Float
+        };
+        let depth_buffer: Vec<u8> = depth.iter().map(|v| to_u8(*v * inv_max_depth)).collect();
+        for (suffix, buffer, color_type) in &[
+            ("_albedo", &albedo_buffer, image::RGB(8)),
+            ("_normal", &normal_buffer, image::RGB(8)),
+            ("_depth", &depth_buffer, image::Gray(8)),
+        ] {
+            let filename: String = format!("{}{}.png", stem, suffix);
+            println!(
+                "Writing image {:?} with bounds {:?}",
+                filename, self.cropped_pixel_bounds
+            );
+            image::save_buffer(&Path::new(&filename), buffer, width, height, *color_type).unwrap();
+        }
+    }
+    /// Filename of the PNG image without the extension, all other PNG
+    /// images get named after it ("pbrt" for non-PNG filenames).
+    fn png_stem(&self) -> &str {
+        if self.filename.to_lowercase().ends_with(".png") {
+            &self.filename[..self.filename.len() - 4]
+        } else {
+            "pbrt"
+        }
+    }
+    /// Writes a grayscale image showing how many samples were taken
+    /// per pixel (white means _max_samples_), named after the image
+    /// (e.g. "cornell_spp.png" for "cornell.png").
+    pub fn write_sample_heatmap(&self) {
+        let width: u32 =
+            (self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x) as u32;
+        let height: u32 =
+            (self.cropped_pixel_bounds.p_max.y - self.cropped_pixel_bounds.p_min.y) as u32;
+        let max_samples: Float = self.max_samples.max(1) as Float;
+        let mut buffer: Vec<u8> = vec![0_u8; (width * height) as usize];
+        {
+            let sample_stats = self.sample_stats.read().unwrap();
+            for (i, stats) in sample_stats.iter().enumerate() {
+                buffer[i] = clamp_t(
+                    255.0 as Float * stats.n_samples as Float / max_samples + 0.5,
                     0.0 as Float,
                     255.0 as Float,
                 ) as u8;
             }
         }
-        // write "pbrt.png" to disk
+        let filename: String = format!("{}_spp.png", self.png_stem());
+        println!(
+            "Writing image {:?} with bounds {:?}",
+            filename, self.cropped_pixel_bounds
+        );
         image::save_buffer(
-            &Path::new("pbrt.png"),
+            &Path::new(&filename),
             &buffer,
             width,
             height,
-            image::RGB(8),
+            image::Gray(8),
         )
         .unwrap();
     }
@@ -656,3 +1651,97 @@ impl Film {
     //     &self.pixels.read().unwrap()[offset as usize]
     // }
 }
+
+/// Denoises the film's image with the ray tracing filter of Intel Open
+/// Image Denoise and returns the linear RGB values of the pixels within
+/// the cropped pixel bounds (in scanline order, one triple per pixel).
+/// The film's XYZ values are converted to RGB first, and the triples
+/// are returned as they come from the filter (no round trip through
+/// **Spectrum**). If denoising fails (or the OIDN library can't be
+/// loaded), a warning is printed and the noisy image is returned.
+///
+/// The albedo and normal AOVs (see **Film::get_aov_buffer()**) are
+/// optional, but improve the result dramatically, because the filter
+/// can tell noise from texture and geometry detail with them. OIDN
+/// only uses a normal buffer together with an albedo buffer.
+///
+/// ```rust
+/// use pbrt::core::film::{denoise, Film};
+/// use pbrt::core::filter::Filter;
+/// use pbrt::core::geometry::{Bounds2f, Point2f, Point2i, Vector2f};
+/// use pbrt::core::pbrt::{Float, Spectrum};
+/// use pbrt::core::rng::Rng;
+/// use pbrt::filters::boxfilter::BoxFilter;
+///
+/// let filter = Box::new(Filter::Bx(BoxFilter {
+///     radius: Vector2f { x: 0.5, y: 0.5 },
+///     inv_radius: Vector2f { x: 2.0, y: 2.0 },
+/// }));
+/// let film = Film::new(
+///     Point2i { x: 16, y: 16 },
+///     Bounds2f {
+///         p_min: Point2f { x: 0.0, y: 0.0 },
+///         p_max: Point2f { x: 1.0, y: 1.0 },
+///     },
+///     filter,
+///     35.0,
+///     String::from("noisy.exr"),
+///     1.0,
+///     std::f32::INFINITY,
+/// );
+/// // one noisy sample per pixel
+/// let mut rng: Rng = Rng::new();
+/// let sample_bounds = film.get_sample_bounds();
+/// let mut tile = film.get_film_tile(&sample_bounds);
+/// for p in &sample_bounds {
+///     let p_film = Point2f {
+///         x: p.x as Float + 0.5,
+///         y: p.y as Float + 0.5,
+///     };
+///     let mut l = Spectrum::new(2.0 * rng.uniform_float());
+///     tile.add_sample(&p_film, &mut l, 1.0);
+/// }
+/// film.merge_film_tile(&tile);
+/// let denoised: Vec<[Float; 3]> = denoise(&film, None, None);
+/// assert_eq!(denoised.len(), 16 * 16);
+/// assert!(denoised.iter().flatten().all(|v| v.is_finite()));
+/// ```
+#[cfg(feature = "denoise")]
+pub fn denoise(
+    film: &Film,
+    albedo: Option<&AovBuffer>,
+    normal: Option<&AovBuffer>,
+) -> Vec<[Float; 3]> {
+    let rgb: Vec<Float> = film.get_rgb(1.0 as Float);
+    let output: Vec<Float> = match oidn_filter(film, &rgb, albedo, normal) {
+        Ok(output) => output,
+        Err(e) => {
+            println!("WARNING: Denoising failed: {}", e);
+            rgb
+        }
+    };
+    output.chunks(3).map(|c| [c[0], c[1], c[2]]).collect()
+}
+
+/// Runs the OIDN ray tracing filter on RGB values of the film's
+/// cropped pixel bounds.
+#[cfg(feature = "denoise")]
+fn oidn_filter(
+    film: &Film,
+    rgb: &[Float],
+    albedo: Option<&AovBuffer>,
+    normal: Option<&AovBuffer>,
+) -> Result<Vec<Float>, String> {
+    let bounds: Bounds2i = film.get_cropped_pixel_bounds();
+    let width: usize = (bounds.p_max.x - bounds.p_min.x) as usize;
+    let height: usize = (bounds.p_max.y - bounds.p_min.y) as usize;
+    let input: Vec<f32> = rgb.iter().map(|v| *v as f32).collect();
+    let to_f32 = |buffer: Option<&AovBuffer>| -> Option<Vec<f32>> {
+        buffer.map(|b| b.values.iter().map(|v| *v as f32).collect())
+    };
+    let albedo: Option<Vec<f32>> = to_f32(albedo);
+    let normal: Option<Vec<f32>> = to_f32(normal);
+    let output: Vec<f32> =
+        crate::core::oidn::filter(width, height, &input, albedo.as_deref(), normal.as_deref())?;
+    Ok(output.iter().map(|v| *v as Float).collect())
+}
@@ -196,6 +196,7 @@ pub type Vector3i = Vector3<i32>;
 pub type Normal3f = Normal3<Float>;
 
 #[derive(Debug, Default, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector2<T> {
     pub x: T,
     pub y: T,
@@ -342,6 +343,7 @@ where
 }
 
 #[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector3<T> {
     pub x: T,
     pub y: T,
@@ -683,6 +685,7 @@ pub fn vec3_coordinate_system(v1: &Vector3f, v2: &mut Vector3f, v3: &mut Vector3
 }
 
 #[derive(Debug, Default, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point2<T> {
     pub x: T,
     pub y: T,
@@ -902,6 +905,7 @@ pub fn bnd2_expand(b: &Bounds2f, delta: Float) -> Bounds2f {
 }
 
 #[derive(Debug, Default, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point3<T> {
     pub x: T,
     pub y: T,
@@ -1153,6 +1157,79 @@ where
 /// offset their origins enough to ensure that they are past the
 /// boundary of the error box and thus won't incorrectly re-intersect
 /// the surface.
+///
+/// Even for a sphere of radius 1e6 centered at (1e6, 0, 0), seen from
+/// close to the origin (the hit points have error bounds of about
+/// 0.3 in object space), the offset origins end up on the requested
+/// side of the surface, and shadow rays towards a point light never
+/// hit the sphere itself:
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use pbrt::core::geometry::{pnt3_offset_ray_origin, Point3f, Ray, Vector3f};
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::rng::Rng;
+/// use pbrt::core::shape::Shape;
+/// use pbrt::core::transform::Transform;
+/// use pbrt::shapes::sphere::Sphere;
+///
+/// let radius: Float = 1e6;
+/// let object_to_world: Transform = Transform::translate(&Vector3f {
+///     x: radius,
+///     y: 0.0,
+///     z: 0.0,
+/// });
+/// let world_to_object: Transform = Transform::inverse(&object_to_world);
+/// let sphere: Shape = Shape::Sphr(Sphere::new(
+///     Arc::new(object_to_world),
+///     Arc::new(world_to_object),
+///     false,
+///     radius,
+///     -radius,
+///     radius,
+///     360.0,
+/// ));
+/// // signed distance to the sphere, computed in double precision
+/// let side = |p: &Point3f| -> f64 {
+///     let x: f64 = p.x as f64 - radius as f64;
+///     (x * x + (p.y as f64).powi(2) + (p.z as f64).powi(2)).sqrt() - radius as f64
+/// };
+/// let eye: Point3f = Point3f { x: -10.0, y: 0.0, z: 0.0 };
+/// let light: Point3f = Point3f { x: -5.0, y: 3.0, z: 2.0 };
+/// let mut rng: Rng = Rng::new();
+/// for _ in 0..10000 {
+///     let ray: Ray = Ray {
+///         o: eye,
+///         d: Vector3f {
+///             x: 1.0,
+///             y: rng.uniform_float() - 0.5,
+///             z: rng.uniform_float() - 0.5,
+///         },
+///         t_max: Float::INFINITY,
+///         time: 0.0,
+///         differential: None,
+///         medium: None,
+///     };
+///     let (isect, _t_hit) = sphere.intersect(&ray).unwrap();
+///     let w: Vector3f = light - isect.p;
+///     let outside: Point3f = pnt3_offset_ray_origin(&isect.p, &isect.p_error, &isect.n, &w);
+///     let inside: Point3f = pnt3_offset_ray_origin(&isect.p, &isect.p_error, &isect.n, &-w);
+///     assert!(side(&outside) > 0.0);
+///     assert!(side(&inside) < 0.0);
+///     // the offset stays tiny compared to the scene
+///     assert!((outside - isect.p).length() < 1.0);
+///     let shadow_ray: Ray = Ray {
+///         o: outside,
+///         d: light - outside,
+///         t_max: 1.0,
+///         time: 0.0,
+///         differential: None,
+///         medium: None,
+///     };
+///     assert!(!sphere.intersect_p(&shadow_ray));
+/// }
+/// ```
 pub fn pnt3_offset_ray_origin(
     p: &Point3f,
     p_error: &Vector3f,
@@ -1226,6 +1303,7 @@ pub fn spherical_phi(v: &Vector3f) -> Float {
 }
 
 #[derive(Debug, Default, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Normal3<T> {
     pub x: T,
     pub y: T,
@@ -1703,19 +1781,6 @@ impl<T> Bounds3<T> {
         }
         o
     }
-    pub fn bounding_sphere(b: &Bounds3f, center: &mut Point3f, radius: &mut Float) {
-        let p_min: Point3f = b.p_min as Point3f;
-        let p_max: Point3f = b.p_max as Point3f;
-        let sum: Point3f = p_min + p_max;
-        *center = sum / 2.0;
-        let center_copy: Point3f = *center as Point3f;
-        let is_inside: bool = pnt3_inside_bnd3(&center_copy, b);
-        if is_inside {
-            *radius = pnt3_distance(&center_copy, &p_max);
-        } else {
-            *radius = 0.0;
-        }
-    }
 }
 
 impl Bounds3<Float> {
@@ -1726,6 +1791,31 @@ impl Bounds3<Float> {
             z: lerp(t.z, self.p_min.z as Float, self.p_max.z as Float),
         }
     }
+    /// Returns the center and radius of a sphere around the bounding
+    /// box (a radius of zero for a degenerate box):
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{pnt3_distance, Bounds3f, Point3f};
+    ///
+    /// let b = Bounds3f::new(
+    ///     Point3f { x: -1.0, y: 2.0, z: 0.5 },
+    ///     Point3f { x: 3.0, y: 2.5, z: -4.0 },
+    /// );
+    /// let (center, radius) = b.bounding_sphere();
+    /// for corner in 0..8_u8 {
+    ///     assert!(pnt3_distance(&center, &b.corner(corner)) <= radius);
+    /// }
+    /// assert_eq!(Bounds3f::default().bounding_sphere().1, 0.0);
+    /// ```
+    pub fn bounding_sphere(&self) -> (Point3f, Float) {
+        let center: Point3f = (self.p_min + self.p_max) / 2.0;
+        let radius: Float = if pnt3_inside_bnd3(&center, self) {
+            pnt3_distance(&center, &self.p_max)
+        } else {
+            0.0
+        };
+        (center, radius)
+    }
     pub fn intersect_b(&self, ray: &Ray, hitt0: &mut Float, hitt1: &mut Float) -> bool {
         let mut t0: Float = 0.0;
         let mut t1: Float = ray.t_max;
@@ -1754,39 +1844,215 @@ impl Bounds3<Float> {
         *hitt1 = t1;
         true
     }
-    pub fn intersect_p(&self, ray: &Ray, inv_dir: &Vector3f, dir_is_neg: [u8; 3]) -> bool {
-        // check for ray intersection against $x$ and $y$ slabs
-        let mut t_min: Float = (self[dir_is_neg[0]].x - ray.o.x) * inv_dir.x;
-        let mut t_max: Float = (self[1_u8 - dir_is_neg[0]].x - ray.o.x) * inv_dir.x;
-        let ty_min: Float = (self[dir_is_neg[1]].y - ray.o.y) * inv_dir.y;
-        let mut ty_max: Float = (self[1_u8 - dir_is_neg[1]].y - ray.o.y) * inv_dir.y;
-        // update _t_max_ and _ty_max_ to ensure robust bounds intersection
-        t_max *= 1.0 + 2.0 * gamma(3_i32);
-        ty_max *= 1.0 + 2.0 * gamma(3_i32);
-        if t_min > ty_max || ty_min > t_max {
-            return false;
-        }
-        if ty_min > t_min {
-            t_min = ty_min;
-        }
-        if ty_max < t_max {
-            t_max = ty_max;
-        }
-        // check for ray intersection against $z$ slab
-        let tz_min: Float = (self[dir_is_neg[2]].z - ray.o.z) * inv_dir.z;
-        let mut tz_max: Float = (self[1_u8 - dir_is_neg[2]].z - ray.o.z) * inv_dir.z;
-        // update _tz_max_ to ensure robust bounds intersection
-        tz_max *= 1.0 + 2.0 * gamma(3_i32);
-        if t_min > tz_max || tz_min > t_max {
-            return false;
-        }
-        if tz_min > t_min {
-            t_min = tz_min;
+    /// Slab test like **intersect_b()**, but with the reciprocal of
+    /// the ray direction and its sign bits (1 for negative components)
+    /// precomputed, as the accelerators test the same ray against many
+    /// boxes. Returns the parametric range of the ray inside the box,
+    /// clipped to [0, _t_max_], which is the same as the range found by
+    /// **intersect_b()**:
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Bounds3f, Point3f, Ray, Vector3f};
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::rng::Rng;
+    ///
+    /// let mut rng: Rng = Rng::new();
+    /// let mut random_point = || Point3f {
+    ///     x: 4.0 * rng.uniform_float() - 2.0,
+    ///     y: 4.0 * rng.uniform_float() - 2.0,
+    ///     z: 4.0 * rng.uniform_float() - 2.0,
+    /// };
+    /// let b = Bounds3f::new(
+    ///     Point3f { x: -1.0, y: -0.5, z: 0.0 },
+    ///     Point3f { x: 0.5, y: 1.0, z: 0.25 },
+    /// );
+    /// let mut n_hits: usize = 0;
+    /// for i in 0..10_000 {
+    ///     let o: Point3f = random_point();
+    ///     let ray = Ray {
+    ///         o,
+    ///         d: random_point() - o,
+    ///         t_max: if i % 2 == 0 { std::f32::INFINITY } else { 0.5 },
+    ///         time: 0.0,
+    ///         differential: None,
+    ///         medium: None,
+    ///     };
+    ///     let inv_dir = Vector3f {
+    ///         x: 1.0 / ray.d.x,
+    ///         y: 1.0 / ray.d.y,
+    ///         z: 1.0 / ray.d.z,
+    ///     };
+    ///     let dir_is_neg: [usize; 3] = [
+    ///         (inv_dir.x < 0.0) as usize,
+    ///         (inv_dir.y < 0.0) as usize,
+    ///         (inv_dir.z < 0.0) as usize,
+    ///     ];
+    ///     let (mut t0, mut t1): (Float, Float) = (0.0, 0.0);
+    ///     let hit: bool = b.intersect_b(&ray, &mut t0, &mut t1);
+    ///     let range = b.intersect_b_precomp(&ray, &inv_dir, dir_is_neg);
+    ///     assert_eq!(range.is_some(), hit);
+    ///     if hit {
+    ///         assert_eq!(range, Some((t0, t1)));
+    ///     }
+    ///     n_hits += hit as usize;
+    /// }
+    /// assert!(n_hits > 1000);
+    /// ```
+    ///
+    /// As in **intersect_b()**, the far distances are scaled by
+    /// 1 + 2 gamma(3), so rounding errors can only make the test
+    /// conservative: a ray grazing the box may count as a hit, but a
+    /// ray passing through the box is never missed. A zero direction
+    /// component turns into infinite distances. If the origin of such
+    /// an axis-parallel ray lies exactly on one of the slab planes, the
+    /// distance is 0 * inf = NaN, which fails all comparisons and so
+    /// leaves the range unchanged (the ray touches the slab).
+    #[inline]
+    pub fn intersect_b_precomp(
+        &self,
+        ray: &Ray,
+        inv_dir: &Vector3f,
+        dir_is_neg: [usize; 3],
+    ) -> Option<(Float, Float)> {
+        // the far distances are scaled to ensure robust bounds intersection
+        let scale: Float = 1.0 + 2.0 * gamma(3_i32);
+        let tx_near: Float = (self[dir_is_neg[0] as u8].x - ray.o.x) * inv_dir.x;
+        let tx_far: Float = (self[1_u8 - dir_is_neg[0] as u8].x - ray.o.x) * inv_dir.x * scale;
+        let ty_near: Float = (self[dir_is_neg[1] as u8].y - ray.o.y) * inv_dir.y;
+        let ty_far: Float = (self[1_u8 - dir_is_neg[1] as u8].y - ray.o.y) * inv_dir.y * scale;
+        let tz_near: Float = (self[dir_is_neg[2] as u8].z - ray.o.z) * inv_dir.z;
+        let tz_far: Float = (self[1_u8 - dir_is_neg[2] as u8].z - ray.o.z) * inv_dir.z * scale;
+        // a NaN distance fails the comparisons and is skipped
+        let mut t0: Float = 0.0;
+        let mut t1: Float = ray.t_max;
+        for t_near in [tx_near, ty_near, tz_near].iter() {
+            if *t_near > t0 {
+                t0 = *t_near;
+            }
         }
-        if tz_max < t_max {
-            t_max = tz_max;
+        for t_far in [tx_far, ty_far, tz_far].iter() {
+            if *t_far < t1 {
+                t1 = *t_far;
+            }
         }
-        (t_min < ray.t_max) && (t_max > 0.0)
+        if t0 > t1 {
+            return None;
+        }
+        Some((t0, t1))
+    }
+    /// Same as **intersect_b_precomp()**, for traversals which only
+    /// need to know whether the ray enters the box.
+    ///
+    /// A million random ray/box pairs, many of them with axis-parallel
+    /// rays (zero direction components, some with origins on the box
+    /// faces) or flat boxes, compared against a straightforward slab
+    /// test in double precision: every hit of the reference is found,
+    /// and extra hits only happen for rays grazing the box.
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Bounds3f, Point3f, Ray, Vector3f};
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::rng::Rng;
+    ///
+    /// // returns the range of the ray inside the box, empty if t0 > t1
+    /// fn reference(b: &Bounds3f, ray: &Ray) -> (f64, f64) {
+    ///     let mut t0: f64 = 0.0;
+    ///     let mut t1: f64 = ray.t_max as f64;
+    ///     for i in 0..3_u8 {
+    ///         let (lo, hi) = (b.p_min[i] as f64, b.p_max[i] as f64);
+    ///         let (o, d) = (ray.o[i] as f64, ray.d[i] as f64);
+    ///         if d == 0.0 {
+    ///             if o < lo || o > hi {
+    ///                 return (std::f64::INFINITY, std::f64::NEG_INFINITY);
+    ///             }
+    ///         } else {
+    ///             let (t_lo, t_hi) = ((lo - o) / d, (hi - o) / d);
+    ///             t0 = t0.max(t_lo.min(t_hi));
+    ///             t1 = t1.min(t_lo.max(t_hi));
+    ///         }
+    ///     }
+    ///     (t0, t1)
+    /// }
+    ///
+    /// let mut rng: Rng = Rng::new();
+    /// let mut coordinate = |rng: &mut Rng| 4.0 * rng.uniform_float() - 2.0;
+    /// let (mut n_hits, mut n_grazing): (usize, usize) = (0, 0);
+    /// for _ in 0..1_000_000 {
+    ///     let mut p0 = Point3f::default();
+    ///     let mut p1 = Point3f::default();
+    ///     let mut o = Point3f::default();
+    ///     let mut d = Vector3f::default();
+    ///     for i in 0..3_u8 {
+    ///         p0[i] = coordinate(&mut rng);
+    ///         // flat boxes
+    ///         p1[i] = if rng.uniform_float() < 0.1 {
+    ///             p0[i]
+    ///         } else {
+    ///             coordinate(&mut rng)
+    ///         };
+    ///         // origins on the box faces
+    ///         o[i] = match (rng.uniform_float() * 8.0) as u8 {
+    ///             0 => p0[i],
+    ///             1 => p1[i],
+    ///             _ => coordinate(&mut rng),
+    ///         };
+    ///         // zero direction components (of either sign)
+    ///         d[i] = match (rng.uniform_float() * 6.0) as u8 {
+    ///             0 => 0.0,
+    ///             1 => -0.0,
+    ///             _ => coordinate(&mut rng),
+    ///         };
+    ///     }
+    ///     if d == Vector3f::default() {
+    ///         // not a ray
+    ///         continue;
+    ///     }
+    ///     let b = Bounds3f::new(p0, p1);
+    ///     let t_max: Float = if rng.uniform_float() < 0.5 {
+    ///         std::f32::INFINITY
+    ///     } else {
+    ///         2.0 * rng.uniform_float()
+    ///     };
+    ///     let ray = Ray {
+    ///         o,
+    ///         d,
+    ///         t_max,
+    ///         time: 0.0,
+    ///         differential: None,
+    ///         medium: None,
+    ///     };
+    ///     let inv_dir = Vector3f {
+    ///         x: 1.0 / d.x,
+    ///         y: 1.0 / d.y,
+    ///         z: 1.0 / d.z,
+    ///     };
+    ///     let dir_is_neg: [usize; 3] = [
+    ///         (inv_dir.x < 0.0) as usize,
+    ///         (inv_dir.y < 0.0) as usize,
+    ///         (inv_dir.z < 0.0) as usize,
+    ///     ];
+    ///     let hit: bool = b.intersect_p_precomp(&ray, &inv_dir, dir_is_neg);
+    ///     let (t0, t1) = reference(&b, &ray);
+    ///     if t0 <= t1 {
+    ///         assert!(hit, "missed {:?} with {:?} {:?}", b, o, d);
+    ///         n_hits += 1;
+    ///     } else if hit {
+    ///         // the gap between entering and leaving is a rounding error
+    ///         assert!(t0 - t1 <= 1e-5 * t0.abs().max(1.0), "{:?} {:?} {:?}", b, o, d);
+    ///         n_grazing += 1;
+    ///     }
+    /// }
+    /// assert!(n_hits > 100_000);
+    /// assert!(n_grazing < 100);
+    /// ```
+    #[inline]
+    pub fn intersect_p_precomp(
+        &self,
+        ray: &Ray,
+        inv_dir: &Vector3f,
+        dir_is_neg: [usize; 3],
+    ) -> bool {
+        self.intersect_b_precomp(ray, inv_dir, dir_is_neg).is_some()
     }
 }
 
@@ -1935,6 +2201,9 @@ impl Ray {
     }
 }
 
+/// Two auxiliary rays, offset by one pixel in x and y on the film,
+/// which let **SurfaceInteraction::compute_differentials()** estimate
+/// the screen-space footprint of a hit for texture filtering.
 #[derive(Debug, Default, Copy, Clone)]
 pub struct RayDifferential {
     pub rx_origin: Point3f,
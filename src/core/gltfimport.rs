@@ -0,0 +1,494 @@
+//! Imports glTF 2.0 scenes (optional, see the **gltf** feature).
+//!
+//! Instead of building primitives, lights and the camera directly,
+//! the importer makes the same API calls the scene file parser does
+//! (**pbrt_camera()**, **pbrt_texture()**,
+//! **pbrt_make_named_material()**, **pbrt_shape()**, ...), so the
+//! rendering code doesn't know where the scene came from:
+//!
+//! - the node hierarchy is flattened, each node's transformation is
+//!   concatenated with the current transformation
+//! - the primitives of each mesh become "trianglemesh" shapes (with
+//!   positions, normals, tangents, the first set of texture
+//!   coordinates and indices)
+//! - metallic-roughness materials become "disney" materials, with
+//!   "imagemap" textures for the base color, the metallic and
+//!   roughness channels and the normal map
+//! - punctual lights (KHR_lights_punctual) become "point", "spot" or
+//!   "distant" lights
+//! - the first camera becomes a "perspective" (or "orthographic")
+//!   camera
+//!
+//! Images embedded in a binary buffer (or a data URI) and emission are
+//! not supported (yet).
+
+// std
+use std::path::{Path, PathBuf};
+// others
+use gltf::camera::Projection;
+use gltf::khr_lights_punctual::Kind;
+use gltf::mesh::Mode;
+use gltf::texture::WrappingMode;
+use gltf::Document;
+// pbrt
+use crate::core::api::{
+    pbrt_attribute_begin, pbrt_attribute_end, pbrt_camera, pbrt_concat_transform,
+    pbrt_light_source, pbrt_make_named_material, pbrt_named_material, pbrt_shape, pbrt_texture,
+    ApiState, BsdfState,
+};
+use crate::core::geometry::Point3f;
+use crate::core::paramset::ParamSet;
+use crate::core::pbrt::{degrees, Float};
+use crate::core::transform::Transform;
+
+/// Luminous efficacy (in lm/W) used to convert the photometric light
+/// units of glTF to radiometric ones
+const LUMINOUS_EFFICACY: Float = 683.0;
+
+pub struct GltfImport {
+    pub document: Document,
+    pub buffers: Vec<gltf::buffer::Data>,
+    /// Directory external buffers and images are relative to
+    pub base: PathBuf,
+}
+
+impl GltfImport {
+    /// Reads a .gltf (or .glb) file and the buffers it references.
+    /// Images are read later by the "imagemap" textures.
+    pub fn open(path: &Path) -> gltf::Result<GltfImport> {
+        let (document, buffers, _images) = gltf::import(path)?;
+        let mut base: PathBuf = match path.parent() {
+            Some(parent) => parent.to_path_buf(),
+            None => PathBuf::new(),
+        };
+        if base.is_relative() {
+            if let Ok(current_dir) = std::env::current_dir() {
+                base = current_dir.join(base);
+            }
+        }
+        Ok(GltfImport {
+            document,
+            buffers,
+            base,
+        })
+    }
+    /// All nodes of the (default) scene with their node-to-world
+    /// transformations.
+    pub fn nodes(&self) -> Vec<(gltf::Node<'_>, Transform)> {
+        let mut nodes: Vec<(gltf::Node<'_>, Transform)> = Vec::new();
+        let scene = match self.document.default_scene() {
+            Some(scene) => Some(scene),
+            None => self.document.scenes().next(),
+        };
+        if let Some(scene) = scene {
+            for node in scene.nodes() {
+                collect_nodes(node, &Transform::default(), &mut nodes);
+            }
+        }
+        nodes
+    }
+    /// Defines the camera from the first camera node (call before
+    /// **pbrt_world_begin()**). Returns false if there is no camera.
+    pub fn make_camera(&self, api_state: &mut ApiState) -> bool {
+        let nodes = self.nodes();
+        let (camera, node_to_world) = match nodes
+            .iter()
+            .find_map(|(node, t)| node.camera().map(|camera| (camera, *t)))
+        {
+            Some(found) => found,
+            None => return false,
+        };
+        // glTF cameras look down -z (right-handed), pbrt's down +z
+        let camera_to_world: Transform =
+            node_to_world * Transform::scale(1.0 as Float, 1.0 as Float, -1.0 as Float);
+        pbrt_concat_transform(api_state, &Transform::inverse(&camera_to_world));
+        let mut params: ParamSet = ParamSet::default();
+        match camera.projection() {
+            Projection::Perspective(perspective) => {
+                // pbrt's field of view spans the shorter image axis
+                let xres: i32 = api_state
+                    .render_options
+                    .film_params
+                    .find_one_int("xresolution", 1280);
+                let yres: i32 = api_state
+                    .render_options
+                    .film_params
+                    .find_one_int("yresolution", 720);
+                let yfov: Float = perspective.yfov() as Float;
+                let fov: Float = if xres < yres {
+                    2.0 as Float
+                        * ((yfov * 0.5 as Float).tan() * xres as Float / yres as Float).atan()
+                } else {
+                    yfov
+                };
+                params.name = String::from("perspective");
+                params.add_float(String::from("fov"), degrees(fov));
+            }
+            Projection::Orthographic(orthographic) => {
+                let xmag: Float = orthographic.xmag() as Float;
+                let ymag: Float = orthographic.ymag() as Float;
+                params.name = String::from("orthographic");
+                params.add_floats(String::from("screenwindow"), vec![-xmag, xmag, -ymag, ymag]);
+            }
+        }
+        pbrt_camera(api_state, params);
+        true
+    }
+    /// Defines materials, shapes and lights (call after
+    /// **pbrt_world_begin()**).
+    pub fn make_world(&self, api_state: &mut ApiState, bsdf_state: &mut BsdfState) {
+        let material_names: Vec<String> = self
+            .document
+            .materials()
+            .map(|material| self.make_material(api_state, bsdf_state, &material))
+            .collect();
+        let mut default_material_name: Option<String> = None;
+        for (node, node_to_world) in self.nodes() {
+            if let Some(mesh) = node.mesh() {
+                for primitive in mesh.primitives() {
+                    if primitive.mode() != Mode::Triangles {
+                        println!(
+                            "WARNING: glTF primitive mode {:?} not supported, skipping mesh \"{}\"",
+                            primitive.mode(),
+                            mesh.name().unwrap_or("")
+                        );
+                        continue;
+                    }
+                    let material_name: String = match primitive.material().index() {
+                        Some(index) => material_names[index].clone(),
+                        None => {
+                            if default_material_name.is_none() {
+                                default_material_name = Some(self.make_material(
+                                    api_state,
+                                    bsdf_state,
+                                    &primitive.material(),
+                                ));
+                            }
+                            default_material_name.clone().unwrap()
+                        }
+                    };
+                    if let Some(params) = self.triangle_mesh_params(&primitive) {
+                        pbrt_attribute_begin(api_state);
+                        pbrt_concat_transform(api_state, &node_to_world);
+                        let mut named_material: ParamSet = ParamSet::default();
+                        named_material.name = material_name;
+                        pbrt_named_material(api_state, named_material);
+                        pbrt_shape(api_state, bsdf_state, params);
+                        pbrt_attribute_end(api_state);
+                    }
+                }
+            }
+            if let Some(light) = node.light() {
+                let color: [f32; 3] = light.color();
+                let scale: Float = light.intensity() as Float / LUMINOUS_EFFICACY;
+                let rgb: [Float; 3] = [
+                    color[0] as Float * scale,
+                    color[1] as Float * scale,
+                    color[2] as Float * scale,
+                ];
+                let mut params: ParamSet = ParamSet::default();
+                // spot and directional lights shine down -z
+                let from: Point3f = Point3f::default();
+                let to: Point3f = Point3f {
+                    x: 0.0 as Float,
+                    y: 0.0 as Float,
+                    z: -1.0 as Float,
+                };
+                match light.kind() {
+                    Kind::Point => {
+                        params.name = String::from("point");
+                        params.add_rgb(String::from("I"), rgb);
+                    }
+                    Kind::Spot {
+                        inner_cone_angle,
+                        outer_cone_angle,
+                    } => {
+                        params.name = String::from("spot");
+                        params.add_rgb(String::from("I"), rgb);
+                        params.add_point3f(String::from("from"), from);
+                        params.add_point3f(String::from("to"), to);
+                        params.add_float(
+                            String::from("coneangle"),
+                            degrees(outer_cone_angle as Float),
+                        );
+                        params.add_float(
+                            String::from("conedeltaangle"),
+                            degrees((outer_cone_angle - inner_cone_angle) as Float),
+                        );
+                    }
+                    Kind::Directional => {
+                        params.name = String::from("distant");
+                        params.add_rgb(String::from("L"), rgb);
+                        params.add_point3f(String::from("from"), from);
+                        params.add_point3f(String::from("to"), to);
+                    }
+                }
+                pbrt_attribute_begin(api_state);
+                pbrt_concat_transform(api_state, &node_to_world);
+                pbrt_light_source(api_state, params);
+                pbrt_attribute_end(api_state);
+            }
+        }
+    }
+    /// Makes a named "disney" material (and its textures) for a
+    /// metallic-roughness material and returns its name.
+    fn make_material(
+        &self,
+        api_state: &mut ApiState,
+        bsdf_state: &mut BsdfState,
+        material: &gltf::Material,
+    ) -> String {
+        let name: String = match material.index() {
+            Some(index) => format!("gltf:material:{}", index),
+            None => String::from("gltf:material:default"),
+        };
+        let pbr = material.pbr_metallic_roughness();
+        let mut params: ParamSet = ParamSet::default();
+        params.name = name.clone();
+        params.add_string(String::from("type"), String::from("disney"));
+        // base color (the alpha channel is ignored)
+        let factor: [f32; 4] = pbr.base_color_factor();
+        let rgb: [Float; 3] = [factor[0] as Float, factor[1] as Float, factor[2] as Float];
+        let base_color: Option<String> = pbr.base_color_texture().and_then(|info| {
+            self.make_image_texture(
+                api_state,
+                &format!("{}:basecolor", name),
+                "spectrum",
+                &info.texture(),
+                "sRGB",
+                None,
+                1.0 as Float,
+            )
+        });
+        match base_color {
+            Some(texture_name) if rgb != [1.0 as Float; 3] => {
+                let mut scale: ParamSet = ParamSet::default();
+                scale.name = format!("{}:color", name);
+                scale.tex_type = String::from("spectrum");
+                scale.tex_name = String::from("scale");
+                scale.add_texture(String::from("tex1"), texture_name);
+                scale.add_rgb(String::from("tex2"), rgb);
+                pbrt_texture(api_state, scale);
+                params.add_texture(String::from("color"), format!("{}:color", name));
+            }
+            Some(texture_name) => params.add_texture(String::from("color"), texture_name),
+            None => params.add_rgb(String::from("color"), rgb),
+        }
+        // metalness (blue) and roughness (green) share one image
+        let metallic: Float = pbr.metallic_factor() as Float;
+        let roughness: Float = pbr.roughness_factor() as Float;
+        match pbr.metallic_roughness_texture() {
+            Some(info) => {
+                let texture = info.texture();
+                match self.make_image_texture(
+                    api_state,
+                    &format!("{}:metallic", name),
+                    "float",
+                    &texture,
+                    "linear",
+                    Some("b"),
+                    metallic,
+                ) {
+                    Some(texture_name) => {
+                        params.add_texture(String::from("metallic"), texture_name)
+                    }
+                    None => params.add_float(String::from("metallic"), metallic),
+                }
+                match self.make_image_texture(
+                    api_state,
+                    &format!("{}:roughness", name),
+                    "float",
+                    &texture,
+                    "linear",
+                    Some("g"),
+                    roughness,
+                ) {
+                    Some(texture_name) => {
+                        params.add_texture(String::from("roughness"), texture_name)
+                    }
+                    None => params.add_float(String::from("roughness"), roughness),
+                }
+            }
+            None => {
+                params.add_float(String::from("metallic"), metallic);
+                params.add_float(String::from("roughness"), roughness);
+            }
+        }
+        if let Some(normal) = material.normal_texture() {
+            if let Some(texture_name) = self.make_image_texture(
+                api_state,
+                &format!("{}:normal", name),
+                "spectrum",
+                &normal.texture(),
+                "linear",
+                None,
+                1.0 as Float,
+            ) {
+                params.add_texture(String::from("normalmap"), texture_name);
+            }
+        }
+        if material.emissive_factor() != [0.0_f32; 3] {
+            println!(
+                "WARNING: glTF material \"{}\" is emissive, emission is ignored",
+                material.name().unwrap_or(&name)
+            );
+        }
+        pbrt_make_named_material(api_state, bsdf_state, params);
+        name
+    }
+    /// Defines an "imagemap" texture for an image stored in a file.
+    /// Returns the texture name, or None if the image can't be used.
+    #[allow(clippy::too_many_arguments)]
+    fn make_image_texture(
+        &self,
+        api_state: &mut ApiState,
+        name: &str,
+        tex_type: &str,
+        texture: &gltf::Texture,
+        encoding: &str,
+        channel: Option<&str>,
+        scale: Float,
+    ) -> Option<String> {
+        let filename: PathBuf = match texture.source().source() {
+            gltf::image::Source::Uri { uri, .. } if !uri.starts_with("data:") => {
+                self.base.join(percent_decode(uri))
+            }
+            _ => {
+                println!(
+                    "WARNING: embedded glTF image {} not supported, ignoring texture \"{}\"",
+                    texture.source().index(),
+                    name
+                );
+                return None;
+            }
+        };
+        let sampler = texture.sampler();
+        if sampler.wrap_s() != sampler.wrap_t() {
+            println!(
+                "WARNING: different glTF wrap modes for s and t, using {:?} for texture \"{}\"",
+                sampler.wrap_s(),
+                name
+            );
+        }
+        let wrap: &str = match sampler.wrap_s() {
+            WrappingMode::ClampToEdge => "clamp",
+            // there is no mirrored repeat mode
+            _ => "repeat",
+        };
+        let mut params: ParamSet = ParamSet::default();
+        params.name = String::from(name);
+        params.tex_type = String::from(tex_type);
+        params.tex_name = String::from("imagemap");
+        params.add_string(
+            String::from("filename"),
+            String::from(filename.to_str().unwrap()),
+        );
+        params.add_string(String::from("wrap"), String::from(wrap));
+        params.add_string(String::from("encoding"), String::from(encoding));
+        if let Some(channel) = channel {
+            params.add_string(String::from("channel"), String::from(channel));
+        }
+        if scale != 1.0 as Float {
+            params.add_float(String::from("scale"), scale);
+        }
+        pbrt_texture(api_state, params);
+        Some(String::from(name))
+    }
+    /// Parameters of a "trianglemesh" shape for a primitive (None if
+    /// it has no positions).
+    fn triangle_mesh_params<'a>(&self, primitive: &'a gltf::Primitive<'a>) -> Option<ParamSet> {
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()].0[..]));
+        let positions: Vec<[f32; 3]> = reader.read_positions()?.collect();
+        let mut params: ParamSet = ParamSet::default();
+        params.name = String::from("trianglemesh");
+        let indices: Vec<i32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as i32).collect(),
+            None => (0..positions.len() as i32).collect(),
+        };
+        params.add_ints(String::from("indices"), indices);
+        params.add_point3fs(String::from("P"), flatten(&positions));
+        if let Some(normals) = reader.read_normals() {
+            let normals: Vec<[f32; 3]> = normals.collect();
+            params.add_normal3fs(String::from("N"), flatten(&normals));
+        }
+        if let Some(tangents) = reader.read_tangents() {
+            // the bitangent sign (w) follows from the texture
+            // coordinates
+            let tangents: Vec<[f32; 3]> = tangents.map(|t| [t[0], t[1], t[2]]).collect();
+            params.add_vector3fs(String::from("S"), flatten(&tangents));
+        }
+        if let Some(tex_coords) = reader.read_tex_coords(0) {
+            // glTF has (0,0) at the upper left corner of an image
+            let uvs: Vec<Float> = tex_coords
+                .into_f32()
+                .flat_map(|uv| vec![uv[0] as Float, 1.0 as Float - uv[1] as Float])
+                .collect();
+            params.add_floats(String::from("uv"), uvs);
+        }
+        Some(params)
+    }
+}
+
+fn collect_nodes<'a>(
+    node: gltf::Node<'a>,
+    parent_to_world: &Transform,
+    nodes: &mut Vec<(gltf::Node<'a>, Transform)>,
+) {
+    let node_to_world: Transform = *parent_to_world * to_transform(&node.transform().matrix());
+    let children = node.children();
+    nodes.push((node, node_to_world));
+    for child in children {
+        collect_nodes(child, &node_to_world, nodes);
+    }
+}
+
+/// Converts a (column-major) glTF matrix.
+fn to_transform(m: &[[f32; 4]; 4]) -> Transform {
+    Transform::new(
+        m[0][0] as Float,
+        m[1][0] as Float,
+        m[2][0] as Float,
+        m[3][0] as Float,
+        m[0][1] as Float,
+        m[1][1] as Float,
+        m[2][1] as Float,
+        m[3][1] as Float,
+        m[0][2] as Float,
+        m[1][2] as Float,
+        m[2][2] as Float,
+        m[3][2] as Float,
+        m[0][3] as Float,
+        m[1][3] as Float,
+        m[2][3] as Float,
+        m[3][3] as Float,
+    )
+}
+
+fn flatten(values: &[[f32; 3]]) -> Vec<Float> {
+    values
+        .iter()
+        .flat_map(|v| vec![v[0] as Float, v[1] as Float, v[2] as Float])
+        .collect()
+}
+
+/// Decodes %XX escapes of relative URIs (e.g. "%20" for a space).
+fn percent_decode(uri: &str) -> String {
+    let bytes: &[u8] = uri.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i: usize = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex: Option<u8> = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = hex {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,166 @@
+//! Reading and writing of high dynamic range images (see imageio.h).
+//!
+//! OpenEXR files are read and written with the _exr_ crate, which
+//! keeps the full floating point precision of rendered images.
+
+// std
+use std::io::{Error, ErrorKind, Result};
+// others
+use exr::prelude::{
+    f16, read_first_flat_layer_from_file, AnyChannel, AnyChannels, Encoding, FlatSamples, Image,
+    IntegerBounds, Layer, LayerAttributes, SmallVec, Vec2, WritableImage,
+};
+// pbrt
+use crate::core::geometry::{Bounds2i, Point2i};
+use crate::core::pbrt::Float;
+
+// see imageio.cpp
+
+/// Writes an OpenEXR file (ZIP compressed scanlines). Each channel is
+/// given by its name and one value per pixel of the data window (in
+/// scanline order). The data window is placed within the display
+/// window defined by _full_resolution_, which preserves the offset of
+/// a crop window. Values are stored as 32-bit floats, or as 16-bit
+/// floats if _half_float_ is true.
+pub fn write_exr(
+    filename: &str,
+    channels: &[(&str, &[Float])],
+    data_window: &Bounds2i,
+    full_resolution: &Point2i,
+    half_float: bool,
+) -> Result<()> {
+    let width: usize = (data_window.p_max.x - data_window.p_min.x) as usize;
+    let height: usize = (data_window.p_max.y - data_window.p_min.y) as usize;
+    let mut list: SmallVec<[AnyChannel<FlatSamples>; 4]> = SmallVec::new();
+    for (name, values) in channels {
+        assert!(
+            values.len() == width * height,
+            "EXR channel {:?} has {} values, expected {}",
+            name,
+            values.len(),
+            width * height
+        );
+        let samples: FlatSamples = if half_float {
+            FlatSamples::F16(values.iter().map(|v| f16::from_f32(*v as f32)).collect())
+        } else {
+            FlatSamples::F32(values.iter().map(|v| *v as f32).collect())
+        };
+        list.push(AnyChannel::new(*name, samples));
+    }
+    let mut attributes: LayerAttributes = LayerAttributes::default();
+    attributes.layer_position = Vec2(data_window.p_min.x, data_window.p_min.y);
+    // channels are stored in alphabetical order
+    let layer = Layer::new(
+        (width, height),
+        attributes,
+        Encoding::SMALL_LOSSLESS,
+        AnyChannels::sort(list),
+    );
+    let mut image = Image::from_layer(layer);
+    image.attributes.display_window = IntegerBounds::new(
+        (0, 0),
+        (full_resolution.x as usize, full_resolution.y as usize),
+    );
+    image.write().to_file(filename).map_err(exr_error)
+}
+
+/// Reads the first layer of an OpenEXR file, e.g. one written by
+/// write_exr(). Returns the data window and the values of each
+/// channel in scanline order.
+///
+/// ```rust
+/// use pbrt::core::geometry::{Bounds2i, Point2i};
+/// use pbrt::core::imageio::{read_exr, write_exr};
+/// use pbrt::core::pbrt::Float;
+///
+/// let data_window = Bounds2i {
+///     p_min: Point2i { x: 1, y: 2 },
+///     p_max: Point2i { x: 4, y: 4 },
+/// };
+/// let y: Vec<Float> = vec![0.0, 0.25, 1.5, -2.0, 1024.0, 3.0];
+/// let z: Vec<Float> = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+/// let path = std::env::temp_dir().join("pbrt_read_exr_doctest.exr");
+/// let filename = path.to_str().unwrap();
+/// for half_float in &[false, true] {
+///     write_exr(
+///         filename,
+///         &[("Z", &z), ("Y", &y)],
+///         &data_window,
+///         &Point2i { x: 8, y: 8 },
+///         *half_float,
+///     )
+///     .unwrap();
+///     let (window, channels) = read_exr(filename).unwrap();
+///     assert_eq!(window.p_min, data_window.p_min);
+///     assert_eq!(window.p_max, data_window.p_max);
+///     // channels are stored in alphabetical order
+///     assert_eq!(channels.len(), 2);
+///     assert_eq!(channels[0], (String::from("Y"), y.clone()));
+///     assert_eq!(channels[1], (String::from("Z"), z.clone()));
+/// }
+/// std::fs::remove_file(&path).unwrap();
+/// ```
+pub fn read_exr(filename: &str) -> Result<(Bounds2i, Vec<(String, Vec<Float>)>)> {
+    let image = read_first_flat_layer_from_file(filename).map_err(exr_error)?;
+    let layer = image.layer_data;
+    let position: Vec2<i32> = layer.attributes.layer_position;
+    let data_window: Bounds2i = Bounds2i {
+        p_min: Point2i {
+            x: position.x(),
+            y: position.y(),
+        },
+        p_max: Point2i {
+            x: position.x() + layer.size.width() as i32,
+            y: position.y() + layer.size.height() as i32,
+        },
+    };
+    let channels: Vec<(String, Vec<Float>)> = layer
+        .channel_data
+        .list
+        .into_iter()
+        .map(|channel| {
+            let values: Vec<Float> = channel
+                .sample_data
+                .values_as_f32()
+                .map(|v| v as Float)
+                .collect();
+            (channel.name.to_string(), values)
+        })
+        .collect();
+    Ok((data_window, channels))
+}
+
+/// Reads the _R_, _G_ and _B_ channels of an OpenEXR file (see
+/// read_exr()), e.g. for the image of a light source. Returns the
+/// resolution of the data window and the RGB values of each pixel in
+/// scanline order.
+pub fn read_exr_rgb(filename: &str) -> Result<(Point2i, Vec<[Float; 3]>)> {
+    let (data_window, channels) = read_exr(filename)?;
+    let mut rgb: Vec<&Vec<Float>> = Vec::with_capacity(3);
+    for name in &["R", "G", "B"] {
+        match channels.iter().find(|(channel, _values)| channel == name) {
+            Some((_channel, values)) => rgb.push(values),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("missing channel {}", name),
+                ))
+            }
+        }
+    }
+    let resolution: Point2i = Point2i {
+        x: data_window.p_max.x - data_window.p_min.x,
+        y: data_window.p_max.y - data_window.p_min.y,
+    };
+    let values: Vec<[Float; 3]> = (0..rgb[0].len())
+        .map(|i| [rgb[0][i], rgb[1][i], rgb[2][i]])
+        .collect();
+    Ok((resolution, values))
+}
+
+fn exr_error(error: exr::error::Error) -> Error {
+    match error {
+        exr::error::Error::Io(error) => error,
+        error => Error::new(ErrorKind::InvalidData, error.to_string()),
+    }
+}
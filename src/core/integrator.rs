@@ -4,15 +4,23 @@
 // std
 use std;
 use std::sync::Arc;
+use std::time::Instant;
+// others
+use rayon::prelude::*;
 // pbrt
 use crate::blockqueue::BlockQueue;
 use crate::core::camera::{Camera, CameraSample};
-use crate::core::geometry::{pnt2_inside_exclusive, vec3_abs_dot_nrm};
-use crate::core::geometry::{Bounds2i, Point2f, Point2i, Ray, Vector2i, Vector3f};
+use crate::core::film::Film;
+use crate::core::geometry::{pnt2_inside_exclusive, pnt3_distance, vec3_abs_dot_nrm};
+use crate::core::geometry::{
+    Bounds2i, Normal3f, Point2f, Point2i, Point3f, Ray, Vector2i, Vector3f,
+};
 use crate::core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
 use crate::core::light::is_delta_light;
 use crate::core::light::{Light, VisibilityTester};
+use crate::core::material::TransportMode;
 use crate::core::pbrt::{Float, Spectrum};
+use crate::core::progressreporter::{CancellationToken, NoProgress, ProgressBar, RenderProgress};
 use crate::core::reflection::BxdfType;
 use crate::core::sampler::Sampler;
 use crate::core::sampling::power_heuristic;
@@ -23,227 +31,627 @@ use crate::integrators::bdpt::BDPTIntegrator;
 use crate::integrators::directlighting::DirectLightingIntegrator;
 use crate::integrators::mlt::MLTIntegrator;
 use crate::integrators::path::PathIntegrator;
+use crate::integrators::restir::ReSTIRDirectIntegrator;
 use crate::integrators::sppm::SPPMIntegrator;
 use crate::integrators::volpath::VolPathIntegrator;
 use crate::integrators::whitted::WhittedIntegrator;
 
 // see integrator.h
 
+crate::stat_counter!("Integrator/Camera rays traced", N_CAMERA_RAYS);
+crate::stat_counter!("Integrator/Light samples taken", N_LIGHT_SAMPLES);
+
+/// Auxiliary feature values (AOVs) of the first surface seen by a
+/// camera ray, used to guide denoisers. Intersections without a BSDF
+/// (e.g. medium boundaries) are skipped, so the values always describe
+/// the next real surface along the ray. A ray leaving the scene keeps
+/// the default (black albedo, zero normal, zero depth) and _hit_ stays
+/// false.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct AOVSample {
+    /// Albedo (hemispherical-directional reflectance) of the BSDF
+    pub albedo: Spectrum,
+    /// Shading normal in world space
+    pub normal: Normal3f,
+    /// Distance from the camera ray origin to the surface
+    pub depth: Float,
+    pub hit: bool,
+}
+
+impl AOVSample {
+    /// Stratified sample points (4x4 grid) used to estimate the
+    /// albedo, independent of the pixel sampler, so requesting AOVs
+    /// doesn't change the beauty image.
+    fn albedo_samples() -> [Point2f; 16] {
+        let mut samples: [Point2f; 16] = [Point2f::default(); 16];
+        for y in 0..4 {
+            for x in 0..4 {
+                samples[y * 4 + x] = Point2f {
+                    x: (x as Float + 0.5 as Float) / 4.0 as Float,
+                    y: (y as Float + 0.5 as Float) / 4.0 as Float,
+                };
+            }
+        }
+        samples
+    }
+    /// Fills the AOVs from a surface interaction (with a BSDF) found
+    /// along a camera ray starting at _ray_origin_.
+    pub fn record(&mut self, isect: &SurfaceInteraction, ray_origin: &Point3f) {
+        if let Some(ref bsdf) = isect.bsdf {
+            self.albedo = bsdf.rho(
+                &isect.wo,
+                &AOVSample::albedo_samples(),
+                BxdfType::BsdfAll as u8,
+            );
+            self.normal = isect.shading.n;
+            self.depth = pnt3_distance(&isect.p, ray_origin);
+            self.hit = true;
+        }
+    }
+}
+
+/// Periodically saves the film of a render (see
+/// **Film::write_checkpoint()**), so a render that got interrupted can
+/// continue where it stopped instead of starting over.
+#[derive(Debug, Clone)]
+pub struct RenderCheckpoint {
+    /// File the checkpoint is written to (and resumed from)
+    pub filename: String,
+    /// Minimum time between two checkpoints in seconds (0 writes one
+    /// after every pass)
+    pub interval: Float,
+    /// Hash of the scene description, checkpoints of other scenes are
+    /// rejected
+    pub scene_hash: u64,
+    /// Continue from an existing checkpoint
+    pub resume: bool,
+}
+
+/// All integrators, rendered through **Render**. Those with their own
+/// render loop (e.g. BDPT or SPPM) are variants of their own,
+/// integrators which only compute the radiance along camera rays share
+/// the tile based render loop of **SamplerRender**.
 pub enum Integrator {
     BDPT(BDPTIntegrator),
     MLT(MLTIntegrator),
+    ReSTIR(ReSTIRDirectIntegrator),
     SPPM(SPPMIntegrator),
     Sampler(SamplerIntegrator),
 }
 
 impl Integrator {
+    /// Renders the scene and writes the image, showing a progress bar
+    /// on stderr.
     pub fn render(&mut self, scene: &Scene, num_threads: u8) {
+        let progress: ProgressBar = ProgressBar::new();
+        let cancel: CancellationToken = CancellationToken::new();
+        self.render_with_progress(scene, num_threads, &progress, &cancel);
+    }
+    /// Like render(), but reports finished work units to _progress_.
+    /// Once _cancel_ is cancelled no new work units are started and
+    /// the partially rendered image is written.
+    pub fn render_with_progress(
+        &mut self,
+        scene: &Scene,
+        num_threads: u8,
+        progress: &dyn RenderProgress,
+        cancel: &CancellationToken,
+    ) {
+        let integrator: &mut dyn Render = match self {
+            Integrator::BDPT(integrator) => integrator,
+            Integrator::MLT(integrator) => integrator,
+            Integrator::ReSTIR(integrator) => integrator,
+            Integrator::SPPM(integrator) => integrator,
+            Integrator::Sampler(integrator) => integrator,
+        };
+        integrator.render(scene, num_threads, progress, cancel);
+    }
+    pub fn get_camera(&self) -> Arc<Camera> {
         match self {
-            Integrator::BDPT(integrator) => integrator.render(scene, num_threads),
-            Integrator::MLT(integrator) => integrator.render(scene, num_threads),
-            Integrator::SPPM(integrator) => integrator.render(scene, num_threads),
-            Integrator::Sampler(integrator) => integrator.render(scene, num_threads),
+            Integrator::BDPT(integrator) => integrator.camera.clone(),
+            Integrator::MLT(integrator) => integrator.camera.clone(),
+            Integrator::ReSTIR(integrator) => integrator.get_camera(),
+            Integrator::SPPM(integrator) => integrator.camera.clone(),
+            Integrator::Sampler(integrator) => integrator.get_camera(),
         }
     }
 }
 
-pub enum SamplerIntegrator {
-    AO(AOIntegrator),
-    DirectLighting(DirectLightingIntegrator),
-    Path(PathIntegrator),
-    VolPath(VolPathIntegrator),
-    Whitted(WhittedIntegrator),
+/// Renders the scene in passes of one sample per pixel (or of
+/// _min_samples_ for adaptive sampling) and writes a checkpoint after
+/// a pass once _checkpoint.interval_ seconds have passed since the
+/// last one. With _checkpoint.resume_ the film is restored from the
+/// checkpoint first and rendering continues with the next pass, so
+/// for the same scene the final image is the same as the one of an
+/// uninterrupted run. Only the sampler integrators support
+/// checkpoints, the others ignore them.
+pub fn render_with_checkpoints(
+    scene: &Scene,
+    integrator: &mut Integrator,
+    num_threads: u8,
+    checkpoint: &RenderCheckpoint,
+) {
+    match integrator {
+        Integrator::Sampler(integrator) => {
+            let progress: ProgressBar = ProgressBar::new();
+            let cancel: CancellationToken = CancellationToken::new();
+            integrator.render_passes(
+                scene,
+                num_threads,
+                &progress,
+                &cancel,
+                None,
+                Some(checkpoint),
+            );
+        }
+        _ => {
+            println!("Integrator doesn't support checkpoints, rendering all samples ...");
+            integrator.render(scene, num_threads);
+        }
+    }
 }
 
-impl SamplerIntegrator {
-    pub fn preprocess(&mut self, scene: &Scene) {
-        match self {
-            SamplerIntegrator::AO(integrator) => integrator.preprocess(scene),
-            SamplerIntegrator::DirectLighting(integrator) => integrator.preprocess(scene),
-            SamplerIntegrator::Path(integrator) => integrator.preprocess(scene),
-            SamplerIntegrator::VolPath(integrator) => integrator.preprocess(scene),
-            SamplerIntegrator::Whitted(integrator) => integrator.preprocess(scene),
+/// Renders the scene progressively for interactive previews: each pass
+/// takes one more sample for all pixels and _on_pass_ gets the
+/// accumulated film and the number of finished passes (samples per
+/// pixel) afterwards. Only the sampler integrators and ReSTIR render
+/// in passes, the others render the whole image and call _on_pass_
+/// once.
+pub fn render_progressive<F>(
+    scene: &Scene,
+    integrator: &mut Integrator,
+    num_threads: u8,
+    on_pass: F,
+) where
+    F: Fn(&Film, usize),
+{
+    let cancel: CancellationToken = CancellationToken::new();
+    match integrator {
+        Integrator::Sampler(integrator) => {
+            integrator.render_progressive(scene, num_threads, &cancel, &on_pass)
+        }
+        Integrator::ReSTIR(integrator) => {
+            let progress: NoProgress = NoProgress::default();
+            integrator.render_passes(scene, num_threads, &progress, &cancel, Some(&on_pass))
+        }
+        _ => {
+            println!("Integrator doesn't support progressive rendering, rendering all samples ...");
+            let progress: ProgressBar = ProgressBar::new();
+            integrator.render_with_progress(scene, num_threads, &progress, &cancel);
+            on_pass(&integrator.get_camera().get_film(), 1_usize);
         }
     }
-    pub fn render(&mut self, scene: &Scene, num_threads: u8) {
-        match self {
-            _ => {
-                let film = self.get_camera().get_film();
-                let sample_bounds: Bounds2i = film.get_sample_bounds();
-                self.preprocess(scene);
-                let sample_extent: Vector2i = sample_bounds.diagonal();
-                let tile_size: i32 = 16;
-                let x: i32 = (sample_extent.x + tile_size - 1) / tile_size;
-                let y: i32 = (sample_extent.y + tile_size - 1) / tile_size;
-                let n_tiles: Point2i = Point2i { x, y };
-                // TODO: ProgressReporter reporter(nTiles.x * nTiles.y, "Rendering");
-                let num_cores: usize;
-                if num_threads == 0_u8 {
-                    num_cores = num_cpus::get();
-                } else {
-                    num_cores = num_threads as usize;
+}
+
+/// Renders a scene into the film of the integrator's camera and
+/// writes the image (see pbrt's Integrator::Render()). The integrators
+/// with a render loop of their own implement it directly, those
+/// computing the radiance along camera rays get it from
+/// **SamplerRender**.
+pub trait Render {
+    /// Renders the scene, reporting finished work units to
+    /// _progress_. Once _cancel_ is cancelled no new work units are
+    /// started and the partially rendered image is written.
+    fn render(
+        &mut self,
+        scene: &Scene,
+        num_threads: u8,
+        progress: &dyn RenderProgress,
+        cancel: &CancellationToken,
+    );
+}
+
+/// Integrators computing the radiance along camera rays (see pbrt's
+/// SamplerIntegrator), rendered by a common loop (see
+/// render_passes()): the image is split into tiles, the rendering
+/// threads generate camera rays for all pixel samples of a tile (with
+/// their own copy of the sampler, starting each pixel at a state
+/// derived from the pixel), and the tiles are merged into the film in
+/// tile order, so the image doesn't depend on the number of threads.
+/// A new integrator of this kind only provides li(), preprocess(), and
+/// access to its camera, sampler, and pixel bounds, and gets render()
+/// (see **Render**) for free. To be selected by the scene description
+/// it also gets a variant of **SamplerIntegrator**.
+pub trait SamplerRender: Sync {
+    fn preprocess(&mut self, scene: &Scene);
+    /// Computes the radiance arriving at the origin of the camera ray.
+    fn li(&self, ray: &mut Ray, scene: &Scene, sampler: &mut Box<Sampler>, depth: i32) -> Spectrum;
+    /// Like li(), but also reports the AOVs of the first surface hit
+    /// (see _AOVSample_). By default the camera ray is traced once more
+    /// for them.
+    fn li_aov(
+        &self,
+        ray: &mut Ray,
+        scene: &Scene,
+        sampler: &mut Box<Sampler>,
+        depth: i32,
+        aov: &mut AOVSample,
+    ) -> Spectrum {
+        first_hit_aov(ray, scene, aov);
+        self.li(ray, scene, sampler, depth)
+    }
+    fn get_camera(&self) -> Arc<Camera>;
+    fn get_sampler(&self) -> &Box<Sampler>;
+    fn get_pixel_bounds(&self) -> Bounds2i;
+    /// Renders one sample per pixel per pass over the whole image and
+    /// calls _on_pass_ with the accumulated film and the number of
+    /// finished passes after each of them. The samplers continue with
+    /// the next sample index in every pass, so for the deterministic
+    /// samplers (e.g. "sobol" or "halton") the final image is the same
+    /// as the one of a regular render.
+    fn render_progressive(
+        &mut self,
+        scene: &Scene,
+        num_threads: u8,
+        cancel: &CancellationToken,
+        on_pass: &dyn Fn(&Film, usize),
+    ) {
+        let progress: NoProgress = NoProgress::default();
+        self.render_passes(scene, num_threads, &progress, cancel, Some(on_pass), None);
+    }
+    /// The render loop behind render() and render_progressive(),
+    /// optionally calling _on_pass_ after each pass and writing
+    /// (or resuming from) a checkpoint (see
+    /// **render_with_checkpoints()**).
+    fn render_passes(
+        &mut self,
+        scene: &Scene,
+        num_threads: u8,
+        progress: &dyn RenderProgress,
+        cancel: &CancellationToken,
+        on_pass: Option<&dyn Fn(&Film, usize)>,
+        checkpoint: Option<&RenderCheckpoint>,
+    ) {
+        let film = self.get_camera().get_film();
+        let sample_bounds: Bounds2i = film.get_sample_bounds();
+        self.preprocess(scene);
+        let sample_extent: Vector2i = sample_bounds.diagonal();
+        let tile_size: i32 = film.tile_size;
+        let x: i32 = (sample_extent.x + tile_size - 1) / tile_size;
+        let y: i32 = (sample_extent.y + tile_size - 1) / tile_size;
+        let n_tiles: Point2i = Point2i { x, y };
+        // TODO: ProgressReporter reporter(nTiles.x * nTiles.y, "Rendering");
+        let num_cores: usize;
+        if num_threads == 0_u8 {
+            num_cores = num_cpus::get();
+        } else {
+            num_cores = num_threads as usize;
+        }
+        println!("Rendering with {:?} thread(s) ...", num_cores);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_cores)
+            .build()
+            .expect("Failed to create thread pool");
+        // adaptive sampling renders in passes of _min_samples_
+        // per pixel, until pixels converge or reach _max_samples_
+        let samples_per_pixel: i64 = self.get_sampler().get_samples_per_pixel();
+        let adaptive: bool = film.is_adaptive();
+        let write_aovs: bool = film.write_aovs;
+        let (mut samples_per_pass, max_samples): (i64, i64) = if adaptive {
+            let max_samples: i64 = std::cmp::min(film.max_samples as i64, samples_per_pixel);
+            (
+                std::cmp::min(film.min_samples as i64, max_samples),
+                max_samples,
+            )
+        } else {
+            (samples_per_pixel, samples_per_pixel)
+        };
+        if on_pass.is_some() || (checkpoint.is_some() && !adaptive) {
+            // progressive rendering (and checkpointing) takes
+            // one sample per pass
+            samples_per_pass = 1_i64;
+        }
+        let n_passes: i64 = (max_samples + samples_per_pass - 1) / samples_per_pass;
+        let mut first_pass: i64 = 0;
+        if let Some(checkpoint) = checkpoint {
+            if checkpoint.resume {
+                match film.read_checkpoint(&checkpoint.filename, checkpoint.scene_hash) {
+                    Ok(passes) => {
+                        println!(
+                            "Resuming from checkpoint {:?} after {} of {} pass(es) ...",
+                            checkpoint.filename, passes, n_passes
+                        );
+                        first_pass = passes as i64;
+                    }
+                    Err(e) => panic!(
+                        "Unable to resume from checkpoint {:?}: {}",
+                        checkpoint.filename, e
+                    ),
                 }
-                println!("Rendering with {:?} thread(s) ...", num_cores);
-                {
-                    let block_queue = BlockQueue::new(
-                        (
-                            (n_tiles.x * tile_size) as u32,
-                            (n_tiles.y * tile_size) as u32,
-                        ),
-                        (tile_size as u32, tile_size as u32),
-                        (0, 0),
-                    );
-                    let integrator = &self;
-                    let bq = &block_queue;
-                    let sampler = &self.get_sampler();
-                    let camera = &self.get_camera();
-                    let film = &film;
-                    let pixel_bounds = self.get_pixel_bounds().clone();
-                    crossbeam::scope(|scope| {
-                        let (pixel_tx, pixel_rx) = crossbeam_channel::bounded(num_cores);
-                        // spawn worker threads
-                        for _ in 0..num_cores {
-                            let pixel_tx = pixel_tx.clone();
-                            let mut tile_sampler: Box<Sampler> =
-                                sampler.clone_with_seed(0_u64);
-                            scope.spawn(move |_| {
-                                while let Some((x, y)) = bq.next() {
-                                    let tile: Point2i = Point2i {
-                                        x: x as i32,
-                                        y: y as i32,
-                                    };
-                                    let seed: i32 = tile.y * n_tiles.x + tile.x;
-                                    tile_sampler.reseed(seed as u64);
-                                    let x0: i32 = sample_bounds.p_min.x + tile.x * tile_size;
-                                    let x1: i32 =
-                                        std::cmp::min(x0 + tile_size, sample_bounds.p_max.x);
-                                    let y0: i32 = sample_bounds.p_min.y + tile.y * tile_size;
-                                    let y1: i32 =
-                                        std::cmp::min(y0 + tile_size, sample_bounds.p_max.y);
-                                    let tile_bounds: Bounds2i = Bounds2i::new(
-                                        Point2i { x: x0, y: y0 },
-                                        Point2i { x: x1, y: y1 },
-                                    );
-                                    // println!("Starting image tile {:?}", tile_bounds);
-                                    let mut film_tile = film.get_film_tile(&tile_bounds);
-                                    for pixel in &tile_bounds {
-                                        tile_sampler.start_pixel(&pixel);
-                                        if !pnt2_inside_exclusive(&pixel, &pixel_bounds) {
+            }
+        }
+        let mut last_checkpoint: Instant = Instant::now();
+        for pass in first_pass..n_passes {
+            let first_sample: i64 = pass * samples_per_pass;
+            let last_sample: i64 = std::cmp::min(first_sample + samples_per_pass, max_samples);
+            // skip converged pixels once they have _min_samples_
+            let skip_converged: bool =
+                adaptive && pass > 0 && first_sample >= film.min_samples as i64;
+            if skip_converged {
+                let mut n_active: usize = 0;
+                for pixel in &film.get_cropped_pixel_bounds() {
+                    if film.needs_more_samples(&pixel) {
+                        n_active += 1;
+                    }
+                }
+                if n_active == 0 {
+                    break;
+                }
+                println!(
+                    "Adaptive sampling pass {} (samples {}..{}): {} pixel(s) above error threshold",
+                    pass, first_sample, last_sample, n_active
+                );
+            }
+            let block_queue = BlockQueue::new(
+                (
+                    (n_tiles.x * tile_size) as u32,
+                    (n_tiles.y * tile_size) as u32,
+                ),
+                (tile_size as u32, tile_size as u32),
+                (0, 0),
+            );
+            let integrator = &self;
+            let bq = &block_queue;
+            let sampler = &self.get_sampler();
+            let camera = &self.get_camera();
+            let film = &film;
+            let pixel_bounds = self.get_pixel_bounds().clone();
+            crossbeam::scope(|scope| {
+                let (pixel_tx, pixel_rx) = crossbeam_channel::bounded(num_cores);
+                // spawn thread to collect pixels and render image to file
+                scope.spawn(move |_| {
+                    // merge image tiles into _Film_ in queue
+                    // order, for the same result with any
+                    // number of threads
+                    film.merge_film_tiles_in_order(pixel_rx.iter(), cancel, &mut |completed| {
+                        progress.on_tile_done(completed, bq.len())
+                    });
+                });
+                // work through the tiles on the thread pool, rayon
+                // balances the load by work-stealing while the
+                // shared counter of the block queue hands out the
+                // tiles (in Morton order)
+                let tx = &pixel_tx;
+                pool.install(|| {
+                    (0..bq.len()).into_par_iter().for_each_init(
+                        || sampler.clone_with_seed(0_u64),
+                        |tile_sampler, _| {
+                            if cancel.is_cancelled() {
+                                return;
+                            }
+                            if let Some((index, (x, y))) = bq.next_indexed() {
+                                let tile: Point2i = Point2i {
+                                    x: x as i32,
+                                    y: y as i32,
+                                };
+                                let x0: i32 = sample_bounds.p_min.x + tile.x * tile_size;
+                                let x1: i32 = std::cmp::min(x0 + tile_size, sample_bounds.p_max.x);
+                                let y0: i32 = sample_bounds.p_min.y + tile.y * tile_size;
+                                let y1: i32 = std::cmp::min(y0 + tile_size, sample_bounds.p_max.y);
+                                let tile_bounds: Bounds2i = Bounds2i::new(
+                                    Point2i { x: x0, y: y0 },
+                                    Point2i { x: x1, y: y1 },
+                                );
+                                // println!("Starting image tile {:?}", tile_bounds);
+                                let mut film_tile = film.get_film_tile(&tile_bounds);
+                                for pixel in &tile_bounds {
+                                    tile_sampler.start_pixel(&pixel);
+                                    if !pnt2_inside_exclusive(&pixel, &pixel_bounds) {
+                                        continue;
+                                    }
+                                    if pass > 0 {
+                                        // resume converging pixels only
+                                        if skip_converged && !film.needs_more_samples(&pixel) {
                                             continue;
                                         }
-                                        let mut done: bool = false;
-                                        while !done {
-                                            // let's use the copy_arena crate instead of pbrt's MemoryArena
-                                            // let mut arena: Arena = Arena::with_capacity(262144); // 256kB
+                                        // same seed in every pass, so
+                                        // the pixel samplers rebuild the
+                                        // same pattern in start_pixel()
+                                        // and continue it here
+                                        tile_sampler.set_sample_number(first_sample);
+                                    }
+                                    let mut done: bool = false;
+                                    while !done {
+                                        // let's use the copy_arena crate instead of pbrt's MemoryArena
+                                        // let mut arena: Arena = Arena::with_capacity(262144); // 256kB
 
-                                            // initialize _CameraSample_ for current sample
-                                            let camera_sample: CameraSample =
-                                                tile_sampler.get_camera_sample(&pixel);
-                                            // generate camera ray for current sample
-                                            let mut ray: Ray = Ray::default();
-                                            let ray_weight: Float = camera
-                                                .generate_ray_differential(
-                                                    &camera_sample,
+                                        // initialize _CameraSample_ for current sample
+                                        let camera_sample: CameraSample =
+                                            tile_sampler.get_camera_sample(&pixel);
+                                        // generate camera ray for current sample
+                                        let mut ray: Ray = Ray::default();
+                                        let ray_weight: Float = camera
+                                            .generate_ray_differential(&camera_sample, &mut ray);
+                                        ray.scale_differentials(
+                                            1.0 as Float
+                                                / (tile_sampler.get_samples_per_pixel() as Float)
+                                                    .sqrt(),
+                                        );
+                                        crate::stat_inc!(N_CAMERA_RAYS);
+                                        // evaluate radiance along camera ray
+                                        let mut l: Spectrum = Spectrum::new(0.0 as Float);
+                                        let mut aov: AOVSample = AOVSample::default();
+                                        if scene.max_intersections > 0 {
+                                            scene.start_path();
+                                        }
+                                        if ray_weight > 0.0 {
+                                            if write_aovs {
+                                                l = integrator.li_aov(
                                                     &mut ray,
+                                                    scene,
+                                                    tile_sampler,
+                                                    0_i32,
+                                                    &mut aov,
                                                 );
-                                            ray.scale_differentials(
-                                                1.0 as Float
-                                                    / (tile_sampler.get_samples_per_pixel()
-                                                        as Float)
-                                                        .sqrt(),
-                                            );
-                                            // TODO: ++nCameraRays;
-                                            // evaluate radiance along camera ray
-                                            let mut l: Spectrum = Spectrum::new(0.0 as Float);
-                                            let y: Float = l.y();
-                                            if ray_weight > 0.0 {
+                                            } else {
                                                 l = integrator.li(
                                                     &mut ray,
                                                     scene,
-                                                    &mut tile_sampler, // &mut arena,
+                                                    tile_sampler, // &mut arena,
                                                     0_i32,
                                                 );
                                             }
-                                            if l.has_nans() {
-                                                println!(
-                                                    "Not-a-number radiance value returned for pixel \
-                                                     ({:?}, {:?}), sample {:?}. Setting to black.",
-                                                    pixel.x,
-                                                    pixel.y,
-                                                    tile_sampler.get_current_sample_number()
-                                                );
-                                                l = Spectrum::new(0.0);
-                                            } else if y < -10.0e-5 as Float {
-                                                println!(
-                                                "Negative luminance value, {:?}, returned for pixel \
+                                        }
+                                        let y: Float = l.y();
+                                        if l.has_nans() {
+                                            println!(
+                                                "Not-a-number radiance value returned for pixel \
                                                  ({:?}, {:?}), sample {:?}. Setting to black.",
-                                                y,
                                                 pixel.x,
                                                 pixel.y,
                                                 tile_sampler.get_current_sample_number()
-                                                );
-                                                l = Spectrum::new(0.0);
-                                            } else if y.is_infinite() {
-                                                println!(
-                                                "Infinite luminance value returned for pixel ({:?}, \
-                                                 {:?}), sample {:?}. Setting to black.",
-                                                pixel.x,
-                                                pixel.y,
-                                                tile_sampler.get_current_sample_number()
-                                                );
-                                                l = Spectrum::new(0.0);
-                                            }
-                                            // println!("Camera sample: {:?} -> ray: {:?} -> L = {:?}",
-                                            //          camera_sample, ray, l);
-                                            // add camera ray's contribution to image
-                                            film_tile.add_sample(
-                                                &camera_sample.p_film,
-                                                &mut l,
-                                                ray_weight,
                                             );
-                                            done = !tile_sampler.start_next_sample();
-                                        } // arena is dropped here !
-                                    }
-                                    // send the tile through the channel to main thread
-                                    pixel_tx
-                                        .send(film_tile)
-                                        .expect(&format!("Failed to send tile"));
+                                            l = Spectrum::new(0.0);
+                                        } else if y < -10.0e-5 as Float {
+                                            println!(
+                                            "Negative luminance value, {:?}, returned for pixel \
+                                             ({:?}, {:?}), sample {:?}. Setting to black.",
+                                            y,
+                                            pixel.x,
+                                            pixel.y,
+                                            tile_sampler.get_current_sample_number()
+                                            );
+                                            l = Spectrum::new(0.0);
+                                        } else if y.is_infinite() {
+                                            println!(
+                                            "Infinite luminance value returned for pixel ({:?}, \
+                                             {:?}), sample {:?}. Setting to black.",
+                                            pixel.x,
+                                            pixel.y,
+                                            tile_sampler.get_current_sample_number()
+                                            );
+                                            l = Spectrum::new(0.0);
+                                        }
+                                        // println!("Camera sample: {:?} -> ray: {:?} -> L = {:?}",
+                                        //          camera_sample, ray, l);
+                                        if adaptive {
+                                            film_tile.add_sample_luminance(&pixel, &l);
+                                        }
+                                        if write_aovs {
+                                            film_tile.add_aov_sample(&pixel, &aov);
+                                        }
+                                        if scene.max_intersections > 0 && scene.path_aborted() {
+                                            film_tile.add_aborted_sample(&pixel);
+                                        }
+                                        // add camera ray's contribution to image
+                                        film_tile.add_sample(
+                                            &camera_sample.p_film,
+                                            &mut l,
+                                            ray_weight,
+                                        );
+                                        done = !tile_sampler.start_next_sample()
+                                            || tile_sampler.get_current_sample_number()
+                                                >= last_sample;
+                                    } // arena is dropped here !
                                 }
-                            });
-                        }
-                        // spawn thread to collect pixels and render image to file
-                        scope.spawn(move |_| {
-                            for _ in pbr::PbIter::new(0..bq.len()) {
-                                let film_tile = pixel_rx.recv().unwrap();
-                                // merge image tile into _Film_
-                                film.merge_film_tile(&film_tile);
+                                // send the tile through the channel to main thread
+                                tx.send((index, film_tile))
+                                    .expect(&format!("Failed to send tile"));
                             }
-                        });
-                    })
-                    .unwrap();
+                        },
+                    );
+                });
+                // let the collecting thread finish
+                drop(pixel_tx);
+            })
+            .unwrap();
+            if let Some(on_pass) = on_pass {
+                on_pass(film, pass as usize + 1);
+            }
+            if let Some(checkpoint) = checkpoint {
+                // a cancelled pass is incomplete, don't save it
+                if !cancel.is_cancelled()
+                    && pass + 1 < n_passes
+                    && last_checkpoint.elapsed().as_secs_f64() >= checkpoint.interval as f64
+                {
+                    if let Err(e) = film.write_checkpoint(
+                        &checkpoint.filename,
+                        checkpoint.scene_hash,
+                        pass as usize + 1,
+                    ) {
+                        println!(
+                            "ERROR: writing checkpoint {:?} failed: {}",
+                            checkpoint.filename, e
+                        );
+                    }
+                    last_checkpoint = Instant::now();
                 }
-                film.write_image(1.0 as Float);
             }
+            if cancel.is_cancelled() {
+                println!("Rendering cancelled, writing partial image ...");
+                break;
+            }
+        }
+        film.write_image(1.0 as Float);
+        if adaptive && film.sample_heatmap {
+            film.write_sample_heatmap();
         }
     }
-    pub fn li(
-        &self,
-        ray: &mut Ray,
+}
+
+impl<T: SamplerRender> Render for T {
+    fn render(
+        &mut self,
         scene: &Scene,
-        sampler: &mut Box<Sampler>,
-        depth: i32,
-    ) -> Spectrum {
+        num_threads: u8,
+        progress: &dyn RenderProgress,
+        cancel: &CancellationToken,
+    ) {
+        self.render_passes(scene, num_threads, progress, cancel, None, None);
+    }
+}
+
+/// All integrators computing the radiance along camera rays, see
+/// **SamplerRender**.
+pub enum SamplerIntegrator {
+    AO(AOIntegrator),
+    DirectLighting(DirectLightingIntegrator),
+    Path(PathIntegrator),
+    VolPath(VolPathIntegrator),
+    Whitted(WhittedIntegrator),
+}
+
+impl SamplerRender for SamplerIntegrator {
+    fn preprocess(&mut self, scene: &Scene) {
+        match self {
+            SamplerIntegrator::AO(integrator) => integrator.preprocess(scene),
+            SamplerIntegrator::DirectLighting(integrator) => integrator.preprocess(scene),
+            SamplerIntegrator::Path(integrator) => integrator.preprocess(scene),
+            SamplerIntegrator::VolPath(integrator) => integrator.preprocess(scene),
+            SamplerIntegrator::Whitted(integrator) => integrator.preprocess(scene),
+        }
+    }
+    fn li(&self, ray: &mut Ray, scene: &Scene, sampler: &mut Box<Sampler>, depth: i32) -> Spectrum {
         match self {
             SamplerIntegrator::AO(integrator) => integrator.li(ray, scene, sampler, depth),
             SamplerIntegrator::DirectLighting(integrator) => {
                 integrator.li(ray, scene, sampler, depth)
             }
-            SamplerIntegrator::Path(integrator) => integrator.li(ray, scene, sampler, depth),
+            SamplerIntegrator::Path(integrator) => integrator.li(ray, scene, sampler, depth, None),
             SamplerIntegrator::VolPath(integrator) => integrator.li(ray, scene, sampler, depth),
             SamplerIntegrator::Whitted(integrator) => integrator.li(ray, scene, sampler, depth),
         }
     }
-    pub fn get_camera(&self) -> Arc<Camera> {
+    fn li_aov(
+        &self,
+        ray: &mut Ray,
+        scene: &Scene,
+        sampler: &mut Box<Sampler>,
+        depth: i32,
+        aov: &mut AOVSample,
+    ) -> Spectrum {
+        // only the path integrator records the AOVs while tracing
+        match self {
+            SamplerIntegrator::Path(integrator) => {
+                integrator.li(ray, scene, sampler, depth, Some(aov))
+            }
+            _ => {
+                first_hit_aov(ray, scene, aov);
+                self.li(ray, scene, sampler, depth)
+            }
+        }
+    }
+    fn get_camera(&self) -> Arc<Camera> {
         match self {
             SamplerIntegrator::AO(integrator) => integrator.get_camera(),
             SamplerIntegrator::DirectLighting(integrator) => integrator.get_camera(),
@@ -252,7 +660,7 @@ impl SamplerIntegrator {
             SamplerIntegrator::Whitted(integrator) => integrator.get_camera(),
         }
     }
-    pub fn get_sampler(&self) -> &Box<Sampler> {
+    fn get_sampler(&self) -> &Box<Sampler> {
         match self {
             SamplerIntegrator::AO(integrator) => integrator.get_sampler(),
             SamplerIntegrator::DirectLighting(integrator) => integrator.get_sampler(),
@@ -261,7 +669,7 @@ impl SamplerIntegrator {
             SamplerIntegrator::Whitted(integrator) => integrator.get_sampler(),
         }
     }
-    pub fn get_pixel_bounds(&self) -> Bounds2i {
+    fn get_pixel_bounds(&self) -> Bounds2i {
         match self {
             SamplerIntegrator::AO(integrator) => integrator.get_pixel_bounds(),
             SamplerIntegrator::DirectLighting(integrator) => integrator.get_pixel_bounds(),
@@ -270,6 +678,9 @@ impl SamplerIntegrator {
             SamplerIntegrator::Whitted(integrator) => integrator.get_pixel_bounds(),
         }
     }
+}
+
+impl SamplerIntegrator {
     pub fn specular_reflect(
         &self,
         ray: &Ray,
@@ -310,6 +721,107 @@ impl SamplerIntegrator {
 
 // see integrator.cpp
 
+/// Traces a copy of the camera ray to fill the AOVs for integrators
+/// which don't report them while computing radiance.
+pub fn first_hit_aov(ray: &Ray, scene: &Scene, aov: &mut AOVSample) {
+    let mut r: Ray = Ray {
+        o: ray.o,
+        d: ray.d,
+        t_max: ray.t_max,
+        time: ray.time,
+        differential: ray.differential,
+        medium: ray.medium.clone(),
+    };
+    while let Some(mut isect) = scene.intersect(&mut r) {
+        isect.compute_scattering_functions(&mut r, true, TransportMode::Radiance);
+        if isect.bsdf.is_some() {
+            aov.record(&isect, &ray.o);
+            return;
+        }
+        // skip over medium boundaries
+        r = isect.spawn_ray(&r.d);
+    }
+}
+
+/// Samples the perfectly specular reflection of the BSDF at *isect*
+/// and returns the radiance arriving along the reflected ray, as
+/// computed by *li* (called with the spawned ray and *depth* + 1).
+/// The recursion stops once *depth* + 1 reaches *max_depth*, so
+/// parallel mirrors terminate.
+pub fn specular_reflect(
+    ray: &Ray,
+    isect: &SurfaceInteraction,
+    sampler: &mut Box<Sampler>,
+    // arena: &mut Arena,
+    depth: i32,
+    max_depth: u32,
+    li: &mut dyn FnMut(&mut Ray, &mut Box<Sampler>, i32) -> Spectrum,
+) -> Spectrum {
+    specular_bounce(ray, isect, sampler, depth, max_depth, li, false)
+}
+
+/// Like **specular_reflect()**, but for the specular transmission
+/// lobe.
+pub fn specular_transmit(
+    ray: &Ray,
+    isect: &SurfaceInteraction,
+    sampler: &mut Box<Sampler>,
+    // arena: &mut Arena,
+    depth: i32,
+    max_depth: u32,
+    li: &mut dyn FnMut(&mut Ray, &mut Box<Sampler>, i32) -> Spectrum,
+) -> Spectrum {
+    specular_bounce(ray, isect, sampler, depth, max_depth, li, true)
+}
+
+fn specular_bounce(
+    ray: &Ray,
+    isect: &SurfaceInteraction,
+    sampler: &mut Box<Sampler>,
+    depth: i32,
+    max_depth: u32,
+    li: &mut dyn FnMut(&mut Ray, &mut Box<Sampler>, i32) -> Spectrum,
+    transmission: bool,
+) -> Spectrum {
+    if depth < 0 || depth as u32 + 1 >= max_depth {
+        return Spectrum::default();
+    }
+    if let Some(ref bsdf) = isect.bsdf {
+        // compute specular direction _wi_ and BSDF value
+        let wo: Vector3f = isect.wo;
+        let mut wi: Vector3f = Vector3f::default();
+        let mut pdf: Float = 0.0 as Float;
+        let ns: Normal3f = isect.shading.n;
+        let mut sampled_type: u8 = 0_u8;
+        let bsdf_flags: u8 = if transmission {
+            BxdfType::BsdfTransmission as u8 | BxdfType::BsdfSpecular as u8
+        } else {
+            BxdfType::BsdfReflection as u8 | BxdfType::BsdfSpecular as u8
+        };
+        let f: Spectrum = bsdf.sample_f(
+            &wo,
+            &mut wi,
+            &sampler.get_2d(),
+            &mut pdf,
+            bsdf_flags,
+            &mut sampled_type,
+        );
+        if pdf > 0.0 as Float && !f.is_black() && vec3_abs_dot_nrm(&wi, &ns) != 0.0 as Float {
+            // compute ray differential _rd_ for the specular bounce
+            let mut rd: Ray = isect.spawn_ray(&wi);
+            rd.differential = if transmission {
+                isect.specular_transmission_differential(ray, &wi, bsdf.eta)
+            } else {
+                isect.specular_reflection_differential(ray, &wi)
+            };
+            return f
+                * li(&mut rd, sampler, depth + 1)
+                * Spectrum::new(vec3_abs_dot_nrm(&wi, &ns) / pdf);
+        }
+    }
+    Spectrum::default()
+}
+
 /// Most basic direct lighting strategy.
 pub fn uniform_sample_all_lights(
     it: &SurfaceInteraction,
@@ -410,6 +922,20 @@ pub fn uniform_sample_one_light(
 }
 
 /// Computes a direct lighting estimate for a single light source sample.
+///
+/// Combines two strategies with the power heuristic: sampling *light*
+/// with *u_light*, and sampling the BSDF (or phase function for medium
+/// interactions) with *u_scattering*. Delta lights only use the light
+/// sample. With *handle_media* the sample is attenuated by the
+/// transmittance towards the light, otherwise it's only tested for
+/// occlusion. Specular lobes are ignored unless *specular* is set, so
+/// a BSDF with only specular components gets no direct lighting: the
+/// callers either skip it (PathIntegrator, VolPathIntegrator) or
+/// follow the specular bounce and pick up emission there
+/// (DirectLightingIntegrator), sampling it here would count those
+/// paths twice. The result is not divided by the light selection
+/// probability, so integrators choosing their own light (see
+/// **uniform_sample_one_light()**) can reuse it.
 pub fn estimate_direct(
     it: &dyn Interaction,
     u_scattering: &Point2f,
@@ -417,10 +943,11 @@ pub fn estimate_direct(
     u_light: &Point2f,
     scene: &Scene,
     sampler: &mut Box<Sampler>,
-    // TODO: arena
+    // no arena: the BSDF lives in the interaction
     handle_media: bool,
     specular: bool,
 ) -> Spectrum {
+    crate::stat_inc!(N_LIGHT_SAMPLES);
     let mut bsdf_flags: u8 = BxdfType::BsdfAll as u8;
     if !specular {
         // bitwise not in Rust is ! (not the ~ operator like in C)
@@ -572,7 +1099,7 @@ pub fn estimate_direct(
                     }
                 }
             }
-            if !found_surface_interaction {
+            if !found_surface_interaction && !scene.path_aborted() {
                 li = light.le(&mut ray);
             }
             if !li.is_black() {
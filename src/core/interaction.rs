@@ -13,9 +13,10 @@ use std::sync::{Arc, RwLock};
 // pbrt
 use crate::core::bssrdf::TabulatedBssrdf;
 use crate::core::geometry::{
-    nrm_faceforward_nrm, pnt3_offset_ray_origin, vec3_cross_vec3, vec3_dot_nrm, vec3_dot_vec3,
+    nrm_dot_nrm, nrm_faceforward_nrm, pnt3_offset_ray_origin, vec3_cross_vec3, vec3_dot_nrm,
+    vec3_dot_vec3,
 };
-use crate::core::geometry::{Normal3f, Point2f, Point3f, Ray, Vector3f};
+use crate::core::geometry::{Normal3f, Point2f, Point3f, Ray, RayDifferential, Vector3f};
 use crate::core::material::TransportMode;
 use crate::core::medium::{Medium, MediumInterface, HenyeyGreenstein};
 use crate::core::pbrt::SHADOW_EPSILON;
@@ -27,6 +28,15 @@ use crate::core::transform::solve_linear_system_2x2;
 
 // see interaction.h
 
+crate::stat_counter!(
+    "Geometry/Geometric normals flipped to the shading normal",
+    N_GEOMETRIC_NORMALS_FLIPPED
+);
+crate::stat_counter!(
+    "Geometry/Shading normals clamped to the visible hemisphere",
+    N_SHADING_NORMALS_CLAMPED
+);
+
 pub trait Interaction {
     fn is_surface_interaction(&self) -> bool;
     fn is_medium_interaction(&self) -> bool;
@@ -108,6 +118,45 @@ impl InteractionCommon {
     }
 }
 
+impl Interaction for InteractionCommon {
+    fn is_surface_interaction(&self) -> bool {
+        self.n != Normal3f::default()
+    }
+    fn is_medium_interaction(&self) -> bool {
+        !self.is_surface_interaction()
+    }
+    fn spawn_ray(&self, d: &Vector3f) -> Ray {
+        InteractionCommon::spawn_ray(self, d)
+    }
+    fn get_p(&self) -> Point3f {
+        self.p
+    }
+    fn get_time(&self) -> Float {
+        self.time
+    }
+    fn get_p_error(&self) -> Vector3f {
+        self.p_error
+    }
+    fn get_wo(&self) -> Vector3f {
+        self.wo
+    }
+    fn get_n(&self) -> Normal3f {
+        self.n
+    }
+    fn get_medium_interface(&self) -> Option<Arc<MediumInterface>> {
+        self.medium_interface.clone()
+    }
+    fn get_bsdf(&self) -> Option<&Bsdf> {
+        None
+    }
+    fn get_shading_n(&self) -> Option<Normal3f> {
+        None
+    }
+    fn get_phase(&self) -> Option<Arc<HenyeyGreenstein>> {
+        None
+    }
+}
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct Shading {
     pub n: Normal3f,
@@ -256,7 +305,6 @@ impl Interaction for MediumInteraction {
     }
 }
 
-#[derive(Default)]
 pub struct SurfaceInteraction<'a> {
     // Interaction Public Data
     pub p: Point3f,
@@ -282,6 +330,44 @@ pub struct SurfaceInteraction<'a> {
     pub bsdf: Option<Bsdf>,
     pub bssrdf: Option<TabulatedBssrdf>,
     pub shape: Option<&'a Shape>,
+    /// bend the shading normal into the hemisphere _wo_ is in (see
+    /// **correct_shading_normal()**), set by **Scene::intersect()**
+    pub clamp_shading_normals: bool,
+    /// reject directions on different sides of the surface with
+    /// respect to the geometric and the shading normal in the BSDF
+    /// (see **Bsdf::is_inconsistent()**), set by **Scene::intersect()**
+    pub check_geometric_normal: bool,
+}
+
+impl<'a> Default for SurfaceInteraction<'a> {
+    fn default() -> Self {
+        SurfaceInteraction {
+            p: Point3f::default(),
+            time: 0.0 as Float,
+            p_error: Vector3f::default(),
+            wo: Vector3f::default(),
+            n: Normal3f::default(),
+            medium_interface: None,
+            uv: Point2f::default(),
+            dpdu: Vector3f::default(),
+            dpdv: Vector3f::default(),
+            dndu: Normal3f::default(),
+            dndv: Normal3f::default(),
+            dpdx: RwLock::new(Vector3f::default()),
+            dpdy: RwLock::new(Vector3f::default()),
+            dudx: RwLock::new(0.0 as Float),
+            dvdx: RwLock::new(0.0 as Float),
+            dudy: RwLock::new(0.0 as Float),
+            dvdy: RwLock::new(0.0 as Float),
+            primitive: None,
+            shading: Shading::default(),
+            bsdf: None,
+            bssrdf: None,
+            shape: None,
+            clamp_shading_normals: false,
+            check_geometric_normal: true,
+        }
+    }
 }
 
 impl<'a> SurfaceInteraction<'a> {
@@ -342,6 +428,8 @@ impl<'a> SurfaceInteraction<'a> {
                 bsdf: None,
                 bssrdf: None,
                 shape: Some(shape.clone()),
+                clamp_shading_normals: false,
+                check_geometric_normal: true,
             }
         } else {
             SurfaceInteraction {
@@ -367,6 +455,8 @@ impl<'a> SurfaceInteraction<'a> {
                 bsdf: None,
                 bssrdf: None,
                 shape: None,
+                clamp_shading_normals: false,
+                check_geometric_normal: true,
             }
         }
     }
@@ -418,6 +508,45 @@ impl<'a> SurfaceInteraction<'a> {
         self.shading.dpdv = *dpdvs;
         self.shading.dndu = *dndus;
         self.shading.dndv = *dndvs;
+        self.correct_shading_normal();
+    }
+    /// Flips the geometric normal if it disagrees with the shading
+    /// normal. With shading normal clamping enabled (see
+    /// **Scene::set_clamp_shading_normals()**), a shading normal which
+    /// puts _wo_ on the other side of the surface than the geometric
+    /// normal does gets bent towards _wo_, just far enough to see it
+    /// from there.
+    pub fn correct_shading_normal(&mut self) {
+        if nrm_dot_nrm(&self.n, &self.shading.n) < 0.0 as Float {
+            self.n = -self.n;
+            crate::stat_inc!(N_GEOMETRIC_NORMALS_FLIPPED);
+        }
+        if !self.clamp_shading_normals {
+            return;
+        }
+        let wo_dot_ng: Float = vec3_dot_nrm(&self.wo, &self.n);
+        if wo_dot_ng == 0.0 as Float {
+            return;
+        }
+        // the side of the (geometric) surface _wo_ is on
+        let side: Float = wo_dot_ng.signum();
+        let wo: Vector3f = self.wo.normalize();
+        let wo_dot_ns: Float = side * vec3_dot_nrm(&wo, &self.shading.n);
+        let min_dot: Float = 1e-3 as Float;
+        if wo_dot_ns >= min_dot {
+            return;
+        }
+        // moving along _wo_ keeps the shading normal on the side of
+        // the geometric normal
+        let ns: Vector3f = Vector3f::from(self.shading.n) + wo * (side * (min_dot - wo_dot_ns));
+        self.shading.n = Normal3f::from(ns.normalize());
+        // keep the shading tangent perpendicular to the normal
+        let ns: Vector3f = Vector3f::from(self.shading.n);
+        let dpdu: Vector3f = self.shading.dpdu - ns * vec3_dot_vec3(&ns, &self.shading.dpdu);
+        if dpdu.length_squared() > 0.0 as Float {
+            self.shading.dpdu = dpdu;
+        }
+        crate::stat_inc!(N_SHADING_NORMALS_CLAMPED);
     }
     pub fn compute_scattering_functions(
         &mut self,
@@ -539,6 +668,238 @@ impl<'a> SurfaceInteraction<'a> {
             *dpdy = Vector3f::default();
         }
     }
+    /// Ray differentials for the perfect specular reflection _wi_ of
+    /// the incoming _ray_ (requires compute_differentials() to be
+    /// called first). Returns None if _ray_ has no differentials.
+    ///
+    /// For a flat mirror the differential rays are mirrored exactly,
+    /// and transmission through an index matched boundary keeps them:
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Normal3f, Point3f, Ray, RayDifferential, Vector3f};
+    /// use pbrt::core::interaction::SurfaceInteraction;
+    /// use pbrt::core::reflection::refract;
+    ///
+    /// // a camera ray straight down onto the plane z = 0, with
+    /// // differentials which are tilted in x and y
+    /// let rx_direction: Vector3f = Vector3f { x: 0.1, y: 0.0, z: -1.0 }.normalize();
+    /// let ry_direction: Vector3f = Vector3f { x: 0.0, y: -0.2, z: -1.0 }.normalize();
+    /// let ray: Ray = Ray {
+    ///     o: Point3f { x: 0.0, y: 0.0, z: 1.0 },
+    ///     d: Vector3f { x: 0.0, y: 0.0, z: -1.0 },
+    ///     differential: Some(RayDifferential {
+    ///         rx_origin: Point3f { x: 0.0, y: 0.0, z: 1.0 },
+    ///         ry_origin: Point3f { x: 0.0, y: 0.0, z: 1.0 },
+    ///         rx_direction,
+    ///         ry_direction,
+    ///     }),
+    ///     ..Ray::default()
+    /// };
+    /// let mut si: SurfaceInteraction = SurfaceInteraction::default();
+    /// si.wo = Vector3f { x: 0.0, y: 0.0, z: 1.0 };
+    /// si.shading.n = Normal3f { x: 0.0, y: 0.0, z: 1.0 };
+    /// // where the differential rays hit the plane
+    /// *si.dpdx.get_mut().unwrap() = Vector3f { x: 0.1, y: 0.0, z: 0.0 };
+    /// *si.dpdy.get_mut().unwrap() = Vector3f { x: 0.0, y: -0.2, z: 0.0 };
+    /// let near = |a: Vector3f, b: Vector3f| (a - b).length() < 1e-5;
+    /// let mirrored = |v: Vector3f| Vector3f { x: v.x, y: v.y, z: -v.z };
+    ///
+    /// let wi: Vector3f = Vector3f { x: 0.0, y: 0.0, z: 1.0 };
+    /// let reflected: RayDifferential = si.specular_reflection_differential(&ray, &wi).unwrap();
+    /// let origin: Point3f = Point3f { x: 0.1, y: 0.0, z: 0.0 };
+    /// assert!((reflected.rx_origin - origin).length() < 1e-6);
+    /// assert!(near(reflected.rx_direction, mirrored(rx_direction)));
+    /// assert!(near(reflected.ry_direction, mirrored(ry_direction)));
+    ///
+    /// let wi: Vector3f = Vector3f { x: 0.0, y: 0.0, z: -1.0 };
+    /// let transmitted: RayDifferential =
+    ///     si.specular_transmission_differential(&ray, &wi, 1.0).unwrap();
+    /// assert!(near(transmitted.rx_direction, rx_direction));
+    /// assert!(near(transmitted.ry_direction, ry_direction));
+    /// // entering glass the differentials bend like the rays themselves
+    /// // (to first order)
+    /// let transmitted: RayDifferential =
+    ///     si.specular_transmission_differential(&ray, &wi, 1.5).unwrap();
+    /// let refracted = |d: Vector3f| -> Vector3f {
+    ///     let mut wt: Vector3f = Vector3f::default();
+    ///     assert!(refract(&-d, &si.shading.n, 1.0 / 1.5, &mut wt));
+    ///     wt
+    /// };
+    /// assert!((transmitted.rx_direction - refracted(rx_direction)).length() < 2e-3);
+    /// assert!((transmitted.ry_direction - refracted(ry_direction)).length() < 2e-3);
+    ///
+    /// // no differentials, nothing to propagate
+    /// let ray: Ray = Ray { differential: None, ..ray };
+    /// assert!(si.specular_reflection_differential(&ray, &wi).is_none());
+    /// ```
+    pub fn specular_reflection_differential(
+        &self,
+        ray: &Ray,
+        wi: &Vector3f,
+    ) -> Option<RayDifferential> {
+        if let Some(ref d) = ray.differential {
+            let wo: Vector3f = self.wo;
+            let ns: Normal3f = self.shading.n;
+            let dudx: Float = *self.dudx.read().unwrap();
+            let dvdx: Float = *self.dvdx.read().unwrap();
+            let dudy: Float = *self.dudy.read().unwrap();
+            let dvdy: Float = *self.dvdy.read().unwrap();
+            let dndx: Normal3f = self.shading.dndu * dudx + self.shading.dndv * dvdx;
+            let dndy: Normal3f = self.shading.dndu * dudy + self.shading.dndv * dvdy;
+            let dwodx: Vector3f = -d.rx_direction - wo;
+            let dwody: Vector3f = -d.ry_direction - wo;
+            let ddndx: Float = vec3_dot_nrm(&dwodx, &ns) + vec3_dot_nrm(&wo, &dndx);
+            let ddndy: Float = vec3_dot_nrm(&dwody, &ns) + vec3_dot_nrm(&wo, &dndy);
+            // compute differential reflected directions
+            let dpdx: Vector3f = *self.dpdx.read().unwrap();
+            let dpdy: Vector3f = *self.dpdy.read().unwrap();
+            Some(RayDifferential {
+                rx_origin: self.p + dpdx,
+                ry_origin: self.p + dpdy,
+                rx_direction: *wi - dwodx
+                    + Vector3f::from(dndx * vec3_dot_nrm(&wo, &ns) + ns * ddndx) * 2.0 as Float,
+                ry_direction: *wi - dwody
+                    + Vector3f::from(dndy * vec3_dot_nrm(&wo, &ns) + ns * ddndy) * 2.0 as Float,
+            })
+        } else {
+            None
+        }
+    }
+    /// Ray differentials for the perfect specular transmission _wi_
+    /// of the incoming _ray_ through a boundary with the (interior)
+    /// index of refraction _eta_ (see _Bsdf::eta_). Requires
+    /// compute_differentials() to be called first and returns None if
+    /// _ray_ has no differentials.
+    pub fn specular_transmission_differential(
+        &self,
+        ray: &Ray,
+        wi: &Vector3f,
+        eta: Float,
+    ) -> Option<RayDifferential> {
+        if let Some(ref d) = ray.differential {
+            let wo: Vector3f = self.wo;
+            let mut ns: Normal3f = self.shading.n;
+            let dudx: Float = *self.dudx.read().unwrap();
+            let dvdx: Float = *self.dvdx.read().unwrap();
+            let dudy: Float = *self.dudy.read().unwrap();
+            let dvdy: Float = *self.dvdy.read().unwrap();
+            let mut dndx: Normal3f = self.shading.dndu * dudx + self.shading.dndv * dvdx;
+            let mut dndy: Normal3f = self.shading.dndu * dudy + self.shading.dndv * dvdy;
+            // relative IOR assuming the ray enters the object
+            let mut eta: Float = 1.0 as Float / eta;
+            if vec3_dot_nrm(&wo, &ns) < 0.0 as Float {
+                // the ray leaves the object, invert the relative IOR
+                // and flip the normal and its derivatives
+                eta = 1.0 as Float / eta;
+                ns = -ns;
+                dndx = -dndx;
+                dndy = -dndy;
+            }
+            let dwodx: Vector3f = -d.rx_direction - wo;
+            let dwody: Vector3f = -d.ry_direction - wo;
+            let ddndx: Float = vec3_dot_nrm(&dwodx, &ns) + vec3_dot_nrm(&wo, &dndx);
+            let ddndy: Float = vec3_dot_nrm(&dwody, &ns) + vec3_dot_nrm(&wo, &dndy);
+            let wi_dot_ns: Float = vec3_dot_nrm(wi, &ns).abs();
+            let mu: Float = eta * vec3_dot_nrm(&wo, &ns) - wi_dot_ns;
+            let dmudx: Float = (eta - (eta * eta * vec3_dot_nrm(&wo, &ns)) / wi_dot_ns) * ddndx;
+            let dmudy: Float = (eta - (eta * eta * vec3_dot_nrm(&wo, &ns)) / wi_dot_ns) * ddndy;
+            let dpdx: Vector3f = *self.dpdx.read().unwrap();
+            let dpdy: Vector3f = *self.dpdy.read().unwrap();
+            Some(RayDifferential {
+                rx_origin: self.p + dpdx,
+                ry_origin: self.p + dpdy,
+                rx_direction: *wi - dwodx * eta + Vector3f::from(dndx * mu + ns * dmudx),
+                ry_direction: *wi - dwody * eta + Vector3f::from(dndy * mu + ns * dmudy),
+            })
+        } else {
+            None
+        }
+    }
+    /// The radiance emitted into direction _w_ if the surface is an
+    /// area light. One-sided lights only emit to the side of the
+    /// geometric normal, which **ReverseOrientation** flips:
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Normal3f, Point3f, Ray, Vector3f};
+    /// use pbrt::core::light::Light;
+    /// use pbrt::core::medium::MediumInterface;
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::primitive::{GeometricPrimitive, Primitive};
+    /// use pbrt::core::shape::Shape;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::lights::diffuse::DiffuseAreaLight;
+    /// use pbrt::shapes::triangle::{Triangle, TriangleMesh};
+    /// use std::sync::Arc;
+    ///
+    /// // a quad light in the z = 0 plane, facing +z
+    /// let quad_light = |reverse_orientation: bool, with_normals: bool| -> Vec<Primitive> {
+    ///     let p: Vec<Point3f> = vec![
+    ///         Point3f { x: -1.0, y: -1.0, z: 0.0 },
+    ///         Point3f { x: 1.0, y: -1.0, z: 0.0 },
+    ///         Point3f { x: 1.0, y: 1.0, z: 0.0 },
+    ///         Point3f { x: -1.0, y: 1.0, z: 0.0 },
+    ///     ];
+    ///     let n: Vec<Normal3f> = if with_normals {
+    ///         vec![Normal3f { x: 0.0, y: 0.0, z: 1.0 }; 4]
+    ///     } else {
+    ///         Vec::new()
+    ///     };
+    ///     let mesh = Arc::new(TriangleMesh::new(
+    ///         Transform::default(),
+    ///         Transform::default(),
+    ///         reverse_orientation,
+    ///         2,
+    ///         vec![0, 1, 2, 0, 2, 3],
+    ///         4,
+    ///         p,
+    ///         Vec::new(),
+    ///         n,
+    ///         Vec::new(),
+    ///         None,
+    ///         None,
+    ///     ));
+    ///     (0..2)
+    ///         .map(|id| {
+    ///             let shape = Arc::new(Shape::Trngl(Triangle::new(mesh.clone(), id)));
+    ///             let light = Arc::new(Light::DiffuseArea(DiffuseAreaLight::new(
+    ///                 &Transform::default(),
+    ///                 &MediumInterface::default(),
+    ///                 &Spectrum::new(2.0),
+    ///                 1,
+    ///                 shape.clone(),
+    ///                 false,
+    ///             )));
+    ///             Primitive::Geometric(GeometricPrimitive::new(shape, None, Some(light), None))
+    ///         })
+    ///         .collect()
+    /// };
+    /// // radiance seen from a point above (z > 0) or below the quad
+    /// let seen_from = |prims: &Vec<Primitive>, z: Float| -> Float {
+    ///     let mut ray = Ray {
+    ///         o: Point3f { x: 0.1, y: 0.2, z },
+    ///         d: Vector3f { x: 0.0, y: 0.0, z: -z.signum() },
+    ///         t_max: std::f32::INFINITY,
+    ///         time: 0.0,
+    ///         medium: None,
+    ///         differential: None,
+    ///     };
+    ///     for prim in prims {
+    ///         if let Some(isect) = prim.intersect(&mut ray) {
+    ///             return isect.le(&-ray.d).y();
+    ///         }
+    ///     }
+    ///     panic!("the ray missed the quad");
+    /// };
+    /// let l_emit: Float = Spectrum::new(2.0).y();
+    /// for with_normals in [false, true].iter() {
+    ///     let front = quad_light(false, *with_normals);
+    ///     assert!((seen_from(&front, 1.0) - l_emit).abs() < 1e-5);
+    ///     assert_eq!(seen_from(&front, -1.0), 0.0);
+    ///     let reversed = quad_light(true, *with_normals);
+    ///     assert_eq!(seen_from(&reversed, 1.0), 0.0);
+    ///     assert!((seen_from(&reversed, -1.0) - l_emit).abs() < 1e-5);
+    /// }
+    /// ```
     pub fn le(&self, w: &Vector3f) -> Spectrum {
         if let Some(ref primitive) = self.primitive.clone() {
             if let Some(area_light) = primitive.get_area_light() {
@@ -211,6 +211,73 @@ pub fn integrate_catmull_rom(
     sum
 }
 
+/// Finds the _x_ for which the spline through the (monotonically
+/// increasing) _values_ takes the value _u_.
+pub fn invert_catmull_rom(x: &[Float], values: &[Float], u: Float) -> Float {
+    let n: usize = values.len();
+    // stop when _u_ is out of bounds
+    if !(u > values[0]) {
+        return x[0];
+    } else if !(u < values[n - 1]) {
+        return x[n - 1];
+    }
+    // map _u_ to a spline interval by inverting _values_
+    let i: usize = find_interval(n as i32, |i| values[i as usize] <= u) as usize;
+    // look up $x_i$ and function values of spline segment _i_
+    let x0: Float = x[i];
+    let x1: Float = x[i + 1];
+    let f0: Float = values[i];
+    let f1: Float = values[i + 1];
+    let width: Float = x1 - x0;
+    // approximate derivatives using finite differences
+    let d0: Float = if i > 0 {
+        width * (f1 - values[i - 1]) / (x1 - x[i - 1])
+    } else {
+        f1 - f0
+    };
+    let d1: Float = if i + 2 < n {
+        width * (values[i + 2] - f0) / (x[i + 2] - x0)
+    } else {
+        f1 - f0
+    };
+    // invert the spline interpolant using Newton-Bisection
+    let mut a: Float = 0.0;
+    let mut b: Float = 1.0;
+    let mut t: Float = 0.5;
+    loop {
+        // fall back to a bisection step when _t_ is out of bounds
+        if !(t >= a && t <= b) {
+            t = 0.5 as Float * (a + b);
+        }
+        // compute powers of _t_
+        let t2: Float = t * t;
+        let t3: Float = t2 * t;
+        // set _f_hat_ using Equation (8.27)
+        let f_hat: Float = (2.0 * t3 - 3.0 * t2 + 1.0) * f0
+            + (-2.0 * t3 + 3.0 * t2) * f1
+            + (t3 - 2.0 * t2 + t) * d0
+            + (t3 - t2) * d1;
+        // set _fhat_ (the derivative of _f_hat_)
+        let fhat: Float = (6.0 * t2 - 6.0 * t) * f0
+            + (-6.0 * t2 + 6.0 * t) * f1
+            + (3.0 * t2 - 4.0 * t + 1.0) * d0
+            + (3.0 * t2 - 2.0 * t) * d1;
+        // stop the iteration if converged
+        if (f_hat - u).abs() < 1e-6 as Float || b - a < 1e-6 as Float {
+            break;
+        }
+        // update bisection bounds using updated _t_
+        if f_hat - u < 0.0 as Float {
+            a = t;
+        } else {
+            b = t;
+        }
+        // perform a Newton step
+        t -= (f_hat - u) / fhat;
+    }
+    x0 + t * width
+}
+
 /// Evaluates the weighted sum of cosines.
 pub fn fourier(a: &SmallVec<[Float; 128]>, si: usize, m: i32, cos_phi: f64) -> Float {
     let mut value: f64 = 0.0;
@@ -0,0 +1,205 @@
+//! A kd-tree over points (e.g. photons or visible points), built with
+//! median splits and stored in a flat array, to find all items within
+//! a given radius of a lookup point.
+
+// std
+use std::cmp::Ordering;
+// pbrt
+use crate::core::geometry::{bnd3_union_pnt3, pnt3_distance_squared};
+use crate::core::geometry::{Bounds3f, Point3f};
+use crate::core::pbrt::Float;
+
+// see kdtree.h (pbrt-v2)
+
+/// Anything with a position that can be stored in a **KdTree**.
+pub trait KdTreeItem {
+    fn position(&self) -> Point3f;
+}
+
+impl KdTreeItem for Point3f {
+    fn position(&self) -> Point3f {
+        *self
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct KdTreeNode {
+    split_pos: Float,
+    // 0, 1, or 2 (x, y, or z), 3 for leaves
+    split_axis: u8,
+    has_left_child: bool,
+    // index of the right child, 0 if there is none (the root is never
+    // a right child)
+    right_child: u32,
+}
+
+/// Each node stores one item, the item with the median coordinate
+/// along the split axis. The left child of a node directly follows it
+/// in the array, the right child is referenced by index, so the items
+/// are kept in the same order as the nodes.
+pub struct KdTree<T> {
+    nodes: Vec<KdTreeNode>,
+    items: Vec<T>,
+}
+
+impl<T: KdTreeItem> KdTree<T> {
+    pub fn new(items: Vec<T>) -> Self {
+        let n_items: usize = items.len();
+        let mut nodes: Vec<KdTreeNode> = vec![KdTreeNode::default(); n_items];
+        let mut order: Vec<(Point3f, usize)> = items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| (item.position(), i))
+            .collect();
+        let mut next_free_node: usize = 0;
+        let mut node_items: Vec<usize> = vec![0_usize; n_items];
+        KdTree::<T>::recursive_build(
+            &mut nodes,
+            &mut node_items,
+            &mut next_free_node,
+            &mut order[..],
+        );
+        // move the items into node order
+        let mut slots: Vec<Option<T>> = items.into_iter().map(Some).collect();
+        let items: Vec<T> = node_items
+            .iter()
+            .map(|i| slots[*i].take().unwrap())
+            .collect();
+        KdTree { nodes, items }
+    }
+    fn recursive_build(
+        nodes: &mut Vec<KdTreeNode>,
+        node_items: &mut Vec<usize>,
+        next_free_node: &mut usize,
+        order: &mut [(Point3f, usize)],
+    ) {
+        let node_num: usize = *next_free_node;
+        *next_free_node += 1;
+        // create leaf node of kd-tree if we've reached the bottom
+        if order.len() == 1 {
+            nodes[node_num] = KdTreeNode {
+                split_pos: 0.0 as Float,
+                split_axis: 3_u8,
+                has_left_child: false,
+                right_child: 0_u32,
+            };
+            node_items[node_num] = order[0].1;
+            return;
+        }
+        // choose split direction and partition data
+
+        // compute bounds of the items in _order_
+        let mut bound: Bounds3f = Bounds3f {
+            p_min: order[0].0,
+            p_max: order[0].0,
+        };
+        for (p, _) in order.iter() {
+            bound = bnd3_union_pnt3(&bound, p);
+        }
+        let split_axis: u8 = bound.maximum_extent();
+        let split_pos: usize = order.len() / 2;
+        order.select_nth_unstable_by(split_pos, |a, b| {
+            a.0[split_axis]
+                .partial_cmp(&b.0[split_axis])
+                .unwrap_or(Ordering::Equal)
+        });
+        // allocate kd-tree node and continue recursively
+        let (left, rest) = order.split_at_mut(split_pos);
+        let (median, right) = rest.split_first_mut().unwrap();
+        nodes[node_num] = KdTreeNode {
+            split_pos: median.0[split_axis],
+            split_axis,
+            has_left_child: !left.is_empty(),
+            right_child: 0_u32,
+        };
+        node_items[node_num] = median.1;
+        if !left.is_empty() {
+            KdTree::<T>::recursive_build(nodes, node_items, next_free_node, left);
+        }
+        if !right.is_empty() {
+            nodes[node_num].right_child = *next_free_node as u32;
+            KdTree::<T>::recursive_build(nodes, node_items, next_free_node, right);
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+    /// Calls _proc_ with every item within _radius_ of _p_ and its
+    /// squared distance to _p_.
+    ///
+    /// A uniform grid of 21^3 points with a spacing of 0.1 has about
+    /// 4/3 pi r^3 / 0.1^3 points within a radius r around its center:
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::Point3f;
+    /// use pbrt::core::kdtree::KdTree;
+    /// use pbrt::core::pbrt::Float;
+    ///
+    /// let mut points: Vec<Point3f> = Vec::new();
+    /// for z in -10..=10 {
+    ///     for y in -10..=10 {
+    ///         for x in -10..=10 {
+    ///             points.push(Point3f {
+    ///                 x: x as Float * 0.1,
+    ///                 y: y as Float * 0.1,
+    ///                 z: z as Float * 0.1,
+    ///             });
+    ///         }
+    ///     }
+    /// }
+    /// let tree = KdTree::new(points);
+    /// assert_eq!(tree.len(), 21 * 21 * 21);
+    /// let radius: Float = 0.75;
+    /// let mut n_found: usize = 0;
+    /// tree.lookup(&Point3f::default(), radius, |p, dist2| {
+    ///     assert!(dist2 <= radius * radius);
+    ///     assert!((p.x * p.x + p.y * p.y + p.z * p.z - dist2).abs() < 1e-5);
+    ///     n_found += 1;
+    /// });
+    /// let expected: Float = 4.0 / 3.0 * std::f32::consts::PI * radius.powi(3) / 0.001;
+    /// assert!((n_found as Float - expected).abs() < 0.05 * expected);
+    /// ```
+    pub fn lookup<F>(&self, p: &Point3f, radius: Float, mut proc: F)
+    where
+        F: FnMut(&T, Float),
+    {
+        if !self.nodes.is_empty() {
+            self.private_lookup(0_usize, p, radius * radius, &mut proc);
+        }
+    }
+    fn private_lookup<F>(&self, node_num: usize, p: &Point3f, max_dist_squared: Float, proc: &mut F)
+    where
+        F: FnMut(&T, Float),
+    {
+        let node: &KdTreeNode = &self.nodes[node_num];
+        // process kd-tree node's children
+        let axis: u8 = node.split_axis;
+        if axis != 3_u8 {
+            let dist2: Float = (p[axis] - node.split_pos) * (p[axis] - node.split_pos);
+            if p[axis] <= node.split_pos {
+                if node.has_left_child {
+                    self.private_lookup(node_num + 1, p, max_dist_squared, proc);
+                }
+                if dist2 <= max_dist_squared && node.right_child > 0 {
+                    self.private_lookup(node.right_child as usize, p, max_dist_squared, proc);
+                }
+            } else {
+                if node.right_child > 0 {
+                    self.private_lookup(node.right_child as usize, p, max_dist_squared, proc);
+                }
+                if dist2 <= max_dist_squared && node.has_left_child {
+                    self.private_lookup(node_num + 1, p, max_dist_squared, proc);
+                }
+            }
+        }
+        // hand kd-tree node to processing function
+        let item: &T = &self.items[node_num];
+        let dist2: Float = pnt3_distance_squared(&item.position(), p);
+        if dist2 <= max_dist_squared {
+            proc(item, dist2);
+        }
+    }
+}
@@ -2,19 +2,17 @@
 //! source of illumination so that some light is reflected from them
 //! to the camera sensor.
 
-// std
-use std::sync::Arc;
 // pbrt
 use crate::core::geometry::{Normal3f, Point2f, Ray, Vector3f};
 use crate::core::interaction::{Interaction, InteractionCommon};
-use crate::core::medium::MediumInterface;
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::sampler::Sampler;
 use crate::core::scene::Scene;
 use crate::lights::diffuse::DiffuseAreaLight;
 use crate::lights::distant::DistantLight;
 use crate::lights::goniometric::GonioPhotometricLight;
-use crate::lights::infinite::InfiniteAreaLight;
+use crate::lights::ies::IESLight;
+use crate::lights::infinite::{ConstantInfiniteLight, InfiniteAreaLight};
 use crate::lights::point::PointLight;
 use crate::lights::projection::ProjectionLight;
 use crate::lights::spot::SpotLight;
@@ -33,7 +31,9 @@ pub enum Light {
     DiffuseArea(DiffuseAreaLight),
     Distant(DistantLight),
     GonioPhotometric(GonioPhotometricLight),
+    IES(IESLight),
     InfiniteArea(InfiniteAreaLight),
+    ConstantInfinite(ConstantInfiniteLight),
     Point(PointLight),
     Projection(ProjectionLight),
     Spot(SpotLight),
@@ -55,7 +55,9 @@ impl Light {
             Light::DiffuseArea(light) => light.sample_li(iref, u, wi, pdf, vis),
             Light::Distant(light) => light.sample_li(iref, u, wi, pdf, vis),
             Light::GonioPhotometric(light) => light.sample_li(iref, u, wi, pdf, vis),
+            Light::IES(light) => light.sample_li(iref, u, wi, pdf, vis),
             Light::InfiniteArea(light) => light.sample_li(iref, u, wi, pdf, vis),
+            Light::ConstantInfinite(light) => light.sample_li(iref, u, wi, pdf, vis),
             Light::Point(light) => light.sample_li(iref, u, wi, pdf, vis),
             Light::Projection(light) => light.sample_li(iref, u, wi, pdf, vis),
             Light::Spot(light) => light.sample_li(iref, u, wi, pdf, vis),
@@ -66,7 +68,9 @@ impl Light {
             Light::DiffuseArea(light) => light.power(),
             Light::Distant(light) => light.power(),
             Light::GonioPhotometric(light) => light.power(),
+            Light::IES(light) => light.power(),
             Light::InfiniteArea(light) => light.power(),
+            Light::ConstantInfinite(light) => light.power(),
             Light::Point(light) => light.power(),
             Light::Projection(light) => light.power(),
             Light::Spot(light) => light.power(),
@@ -77,7 +81,9 @@ impl Light {
             Light::DiffuseArea(light) => light.preprocess(scene),
             Light::Distant(light) => light.preprocess(scene),
             Light::GonioPhotometric(light) => light.preprocess(scene),
+            Light::IES(light) => light.preprocess(scene),
             Light::InfiniteArea(light) => light.preprocess(scene),
+            Light::ConstantInfinite(light) => light.preprocess(scene),
             Light::Point(light) => light.preprocess(scene),
             Light::Projection(light) => light.preprocess(scene),
             Light::Spot(light) => light.preprocess(scene),
@@ -88,7 +94,9 @@ impl Light {
             Light::DiffuseArea(light) => light.le(ray),
             Light::Distant(light) => light.le(ray),
             Light::GonioPhotometric(light) => light.le(ray),
+            Light::IES(light) => light.le(ray),
             Light::InfiniteArea(light) => light.le(ray),
+            Light::ConstantInfinite(light) => light.le(ray),
             Light::Point(light) => light.le(ray),
             Light::Projection(light) => light.le(ray),
             Light::Spot(light) => light.le(ray),
@@ -99,7 +107,9 @@ impl Light {
             Light::DiffuseArea(light) => light.pdf_li(iref, wi),
             Light::Distant(light) => light.pdf_li(iref, wi),
             Light::GonioPhotometric(light) => light.pdf_li(iref, wi),
+            Light::IES(light) => light.pdf_li(iref, wi),
             Light::InfiniteArea(light) => light.pdf_li(iref, wi),
+            Light::ConstantInfinite(light) => light.pdf_li(iref, wi),
             Light::Point(light) => light.pdf_li(iref, wi),
             Light::Projection(light) => light.pdf_li(iref, wi),
             Light::Spot(light) => light.pdf_li(iref, wi),
@@ -123,9 +133,13 @@ impl Light {
             Light::GonioPhotometric(light) => {
                 light.sample_le(u1, u2, time, ray, n_light, pdf_pos, pdf_dir)
             }
+            Light::IES(light) => light.sample_le(u1, u2, time, ray, n_light, pdf_pos, pdf_dir),
             Light::InfiniteArea(light) => {
                 light.sample_le(u1, u2, time, ray, n_light, pdf_pos, pdf_dir)
             }
+            Light::ConstantInfinite(light) => {
+                light.sample_le(u1, u2, time, ray, n_light, pdf_pos, pdf_dir)
+            }
             Light::Point(light) => light.sample_le(u1, u2, time, ray, n_light, pdf_pos, pdf_dir),
             Light::Projection(light) => {
                 light.sample_le(u1, u2, time, ray, n_light, pdf_pos, pdf_dir)
@@ -138,7 +152,9 @@ impl Light {
             Light::DiffuseArea(light) => light.pdf_le(ray, n_light, pdf_pos, pdf_dir),
             Light::Distant(light) => light.pdf_le(ray, n_light, pdf_pos, pdf_dir),
             Light::GonioPhotometric(light) => light.pdf_le(ray, n_light, pdf_pos, pdf_dir),
+            Light::IES(light) => light.pdf_le(ray, n_light, pdf_pos, pdf_dir),
             Light::InfiniteArea(light) => light.pdf_le(ray, n_light, pdf_pos, pdf_dir),
+            Light::ConstantInfinite(light) => light.pdf_le(ray, n_light, pdf_pos, pdf_dir),
             Light::Point(light) => light.pdf_le(ray, n_light, pdf_pos, pdf_dir),
             Light::Projection(light) => light.pdf_le(ray, n_light, pdf_pos, pdf_dir),
             Light::Spot(light) => light.pdf_le(ray, n_light, pdf_pos, pdf_dir),
@@ -149,7 +165,9 @@ impl Light {
             Light::DiffuseArea(light) => light.get_flags(),
             Light::Distant(light) => light.get_flags(),
             Light::GonioPhotometric(light) => light.get_flags(),
+            Light::IES(light) => light.get_flags(),
             Light::InfiniteArea(light) => light.get_flags(),
+            Light::ConstantInfinite(light) => light.get_flags(),
             Light::Point(light) => light.get_flags(),
             Light::Projection(light) => light.get_flags(),
             Light::Spot(light) => light.get_flags(),
@@ -160,7 +178,9 @@ impl Light {
             Light::DiffuseArea(light) => light.get_n_samples(),
             Light::Distant(light) => light.get_n_samples(),
             Light::GonioPhotometric(light) => light.get_n_samples(),
+            Light::IES(light) => light.get_n_samples(),
             Light::InfiniteArea(light) => light.get_n_samples(),
+            Light::ConstantInfinite(light) => light.get_n_samples(),
             Light::Point(light) => light.get_n_samples(),
             Light::Projection(light) => light.get_n_samples(),
             Light::Spot(light) => light.get_n_samples(),
@@ -203,41 +223,13 @@ impl VisibilityTester {
     }
     pub fn tr(&self, scene: &Scene, sampler: &mut Box<Sampler>) -> Spectrum {
         let mut ray: Ray = self.p0.spawn_ray_to(&self.p1);
-        let mut tr: Spectrum = Spectrum::new(1.0 as Float);
-        loop {
-            let mut it: InteractionCommon = InteractionCommon::default();
-            let mut medium_interface: Option<Arc<MediumInterface>> = None;
-            if let Some(isect) = scene.intersect(&mut ray) {
-                // handle opaque surface along ray's path
-                if let Some(primitive) = isect.primitive {
-                    if let Some(_material) = primitive.get_material() {
-                        return Spectrum::default();
-                    } else {
-                        // update transmittance for current ray segment
-                        if let Some(ref medium_arc) = ray.medium {
-                            tr *= medium_arc.tr(&ray, sampler);
-                        }
-                    }
-                }
-                if let Some(mi_arc) = isect.medium_interface {
-                    medium_interface = Some(mi_arc.clone());
-                }
-                it.p = isect.p;
-                it.time = isect.time;
-                it.p_error = isect.p_error;
-                it.wo = isect.wo;
-                it.n = isect.n;
-                it.medium_interface = medium_interface;
-            } else {
-                // update transmittance for current ray segment
-                if let Some(ref medium_arc) = ray.medium {
-                    tr *= medium_arc.tr(&ray, sampler);
-                }
-                break;
-            }
-            ray = it.spawn_ray_to(&self.p1);
+        // only surfaces with a material block the ray
+        let (isect, tr) = scene.intersect_tr(&mut ray, sampler);
+        if isect.is_some() {
+            Spectrum::default()
+        } else {
+            tr
         }
-        tr
     }
 }
 
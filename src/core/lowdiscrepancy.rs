@@ -3,8 +3,9 @@
 use hexf::*;
 
 // pbrt
-use crate::core::geometry::{Point2f, Point2i};
+use crate::core::geometry::{Bounds2i, Point2f, Point2i, Vector2i};
 use crate::core::pbrt::Float;
+use crate::core::pbrt::{log_2_int_u32, round_up_pow2_32};
 use crate::core::rng::Rng;
 use crate::core::rng::FLOAT_ONE_MINUS_EPSILON;
 use crate::core::sampling::shuffle;
@@ -553,6 +554,74 @@ pub fn sobol_interval_to_index(m: u32, frame: u64, p: &Point2i) -> u64 {
     return index;
 }
 
+/// The sample bounds of the film padded to a square with a power of
+/// two resolution, so that the first two dimensions of the Sobol
+/// sequence, scaled by the resolution, cover all pixels. Each pixel
+/// gets its own subsequence of sample indices, which depends only on
+/// the pixel (not on the order pixels are visited).
+///
+/// ```rust
+/// use pbrt::core::geometry::{Bounds2i, Point2i};
+/// use pbrt::core::lowdiscrepancy::{sobol_sample, SampleBounds};
+/// use pbrt::core::pbrt::Float;
+///
+/// let bounds = Bounds2i::new(Point2i { x: 2, y: 1 }, Point2i { x: 7, y: 4 });
+/// let padded = SampleBounds::new(&bounds);
+/// assert_eq!(padded.resolution, 8);
+/// assert_eq!(padded.log_2_resolution, 3);
+/// let mut indices: Vec<u64> = Vec::new();
+/// for p in &bounds {
+///     for sample_num in 0..4_u64 {
+///         let index: u64 = padded.index_for_sample(&p, sample_num);
+///         // the first two dimensions land in the pixel
+///         for dim in 0..2_u8 {
+///             let s: Float = sobol_sample(index as i64, dim as i32, 0)
+///                 * padded.resolution as Float
+///                 + bounds.p_min[dim] as Float;
+///             assert_eq!(s.floor() as i32, p[dim]);
+///         }
+///         indices.push(index);
+///     }
+/// }
+/// // no two pixels share a sample
+/// let n: usize = indices.len();
+/// indices.sort();
+/// indices.dedup();
+/// assert_eq!(indices.len(), n);
+/// ```
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SampleBounds {
+    pub bounds: Bounds2i,
+    pub resolution: i32,
+    pub log_2_resolution: i32,
+}
+
+impl SampleBounds {
+    pub fn new(sample_bounds: &Bounds2i) -> Self {
+        let diagonal: Vector2i = sample_bounds.diagonal();
+        let resolution: i32 = round_up_pow2_32(diagonal.x.max(diagonal.y));
+        let log_2_resolution: i32 = log_2_int_u32(resolution as u32);
+        if resolution > 0_i32 {
+            assert!(1_i32 << log_2_resolution == resolution);
+        }
+        SampleBounds {
+            bounds: *sample_bounds,
+            resolution,
+            log_2_resolution,
+        }
+    }
+    /// Returns the global index of the _sample_num_th sample of pixel
+    /// _p_, the base index of the pixel for _sample_num_ = 0.
+    pub fn index_for_sample(&self, p: &Point2i, sample_num: u64) -> u64 {
+        let v: Vector2i = *p - self.bounds.p_min;
+        sobol_interval_to_index(
+            self.log_2_resolution as u32,
+            sample_num,
+            &Point2i { x: v.x, y: v.y },
+        )
+    }
+}
+
 /// Takes different paths for 32- and 64-bit floating point values.
 pub fn sobol_sample(index: i64, dimension: i32, scramble: u64) -> Float {
     // #ifdef PBRT_FLOAT_AS_DOUBLE
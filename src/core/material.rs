@@ -4,7 +4,7 @@
 //std
 use std::sync::{Arc, RwLock};
 // pbrt
-use crate::core::geometry::vec3_cross_vec3;
+use crate::core::geometry::{vec3_cross_vec3, vec3_dot_vec3};
 use crate::core::geometry::{Normal3f, Vector2f, Vector3f};
 use crate::core::interaction::SurfaceInteraction;
 use crate::core::pbrt::{Float, Spectrum};
@@ -13,6 +13,7 @@ use crate::materials::disney::DisneyMaterial;
 use crate::materials::fourier::FourierMaterial;
 use crate::materials::glass::GlassMaterial;
 use crate::materials::hair::HairMaterial;
+use crate::materials::kdsubsurface::KdSubsurfaceMaterial;
 use crate::materials::matte::MatteMaterial;
 use crate::materials::metal::MetalMaterial;
 use crate::materials::mirror::MirrorMaterial;
@@ -39,6 +40,7 @@ pub enum Material {
     Fourier(FourierMaterial),
     Glass(GlassMaterial),
     Hair(HairMaterial),
+    KdSubsurface(KdSubsurfaceMaterial),
     Matte(MatteMaterial),
     Metal(MetalMaterial),
     Mirror(MirrorMaterial),
@@ -80,6 +82,9 @@ impl Material {
             Material::Hair(material) => {
                 material.compute_scattering_functions(si, mode, allow_multiple_lobes, mat, scale)
             }
+            Material::KdSubsurface(material) => {
+                material.compute_scattering_functions(si, mode, allow_multiple_lobes, mat, scale)
+            }
             Material::Matte(material) => {
                 material.compute_scattering_functions(si, mode, allow_multiple_lobes, mat, scale)
             }
@@ -221,4 +226,46 @@ impl Material {
         let dndv = si.shading.dndv;
         si.set_shading_geometry(&dpdu, &dpdv, &dndu, &dndv, false);
     }
+    /// Perturbs the shading normal by a tangent-space normal map (with
+    /// the RGB values encoding a unit vector relative to the shading
+    /// tangent, bitangent and normal, like in glTF).
+    pub fn normal_map(map: &Arc<dyn Texture<Spectrum> + Send + Sync>, si: &mut SurfaceInteraction)
+    where
+        Self: Sized,
+    {
+        let mut rgb: [Float; 3] = [0.0 as Float; 3];
+        map.evaluate(si).to_rgb(&mut rgb);
+        let ns_local: Vector3f = Vector3f {
+            x: 2.0 as Float * rgb[0] - 1.0 as Float,
+            y: 2.0 as Float * rgb[1] - 1.0 as Float,
+            z: 2.0 as Float * rgb[2] - 1.0 as Float,
+        };
+        if ns_local.length_squared() == 0.0 as Float {
+            return;
+        }
+        let ns_local: Vector3f = ns_local.normalize();
+        // tangent frame around the shading normal, the bitangent
+        // follows _dpdv_ (mirrored texture coordinates flip it)
+        let n: Vector3f = Vector3f::from(si.shading.n).normalize();
+        let t: Vector3f = si.shading.dpdu - n * vec3_dot_vec3(&n, &si.shading.dpdu);
+        if t.length_squared() == 0.0 as Float {
+            return;
+        }
+        let t: Vector3f = t.normalize();
+        let mut b: Vector3f = vec3_cross_vec3(&n, &t);
+        if vec3_dot_vec3(&b, &si.shading.dpdv) < 0.0 as Float {
+            b = -b;
+        }
+        let ns: Vector3f = (t * ns_local.x + b * ns_local.y + n * ns_local.z).normalize();
+        // keep the lengths of the partial derivatives, but make them
+        // perpendicular to the new shading normal
+        let ulen: Float = si.shading.dpdu.length();
+        let vlen: Float = si.shading.dpdv.length();
+        let dpdu: Vector3f =
+            (si.shading.dpdu - ns * vec3_dot_vec3(&ns, &si.shading.dpdu)).normalize() * ulen;
+        let dpdv: Vector3f = vec3_cross_vec3(&ns, &dpdu).normalize() * vlen;
+        let dndu = si.shading.dndu;
+        let dndv = si.shading.dndv;
+        si.set_shading_geometry(&dpdu, &dpdv, &dndu, &dndv, false);
+    }
 }
@@ -294,15 +294,64 @@ impl Medium {
     }
 }
 
+/// The Henyey-Greenstein phase function. The asymmetry parameter _g_
+/// (in (-1, 1)) is the average cosine of the scattering angle:
+/// positive values favor forward scattering, negative values back
+/// scattering, and zero gives isotropic scattering. As everywhere in
+/// pbrt _wo_ and _wi_ both point away from the scattering point.
+///
+/// The phase function integrates to one over the sphere, for _g_ == 0
+/// it is constant (1 / 4 pi), and sample_p() returns the value of p()
+/// for the sampled direction:
+///
+/// ```rust
+/// use pbrt::core::geometry::{Point2f, Vector3f};
+/// use pbrt::core::medium::HenyeyGreenstein;
+/// use pbrt::core::pbrt::{Float, INV_4_PI};
+/// use std::f32::consts::PI;
+///
+/// let wo = Vector3f { x: 0.0, y: 0.0, z: 1.0 };
+/// let n: usize = 256;
+/// for g in &[-0.6 as Float, 0.0, 0.3, 0.6] {
+///     let hg = HenyeyGreenstein { g: *g };
+///     // midpoint rule in (cos(theta), phi)
+///     let mut integral: f64 = 0.0;
+///     for i in 0..n {
+///         let cos_theta: Float = -1.0 + 2.0 * (i as Float + 0.5) / n as Float;
+///         let sin_theta: Float = (1.0 - cos_theta * cos_theta).sqrt();
+///         for j in 0..n {
+///             let phi: Float = 2.0 * PI * (j as Float + 0.5) / n as Float;
+///             let wi = Vector3f {
+///                 x: sin_theta * phi.cos(),
+///                 y: sin_theta * phi.sin(),
+///                 z: cos_theta,
+///             };
+///             let p: Float = hg.p(&wo, &wi);
+///             if *g == 0.0 {
+///                 assert!((p - INV_4_PI).abs() < 1e-6);
+///             }
+///             integral += p as f64 * (4.0 * PI as f64) / (n * n) as f64;
+///         }
+///     }
+///     assert!((integral - 1.0).abs() < 1e-2, "g = {}: {}", g, integral);
+///     let mut wi = Vector3f::default();
+///     let pdf: Float = hg.sample_p(&wo, &mut wi, &Point2f { x: 0.3, y: 0.8 });
+///     assert!((pdf - hg.p(&wo, &wi)).abs() < 1e-3 * pdf);
+/// }
+/// ```
 pub struct HenyeyGreenstein {
     pub g: Float,
 }
 
 impl HenyeyGreenstein {
+    /// Value of the phase function for scattering light arriving from
+    /// _wi_ towards _wo_.
     pub fn p(&self, wo: &Vector3f, wi: &Vector3f) -> Float {
         // TODO: ProfilePhase _(Prof::PhaseFuncEvaluation);
         phase_hg(vec3_dot_vec3(wo, wi), self.g)
     }
+    /// Samples _wi_ proportional to the phase function (exactly, so
+    /// the returned value is both the PDF and the phase function value).
     pub fn sample_p(&self, wo: &Vector3f, wi: &mut Vector3f, u: &Point2f) -> Float {
         // TODO: ProfilePhase _(Prof::PhaseFuncSampling);
         // compute $\cos \theta$ for Henyey--Greenstein sample
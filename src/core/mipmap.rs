@@ -17,6 +17,9 @@ use crate::core::texture::lanczos;
 
 // see mipmap.h
 
+crate::stat_counter!("Texture/Trilinear MIPMap lookups", N_TRILERP_LOOKUPS);
+crate::stat_counter!("Texture/EWA MIPMap lookups", N_EWA_LOOKUPS);
+
 const WEIGHT_LUT_SIZE: usize = 128;
 
 #[derive(Debug, Clone)]
@@ -234,8 +237,7 @@ where
         &l[(ss, tt)]
     }
     pub fn lookup_pnt_flt(&self, st: &Point2f, width: Float) -> T {
-        // TODO: ++nTrilerpLookups;
-        // TODO: ProfilePhase p(Prof::TexFiltTrilerp);
+        crate::stat_inc!(N_TRILERP_LOOKUPS);
         // compute MIPMap level for trilinear filtering
         let level: Float = self.levels() as Float - 1.0 as Float + width.max(1e-8 as Float).log2();
         // perform trilinear interpolation at appropriate MIPMap level
@@ -262,8 +264,7 @@ where
                 .max(dst1.x.abs().max(dst1.y.abs()));
             return self.lookup_pnt_flt(st, width);
         }
-        // TODO: ++nEWALookups;
-        // TODO: ProfilePhase p(Prof::TexFiltEWA);
+        crate::stat_inc!(N_EWA_LOOKUPS);
         // compute ellipse minor and major axes
         if dst0.length_squared() < dst1.length_squared() {
             // std::swap(dst0, dst1);
@@ -408,10 +409,6 @@ impl Clampable for Float {
 
 impl Clampable for Spectrum {
     fn clamp(self, min: Float, max: Float) -> Spectrum {
-        Spectrum::rgb(
-            clamp_t(self.c[0], min, max),
-            clamp_t(self.c[1], min, max),
-            clamp_t(self.c[2], min, max),
-        )
+        Spectrum::clamp(&self, min, max)
     }
 }
@@ -8,9 +8,13 @@ pub mod film;
 pub mod filter;
 pub mod floatfile;
 pub mod geometry;
+#[cfg(feature = "gltf")]
+pub mod gltfimport;
+pub mod imageio;
 pub mod integrator;
 pub mod interaction;
 pub mod interpolation;
+pub mod kdtree;
 pub mod light;
 pub mod lightdistrib;
 pub mod lowdiscrepancy;
@@ -19,18 +23,25 @@ pub mod medium;
 pub mod memory;
 pub mod microfacet;
 pub mod mipmap;
+#[cfg(feature = "denoise")]
+pub mod oidn;
 pub mod parallel;
 pub mod paramset;
 pub mod pbrt;
 pub mod primitive;
+pub mod progressreporter;
 pub mod quaternion;
 pub mod reflection;
 pub mod rng;
 pub mod sampler;
 pub mod sampling;
 pub mod scene;
+pub mod scenecache;
 pub mod shape;
 pub mod sobolmatrices;
 pub mod spectrum;
+pub mod stats;
+#[cfg(feature = "tev")]
+pub mod tev;
 pub mod texture;
 pub mod transform;
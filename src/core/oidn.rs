@@ -0,0 +1,222 @@
+//! Denoising with [Intel Open Image
+//! Denoise](https://www.openimagedenoise.org). The shared library is
+//! loaded at run time (from `$OIDN_DIR/lib` or the system's library
+//! search path), so rs_pbrt builds without it, and **Film::denoise()**
+//! just prints a warning if it can't be found.
+//!
+//! Only the few functions of OIDN's C API needed to run the ray
+//! tracing filter on shared buffers are looked up. They are the same
+//! in OIDN 1.x and 2.x, except for setting boolean parameters, which
+//! got renamed from `oidnSetFilter1b` to `oidnSetFilterBool`.
+
+// std
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::PathBuf;
+
+type OidnDevice = *mut c_void;
+type OidnFilter = *mut c_void;
+
+/// see OIDNDeviceType
+const OIDN_DEVICE_TYPE_DEFAULT: c_int = 0;
+/// see OIDNError
+const OIDN_ERROR_NONE: c_int = 0;
+/// see OIDNFormat
+const OIDN_FORMAT_FLOAT3: c_int = 3;
+
+#[cfg(target_os = "macos")]
+const LIBRARY_NAMES: [&str; 3] = [
+    "libOpenImageDenoise.dylib",
+    "libOpenImageDenoise.2.dylib",
+    "libOpenImageDenoise.1.dylib",
+];
+#[cfg(not(target_os = "macos"))]
+const LIBRARY_NAMES: [&str; 3] = [
+    "libOpenImageDenoise.so",
+    "libOpenImageDenoise.so.2",
+    "libOpenImageDenoise.so.1",
+];
+
+/// The loaded shared library and its functions.
+struct Library {
+    handle: *mut c_void,
+    new_device: unsafe extern "C" fn(c_int) -> OidnDevice,
+    commit_device: unsafe extern "C" fn(OidnDevice),
+    get_device_error: unsafe extern "C" fn(OidnDevice, *mut *const c_char) -> c_int,
+    release_device: unsafe extern "C" fn(OidnDevice),
+    new_filter: unsafe extern "C" fn(OidnDevice, *const c_char) -> OidnFilter,
+    set_shared_filter_image: unsafe extern "C" fn(
+        OidnFilter,
+        *const c_char,
+        *mut c_void,
+        c_int,
+        usize,
+        usize,
+        usize,
+        usize,
+        usize,
+    ),
+    set_filter_bool: unsafe extern "C" fn(OidnFilter, *const c_char, bool),
+    commit_filter: unsafe extern "C" fn(OidnFilter),
+    execute_filter: unsafe extern "C" fn(OidnFilter),
+    release_filter: unsafe extern "C" fn(OidnFilter),
+}
+
+impl Library {
+    #[cfg(unix)]
+    fn open() -> Result<Library, String> {
+        let mut candidates: Vec<PathBuf> = Vec::new();
+        if let Some(dir) = std::env::var_os("OIDN_DIR") {
+            for name in LIBRARY_NAMES.iter() {
+                candidates.push(PathBuf::from(&dir).join("lib").join(name));
+            }
+        }
+        for name in LIBRARY_NAMES.iter() {
+            candidates.push(PathBuf::from(name));
+        }
+        for candidate in candidates {
+            let path = CString::new(candidate.to_string_lossy().as_bytes()).unwrap();
+            let handle: *mut c_void =
+                unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+            if !handle.is_null() {
+                let library = unsafe { Library::load(handle) };
+                if library.is_err() {
+                    unsafe { libc::dlclose(handle) };
+                }
+                return library;
+            }
+        }
+        Err(format!(
+            "Intel Open Image Denoise not found ({} in $OIDN_DIR/lib or the library search path)",
+            LIBRARY_NAMES[0]
+        ))
+    }
+    #[cfg(not(unix))]
+    fn open() -> Result<Library, String> {
+        Err(String::from(
+            "loading Intel Open Image Denoise is only supported on Unix",
+        ))
+    }
+    /// Looks up the functions in the library opened as _handle_.
+    #[cfg(unix)]
+    unsafe fn load(handle: *mut c_void) -> Result<Library, String> {
+        Ok(Library {
+            handle,
+            new_device: symbol(handle, &["oidnNewDevice"])?,
+            commit_device: symbol(handle, &["oidnCommitDevice"])?,
+            get_device_error: symbol(handle, &["oidnGetDeviceError"])?,
+            release_device: symbol(handle, &["oidnReleaseDevice"])?,
+            new_filter: symbol(handle, &["oidnNewFilter"])?,
+            set_shared_filter_image: symbol(handle, &["oidnSetSharedFilterImage"])?,
+            set_filter_bool: symbol(handle, &["oidnSetFilterBool", "oidnSetFilter1b"])?,
+            commit_filter: symbol(handle, &["oidnCommitFilter"])?,
+            execute_filter: symbol(handle, &["oidnExecuteFilter"])?,
+            release_filter: symbol(handle, &["oidnReleaseFilter"])?,
+        })
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::dlclose(self.handle);
+        }
+    }
+}
+
+/// Returns the first of the functions _names_ found in the library
+/// opened as _handle_. _T_ has to be a matching function pointer.
+#[cfg(unix)]
+unsafe fn symbol<T: Copy>(handle: *mut c_void, names: &[&str]) -> Result<T, String> {
+    for name in names {
+        let c_name = CString::new(*name).unwrap();
+        let address: *mut c_void = libc::dlsym(handle, c_name.as_ptr());
+        if !address.is_null() {
+            return Ok(std::mem::transmute_copy::<*mut c_void, T>(&address));
+        }
+    }
+    Err(format!(
+        "{} missing in Intel Open Image Denoise library",
+        names.join(" or ")
+    ))
+}
+
+/// Whether the shared library can be loaded.
+pub fn is_available() -> bool {
+    Library::open().is_ok()
+}
+
+/// Runs the ray tracing ("RT") filter on the _width_ x _height_ RGB
+/// values _color_ (HDR, not tonemapped), guided by the optional
+/// _albedo_ and _normal_ buffers (of the same layout), and returns the
+/// denoised values.
+pub fn filter(
+    width: usize,
+    height: usize,
+    color: &[f32],
+    albedo: Option<&[f32]>,
+    normal: Option<&[f32]>,
+) -> Result<Vec<f32>, String> {
+    let library: Library = Library::open()?;
+    let mut output: Vec<f32> = vec![0.0_f32; color.len()];
+    unsafe {
+        let device: OidnDevice = (library.new_device)(OIDN_DEVICE_TYPE_DEFAULT);
+        if device.is_null() {
+            return Err(String::from("unable to create an OIDN device"));
+        }
+        (library.commit_device)(device);
+        let filter: OidnFilter = (library.new_filter)(device, b"RT\0".as_ptr() as *const c_char);
+        if !filter.is_null() {
+            let set_image = |name: &[u8], buffer: *mut f32| {
+                (library.set_shared_filter_image)(
+                    filter,
+                    name.as_ptr() as *const c_char,
+                    buffer as *mut c_void,
+                    OIDN_FORMAT_FLOAT3,
+                    width,
+                    height,
+                    0,
+                    0,
+                    0,
+                )
+            };
+            // OIDN only reads the input buffers
+            set_image(b"color\0", color.as_ptr() as *mut f32);
+            match (albedo, normal) {
+                (Some(albedo), Some(normal)) => {
+                    set_image(b"albedo\0", albedo.as_ptr() as *mut f32);
+                    set_image(b"normal\0", normal.as_ptr() as *mut f32);
+                }
+                (Some(albedo), None) => {
+                    set_image(b"albedo\0", albedo.as_ptr() as *mut f32);
+                }
+                (None, Some(_normal)) => {
+                    println!("WARNING: Ignoring normal buffer for denoising without albedo buffer");
+                }
+                (None, None) => {}
+            }
+            set_image(b"output\0", output.as_mut_ptr());
+            (library.set_filter_bool)(filter, b"hdr\0".as_ptr() as *const c_char, true);
+            (library.commit_filter)(filter);
+            println!("Denoising image ...");
+            (library.execute_filter)(filter);
+            (library.release_filter)(filter);
+        }
+        let mut message: *const c_char = std::ptr::null();
+        let error: c_int = (library.get_device_error)(device, &mut message);
+        let result: Result<Vec<f32>, String> = if error != OIDN_ERROR_NONE {
+            if message.is_null() {
+                Err(format!("OIDN error {}", error))
+            } else {
+                Err(CStr::from_ptr(message).to_string_lossy().into_owned())
+            }
+        } else if filter.is_null() {
+            Err(String::from("unable to create an OIDN filter"))
+        } else {
+            Ok(output)
+        };
+        (library.release_device)(device);
+        result
+    }
+}
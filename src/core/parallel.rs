@@ -20,6 +20,9 @@ impl AtomicFloat {
             bits: Atomic::new(float_to_bits(v)),
         }
     }
+    pub fn store(&self, v: Float) {
+        self.bits.store(float_to_bits(v), Ordering::SeqCst);
+    }
     pub fn add(&self, v: Float) {
         let mut old_bits: u32 = self.bits.load(Ordering::Relaxed);
         loop {
@@ -1,6 +1,7 @@
 //! Bundle up parameters and their values in a generic way.
 
 // std
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::env;
 use std::fs::File;
@@ -10,21 +11,24 @@ use std::sync::Arc;
 use crate::core::floatfile::read_float_file;
 use crate::core::geometry::{Normal3f, Point2f, Point3f, Vector2f, Vector3f};
 use crate::core::pbrt::{Float, Spectrum};
-use crate::core::spectrum::blackbody_normalized;
-use crate::core::spectrum::{CIE_LAMBDA, N_CIE_SAMPLES};
+use crate::core::spectrum::SpectrumType;
 use crate::core::texture::Texture;
 use crate::textures::constant::ConstantTexture;
 
 // see paramset.h
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParamSetItem<T> {
     pub name: String,
     pub values: Vec<T>,
     pub n_values: usize,
-    pub looked_up: bool, // false
+    /// set by the **find_...()** methods (see **report_unused()**)
+    pub looked_up: Cell<bool>, // false
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParamSet {
     pub key_word: String,
     pub name: String,
@@ -39,6 +43,9 @@ pub struct ParamSet {
     pub vector3fs: Vec<ParamSetItem<Vector3f>>,
     pub normals: Vec<ParamSetItem<Normal3f>>,
     pub spectra: Vec<ParamSetItem<Spectrum>>,
+    /// RGB values from _spectra_, converted as illuminants (see
+    /// **find_one_illuminant()**)
+    pub rgb_illuminants: Vec<ParamSetItem<Spectrum>>,
     pub strings: Vec<ParamSetItem<String>>,
     pub textures: Vec<ParamSetItem<String>>,
 }
@@ -58,6 +65,7 @@ impl ParamSet {
         self.vector3fs.clear();
         self.normals.clear();
         self.spectra.clear();
+        self.rgb_illuminants.clear();
         self.strings.clear();
         self.textures.clear();
     }
@@ -66,7 +74,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_floats(&mut self, name: String, values: Vec<Float>) {
@@ -75,7 +83,7 @@ impl ParamSet {
             name,
             values,
             n_values,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_int(&mut self, name: String, value: i32) {
@@ -83,7 +91,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_ints(&mut self, name: String, values: Vec<i32>) {
@@ -92,7 +100,7 @@ impl ParamSet {
             name,
             values,
             n_values,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_bool(&mut self, name: String, value: bool) {
@@ -100,7 +108,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_point2f(&mut self, name: String, value: Point2f) {
@@ -108,7 +116,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_point2fs(&mut self, name: String, values: Vec<Float>) {
@@ -130,7 +138,37 @@ impl ParamSet {
             name,
             values: p_values,
             n_values: n_points,
-            looked_up: false,
+            looked_up: Cell::new(false),
+        });
+    }
+    pub fn add_vector2f(&mut self, name: String, value: Vector2f) {
+        self.vector2fs.push(ParamSetItem::<Vector2f> {
+            name,
+            values: vec![value],
+            n_values: 1_usize,
+            looked_up: Cell::new(false),
+        });
+    }
+    pub fn add_vector2fs(&mut self, name: String, values: Vec<Float>) {
+        let n_values: usize = values.len();
+        let mut v_values: Vec<Vector2f> = Vec::new();
+        let n_vectors: usize = values.len() / 2_usize;
+        assert!(
+            n_values % 2 == 0,
+            "vector parameters need 2 coordinates ({} found for {:?})",
+            n_values,
+            name
+        );
+        for i in 0..n_vectors {
+            let x: Float = values[i * 2 + 0];
+            let y: Float = values[i * 2 + 1];
+            v_values.push(Vector2f { x, y });
+        }
+        self.vector2fs.push(ParamSetItem::<Vector2f> {
+            name,
+            values: v_values,
+            n_values: n_vectors,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_point3f(&mut self, name: String, value: Point3f) {
@@ -138,7 +176,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_point3fs(&mut self, name: String, values: Vec<Float>) {
@@ -161,7 +199,7 @@ impl ParamSet {
             name,
             values: p_values,
             n_values: n_points,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_sampled_spectrum_files(&mut self, name: String, names: Vec<String>) {
@@ -210,7 +248,7 @@ impl ParamSet {
             name: name.clone(),
             values: s,
             n_values,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_string(&mut self, name: String, value: String) {
@@ -218,7 +256,16 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
+        });
+    }
+    pub fn add_spectrum(&mut self, name: String, value: Spectrum) {
+        self.erase_spectrum(name.clone());
+        self.spectra.push(ParamSetItem::<Spectrum> {
+            name,
+            values: vec![value],
+            n_values: 1_usize,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_texture(&mut self, name: String, value: String) {
@@ -226,7 +273,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_vector3f(&mut self, name: String, value: Vector3f) {
@@ -234,7 +281,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_vector3fs(&mut self, name: String, values: Vec<Float>) {
@@ -252,7 +299,7 @@ impl ParamSet {
             name,
             values: p_values,
             n_values: n_vectors,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_normal3f(&mut self, name: String, value: Normal3f) {
@@ -260,7 +307,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_normal3fs(&mut self, name: String, values: Vec<Float>) {
@@ -278,36 +325,57 @@ impl ParamSet {
             name,
             values: p_values,
             n_values: n_normals,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
+    /// Adds a spectrum, e.g. made with **Spectrum::rgb()**. Lights
+    /// use it as is, so to get an illuminant from RGB values with the
+    /// sampled-spectrum feature, use **add_rgb()** instead.
     pub fn add_rgb_spectrum(&mut self, name: String, value: Spectrum) {
+        self.erase_spectrum(name.clone());
         self.spectra.push(ParamSetItem::<Spectrum> {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
+        });
+    }
+    /// Adds an RGB value (like the "rgb" parameters of scene files),
+    /// converted to a reflectance spectrum, and to an illuminant
+    /// spectrum for lights (see **find_one_illuminant()**).
+    pub fn add_rgb(&mut self, name: String, rgb: [Float; 3]) {
+        self.erase_spectrum(name.clone());
+        self.spectra.push(ParamSetItem::<Spectrum> {
+            name: name.clone(),
+            values: vec![Spectrum::from_rgb(&rgb)],
+            n_values: 1_usize,
+            looked_up: Cell::new(false),
+        });
+        self.rgb_illuminants.push(ParamSetItem::<Spectrum> {
+            name,
+            values: vec![Spectrum::from_rgb_type(&rgb, SpectrumType::Illuminant)],
+            n_values: 1_usize,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_blackbody_spectrum(&mut self, name: String, values: Vec<Float>) {
         assert!(values.len() % 2 == 0);
+        self.erase_spectrum(name.clone());
         // temperature (K), scale, ...
         let n_values: usize = values.len() / 2_usize;
         let mut s: Vec<Spectrum> = Vec::with_capacity(n_values);
-        let mut v: Vec<Float> = Vec::with_capacity(N_CIE_SAMPLES as usize);
         for i in 0..n_values {
-            blackbody_normalized(&CIE_LAMBDA, N_CIE_SAMPLES as usize, values[2 * i], &mut v);
-            s.push(
-                Spectrum::from_sampled(&CIE_LAMBDA, &v, N_CIE_SAMPLES as i32) * values[2 * i + 1],
-            );
+            s.push(Spectrum::blackbody(values[2 * i], values[2 * i + 1]));
         }
         self.spectra.push(ParamSetItem::<Spectrum> {
             name,
             values: s,
             n_values,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
+    /// Copies the parameters (keeping track of the ones looked up so
+    /// far, like the shared items on the C++ side).
     pub fn copy_from(&mut self, param_set: &ParamSet) {
         self.key_word = param_set.key_word.clone();
         // self.name = param_set.name.clone();
@@ -321,7 +389,7 @@ impl ParamSet {
                 name: b.name.clone(),
                 values,
                 n_values: b.n_values,
-                looked_up: false,
+                looked_up: Cell::new(b.looked_up.get()),
             });
         }
         self.ints.clear();
@@ -334,7 +402,7 @@ impl ParamSet {
                 name: i.name.clone(),
                 values,
                 n_values: i.n_values,
-                looked_up: false,
+                looked_up: Cell::new(i.looked_up.get()),
             });
         }
         self.floats.clear();
@@ -347,7 +415,7 @@ impl ParamSet {
                 name: f.name.clone(),
                 values,
                 n_values: f.n_values,
-                looked_up: false,
+                looked_up: Cell::new(f.looked_up.get()),
             });
         }
         self.point2fs.clear();
@@ -362,7 +430,7 @@ impl ParamSet {
                 name: p.name.clone(),
                 values,
                 n_values: p.n_values,
-                looked_up: false,
+                looked_up: Cell::new(p.looked_up.get()),
             });
         }
         self.vector3fs.clear();
@@ -377,7 +445,16 @@ impl ParamSet {
                 name: s.name.clone(),
                 values,
                 n_values: s.n_values,
-                looked_up: false,
+                looked_up: Cell::new(s.looked_up.get()),
+            });
+        }
+        self.rgb_illuminants.clear();
+        for s in &param_set.rgb_illuminants {
+            self.rgb_illuminants.push(ParamSetItem::<Spectrum> {
+                name: s.name.clone(),
+                values: s.values.clone(),
+                n_values: s.n_values,
+                looked_up: Cell::new(s.looked_up.get()),
             });
         }
         self.strings.clear();
@@ -390,7 +467,7 @@ impl ParamSet {
                 name: s.name.clone(),
                 values,
                 n_values: s.n_values,
-                looked_up: false,
+                looked_up: Cell::new(s.looked_up.get()),
             });
         }
         self.textures.clear();
@@ -403,11 +480,67 @@ impl ParamSet {
                 name: s.name.clone(),
                 values,
                 n_values: s.n_values,
-                looked_up: false,
+                looked_up: Cell::new(s.looked_up.get()),
             });
         }
     }
+    /// Names of all parameters which were never looked up, e.g.
+    /// misspelled ones.
+    pub fn report_unused(&self) -> Vec<String> {
+        let mut unused: Vec<String> = Vec::new();
+        fn check<T>(items: &[ParamSetItem<T>], unused: &mut Vec<String>) {
+            for item in items {
+                if !item.looked_up.get() {
+                    unused.push(item.name.clone());
+                }
+            }
+        }
+        check(&self.bools, &mut unused);
+        check(&self.ints, &mut unused);
+        check(&self.floats, &mut unused);
+        check(&self.point2fs, &mut unused);
+        check(&self.vector2fs, &mut unused);
+        check(&self.point3fs, &mut unused);
+        check(&self.vector3fs, &mut unused);
+        check(&self.normals, &mut unused);
+        // rgb_illuminants duplicate spectra
+        check(&self.spectra, &mut unused);
+        check(&self.strings, &mut unused);
+        check(&self.textures, &mut unused);
+        unused
+    }
+    /// Marks all parameters as looked up, so they don't get reported
+    /// (again) by **report_unused()**.
+    pub fn mark_looked_up(&self) {
+        fn mark<T>(items: &[ParamSetItem<T>]) {
+            for item in items {
+                item.looked_up.set(true);
+            }
+        }
+        mark(&self.bools);
+        mark(&self.ints);
+        mark(&self.floats);
+        mark(&self.point2fs);
+        mark(&self.vector2fs);
+        mark(&self.point3fs);
+        mark(&self.vector3fs);
+        mark(&self.normals);
+        mark(&self.spectra);
+        mark(&self.strings);
+        mark(&self.textures);
+    }
+    pub fn erase_int(&mut self, name: &str) -> bool {
+        let n_ints: usize = self.ints.len();
+        self.ints.retain(|i| i.name != name);
+        self.ints.len() != n_ints
+    }
+    pub fn erase_string(&mut self, name: &str) -> bool {
+        let n_strings: usize = self.strings.len();
+        self.strings.retain(|s| s.name != name);
+        self.strings.len() != n_strings
+    }
     pub fn erase_spectrum(&mut self, name: String) -> bool {
+        self.rgb_illuminants.retain(|s| s.name != name);
         for i in 0..self.spectra.len() {
             if self.spectra[i].name == name {
                 self.spectra.remove(i);
@@ -419,7 +552,7 @@ impl ParamSet {
     pub fn find_one_float(&self, name: &str, d: Float) -> Float {
         for v in &self.floats {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
@@ -428,7 +561,7 @@ impl ParamSet {
     pub fn find_one_int(&self, name: &str, d: i32) -> i32 {
         for v in &self.ints {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
@@ -437,7 +570,7 @@ impl ParamSet {
     pub fn find_one_bool(&self, name: &str, d: bool) -> bool {
         for v in &self.bools {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
@@ -446,7 +579,7 @@ impl ParamSet {
     pub fn find_one_point3f(&self, name: &str, d: Point3f) -> Point3f {
         for v in &self.point3fs {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
@@ -455,7 +588,7 @@ impl ParamSet {
     pub fn find_one_vector3f(&self, name: &str, d: Vector3f) -> Vector3f {
         for v in &self.vector3fs {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
@@ -464,16 +597,29 @@ impl ParamSet {
     pub fn find_one_spectrum(&self, name: &str, d: Spectrum) -> Spectrum {
         for v in &self.spectra {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
         d
     }
+    /// Like **find_one_spectrum()**, but converts RGB values as
+    /// illuminants (instead of reflectances), which makes a difference
+    /// for a **SampledSpectrum**. Use it for emitted radiance.
+    pub fn find_one_illuminant(&self, name: &str, d: Spectrum) -> Spectrum {
+        // marks the spectrum as looked up
+        let s: Spectrum = self.find_one_spectrum(name, d);
+        for v in &self.rgb_illuminants {
+            if v.name == name && v.n_values == 1 {
+                return v.values[0];
+            }
+        }
+        s
+    }
     pub fn find_one_string(&self, name: &str, d: String) -> String {
         for v in &self.strings {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0].clone();
             }
         }
@@ -491,12 +637,25 @@ impl ParamSet {
         let d: String = String::new();
         lookup_one(&self.textures, name, d)
     }
+    pub fn find_bool(&self, name: &str) -> Vec<bool> {
+        let mut values: Vec<bool> = Vec::new();
+        for v in &self.bools {
+            if v.name == name {
+                let n_values = v.n_values;
+                v.looked_up.set(true);
+                for i in 0..n_values {
+                    values.push(v.values[i]);
+                }
+            }
+        }
+        values
+    }
     pub fn find_int(&self, name: &str) -> Vec<i32> {
         let mut values: Vec<i32> = Vec::new();
         for v in &self.ints {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -509,7 +668,7 @@ impl ParamSet {
         for v in &self.floats {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -522,7 +681,7 @@ impl ParamSet {
         for v in &self.point2fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -535,7 +694,7 @@ impl ParamSet {
         for v in &self.vector2fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -548,7 +707,7 @@ impl ParamSet {
         for v in &self.point3fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -561,7 +720,7 @@ impl ParamSet {
         for v in &self.vector3fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -574,7 +733,7 @@ impl ParamSet {
         for v in &self.normals {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -587,7 +746,7 @@ impl ParamSet {
         for v in &self.spectra {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -730,6 +889,52 @@ impl TextureParams {
             Some(Arc::new(ConstantTexture { value: val[0] }))
         }
     }
+    /// Is parameter _n_ given as a float (value or named float
+    /// texture) instead of a spectrum?
+    pub fn is_float_texture(&self, n: &str) -> bool {
+        let mut name: String = self.geom_params.find_texture(n);
+        if name == "" {
+            name = self.material_params.find_texture(n);
+        }
+        if name != "" {
+            return self.float_textures.contains_key(name.as_str())
+                && !self.spectrum_textures.contains_key(name.as_str());
+        }
+        !self.geom_params.find_float(n).is_empty() || !self.material_params.find_float(n).is_empty()
+    }
+    /// Names of the geometry and material parameters which were never
+    /// read, to catch typos:
+    ///
+    /// ```rust
+    /// use pbrt::core::paramset::{ParamSet, TextureParams};
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::materials::metal::MetalMaterial;
+    /// use std::collections::HashMap;
+    /// use std::sync::Arc;
+    ///
+    /// let mut material_params = ParamSet::default();
+    /// material_params.add_float(String::from("roughnes"), 0.2 as Float);
+    /// material_params.add_spectrum(String::from("k"), Spectrum::new(3.0 as Float));
+    /// material_params.add_texture(String::from("bumpmap"), String::from("bumps"));
+    /// let mut float_textures = HashMap::new();
+    /// float_textures.insert(
+    ///     String::from("bumps"),
+    ///     Arc::new(pbrt::textures::constant::ConstantTexture { value: 0.0 as Float }) as _,
+    /// );
+    /// let mut mp = TextureParams::new(
+    ///     ParamSet::default(),
+    ///     material_params,
+    ///     Arc::new(float_textures),
+    ///     Arc::new(HashMap::new()),
+    /// );
+    /// let _metal = MetalMaterial::create(&mut mp);
+    /// assert_eq!(mp.report_unused(), vec![String::from("roughnes")]);
+    /// ```
+    pub fn report_unused(&self) -> Vec<String> {
+        let mut unused: Vec<String> = self.geom_params.report_unused();
+        unused.extend(self.material_params.report_unused());
+        unused
+    }
     pub fn find_float(&mut self, name: &str, d: Float) -> Float {
         self.geom_params
             .find_one_float(name, self.material_params.find_one_float(name, d))
@@ -767,7 +972,7 @@ where
 {
     for v in vec {
         if v.name == name && v.n_values == 1_usize {
-            // v.looked_up = true;
+            v.looked_up.set(true);
             return v.values[0].clone();
         }
     }
@@ -8,11 +8,17 @@ use std::ops::{Add, BitAnd, Div, Mul, Sub};
 // others
 use num;
 // pbrt
+#[cfg(not(feature = "sampled-spectrum"))]
 use crate::core::spectrum::RGBSpectrum;
+#[cfg(feature = "sampled-spectrum")]
+use crate::core::spectrum::SampledSpectrum;
 
 // see pbrt.h
 
+#[cfg(not(feature = "sampled-spectrum"))]
 pub type Spectrum = RGBSpectrum;
+#[cfg(feature = "sampled-spectrum")]
+pub type Spectrum = SampledSpectrum;
 
 pub type Float = f32;
 
@@ -200,6 +206,15 @@ where
 }
 
 /// Round an integer up to the next higher (or equal) power of 2.
+///
+/// ```rust
+/// use pbrt::core::pbrt::round_up_pow2_32;
+///
+/// assert_eq!(round_up_pow2_32(1), 1);
+/// assert_eq!(round_up_pow2_32(5), 8);
+/// assert_eq!(round_up_pow2_32(64), 64);
+/// assert_eq!(round_up_pow2_32(1000), 1024);
+/// ```
 pub fn round_up_pow2_32(v: i32) -> i32 {
     let mut ret: i32 = v; // copy value
     ret -= 1_i32;
@@ -5,8 +5,9 @@
 use std::sync::Arc;
 // pbrt
 use crate::accelerators::bvh::BVHAccel;
+#[cfg(feature = "embree")]
+use crate::accelerators::embree::EmbreeAccel;
 use crate::accelerators::kdtreeaccel::KdTreeAccel;
-use crate::core::geometry::nrm_dot_nrm;
 use crate::core::geometry::{Bounds3f, Ray};
 use crate::core::interaction::SurfaceInteraction;
 use crate::core::light::Light;
@@ -14,15 +15,21 @@ use crate::core::material::{Material, TransportMode};
 use crate::core::medium::{Medium, MediumInterface};
 use crate::core::pbrt::Float;
 use crate::core::shape::Shape;
+#[cfg(feature = "stats")]
+use crate::core::stats::{named_stat_add, NamedStat};
 use crate::core::transform::{AnimatedTransform, Transform};
 
 // see primitive.h
 
+crate::stat_memory!("Memory/Primitives", PRIMITIVE_MEMORY);
+
 pub enum Primitive {
     Geometric(GeometricPrimitive),
     Transformed(TransformedPrimitive),
     BVH(BVHAccel),
     KdTree(KdTreeAccel),
+    #[cfg(feature = "embree")]
+    Embree(EmbreeAccel),
 }
 
 impl Primitive {
@@ -32,6 +39,8 @@ impl Primitive {
             Primitive::Transformed(primitive) => primitive.world_bound(),
             Primitive::BVH(primitive) => primitive.world_bound(),
             Primitive::KdTree(primitive) => primitive.world_bound(),
+            #[cfg(feature = "embree")]
+            Primitive::Embree(primitive) => primitive.world_bound(),
         }
     }
     pub fn intersect(&self, ray: &mut Ray) -> Option<SurfaceInteraction> {
@@ -48,6 +57,8 @@ impl Primitive {
             Primitive::Transformed(primitive) => primitive.intersect(ray),
             Primitive::BVH(primitive) => primitive.intersect(ray),
             Primitive::KdTree(primitive) => primitive.intersect(ray),
+            #[cfg(feature = "embree")]
+            Primitive::Embree(primitive) => primitive.intersect(ray),
         }
     }
     pub fn intersect_p(&self, ray: &Ray) -> bool {
@@ -56,6 +67,8 @@ impl Primitive {
             Primitive::Transformed(primitive) => primitive.intersect_p(ray),
             Primitive::BVH(primitive) => primitive.intersect_p(ray),
             Primitive::KdTree(primitive) => primitive.intersect_p(ray),
+            #[cfg(feature = "embree")]
+            Primitive::Embree(primitive) => primitive.intersect_p(ray),
         }
     }
     pub fn get_area_light(&self) -> Option<Arc<Light>> {
@@ -64,6 +77,8 @@ impl Primitive {
             Primitive::Transformed(primitive) => primitive.get_area_light(),
             Primitive::BVH(primitive) => primitive.get_area_light(),
             Primitive::KdTree(primitive) => primitive.get_area_light(),
+            #[cfg(feature = "embree")]
+            Primitive::Embree(primitive) => primitive.get_area_light(),
         }
     }
     pub fn get_material(&self) -> Option<Arc<Material>> {
@@ -72,6 +87,16 @@ impl Primitive {
             Primitive::Transformed(primitive) => primitive.get_material(),
             Primitive::BVH(primitive) => primitive.get_material(),
             Primitive::KdTree(primitive) => primitive.get_material(),
+            #[cfg(feature = "embree")]
+            Primitive::Embree(primitive) => primitive.get_material(),
+        }
+    }
+    /// The id the material is reported under in the statistics (see
+    /// **stat_name_id()**), 0 for everything but geometric primitives.
+    pub fn get_material_stats_id(&self) -> u32 {
+        match self {
+            Primitive::Geometric(primitive) => primitive.material_stats_id,
+            _ => 0,
         }
     }
     pub fn compute_scattering_functions(
@@ -82,7 +107,11 @@ impl Primitive {
     ) {
         match self {
             _ => {
+                isect.correct_shading_normal();
                 if let Some(ref material) = self.get_material() {
+                    let stats_id: u32 = self.get_material_stats_id();
+                    #[cfg(feature = "stats")]
+                    let start = std::time::Instant::now();
                     material.compute_scattering_functions(
                         isect,
                         mode,
@@ -90,13 +119,16 @@ impl Primitive {
                         self.get_material(),
                         None,
                     );
+                    #[cfg(feature = "stats")]
+                    named_stat_add(
+                        stats_id,
+                        NamedStat::ShadingNanos,
+                        start.elapsed().as_nanos() as i64,
+                    );
+                    if let Some(ref mut bsdf) = isect.bsdf {
+                        bsdf.stats_id = stats_id;
+                    }
                 }
-                assert!(
-                    nrm_dot_nrm(&isect.n, &isect.shading.n) >= 0.0,
-                    "n: {:?} dot shading.n: {:?}",
-                    isect.n,
-                    isect.shading.n
-                );
             }
         }
     }
@@ -108,6 +140,8 @@ pub struct GeometricPrimitive {
     pub material: Option<Arc<Material>>,
     pub area_light: Option<Arc<Light>>,
     pub medium_interface: Option<Arc<MediumInterface>>,
+    /// see **stat_name_id()**
+    pub material_stats_id: u32,
 }
 
 impl GeometricPrimitive {
@@ -117,6 +151,7 @@ impl GeometricPrimitive {
         area_light: Option<Arc<Light>>,
         medium_interface: Option<Arc<MediumInterface>>,
     ) -> Self {
+        crate::stat_add!(PRIMITIVE_MEMORY, std::mem::size_of::<Primitive>());
         if let Some(area_light) = area_light {
             if let Some(medium_interface) = medium_interface {
                 GeometricPrimitive {
@@ -124,6 +159,7 @@ impl GeometricPrimitive {
                     material,
                     area_light: Some(area_light),
                     medium_interface: Some(medium_interface),
+                    material_stats_id: 0,
                 }
             } else {
                 GeometricPrimitive {
@@ -131,6 +167,7 @@ impl GeometricPrimitive {
                     material,
                     area_light: Some(area_light),
                     medium_interface: None,
+                    material_stats_id: 0,
                 }
             }
         } else {
@@ -140,6 +177,7 @@ impl GeometricPrimitive {
                     material,
                     area_light: None,
                     medium_interface: Some(medium_interface),
+                    material_stats_id: 0,
                 }
             } else {
                 GeometricPrimitive {
@@ -147,6 +185,7 @@ impl GeometricPrimitive {
                     material,
                     area_light: None,
                     medium_interface: None,
+                    material_stats_id: 0,
                 }
             }
         }
@@ -156,40 +195,50 @@ impl GeometricPrimitive {
         self.shape.world_bound()
     }
     pub fn intersect(&self, ray: &mut Ray) -> Option<SurfaceInteraction> {
-        if let Some((mut isect, t_hit)) = self.shape.intersect(ray) {
-            // isect.primitive = Some(self);
-            ray.t_max = t_hit;
-            assert!(nrm_dot_nrm(&isect.n, &isect.shading.n) >= 0.0 as Float);
-            // initialize _SurfaceInteraction::mediumInterface_ after
-            // _Shape_ intersection
-            if let Some(ref medium_interface) = self.medium_interface {
-                if medium_interface.is_medium_transition() {
-                    isect.medium_interface = Some(medium_interface.clone());
-                } else {
-                    if let Some(ref medium_arc) = ray.medium {
-                        let inside: Option<Arc<Medium>> = Some(medium_arc.clone());
-                        let outside: Option<Arc<Medium>> = Some(medium_arc.clone());
-                        isect.medium_interface =
-                            Some(Arc::new(MediumInterface::new(inside, outside)));
-                    }
-                }
-                // print!("medium_interface = {{inside = ");
-                // if let Some(ref inside) = medium_interface.inside {
-                //     print!("{:p} , outside = ", inside);
-                // } else {
-                //     print!("0x0 , outside = ")
-                // }
-                // if let Some(ref outside) = medium_interface.outside {
-                //     println!("{:p}}}", outside);
-                // } else {
-                //     println!("0x0}}")
-                // }
-            }
-            Some(isect)
+        if let Some((isect, t_hit)) = self.shape.intersect(ray) {
+            Some(self.complete_interaction(ray, isect, t_hit))
         } else {
             None
         }
     }
+    /// Finishes a hit of the shape at distance _t_hit_ along _ray_
+    /// (found by intersect() or e.g. by Embree): updates the ray's
+    /// _t_max_, and the shading normal and medium interface of the
+    /// surface interaction.
+    pub fn complete_interaction<'a>(
+        &self,
+        ray: &mut Ray,
+        mut isect: SurfaceInteraction<'a>,
+        t_hit: Float,
+    ) -> SurfaceInteraction<'a> {
+        ray.t_max = t_hit;
+        isect.correct_shading_normal();
+        // initialize _SurfaceInteraction::mediumInterface_ after
+        // _Shape_ intersection
+        if let Some(ref medium_interface) = self.medium_interface {
+            if medium_interface.is_medium_transition() {
+                isect.medium_interface = Some(medium_interface.clone());
+            } else {
+                if let Some(ref medium_arc) = ray.medium {
+                    let inside: Option<Arc<Medium>> = Some(medium_arc.clone());
+                    let outside: Option<Arc<Medium>> = Some(medium_arc.clone());
+                    isect.medium_interface = Some(Arc::new(MediumInterface::new(inside, outside)));
+                }
+            }
+            // print!("medium_interface = {{inside = ");
+            // if let Some(ref inside) = medium_interface.inside {
+            //     print!("{:p} , outside = ", inside);
+            // } else {
+            //     print!("0x0 , outside = ")
+            // }
+            // if let Some(ref outside) = medium_interface.outside {
+            //     println!("{:p}}}", outside);
+            // } else {
+            //     println!("0x0}}")
+            // }
+        }
+        isect
+    }
     pub fn intersect_p(&self, r: &Ray) -> bool {
         self.shape.intersect_p(r)
     }
@@ -211,11 +260,12 @@ impl GeometricPrimitive {
 
 pub struct TransformedPrimitive {
     pub primitive: Arc<Primitive>,
-    pub primitive_to_world: AnimatedTransform,
+    pub primitive_to_world: Arc<AnimatedTransform>,
 }
 
 impl TransformedPrimitive {
-    pub fn new(primitive: Arc<Primitive>, primitive_to_world: AnimatedTransform) -> Self {
+    pub fn new(primitive: Arc<Primitive>, primitive_to_world: Arc<AnimatedTransform>) -> Self {
+        crate::stat_add!(PRIMITIVE_MEMORY, std::mem::size_of::<Primitive>());
         TransformedPrimitive {
             primitive,
             primitive_to_world,
@@ -236,8 +286,9 @@ impl TransformedPrimitive {
             r.t_max = ray.t_max;
             // transform instance's intersection data to world space
             if !interpolated_prim_to_world.is_identity() {
-                let new_isect = interpolated_prim_to_world.transform_surface_interaction(&isect);
-                assert!(nrm_dot_nrm(&new_isect.n, &new_isect.shading.n) >= 0.0 as Float);
+                let mut new_isect =
+                    interpolated_prim_to_world.transform_surface_interaction(&isect);
+                new_isect.correct_shading_normal();
                 let mut is: SurfaceInteraction = SurfaceInteraction::new(
                     &new_isect.p,
                     &new_isect.p_error,
@@ -263,7 +314,8 @@ impl TransformedPrimitive {
                 is.shading.dndv = new_isect.shading.dndv;
                 return Some(is);
             }
-            None
+            // identity transform: intersection (and primitive) unchanged
+            Some(isect)
         } else {
             None
         }
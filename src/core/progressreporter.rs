@@ -0,0 +1,86 @@
+//! Progress reporting and cancellation for long running renders (see
+//! progressreporter.h). The integrators report finished work units
+//! (image tiles, SPPM iterations, or Markov chains) through the
+//! **RenderProgress** trait and check a **CancellationToken** before
+//! starting new ones, so a caller (e.g. a GUI) can show progress and
+//! stop a render cleanly. A cancelled render still writes the film
+//! with the work finished so far.
+
+// std
+use std::io::{stderr, Stderr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+// see progressreporter.h
+
+/// Receives progress updates from the render loop. Updates can come
+/// from any thread, but are reported in increasing order of
+/// _completed_.
+pub trait RenderProgress: Send + Sync {
+    /// Called after a work unit (e.g. an image tile) was merged into
+    /// the film, _completed_ out of _total_ are done.
+    fn on_tile_done(&self, completed: usize, total: usize);
+}
+
+/// Shared flag to abort a render between work units. Clones refer to
+/// the same flag, so one can be handed to the render loop while
+/// another is kept to cancel it.
+#[derive(Debug, Default, Clone)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+    /// Requests the render to stop. Work units merged into the film
+    /// so far are kept, image tiles finished after the request are
+    /// dropped.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+}
+
+/// Default **RenderProgress** implementation drawing a progress bar
+/// on stderr. A new bar is started after the previous one is complete
+/// (e.g. for the passes of adaptive sampling).
+#[derive(Default)]
+pub struct ProgressBar {
+    bar: Mutex<Option<pbr::ProgressBar<Stderr>>>,
+}
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        ProgressBar::default()
+    }
+}
+
+impl RenderProgress for ProgressBar {
+    fn on_tile_done(&self, completed: usize, total: usize) {
+        let mut bar = self.bar.lock().unwrap();
+        if bar.is_none() {
+            *bar = Some(pbr::ProgressBar::on(stderr(), total as u64));
+        }
+        if let Some(ref mut pb) = *bar {
+            pb.set(completed as u64);
+        }
+        if completed >= total {
+            if let Some(mut pb) = bar.take() {
+                pb.finish();
+            }
+        }
+    }
+}
+
+/// **RenderProgress** implementation ignoring all updates, e.g. for
+/// progressive rendering, which reports finished passes instead.
+#[derive(Default)]
+pub struct NoProgress;
+
+impl RenderProgress for NoProgress {
+    fn on_tile_done(&self, _completed: usize, _total: usize) {}
+}
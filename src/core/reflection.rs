@@ -33,6 +33,7 @@ use crate::core::pbrt::{clamp_t, lerp, radians};
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::rng::FLOAT_ONE_MINUS_EPSILON;
 use crate::core::sampling::cosine_sample_hemisphere;
+use crate::core::stats::{named_stat_add, NamedStat};
 use crate::materials::disney::{
     DisneyClearCoat, DisneyDiffuse, DisneyFakeSS, DisneyRetro, DisneySheen,
 };
@@ -63,6 +64,13 @@ fn fr_schlick_spectrum(r0: Spectrum, cos_theta: Float) -> Spectrum {
 
 // see reflection.h
 
+crate::stat_counter!("BSDF/BSDF evaluations", N_BSDF_EVALUATIONS);
+crate::stat_counter!("BSDF/BSDF samples", N_BSDF_SAMPLES);
+crate::stat_counter!(
+    "BSDF/Directions rejected by geometric normal",
+    N_BSDF_DIRECTIONS_REJECTED
+);
+
 #[derive(Default)]
 pub struct FourierBSDFTable {
     pub eta: Float,
@@ -230,6 +238,10 @@ pub struct Bsdf {
     pub ss: Vector3f,
     pub ts: Vector3f,
     pub bxdfs: [Bxdf; 8],
+    /// the material's id in the statistics (see **stat_name_id()**)
+    pub stats_id: u32,
+    /// reject inconsistent directions (see is_inconsistent())
+    pub check_geometric_normal: bool,
 }
 
 impl Bsdf {
@@ -251,6 +263,8 @@ impl Bsdf {
                 Bxdf::Empty(NoBxdf::default()),
                 Bxdf::Empty(NoBxdf::default()),
             ],
+            stats_id: 0,
+            check_geometric_normal: si.check_geometric_normal,
         }
     }
     pub fn num_components(&self, flags: u8) -> u8 {
@@ -277,13 +291,25 @@ impl Bsdf {
             z: self.ss.z * v.x + self.ts.z * v.y + self.ns.z * v.z,
         }
     }
+    /// Is _wi_ on different sides of the surface with respect to the
+    /// geometric and the shading normal (see
+    /// **Scene::set_check_geometric_normal()**)?
+    pub fn is_inconsistent(&self, wi_world: &Vector3f) -> bool {
+        self.check_geometric_normal
+            && vec3_dot_nrm(wi_world, &self.ng) * vec3_dot_nrm(wi_world, &self.ns) < 0.0 as Float
+    }
     pub fn f(&self, wo_w: &Vector3f, wi_w: &Vector3f, flags: u8) -> Spectrum {
-        // TODO: ProfilePhase pp(Prof::BSDFEvaluation);
+        crate::stat_inc!(N_BSDF_EVALUATIONS);
+        named_stat_add(self.stats_id, NamedStat::BsdfEvaluations, 1);
         let wi: Vector3f = self.world_to_local(wi_w);
         let wo: Vector3f = self.world_to_local(wo_w);
         if wo.z == 0.0 as Float {
             return Spectrum::new(0.0 as Float);
         }
+        if self.is_inconsistent(wi_w) {
+            crate::stat_inc!(N_BSDF_DIRECTIONS_REJECTED);
+            return Spectrum::new(0.0 as Float);
+        }
         let reflect: bool = (vec3_dot_vec3(wi_w, &Vector3f::from(self.ng))
             * vec3_dot_vec3(wo_w, &Vector3f::from(self.ng)))
             > 0.0 as Float;
@@ -300,6 +326,23 @@ impl Bsdf {
         }
         f
     }
+    /// Monte Carlo estimate of the hemispherical-directional
+    /// reflectance (albedo) for direction _wo_, using the given
+    /// sample points to sample the BSDF.
+    pub fn rho(&self, wo: &Vector3f, samples: &[Point2f], bsdf_flags: u8) -> Spectrum {
+        let mut r: Spectrum = Spectrum::new(0.0 as Float);
+        for u in samples {
+            let mut wi: Vector3f = Vector3f::default();
+            let mut pdf: Float = 0.0 as Float;
+            let mut sampled_type: u8 = 0_u8;
+            let f: Spectrum =
+                self.sample_f(wo, &mut wi, u, &mut pdf, bsdf_flags, &mut sampled_type);
+            if pdf > 0.0 as Float {
+                r += f * Spectrum::new(vec3_dot_nrm(&wi, &self.ns).abs() / pdf);
+            }
+        }
+        r / samples.len() as Float
+    }
     /// Calls the individual Bxdf::sample_f() methods to generate samples.
     pub fn sample_f(
         &self,
@@ -310,7 +353,8 @@ impl Bsdf {
         bsdf_flags: u8,
         sampled_type: &mut u8,
     ) -> Spectrum {
-        // TODO: ProfilePhase pp(Prof::BSDFSampling);
+        crate::stat_inc!(N_BSDF_SAMPLES);
+        named_stat_add(self.stats_id, NamedStat::BsdfEvaluations, 1);
         // choose which _BxDF_ to sample
         let matching_comps: u8 = self.num_components(bsdf_flags);
         if matching_comps == 0 {
@@ -382,6 +426,14 @@ impl Bsdf {
                 return Spectrum::default();
             }
             *wi_world = self.local_to_world(&wi);
+            if self.is_inconsistent(wi_world) {
+                crate::stat_inc!(N_BSDF_DIRECTIONS_REJECTED);
+                *pdf = 0.0 as Float;
+                if *sampled_type != 0_u8 {
+                    *sampled_type = 0_u8;
+                }
+                return Spectrum::default();
+            }
             // compute overall PDF with all matching _BxDF_s
             if (bxdf.get_type() & BxdfType::BsdfSpecular as u8 == 0_u8) && matching_comps > 1_u8 {
                 for i in 0..n_bxdfs {
@@ -431,7 +483,7 @@ impl Bsdf {
         }
         let wo: Vector3f = self.world_to_local(wo_world);
         let wi: Vector3f = self.world_to_local(wi_world);
-        if wo.z == 0.0 as Float {
+        if wo.z == 0.0 as Float || self.is_inconsistent(wi_world) {
             return 0.0 as Float;
         }
         let mut pdf: Float = 0.0 as Float;
@@ -1048,11 +1100,7 @@ impl LambertianTransmission {
             wi.z *= -1.0 as Float;
         }
         *pdf = self.pdf(wo, &*wi);
-        if let Some(sc) = self.sc_opt {
-            sc * self.f(wo, &*wi)
-        } else {
-            self.f(wo, &*wi)
-        }
+        self.f(wo, &*wi)
     }
     pub fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> Float {
         if !vec3_same_hemisphere_vec3(wo, wi) {
@@ -1075,6 +1123,31 @@ pub struct OrenNayar {
 }
 
 impl OrenNayar {
+    /// Rough diffuse reflection with reflectance _r_ and the standard
+    /// deviation _sigma_ (in degrees) of the microfacet orientation
+    /// angle. Without roughness it's a Lambertian reflector:
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Point2f, Vector3f};
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::reflection::{LambertianReflection, OrenNayar};
+    ///
+    /// let r: Spectrum = Spectrum::new(0.8);
+    /// let oren_nayar = OrenNayar::new(r, 0.0, None);
+    /// let lambertian = LambertianReflection::new(r, None);
+    /// let wo: Vector3f = Vector3f { x: 0.3, y: -0.5, z: 0.8 }.normalize();
+    /// for &(x, y) in &[(0.1 as Float, 0.2 as Float), (0.5, 0.5), (0.9, 0.7)] {
+    ///     let u: Point2f = Point2f { x, y };
+    ///     let mut wi: Vector3f = Vector3f::default();
+    ///     let mut pdf: Float = 0.0;
+    ///     let mut sampled_type: u8 = 0;
+    ///     let f: Spectrum = oren_nayar.sample_f(&wo, &mut wi, &u, &mut pdf, &mut sampled_type);
+    ///     assert!(pdf > 0.0);
+    ///     let expected: Spectrum = lambertian.f(&wo, &wi);
+    ///     assert!((f.y() - expected.y()).abs() < 1e-6);
+    ///     assert!((oren_nayar.f(&wo, &wi).y() - expected.y()).abs() < 1e-6);
+    /// }
+    /// ```
     pub fn new(r: Spectrum, sigma: Float, sc_opt: Option<Spectrum>) -> Self {
         let sigma = radians(sigma);
         let sigma2: Float = sigma * sigma;
@@ -1122,16 +1195,14 @@ impl OrenNayar {
         pdf: &mut Float,
         _sampled_type: &mut u8,
     ) -> Spectrum {
+        // cosine-sample the hemisphere (BxDF::Sample_f), flipping the
+        // direction if necessary; f() already applies the scale
         *wi = cosine_sample_hemisphere(u);
         if wo.z < 0.0 as Float {
             wi.z *= -1.0 as Float;
         }
         *pdf = self.pdf(wo, &*wi);
-        if let Some(sc) = self.sc_opt {
-            sc * self.f(wo, &*wi)
-        } else {
-            self.f(wo, &*wi)
-        }
+        self.f(wo, &*wi)
     }
     pub fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> Float {
         if vec3_same_hemisphere_vec3(wo, wi) {
@@ -1389,6 +1460,51 @@ pub struct FresnelBlend {
 }
 
 impl FresnelBlend {
+    /// A glossy coating with specular reflectance _rs_ over a diffuse
+    /// base with reflectance _rd_. For _rd_ + _rs_ <= 1 it doesn't
+    /// reflect more light than arrives (a white furnace test):
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Point2f, Vector3f};
+    /// use pbrt::core::microfacet::{MicrofacetDistribution, TrowbridgeReitzDistribution};
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::reflection::{abs_cos_theta, FresnelBlend};
+    ///
+    /// let distribution = MicrofacetDistribution::TrowbridgeReitz(
+    ///     TrowbridgeReitzDistribution::new(0.3, 0.3, true),
+    /// );
+    /// let rd: Spectrum = Spectrum::new(0.5);
+    /// let rs: Spectrum = Spectrum::new(0.5);
+    /// let blend = FresnelBlend::new(rd, rs, Some(distribution), None);
+    /// let scaled = FresnelBlend::new(rd, rs, Some(distribution), Some(Spectrum::new(0.5)));
+    /// for &cos_theta_o in &[0.05 as Float, 0.3, 0.7, 1.0] {
+    ///     let sin_theta_o: Float = (1.0 - cos_theta_o * cos_theta_o).sqrt();
+    ///     let wo: Vector3f = Vector3f { x: sin_theta_o, y: 0.0, z: cos_theta_o };
+    ///     // estimate the reflected fraction with stratified samples
+    ///     let n: usize = 128;
+    ///     let mut albedo: Float = 0.0;
+    ///     for i in 0..n * n {
+    ///         let u: Point2f = Point2f {
+    ///             x: ((i % n) as Float + 0.5) / n as Float,
+    ///             y: ((i / n) as Float + 0.5) / n as Float,
+    ///         };
+    ///         let mut wi: Vector3f = Vector3f::default();
+    ///         let mut pdf: Float = 0.0;
+    ///         let mut sampled_type: u8 = 0;
+    ///         let f: Spectrum = blend.sample_f(&wo, &mut wi, &u, &mut pdf, &mut sampled_type);
+    ///         if pdf > 0.0 {
+    ///             albedo += f.y() * abs_cos_theta(&wi) / pdf;
+    ///             // the scale is applied once
+    ///             let mut scaled_wi: Vector3f = Vector3f::default();
+    ///             let sf: Spectrum =
+    ///                 scaled.sample_f(&wo, &mut scaled_wi, &u, &mut pdf, &mut sampled_type);
+    ///             assert!((sf.y() - 0.5 * f.y()).abs() <= 1e-4 * f.y());
+    ///         }
+    ///     }
+    ///     albedo /= (n * n) as Float;
+    ///     assert!(albedo > 0.0 && albedo <= 1.0, "{} at cos {}", albedo, cos_theta_o);
+    /// }
+    /// ```
     pub fn new(
         rd: Spectrum,
         rs: Spectrum,
@@ -1465,11 +1581,8 @@ impl FresnelBlend {
             }
         }
         *pdf = self.pdf(wo, &*wi);
-        if let Some(sc) = self.sc_opt {
-            sc * self.f(wo, &*wi)
-        } else {
-            self.f(wo, &*wi)
-        }
+        // f() already applies the scale
+        self.f(wo, &*wi)
     }
     pub fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> Float {
         // if (!SameHemisphere(wo, wi)) return 0;
@@ -1496,6 +1609,130 @@ pub struct FourierBSDF {
 }
 
 impl FourierBSDF {
+    /// A BSDF given by the Fourier coefficients in _bsdf_table_, e.g.
+    /// one of a Lambertian reflector built in code:
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::geometry::{Point2f, Vector3f};
+    /// use pbrt::core::interpolation::integrate_catmull_rom;
+    /// use pbrt::core::material::TransportMode;
+    /// use pbrt::core::pbrt::{Float, Spectrum, INV_PI};
+    /// use pbrt::core::reflection::{FourierBSDF, FourierBSDFTable};
+    ///
+    /// // the single (order 0) coefficient of each (mu_i, mu_o) pair is
+    /// // f * |mu_i|, where mu_i is the cosine of -wi
+    /// let n_mu: usize = 41;
+    /// let mu: Vec<Float> = (0..n_mu)
+    ///     .map(|i| -1.0 + 2.0 * i as Float / (n_mu - 1) as Float)
+    ///     .collect();
+    /// let mut table = FourierBSDFTable::default();
+    /// table.eta = 1.0;
+    /// table.m_max = 1;
+    /// table.n_channels = 1;
+    /// table.n_mu = n_mu as i32;
+    /// for o in 0..n_mu {
+    ///     for i in 0..n_mu {
+    ///         let reflects: bool = mu[i] * mu[o] < 0.0;
+    ///         let a: Float = if reflects { 0.5 * INV_PI * mu[i].abs() } else { 0.0 };
+    ///         table.a_offset.push(table.a.len() as i32);
+    ///         table.m.push(1);
+    ///         table.a.push(a);
+    ///         table.a0.push(a);
+    ///     }
+    /// }
+    /// table.cdf = vec![0.0; n_mu * n_mu];
+    /// for o in 0..n_mu {
+    ///     integrate_catmull_rom(n_mu as i32, &mu, o * n_mu, &table.a0, &mut table.cdf);
+    /// }
+    /// table.recip = vec![Float::INFINITY];
+    /// table.mu = mu;
+    /// let bsdf = FourierBSDF::new(Arc::new(table), TransportMode::Radiance, None);
+    /// let wo: Vector3f = Vector3f { x: 0.3, y: 0.2, z: 0.9 }.normalize();
+    /// // reflects like a Lambertian surface with albedo 0.5 ...
+    /// let wi: Vector3f = Vector3f { x: -0.4, y: 0.1, z: 0.8 }.normalize();
+    /// assert!((bsdf.f(&wo, &wi).y() - 0.5 * INV_PI).abs() < 1e-4);
+    /// // ... and doesn't transmit
+    /// assert!(bsdf.f(&wo, &-wi).y().abs() < 1e-4);
+    /// // sample_f() evaluates the same coefficients as f() and pdf()
+    /// for &(x, y) in &[(0.1 as Float, 0.2 as Float), (0.5, 0.5), (0.8, 0.7)] {
+    ///     let mut wi: Vector3f = Vector3f::default();
+    ///     let mut pdf: Float = 0.0;
+    ///     let mut sampled_type: u8 = 0;
+    ///     let f: Spectrum = bsdf.sample_f(&wo, &mut wi, &Point2f { x, y }, &mut pdf, &mut sampled_type);
+    ///     assert!(wi.z > 0.0 && pdf > 0.0);
+    ///     assert!((f.y() - bsdf.f(&wo, &wi).y()).abs() < 1e-4);
+    ///     assert!((pdf - bsdf.pdf(&wo, &wi)).abs() < 1e-3 * pdf);
+    /// }
+    /// ```
+    ///
+    /// or one of a smooth conductor, whose azimuthal lobe (a Fejér
+    /// kernel of _m_ terms, peaking with 1 in the mirror direction)
+    /// gets scaled by the Fresnel reflectance of the metal:
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::geometry::Vector3f;
+    /// use pbrt::core::interpolation::integrate_catmull_rom;
+    /// use pbrt::core::material::TransportMode;
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::reflection::{fr_conductor, FourierBSDF, FourierBSDFTable};
+    ///
+    /// let (eta, k): (Spectrum, Spectrum) = (Spectrum::new(0.2), Spectrum::new(3.9));
+    /// let fresnel = |cos_theta: Float| -> Float {
+    ///     fr_conductor(cos_theta, Spectrum::new(1.0), eta, k).y()
+    /// };
+    /// let m: usize = 8;
+    /// let lobe: Vec<Float> = (0..m)
+    ///     .map(|k| if k == 0 { 1.0 } else { 2.0 * (m - k) as Float / m as Float } / m as Float)
+    ///     .collect();
+    /// let n_mu: usize = 101;
+    /// let mu: Vec<Float> = (0..n_mu)
+    ///     .map(|i| -1.0 + 2.0 * i as Float / (n_mu - 1) as Float)
+    ///     .collect();
+    /// let mut table = FourierBSDFTable::default();
+    /// // only scales transmission, a conductor has none
+    /// table.eta = 1.5;
+    /// table.m_max = m as i32;
+    /// table.n_channels = 1;
+    /// table.n_mu = n_mu as i32;
+    /// for o in 0..n_mu {
+    ///     for i in 0..n_mu {
+    ///         table.a_offset.push(table.a.len() as i32);
+    ///         if mu[i] * mu[o] < 0.0 {
+    ///             let scale: Float = mu[i].abs() * fresnel(mu[i].abs());
+    ///             table.m.push(m as i32);
+    ///             table.a.extend(lobe.iter().map(|l| l * scale));
+    ///             table.a0.push(lobe[0] * scale);
+    ///         } else {
+    ///             table.m.push(0);
+    ///             table.a0.push(0.0);
+    ///         }
+    ///     }
+    /// }
+    /// table.cdf = vec![0.0; n_mu * n_mu];
+    /// for o in 0..n_mu {
+    ///     integrate_catmull_rom(n_mu as i32, &mu, o * n_mu, &table.a0, &mut table.cdf);
+    /// }
+    /// table.recip = (0..m).map(|i| 1.0 / i as Float).collect();
+    /// table.mu = mu;
+    /// let table = Arc::new(table);
+    /// for &mode in &[TransportMode::Radiance, TransportMode::Importance] {
+    ///     let bsdf = FourierBSDF::new(table.clone(), mode, None);
+    ///     for &z in &[0.4 as Float, 0.7, 0.95] {
+    ///         let wo: Vector3f = Vector3f { x: 0.6, y: 0.8, z: 0.0 } * (1.0 - z * z).sqrt()
+    ///             + Vector3f { x: 0.0, y: 0.0, z };
+    ///         // reflects like the analytic Fresnel in the mirror direction ...
+    ///         let mirror: Vector3f = Vector3f { x: -wo.x, y: -wo.y, z: wo.z };
+    ///         let f: Float = bsdf.f(&wo, &mirror).y();
+    ///         assert!((f - fresnel(z)).abs() < 1e-3 * fresnel(z), "{} vs. {}", f, fresnel(z));
+    ///         // ... not at all back towards _wo_, where the lobe is zero ...
+    ///         assert!(bsdf.f(&wo, &wo).y().abs() < 1e-3);
+    ///         // ... and doesn't transmit
+    ///         assert!(bsdf.f(&wo, &-mirror).y().abs() < 1e-4);
+    ///     }
+    /// }
+    /// ```
     pub fn new(
         bsdf_table: Arc<FourierBSDFTable>,
         mode: TransportMode,
@@ -1528,77 +1765,11 @@ impl FourierBSDF {
         {
             return Spectrum::default();
         }
-        // allocate storage to accumulate _ak_ coefficients
-        let mut ak: SmallVec<[Float; 128]> =
-            SmallVec::with_capacity((self.bsdf_table.m_max * self.bsdf_table.n_channels) as usize);
-        for _i in 0..(self.bsdf_table.m_max * self.bsdf_table.n_channels) as usize {
-            ak.push(0.0 as Float); // initialize with 0
-        }
         // accumulate weighted sums of nearby $a_k$ coefficients
-        let mut m_max: i32 = 0;
-        for b in 0..4 {
-            for a in 0..4 {
-                // add contribution of _(a, b)_ to $a_k$ values
-                let weight: Float = weights_i[a] * weights_o[b];
-                if weight != 0.0 as Float {
-                    let mut m: i32 = 0;
-                    let a_idx: i32 =
-                        self.bsdf_table
-                            .get_ak(offset_i + a as i32, offset_o + b as i32, &mut m);
-                    m_max = std::cmp::max(m_max, m);
-                    for c in 0..self.bsdf_table.n_channels as usize {
-                        for k in 0..m as usize {
-                            ak[c * self.bsdf_table.m_max as usize + k] += weight
-                                * self.bsdf_table.a[(a_idx + c as i32 * m + k as i32) as usize];
-                        }
-                    }
-                }
-            }
-        }
+        let (ak, m_max) = self.accumulate_ak(offset_i, &weights_i, offset_o, &weights_o);
         // evaluate Fourier expansion for angle $\phi$
         let y: Float = (0.0 as Float).max(fourier(&ak, 0_usize, m_max, cos_phi as f64));
-        let mut scale: Float = 0.0 as Float;
-        if mu_i != 0.0 as Float {
-            scale = 1.0 as Float / mu_i.abs();
-        }
-        // update _scale_ to account for adjoint light transport
-        if self.mode == TransportMode::Radiance && (mu_i * mu_o) > 0.0 as Float {
-            let eta: Float;
-            if mu_i > 0.0 as Float {
-                eta = 1.0 as Float / self.bsdf_table.eta;
-            } else {
-                eta = self.bsdf_table.eta;
-            }
-            scale *= eta * eta;
-        }
-        if self.bsdf_table.n_channels == 1_i32 {
-            if let Some(sc) = self.sc_opt {
-                sc * Spectrum::new(y * scale)
-            } else {
-                Spectrum::new(y * scale)
-            }
-        } else {
-            // compute and return RGB colors for tabulated BSDF
-            let r: Float = fourier(
-                &ak,
-                (1_i32 * self.bsdf_table.m_max) as usize,
-                m_max,
-                cos_phi as f64,
-            );
-            let b: Float = fourier(
-                &ak,
-                (2_i32 * self.bsdf_table.m_max) as usize,
-                m_max,
-                cos_phi as f64,
-            );
-            let g: Float = 1.39829 as Float * y - 0.100913 as Float * b - 0.297375 as Float * r;
-            let rgb: [Float; 3] = [r * scale, g * scale, b * scale];
-            if let Some(sc) = self.sc_opt {
-                sc * Spectrum::from_rgb(&rgb).clamp(0.0 as Float, std::f32::INFINITY as Float)
-            } else {
-                Spectrum::from_rgb(&rgb).clamp(0.0 as Float, std::f32::INFINITY as Float)
-            }
-        }
+        self.eval_spectrum(&ak, m_max, y, mu_i, mu_o, cos_phi)
     }
     pub fn sample_f(
         &self,
@@ -1637,33 +1808,8 @@ impl FourierBSDF {
         {
             return Spectrum::default();
         }
-        // allocate storage to accumulate _ak_ coefficients
-        let mut ak: SmallVec<[Float; 128]> =
-            SmallVec::with_capacity((self.bsdf_table.m_max * self.bsdf_table.n_channels) as usize);
-        for _i in 0..(self.bsdf_table.m_max * self.bsdf_table.n_channels) as usize {
-            ak.push(0.0 as Float); // initialize with 0
-        }
         // accumulate weighted sums of nearby $a_k$ coefficients
-        let mut m_max: i32 = 0;
-        for b in 0..4 {
-            for a in 0..4 {
-                // add contribution of _(a, b)_ to $a_k$ values
-                let weight: Float = weights_i[a] * weights_o[b];
-                if weight != 0.0 as Float {
-                    let mut m: i32 = 0;
-                    let a_idx =
-                        self.bsdf_table
-                            .get_ak(offset_i + a as i32, offset_o + b as i32, &mut m);
-                    m_max = std::cmp::max(m_max, m);
-                    for c in 0..self.bsdf_table.n_channels as usize {
-                        for k in 0..m as usize {
-                            ak[c * self.bsdf_table.m_max as usize + k] += weight
-                                * self.bsdf_table.a[(a_idx + c as i32 * m + k as i32) as usize];
-                        }
-                    }
-                }
-            }
-        }
+        let (ak, m_max) = self.accumulate_ak(offset_i, &weights_i, offset_o, &weights_o);
         // importance sample the luminance Fourier expansion
         let mut phi: Float = 0.0;
         let mut pdf_phi: Float = 0.0;
@@ -1701,6 +1847,58 @@ impl FourierBSDF {
         // we normalize again here.
         *wi = wi.normalize();
         // evaluate remaining Fourier expansions for angle $\phi$
+        self.eval_spectrum(&ak, m_max, y, mu_i, mu_o, cos_phi)
+    }
+    /// Interpolates the Fourier coefficients $a_k$ (of all channels)
+    /// of the 4x4 nearby $(\mu_i, \mu_o)$ table entries and returns
+    /// them together with the maximum order encountered.
+    fn accumulate_ak(
+        &self,
+        offset_i: i32,
+        weights_i: &[Float; 4],
+        offset_o: i32,
+        weights_o: &[Float; 4],
+    ) -> (SmallVec<[Float; 128]>, i32) {
+        // allocate storage to accumulate _ak_ coefficients
+        let mut ak: SmallVec<[Float; 128]> =
+            SmallVec::with_capacity((self.bsdf_table.m_max * self.bsdf_table.n_channels) as usize);
+        for _i in 0..(self.bsdf_table.m_max * self.bsdf_table.n_channels) as usize {
+            ak.push(0.0 as Float); // initialize with 0
+        }
+        let mut m_max: i32 = 0;
+        for b in 0..4 {
+            for a in 0..4 {
+                // add contribution of _(a, b)_ to $a_k$ values
+                let weight: Float = weights_i[a] * weights_o[b];
+                if weight != 0.0 as Float {
+                    let mut m: i32 = 0;
+                    let a_idx: i32 =
+                        self.bsdf_table
+                            .get_ak(offset_i + a as i32, offset_o + b as i32, &mut m);
+                    m_max = std::cmp::max(m_max, m);
+                    for c in 0..self.bsdf_table.n_channels as usize {
+                        for k in 0..m as usize {
+                            ak[c * self.bsdf_table.m_max as usize + k] += weight
+                                * self.bsdf_table.a[(a_idx + c as i32 * m + k as i32) as usize];
+                        }
+                    }
+                }
+            }
+        }
+        (ak, m_max)
+    }
+    /// Turns the luminance _y_ (and, for RGB tables, the red and blue
+    /// expansions) into a spectrum, dividing by $|\mu_i|$ and
+    /// applying the $\eta^2$ factor for radiance transport.
+    fn eval_spectrum(
+        &self,
+        ak: &SmallVec<[Float; 128]>,
+        m_max: i32,
+        y: Float,
+        mu_i: Float,
+        mu_o: Float,
+        cos_phi: Float,
+    ) -> Spectrum {
         let mut scale: Float = 0.0 as Float;
         if mu_i != 0.0 as Float {
             scale = 1.0 as Float / mu_i.abs();
@@ -1724,13 +1922,13 @@ impl FourierBSDF {
         } else {
             // compute and return RGB colors for tabulated BSDF
             let r: Float = fourier(
-                &ak,
+                ak,
                 (1_i32 * self.bsdf_table.m_max) as usize,
                 m_max,
                 cos_phi as f64,
             );
             let b: Float = fourier(
-                &ak,
+                ak,
                 (2_i32 * self.bsdf_table.m_max) as usize,
                 m_max,
                 cos_phi as f64,
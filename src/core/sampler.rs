@@ -10,17 +10,52 @@ use crate::integrators::mlt::MLTSampler;
 use crate::samplers::halton::HaltonSampler;
 use crate::samplers::maxmin::MaxMinDistSampler;
 use crate::samplers::random::RandomSampler;
+use crate::samplers::recording::RecordingSampler;
 use crate::samplers::sobol::SobolSampler;
 use crate::samplers::stratified::StratifiedSampler;
 use crate::samplers::zerotwosequence::ZeroTwoSequenceSampler;
 
 // see sampler.h
 
+/// Combines the render seed (see **Sampler::set_seed()**) with a
+/// per-pass (or per-tile) seed. A render seed of zero leaves _seed_ unchanged.
+pub fn mix_seed(render_seed: u64, seed: u64) -> u64 {
+    seed ^ mix_bits(render_seed)
+}
+
+/// Combines a seed with the coordinates of pixel _p_, so the random
+/// samples of a pixel depend on the pixel itself, not on the tile (or
+/// the order) it gets rendered in.
+pub fn pixel_seed(seed: u64, p: &Point2i) -> u64 {
+    seed ^ mix_bits((u64::from(p.x as u32) << 32) | u64::from(p.y as u32))
+}
+
+/// Combines the seed of a pixel (see pixel_seed()) with the number of
+/// one of its samples. The random numbers a sample takes (beyond the
+/// precomputed dimensions of the pixel samplers) start from it, so
+/// they are the same whether the pixel gets rendered in one go or
+/// resumed in a later pass (see **Sampler::set_sample_number()**).
+pub fn sample_seed(seed: u64, sample_num: i64) -> u64 {
+    seed ^ mix_bits(sample_num as u64)
+}
+
+// 64-bit finalizer of MurmurHash3 (variant 13 by David Stafford),
+// maps zero to zero
+fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5_d329_728e_a185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81da_def4_bc2d_d44d);
+    v ^= v >> 33;
+    v
+}
+
 pub enum Sampler {
     Halton(HaltonSampler),
     MaxMinDist(MaxMinDistSampler),
     MLT(MLTSampler),
     Random(RandomSampler),
+    Recording(RecordingSampler),
     Sobol(SobolSampler),
     Stratified(StratifiedSampler),
     ZeroTwoSequence(ZeroTwoSequenceSampler),
@@ -33,17 +68,39 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.clone_with_seed(seed),
             Sampler::MLT(sampler) => sampler.clone_with_seed(seed),
             Sampler::Random(sampler) => sampler.clone_with_seed(seed),
+            Sampler::Recording(sampler) => sampler.clone_with_seed(seed),
             Sampler::Sobol(sampler) => sampler.clone_with_seed(seed),
             Sampler::Stratified(sampler) => sampler.clone_with_seed(seed),
             Sampler::ZeroTwoSequence(sampler) => sampler.clone_with_seed(seed),
         }
     }
+    /// Starts sampling pixel _p_. Samplers based on random numbers
+    /// reseed their generator from the pixel coordinates (and the seed
+    /// last passed to reseed()), so the noise in the image doesn't
+    /// depend on how the image is split into tiles:
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Point2f, Point2i};
+    /// use pbrt::core::sampler::Sampler;
+    /// use pbrt::samplers::random::RandomSampler;
+    ///
+    /// let mut sampler = Sampler::Random(RandomSampler::new(4));
+    /// let p: Point2i = Point2i { x: 3, y: 2 };
+    /// sampler.start_pixel(&p);
+    /// let first: Point2f = sampler.get_2d();
+    /// // the same samples after another pixel (of another tile)
+    /// sampler.start_pixel(&Point2i { x: 17, y: 9 });
+    /// assert_ne!(sampler.get_2d(), first);
+    /// sampler.start_pixel(&p);
+    /// assert_eq!(sampler.get_2d(), first);
+    /// ```
     pub fn start_pixel(&mut self, p: &Point2i) {
         match self {
             Sampler::Halton(sampler) => sampler.start_pixel(p),
             Sampler::MaxMinDist(sampler) => sampler.start_pixel(p),
             Sampler::MLT(sampler) => sampler.start_pixel(p),
             Sampler::Random(sampler) => sampler.start_pixel(p),
+            Sampler::Recording(sampler) => sampler.start_pixel(p),
             Sampler::Sobol(sampler) => sampler.start_pixel(p),
             Sampler::Stratified(sampler) => sampler.start_pixel(p),
             Sampler::ZeroTwoSequence(sampler) => sampler.start_pixel(p),
@@ -55,6 +112,7 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.get_1d(),
             Sampler::MLT(sampler) => sampler.get_1d(),
             Sampler::Random(sampler) => sampler.get_1d(),
+            Sampler::Recording(sampler) => sampler.get_1d(),
             Sampler::Sobol(sampler) => sampler.get_1d(),
             Sampler::Stratified(sampler) => sampler.get_1d(),
             Sampler::ZeroTwoSequence(sampler) => sampler.get_1d(),
@@ -66,6 +124,7 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.get_2d(),
             Sampler::MLT(sampler) => sampler.get_2d(),
             Sampler::Random(sampler) => sampler.get_2d(),
+            Sampler::Recording(sampler) => sampler.get_2d(),
             Sampler::Sobol(sampler) => sampler.get_2d(),
             Sampler::Stratified(sampler) => sampler.get_2d(),
             Sampler::ZeroTwoSequence(sampler) => sampler.get_2d(),
@@ -87,6 +146,7 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.request_2d_array(n),
             Sampler::MLT(sampler) => sampler.request_2d_array(n),
             Sampler::Random(sampler) => sampler.request_2d_array(n),
+            Sampler::Recording(sampler) => sampler.request_2d_array(n),
             Sampler::Sobol(sampler) => sampler.request_2d_array(n),
             Sampler::Stratified(sampler) => sampler.request_2d_array(n),
             Sampler::ZeroTwoSequence(sampler) => sampler.request_2d_array(n),
@@ -98,6 +158,7 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.round_count(count),
             Sampler::MLT(sampler) => sampler.round_count(count),
             Sampler::Random(sampler) => sampler.round_count(count),
+            Sampler::Recording(sampler) => sampler.round_count(count),
             Sampler::Sobol(sampler) => sampler.round_count(count),
             Sampler::Stratified(sampler) => sampler.round_count(count),
             Sampler::ZeroTwoSequence(sampler) => sampler.round_count(count),
@@ -109,6 +170,7 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.get_2d_array(n),
             Sampler::MLT(sampler) => sampler.get_2d_array(n),
             Sampler::Random(sampler) => sampler.get_2d_array(n),
+            Sampler::Recording(sampler) => sampler.get_2d_array(n),
             Sampler::Sobol(sampler) => sampler.get_2d_array(n),
             Sampler::Stratified(sampler) => sampler.get_2d_array(n),
             Sampler::ZeroTwoSequence(sampler) => sampler.get_2d_array(n),
@@ -120,6 +182,7 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.get_2d_arrays(n),
             Sampler::MLT(sampler) => sampler.get_2d_arrays(n),
             Sampler::Random(sampler) => sampler.get_2d_arrays(n),
+            Sampler::Recording(sampler) => sampler.get_2d_arrays(n),
             Sampler::Sobol(sampler) => sampler.get_2d_arrays(n),
             Sampler::Stratified(sampler) => sampler.get_2d_arrays(n),
             Sampler::ZeroTwoSequence(sampler) => sampler.get_2d_arrays(n),
@@ -131,6 +194,7 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.get_2d_array_vec(n),
             Sampler::MLT(sampler) => sampler.get_2d_array_vec(n),
             Sampler::Random(sampler) => sampler.get_2d_array_vec(n),
+            Sampler::Recording(sampler) => sampler.get_2d_array_vec(n),
             Sampler::Sobol(sampler) => sampler.get_2d_array_vec(n),
             Sampler::Stratified(sampler) => sampler.get_2d_array_vec(n),
             Sampler::ZeroTwoSequence(sampler) => sampler.get_2d_array_vec(n),
@@ -142,6 +206,7 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.start_next_sample(),
             Sampler::MLT(sampler) => sampler.start_next_sample(),
             Sampler::Random(sampler) => sampler.start_next_sample(),
+            Sampler::Recording(sampler) => sampler.start_next_sample(),
             Sampler::Sobol(sampler) => sampler.start_next_sample(),
             Sampler::Stratified(sampler) => sampler.start_next_sample(),
             Sampler::ZeroTwoSequence(sampler) => sampler.start_next_sample(),
@@ -153,17 +218,53 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.reseed(seed),
             Sampler::MLT(sampler) => sampler.reseed(seed),
             Sampler::Random(sampler) => sampler.reseed(seed),
+            Sampler::Recording(sampler) => sampler.reseed(seed),
             Sampler::Sobol(sampler) => sampler.reseed(seed),
             Sampler::Stratified(sampler) => sampler.reseed(seed),
             Sampler::ZeroTwoSequence(sampler) => sampler.reseed(seed),
         }
     }
+    /// Sets the render seed, which gets mixed into every seed passed to
+    /// reseed() or clone_with_seed(), so a different seed gives an
+    /// independent (but again reproducible) set of random samples. The
+    /// "halton" and "sobol" samplers are deterministic and ignore it,
+    /// so do the MLT samplers (seeded by their Markov chain index).
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::Point2i;
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::sampler::Sampler;
+    /// use pbrt::samplers::random::RandomSampler;
+    ///
+    /// fn first_sample(seed: u64) -> Float {
+    ///     let mut sampler = Sampler::Random(RandomSampler::new(4));
+    ///     sampler.set_seed(seed);
+    ///     sampler.start_pixel(&Point2i { x: 1, y: 1 });
+    ///     sampler.get_1d()
+    /// }
+    ///
+    /// assert_eq!(first_sample(7), first_sample(7));
+    /// assert_ne!(first_sample(8), first_sample(7));
+    /// ```
+    pub fn set_seed(&mut self, seed: u64) {
+        match self {
+            Sampler::Halton(_sampler) => {}
+            Sampler::MaxMinDist(sampler) => sampler.seed = seed,
+            Sampler::MLT(_sampler) => {}
+            Sampler::Random(sampler) => sampler.seed = seed,
+            Sampler::Recording(sampler) => sampler.sampler.set_seed(seed),
+            Sampler::Sobol(_sampler) => {}
+            Sampler::Stratified(sampler) => sampler.seed = seed,
+            Sampler::ZeroTwoSequence(sampler) => sampler.seed = seed,
+        }
+    }
     pub fn get_current_pixel(&self) -> Point2i {
         match self {
             Sampler::Halton(sampler) => sampler.get_current_pixel(),
             Sampler::MaxMinDist(sampler) => sampler.get_current_pixel(),
             Sampler::MLT(sampler) => sampler.get_current_pixel(),
             Sampler::Random(sampler) => sampler.get_current_pixel(),
+            Sampler::Recording(sampler) => sampler.get_current_pixel(),
             Sampler::Sobol(sampler) => sampler.get_current_pixel(),
             Sampler::Stratified(sampler) => sampler.get_current_pixel(),
             Sampler::ZeroTwoSequence(sampler) => sampler.get_current_pixel(),
@@ -175,6 +276,7 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.get_current_sample_number(),
             Sampler::MLT(sampler) => sampler.get_current_sample_number(),
             Sampler::Random(sampler) => sampler.get_current_sample_number(),
+            Sampler::Recording(sampler) => sampler.get_current_sample_number(),
             Sampler::Sobol(sampler) => sampler.get_current_sample_number(),
             Sampler::Stratified(sampler) => sampler.get_current_sample_number(),
             Sampler::ZeroTwoSequence(sampler) => sampler.get_current_sample_number(),
@@ -186,16 +288,21 @@ impl Sampler {
             Sampler::MaxMinDist(sampler) => sampler.get_samples_per_pixel(),
             Sampler::MLT(sampler) => sampler.get_samples_per_pixel(),
             Sampler::Random(sampler) => sampler.get_samples_per_pixel(),
+            Sampler::Recording(sampler) => sampler.get_samples_per_pixel(),
             Sampler::Sobol(sampler) => sampler.get_samples_per_pixel(),
             Sampler::Stratified(sampler) => sampler.get_samples_per_pixel(),
             Sampler::ZeroTwoSequence(sampler) => sampler.get_samples_per_pixel(),
         }
     }
-    // GlobalSampler
     pub fn set_sample_number(&mut self, sample_num: i64) -> bool {
         match self {
             Sampler::Halton(sampler) => sampler.set_sample_number(sample_num),
+            Sampler::MaxMinDist(sampler) => sampler.set_sample_number(sample_num),
+            Sampler::Random(sampler) => sampler.set_sample_number(sample_num),
+            Sampler::Recording(sampler) => sampler.set_sample_number(sample_num),
             Sampler::Sobol(sampler) => sampler.set_sample_number(sample_num),
+            Sampler::Stratified(sampler) => sampler.set_sample_number(sample_num),
+            Sampler::ZeroTwoSequence(sampler) => sampler.set_sample_number(sample_num),
             _ => false,
         }
     }
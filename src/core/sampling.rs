@@ -146,6 +146,52 @@ impl Distribution1D {
     }
 }
 
+/// Piecewise-constant 2D distribution over [0,1)^2, e.g. for
+/// importance sampling an environment map. Samples are drawn by first
+/// picking a row _v_ from the marginal distribution and then a
+/// position within that row from its conditional distribution.
+///
+/// A function with one bright texel (and a dim background) produces
+/// samples concentrated in that texel, with a pdf (with respect to
+/// area in [0,1)^2) proportional to the function:
+///
+/// ```rust
+/// use pbrt::core::geometry::Point2f;
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::rng::Rng;
+/// use pbrt::core::sampling::Distribution2D;
+///
+/// let (nu, nv): (i32, i32) = (8, 4);
+/// let mut func: Vec<Float> = vec![1.0; (nu * nv) as usize];
+/// // bright texel at (5, 2)
+/// func[(2 * nu + 5) as usize] = 100.0;
+/// let distrib: Distribution2D = Distribution2D::new(func, nu, nv);
+/// let average: Float = (100.0 + 31.0) / 32.0;
+/// let mut rng: Rng = Rng::new();
+/// let n: usize = 10000;
+/// let mut n_bright: usize = 0;
+/// for _ in 0..n {
+///     let u: Point2f = Point2f {
+///         x: rng.uniform_float(),
+///         y: rng.uniform_float(),
+///     };
+///     let mut pdf: Float = 0.0;
+///     let p: Point2f = distrib.sample_continuous(&u, &mut pdf);
+///     assert!(p.x >= 0.0 && p.x < 1.0 && p.y >= 0.0 && p.y < 1.0);
+///     let bright: bool = (p.x * 8.0) as i32 == 5 && (p.y * 4.0) as i32 == 2;
+///     let expected: Float = if bright { 100.0 } else { 1.0 } / average;
+///     assert!((pdf - expected).abs() < 1e-3 * expected);
+///     assert!((distrib.pdf(&p) - pdf).abs() < 1e-3 * pdf);
+///     if bright {
+///         n_bright += 1;
+///     }
+/// }
+/// let fraction: Float = n_bright as Float / n as Float;
+/// assert!((fraction - 100.0 / 131.0).abs() < 0.02);
+/// // a function that is zero everywhere has a zero pdf
+/// let black: Distribution2D = Distribution2D::new(vec![0.0; 4], 2, 2);
+/// assert_eq!(black.pdf(&Point2f { x: 0.5, y: 0.5 }), 0.0);
+/// ```
 #[derive(Debug, Default, Clone)]
 pub struct Distribution2D {
     pub p_conditional_v: Vec<Arc<Distribution1D>>,
@@ -192,6 +238,10 @@ impl Distribution2D {
             0_usize,
             self.p_marginal.count() - 1_usize,
         );
+        if self.p_marginal.func_int == 0.0 as Float {
+            // the function is zero everywhere
+            return 0.0 as Float;
+        }
         self.p_conditional_v[iv].func[iu] / self.p_marginal.func_int
     }
 }
@@ -225,6 +275,28 @@ pub fn cosine_hemisphere_pdf(cos_theta: Float) -> Float {
 }
 
 /// Reducing the variance according to Veach's heuristic.
+///
+/// The weights of two strategies evaluated for the same sample
+/// always sum to one, which keeps the combined estimator unbiased:
+///
+/// ```rust
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::sampling::power_heuristic;
+///
+/// fn main() {
+///     for &(f_pdf, g_pdf) in &[(0.5 as Float, 2.0 as Float), (3.0, 0.01), (1.0, 1.0)] {
+///         let w_f: Float = power_heuristic(1, f_pdf, 1, g_pdf);
+///         let w_g: Float = power_heuristic(1, g_pdf, 1, f_pdf);
+///         assert!((w_f + w_g - 1.0).abs() < 1e-6);
+///         // also with more than one sample per strategy
+///         let w_f: Float = power_heuristic(4, f_pdf, 1, g_pdf);
+///         let w_g: Float = power_heuristic(1, g_pdf, 4, f_pdf);
+///         assert!((w_f + w_g - 1.0).abs() < 1e-6);
+///     }
+///     // equal densities share the sample equally
+///     assert_eq!(power_heuristic(1, 0.25, 1, 0.25), 0.5);
+/// }
+/// ```
 pub fn power_heuristic(nf: u8, f_pdf: Float, ng: u8, g_pdf: Float) -> Float {
     let f: Float = nf as Float * f_pdf;
     let g: Float = ng as Float * g_pdf;
@@ -247,6 +319,28 @@ pub fn stratified_sample_1d(samp: &mut [Float], n_samples: i32, rng: &mut Rng, j
     }
 }
 
+/// Fills _samp_ with one sample per cell of an _nx_ x _ny_ grid over
+/// [0,1)^2, in scanline order. Without _jitter_ the samples are placed
+/// at the cell centers.
+///
+/// ```rust
+/// use pbrt::core::geometry::Point2f;
+/// use pbrt::core::rng::Rng;
+/// use pbrt::core::sampling::stratified_sample_2d;
+///
+/// let (nx, ny): (i32, i32) = (5, 3);
+/// let mut samples: Vec<Point2f> = vec![Point2f::default(); (nx * ny) as usize];
+/// let mut rng: Rng = Rng::new();
+/// stratified_sample_2d(&mut samples, nx, ny, &mut rng, true);
+/// // each stratum contains exactly one sample
+/// let mut count: Vec<u32> = vec![0; (nx * ny) as usize];
+/// for p in samples.iter() {
+///     assert!(p.x >= 0.0 && p.x < 1.0 && p.y >= 0.0 && p.y < 1.0);
+///     let (x, y): (i32, i32) = ((p.x * nx as f32) as i32, (p.y * ny as f32) as i32);
+///     count[(y * nx + x) as usize] += 1;
+/// }
+/// assert!(count.iter().all(|c| *c == 1));
+/// ```
 pub fn stratified_sample_2d(samp: &mut [Point2f], nx: i32, ny: i32, rng: &mut Rng, jitter: bool) {
     let dx: Float = 1.0 as Float / nx as Float;
     let dy: Float = 1.0 as Float / ny as Float;
@@ -272,37 +366,52 @@ pub fn stratified_sample_2d(samp: &mut [Point2f], nx: i32, ny: i32, rng: &mut Rn
     }
 }
 
+/// Fills the first _n_samples_ entries of _samples_ with a Latin
+/// hypercube: when [0,1) is divided into _n_samples_ intervals along
+/// each dimension, every interval contains exactly one sample.
+///
+/// ```rust
+/// use pbrt::core::geometry::Point2f;
+/// use pbrt::core::rng::Rng;
+/// use pbrt::core::sampling::latin_hypercube;
+///
+/// let n: u32 = 16;
+/// let mut samples: Vec<Point2f> = vec![Point2f::default(); n as usize];
+/// let mut rng: Rng = Rng::new();
+/// latin_hypercube(&mut samples, n, &mut rng);
+/// let mut rows: Vec<u32> = vec![0; n as usize];
+/// let mut columns: Vec<u32> = vec![0; n as usize];
+/// for p in samples.iter() {
+///     assert!(p.x >= 0.0 && p.x < 1.0 && p.y >= 0.0 && p.y < 1.0);
+///     columns[(p.x * n as f32) as usize] += 1;
+///     rows[(p.y * n as f32) as usize] += 1;
+/// }
+/// // one sample per row and per column
+/// assert!(rows.iter().all(|c| *c == 1));
+/// assert!(columns.iter().all(|c| *c == 1));
+/// // the dimensions are permuted independently, so the samples don't
+/// // just sit on the diagonal
+/// assert!(samples
+///     .iter()
+///     .any(|p| (p.x * n as f32) as u32 != (p.y * n as f32) as u32));
+/// ```
 pub fn latin_hypercube(samples: &mut [Point2f], n_samples: u32, rng: &mut Rng) {
-    let n_dim: usize = 2;
+    let n_dim: u8 = 2;
     // generate LHS samples along diagonal
     let inv_n_samples: Float = 1.0 as Float / n_samples as Float;
     for i in 0..n_samples {
         for j in 0..n_dim {
             let sj: Float = (i as Float + (rng.uniform_float())) * inv_n_samples;
-            if j == 0 {
-                samples[i as usize].x = sj.min(FLOAT_ONE_MINUS_EPSILON);
-            } else {
-                samples[i as usize].y = sj.min(FLOAT_ONE_MINUS_EPSILON);
-            }
+            samples[i as usize][j] = sj.min(FLOAT_ONE_MINUS_EPSILON);
         }
     }
     // permute LHS samples in each dimension
     for i in 0..n_dim {
         for j in 0..n_samples {
-            let other: u32 = j as u32 + rng.uniform_uint32_bounded((n_samples - j) as u32);
-            if i == 0 {
-                let tmp = samples[j as usize].x;
-                samples[j as usize].x = samples[other as usize].x;
-                samples[other as usize].x = tmp;
-            } else {
-                let tmp = samples[j as usize].y;
-                samples[j as usize].y = samples[other as usize].y;
-                samples[other as usize].y = tmp;
-            }
-            // samples.swap(
-            //     (n_dim * j + i) as usize,
-            //     (n_dim * other + i) as usize,
-            // );
+            let other: u32 = j + rng.uniform_uint32_bounded(n_samples - j);
+            let tmp: Float = samples[j as usize][i];
+            samples[j as usize][i] = samples[other as usize][i];
+            samples[other as usize][i] = tmp;
         }
     }
 }
@@ -326,6 +435,32 @@ pub fn uniform_hemisphere_pdf() -> Float {
 }
 
 /// Uniformly sample rays in a full sphere. Choose a direction.
+///
+/// Equal areas of the unit square map to equal solid angles, e.g. a
+/// quarter of the samples end up in the cap above z = 0.5:
+///
+/// ```rust
+/// use pbrt::core::geometry::{Point2f, Vector3f};
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::sampling::uniform_sample_sphere;
+///
+/// let n: usize = 64;
+/// let mut in_cap: usize = 0;
+/// for y in 0..n {
+///     for x in 0..n {
+///         let u = Point2f {
+///             x: (x as Float + 0.5) / n as Float,
+///             y: (y as Float + 0.5) / n as Float,
+///         };
+///         let w: Vector3f = uniform_sample_sphere(&u);
+///         assert!((w.length() - 1.0).abs() < 1e-5);
+///         if w.z > 0.5 {
+///             in_cap += 1;
+///         }
+///     }
+/// }
+/// assert_eq!(in_cap, n * n / 4);
+/// ```
 pub fn uniform_sample_sphere(u: &Point2f) -> Vector3f {
     let z: Float = 1.0 as Float - 2.0 as Float * u[0];
     let r: Float = (0.0 as Float).max(1.0 as Float - z * z).sqrt();
@@ -343,6 +478,37 @@ pub fn uniform_sphere_pdf() -> Float {
 }
 
 /// Uniformly distribute samples over a unit disk.
+///
+/// Unlike the polar mapping (r = u0), which clumps samples near the
+/// center, the concentric mapping preserves relative areas: the inner
+/// disk of radius 0.5 receives a quarter of the samples.
+///
+/// ```rust
+/// use pbrt::core::geometry::Point2f;
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::sampling::concentric_sample_disk;
+///
+/// let n: usize = 64;
+/// let mut inner: usize = 0;
+/// for y in 0..n {
+///     for x in 0..n {
+///         let u = Point2f {
+///             x: (x as Float + 0.5) / n as Float,
+///             y: (y as Float + 0.5) / n as Float,
+///         };
+///         let d: Point2f = concentric_sample_disk(&u);
+///         let r: Float = (d.x * d.x + d.y * d.y).sqrt();
+///         assert!(r <= 1.0 + 1e-6);
+///         if r < 0.5 {
+///             inner += 1;
+///         }
+///     }
+/// }
+/// assert_eq!(inner, n * n / 4);
+/// // the center of the square maps to the center of the disk
+/// let c: Point2f = concentric_sample_disk(&Point2f { x: 0.5, y: 0.5 });
+/// assert_eq!((c.x, c.y), (0.0, 0.0));
+/// ```
 pub fn concentric_sample_disk(u: &Point2f) -> Point2f {
     // map uniform random numbers to $[-1,1]^2$
     let u_offset: Point2f = *u * 2.0 as Float - Vector2f { x: 1.0, y: 1.0 };
@@ -373,6 +539,39 @@ pub fn uniform_cone_pdf(cos_theta_max: Float) -> Float {
 }
 
 /// Samples in a cone of directions about the (0, 0, 1) axis.
+///
+/// All directions stay within the cone and half of them fall into the
+/// inner part covering half of its solid angle:
+///
+/// ```rust
+/// use pbrt::core::geometry::{Point2f, Vector3f};
+/// use std::f32::consts::PI;
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::sampling::{uniform_cone_pdf, uniform_sample_cone};
+///
+/// let cos_theta_max: Float = 0.8;
+/// let cos_theta_half: Float = 0.5 * (1.0 + cos_theta_max);
+/// let n: usize = 64;
+/// let mut inner: usize = 0;
+/// for y in 0..n {
+///     for x in 0..n {
+///         let u = Point2f {
+///             x: (x as Float + 0.5) / n as Float,
+///             y: (y as Float + 0.5) / n as Float,
+///         };
+///         let w: Vector3f = uniform_sample_cone(&u, cos_theta_max);
+///         assert!((w.length() - 1.0).abs() < 1e-5);
+///         assert!(w.z >= cos_theta_max - 1e-6);
+///         if w.z > cos_theta_half {
+///             inner += 1;
+///         }
+///     }
+/// }
+/// assert_eq!(inner, n * n / 2);
+/// // the pdf integrates to one over the cone's solid angle
+/// let solid_angle: Float = 2.0 * PI * (1.0 - cos_theta_max);
+/// assert!((uniform_cone_pdf(cos_theta_max) * solid_angle - 1.0).abs() < 1e-5);
+/// ```
 pub fn uniform_sample_cone(u: &Point2f, cos_theta_max: Float) -> Vector3f {
     let cos_theta: Float = (1.0 as Float - u[0]) + u[0] * cos_theta_max;
     let sin_theta: Float = (1.0 as Float - cos_theta * cos_theta).sqrt();
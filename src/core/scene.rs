@@ -4,10 +4,11 @@
 //!
 
 // std
+use std::cell::Cell;
 use std::sync::Arc;
 // pbrt
-use crate::core::geometry::{Bounds3f, Ray, Vector3f};
-use crate::core::interaction::{Interaction, SurfaceInteraction};
+use crate::core::geometry::{Bounds3f, Point3f, Ray, Vector3f};
+use crate::core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
 use crate::core::light::{Light, LightFlags};
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::primitive::Primitive;
@@ -15,25 +16,104 @@ use crate::core::sampler::Sampler;
 
 // see scene.h
 
+crate::stat_counter!("Intersections/Regular ray intersection tests", N_INTERSECTION_TESTS);
+crate::stat_counter!("Intersections/Shadow ray intersection tests", N_SHADOW_TESTS);
+crate::stat_counter!(
+    "Intersections/Paths aborted (max. intersections)",
+    N_ABORTED_PATHS
+);
+
+thread_local! {
+    // intersect() calls of the current path (None before the first
+    // Scene::start_path() of this thread) and whether it exceeded the
+    // scene's _max_intersections_
+    static PATH_INTERSECTIONS: Cell<Option<u64>> = Cell::new(None);
+    static PATH_ABORTED: Cell<bool> = Cell::new(false);
+}
+
 #[derive(Clone)]
 pub struct Scene {
     pub lights: Vec<Arc<Light>>,
     pub infinite_lights: Vec<Arc<Light>>,
     pub aggregate: Arc<Primitive>,
     pub world_bound: Bounds3f,
+    pub world_center: Point3f,
+    pub world_radius: Float,
+    /// Maximum number of intersect() calls per path, 0 means no limit
+    /// (see set_max_intersections())
+    pub max_intersections: u64,
+    /// Bend shading normals into the visible hemisphere (see
+    /// set_clamp_shading_normals())
+    pub clamp_shading_normals: bool,
+    /// Reject light-leaking directions in the BSDFs (see
+    /// set_check_geometric_normal())
+    pub check_geometric_normal: bool,
 }
 
 impl Scene {
+    /// Creates the scene from the aggregate (usually an acceleration
+    /// structure) and all lights. The world bound is computed once,
+    /// every light gets a chance to preprocess the scene (e.g. distant
+    /// and infinite area lights need the bounding sphere of the world),
+    /// and lights flagged as infinite are additionally collected in
+    /// _infinite_lights_.
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::geometry::Vector3f;
+    /// use pbrt::core::light::Light;
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::primitive::{GeometricPrimitive, Primitive};
+    /// use pbrt::core::scene::Scene;
+    /// use pbrt::core::shape::Shape;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::lights::distant::DistantLight;
+    /// use pbrt::shapes::sphere::Sphere;
+    ///
+    /// let sphere = Arc::new(Shape::Sphr(Sphere::new(
+    ///     Arc::new(Transform::default()),
+    ///     Arc::new(Transform::default()),
+    ///     false,
+    ///     2.0,
+    ///     -2.0,
+    ///     2.0,
+    ///     360.0,
+    /// )));
+    /// let aggregate = Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+    ///     sphere, None, None, None,
+    /// )));
+    /// let light = Arc::new(Light::Distant(DistantLight::new(
+    ///     &Transform::default(),
+    ///     &Spectrum::new(1.0),
+    ///     &Vector3f { x: 0.0, y: 0.0, z: 1.0 },
+    /// )));
+    /// let scene = Scene::new(aggregate, vec![light]);
+    /// assert_eq!(scene.lights.len(), 1);
+    /// assert!(scene.infinite_lights.is_empty());
+    /// if let Light::Distant(distant) = &*scene.lights[0] {
+    ///     // bounding sphere of the sphere's bounding box
+    ///     let world_radius: Float = *distant.world_radius.read().unwrap();
+    ///     assert!((world_radius - (12.0 as Float).sqrt()).abs() < 1e-4);
+    /// } else {
+    ///     unreachable!();
+    /// }
+    /// ```
     pub fn new(
         aggregate: Arc<Primitive>,
         lights: Vec<Arc<Light>>,
     ) -> Self {
         let world_bound: Bounds3f = aggregate.world_bound();
+        let (world_center, world_radius) = world_bound.bounding_sphere();
         let scene: Scene = Scene {
             lights: Vec::new(),
             infinite_lights: Vec::new(),
             aggregate: aggregate.clone(),
             world_bound,
+            world_center,
+            world_radius,
+            max_intersections: 0,
+            clamp_shading_normals: false,
+            check_geometric_normal: true,
         };
         let mut changed_lights = Vec::new();
         let mut infinite_lights = Vec::new();
@@ -50,13 +130,289 @@ impl Scene {
             infinite_lights,
             aggregate,
             world_bound,
+            world_center,
+            world_radius,
+            max_intersections: 0,
+            clamp_shading_normals: false,
+            check_geometric_normal: true,
         }
     }
     pub fn world_bound(&self) -> Bounds3f {
         self.world_bound
     }
+    /// Returns the bounding sphere (center and radius) of the world
+    /// bound, computed once when the scene was created. Infinite lights
+    /// use it to place the end points of shadow rays safely outside of
+    /// the scene.
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::geometry::{pnt3_inside_bnd3, Point2f, Point3f, Vector3f};
+    /// use pbrt::core::interaction::InteractionCommon;
+    /// use pbrt::core::light::{Light, VisibilityTester};
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::primitive::{GeometricPrimitive, Primitive};
+    /// use pbrt::core::scene::Scene;
+    /// use pbrt::core::shape::Shape;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::lights::infinite::ConstantInfiniteLight;
+    /// use pbrt::shapes::sphere::Sphere;
+    ///
+    /// let sphere = Arc::new(Shape::Sphr(Sphere::new(
+    ///     Arc::new(Transform::default()),
+    ///     Arc::new(Transform::default()),
+    ///     false,
+    ///     2.0,
+    ///     -2.0,
+    ///     2.0,
+    ///     360.0,
+    /// )));
+    /// let aggregate = Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+    ///     sphere, None, None, None,
+    /// )));
+    /// let light = Arc::new(Light::ConstantInfinite(ConstantInfiniteLight::new(
+    ///     &Spectrum::new(1.0),
+    ///     1,
+    /// )));
+    /// let scene = Scene::new(aggregate, vec![light]);
+    /// let (world_center, world_radius) = scene.bounding_sphere();
+    /// assert_eq!((world_center - Point3f::default()).length(), 0.0);
+    /// assert!((world_radius - (12.0 as Float).sqrt()).abs() < 1e-4);
+    /// assert_eq!(scene.infinite_lights.len(), 1);
+    /// // shadow rays towards the infinite light end outside the scene
+    /// let iref = InteractionCommon {
+    ///     p: Point3f { x: 0.0, y: 0.0, z: 2.0 },
+    ///     ..Default::default()
+    /// };
+    /// for u in [0.1 as Float, 0.35, 0.6, 0.85].iter() {
+    ///     let mut wi: Vector3f = Vector3f::default();
+    ///     let mut pdf: Float = 0.0;
+    ///     let mut vis: VisibilityTester = VisibilityTester::default();
+    ///     scene.infinite_lights[0].sample_li(
+    ///         &iref,
+    ///         &Point2f { x: *u, y: 1.0 - *u },
+    ///         &mut wi,
+    ///         &mut pdf,
+    ///         &mut vis,
+    ///     );
+    ///     assert!(pdf > 0.0);
+    ///     assert!(!pnt3_inside_bnd3(&vis.p1.p, &scene.world_bound()));
+    /// }
+    /// ```
+    pub fn bounding_sphere(&self) -> (Point3f, Float) {
+        (self.world_center, self.world_radius)
+    }
+    /// Limits the number of intersect() calls for each path (see
+    /// start_path()) to _n_, to find geometry which makes a render
+    /// hang, e.g. rays trapped between mirrors or escaping a surface
+    /// (by self-intersection) over and over again. Further calls don't
+    /// find anything, so the path ends, and path_aborted() reports it.
+    /// 0 removes the limit.
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::geometry::{Point3f, Ray, Vector3f};
+    /// use pbrt::core::primitive::{GeometricPrimitive, Primitive};
+    /// use pbrt::core::scene::Scene;
+    /// use pbrt::core::shape::Shape;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::shapes::sphere::Sphere;
+    ///
+    /// let sphere = Arc::new(Shape::Sphr(Sphere::new(
+    ///     Arc::new(Transform::default()),
+    ///     Arc::new(Transform::default()),
+    ///     false,
+    ///     1.0,
+    ///     -1.0,
+    ///     1.0,
+    ///     360.0,
+    /// )));
+    /// let aggregate = Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+    ///     sphere, None, None, None,
+    /// )));
+    /// let mut scene = Scene::new(aggregate, vec![]);
+    /// scene.set_max_intersections(2);
+    /// let mut ray = || Ray {
+    ///     o: Point3f { x: 0.0, y: 0.0, z: -5.0 },
+    ///     d: Vector3f { x: 0.0, y: 0.0, z: 1.0 },
+    ///     t_max: std::f32::INFINITY,
+    ///     time: 0.0,
+    ///     differential: None,
+    ///     medium: None,
+    /// };
+    /// scene.start_path();
+    /// assert!(scene.intersect(&mut ray()).is_some());
+    /// assert!(scene.intersect(&mut ray()).is_some());
+    /// assert!(!scene.path_aborted());
+    /// // the third call exceeds the limit
+    /// assert!(scene.intersect(&mut ray()).is_none());
+    /// assert!(scene.path_aborted());
+    /// // until the next path starts
+    /// scene.start_path();
+    /// assert!(scene.intersect(&mut ray()).is_some());
+    /// assert!(!scene.path_aborted());
+    /// ```
+    pub fn set_max_intersections(&mut self, n: u64) {
+        self.max_intersections = n;
+    }
+    /// Enables (or disables) shading normal clamping for the surface
+    /// interactions returned by intersect(), see
+    /// **SurfaceInteraction::correct_shading_normal()**. Interpolated
+    /// normals of low-poly meshes can face away from directions the
+    /// (flat) surface is seen from, e.g. at the silhouette of a sphere,
+    /// which renders black without clamping:
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::geometry::{vec3_dot_nrm, Normal3f, Point3f, Ray, Vector3f};
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::primitive::{GeometricPrimitive, Primitive};
+    /// use pbrt::core::scene::Scene;
+    /// use pbrt::core::shape::Shape;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::shapes::triangle::{Triangle, TriangleMesh};
+    ///
+    /// // a triangle in the xy plane with vertex normals tilted towards -x
+    /// let p = vec![
+    ///     Point3f { x: -1.0, y: -1.0, z: 0.0 },
+    ///     Point3f { x: 1.0, y: -1.0, z: 0.0 },
+    ///     Point3f { x: 0.0, y: 1.0, z: 0.0 },
+    /// ];
+    /// let n = vec![Normal3f { x: -1.0, y: 0.0, z: 0.2 }.normalize(); 3];
+    /// let mesh = Arc::new(TriangleMesh::new(
+    ///     Transform::default(), Transform::default(), false,
+    ///     1, vec![0, 1, 2], 3, p, vec![], n, vec![], None, None,
+    /// ));
+    /// let triangle = Arc::new(Shape::Trngl(Triangle::new(mesh, 0)));
+    /// let aggregate = Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+    ///     triangle, None, None, None,
+    /// )));
+    /// // seen at a grazing angle from +x
+    /// let o = Point3f { x: 3.0, y: 0.0, z: 0.5 };
+    /// let ray = || Ray {
+    ///     o,
+    ///     d: Point3f::default() - o,
+    ///     t_max: Float::INFINITY,
+    ///     time: 0.0,
+    ///     medium: None,
+    ///     differential: None,
+    /// };
+    /// let wo: Vector3f = -ray().d;
+    /// let mut scene = Scene::new(aggregate, vec![]);
+    /// let si = scene.intersect(&mut ray()).unwrap();
+    /// assert!(vec3_dot_nrm(&wo, &si.n) > 0.0);
+    /// assert!(vec3_dot_nrm(&wo, &si.shading.n) < 0.0);
+    /// scene.set_clamp_shading_normals(true);
+    /// let si = scene.intersect(&mut ray()).unwrap();
+    /// assert!(vec3_dot_nrm(&wo, &si.n) > 0.0);
+    /// assert!(vec3_dot_nrm(&wo, &si.shading.n) > 0.0);
+    /// assert!((si.shading.n.length() - 1.0).abs() < 1e-5);
+    /// // other scenes are not affected
+    /// let aggregate = scene.aggregate.clone();
+    /// let other = Scene::new(aggregate, vec![]);
+    /// let si = other.intersect(&mut ray()).unwrap();
+    /// assert!(vec3_dot_nrm(&wo, &si.shading.n) < 0.0);
+    /// ```
+    pub fn set_clamp_shading_normals(&mut self, clamp: bool) {
+        self.clamp_shading_normals = clamp;
+    }
+    /// Enables (default) or disables rejecting incident directions which
+    /// are on different sides of the surface with respect to the
+    /// geometric and the shading normal, for the BSDFs at the surface
+    /// interactions returned by intersect() (see **Bsdf::f()** and
+    /// **Bsdf::sample_f()**). Those directions let light leak through
+    /// thin geometry, but rejecting them slightly darkens the image.
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::geometry::{Normal3f, Point3f, Ray, Vector3f};
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::primitive::{GeometricPrimitive, Primitive};
+    /// use pbrt::core::reflection::{Bsdf, Bxdf, BxdfType, LambertianReflection};
+    /// use pbrt::core::scene::Scene;
+    /// use pbrt::core::shape::Shape;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::shapes::triangle::{Triangle, TriangleMesh};
+    ///
+    /// // a triangle in the xy plane with vertex normals tilted towards -x
+    /// let p = vec![
+    ///     Point3f { x: -1.0, y: -1.0, z: 0.0 },
+    ///     Point3f { x: 1.0, y: -1.0, z: 0.0 },
+    ///     Point3f { x: 0.0, y: 1.0, z: 0.0 },
+    /// ];
+    /// let n = vec![Normal3f { x: -1.0, y: 0.0, z: 0.2 }.normalize(); 3];
+    /// let mesh = Arc::new(TriangleMesh::new(
+    ///     Transform::default(), Transform::default(), false,
+    ///     1, vec![0, 1, 2], 3, p, vec![], n, vec![], None, None,
+    /// ));
+    /// let triangle = Arc::new(Shape::Trngl(Triangle::new(mesh, 0)));
+    /// let aggregate = Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+    ///     triangle, None, None, None,
+    /// )));
+    /// // seen from above, lit from a direction above the geometric
+    /// // surface but below the shading normal's hemisphere
+    /// let wo = Vector3f { x: 0.0, y: 0.0, z: 1.0 };
+    /// let wi = Vector3f { x: 0.5, y: 0.0, z: 0.1 }.normalize();
+    /// let f = |scene: &Scene| -> Spectrum {
+    ///     let mut ray = Ray {
+    ///         o: Point3f { x: 0.0, y: 0.0, z: 2.0 },
+    ///         d: -wo,
+    ///         t_max: Float::INFINITY,
+    ///         time: 0.0,
+    ///         medium: None,
+    ///         differential: None,
+    ///     };
+    ///     let si = scene.intersect(&mut ray).unwrap();
+    ///     let mut bsdf = Bsdf::new(&si, 1.0);
+    ///     bsdf.bxdfs[0] = Bxdf::LambertianRefl(LambertianReflection::new(Spectrum::new(0.5), None));
+    ///     bsdf.f(&wo, &wi, BxdfType::BsdfAll as u8)
+    /// };
+    /// let checking = Scene::new(aggregate.clone(), vec![]);
+    /// let mut leaking = Scene::new(aggregate, vec![]);
+    /// leaking.set_check_geometric_normal(false);
+    /// assert!(f(&checking).is_black());
+    /// assert!(!f(&leaking).is_black());
+    /// ```
+    pub fn set_check_geometric_normal(&mut self, check: bool) {
+        self.check_geometric_normal = check;
+    }
+    /// Starts counting the intersect() calls of a new path (for the
+    /// current thread). Threads which never start a path aren't
+    /// limited.
+    pub fn start_path(&self) {
+        PATH_INTERSECTIONS.with(|n| n.set(Some(0)));
+        PATH_ABORTED.with(|aborted| aborted.set(false));
+    }
+    /// Did the current path (of this thread) exceed the maximum number
+    /// of intersect() calls?
+    pub fn path_aborted(&self) -> bool {
+        PATH_ABORTED.with(|aborted| aborted.get())
+    }
+    /// Counts an intersect() call, returns false if the path exceeded
+    /// _max_intersections_.
+    fn count_intersection(&self) -> bool {
+        let n: Option<u64> = PATH_INTERSECTIONS.with(|n| {
+            n.set(n.get().map(|n| n + 1));
+            n.get()
+        });
+        match n {
+            Some(n) if n > self.max_intersections => {
+                PATH_ABORTED.with(|aborted| {
+                    if !aborted.get() {
+                        aborted.set(true);
+                        crate::stat_inc!(N_ABORTED_PATHS);
+                    }
+                });
+                false
+            }
+            _ => true,
+        }
+    }
     pub fn intersect(&self, ray: &mut Ray) -> Option<SurfaceInteraction> {
-        // TODO: ++nIntersectionTests;
+        crate::stat_inc!(N_INTERSECTION_TESTS);
+        if self.max_intersections > 0 && !self.count_intersection() {
+            return None;
+        }
         assert_ne!(
             ray.d,
             Vector3f {
@@ -65,10 +421,17 @@ impl Scene {
                 z: 0.0,
             }
         );
-        self.aggregate.intersect(ray)
+        let mut si: SurfaceInteraction = self.aggregate.intersect(ray)?;
+        si.check_geometric_normal = self.check_geometric_normal;
+        if self.clamp_shading_normals {
+            // materials calling set_shading_geometry() clamp again
+            si.clamp_shading_normals = true;
+            si.correct_shading_normal();
+        }
+        Some(si)
     }
     pub fn intersect_p(&self, ray: &mut Ray) -> bool {
-        // TODO: ++nShadowTests;
+        crate::stat_inc!(N_SHADOW_TESTS);
         assert_ne!(
             ray.d,
             Vector3f {
@@ -79,12 +442,111 @@ impl Scene {
         );
         self.aggregate.intersect_p(ray)
     }
+    /// Like **intersect()**, but passes through surfaces without a
+    /// material (boundaries between participating media) and returns
+    /// the first hit of a surface with a material together with the
+    /// beam transmittance along the way. A ray with a finite _t_max_
+    /// (e.g. a shadow ray) keeps its end point, so it's continued
+    /// towards that point after each boundary.
+    ///
+    /// Three nested, material-less spheres around the origin with
+    /// purely absorbing media inside (and vacuum outside):
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::accelerators::bvh::{BVHAccel, SplitMethod};
+    /// use pbrt::core::geometry::{Point3f, Ray, Vector3f};
+    /// use pbrt::core::interaction::InteractionCommon;
+    /// use pbrt::core::light::VisibilityTester;
+    /// use pbrt::core::medium::{Medium, MediumInterface};
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::primitive::{GeometricPrimitive, Primitive};
+    /// use pbrt::core::sampler::Sampler;
+    /// use pbrt::core::scene::Scene;
+    /// use pbrt::core::shape::Shape;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::media::homogeneous::HomogeneousMedium;
+    /// use pbrt::samplers::random::RandomSampler;
+    /// use pbrt::shapes::sphere::Sphere;
+    ///
+    /// let sigma_a: [Float; 3] = [0.5, 0.25, 0.125];
+    /// let media: Vec<Arc<Medium>> = sigma_a
+    ///     .iter()
+    ///     .map(|s| {
+    ///         Arc::new(Medium::Homogeneous(HomogeneousMedium::new(
+    ///             &Spectrum::new(*s),
+    ///             &Spectrum::new(0.0),
+    ///             0.0,
+    ///         )))
+    ///     })
+    ///     .collect();
+    /// let mut prims: Vec<Arc<Primitive>> = Vec::new();
+    /// for i in 0..3 {
+    ///     let radius: Float = (i + 1) as Float;
+    ///     let sphere = Arc::new(Shape::Sphr(Sphere::new(
+    ///         Arc::new(Transform::default()),
+    ///         Arc::new(Transform::default()),
+    ///         false,
+    ///         radius,
+    ///         -radius,
+    ///         radius,
+    ///         360.0,
+    ///     )));
+    ///     let outside: Option<Arc<Medium>> = media.get(i + 1).cloned();
+    ///     let mi = Arc::new(MediumInterface::new(Some(media[i].clone()), outside));
+    ///     prims.push(Arc::new(Primitive::Geometric(GeometricPrimitive::new(
+    ///         sphere,
+    ///         None,
+    ///         None,
+    ///         Some(mi),
+    ///     ))));
+    /// }
+    /// let aggregate = Arc::new(Primitive::BVH(BVHAccel::new(prims, 4, SplitMethod::SAH)));
+    /// let scene = Scene::new(aggregate, Vec::new());
+    /// let mut sampler: Box<Sampler> = Box::new(Sampler::Random(RandomSampler::new(1)));
+    /// // all the way through: every shell twice, the inner sphere's diameter once
+    /// let mut ray = Ray {
+    ///     o: Point3f { x: -5.0, y: 0.0, z: 0.0 },
+    ///     d: Vector3f { x: 1.0, y: 0.0, z: 0.0 },
+    ///     t_max: std::f32::INFINITY,
+    ///     time: 0.0,
+    ///     differential: None,
+    ///     medium: None,
+    /// };
+    /// let (isect, tr) = scene.intersect_tr(&mut ray, &mut sampler);
+    /// assert!(isect.is_none());
+    /// let optical_depth: Float = 2.0 * sigma_a[0] + 2.0 * sigma_a[1] + 2.0 * sigma_a[2];
+    /// assert!((tr.y() - Spectrum::new((-optical_depth).exp()).y()).abs() < 1e-4);
+    /// // a shadow ray from outside to the center passes every medium once
+    /// let vis = VisibilityTester {
+    ///     p0: InteractionCommon {
+    ///         p: Point3f { x: -5.0, y: 0.0, z: 0.0 },
+    ///         ..Default::default()
+    ///     },
+    ///     p1: InteractionCommon {
+    ///         p: Point3f::default(),
+    ///         medium_interface: Some(Arc::new(MediumInterface::new(
+    ///             Some(media[0].clone()),
+    ///             Some(media[0].clone()),
+    ///         ))),
+    ///         ..Default::default()
+    ///     },
+    /// };
+    /// let optical_depth: Float = sigma_a[0] + sigma_a[1] + sigma_a[2];
+    /// let tr: Spectrum = vis.tr(&scene, &mut sampler);
+    /// assert!((tr.y() - Spectrum::new((-optical_depth).exp()).y()).abs() < 1e-3);
+    /// ```
     pub fn intersect_tr(
         &self,
         ray: &mut Ray,
         sampler: &mut Box<Sampler>,
     ) -> (Option<SurfaceInteraction>, Spectrum) {
         let mut tr: Spectrum = Spectrum::new(1.0 as Float);
+        let p_end: Option<Point3f> = if ray.t_max < std::f32::INFINITY {
+            Some(ray.position(ray.t_max))
+        } else {
+            None
+        };
         loop {
             // bool hit_surface = Intersect(ray, isect);
             if let Some(isect) = self.intersect(ray) {
@@ -98,7 +560,20 @@ impl Scene {
                         return (Some(isect), tr);
                     }
                 }
-                *ray = isect.spawn_ray(&ray.d);
+                *ray = match p_end {
+                    Some(p_end) => {
+                        let it: InteractionCommon = InteractionCommon {
+                            p: isect.p,
+                            time: isect.time,
+                            p_error: isect.p_error,
+                            wo: isect.wo,
+                            n: isect.n,
+                            medium_interface: isect.medium_interface.clone(),
+                        };
+                        it.spawn_ray_to_pnt(&p_end)
+                    }
+                    None => isect.spawn_ray(&ray.d),
+                };
             } else {
                 // accumulate beam transmittance for ray segment
                 if let Some(ref medium_arc) = ray.medium {
@@ -0,0 +1,212 @@
+//! Records the scene description (the calls made to a
+//! **SceneBuilder**) so it can be replayed later without parsing the
+//! scene files (or the PLY meshes they refer to) again. With the
+//! `serde` feature the recording can be written to (and read from) a
+//! binary cache file.
+
+// std
+use std::path::{Path, PathBuf};
+// pbrt
+use crate::core::api::SceneBuilder;
+use crate::core::paramset::ParamSet;
+use crate::core::pbrt::Float;
+use crate::core::transform::Transform;
+
+/// One statement of the scene description, with the same arguments
+/// as the corresponding **SceneBuilder** method.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Statement {
+    SearchDirectory(PathBuf),
+    Translate(Float, Float, Float),
+    Transform(Transform),
+    ConcatTransform(Transform),
+    Rotate(Float, Float, Float, Float),
+    Scale(Float, Float, Float),
+    LookAt([Float; 9]),
+    CoordinateSystem(ParamSet),
+    CoordSysTransform(ParamSet),
+    ActiveTransformAll,
+    ActiveTransformEndTime,
+    ActiveTransformStartTime,
+    TransformTimes(Float, Float),
+    PixelFilter(ParamSet),
+    Film(ParamSet),
+    Sampler(ParamSet),
+    Accelerator(ParamSet),
+    Integrator(ParamSet),
+    Camera(ParamSet),
+    MakeNamedMedium(ParamSet),
+    MediumInterface(String, String),
+    WorldBegin,
+    AttributeBegin,
+    AttributeEnd,
+    TransformBegin,
+    TransformEnd,
+    Texture(ParamSet),
+    Material(ParamSet),
+    MakeNamedMaterial(ParamSet),
+    NamedMaterial(ParamSet),
+    LightSource(ParamSet),
+    AreaLightSource(ParamSet),
+    Shape(ParamSet),
+    ReverseOrientation,
+    ObjectBegin(ParamSet),
+    ObjectEnd,
+    ObjectInstance(ParamSet),
+}
+
+/// A file the scene description was read from, and a hash of its
+/// contents at that time.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub hash: u64,
+}
+
+/// The statements of a scene description up to (but excluding)
+/// WorldEnd, see **SceneBuilder::start_recording()**.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SceneCache {
+    pub sources: Vec<SourceFile>,
+    pub statements: Vec<Statement>,
+}
+
+/// Hashes the contents of a file (to detect changes). The 64-bit
+/// FNV-1a hash is used, because it's stable across Rust releases
+/// (unlike **DefaultHasher**), so cache files stay valid.
+///
+/// ```rust
+/// use pbrt::core::scenecache::hash_file;
+///
+/// let path = std::env::temp_dir().join("hash_file.txt");
+/// std::fs::write(&path, "a").unwrap();
+/// assert_eq!(hash_file(&path).unwrap(), 0xaf63_dc4c_8601_ec8c);
+/// std::fs::remove_file(&path).unwrap();
+/// ```
+pub fn hash_file(path: &Path) -> std::io::Result<u64> {
+    let bytes: Vec<u8> = std::fs::read(path)?;
+    Ok(fnv1a(&bytes))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+impl SceneCache {
+    /// Adds a file the statements were read from (with the hash of
+    /// its current contents).
+    pub fn add_source(&mut self, path: &Path) -> std::io::Result<()> {
+        let path: PathBuf = std::fs::canonicalize(path)?;
+        let hash: u64 = hash_file(&path)?;
+        self.sources.push(SourceFile { path, hash });
+        Ok(())
+    }
+    /// Are all source files unchanged since they were recorded?
+    pub fn is_up_to_date(&self) -> bool {
+        !self.sources.is_empty()
+            && self
+                .sources
+                .iter()
+                .all(|source| match hash_file(&source.path) {
+                    Ok(hash) => hash == source.hash,
+                    Err(_) => false,
+                })
+    }
+    /// Makes the same calls to _builder_ as the recorded ones.
+    ///
+    /// ```rust
+    /// use pbrt::core::api::SceneBuilder;
+    /// use pbrt::core::paramset::ParamSet;
+    /// use pbrt::core::pbrt::Float;
+    ///
+    /// let mut sphere = ParamSet::default();
+    /// sphere.name = String::from("sphere");
+    /// sphere.add_float(String::from("radius"), 2.0 as Float);
+    /// let mut builder = SceneBuilder::new(1);
+    /// builder.start_recording();
+    /// builder
+    ///     .world_begin()
+    ///     .translate(0.0, 0.0, 5.0)
+    ///     .shape(sphere);
+    /// let recording = builder.take_recording().unwrap();
+    /// assert_eq!(recording.statements.len(), 3);
+    /// let (scene, _) = builder.build();
+    /// // replaying builds the same scene
+    /// let mut replayed = SceneBuilder::new(1);
+    /// recording.replay(&mut replayed);
+    /// let (replayed_scene, _) = replayed.build();
+    /// let bounds = scene.world_bound();
+    /// let replayed_bounds = replayed_scene.world_bound();
+    /// assert_eq!(bounds.p_min.z, replayed_bounds.p_min.z);
+    /// assert_eq!(bounds.p_max.z, replayed_bounds.p_max.z);
+    /// ```
+    pub fn replay(&self, builder: &mut SceneBuilder) {
+        for statement in &self.statements {
+            match statement.clone() {
+                Statement::SearchDirectory(path) => builder.search_directory(path),
+                Statement::Translate(dx, dy, dz) => builder.translate(dx, dy, dz),
+                Statement::Transform(tr) => builder.transform(&tr),
+                Statement::ConcatTransform(tr) => builder.concat_transform(&tr),
+                Statement::Rotate(angle, dx, dy, dz) => builder.rotate(angle, dx, dy, dz),
+                Statement::Scale(sx, sy, sz) => builder.scale(sx, sy, sz),
+                Statement::LookAt(v) => {
+                    builder.look_at(v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7], v[8])
+                }
+                Statement::CoordinateSystem(params) => builder.coordinate_system(params),
+                Statement::CoordSysTransform(params) => builder.coord_sys_transform(params),
+                Statement::ActiveTransformAll => builder.active_transform_all(),
+                Statement::ActiveTransformEndTime => builder.active_transform_end_time(),
+                Statement::ActiveTransformStartTime => builder.active_transform_start_time(),
+                Statement::TransformTimes(start, end) => builder.transform_times(start, end),
+                Statement::PixelFilter(params) => builder.pixel_filter(params),
+                Statement::Film(params) => builder.film(params),
+                Statement::Sampler(params) => builder.sampler(params),
+                Statement::Accelerator(params) => builder.accelerator(params),
+                Statement::Integrator(params) => builder.integrator(params),
+                Statement::Camera(params) => builder.camera(params),
+                Statement::MakeNamedMedium(params) => builder.make_named_medium(params),
+                Statement::MediumInterface(inside, outside) => {
+                    builder.medium_interface(&inside, &outside)
+                }
+                Statement::WorldBegin => builder.world_begin(),
+                Statement::AttributeBegin => builder.attribute_begin(),
+                Statement::AttributeEnd => builder.attribute_end(),
+                Statement::TransformBegin => builder.transform_begin(),
+                Statement::TransformEnd => builder.transform_end(),
+                Statement::Texture(params) => builder.texture(params),
+                Statement::Material(params) => builder.material(params),
+                Statement::MakeNamedMaterial(params) => builder.make_named_material(params),
+                Statement::NamedMaterial(params) => builder.named_material(params),
+                Statement::LightSource(params) => builder.light_source(params),
+                Statement::AreaLightSource(params) => builder.area_light_source(params),
+                Statement::Shape(params) => builder.shape(params),
+                Statement::ReverseOrientation => builder.reverse_orientation(),
+                Statement::ObjectBegin(params) => builder.object_begin(params),
+                Statement::ObjectEnd => builder.object_end(),
+                Statement::ObjectInstance(params) => builder.object_instance(params),
+            };
+        }
+    }
+    /// Writes the recording as binary file (see **read()**).
+    #[cfg(feature = "serde")]
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        bincode::serialize_into(std::io::BufWriter::new(file), self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+    }
+    /// Reads a recording written by **write()**.
+    #[cfg(feature = "serde")]
+    pub fn read(path: &Path) -> std::io::Result<SceneCache> {
+        let file = std::fs::File::open(path)?;
+        bincode::deserialize_from(std::io::BufReader::new(file))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
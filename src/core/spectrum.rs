@@ -1479,6 +1479,114 @@ pub const CIE_LAMBDA: [Float; N_CIE_SAMPLES as usize] = [
 ];
 pub const CIE_Y_INTEGRAL: Float = 106.856895;
 
+// wavelength range and number of samples of a **SampledSpectrum**
+pub const SAMPLED_LAMBDA_START: Float = 400.0;
+pub const SAMPLED_LAMBDA_END: Float = 700.0;
+pub const N_SPECTRAL_SAMPLES: usize = 60;
+
+// Smits-style basis spectra to convert RGB values to a
+// **SampledSpectrum** (for reflectances and illuminants), fitted to be
+// as smooth as possible while reproducing the primaries they are named
+// after (reflectances when lit by the illuminant white)
+
+// TODO: These are our own fits, not the RGBRefl2Spect*/RGBIllum2Spect*
+// tables of pbrt-v3 (spectrum.cpp), so with the "sampled-spectrum"
+// feature RGB colors become (slightly) different spectra than in pbrt
+// and images don't match pbrt's exactly. Replace them with pbrt-v3's
+// data (same 32 wavelengths) and keep the RGB round trip tests.
+
+pub const N_RGB_2_SPECT_SAMPLES: usize = 32;
+pub const RGB_2_SPECT_LAMBDA: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    380.000000, 390.967742, 401.935484, 412.903226, 423.870968, 434.838710, 445.806452, 456.774194,
+    467.741935, 478.709677, 489.677419, 500.645161, 511.612903, 522.580645, 533.548387, 544.516129,
+    555.483871, 566.451613, 577.419355, 588.387097, 599.354839, 610.322581, 621.290323, 632.258065,
+    643.225806, 654.193548, 665.161290, 676.129032, 687.096774, 698.064516, 709.032258, 720.000000,
+];
+pub const RGB_REFL_2_SPECT_WHITE: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000,
+    1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000,
+    1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000,
+    1.000000, 1.000000, 1.000000, 1.000000, 1.000000,
+];
+pub const RGB_REFL_2_SPECT_CYAN: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    0.937332, 0.937332, 0.937353, 0.938475, 0.943964, 0.959425, 0.983480, 1.000000, 1.000000,
+    1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000,
+    0.858908, 0.567913, 0.266381, 0.059548, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
+    0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
+];
+pub const RGB_REFL_2_SPECT_MAGENTA: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 0.938785,
+    0.737952, 0.476434, 0.224859, 0.045202, 0.000000, 0.000000, 0.000000, 0.000000, 0.080935,
+    0.262480, 0.488199, 0.704180, 0.870523, 0.968567, 1.000000, 1.000000, 1.000000, 1.000000,
+    1.000000, 1.000000, 1.000000, 1.000000, 1.000000,
+];
+pub const RGB_REFL_2_SPECT_YELLOW: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.037853, 0.147742,
+    0.310626, 0.497918, 0.683625, 0.844612, 0.956745, 1.000000, 1.000000, 1.000000, 1.000000,
+    1.000000, 1.000000, 0.997837, 0.985116, 0.971041, 0.959849, 0.952421, 0.948140, 0.945950,
+    0.944936, 0.944528, 0.944417, 0.944413, 0.944412,
+];
+pub const RGB_REFL_2_SPECT_RED: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    0.062658, 0.062658, 0.062639, 0.061520, 0.056034, 0.040576, 0.016522, 0.000000, 0.000000,
+    0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
+    0.141092, 0.432087, 0.733619, 0.940452, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000,
+    1.000000, 1.000000, 1.000000, 1.000000, 1.000000,
+];
+pub const RGB_REFL_2_SPECT_GREEN: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.061215,
+    0.262048, 0.523566, 0.775141, 0.954798, 1.000000, 1.000000, 1.000000, 1.000000, 0.919066,
+    0.737520, 0.511801, 0.295819, 0.129477, 0.031433, 0.000000, 0.000000, 0.000000, 0.000000,
+    0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
+];
+pub const RGB_REFL_2_SPECT_BLUE: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 1.000000, 0.962148, 0.852259,
+    0.689374, 0.502081, 0.316374, 0.155387, 0.043255, 0.000000, 0.000000, 0.000000, 0.000000,
+    0.000000, 0.000000, 0.002167, 0.014889, 0.028962, 0.040150, 0.047572, 0.051847, 0.054033,
+    0.055042, 0.055446, 0.055554, 0.055556, 0.055556,
+];
+pub const RGB_ILLUM_2_SPECT_WHITE: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    1.087448, 1.087449, 1.087452, 1.087528, 1.087902, 1.089073, 1.091427, 1.094828, 1.098745,
+    1.102338, 1.104612, 1.104534, 1.100783, 1.091736, 1.076242, 1.054243, 1.026630, 0.995082,
+    0.961926, 0.929869, 0.901519, 0.878749, 0.862226, 0.851381, 0.844890, 0.841354, 0.839598,
+    0.838799, 0.838480, 0.838394, 0.838390, 0.838390,
+];
+pub const RGB_ILLUM_2_SPECT_CYAN: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    1.001440, 1.001441, 1.001454, 1.002105, 1.005434, 1.016068, 1.038069, 1.071339, 1.112791,
+    1.156865, 1.196505, 1.224738, 1.233467, 1.213086, 1.156611, 1.063143, 0.937028, 0.786978,
+    0.625289, 0.466436, 0.324482, 0.209717, 0.126095, 0.071076, 0.038101, 0.020131, 0.011212,
+    0.007163, 0.005557, 0.005129, 0.005121, 0.005121,
+];
+pub const RGB_ILLUM_2_SPECT_MAGENTA: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    1.283391, 1.283393, 1.283355, 1.280985, 1.268739, 1.229127, 1.145505, 1.015003, 0.844101,
+    0.647752, 0.446789, 0.260905, 0.110141, 0.017147, 0.000000, 0.000000, 0.027263, 0.120090,
+    0.258879, 0.419241, 0.576267, 0.710251, 0.811077, 0.878740, 0.919796, 0.942330, 0.953558,
+    0.958665, 0.960691, 0.961230, 0.961239, 0.961238,
+];
+pub const RGB_ILLUM_2_SPECT_YELLOW: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.006158, 0.067718, 0.180262,
+    0.330523, 0.499020, 0.668676, 0.825227, 0.954671, 1.046101, 1.095259, 1.103423, 1.076377,
+    1.023602, 0.957110, 0.889318, 0.830221, 0.785205, 0.754775, 0.736228, 0.726018, 0.720922,
+    0.718599, 0.717674, 0.717426, 0.717420, 0.717419,
+];
+pub const RGB_ILLUM_2_SPECT_RED: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    0.048643, 0.048643, 0.048635, 0.048164, 0.045798, 0.038679, 0.025660, 0.010255, 0.000000,
+    0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.045931,
+    0.178496, 0.360165, 0.551912, 0.722004, 0.852798, 0.941695, 0.996051, 1.026021, 1.040991,
+    1.047810, 1.050519, 1.051240, 1.051251, 1.051250,
+];
+pub const RGB_ILLUM_2_SPECT_GREEN: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.107784,
+    0.297058, 0.525086, 0.754768, 0.955511, 1.098008, 1.160846, 1.137960, 1.035765, 0.870864,
+    0.668122, 0.457635, 0.269297, 0.125750, 0.037005, 0.000000, 0.000000, 0.000000, 0.000000,
+    0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
+];
+pub const RGB_ILLUM_2_SPECT_BLUE: [Float; N_RGB_2_SPECT_SAMPLES] = [
+    1.212725, 1.212726, 1.212697, 1.210800, 1.200980, 1.169085, 1.101308, 0.994473, 0.852460,
+    0.685849, 0.510188, 0.340068, 0.189630, 0.074720, 0.009147, 0.000000, 0.000000, 0.000000,
+    0.000000, 0.000000, 0.000000, 0.008621, 0.019303, 0.028155, 0.034153, 0.037647, 0.039444,
+    0.040275, 0.040609, 0.040699, 0.040700, 0.040700,
+];
+
 pub fn blackbody(lambda: &[Float], n: usize, t: Float, le: &mut Vec<Float>) {
     if t <= 0.0 as Float {
         for _i in 0..n {
@@ -1511,18 +1619,66 @@ pub fn blackbody_normalized(lambda: &[Float], n: usize, t: Float, le: &mut Vec<F
     }
 }
 
-#[derive(Debug, Clone)]
+// CIE standard illuminants, tabulated from 300nm to 830nm in 10nm
+// steps (relative spectral power, 100 at 560nm); use them with
+// **from_sampled()**, e.g.
+// Spectrum::from_sampled(&CIE_ILLUM_LAMBDA, &CIE_ILLUM_D65, N_CIE_ILLUM_SAMPLES as i32)
+
+pub const N_CIE_ILLUM_SAMPLES: usize = 54;
+pub const CIE_ILLUM_LAMBDA: [Float; N_CIE_ILLUM_SAMPLES] = [
+    300.0, 310.0, 320.0, 330.0, 340.0, 350.0, 360.0, 370.0, 380.0, 390.0, 400.0, 410.0, 420.0,
+    430.0, 440.0, 450.0, 460.0, 470.0, 480.0, 490.0, 500.0, 510.0, 520.0, 530.0, 540.0, 550.0,
+    560.0, 570.0, 580.0, 590.0, 600.0, 610.0, 620.0, 630.0, 640.0, 650.0, 660.0, 670.0, 680.0,
+    690.0, 700.0, 710.0, 720.0, 730.0, 740.0, 750.0, 760.0, 770.0, 780.0, 790.0, 800.0, 810.0,
+    820.0, 830.0,
+];
+/// Average daylight (correlated color temperature of about 6504K).
+///
+/// ```rust
+/// use pbrt::core::pbrt::{Float, Spectrum};
+/// use pbrt::core::spectrum::{CIE_ILLUM_D65, CIE_ILLUM_LAMBDA, N_CIE_ILLUM_SAMPLES};
+///
+/// let d65: Spectrum =
+///     Spectrum::from_sampled(&CIE_ILLUM_LAMBDA, &CIE_ILLUM_D65, N_CIE_ILLUM_SAMPLES as i32);
+/// let mut xyz: [Float; 3] = [0.0; 3];
+/// d65.to_xyz(&mut xyz);
+/// let sum: Float = xyz[0] + xyz[1] + xyz[2];
+/// assert!((xyz[0] / sum - 0.3127).abs() < 0.002);
+/// assert!((xyz[1] / sum - 0.3290).abs() < 0.002);
+/// ```
+pub const CIE_ILLUM_D65: [Float; N_CIE_ILLUM_SAMPLES] = [
+    0.0341, 3.2945, 20.236, 37.0535, 39.9488, 44.9117, 46.6383, 52.0891, 49.9755, 54.6482, 82.7549,
+    91.486, 93.4318, 86.6823, 104.865, 117.008, 117.812, 114.861, 115.923, 108.811, 109.354,
+    107.802, 104.79, 107.689, 104.405, 104.046, 100.0, 96.3342, 95.788, 88.6856, 90.0062, 89.5991,
+    87.6987, 83.2886, 83.6992, 80.0268, 80.2146, 82.2778, 78.2842, 69.7213, 71.6091, 74.349,
+    61.604, 69.8856, 75.087, 63.5927, 46.4182, 66.8054, 63.3828, 64.304, 59.4519, 51.959, 57.4406,
+    60.3125,
+];
+/// Incandescent (tungsten filament) light, a blackbody at about
+/// 2856K.
+pub const CIE_ILLUM_A: [Float; N_CIE_ILLUM_SAMPLES] = [
+    0.930483, 1.35769, 1.92508, 2.65981, 3.58968, 4.74238, 6.14462, 7.82135, 9.7951, 12.0853,
+    14.708, 17.6753, 20.995, 24.6709, 28.7027, 33.0859, 37.8121, 42.8693, 48.2423, 53.9132,
+    59.8611, 66.0635, 72.4959, 79.1326, 85.947, 92.912, 100.0, 107.184, 114.436, 121.731, 129.043,
+    136.346, 143.618, 150.836, 157.979, 165.028, 171.963, 178.769, 185.429, 191.931, 198.261,
+    204.409, 210.365, 216.12, 221.667, 227.0, 232.115, 237.008, 241.675, 246.116, 250.329, 254.314,
+    258.071, 261.602,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum SpectrumType {
     Reflectance,
     Illuminant,
 }
 
 #[derive(Debug, Default, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RGBSpectrum {
     pub c: [Float; 3],
 }
 
 impl RGBSpectrum {
+    pub const N_SAMPLES: usize = 3;
     pub fn new(v: Float) -> Self {
         // let n_spectrum_samples = 3; // RGB
         RGBSpectrum { c: [v, v, v] }
@@ -1556,6 +1712,10 @@ impl RGBSpectrum {
         // TODO: DCHECK(!s.HasNaNs());
         s
     }
+    /// The spectrum type only matters for a **SampledSpectrum**.
+    pub fn from_rgb_type(rgb: &[Float; 3], _spectrum_type: SpectrumType) -> RGBSpectrum {
+        RGBSpectrum::from_rgb(rgb)
+    }
     pub fn to_rgb(&self, rgb: &mut [Float; 3]) {
         rgb[0] = self.c[0];
         rgb[1] = self.c[1];
@@ -1596,6 +1756,38 @@ impl RGBSpectrum {
         xyz[2] *= scale;
         RGBSpectrum::from_xyz(&xyz, SpectrumType::Reflectance)
     }
+    /// The emission of a blackbody at _temperature_k_ (in Kelvin),
+    /// normalized so that its maximum (at the peak wavelength) is
+    /// _scale_, like a **blackbody** parameter of a scene file.
+    ///
+    /// A 6500K blackbody is close to the Planckian locus coordinates
+    /// (x, y) = (0.3135, 0.3236):
+    ///
+    /// ```rust
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    ///
+    /// let s: Spectrum = Spectrum::blackbody(6500.0, 1.0);
+    /// let mut xyz: [Float; 3] = [0.0; 3];
+    /// s.to_xyz(&mut xyz);
+    /// let sum: Float = xyz[0] + xyz[1] + xyz[2];
+    /// assert!((xyz[0] / sum - 0.3135).abs() < 0.002);
+    /// assert!((xyz[1] / sum - 0.3236).abs() < 0.002);
+    /// // twice the scale, twice the radiance
+    /// let s2: Spectrum = Spectrum::blackbody(6500.0, 2.0);
+    /// assert!((s2.y() - 2.0 * s.y()).abs() < 1e-5);
+    /// // normalized to a luminance of one it's nearly white
+    /// let white: Spectrum = s / s.y();
+    /// let mut rgb: [Float; 3] = [0.0; 3];
+    /// white.to_rgb(&mut rgb);
+    /// for c in rgb.iter() {
+    ///     assert!((c - 1.0).abs() < 0.1);
+    /// }
+    /// ```
+    pub fn blackbody(temperature_k: Float, scale: Float) -> RGBSpectrum {
+        let mut v: Vec<Float> = Vec::with_capacity(N_CIE_SAMPLES as usize);
+        blackbody_normalized(&CIE_LAMBDA, N_CIE_SAMPLES as usize, temperature_k, &mut v);
+        RGBSpectrum::from_sampled(&CIE_LAMBDA, &v, N_CIE_SAMPLES as i32) * scale
+    }
     // from CoefficientSpectrum
     pub fn is_black(&self) -> bool {
         for i in 0..3 {
@@ -1811,6 +2003,425 @@ impl From<Float> for RGBSpectrum {
     }
 }
 
+/// Spectral power distribution, represented by
+/// **N_SPECTRAL_SAMPLES** buckets of equal width between
+/// **SAMPLED_LAMBDA_START** and **SAMPLED_LAMBDA_END**. Each bucket
+/// stores the average value of the distribution over its wavelength
+/// range. Used as **Spectrum** with the *sampled-spectrum* feature.
+///
+/// Converting RGB values to a spectrum and back reproduces them:
+///
+/// ```rust
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::spectrum::{SampledSpectrum, SpectrumType};
+///
+/// fn main() {
+///     let colors: [[Float; 3]; 4] = [
+///         [0.8, 0.3, 0.1],
+///         [0.05, 0.5, 0.95],
+///         [0.25, 0.25, 0.25],
+///         [2.0, 4.0, 1.0],
+///     ];
+///     let white = SampledSpectrum::from_rgb_type(&[1.0; 3], SpectrumType::Illuminant);
+///     for color in colors.iter() {
+///         let mut rgb: [Float; 3] = [0.0; 3];
+///         // illuminants directly
+///         SampledSpectrum::from_rgb_type(color, SpectrumType::Illuminant).to_rgb(&mut rgb);
+///         for c in 0..3 {
+///             assert!((rgb[c] - color[c]).abs() < 1e-3 * color[c].max(1.0));
+///         }
+///         // reflectances when lit by white light
+///         (SampledSpectrum::from_rgb(color) * white).to_rgb(&mut rgb);
+///         for c in 0..3 {
+///             assert!((rgb[c] - color[c]).abs() < 1e-3 * color[c].max(1.0));
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct SampledSpectrum {
+    pub c: [Float; N_SPECTRAL_SAMPLES],
+}
+
+impl SampledSpectrum {
+    pub const N_SAMPLES: usize = N_SPECTRAL_SAMPLES;
+    pub fn new(v: Float) -> Self {
+        SampledSpectrum {
+            c: [v; N_SPECTRAL_SAMPLES],
+        }
+    }
+    /// Converts RGB reflectance values.
+    pub fn rgb(r: Float, g: Float, b: Float) -> SampledSpectrum {
+        SampledSpectrum::from_rgb(&[r, g, b])
+    }
+    pub fn from_srgb(rgb: &[u8; 3]) -> SampledSpectrum {
+        fn as_float(v: u8) -> Float {
+            v as Float / 255.0
+        }
+        SampledSpectrum::rgb(
+            inverse_gamma_convert_float(as_float(rgb[0])),
+            inverse_gamma_convert_float(as_float(rgb[1])),
+            inverse_gamma_convert_float(as_float(rgb[2])),
+        )
+    }
+    pub fn inverse_gamma_correct(&self) -> SampledSpectrum {
+        let mut rgb: [Float; 3] = [0.0 as Float; 3];
+        self.to_rgb(&mut rgb);
+        SampledSpectrum::rgb(
+            inverse_gamma_convert_float(rgb[0]),
+            inverse_gamma_convert_float(rgb[1]),
+            inverse_gamma_convert_float(rgb[2]),
+        )
+    }
+    pub fn from_rgb(rgb: &[Float; 3]) -> SampledSpectrum {
+        SampledSpectrum::from_rgb_type(rgb, SpectrumType::Reflectance)
+    }
+    /// Adds up the basis spectra for white and the (secondary and
+    /// primary) colors in between the smallest and the largest RGB
+    /// value.
+    pub fn from_rgb_type(rgb: &[Float; 3], spectrum_type: SpectrumType) -> SampledSpectrum {
+        let basis: &[SampledSpectrum; 7] = match spectrum_type {
+            SpectrumType::Reflectance => &RGB_REFL_2_SPECT,
+            SpectrumType::Illuminant => &RGB_ILLUM_2_SPECT,
+        };
+        let [white, cyan, magenta, yellow, red, green, blue] = basis;
+        let mut r: SampledSpectrum = SampledSpectrum::new(0.0 as Float);
+        if rgb[0] <= rgb[1] && rgb[0] <= rgb[2] {
+            // compute spectrum from RGB with red as minimum component
+            r += *white * rgb[0];
+            if rgb[1] <= rgb[2] {
+                r += *cyan * (rgb[1] - rgb[0]);
+                r += *blue * (rgb[2] - rgb[1]);
+            } else {
+                r += *cyan * (rgb[2] - rgb[0]);
+                r += *green * (rgb[1] - rgb[2]);
+            }
+        } else if rgb[1] <= rgb[0] && rgb[1] <= rgb[2] {
+            // compute spectrum from RGB with green as minimum component
+            r += *white * rgb[1];
+            if rgb[0] <= rgb[2] {
+                r += *magenta * (rgb[0] - rgb[1]);
+                r += *blue * (rgb[2] - rgb[0]);
+            } else {
+                r += *magenta * (rgb[2] - rgb[1]);
+                r += *red * (rgb[0] - rgb[2]);
+            }
+        } else {
+            // compute spectrum from RGB with blue as minimum component
+            r += *white * rgb[2];
+            if rgb[0] <= rgb[1] {
+                r += *yellow * (rgb[0] - rgb[2]);
+                r += *green * (rgb[1] - rgb[0]);
+            } else {
+                r += *yellow * (rgb[1] - rgb[2]);
+                r += *red * (rgb[0] - rgb[1]);
+            }
+        }
+        r.clamp(0.0 as Float, std::f32::INFINITY as Float)
+    }
+    pub fn to_rgb(&self, rgb: &mut [Float; 3]) {
+        let mut xyz: [Float; 3] = [0.0 as Float; 3];
+        self.to_xyz(&mut xyz);
+        xyz_to_rgb(&xyz, rgb);
+    }
+    pub fn to_xyz(&self, xyz: &mut [Float; 3]) {
+        let tables: &[SampledSpectrum; 3] = &CIE_XYZ_SAMPLED;
+        for (j, table) in tables.iter().enumerate() {
+            let mut sum: Float = 0.0 as Float;
+            for i in 0..N_SPECTRAL_SAMPLES {
+                sum += table.c[i] * self.c[i];
+            }
+            xyz[j] = sum * SampledSpectrum::xyz_scale();
+        }
+    }
+    pub fn from_xyz(xyz: &[Float; 3], spectrum_type: SpectrumType) -> SampledSpectrum {
+        let mut rgb: [Float; 3] = [0.0 as Float; 3];
+        xyz_to_rgb(xyz, &mut rgb);
+        SampledSpectrum::from_rgb_type(&rgb, spectrum_type)
+    }
+    pub fn y(&self) -> Float {
+        let y: &SampledSpectrum = &CIE_XYZ_SAMPLED[1];
+        let mut yy: Float = 0.0 as Float;
+        for i in 0..N_SPECTRAL_SAMPLES {
+            yy += y.c[i] * self.c[i];
+        }
+        yy * SampledSpectrum::xyz_scale()
+    }
+    /// Averages the piecewise-linear distribution given by the samples
+    /// over each bucket.
+    pub fn from_sampled(lambda: &[Float], v: &[Float], n: i32) -> SampledSpectrum {
+        // sort samples if unordered, use sorted for returned spectrum
+        if !spectrum_samples_sorted(lambda, v, n) {
+            let mut samples: Vec<(Float, Float)> = lambda[..n as usize]
+                .iter()
+                .cloned()
+                .zip(v[..n as usize].iter().cloned())
+                .collect();
+            samples.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            let (slambda, sv): (Vec<Float>, Vec<Float>) = samples.into_iter().unzip();
+            return SampledSpectrum::from_sampled(&slambda, &sv, n);
+        }
+        let mut r: SampledSpectrum = SampledSpectrum::new(0.0 as Float);
+        for i in 0..N_SPECTRAL_SAMPLES {
+            // compute average value of given SPD over $i$th sample's range
+            let (lambda0, lambda1) = SampledSpectrum::bucket_range(i);
+            r.c[i] = average_spectrum_samples(lambda, v, n, lambda0, lambda1);
+        }
+        r
+    }
+    /// The emission of a blackbody at _temperature_k_ (in Kelvin),
+    /// normalized so that its maximum (at the peak wavelength) is
+    /// _scale_, like a **blackbody** parameter of a scene file.
+    pub fn blackbody(temperature_k: Float, scale: Float) -> SampledSpectrum {
+        let mut v: Vec<Float> = Vec::with_capacity(N_CIE_SAMPLES as usize);
+        blackbody_normalized(&CIE_LAMBDA, N_CIE_SAMPLES as usize, temperature_k, &mut v);
+        SampledSpectrum::from_sampled(&CIE_LAMBDA, &v, N_CIE_SAMPLES as i32) * scale
+    }
+    // from CoefficientSpectrum
+    pub fn is_black(&self) -> bool {
+        self.c.iter().all(|v| *v == 0.0 as Float)
+    }
+    pub fn sqrt(&self) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = *self;
+        for v in ret.c.iter_mut() {
+            *v = v.sqrt();
+        }
+        ret
+    }
+    pub fn exp(&self) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = *self;
+        for v in ret.c.iter_mut() {
+            *v = v.exp();
+        }
+        ret
+    }
+    /// Clamp spectrum to lie between the values low and high. Use
+    /// (0.0 as Float, std::f32::INFINITY as Float) if there are no
+    /// specific values.
+    pub fn clamp(&self, low: Float, high: Float) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = *self;
+        for v in ret.c.iter_mut() {
+            *v = clamp_t(*v, low, high);
+        }
+        assert!(!ret.has_nans());
+        ret
+    }
+    pub fn max_component_value(&self) -> Float {
+        self.c.iter().fold(self.c[0], |m, v| m.max(*v))
+    }
+    pub fn has_nans(&self) -> bool {
+        self.c.iter().any(|v| v.is_nan())
+    }
+    fn bucket_range(i: usize) -> (Float, Float) {
+        let lambda0: Float = lerp(
+            i as Float / N_SPECTRAL_SAMPLES as Float,
+            SAMPLED_LAMBDA_START,
+            SAMPLED_LAMBDA_END,
+        );
+        let lambda1: Float = lerp(
+            (i + 1) as Float / N_SPECTRAL_SAMPLES as Float,
+            SAMPLED_LAMBDA_START,
+            SAMPLED_LAMBDA_END,
+        );
+        (lambda0, lambda1)
+    }
+    fn xyz_scale() -> Float {
+        (SAMPLED_LAMBDA_END - SAMPLED_LAMBDA_START) / (CIE_Y_INTEGRAL * N_SPECTRAL_SAMPLES as Float)
+    }
+}
+
+impl Default for SampledSpectrum {
+    fn default() -> Self {
+        SampledSpectrum::new(0.0 as Float)
+    }
+}
+
+impl PartialEq for SampledSpectrum {
+    fn eq(&self, rhs: &SampledSpectrum) -> bool {
+        self.c[..] == rhs.c[..]
+    }
+}
+
+// serde derives arrays only up to 32 elements
+#[cfg(feature = "serde")]
+impl serde::Serialize for SampledSpectrum {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.c.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SampledSpectrum {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let values: Vec<Float> = serde::Deserialize::deserialize(deserializer)?;
+        if values.len() != N_SPECTRAL_SAMPLES {
+            return Err(serde::de::Error::invalid_length(
+                values.len(),
+                &"N_SPECTRAL_SAMPLES values",
+            ));
+        }
+        let mut s: SampledSpectrum = SampledSpectrum::default();
+        s.c.copy_from_slice(&values);
+        Ok(s)
+    }
+}
+
+impl Add for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn add(mut self, rhs: SampledSpectrum) -> SampledSpectrum {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for SampledSpectrum {
+    fn add_assign(&mut self, rhs: SampledSpectrum) {
+        for i in 0..N_SPECTRAL_SAMPLES {
+            self.c[i] += rhs.c[i];
+        }
+    }
+}
+
+impl Mul for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn mul(mut self, rhs: SampledSpectrum) -> SampledSpectrum {
+        self *= rhs;
+        self
+    }
+}
+
+impl Mul<Float> for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn mul(mut self, rhs: Float) -> SampledSpectrum {
+        for v in self.c.iter_mut() {
+            *v *= rhs;
+        }
+        self
+    }
+}
+
+impl Mul<SampledSpectrum> for Float {
+    type Output = SampledSpectrum;
+    fn mul(self, rhs: SampledSpectrum) -> SampledSpectrum {
+        rhs * self
+    }
+}
+
+impl MulAssign for SampledSpectrum {
+    fn mul_assign(&mut self, rhs: SampledSpectrum) {
+        for i in 0..N_SPECTRAL_SAMPLES {
+            self.c[i] *= rhs.c[i];
+        }
+    }
+}
+
+impl Sub for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn sub(mut self, rhs: SampledSpectrum) -> SampledSpectrum {
+        for i in 0..N_SPECTRAL_SAMPLES {
+            self.c[i] -= rhs.c[i];
+        }
+        self
+    }
+}
+
+impl Div for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn div(mut self, rhs: SampledSpectrum) -> SampledSpectrum {
+        for i in 0..N_SPECTRAL_SAMPLES {
+            self.c[i] /= rhs.c[i];
+        }
+        self
+    }
+}
+
+impl Div<Float> for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn div(mut self, rhs: Float) -> SampledSpectrum {
+        self /= rhs;
+        assert!(!self.has_nans());
+        self
+    }
+}
+
+impl DivAssign<Float> for SampledSpectrum {
+    fn div_assign(&mut self, rhs: Float) {
+        assert_ne!(rhs, 0.0 as Float);
+        assert!(!rhs.is_nan());
+        for v in self.c.iter_mut() {
+            *v /= rhs;
+        }
+    }
+}
+
+impl Neg for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn neg(self) -> SampledSpectrum {
+        self * -1.0 as Float
+    }
+}
+
+impl Zero for SampledSpectrum {
+    fn zero() -> SampledSpectrum {
+        SampledSpectrum::new(0.0 as Float)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.is_black()
+    }
+}
+
+impl Index<usize> for SampledSpectrum {
+    type Output = Float;
+    fn index(&self, index: usize) -> &Float {
+        &self.c[index]
+    }
+}
+
+impl IndexMut<usize> for SampledSpectrum {
+    fn index_mut(&mut self, index: usize) -> &mut Float {
+        &mut self.c[index]
+    }
+}
+
+impl From<Float> for SampledSpectrum {
+    fn from(f: Float) -> Self {
+        SampledSpectrum::new(f)
+    }
+}
+
+fn sampled_from_table(table: &[Float; N_RGB_2_SPECT_SAMPLES]) -> SampledSpectrum {
+    SampledSpectrum::from_sampled(&RGB_2_SPECT_LAMBDA, table, N_RGB_2_SPECT_SAMPLES as i32)
+}
+
+lazy_static::lazy_static! {
+    // the CIE matching functions, averaged over the buckets
+    static ref CIE_XYZ_SAMPLED: [SampledSpectrum; 3] = [
+        SampledSpectrum::from_sampled(&CIE_LAMBDA, &CIE_X, N_CIE_SAMPLES as i32),
+        SampledSpectrum::from_sampled(&CIE_LAMBDA, &CIE_Y, N_CIE_SAMPLES as i32),
+        SampledSpectrum::from_sampled(&CIE_LAMBDA, &CIE_Z, N_CIE_SAMPLES as i32),
+    ];
+    // white, cyan, magenta, yellow, red, green, blue
+    static ref RGB_REFL_2_SPECT: [SampledSpectrum; 7] = [
+        sampled_from_table(&RGB_REFL_2_SPECT_WHITE),
+        sampled_from_table(&RGB_REFL_2_SPECT_CYAN),
+        sampled_from_table(&RGB_REFL_2_SPECT_MAGENTA),
+        sampled_from_table(&RGB_REFL_2_SPECT_YELLOW),
+        sampled_from_table(&RGB_REFL_2_SPECT_RED),
+        sampled_from_table(&RGB_REFL_2_SPECT_GREEN),
+        sampled_from_table(&RGB_REFL_2_SPECT_BLUE),
+    ];
+    static ref RGB_ILLUM_2_SPECT: [SampledSpectrum; 7] = [
+        sampled_from_table(&RGB_ILLUM_2_SPECT_WHITE),
+        sampled_from_table(&RGB_ILLUM_2_SPECT_CYAN),
+        sampled_from_table(&RGB_ILLUM_2_SPECT_MAGENTA),
+        sampled_from_table(&RGB_ILLUM_2_SPECT_YELLOW),
+        sampled_from_table(&RGB_ILLUM_2_SPECT_RED),
+        sampled_from_table(&RGB_ILLUM_2_SPECT_GREEN),
+        sampled_from_table(&RGB_ILLUM_2_SPECT_BLUE),
+    ];
+}
+
 /// Calculate RGB coefficients from a XYZ representation.
 pub fn xyz_to_rgb(xyz: &[Float; 3], rgb: &mut [Float; 3]) {
     rgb[0] = 3.240479 * xyz[0] - 1.537150 * xyz[1] - 0.498535 * xyz[2];
@@ -1837,6 +2448,59 @@ pub fn spectrum_samples_sorted(lambda: &[Float], _vals: &[Float], n: i32) -> boo
     true
 }
 
+/// Average of the piecewise-linear function given by the samples over
+/// the wavelength range from _lambda_start_ to _lambda_end_ (constant
+/// outside of the sampled range).
+pub fn average_spectrum_samples(
+    lambda: &[Float],
+    vals: &[Float],
+    n: i32,
+    lambda_start: Float,
+    lambda_end: Float,
+) -> Float {
+    let n: usize = n as usize;
+    // handle cases with out-of-bounds range or single sample only
+    if lambda_end <= lambda[0] {
+        return vals[0];
+    }
+    if lambda_start >= lambda[n - 1] {
+        return vals[n - 1];
+    }
+    if n == 1 {
+        return vals[0];
+    }
+    let mut sum: Float = 0.0 as Float;
+    // add contributions of constant segments before/after samples
+    if lambda_start < lambda[0] {
+        sum += vals[0] * (lambda[0] - lambda_start);
+    }
+    if lambda_end > lambda[n - 1] {
+        sum += vals[n - 1] * (lambda_end - lambda[n - 1]);
+    }
+    // advance to first relevant wavelength segment
+    let mut i: usize = 0;
+    while lambda_start > lambda[i + 1] {
+        i += 1;
+    }
+    // loop over wavelength sample segments and add contributions
+    let interp = |w: Float, i: usize| -> Float {
+        lerp(
+            (w - lambda[i]) / (lambda[i + 1] - lambda[i]),
+            vals[i],
+            vals[i + 1],
+        )
+    };
+    while i + 1 < n && lambda_end >= lambda[i] {
+        let seg_lambda_start: Float = lambda_start.max(lambda[i]);
+        let seg_lambda_end: Float = lambda_end.min(lambda[i + 1]);
+        sum += 0.5 as Float
+            * (interp(seg_lambda_start, i) + interp(seg_lambda_end, i))
+            * (seg_lambda_end - seg_lambda_start);
+        i += 1;
+    }
+    sum / (lambda_end - lambda_start)
+}
+
 /// Find responsible interval and linearly interpolate between the two
 /// sample values.
 pub fn interpolate_spectrum_samples(lambda: &[Float], vals: &[Float], n: i32, l: Float) -> Float {
@@ -0,0 +1,545 @@
+//! Statistics about the rendering process: event counters, ratios,
+//! memory sizes, and the time spent in coarse phases (building
+//! acceleration structures, loading textures, rendering).
+//!
+//! Counters are declared with the **stat_counter!**, **stat_ratio!**
+//! and **stat_memory!** macros and updated with **stat_inc!** or
+//! **stat_add!**. Each thread gets its own copy of a counter, which it
+//! registers on first use, so updates are plain (uncontended) stores
+//! into thread-local memory. When the thread exits, its values are
+//! added to the totals and the copy is freed. **print_stats()** sums
+//! up the totals and the values of all running threads.
+//!
+//! Counters only exist with the `stats` feature, without it the
+//! macros expand to nothing, so the intersection and shading code
+//! pays nothing for them. The feature also adds counters per name
+//! (see **stat_name_id()** and **named_stat_add()**), to find the
+//! meshes and materials which cost the most render time. The time
+//! spent in the coarse phases is always measured.
+
+// std
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// see stats.h
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StatKind {
+    Counter,
+    Memory,
+    RatioNumerator,
+    RatioDenominator,
+}
+
+/// The per-thread value of a counter declared by one of the stat_*
+/// macros.
+pub struct StatCounter {
+    title: &'static str,
+    kind: StatKind,
+    // shared with the registry, so print_stats() can read it while
+    // the thread is running
+    value: Arc<AtomicI64>,
+}
+
+impl StatCounter {
+    /// Creates the counter for the current thread and registers it, so
+    /// its value shows up in the statistics.
+    pub fn register(title: &'static str, kind: StatKind) -> Self {
+        let value: Arc<AtomicI64> = Arc::new(AtomicI64::new(0));
+        REGISTRY
+            .lock()
+            .unwrap()
+            .running
+            .push((title, kind, value.clone()));
+        StatCounter { title, kind, value }
+    }
+    pub fn add(&self, v: i64) {
+        // only this thread writes, so there is no need for an atomic add
+        self.value
+            .store(self.value.load(Ordering::Relaxed) + v, Ordering::Relaxed);
+    }
+}
+
+impl Drop for StatCounter {
+    // the thread exits: add the value to the totals and unregister
+    fn drop(&mut self) {
+        if let Ok(mut registry) = REGISTRY.lock() {
+            if let Some(i) = registry
+                .running
+                .iter()
+                .position(|(_, _, value)| Arc::ptr_eq(value, &self.value))
+            {
+                registry.running.swap_remove(i);
+            }
+            *registry.totals.entry((self.title, self.kind)).or_insert(0) +=
+                self.value.load(Ordering::Relaxed);
+        }
+    }
+}
+
+/// The counters of the running threads, and the summed up values of
+/// the threads which exited.
+#[derive(Default)]
+struct Registry {
+    running: Vec<(&'static str, StatKind, Arc<AtomicI64>)>,
+    totals: BTreeMap<(&'static str, StatKind), i64>,
+}
+
+/// Number of running threads which hold a counter with _title_.
+/// Threads add their values to the totals when they exit, so this
+/// doesn't grow with the number of threads ever started.
+pub fn stat_threads(title: &str) -> usize {
+    REGISTRY
+        .lock()
+        .unwrap()
+        .running
+        .iter()
+        .filter(|(running, _, _)| *running == title)
+        .count()
+}
+
+/// The sum of the values of all counters with _title_ (of running and
+/// exited threads), e.g. the bytes of a **stat_memory!** counter.
+pub fn stat_total(title: &str) -> i64 {
+    let registry = REGISTRY.lock().unwrap();
+    let running: i64 = registry
+        .running
+        .iter()
+        .filter(|(running, _, _)| *running == title)
+        .map(|(_, _, value)| value.load(Ordering::Relaxed))
+        .sum();
+    let exited: i64 = registry
+        .totals
+        .iter()
+        .filter(|((exited, _), _)| *exited == title)
+        .map(|(_, value)| *value)
+        .sum();
+    running + exited
+}
+
+/// Coarse phases of a render, see **ProfilePhase**.
+#[derive(Debug, Copy, Clone)]
+pub enum Prof {
+    AccelConstruction,
+    TextureLoading,
+    Rendering,
+}
+
+const PROF_NAMES: [&str; 3] = [
+    "Acceleration structure construction",
+    "Texture loading",
+    "Rendering",
+];
+
+/// Adds the time until it gets dropped to the total time spent in a
+/// phase:
+///
+/// ```rust
+/// use pbrt::core::stats::{Prof, ProfilePhase};
+///
+/// {
+///     let _phase = ProfilePhase::new(Prof::TextureLoading);
+///     // load textures ...
+/// }
+/// ```
+pub struct ProfilePhase {
+    prof: Prof,
+    start: Instant,
+}
+
+impl ProfilePhase {
+    pub fn new(prof: Prof) -> Self {
+        ProfilePhase {
+            prof,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for ProfilePhase {
+    fn drop(&mut self) {
+        let nanos: u64 = self.start.elapsed().as_nanos() as u64;
+        PHASE_NANOS[self.prof as usize].fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref REGISTRY: Mutex<Registry> = Mutex::new(Registry::default());
+}
+
+static PHASE_NANOS: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+/// Counters kept per name, see **named_stat_add()**.
+#[derive(Debug, Copy, Clone)]
+pub enum NamedStat {
+    /// Ray intersection tests of a mesh
+    RayTests,
+    /// Rays hitting a mesh
+    RayHits,
+    /// Time spent computing the BSDFs of a material
+    ShadingNanos,
+    /// Evaluations and samples of the BSDFs of a material
+    BsdfEvaluations,
+}
+
+const N_NAMED_STATS: usize = 4;
+
+/// Maximum number of distinct names, further names share the id of
+/// the last one.
+pub const MAX_STAT_NAMES: usize = 1024;
+
+lazy_static::lazy_static! {
+    static ref STAT_NAMES: Mutex<Vec<String>> = Mutex::new(vec![String::from("(unnamed)")]);
+    static ref NAMED_REGISTRY: Mutex<NamedRegistry> = Mutex::new(NamedRegistry::default());
+}
+
+/// Like **Registry**, for the named counters of each thread (indexed
+/// by name id times **N_NAMED_STATS** plus the **NamedStat**).
+#[derive(Default)]
+struct NamedRegistry {
+    running: Vec<Arc<[AtomicI64]>>,
+    // empty until the first thread exits
+    totals: Vec<i64>,
+}
+
+/// The named counters of a thread, see **named_stat_add()**.
+#[cfg(feature = "stats")]
+struct NamedCounters {
+    values: Arc<[AtomicI64]>,
+}
+
+#[cfg(feature = "stats")]
+impl NamedCounters {
+    fn register() -> Self {
+        let values: Arc<[AtomicI64]> = (0..MAX_STAT_NAMES * N_NAMED_STATS)
+            .map(|_| AtomicI64::new(0))
+            .collect();
+        NAMED_REGISTRY.lock().unwrap().running.push(values.clone());
+        NamedCounters { values }
+    }
+}
+
+#[cfg(feature = "stats")]
+impl Drop for NamedCounters {
+    // like StatCounter::drop()
+    fn drop(&mut self) {
+        if let Ok(mut registry) = NAMED_REGISTRY.lock() {
+            if let Some(i) = registry
+                .running
+                .iter()
+                .position(|values| Arc::ptr_eq(values, &self.values))
+            {
+                registry.running.swap_remove(i);
+            }
+            if registry.totals.is_empty() {
+                registry.totals = vec![0; MAX_STAT_NAMES * N_NAMED_STATS];
+            }
+            for (total, value) in registry.totals.iter_mut().zip(self.values.iter()) {
+                *total += value.load(Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "stats")]
+thread_local! {
+    static NAMED_COUNTERS: NamedCounters = NamedCounters::register();
+}
+
+/// Number of running threads which hold named counters (see
+/// **stat_threads()**).
+pub fn named_stat_threads() -> usize {
+    NAMED_REGISTRY.lock().unwrap().running.len()
+}
+
+/// Returns the id **named_stat_add()** expects for _name_ (the same
+/// id for the same name). Without a name the id is 0.
+pub fn stat_name_id(name: Option<&str>) -> u32 {
+    let name: &str = match name {
+        Some(name) => name,
+        None => return 0_u32,
+    };
+    let mut names = STAT_NAMES.lock().unwrap();
+    if let Some(id) = names.iter().position(|known| known == name) {
+        return id as u32;
+    }
+    if names.len() == MAX_STAT_NAMES {
+        return (MAX_STAT_NAMES - 1) as u32;
+    }
+    names.push(String::from(name));
+    (names.len() - 1) as u32
+}
+
+/// Adds _value_ to the counter _stat_ of the name with _id_ (see
+/// **stat_name_id()**). Does nothing without the `stats` feature.
+#[inline]
+pub fn named_stat_add(id: u32, stat: NamedStat, value: i64) {
+    #[cfg(feature = "stats")]
+    NAMED_COUNTERS.with(|counters| {
+        // only this thread writes, like in StatCounter::add()
+        let counter: &AtomicI64 = &counters.values[id as usize * N_NAMED_STATS + stat as usize];
+        counter.store(counter.load(Ordering::Relaxed) + value, Ordering::Relaxed);
+    });
+    #[cfg(not(feature = "stats"))]
+    let _ = (id, stat, value);
+}
+
+/// Sums up the named counters of all threads (by name id).
+fn named_stats() -> Vec<[i64; N_NAMED_STATS]> {
+    let mut totals: Vec<[i64; N_NAMED_STATS]> = vec![[0; N_NAMED_STATS]; MAX_STAT_NAMES];
+    let registry = NAMED_REGISTRY.lock().unwrap();
+    for values in registry.running.iter() {
+        for (i, value) in values.iter().enumerate() {
+            totals[i / N_NAMED_STATS][i % N_NAMED_STATS] += value.load(Ordering::Relaxed);
+        }
+    }
+    for (i, value) in registry.totals.iter().enumerate() {
+        totals[i / N_NAMED_STATS][i % N_NAMED_STATS] += value;
+    }
+    totals
+}
+
+/// Prints the names with the highest _key_ (at most 20) of those with
+/// a non-zero _key_.
+fn print_named_stats(
+    dest: &mut dyn Write,
+    heading: &str,
+    totals: &[[i64; N_NAMED_STATS]],
+    key: NamedStat,
+    format: &dyn Fn(&[i64; N_NAMED_STATS]) -> String,
+) -> std::io::Result<()> {
+    let names = STAT_NAMES.lock().unwrap();
+    let mut ids: Vec<usize> = (0..names.len())
+        .filter(|id| totals[*id][key as usize] > 0)
+        .collect();
+    if ids.is_empty() {
+        return Ok(());
+    }
+    ids.sort_by(|a, b| totals[*b][key as usize].cmp(&totals[*a][key as usize]));
+    writeln!(dest, "  {}", heading)?;
+    for id in ids.iter().take(20) {
+        writeln!(dest, "    {:<42}{}", names[*id], format(&totals[*id]))?;
+    }
+    if ids.len() > 20 {
+        writeln!(dest, "    ... {} more", ids.len() - 20)?;
+    }
+    Ok(())
+}
+
+/// Declares a thread-local counter, e.g.
+/// `stat_counter!("Integrator/Camera rays traced", N_CAMERA_RAYS);`.
+#[cfg(feature = "stats")]
+#[macro_export]
+macro_rules! stat_counter {
+    ($title:expr, $var:ident) => {
+        thread_local! {
+            static $var: $crate::core::stats::StatCounter =
+                $crate::core::stats::StatCounter::register(
+                    $title,
+                    $crate::core::stats::StatKind::Counter,
+                );
+        }
+    };
+}
+
+/// Declares a thread-local amount of memory in bytes.
+#[cfg(feature = "stats")]
+#[macro_export]
+macro_rules! stat_memory {
+    ($title:expr, $var:ident) => {
+        thread_local! {
+            static $var: $crate::core::stats::StatCounter =
+                $crate::core::stats::StatCounter::register(
+                    $title,
+                    $crate::core::stats::StatKind::Memory,
+                );
+        }
+    };
+}
+
+/// Declares two thread-local counters reported as ratio, e.g.
+/// `stat_ratio!("Intersections/Ray-triangle intersection tests", TRIANGLE_HITS, TRIANGLE_TESTS);`.
+#[cfg(feature = "stats")]
+#[macro_export]
+macro_rules! stat_ratio {
+    ($title:expr, $num:ident, $denom:ident) => {
+        thread_local! {
+            static $num: $crate::core::stats::StatCounter =
+                $crate::core::stats::StatCounter::register(
+                    $title,
+                    $crate::core::stats::StatKind::RatioNumerator,
+                );
+            static $denom: $crate::core::stats::StatCounter =
+                $crate::core::stats::StatCounter::register(
+                    $title,
+                    $crate::core::stats::StatKind::RatioDenominator,
+                );
+        }
+    };
+}
+
+/// Increments a counter declared with one of the stat_* macros.
+#[cfg(feature = "stats")]
+#[macro_export]
+macro_rules! stat_inc {
+    ($var:ident) => {
+        $var.with(|counter| counter.add(1))
+    };
+}
+
+/// Adds a value to a counter declared with one of the stat_* macros.
+#[cfg(feature = "stats")]
+#[macro_export]
+macro_rules! stat_add {
+    ($var:ident, $value:expr) => {
+        $var.with(|counter| counter.add($value as i64))
+    };
+}
+
+// without the stats feature there are no counters
+
+#[cfg(not(feature = "stats"))]
+#[macro_export]
+macro_rules! stat_counter {
+    ($title:expr, $var:ident) => {};
+}
+
+#[cfg(not(feature = "stats"))]
+#[macro_export]
+macro_rules! stat_memory {
+    ($title:expr, $var:ident) => {};
+}
+
+#[cfg(not(feature = "stats"))]
+#[macro_export]
+macro_rules! stat_ratio {
+    ($title:expr, $num:ident, $denom:ident) => {};
+}
+
+#[cfg(not(feature = "stats"))]
+#[macro_export]
+macro_rules! stat_inc {
+    ($var:ident) => {
+        ()
+    };
+}
+
+#[cfg(not(feature = "stats"))]
+#[macro_export]
+macro_rules! stat_add {
+    ($var:ident, $value:expr) => {{
+        // keeps the value used, the optimizer removes it
+        let _ = $value;
+    }};
+}
+
+/// Prints all statistics gathered so far (from all threads), grouped
+/// by the category before the slash in their titles.
+pub fn print_stats(dest: &mut dyn Write) -> std::io::Result<()> {
+    let mut counters: BTreeMap<&'static str, i64> = BTreeMap::new();
+    let mut memory: BTreeMap<&'static str, i64> = BTreeMap::new();
+    let mut ratios: BTreeMap<&'static str, (i64, i64)> = BTreeMap::new();
+    let values: Vec<(&'static str, StatKind, i64)> = {
+        let registry = REGISTRY.lock().unwrap();
+        registry
+            .running
+            .iter()
+            .map(|(title, kind, value)| (*title, *kind, value.load(Ordering::Relaxed)))
+            .chain(
+                registry
+                    .totals
+                    .iter()
+                    .map(|((title, kind), value)| (*title, *kind, *value)),
+            )
+            .collect()
+    };
+    for (title, kind, value) in values {
+        match kind {
+            StatKind::Counter => *counters.entry(title).or_insert(0) += value,
+            StatKind::Memory => *memory.entry(title).or_insert(0) += value,
+            StatKind::RatioNumerator => ratios.entry(title).or_insert((0, 0)).0 += value,
+            StatKind::RatioDenominator => ratios.entry(title).or_insert((0, 0)).1 += value,
+        }
+    }
+    // category -> formatted lines
+    let mut lines: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut add_line = |title: &'static str, text: String| {
+        let (category, name) = match title.find('/') {
+            Some(slash) => (&title[..slash], &title[slash + 1..]),
+            None => ("", title),
+        };
+        lines
+            .entry(category)
+            .or_insert_with(Vec::new)
+            .push(format!("    {:<42}{}", name, text));
+    };
+    for (title, value) in counters {
+        add_line(title, format!("{:12}", value));
+    }
+    for (title, value) in memory {
+        add_line(
+            title,
+            format!("{:12.2} MiB", value as f64 / (1024.0 * 1024.0)),
+        );
+    }
+    for (title, (num, denom)) in ratios {
+        let percent: f64 = if denom > 0 {
+            100.0 * num as f64 / denom as f64
+        } else {
+            0.0
+        };
+        add_line(
+            title,
+            format!("{:12} / {:12} ({:.2}%)", num, denom, percent),
+        );
+    }
+    writeln!(dest, "Statistics:")?;
+    if cfg!(not(feature = "stats")) {
+        writeln!(dest, "  (built without the stats feature, only timings)")?;
+    }
+    for (category, lines) in lines {
+        writeln!(dest, "  {}", category)?;
+        for line in lines {
+            writeln!(dest, "{}", line)?;
+        }
+    }
+    let totals: Vec<[i64; N_NAMED_STATS]> = named_stats();
+    print_named_stats(
+        dest,
+        "Meshes (by ray intersection tests)",
+        &totals,
+        NamedStat::RayTests,
+        &|values| {
+            let tests: i64 = values[NamedStat::RayTests as usize];
+            let hits: i64 = values[NamedStat::RayHits as usize];
+            format!(
+                "{:12} tests {:12} hits ({:.2}%)",
+                tests,
+                hits,
+                100.0 * hits as f64 / tests as f64
+            )
+        },
+    )?;
+    print_named_stats(
+        dest,
+        "Materials (by shading time)",
+        &totals,
+        NamedStat::ShadingNanos,
+        &|values| {
+            format!(
+                "{:12.3} s {:12} BSDF evaluations",
+                values[NamedStat::ShadingNanos as usize] as f64 * 1e-9,
+                values[NamedStat::BsdfEvaluations as usize]
+            )
+        },
+    )?;
+    writeln!(dest, "  Time")?;
+    for (name, nanos) in PROF_NAMES.iter().zip(PHASE_NANOS.iter()) {
+        let seconds: f64 = nanos.load(Ordering::Relaxed) as f64 * 1e-9;
+        writeln!(dest, "    {:<42}{:12.3} s", name, seconds)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! Live preview in the [tev](https://github.com/Tom94/tev) image
+//! viewer. **Film::open_tev()** connects to a running tev instance
+//! (`tev --hostname 127.0.0.1:14158`), creates an image with the
+//! film's resolution, and the render loop sends each merged tile.
+//!
+//! Each packet of tev's TCP protocol starts with its length in bytes
+//! (including the length itself) as little-endian 32-bit integer,
+//! followed by a one byte packet type and the payload. Strings are
+//! zero-terminated.
+
+// std
+use std::io::Write;
+use std::net::{Shutdown, TcpStream};
+use std::sync::{Arc, Mutex};
+// pbrt
+use crate::core::film::{Film, FilmTile};
+use crate::core::geometry::{bnd2_intersect_bnd2, Bounds2i};
+use crate::core::pbrt::Float;
+
+/// Packet types of tev's protocol.
+#[repr(u8)]
+pub enum TevPacket {
+    CreateImage = 4,
+    UpdateImage = 5,
+}
+
+const CHANNELS: [&str; 3] = ["R", "G", "B"];
+
+/// Builds one packet (with the length in front).
+struct PacketWriter {
+    bytes: Vec<u8>,
+}
+
+impl PacketWriter {
+    fn new(packet_type: TevPacket) -> Self {
+        let mut bytes: Vec<u8> = vec![0_u8; 4];
+        bytes.push(packet_type as u8);
+        PacketWriter { bytes }
+    }
+    fn bool(&mut self, value: bool) {
+        self.bytes.push(value as u8);
+    }
+    fn string(&mut self, value: &str) {
+        self.bytes.extend_from_slice(value.as_bytes());
+        self.bytes.push(0_u8);
+    }
+    fn i32(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fn f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fn finish(mut self) -> Vec<u8> {
+        let length: u32 = self.bytes.len() as u32;
+        self.bytes[0..4].copy_from_slice(&length.to_le_bytes());
+        self.bytes
+    }
+}
+
+/// A connection to tev showing one image, see **Film::open_tev()**.
+/// Clones share the connection.
+#[derive(Clone)]
+pub struct TevDisplay {
+    stream: Arc<Mutex<TcpStream>>,
+    name: String,
+    /// The pixel bounds of the film, tev's image starts at _p_min_
+    bounds: Bounds2i,
+}
+
+impl TevDisplay {
+    /// Connects to tev at _host_:_port_ and creates an RGB image
+    /// called _name_ covering _bounds_.
+    pub fn connect(host: &str, port: u16, name: &str, bounds: Bounds2i) -> std::io::Result<Self> {
+        let stream: TcpStream = TcpStream::connect((host, port))?;
+        stream.set_nodelay(true)?;
+        let display = TevDisplay {
+            stream: Arc::new(Mutex::new(stream)),
+            name: String::from(name),
+            bounds,
+        };
+        let mut packet = PacketWriter::new(TevPacket::CreateImage);
+        packet.bool(true);
+        packet.string(&display.name);
+        packet.i32(bounds.p_max.x - bounds.p_min.x);
+        packet.i32(bounds.p_max.y - bounds.p_min.y);
+        packet.i32(CHANNELS.len() as i32);
+        for channel in CHANNELS.iter() {
+            packet.string(channel);
+        }
+        display.send(packet)?;
+        Ok(display)
+    }
+    /// Sends the (merged) pixels of _film_ covered by _tile_.
+    pub fn update_tile(&self, film: &Film, tile: &FilmTile) -> std::io::Result<()> {
+        let bounds: Bounds2i = bnd2_intersect_bnd2(&tile.pixel_bounds, &self.bounds);
+        if bounds.p_max.x <= bounds.p_min.x || bounds.p_max.y <= bounds.p_min.y {
+            return Ok(());
+        }
+        self.update_pixels(&bounds, &film.get_rgb_in(&bounds, 1.0 as Float))
+    }
+    /// Sends the linear RGB values (three per pixel, row by row) of
+    /// the pixels within _bounds_.
+    pub fn update_pixels(&self, bounds: &Bounds2i, rgb: &[Float]) -> std::io::Result<()> {
+        let n_pixels: usize = bounds.area() as usize;
+        assert_eq!(rgb.len(), 3 * n_pixels);
+        let mut packet = PacketWriter::new(TevPacket::UpdateImage);
+        packet.bool(false);
+        packet.string(&self.name);
+        packet.i32(CHANNELS.len() as i32);
+        for channel in CHANNELS.iter() {
+            packet.string(channel);
+        }
+        packet.i32(bounds.p_min.x - self.bounds.p_min.x);
+        packet.i32(bounds.p_min.y - self.bounds.p_min.y);
+        packet.i32(bounds.p_max.x - bounds.p_min.x);
+        packet.i32(bounds.p_max.y - bounds.p_min.y);
+        // one plane per channel
+        for c in 0..CHANNELS.len() {
+            for i in 0..n_pixels {
+                packet.f32(rgb[3 * i + c] as f32);
+            }
+        }
+        self.send(packet)
+    }
+    /// Closes the connection, the image stays open in tev.
+    pub fn close(&self) -> std::io::Result<()> {
+        self.stream.lock().unwrap().shutdown(Shutdown::Both)
+    }
+    fn send(&self, packet: PacketWriter) -> std::io::Result<()> {
+        self.stream.lock().unwrap().write_all(&packet.finish())
+    }
+}
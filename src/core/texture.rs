@@ -10,6 +10,7 @@ use std::f32::consts::PI;
 use crate::core::geometry::{spherical_phi, spherical_theta, vec3_dot_vec3};
 use crate::core::geometry::{Point2f, Point3f, Vector2f, Vector3f};
 use crate::core::interaction::SurfaceInteraction;
+use crate::core::paramset::TextureParams;
 use crate::core::pbrt::Float;
 use crate::core::pbrt::{clamp_t, lerp, log_2};
 use crate::core::pbrt::{INV_2_PI, INV_PI};
@@ -56,6 +57,113 @@ pub enum TextureMapping2D {
 }
 
 impl TextureMapping2D {
+    /// Creates the 2D mapping selected by the "mapping" parameter
+    /// ("uv", "spherical", "cylindrical", or "planar").
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Point2f, Point3f, Vector2f, Vector3f};
+    /// use pbrt::core::interaction::SurfaceInteraction;
+    /// use pbrt::core::paramset::{ParamSet, TextureParams};
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::texture::TextureMapping2D;
+    /// use pbrt::core::transform::Transform;
+    ///
+    /// let mapping = |params: ParamSet, tex_2_world: Transform| -> Box<TextureMapping2D> {
+    ///     let mut tp: TextureParams = TextureParams {
+    ///         geom_params: params,
+    ///         ..Default::default()
+    ///     };
+    ///     TextureMapping2D::create(&tex_2_world, &mut tp)
+    /// };
+    /// let st = |mapping: &TextureMapping2D, p: Point3f, uv: Point2f| -> (Point2f, Vector2f) {
+    ///     let si: SurfaceInteraction = SurfaceInteraction::default();
+    ///     let mut si = SurfaceInteraction { p, uv, ..si };
+    ///     *si.dudx.get_mut().unwrap() = 0.1;
+    ///     let mut dstdx: Vector2f = Vector2f::default();
+    ///     let mut dstdy: Vector2f = Vector2f::default();
+    ///     (mapping.map(&si, &mut dstdx, &mut dstdy), dstdx)
+    /// };
+    /// let near = |a: Point2f, x: Float, y: Float| -> bool {
+    ///     (a.x - x).abs() < 1e-5 && (a.y - y).abs() < 1e-5
+    /// };
+    /// let origin: Point3f = Point3f::default();
+    /// // uv: scaled and offset, the derivatives are scaled as well
+    /// let mut params: ParamSet = ParamSet::default();
+    /// params.add_float(String::from("uscale"), 2.0);
+    /// params.add_float(String::from("vscale"), 3.0);
+    /// params.add_float(String::from("udelta"), 0.5);
+    /// let uv = mapping(params, Transform::default());
+    /// let (p, dstdx) = st(&uv, origin, Point2f { x: 0.25, y: 0.5 });
+    /// assert!(near(p, 1.0, 1.5));
+    /// assert!((dstdx.x - 0.2).abs() < 1e-6);
+    /// // cylindrical: the angle around z maps to [0, 1], z is kept
+    /// let mut params: ParamSet = ParamSet::default();
+    /// params.add_string(String::from("mapping"), String::from("cylindrical"));
+    /// let cylindrical = mapping(params, Transform::default());
+    /// let at = |x: Float, y: Float| -> Point2f {
+    ///     st(&cylindrical, Point3f { x, y, z: 0.0 }, Point2f::default()).0
+    /// };
+    /// assert!(near(at(1.0, 0.0), 0.5, 0.0));
+    /// assert!(near(at(0.0, 1.0), 0.75, 0.0));
+    /// assert!(near(at(0.0, -1.0), 0.25, 0.0));
+    /// // spherical: relative to the texture space, here moved up by 5
+    /// let mut params: ParamSet = ParamSet::default();
+    /// params.add_string(String::from("mapping"), String::from("spherical"));
+    /// let up: Transform = Transform::translate(&Vector3f { x: 0.0, y: 0.0, z: 5.0 });
+    /// let spherical = mapping(params, up);
+    /// let equator: Point3f = Point3f { x: 0.0, y: 1.0, z: 5.0 };
+    /// assert!(near(st(&spherical, equator, Point2f::default()).0, 0.5, 0.25));
+    /// // planar: projections onto v1 and v2
+    /// let mut params: ParamSet = ParamSet::default();
+    /// params.add_string(String::from("mapping"), String::from("planar"));
+    /// params.add_vector3f(String::from("v1"), Vector3f { x: 0.0, y: 0.0, z: 2.0 });
+    /// params.add_vector3f(String::from("v2"), Vector3f { x: 1.0, y: 0.0, z: 0.0 });
+    /// params.add_float(String::from("vdelta"), 0.5);
+    /// let planar = mapping(params, Transform::default());
+    /// let p: Point3f = Point3f { x: 3.0, y: 7.0, z: 0.25 };
+    /// assert!(near(st(&planar, p, Point2f::default()).0, 0.5, 3.5));
+    /// ```
+    pub fn create(tex_2_world: &Transform, tp: &mut TextureParams) -> Box<TextureMapping2D> {
+        let mapping: String = tp.find_string("mapping", String::from("uv"));
+        if mapping == "uv" {
+            let su: Float = tp.find_float("uscale", 1.0);
+            let sv: Float = tp.find_float("vscale", 1.0);
+            let du: Float = tp.find_float("udelta", 0.0);
+            let dv: Float = tp.find_float("vdelta", 0.0);
+            Box::new(TextureMapping2D::UV(UVMapping2D::new(su, sv, du, dv)))
+        } else if mapping == "spherical" {
+            Box::new(TextureMapping2D::Spherical(SphericalMapping2D::new(
+                Transform::inverse(tex_2_world),
+            )))
+        } else if mapping == "cylindrical" {
+            Box::new(TextureMapping2D::Cylindrical(CylindricalMapping2D::new(
+                Transform::inverse(tex_2_world),
+            )))
+        } else if mapping == "planar" {
+            Box::new(TextureMapping2D::Planar(PlanarMapping2D {
+                vs: tp.find_vector3f(
+                    "v1",
+                    Vector3f {
+                        x: 1.0,
+                        y: 0.0,
+                        z: 0.0,
+                    },
+                ),
+                vt: tp.find_vector3f(
+                    "v2",
+                    Vector3f {
+                        x: 0.0,
+                        y: 1.0,
+                        z: 0.0,
+                    },
+                ),
+                ds: tp.find_float("udelta", 0.0),
+                dt: tp.find_float("vdelta", 0.0),
+            }))
+        } else {
+            panic!("2D texture mapping \"{}\" unknown", mapping);
+        }
+    }
     pub fn map(
         &self,
         si: &SurfaceInteraction,
@@ -99,6 +207,9 @@ pub struct UVMapping2D {
 }
 
 impl UVMapping2D {
+    pub fn new(su: Float, sv: Float, du: Float, dv: Float) -> Self {
+        UVMapping2D { su, sv, du, dv }
+    }
     pub fn map(
         &self,
         si: &SurfaceInteraction,
@@ -194,7 +305,7 @@ impl CylindricalMapping2D {
         let vec3f: Vector3f =
             (self.world_to_texture.transform_point(p) - Point3f::default()).normalize();
         Point2f {
-            x: PI + vec3f.y.atan2(vec3f.x) * INV_2_PI,
+            x: (PI + vec3f.y.atan2(vec3f.x)) * INV_2_PI,
             y: vec3f.z,
         }
     }
@@ -73,6 +73,7 @@ use crate::core::quaternion::{quat_dot_quat, quat_normalize, quat_slerp};
 // see transform.h
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Matrix4x4 {
     pub m: [[Float; 4]; 4],
 }
@@ -266,6 +267,7 @@ pub fn mtx_mul(m1: &Matrix4x4, m2: &Matrix4x4) -> Matrix4x4 {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Transform {
     pub m: Matrix4x4,
     pub m_inv: Matrix4x4,
@@ -314,6 +316,61 @@ impl Transform {
             m_inv: t.m,
         }
     }
+    /// Decomposes the transformation into a translation _t_, a
+    /// rotation _r_, and the remaining scale (and shear) _s_, so that
+    /// it equals translate(t) * r * s (see **AnimatedTransform**, which
+    /// interpolates the three components).
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::Vector3f;
+    /// use pbrt::core::transform::{AnimatedTransform, Matrix4x4, Transform};
+    ///
+    /// let transforms: [Transform; 2] = [
+    ///     Transform::translate(&Vector3f { x: 1.0, y: -2.0, z: 3.0 })
+    ///         * Transform::rotate(35.0, &Vector3f { x: 1.0, y: 2.0, z: 0.5 })
+    ///         * Transform::scale(0.5, 2.0, 3.0),
+    ///     // mirrored
+    ///     Transform::rotate(-70.0, &Vector3f { x: 0.0, y: 1.0, z: 0.0 })
+    ///         * Transform::scale(-1.0, 1.0, 1.0),
+    /// ];
+    /// for transform in transforms.iter() {
+    ///     let (t, r, s) = transform.decompose();
+    ///     let recomposed: Transform = Transform::translate(&t)
+    ///         * r.to_transform()
+    ///         * Transform {
+    ///             m: s,
+    ///             m_inv: Matrix4x4::inverse(&s),
+    ///         };
+    ///     for i in 0..4 {
+    ///         for j in 0..4 {
+    ///             assert!((recomposed.m.m[i][j] - transform.m.m[i][j]).abs() < 1e-4);
+    ///         }
+    ///     }
+    /// }
+    /// // half way between two rotations (and scales)
+    /// let axis = Vector3f { x: 0.0, y: 1.0, z: 0.0 };
+    /// let animated = AnimatedTransform::new(
+    ///     &Transform::scale(1.0, 1.0, 1.0),
+    ///     0.0,
+    ///     &(Transform::rotate(90.0, &axis) * Transform::scale(3.0, 3.0, 3.0)),
+    ///     1.0,
+    /// );
+    /// let mut halfway: Transform = Transform::default();
+    /// animated.interpolate(0.5, &mut halfway);
+    /// let expected: Transform = Transform::rotate(45.0, &axis) * Transform::scale(2.0, 2.0, 2.0);
+    /// for i in 0..4 {
+    ///     for j in 0..4 {
+    ///         assert!((halfway.m.m[i][j] - expected.m.m[i][j]).abs() < 1e-4);
+    ///     }
+    /// }
+    /// ```
+    pub fn decompose(&self) -> (Vector3f, Quaternion, Matrix4x4) {
+        let mut t: Vector3f = Vector3f::default();
+        let mut r: Quaternion = Quaternion::default();
+        let mut s: Matrix4x4 = Matrix4x4::default();
+        AnimatedTransform::decompose(&self.m, &mut t, &mut r, &mut s);
+        (t, r, s)
+    }
     pub fn is_identity(&self) -> bool {
         self.m.m[0][0] == 1.0 as Float
             && self.m.m[0][1] == 0.0 as Float
@@ -437,7 +494,57 @@ impl Transform {
             m_inv: Matrix4x4::transpose(&m),
         }
     }
+    /// The world-to-camera transformation for a camera at _pos_
+    /// looking at _look_ (like the **LookAt** directive). The camera
+    /// looks down its +z axis, with +y pointing towards _up_ and +x to
+    /// the left (pbrt uses a left-handed coordinate system). Its
+    /// inverse (see **m_inv**) is the camera-to-world transformation.
+    /// If the viewing direction is undefined the identity is returned.
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{vec3_dot_vec3, Point3f, Vector3f};
+    /// use pbrt::core::transform::Transform;
+    ///
+    /// let up = Vector3f { x: 0.0, y: 1.0, z: 0.0 };
+    /// let world_to_camera = Transform::look_at(
+    ///     &Point3f { x: 0.0, y: 0.0, z: 0.0 },
+    ///     &Point3f { x: 0.0, y: 0.0, z: 1.0 },
+    ///     &up,
+    /// );
+    /// assert_eq!(world_to_camera, Transform::default());
+    /// // from somewhere else, the camera's z axis points to _look_
+    /// let pos = Point3f { x: 1.0, y: 2.0, z: -3.0 };
+    /// let look = Point3f { x: -2.0, y: 0.5, z: 4.0 };
+    /// let camera_to_world = Transform::inverse(&Transform::look_at(&pos, &look, &up));
+    /// let axes: Vec<Vector3f> = [
+    ///     Vector3f { x: 1.0, y: 0.0, z: 0.0 },
+    ///     Vector3f { x: 0.0, y: 1.0, z: 0.0 },
+    ///     Vector3f { x: 0.0, y: 0.0, z: 1.0 },
+    /// ]
+    /// .iter()
+    /// .map(|v| camera_to_world.transform_vector(v))
+    /// .collect();
+    /// let forward: Vector3f = (look - pos).normalize();
+    /// assert!(vec3_dot_vec3(&axes[2], &forward) > 1.0 - 1e-5);
+    /// assert!(vec3_dot_vec3(&axes[1], &up) > 0.0);
+    /// for i in 0..3 {
+    ///     for j in 0..3 {
+    ///         let expected = if i == j { 1.0 } else { 0.0 };
+    ///         assert!((vec3_dot_vec3(&axes[i], &axes[j]) - expected).abs() < 1e-5);
+    ///     }
+    /// }
+    /// let origin = camera_to_world.transform_point(&Point3f::default());
+    /// assert!((origin - pos).length() < 1e-5);
+    /// ```
     pub fn look_at(pos: &Point3f, look: &Point3f, up: &Vector3f) -> Transform {
+        if (*look - *pos).length_squared() == 0.0 as Float {
+            println!(
+                "Position ({}, {}, {}) and look-at point passed to LookAt are the \
+                 same.  Using the identity transformation.",
+                pos.x, pos.y, pos.z
+            );
+            return Transform::default();
+        }
         let mut camera_to_world = Matrix4x4::default();
         // initialize fourth column of viewing matrix
         camera_to_world.m[0][3] = pos.x;
@@ -848,7 +955,10 @@ impl Transform {
             medium: r.medium.clone(),
         }
     }
-    pub fn transform_surface_interaction(&self, si: &SurfaceInteraction) -> SurfaceInteraction {
+    pub fn transform_surface_interaction<'a>(
+        &self,
+        si: &SurfaceInteraction<'a>,
+    ) -> SurfaceInteraction<'a> {
         let mut ret: SurfaceInteraction = SurfaceInteraction::default();
         // transform _p_ and _pError_ in _SurfaceInteraction_
         ret.p = self.transform_point_with_abs_error(&si.p, &si.p_error, &mut ret.p_error);
@@ -891,6 +1001,8 @@ impl Transform {
         // ret.bssrdf = si.bssrdf.clone();
         ret.primitive = None; // TODO? si.primitive;
         ret.shading.n = nrm_faceforward_nrm(&ret.shading.n, &ret.n);
+        ret.clamp_shading_normals = si.clamp_shading_normals;
+        ret.check_geometric_normal = si.check_geometric_normal;
         // TODO: ret.faceIndex = si.faceIndex;
         ret
     }
@@ -947,6 +1059,16 @@ pub struct AnimatedTransform {
     c5: [DerivativeTerm; 3],
 }
 
+impl PartialEq for AnimatedTransform {
+    fn eq(&self, rhs: &AnimatedTransform) -> bool {
+        // everything else is derived from the two keyframes
+        self.start_transform == rhs.start_transform
+            && self.end_transform == rhs.end_transform
+            && self.start_time == rhs.start_time
+            && self.end_time == rhs.end_time
+    }
+}
+
 impl AnimatedTransform {
     pub fn new(
         start_transform: &Transform,
@@ -2105,7 +2227,18 @@ impl AnimatedTransform {
                 break;
             }
         }
-        // XXX TODO FIXME deal with flip...
+        // a reflection can't be represented by a quaternion, leave it
+        // in the scale matrix (R S = (-R) (-S))
+        let det: Float = r.m[0][0] * (r.m[1][1] * r.m[2][2] - r.m[1][2] * r.m[2][1])
+            - r.m[0][1] * (r.m[1][0] * r.m[2][2] - r.m[1][2] * r.m[2][0])
+            + r.m[0][2] * (r.m[1][0] * r.m[2][1] - r.m[1][1] * r.m[2][0]);
+        if det < 0.0 as Float {
+            for i in 0..3 {
+                for j in 0..3 {
+                    r.m[i][j] = -r.m[i][j];
+                }
+            }
+        }
         let transform: Transform = Transform {
             m: r.clone(),
             m_inv: Matrix4x4::inverse(&r.clone()),
@@ -2113,8 +2246,48 @@ impl AnimatedTransform {
         *rquat = Quaternion::new(transform);
 
         // compute scale _S_ using rotation and original matrix
-        *s = mtx_mul(&Matrix4x4::inverse(&r), &*m);
+        // (without translation)
+        *s = mtx_mul(&Matrix4x4::inverse(&r), &matrix);
     }
+    /// Computes the transformation at _time_ from the decompositions
+    /// of both keyframes: translation and scale are interpolated
+    /// linearly, the rotation with a quaternion slerp, so a rotating
+    /// object doesn't get skewed on the way:
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::Vector3f;
+    /// use pbrt::core::pbrt::{lerp, Float};
+    /// use pbrt::core::transform::{AnimatedTransform, Transform};
+    ///
+    /// let axis = Vector3f { x: 1.0, y: 2.0, z: -0.5 };
+    /// let t0 = Vector3f { x: 1.0, y: 0.0, z: 0.0 };
+    /// let t1 = Vector3f { x: -2.0, y: 3.0, z: 1.0 };
+    /// let compose = |t: &Vector3f, angle: Float, scale: Float| {
+    ///     Transform::translate(t)
+    ///         * Transform::rotate(angle, &axis)
+    ///         * Transform::scale(scale, 2.0 * scale, 1.0)
+    /// };
+    /// let animated = AnimatedTransform::new(
+    ///     &compose(&t0, 10.0, 1.0),
+    ///     2.0,
+    ///     &compose(&t1, 130.0, 3.0),
+    ///     4.0,
+    /// );
+    /// for dt in [0.0, 0.25, 0.5, 0.75, 1.0].iter() {
+    ///     let mut t: Transform = Transform::default();
+    ///     animated.interpolate(lerp(*dt, 2.0, 4.0), &mut t);
+    ///     let expected: Transform = compose(
+    ///         &(t0 * (1.0 - dt) + t1 * *dt),
+    ///         lerp(*dt, 10.0, 130.0),
+    ///         lerp(*dt, 1.0, 3.0),
+    ///     );
+    ///     for i in 0..4 {
+    ///         for j in 0..4 {
+    ///             assert!((t.m.m[i][j] - expected.m.m[i][j]).abs() < 1e-4);
+    ///         }
+    ///     }
+    /// }
+    /// ```
     pub fn interpolate(&self, time: Float, t: &mut Transform) {
         // handle boundary conditions for matrix interpolation
         if !self.actually_animated || time <= self.start_time {
@@ -2181,6 +2354,46 @@ impl AnimatedTransform {
             t.transform_vector(v)
         }
     }
+    /// Bounds _b_ over the whole time range. Without rotation the
+    /// corners move on straight lines, so the bounds at both
+    /// keyframes suffice. Otherwise **bound_point_motion()** bounds
+    /// the trajectory of each corner, adding the points where the
+    /// derivative of a coordinate vanishes:
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{bnd3_union_pnt3, Bounds3f, Point3f, Vector3f};
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::transform::{AnimatedTransform, Transform};
+    ///
+    /// let axis = Vector3f { x: 0.3, y: 1.0, z: 0.2 };
+    /// let animated = AnimatedTransform::new(
+    ///     &Transform::translate(&Vector3f { x: 2.0, y: 0.0, z: 0.0 }),
+    ///     0.0,
+    ///     &(Transform::rotate(160.0, &axis) * Transform::scale(1.5, 1.0, 0.5)),
+    ///     1.0,
+    /// );
+    /// let b = Bounds3f::new(
+    ///     Point3f { x: -1.0, y: -0.5, z: 0.0 },
+    ///     Point3f { x: 1.0, y: 0.5, z: 2.0 },
+    /// );
+    /// let bounds: Bounds3f = animated.motion_bounds(&b);
+    /// let mut sampled: Bounds3f = Bounds3f::default();
+    /// for i in 0..=1000 {
+    ///     let time: Float = i as Float / 1000.0;
+    ///     for corner in 0..8_u8 {
+    ///         let p: Point3f = animated.transform_point(time, &b.corner(corner));
+    ///         sampled = bnd3_union_pnt3(&sampled, &p);
+    ///     }
+    /// }
+    /// for i in 0..3_u8 {
+    ///     // contains all trajectories ...
+    ///     assert!(bounds.p_min[i] <= sampled.p_min[i] + 1e-4);
+    ///     assert!(bounds.p_max[i] >= sampled.p_max[i] - 1e-4);
+    ///     // ... and isn't much larger
+    ///     assert!(bounds.p_min[i] > sampled.p_min[i] - 1e-3);
+    ///     assert!(bounds.p_max[i] < sampled.p_max[i] + 1e-3);
+    /// }
+    /// ```
     pub fn motion_bounds(&self, b: &Bounds3f) -> Bounds3f {
         if !self.actually_animated {
             return self.start_transform.transform_bounds(b);
@@ -2230,7 +2443,7 @@ impl AnimatedTransform {
                 8_usize,
             );
             // expand bounding box for any motion derivative zeros found
-            for i in 0..zeros.len() {
+            for i in 0..n_zeros as usize {
                 let pz: Point3f =
                     self.transform_point(lerp(zeros[i], self.start_time, self.end_time), p);
                 bounds = bnd3_union_pnt3(&bounds, &pz);
@@ -2380,7 +2593,11 @@ pub fn interval_find_zeros(
             }
             t_newton = t_newton - f_newton / f_prime_newton;
         }
-        if t_newton >= t_interval.low - 1e-3 as Float && t_newton < t_interval.high + 1e-3 as Float
+        // neighboring intervals may report the same zero twice, don't
+        // write past the end of _zeros_
+        if t_newton >= t_interval.low - 1e-3 as Float
+            && t_newton < t_interval.high + 1e-3 as Float
+            && (*zero_count as usize) < zeros.len()
         {
             zeros[*zero_count as usize] = t_newton;
             *zero_count += 1;
@@ -46,7 +46,7 @@
 //! ```rust
 //! use pbrt::core::pbrt::Float;
 //! use pbrt::filters::gaussian::GaussianFilter;
-//! use pbrt::core::geometry::Vector2f;
+//! use pbrt::core::geometry::{Point2f, Vector2f};
 //!
 //! fn main() {
 //!     let xw: Float = 2.0;
@@ -66,13 +66,18 @@
 //!     };
 //!
 //!     println!("gaussian_filter = {:?}", gaussian_filter);
+//!     // peak at the center, falls off to zero at the radius
+//!     let g0: Float = 1.0 - exp_x;
+//!     let center: Float = gaussian_filter.evaluate(Point2f { x: 0.0, y: 0.0 });
+//!     assert!((center - g0 * g0).abs() < 1e-5);
+//!     assert_eq!(gaussian_filter.evaluate(Point2f { x: xw, y: 0.0 }), 0.0);
 //! }
 //! ```
 //!
 //! ## MitchellFilter
 //!
 //! ```rust
-//! use pbrt::core::geometry::Vector2f;
+//! use pbrt::core::geometry::Point2f;
 //! use pbrt::core::pbrt::Float;
 //! use pbrt::filters::mitchell::MitchellNetravali;
 //!
@@ -84,13 +89,21 @@
 //!     let mitchell_filter = MitchellNetravali::new(xwidth, ywidth, b, c);
 //!
 //!     println!("mitchell_filter = {:?}", mitchell_filter);
+//!     // (6 - 2B) / 6 in each dimension at the center, zero at the radius
+//!     let m0: Float = (6.0 - 2.0 * b) / 6.0;
+//!     let center: Float = mitchell_filter.evaluate(Point2f { x: 0.0, y: 0.0 });
+//!     assert!((center - m0 * m0).abs() < 1e-5);
+//!     let border: Float = mitchell_filter.evaluate(Point2f { x: xwidth, y: 0.0 });
+//!     assert!(border.abs() < 1e-5);
+//!     // negative lobe
+//!     assert!(mitchell_filter.evaluate(Point2f { x: 1.5, y: 0.0 }) < 0.0);
 //! }
 //! ```
 //!
 //! ## LanczosSincFilter
 //!
 //! ```rust
-//! use pbrt::core::geometry::Vector2f;
+//! use pbrt::core::geometry::{Point2f, Vector2f};
 //! use pbrt::core::pbrt::Float;
 //! use pbrt::filters::sinc::LanczosSincFilter;
 //!
@@ -102,6 +115,9 @@
 //!     let sinc_filter = LanczosSincFilter::new(&radius, tau);
 //!
 //!     println!("sinc_filter = {:?}", sinc_filter);
+//!     assert_eq!(sinc_filter.evaluate(Point2f { x: 0.0, y: 0.0 }), 1.0);
+//!     assert!(sinc_filter.evaluate(Point2f { x: xw, y: 0.0 }).abs() < 1e-5);
+//!     assert_eq!(sinc_filter.evaluate(Point2f { x: xw + 0.1, y: 0.0 }), 0.0);
 //! }
 //! ```
 //!
@@ -4,6 +4,7 @@ use std::sync::Arc;
 use crate::core::camera::Camera;
 use crate::core::geometry::{nrm_cross_vec3, nrm_faceforward_vec3, vec3_dot_nrm};
 use crate::core::geometry::{Bounds2i, Normal3f, Point2f, Ray, Vector3f};
+use crate::core::integrator::SamplerRender;
 use crate::core::interaction::Interaction;
 use crate::core::material::TransportMode;
 use crate::core::pbrt::{Float, Spectrum};
@@ -35,6 +36,14 @@ impl AOIntegrator {
         sampler: Box<Sampler>,
         pixel_bounds: Bounds2i,
     ) -> Self {
+        let ns: i32 = n_samples;
+        let n_samples: i32 = sampler.round_count(ns);
+        if ns != n_samples {
+            println!(
+                "WARNING: Taking {} samples, not {} as specified",
+                n_samples, ns
+            );
+        }
         AOIntegrator {
             camera,
             sampler,
@@ -43,10 +52,13 @@ impl AOIntegrator {
             n_samples,
         }
     }
-    pub fn preprocess(&mut self, _scene: &Scene) {
+}
+
+impl SamplerRender for AOIntegrator {
+    fn preprocess(&mut self, _scene: &Scene) {
         self.sampler.request_2d_array(self.n_samples);
     }
-    pub fn li(
+    fn li(
         &self,
         r: &mut Ray,
         scene: &Scene,
@@ -105,13 +117,13 @@ impl AOIntegrator {
         }
         l
     }
-    pub fn get_camera(&self) -> Arc<Camera> {
+    fn get_camera(&self) -> Arc<Camera> {
         self.camera.clone()
     }
-    pub fn get_sampler(&self) -> &Box<Sampler> {
+    fn get_sampler(&self) -> &Box<Sampler> {
         &self.sampler
     }
-    pub fn get_pixel_bounds(&self) -> Bounds2i {
+    fn get_pixel_bounds(&self) -> Bounds2i {
         self.pixel_bounds
     }
 }
@@ -8,8 +8,9 @@ use crate::core::geometry::{
     nrm_abs_dot_vec3, pnt2_inside_exclusive, pnt3_offset_ray_origin, vec3_abs_dot_nrm, vec3_dot_nrm,
 };
 use crate::core::geometry::{
-    Bounds2i, Bounds3f, Normal3f, Point2f, Point2i, Point3f, Ray, Vector2i, Vector3f,
+    Bounds2i, Normal3f, Point2f, Point2i, Point3f, Ray, Vector2i, Vector3f,
 };
+use crate::core::integrator::Render;
 use crate::core::interaction::{
     Interaction, InteractionCommon, MediumInteraction, SurfaceInteraction,
 };
@@ -19,6 +20,7 @@ use crate::core::lightdistrib::create_light_sample_distribution;
 use crate::core::material::TransportMode;
 use crate::core::medium::{HenyeyGreenstein, Medium, MediumInterface};
 use crate::core::pbrt::{Float, Spectrum};
+use crate::core::progressreporter::{CancellationToken, RenderProgress};
 use crate::core::reflection::Bsdf;
 use crate::core::reflection::BxdfType;
 use crate::core::sampler::Sampler;
@@ -633,9 +635,7 @@ impl<'a> Vertex<'a> {
         let mut pdf: Float = 0.0;
         if self.is_infinite_light() {
             // compute planar sampling density for infinite light sources
-            let mut world_center: Point3f = Point3f::default();
-            let mut world_radius: Float = 0.0;
-            Bounds3f::bounding_sphere(&scene.world_bound(), &mut world_center, &mut world_radius);
+            let (_world_center, world_radius) = scene.bounding_sphere();
             pdf = 1.0 as Float / (PI * world_radius * world_radius);
         } else {
             assert!(self.is_light());
@@ -825,7 +825,22 @@ impl BDPTIntegrator {
     pub fn get_light_sample_strategy(&self) -> String {
         self.light_sample_strategy.clone()
     }
-    pub fn render(&self, scene: &Scene, num_threads: u8) {
+    pub fn get_camera(&self) -> Arc<Camera> {
+        self.camera.clone()
+    }
+    pub fn get_sampler(&self) -> &Box<Sampler> {
+        &self.sampler
+    }
+}
+
+impl Render for BDPTIntegrator {
+    fn render(
+        &mut self,
+        scene: &Scene,
+        num_threads: u8,
+        progress: &dyn RenderProgress,
+        cancel: &CancellationToken,
+    ) {
         // TODO
         // Compute a reverse mapping from light pointers to offsets into
         // the scene lights vector (and, equivalently, offsets into
@@ -840,7 +855,7 @@ impl BDPTIntegrator {
         let film = self.get_camera().get_film();
         let sample_bounds: Bounds2i = film.get_sample_bounds();
         let sample_extent: Vector2i = sample_bounds.diagonal();
-        let tile_size: i32 = 16;
+        let tile_size: i32 = film.tile_size;
         let n_x_tiles: i32 = (sample_extent.x + tile_size - 1) / tile_size;
         let n_y_tiles: i32 = (sample_extent.y + tile_size - 1) / tile_size;
         // TODO: ProgressReporter reporter(nXTiles * nYTiles, "Rendering");
@@ -877,7 +892,10 @@ impl BDPTIntegrator {
                     for _ in 0..num_cores {
                         let pixel_tx = pixel_tx.clone();
                         scope.spawn(move |_| {
-                            while let Some((x, y)) = bq.next() {
+                            // stop picking up tiles once cancelled
+                            while let Some((index, (x, y))) =
+                                bq.next_indexed().filter(|_| !cancel.is_cancelled())
+                            {
                                 let tile: Point2i = Point2i {
                                     x: x as i32,
                                     y: y as i32,
@@ -1016,7 +1034,8 @@ impl BDPTIntegrator {
                                                         l += lpath;
                                                     } else {
                                                         if !lpath.is_black() {
-                                                            film.add_splat(&p_film_new, &lpath);
+                                                            film_tile
+                                                                .add_splat(&p_film_new, &lpath);
                                                         }
                                                     }
                                                 }
@@ -1034,32 +1053,30 @@ impl BDPTIntegrator {
                                 }
                                 // send the tile through the channel to main thread
                                 pixel_tx
-                                    .send(film_tile)
+                                    .send((index, film_tile))
                                     .expect(&format!("Failed to send tile"));
                             }
                         });
                     }
+                    // the workers hold the remaining senders
+                    drop(pixel_tx);
                     // spawn thread to collect pixels and render image to file
                     scope.spawn(move |_| {
-                        for _ in pbr::PbIter::new(0..bq.len()) {
-                            let film_tile = pixel_rx.recv().unwrap();
-                            // merge image tile into _Film_
-                            film.merge_film_tile(&film_tile);
-                        }
+                        // merge image tiles into _Film_ in queue order
+                        film.merge_film_tiles_in_order(pixel_rx.iter(), cancel, &mut |completed| {
+                            progress.on_tile_done(completed, bq.len())
+                        });
                     });
                 })
                 .unwrap();
             }
+            if cancel.is_cancelled() {
+                println!("Rendering cancelled, writing partial image ...");
+            }
             film.write_image(1.0 as Float / samples_per_pixel as Float);
             // TODO: Write buffers for debug visualization
         }
     }
-    pub fn get_camera(&self) -> Arc<Camera> {
-        self.camera.clone()
-    }
-    pub fn get_sampler(&self) -> &Box<Sampler> {
-        &self.sampler
-    }
 }
 
 // BDPT Utility Functions
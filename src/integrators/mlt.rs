@@ -1,15 +1,15 @@
 // std
 use std::ops::DerefMut;
 use std::sync::Arc;
-use std::thread;
 // pbrt
 use crate::core::camera::Camera;
 use crate::core::film::Film;
 use crate::core::geometry::{Bounds2f, Bounds2i, Point2f, Point2i};
-use crate::core::integrator::compute_light_power_distribution;
+use crate::core::integrator::{compute_light_power_distribution, Render};
 use crate::core::pbrt::erf_inv;
 use crate::core::pbrt::SQRT_2;
 use crate::core::pbrt::{Float, Spectrum};
+use crate::core::progressreporter::{CancellationToken, RenderProgress};
 use crate::core::rng::Rng;
 use crate::core::sampler::Sampler;
 use crate::core::sampling::Distribution1D;
@@ -436,7 +436,19 @@ impl MLTIntegrator {
             None,
         ) * (n_strategies as Float)
     }
-    pub fn render(&self, scene: &Scene, num_threads: u8) {
+    pub fn get_camera(&self) -> Arc<Camera> {
+        self.camera.clone()
+    }
+}
+
+impl Render for MLTIntegrator {
+    fn render(
+        &mut self,
+        scene: &Scene,
+        num_threads: u8,
+        progress: &dyn RenderProgress,
+        cancel: &CancellationToken,
+    ) {
         let num_cores: usize;
         if num_threads == 0_u8 {
             num_cores = num_cpus::get();
@@ -496,7 +508,7 @@ impl MLTIntegrator {
                 }
             }
             let bootstrap: Distribution1D = Distribution1D::new(bootstrap_weights);
-            let b: Float = bootstrap.func_int * (self.max_depth + 1) as Float;
+            let mut b: Float = bootstrap.func_int * (self.max_depth + 1) as Float;
             // run _n_chains_ Markov chains in parallel
             let film: Arc<Film> = self.get_camera().get_film();
             let n_total_mutations: u64 =
@@ -505,88 +517,108 @@ impl MLTIntegrator {
                 // TODO: let progress_frequency = 32768;
                 // TODO: ProgressReporter progress(nTotalMutations / progressFrequency,
                 //                           "Rendering");
-                // use parallel iterator (par_iter_with) from rayon crate
-                let (sender, receiver) = crossbeam_channel::bounded(num_cores);
                 let n_chains = self.n_chains;
-                // spawn thread to report progress
-                let finish = thread::spawn(move || {
-                    for _ in pbr::PbIter::new(0..n_chains) {
-                        receiver.recv().unwrap();
-                    }
-                });
-                // for i in 0..n_chains {
-                let ivec: Vec<u32> = (0..n_chains).collect();
-                ivec.par_iter().for_each_with(sender, |s, &i| {
-                    s.send(i).expect(&format!("Failed to send chain"));
-                    let n_chain_mutations: u64 = ((i as u64 + 1) * n_total_mutations
-                        / n_chains as u64)
-                        .min(n_total_mutations)
-                        - i as u64 * n_total_mutations / n_chains as u64;
-                    // select initial state from the set of bootstrap samples
-                    let mut rng: Rng = Rng::default();
-                    rng.set_sequence(i as u64);
-                    let bootstrap_index: usize =
-                        bootstrap.sample_discrete(rng.uniform_float(), None);
-                    let depth: u32 = bootstrap_index as u32 % (self.max_depth as u32 + 1);
-                    // initialize local variables for selected state
-                    let mut sampler: Box<Sampler> = Box::new(Sampler::MLT(MLTSampler::new(
-                        self.mutations_per_pixel as i64,
-                        bootstrap_index as u64,
-                        self.sigma,
-                        self.large_step_probability,
-                        N_SAMPLE_STREAMS as i32,
-                    )));
-                    let mut p_current: Point2f = Point2f::default();
-                    let mut l_current: Spectrum =
-                        self.l(scene, &light_distr, &mut sampler, depth, &mut p_current);
-                    // run the Markov chain for _n_chain_mutations_ steps
-                    for _j in 0..n_chain_mutations {
-                        match sampler.deref_mut() {
-                            Sampler::MLT(mlt_sampler) => mlt_sampler.start_iteration(),
-                            _ => panic!("MLTSampler needed."),
+                let mut n_completed: u32 = n_chains;
+                crossbeam::scope(|scope| {
+                    let (sender, receiver) = crossbeam_channel::bounded(num_cores);
+                    // spawn thread to report progress
+                    let finish = scope.spawn(move |_| {
+                        let mut completed: usize = 0;
+                        for _ in receiver.iter() {
+                            completed += 1;
+                            progress.on_tile_done(completed, n_chains as usize);
                         }
-                        let mut p_proposed: Point2f = Point2f::default();
-                        let l_proposed: Spectrum =
-                            self.l(scene, &light_distr, &mut sampler, depth, &mut p_proposed);
-                        // compute acceptance probability for proposed sample
-                        let accept: Float = (1.0 as Float).min(l_proposed.y() / l_current.y());
-                        // splat both current and proposed samples to _film_
-                        if accept > 0.0 as Float {
-                            film.add_splat(&p_proposed, &(l_proposed * accept / l_proposed.y()));
+                        completed
+                    });
+                    // use parallel iterator (par_iter_with) from rayon crate
+                    let ivec: Vec<u32> = (0..n_chains).collect();
+                    ivec.par_iter().for_each_with(sender, |s, &i| {
+                        // cancellation is checked before each chain
+                        if cancel.is_cancelled() {
+                            return;
                         }
-                        film.add_splat(
-                            &p_current,
-                            &(l_current * (1.0 as Float - accept) / l_current.y()),
-                        );
-                        // accept or reject the proposal
-                        if rng.uniform_float() < accept {
-                            p_current = p_proposed;
-                            l_current = l_proposed;
+                        let n_chain_mutations: u64 = ((i as u64 + 1) * n_total_mutations
+                            / n_chains as u64)
+                            .min(n_total_mutations)
+                            - i as u64 * n_total_mutations / n_chains as u64;
+                        // select initial state from the set of bootstrap samples
+                        let mut rng: Rng = Rng::default();
+                        rng.set_sequence(i as u64);
+                        let bootstrap_index: usize =
+                            bootstrap.sample_discrete(rng.uniform_float(), None);
+                        let depth: u32 = bootstrap_index as u32 % (self.max_depth as u32 + 1);
+                        // initialize local variables for selected state
+                        let mut sampler: Box<Sampler> = Box::new(Sampler::MLT(MLTSampler::new(
+                            self.mutations_per_pixel as i64,
+                            bootstrap_index as u64,
+                            self.sigma,
+                            self.large_step_probability,
+                            N_SAMPLE_STREAMS as i32,
+                        )));
+                        let mut p_current: Point2f = Point2f::default();
+                        let mut l_current: Spectrum =
+                            self.l(scene, &light_distr, &mut sampler, depth, &mut p_current);
+                        // run the Markov chain for _n_chain_mutations_ steps
+                        for _j in 0..n_chain_mutations {
                             match sampler.deref_mut() {
-                                Sampler::MLT(mlt_sampler) => mlt_sampler.accept(),
+                                Sampler::MLT(mlt_sampler) => mlt_sampler.start_iteration(),
                                 _ => panic!("MLTSampler needed."),
                             }
-                        // TODO: ++acceptedMutations;
-                        } else {
-                            match sampler.deref_mut() {
-                                Sampler::MLT(mlt_sampler) => mlt_sampler.reject(),
-                                _ => panic!("MLTSampler needed."),
+                            let mut p_proposed: Point2f = Point2f::default();
+                            let l_proposed: Spectrum =
+                                self.l(scene, &light_distr, &mut sampler, depth, &mut p_proposed);
+                            // compute acceptance probability for proposed sample
+                            let accept: Float = (1.0 as Float).min(l_proposed.y() / l_current.y());
+                            // splat both current and proposed samples to _film_
+                            if accept > 0.0 as Float {
+                                film.add_splat(
+                                    &p_proposed,
+                                    &(l_proposed * accept / l_proposed.y()),
+                                );
+                            }
+                            film.add_splat(
+                                &p_current,
+                                &(l_current * (1.0 as Float - accept) / l_current.y()),
+                            );
+                            // accept or reject the proposal
+                            if rng.uniform_float() < accept {
+                                p_current = p_proposed;
+                                l_current = l_proposed;
+                                match sampler.deref_mut() {
+                                    Sampler::MLT(mlt_sampler) => mlt_sampler.accept(),
+                                    _ => panic!("MLTSampler needed."),
+                                }
+                            // TODO: ++acceptedMutations;
+                            } else {
+                                match sampler.deref_mut() {
+                                    Sampler::MLT(mlt_sampler) => mlt_sampler.reject(),
+                                    _ => panic!("MLTSampler needed."),
+                                }
                             }
+                            // TODO: ++totalMutations;
+                            // if (i * n_total_mutations / n_chains + j) % progress_frequency == 0 {
+                            //     progress.update();
+                            // }
+                            // TODO: arena.Reset();
                         }
-                        // TODO: ++totalMutations;
-                        // if (i * n_total_mutations / n_chains + j) % progress_frequency == 0 {
-                        //     progress.update();
-                        // }
-                        // TODO: arena.Reset();
+                        s.send(i).expect(&format!("Failed to send chain"));
+                    });
+                    n_completed = finish.join().unwrap() as u32;
+                })
+                .unwrap();
+                if n_completed < n_chains {
+                    println!(
+                        "Rendering cancelled after {} of {} Markov chain(s)",
+                        n_completed, n_chains
+                    );
+                    // compensate for the missing chains
+                    if n_completed > 0 {
+                        b *= n_chains as Float / n_completed as Float;
                     }
-                });
-                finish.join().unwrap();
+                }
             }
             // Store final image computed with MLT
             film.write_image(b / self.mutations_per_pixel as Float);
         }
     }
-    pub fn get_camera(&self) -> Arc<Camera> {
-        self.camera.clone()
-    }
 }
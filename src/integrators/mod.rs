@@ -6,6 +6,7 @@
 //! - DirectLightingIntegrator
 //! - MLTIntegrator
 //! - PathIntegrator
+//! - ReSTIRDirectIntegrator
 //! - SPPMIntegrator
 //! - VolPathIntegrator
 //! - WhittedIntegrator
@@ -39,6 +40,15 @@
 //!
 //! ![Path Tracing](/doc/img/cornell_box_pbrt_rust_path.png)
 //!
+//! ## Reservoir-based Spatiotemporal Importance Resampling (ReSTIR)
+//!
+//! The **ReSTIRDirectIntegrator** computes direct lighting like the
+//! **DirectLightingIntegrator**, but chooses the light sample of each
+//! pixel by resampling many light candidates, and reuses the chosen
+//! samples across passes (temporal reuse) and between neighboring
+//! pixels (spatial reuse). With hundreds of lights this gives much
+//! less noise than sampling a single light per shading point.
+//!
 //! ## Bidirectional Path Tracing (BDPT)
 //!
 //! Bidirectional path tracing is a generalization of the standard
@@ -63,6 +73,7 @@ pub mod bdpt;
 pub mod directlighting;
 pub mod mlt;
 pub mod path;
+pub mod restir;
 pub mod sppm;
 pub mod volpath;
 pub mod whitted;
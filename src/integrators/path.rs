@@ -1,25 +1,39 @@
 // std
 use std::borrow::Borrow;
+use std::collections::HashMap;
 use std::sync::Arc;
 // pbrt
 // use crate::core::bssrdf::Bssrdf;
 use crate::core::camera::Camera;
 use crate::core::geometry::{vec3_abs_dot_nrm, vec3_dot_nrm};
 use crate::core::geometry::{Bounds2i, Point2f, Ray, Vector3f};
-use crate::core::integrator::uniform_sample_one_light;
-use crate::core::interaction::Interaction;
+use crate::core::integrator::{AOVSample, SamplerRender};
+use crate::core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
+use crate::core::light::{is_delta_light, Light, VisibilityTester};
 use crate::core::lightdistrib::create_light_sample_distribution;
 use crate::core::lightdistrib::LightDistribution;
 use crate::core::material::TransportMode;
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::reflection::BxdfType;
 use crate::core::sampler::Sampler;
-use crate::core::sampling::Distribution1D;
+use crate::core::sampling::{power_heuristic, Distribution1D};
 use crate::core::scene::Scene;
 
 // see path.h
 
+crate::stat_counter!("Integrator/Light samples taken", N_LIGHT_SAMPLES);
+crate::stat_ratio!(
+    "Integrator/Zero-radiance paths",
+    ZERO_RADIANCE_PATHS,
+    TOTAL_PATHS
+);
+
 /// Path Tracing (Global Illumination)
+///
+/// Direct lighting is estimated with one light sample per path
+/// vertex. The BSDF sample used to continue the path doubles as the
+/// second strategy: when it hits an emitter, the emission is weighted
+/// against the light sampling pdf with the power heuristic.
 pub struct PathIntegrator {
     // inherited from SamplerIntegrator (see integrator.h)
     pub camera: Arc<Camera>,
@@ -30,6 +44,7 @@ pub struct PathIntegrator {
     rr_threshold: Float,           // 1.0
     light_sample_strategy: String, // "spatial"
     light_distribution: Option<Arc<LightDistribution>>,
+    light_to_index: HashMap<usize, usize>,
 }
 
 impl PathIntegrator {
@@ -49,12 +64,14 @@ impl PathIntegrator {
             rr_threshold,
             light_sample_strategy,
             light_distribution: None,
+            light_to_index: HashMap::new(),
         }
     }
-    pub fn preprocess(&mut self, scene: &Scene) {
-        self.light_distribution =
-            create_light_sample_distribution(self.light_sample_strategy.clone(), scene);
-    }
+    /// Emission found by a BSDF sampled ray, from an area light it hits
+    /// or from the environment lights if it escapes, is weighted
+    /// against light sampling with the power heuristic. Only camera
+    /// rays and specular bounces, which light sampling can't
+    /// reproduce, add it unweighted.
     pub fn li(
         &self,
         r: &mut Ray,
@@ -62,6 +79,7 @@ impl PathIntegrator {
         sampler: &mut Box<Sampler>,
         // arena: &mut Arena,
         _depth: i32,
+        mut aov: Option<&mut AOVSample>,
     ) -> Spectrum {
         // TODO: ProfilePhase p(Prof::SamplerIntegratorLi);
         let mut l: Spectrum = Spectrum::default();
@@ -76,6 +94,11 @@ impl PathIntegrator {
         };
         let mut specular_bounce: bool = false;
         let mut bounces: u32 = 0_u32;
+        // previous path vertex, its light distribution and the BSDF
+        // pdf of the sampled direction (for MIS at emitters)
+        let mut prev_it: InteractionCommon = InteractionCommon::default();
+        let mut prev_distrib: Option<Arc<Distribution1D>> = None;
+        let mut bsdf_pdf: Float = 0.0 as Float;
         // Added after book publication: etaScale tracks the
         // accumulated effect of radiance scaling due to rays passing
         // through refractive boundaries (see the derivation on p. 527
@@ -96,6 +119,21 @@ impl PathIntegrator {
                     // add emitted light at path vertex
                     l += beta * isect.le(&-ray.d);
                     // println!("Added Le -> L = {:?}", l);
+                } else if let Some(primitive) = isect.primitive {
+                    if let Some(area_light) = primitive.get_area_light() {
+                        // add MIS-weighted emitted light at path vertex
+                        let le: Spectrum = isect.le(&-ray.d);
+                        if !le.is_black() {
+                            let weight: Float = self.emitter_weight(
+                                &area_light,
+                                &prev_it,
+                                prev_distrib.as_ref(),
+                                &ray.d,
+                                bsdf_pdf,
+                            );
+                            l += beta * le * weight;
+                        }
+                    }
                 }
                 // terminate path if _maxDepth_ was reached
                 if bounces >= self.max_depth {
@@ -112,6 +150,12 @@ impl PathIntegrator {
                     // bounces--;
                     continue;
                 }
+                if bounces == 0 {
+                    // first real surface seen by the camera ray
+                    if let Some(ref mut aov) = aov {
+                        aov.record(&isect, &r.o);
+                    }
+                }
                 if let Some(ref light_distribution) = self.light_distribution {
                     let distrib: Arc<Distribution1D> = light_distribution.lookup(&isect.p);
                     // Sample illumination from lights to find path contribution.
@@ -119,19 +163,13 @@ impl PathIntegrator {
                     let bsdf_flags: u8 = BxdfType::BsdfAll as u8 & !(BxdfType::BsdfSpecular as u8);
                     if let Some(ref bsdf) = isect.bsdf {
                         if bsdf.num_components(bsdf_flags) > 0 {
-                            // TODO: ++total_paths;
-                            let ld: Spectrum = beta
-                                * uniform_sample_one_light(
-                                    &isect,
-                                    scene,
-                                    sampler,
-                                    false,
-                                    Some(Arc::borrow(&distrib)),
-                                );
+                            crate::stat_inc!(TOTAL_PATHS);
+                            let ld: Spectrum =
+                                beta * self.sample_light(&isect, scene, sampler, &distrib);
                             // TODO: println!("Sampled direct lighting Ld = {:?}", ld);
-                            // TODO: if ld.is_black() {
-                            //     ++zero_radiance_paths;
-                            // }
+                            if ld.is_black() {
+                                crate::stat_inc!(ZERO_RADIANCE_PATHS);
+                            }
                             assert!(ld.y() >= 0.0 as Float, "ld = {:?}", ld);
                             l += ld;
                         }
@@ -154,6 +192,16 @@ impl PathIntegrator {
                         if f.is_black() || pdf == 0.0 as Float {
                             break;
                         }
+                        bsdf_pdf = pdf;
+                        prev_it = InteractionCommon {
+                            p: isect.p,
+                            time: isect.time,
+                            p_error: isect.p_error,
+                            wo: isect.wo,
+                            n: isect.n,
+                            medium_interface: isect.medium_interface.clone(),
+                        };
+                        prev_distrib = Some(distrib.clone());
                         beta *= (f * vec3_abs_dot_nrm(&wi, &isect.shading.n)) / pdf;
                         // println!("Updated beta = {:?}", beta);
                         assert!(beta.y() >= 0.0 as Float);
@@ -182,7 +230,18 @@ impl PathIntegrator {
                                 eta_scale *= 1.0 as Float / (eta * eta);
                             }
                         }
+                        // propagate ray differentials across specular
+                        // bounces (filtered textures in mirrors and glass)
+                        let differential = if (sampled_type & BxdfType::BsdfSpecular as u8) == 0_u8
+                        {
+                            None
+                        } else if (sampled_type & BxdfType::BsdfTransmission as u8) != 0_u8 {
+                            isect.specular_transmission_differential(&ray, &wi, bsdf.eta)
+                        } else {
+                            isect.specular_reflection_differential(&ray, &wi)
+                        };
                         ray = isect.spawn_ray(&wi);
+                        ray.differential = differential;
 
                         // account for subsurface scattering, if applicable
                         if let Some(ref bssrdf) = isect.bssrdf {
@@ -211,12 +270,11 @@ impl PathIntegrator {
                                     let distrib: Arc<Distribution1D> =
                                         light_distribution.lookup(&pi.p);
                                     l += beta
-                                        * uniform_sample_one_light(
+                                        * self.sample_light(
                                             &pi,
                                             scene,
                                             sampler,
-                                            false,
-                                            Some(Arc::borrow(&distrib)),
+                                            Arc::borrow(&distrib),
                                         );
                                     // account for the indirect subsurface scattering component
                                     let mut wi: Vector3f = Vector3f::default();
@@ -235,6 +293,16 @@ impl PathIntegrator {
                                         if f.is_black() || pdf == 0.0 as Float {
                                             break;
                                         }
+                                        bsdf_pdf = pdf;
+                                        prev_it = InteractionCommon {
+                                            p: pi.p,
+                                            time: pi.time,
+                                            p_error: pi.p_error,
+                                            wo: pi.wo,
+                                            n: pi.n,
+                                            medium_interface: pi.medium_interface.clone(),
+                                        };
+                                        prev_distrib = Some(distrib);
                                         beta *= f * vec3_abs_dot_nrm(&wi, &pi.shading.n) / pdf;
                                         assert!(!(beta.y().is_infinite()));
                                         specular_bounce =
@@ -266,14 +334,28 @@ impl PathIntegrator {
                     }
                 }
             } else {
+                // an aborted path didn't escape (see
+                // Scene::set_max_intersections())
+                if scene.path_aborted() {
+                    break;
+                }
                 // add emitted light from the environment
-                if bounces == 0 || specular_bounce {
-                    // for (const auto &light : scene.infiniteLights)
-                    for light in &scene.infinite_lights {
+                // for (const auto &light : scene.infiniteLights)
+                for light in &scene.infinite_lights {
+                    if bounces == 0 || specular_bounce {
                         l += beta * light.le(&mut ray);
+                    } else {
+                        let weight: Float = self.emitter_weight(
+                            light,
+                            &prev_it,
+                            prev_distrib.as_ref(),
+                            &ray.d,
+                            bsdf_pdf,
+                        );
+                        l += beta * light.le(&mut ray) * weight;
                     }
-                    // println!("Added infinite area lights -> L = {:?}", l);
                 }
+                // println!("Added infinite area lights -> L = {:?}", l);
                 // terminate path if ray escaped
                 break;
             }
@@ -281,13 +363,120 @@ impl PathIntegrator {
         }
         l
     }
-    pub fn get_camera(&self) -> Arc<Camera> {
+    /// Samples one light (chosen from *distrib*) and returns its
+    /// contribution at *it*, weighted against BSDF sampling.
+    fn sample_light(
+        &self,
+        it: &SurfaceInteraction,
+        scene: &Scene,
+        sampler: &mut Box<Sampler>,
+        distrib: &Distribution1D,
+    ) -> Spectrum {
+        crate::stat_inc!(N_LIGHT_SAMPLES);
+        let mut light_select_pdf: Float = 0.0 as Float;
+        let light_num: usize =
+            distrib.sample_discrete(sampler.get_1d(), Some(&mut light_select_pdf));
+        if light_select_pdf == 0.0 as Float {
+            return Spectrum::default();
+        }
+        let light = &scene.lights[light_num];
+        let u_light: Point2f = sampler.get_2d();
+        let it_common: InteractionCommon = InteractionCommon {
+            p: it.p,
+            time: it.time,
+            p_error: it.p_error,
+            wo: it.wo,
+            n: it.n,
+            medium_interface: it.medium_interface.clone(),
+        };
+        let mut wi: Vector3f = Vector3f::default();
+        let mut light_pdf: Float = 0.0 as Float;
+        let mut visibility: VisibilityTester = VisibilityTester::default();
+        let li: Spectrum = light.sample_li(
+            &it_common,
+            &u_light,
+            &mut wi,
+            &mut light_pdf,
+            &mut visibility,
+        );
+        if light_pdf == 0.0 as Float || li.is_black() {
+            return Spectrum::default();
+        }
+        if let Some(ref bsdf) = it.bsdf {
+            // all lobes, to match the pdf of the continuation ray
+            let bsdf_flags: u8 = BxdfType::BsdfAll as u8;
+            let f: Spectrum = bsdf.f(&it.wo, &wi, bsdf_flags)
+                * Spectrum::new(vec3_abs_dot_nrm(&wi, &it.shading.n));
+            if f.is_black() || !visibility.unoccluded(scene) {
+                return Spectrum::default();
+            }
+            let light_pdf: Float = light_pdf * light_select_pdf;
+            if is_delta_light(light.get_flags()) {
+                f * li / light_pdf
+            } else {
+                let scattering_pdf: Float = bsdf.pdf(&it.wo, &wi, bsdf_flags);
+                let weight: Float = power_heuristic(1_u8, light_pdf, 1_u8, scattering_pdf);
+                f * li * Spectrum::new(weight) / light_pdf
+            }
+        } else {
+            Spectrum::default()
+        }
+    }
+    /// Power heuristic weight for emission from *light* found by
+    /// sampling the BSDF at *prev* in direction *wi*.
+    fn emitter_weight(
+        &self,
+        light: &Arc<Light>,
+        prev: &InteractionCommon,
+        prev_distrib: Option<&Arc<Distribution1D>>,
+        wi: &Vector3f,
+        bsdf_pdf: Float,
+    ) -> Float {
+        if let (Some(distrib), Some(light_num)) = (
+            prev_distrib,
+            self.light_to_index.get(&(Arc::as_ptr(light) as usize)),
+        ) {
+            let light_pdf: Float = distrib.discrete_pdf(*light_num) * light.pdf_li(prev, *wi);
+            power_heuristic(1_u8, bsdf_pdf, 1_u8, light_pdf)
+        } else {
+            // light can't be reached by light sampling
+            1.0 as Float
+        }
+    }
+}
+
+impl SamplerRender for PathIntegrator {
+    fn preprocess(&mut self, scene: &Scene) {
+        self.light_distribution =
+            create_light_sample_distribution(self.light_sample_strategy.clone(), scene);
+        // reverse mapping from light pointers to offsets into the
+        // scene lights vector (and the light distributions)
+        self.light_to_index.clear();
+        for (li, light) in scene.lights.iter().enumerate() {
+            self.light_to_index.insert(Arc::as_ptr(light) as usize, li);
+        }
+    }
+    fn li(&self, ray: &mut Ray, scene: &Scene, sampler: &mut Box<Sampler>, depth: i32) -> Spectrum {
+        PathIntegrator::li(self, ray, scene, sampler, depth, None)
+    }
+    fn li_aov(
+        &self,
+        ray: &mut Ray,
+        scene: &Scene,
+        sampler: &mut Box<Sampler>,
+        depth: i32,
+        aov: &mut AOVSample,
+    ) -> Spectrum {
+        // records the AOVs while tracing the path
+        PathIntegrator::li(self, ray, scene, sampler, depth, Some(aov))
+    }
+    fn get_camera(&self) -> Arc<Camera> {
         self.camera.clone()
     }
-    pub fn get_sampler(&self) -> &Box<Sampler> {
+    fn get_sampler(&self) -> &Box<Sampler> {
         &self.sampler
     }
-    pub fn get_pixel_bounds(&self) -> Bounds2i {
+    fn get_pixel_bounds(&self) -> Bounds2i {
         self.pixel_bounds
     }
 }
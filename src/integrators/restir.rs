@@ -0,0 +1,555 @@
+// std
+use std::sync::Arc;
+// others
+use rayon::prelude::*;
+// pbrt
+use crate::core::camera::{Camera, CameraSample};
+use crate::core::film::Film;
+use crate::core::geometry::{nrm_dot_nrm, pnt3_distance, vec3_abs_dot_nrm};
+use crate::core::geometry::{Bounds2i, Point2f, Point2i, Ray, Vector3f};
+use crate::core::integrator::{compute_light_power_distribution, AOVSample, Render};
+use crate::core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
+use crate::core::light::VisibilityTester;
+use crate::core::material::TransportMode;
+use crate::core::pbrt::{Float, Spectrum};
+use crate::core::progressreporter::{CancellationToken, RenderProgress};
+use crate::core::reflection::BxdfType;
+use crate::core::rng::Rng;
+use crate::core::sampler::Sampler;
+use crate::core::sampling::Distribution1D;
+use crate::core::scene::Scene;
+use crate::integrators::directlighting::{DirectLightingIntegrator, LightStrategy};
+
+// see "Spatiotemporal reservoir resampling for real-time ray tracing
+// with dynamic direct lighting" (Bitterli et al. 2020)
+
+/// Light sample chosen by weighted reservoir sampling. Samples are
+/// stored in primary sample space (the light and the 2D sample for
+/// **Light::sample_li()**), so a sample found for one pixel can be
+/// evaluated at the surface point of any other pixel.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Reservoir {
+    pub light_num: usize,
+    pub u_light: Point2f,
+    /// sum of the resampling weights of all candidates seen
+    pub w_sum: Float,
+    /// number of candidates seen
+    pub m: Float,
+    /// unbiased contribution weight of the selected sample
+    pub w: Float,
+}
+
+impl Reservoir {
+    /// Streams a candidate with resampling weight _weight_ through the
+    /// reservoir, replacing the selected sample with probability
+    /// weight / w_sum (_u_ is a uniform random number).
+    pub fn update(&mut self, light_num: usize, u_light: Point2f, weight: Float, u: Float) -> bool {
+        self.w_sum += weight;
+        self.m += 1.0 as Float;
+        if weight > 0.0 as Float && u * self.w_sum < weight {
+            self.light_num = light_num;
+            self.u_light = u_light;
+            true
+        } else {
+            false
+        }
+    }
+    /// Merges another reservoir, whose selected sample has the target
+    /// function value _p_hat_ at the point this reservoir is used for.
+    /// At most _m_max_ candidates of _other_ are taken into account.
+    pub fn merge(&mut self, other: &Reservoir, p_hat: Float, m_max: Float, u: Float) -> bool {
+        let m: Float = other.m.min(m_max);
+        let selected: bool = self.update(other.light_num, other.u_light, p_hat * other.w * m, u);
+        self.m += m - 1.0 as Float;
+        selected
+    }
+    /// Computes the contribution weight for the selected sample with
+    /// target function value _p_hat_.
+    pub fn finalize(&mut self, p_hat: Float) {
+        self.w = if p_hat > 0.0 as Float && self.m > 0.0 as Float {
+            self.w_sum / (self.m * p_hat)
+        } else {
+            0.0 as Float
+        };
+    }
+}
+
+/// First surface (with a BSDF) seen through a pixel in one pass.
+struct PixelSample<'a> {
+    camera_sample: CameraSample,
+    ray_weight: Float,
+    isect: Option<SurfaceInteraction<'a>>,
+    depth: Float,
+    /// emitted and specularly reflected or transmitted radiance
+    l: Spectrum,
+    aov: AOVSample,
+    reservoir: Reservoir,
+}
+
+/// Direct lighting with reservoir-based spatiotemporal importance
+/// resampling (ReSTIR) for scenes with many lights.
+pub struct ReSTIRDirectIntegrator {
+    pub camera: Arc<Camera>,
+    pub sampler: Box<Sampler>,
+    pixel_bounds: Bounds2i,
+    max_depth: u32,
+    /// initial light candidates per pixel
+    n_candidates: u32,
+    /// neighbors to reuse samples from
+    n_spatial: u32,
+    /// pixel radius to choose neighbors from
+    spatial_radius: Float,
+    /// reuse the reservoirs of the previous pass
+    temporal: bool,
+    light_sample_strategy: String,
+}
+
+impl ReSTIRDirectIntegrator {
+    pub fn new(
+        camera: Arc<Camera>,
+        sampler: Box<Sampler>,
+        pixel_bounds: Bounds2i,
+        max_depth: u32,
+        n_candidates: u32,
+        n_spatial: u32,
+        spatial_radius: Float,
+        temporal: bool,
+        light_sample_strategy: String,
+    ) -> Self {
+        ReSTIRDirectIntegrator {
+            camera,
+            sampler,
+            pixel_bounds,
+            max_depth,
+            n_candidates,
+            n_spatial,
+            spatial_radius,
+            temporal,
+            light_sample_strategy,
+        }
+    }
+    /// Renders one sample per pixel per pass. Each pass resamples light
+    /// candidates per pixel, reuses the reservoir of the previous pass
+    /// (temporal reuse) and those of neighboring pixels (spatial
+    /// reuse), and shades the selected sample with a shadow ray.
+    /// _on_pass_ (if given) gets the accumulated film after each pass.
+    pub fn render_passes(
+        &self,
+        scene: &Scene,
+        num_threads: u8,
+        progress: &dyn RenderProgress,
+        cancel: &CancellationToken,
+        on_pass: Option<&dyn Fn(&Film, usize)>,
+    ) {
+        let num_cores: usize;
+        if num_threads == 0_u8 {
+            num_cores = num_cpus::get();
+        } else {
+            num_cores = num_threads as usize;
+        }
+        println!("Rendering with {:?} thread(s) ...", num_cores);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_cores)
+            .build()
+            .expect("Failed to create thread pool");
+        let film: Arc<Film> = self.camera.get_film();
+        let sample_bounds: Bounds2i = film.get_sample_bounds();
+        let pixel_bounds: Bounds2i = self.pixel_bounds;
+        let width: i32 = pixel_bounds.p_max.x - pixel_bounds.p_min.x;
+        let height: i32 = pixel_bounds.p_max.y - pixel_bounds.p_min.y;
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let light_distr: Option<Arc<Distribution1D>> = if self.light_sample_strategy == "uniform" {
+            if scene.lights.is_empty() {
+                None
+            } else {
+                Some(Arc::new(Distribution1D::new(vec![
+                    1.0 as Float;
+                    scene.lights.len()
+                ])))
+            }
+        } else {
+            compute_light_power_distribution(scene)
+        };
+        let light_distr: Option<&Distribution1D> = light_distr.as_deref();
+        // handles specular reflection and transmission below the first hit
+        let specular: DirectLightingIntegrator = DirectLightingIntegrator::new(
+            LightStrategy::UniformSampleOne,
+            self.max_depth,
+            self.camera.clone(),
+            self.sampler.clone_with_seed(0_u64),
+            pixel_bounds,
+        );
+        let samples_per_pixel: i64 = self.sampler.get_samples_per_pixel();
+        let mut previous: Vec<Reservoir> = Vec::new();
+        for pass in 0..samples_per_pixel {
+            // trace camera rays and resample light candidates
+            let pixels: Vec<PixelSample> = pool
+                .install(|| {
+                    (0..height)
+                        .into_par_iter()
+                        .map(|row| {
+                            let y: i32 = pixel_bounds.p_min.y + row;
+                            let mut sampler: Box<Sampler> = self
+                                .sampler
+                                .clone_with_seed((pass * height as i64 + row as i64) as u64);
+                            (0..width)
+                                .map(|column| {
+                                    let pixel: Point2i = Point2i {
+                                        x: pixel_bounds.p_min.x + column,
+                                        y,
+                                    };
+                                    let index: usize = (row * width + column) as usize;
+                                    let mut rng: Rng = Rng::new();
+                                    rng.set_sequence(
+                                        (pass as u64) * (width * height) as u64 + index as u64,
+                                    );
+                                    let mut pixel_sample: PixelSample = self.trace_camera_ray(
+                                        &pixel,
+                                        pass,
+                                        scene,
+                                        &mut sampler,
+                                        &specular,
+                                    );
+                                    if let Some(ref isect) = pixel_sample.isect {
+                                        let mut reservoir: Reservoir = self.initial_candidates(
+                                            isect,
+                                            scene,
+                                            light_distr,
+                                            &mut rng,
+                                        );
+                                        if self.temporal && !previous.is_empty() {
+                                            reservoir = temporal_reuse(
+                                                &reservoir,
+                                                &previous[index],
+                                                isect,
+                                                scene,
+                                                &mut rng,
+                                            );
+                                        }
+                                        pixel_sample.reservoir = reservoir;
+                                    }
+                                    pixel_sample
+                                })
+                                .collect::<Vec<PixelSample>>()
+                        })
+                        .collect::<Vec<Vec<PixelSample>>>()
+                })
+                .into_iter()
+                .flatten()
+                .collect();
+            // spatial reuse and shading
+            let radiance: Vec<Spectrum> = pool.install(|| {
+                (0..pixels.len())
+                    .into_par_iter()
+                    .map(|index| {
+                        let pixel_sample: &PixelSample = &pixels[index];
+                        let mut l: Spectrum = pixel_sample.l;
+                        if let Some(ref isect) = pixel_sample.isect {
+                            let mut rng: Rng = Rng::new();
+                            rng.set_sequence(
+                                (pass as u64 + samples_per_pixel as u64) * pixels.len() as u64
+                                    + index as u64,
+                            );
+                            let reservoir: Reservoir = self.spatial_reuse(
+                                index, width, height, &pixels, isect, scene, &mut rng,
+                            );
+                            let (f, p_hat, visibility) = target_function(isect, scene, &reservoir);
+                            if p_hat > 0.0 as Float && visibility.unoccluded(scene) {
+                                l += f * reservoir.w;
+                            }
+                        }
+                        l
+                    })
+                    .collect()
+            });
+            // add the pass to the film
+            let mut film_tile = film.get_film_tile(&sample_bounds);
+            for (index, (pixel_sample, l)) in pixels.iter().zip(radiance.iter()).enumerate() {
+                let pixel: Point2i = Point2i {
+                    x: pixel_bounds.p_min.x + index as i32 % width,
+                    y: pixel_bounds.p_min.y + index as i32 / width,
+                };
+                let mut l: Spectrum = *l;
+                if l.has_nans() || l.y().is_infinite() {
+                    l = Spectrum::default();
+                }
+                if film.is_adaptive() {
+                    film_tile.add_sample_luminance(&pixel, &l);
+                }
+                if film.write_aovs {
+                    film_tile.add_aov_sample(&pixel, &pixel_sample.aov);
+                }
+                film_tile.add_sample(
+                    &pixel_sample.camera_sample.p_film,
+                    &mut l,
+                    pixel_sample.ray_weight,
+                );
+            }
+            film.merge_film_tile(&film_tile);
+            film.update_tev(&film_tile);
+            // the next pass reuses the reservoirs before spatial reuse,
+            // so the bias of the spatial reuse doesn't accumulate
+            previous = pixels
+                .iter()
+                .map(|pixel_sample| pixel_sample.reservoir)
+                .collect();
+            progress.on_tile_done(pass as usize + 1, samples_per_pixel as usize);
+            if let Some(on_pass) = on_pass {
+                on_pass(&film, pass as usize + 1);
+            }
+            if cancel.is_cancelled() {
+                println!("Rendering cancelled, writing partial image ...");
+                break;
+            }
+        }
+        film.write_image(1.0 as Float);
+    }
+    pub fn get_camera(&self) -> Arc<Camera> {
+        self.camera.clone()
+    }
+    fn trace_camera_ray<'a>(
+        &self,
+        pixel: &Point2i,
+        pass: i64,
+        scene: &'a Scene,
+        sampler: &mut Box<Sampler>,
+        specular: &DirectLightingIntegrator,
+    ) -> PixelSample<'a> {
+        sampler.start_pixel(pixel);
+        if pass > 0 {
+            sampler.set_sample_number(pass);
+        }
+        let camera_sample: CameraSample = sampler.get_camera_sample(pixel);
+        let mut ray: Ray = Ray::default();
+        let ray_weight: Float = self
+            .camera
+            .generate_ray_differential(&camera_sample, &mut ray);
+        ray.scale_differentials(1.0 as Float / (sampler.get_samples_per_pixel() as Float).sqrt());
+        let mut pixel_sample: PixelSample = PixelSample {
+            camera_sample,
+            ray_weight,
+            isect: None,
+            depth: 0.0 as Float,
+            l: Spectrum::default(),
+            aov: AOVSample::default(),
+            reservoir: Reservoir::default(),
+        };
+        if ray_weight <= 0.0 as Float {
+            return pixel_sample;
+        }
+        let origin = ray.o;
+        loop {
+            if let Some(mut isect) = scene.intersect(&mut ray) {
+                isect.compute_scattering_functions(&mut ray, false, TransportMode::Radiance);
+                if isect.bsdf.is_none() {
+                    // skip over medium boundaries
+                    ray = isect.spawn_ray(&ray.d);
+                    continue;
+                }
+                let wo: Vector3f = isect.wo;
+                pixel_sample.l += isect.le(&wo);
+                if self.max_depth > 1 {
+                    pixel_sample.l += specular.specular_reflect(&ray, &isect, scene, sampler, 0);
+                    pixel_sample.l += specular.specular_transmit(&ray, &isect, scene, sampler, 0);
+                }
+                pixel_sample.aov.record(&isect, &origin);
+                pixel_sample.depth = pnt3_distance(&isect.p, &origin);
+                pixel_sample.isect = Some(isect);
+            } else {
+                for light in &scene.lights {
+                    pixel_sample.l += light.le(&mut ray);
+                }
+            }
+            break;
+        }
+        pixel_sample
+    }
+    /// Resamples one light sample from _n_candidates_ candidates
+    /// drawn from _light_distr_, weighted by the unshadowed
+    /// contribution, and checks its visibility.
+    fn initial_candidates(
+        &self,
+        isect: &SurfaceInteraction,
+        scene: &Scene,
+        light_distr: Option<&Distribution1D>,
+        rng: &mut Rng,
+    ) -> Reservoir {
+        let mut reservoir: Reservoir = Reservoir::default();
+        if let Some(light_distr) = light_distr {
+            for _ in 0..self.n_candidates {
+                let mut light_pdf: Float = 0.0 as Float;
+                let light_num: usize =
+                    light_distr.sample_discrete(rng.uniform_float(), Some(&mut light_pdf));
+                let u_light: Point2f = Point2f {
+                    x: rng.uniform_float(),
+                    y: rng.uniform_float(),
+                };
+                let candidate: Reservoir = Reservoir {
+                    light_num,
+                    u_light,
+                    ..Default::default()
+                };
+                let p_hat: Float = target_function(isect, scene, &candidate).1;
+                let weight: Float = if light_pdf > 0.0 as Float {
+                    p_hat / light_pdf
+                } else {
+                    0.0 as Float
+                };
+                reservoir.update(light_num, u_light, weight, rng.uniform_float());
+            }
+            let (_f, p_hat, visibility) = target_function(isect, scene, &reservoir);
+            reservoir.finalize(p_hat);
+            // discard occluded samples before they are reused
+            if reservoir.w > 0.0 as Float && !visibility.unoccluded(scene) {
+                reservoir.w = 0.0 as Float;
+            }
+        }
+        reservoir
+    }
+    /// Merges the reservoir of pixel _index_ with those of randomly
+    /// chosen neighbors with a similar surface (normal and depth).
+    fn spatial_reuse(
+        &self,
+        index: usize,
+        width: i32,
+        height: i32,
+        pixels: &[PixelSample],
+        isect: &SurfaceInteraction,
+        scene: &Scene,
+        rng: &mut Rng,
+    ) -> Reservoir {
+        let own: &PixelSample = &pixels[index];
+        let x: i32 = index as i32 % width;
+        let y: i32 = index as i32 / width;
+        let mut reservoir: Reservoir = Reservoir::default();
+        let p_hat: Float = target_function(isect, scene, &own.reservoir).1;
+        reservoir.merge(&own.reservoir, p_hat, own.reservoir.m, rng.uniform_float());
+        let mut merged: Vec<(&SurfaceInteraction, Float)> = vec![(isect, own.reservoir.m)];
+        for _ in 0..self.n_spatial {
+            let dx: i32 = ((2.0 as Float * rng.uniform_float() - 1.0 as Float)
+                * self.spatial_radius)
+                .round() as i32;
+            let dy: i32 = ((2.0 as Float * rng.uniform_float() - 1.0 as Float)
+                * self.spatial_radius)
+                .round() as i32;
+            let nx: i32 = x + dx;
+            let ny: i32 = y + dy;
+            if (dx == 0 && dy == 0) || nx < 0 || ny < 0 || nx >= width || ny >= height {
+                continue;
+            }
+            let neighbor: &PixelSample = &pixels[(ny * width + nx) as usize];
+            if let Some(ref neighbor_isect) = neighbor.isect {
+                // only reuse samples of similar surfaces, which keeps
+                // the bias of the reuse low
+                if nrm_dot_nrm(&neighbor_isect.shading.n, &isect.shading.n) < 0.906 as Float
+                    || (neighbor.depth - own.depth).abs() > 0.1 as Float * own.depth
+                {
+                    continue;
+                }
+                let p_hat: Float = target_function(isect, scene, &neighbor.reservoir).1;
+                reservoir.merge(
+                    &neighbor.reservoir,
+                    p_hat,
+                    neighbor.reservoir.m,
+                    rng.uniform_float(),
+                );
+                merged.push((neighbor_isect, neighbor.reservoir.m));
+            }
+        }
+        // only count the candidates of pixels which could have
+        // produced the selected sample, otherwise samples which are
+        // zero at a neighbor would darken the image
+        reservoir.m = merged
+            .iter()
+            .filter(|(merged_isect, _m)| target_function(merged_isect, scene, &reservoir).1 > 0.0)
+            .map(|(_merged_isect, m)| m)
+            .sum();
+        let p_hat: Float = target_function(isect, scene, &reservoir).1;
+        reservoir.finalize(p_hat);
+        reservoir
+    }
+}
+
+impl Render for ReSTIRDirectIntegrator {
+    fn render(
+        &mut self,
+        scene: &Scene,
+        num_threads: u8,
+        progress: &dyn RenderProgress,
+        cancel: &CancellationToken,
+    ) {
+        self.render_passes(scene, num_threads, progress, cancel, None);
+    }
+}
+
+/// Merges the reservoir of a pixel with the one of the previous pass,
+/// which counts at most 20 times as much as the new candidates.
+fn temporal_reuse(
+    reservoir: &Reservoir,
+    previous: &Reservoir,
+    isect: &SurfaceInteraction,
+    scene: &Scene,
+    rng: &mut Rng,
+) -> Reservoir {
+    let mut merged: Reservoir = Reservoir::default();
+    let p_hat: Float = target_function(isect, scene, reservoir).1;
+    merged.merge(reservoir, p_hat, reservoir.m, rng.uniform_float());
+    let p_hat: Float = target_function(isect, scene, previous).1;
+    merged.merge(
+        previous,
+        p_hat,
+        20.0 as Float * reservoir.m,
+        rng.uniform_float(),
+    );
+    let p_hat: Float = target_function(isect, scene, &merged).1;
+    merged.finalize(p_hat);
+    merged
+}
+
+/// Evaluates the unshadowed contribution of a reservoir's selected
+/// sample at _isect_: the BSDF times the incident radiance and cosine
+/// divided by the light's sampling density. Returns it with its
+/// luminance (the target function of the resampling) and the
+/// visibility tester for a shadow ray.
+fn target_function(
+    isect: &SurfaceInteraction,
+    scene: &Scene,
+    reservoir: &Reservoir,
+) -> (Spectrum, Float, VisibilityTester) {
+    let mut visibility: VisibilityTester = VisibilityTester::default();
+    if scene.lights.is_empty() {
+        return (Spectrum::default(), 0.0 as Float, visibility);
+    }
+    let light = &scene.lights[reservoir.light_num];
+    let it_common: InteractionCommon = InteractionCommon {
+        p: isect.p,
+        time: isect.time,
+        p_error: isect.p_error,
+        wo: isect.wo,
+        n: isect.n,
+        medium_interface: isect.medium_interface.clone(),
+    };
+    let mut wi: Vector3f = Vector3f::default();
+    let mut light_pdf: Float = 0.0 as Float;
+    let li: Spectrum = light.sample_li(
+        &it_common,
+        &reservoir.u_light,
+        &mut wi,
+        &mut light_pdf,
+        &mut visibility,
+    );
+    if light_pdf == 0.0 as Float || li.is_black() {
+        return (Spectrum::default(), 0.0 as Float, visibility);
+    }
+    if let Some(ref bsdf) = isect.bsdf {
+        let bsdf_flags: u8 = BxdfType::BsdfAll as u8 & !(BxdfType::BsdfSpecular as u8);
+        let f: Spectrum = bsdf.f(&isect.wo, &wi, bsdf_flags)
+            * Spectrum::new(vec3_abs_dot_nrm(&wi, &isect.shading.n));
+        let contribution: Spectrum = f * li / light_pdf;
+        (contribution, contribution.y().max(0.0 as Float), visibility)
+    } else {
+        (Spectrum::default(), 0.0 as Float, visibility)
+    }
+}
@@ -15,13 +15,14 @@ use crate::core::geometry::{
 use crate::core::geometry::{
     Bounds2i, Bounds3f, Normal3f, Point2f, Point2i, Point3f, Point3i, Ray, Vector2i, Vector3f,
 };
-use crate::core::integrator::{compute_light_power_distribution, uniform_sample_one_light};
+use crate::core::integrator::{compute_light_power_distribution, uniform_sample_one_light, Render};
 use crate::core::interaction::Interaction;
 use crate::core::lowdiscrepancy::radical_inverse;
 use crate::core::material::TransportMode;
 use crate::core::parallel::AtomicFloat;
-use crate::core::pbrt::{clamp_t, lerp};
+use crate::core::pbrt::clamp_t;
 use crate::core::pbrt::{Float, Spectrum};
+use crate::core::progressreporter::{CancellationToken, RenderProgress};
 use crate::core::reflection::{Bsdf, BxdfType};
 use crate::core::scene::Scene;
 use crate::samplers::halton::HaltonSampler;
@@ -60,7 +61,19 @@ impl SPPMIntegrator {
             write_frequency,
         }
     }
-    pub fn render(&self, scene: &Scene, num_threads: u8) {
+    pub fn get_camera(&self) -> Arc<Camera> {
+        self.camera.clone()
+    }
+}
+
+impl Render for SPPMIntegrator {
+    fn render(
+        &mut self,
+        scene: &Scene,
+        num_threads: u8,
+        progress: &dyn RenderProgress,
+        cancel: &CancellationToken,
+    ) {
         let num_cores: usize;
         if num_threads == 0_u8 {
             num_cores = num_cpus::get();
@@ -93,13 +106,12 @@ impl SPPMIntegrator {
             ));
             // compute number of tiles to use for SPPM camera pass
             let pixel_extent: Vector2i = pixel_bounds.diagonal();
-            let tile_size: i32 = 16;
+            let tile_size: i32 = film.tile_size;
             let n_tiles: Point2i = Point2i {
                 x: (pixel_extent.x + tile_size - 1) / tile_size,
                 y: (pixel_extent.y + tile_size - 1) / tile_size,
             };
-            // TODO: ProgressReporter progress(2 * nIterations, "Rendering");
-            for iteration in pbr::PbIter::new(0..self.n_iterations) {
+            for iteration in 0..self.n_iterations {
                 // generate SPPM visible points
                 {
                     // TODO: ProfilePhase _(Prof::SPPMCameraPass);
@@ -133,8 +145,14 @@ impl SPPMIntegrator {
                                         // TODO: MemoryArena &arena = perThreadArenas[ThreadIndex];
 
                                         // follow camera paths for _tile_ in image for SPPM
-                                        // TODO: let tile_index: i32 = tile.y * n_tiles.x + tile.x;
-                                        let mut tile_sampler = sampler.clone_with_seed(0_u64);
+                                        // a new seed for each tile and
+                                        // iteration, so random samplers
+                                        // don't repeat earlier samples
+                                        let tile_index: i64 = iteration as i64
+                                            * (n_tiles.x * n_tiles.y) as i64
+                                            + (tile.y * n_tiles.x + tile.x) as i64;
+                                        let mut tile_sampler =
+                                            sampler.clone_with_seed(tile_index as u64);
                                         // compute _tileBounds_ for SPPM tile
                                         let x0: i32 = pixel_bounds.p_min.x + tile.x * tile_size;
                                         let x1: i32 =
@@ -155,7 +173,7 @@ impl SPPMIntegrator {
                                                 tile_sampler.get_camera_sample(&p_pixel);
                                             let mut ray: Ray = Ray::default();
                                             let mut beta: Spectrum = Spectrum::new(
-                                                self.get_camera().generate_ray_differential(
+                                                integrator.get_camera().generate_ray_differential(
                                                     &camera_sample,
                                                     &mut ray,
                                                 ),
@@ -203,8 +221,7 @@ impl SPPMIntegrator {
                                                             * uniform_sample_one_light(
                                                                 &isect,
                                                                 scene,
-                                                                &mut tile_sampler
-                                                                    .clone_with_seed(0_u64),
+                                                                &mut tile_sampler,
                                                                 false,
                                                                 None,
                                                             );
@@ -494,11 +511,11 @@ impl SPPMIntegrator {
                                                 halton_index,
                                             ),
                                         };
-                                        let u_light_time: Float = lerp(
-                                            radical_inverse((halton_dim + 4) as u16, halton_index),
-                                            self.get_camera().get_shutter_open(),
-                                            self.get_camera().get_shutter_close(),
-                                        );
+                                        let u_light_time: Float =
+                                            integrator.get_camera().sample_time(radical_inverse(
+                                                (halton_dim + 4) as u16,
+                                                halton_index,
+                                            ));
                                         halton_dim += 5;
                                         // generate _photon_ray_ from light source and initialize _beta_
                                         // RayDifferential photon_ray;
@@ -600,7 +617,7 @@ impl SPPMIntegrator {
                                                                                     &wi,
                                                                                     bsdf_flags,
                                                                                 );
-                                                                            for i in 0..3 {
+                                                                            for i in 0..Spectrum::N_SAMPLES {
                                                                                 pixel.phi[i]
                                                                                     .add(phi[i]);
                                                                             }
@@ -725,7 +742,7 @@ impl SPPMIntegrator {
                                             let r_new: Float =
                                                 p.radius * (n_new / (p.n + p_m as Float)).sqrt();
                                             let mut phi: Spectrum = Spectrum::default();
-                                            for j in 0..3 {
+                                            for j in 0..Spectrum::N_SAMPLES {
                                                 phi[j] = Float::from(&p.phi[j]);
                                             }
                                             p.tau = (p.tau + p.vp.beta * phi) * (r_new * r_new)
@@ -733,7 +750,7 @@ impl SPPMIntegrator {
                                             p.n = n_new;
                                             p.radius = r_new;
                                             p.m.store(0, atomic::Ordering::Relaxed);
-                                            for j in 0..3 {
+                                            for j in 0..Spectrum::N_SAMPLES {
                                                 p.phi[j] = AtomicFloat::new(0.0 as Float);
                                             }
                                         }
@@ -755,9 +772,13 @@ impl SPPMIntegrator {
                         .unwrap();
                     }
                 }
+                // cancellation is checked between iterations, the
+                // partial result is written like the final image
+                let cancelled: bool = cancel.is_cancelled();
                 // periodically store SPPM image in film and write image
                 if iteration + 1 == self.n_iterations
                     || ((iteration + 1) % self.write_frequency) == 0
+                    || cancelled
                 {
                     let x0: i32 = pixel_bounds.p_min.x;
                     let x1: i32 = pixel_bounds.p_max.x;
@@ -810,13 +831,18 @@ impl SPPMIntegrator {
                     //     WriteImage("sppm_radius.png", rimg.get(), pixel_bounds, res);
                     // }
                 }
+                progress.on_tile_done(iteration as usize + 1, self.n_iterations as usize);
+                if cancelled {
+                    println!(
+                        "Rendering cancelled after {} of {} iteration(s)",
+                        iteration + 1,
+                        self.n_iterations
+                    );
+                    break;
+                }
             }
-            // TODO: progress.Done();
         }
     }
-    pub fn get_camera(&self) -> Arc<Camera> {
-        self.camera.clone()
-    }
 }
 
 #[derive(Default)]
@@ -827,17 +853,30 @@ pub struct VisiblePoint {
     pub beta: Spectrum,
 }
 
-#[derive(Default)]
 pub struct SPPMPixel {
     pub radius: Float,
     pub ld: Spectrum,
     pub vp: VisiblePoint,
-    pub phi: [AtomicFloat; 3],
+    pub phi: [AtomicFloat; Spectrum::N_SAMPLES],
     pub m: Atomic<i32>,
     pub n: Float,
     pub tau: Spectrum,
 }
 
+impl Default for SPPMPixel {
+    fn default() -> Self {
+        SPPMPixel {
+            radius: 0.0 as Float,
+            ld: Spectrum::default(),
+            vp: VisiblePoint::default(),
+            phi: std::array::from_fn(|_| AtomicFloat::default()),
+            m: Atomic::default(),
+            n: 0.0 as Float,
+            tau: Spectrum::default(),
+        }
+    }
+}
+
 pub struct SPPMPixelListNode<'p> {
     pub pixel: &'p SPPMPixel,
     pub next: AtomSetOnce<Arc<SPPMPixelListNode<'p>>>,
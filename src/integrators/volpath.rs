@@ -6,7 +6,7 @@ use std::sync::Arc;
 use crate::core::camera::Camera;
 use crate::core::geometry::{vec3_abs_dot_nrm, vec3_dot_nrm};
 use crate::core::geometry::{Bounds2i, Point2f, Ray, Vector3f};
-use crate::core::integrator::uniform_sample_one_light;
+use crate::core::integrator::{uniform_sample_one_light, SamplerRender};
 use crate::core::interaction::{Interaction, MediumInteraction};
 use crate::core::lightdistrib::create_light_sample_distribution;
 use crate::core::lightdistrib::LightDistribution;
@@ -52,11 +52,14 @@ impl VolPathIntegrator {
             light_distribution: None,
         }
     }
-    pub fn preprocess(&mut self, scene: &Scene) {
+}
+
+impl SamplerRender for VolPathIntegrator {
+    fn preprocess(&mut self, scene: &Scene) {
         self.light_distribution =
             create_light_sample_distribution(self.light_sample_strategy.clone(), scene);
     }
-    pub fn li(
+    fn li(
         &self,
         r: &mut Ray,
         scene: &Scene,
@@ -206,7 +209,19 @@ impl VolPathIntegrator {
                                     eta_scale *= 1.0 as Float / (eta * eta);
                                 }
                             }
+                            // propagate ray differentials across specular
+                            // bounces (filtered textures in mirrors and glass)
+                            let differential = if (sampled_type & BxdfType::BsdfSpecular as u8)
+                                == 0_u8
+                            {
+                                None
+                            } else if (sampled_type & BxdfType::BsdfTransmission as u8) != 0_u8 {
+                                isect.specular_transmission_differential(&ray, &wi, bsdf.eta)
+                            } else {
+                                isect.specular_reflection_differential(&ray, &wi)
+                            };
                             ray = isect.spawn_ray(&wi);
+                            ray.differential = differential;
                             // account for attenuated subsurface scattering, if applicable
                             if let Some(ref bssrdf) = isect.bssrdf {
                                 if (sampled_type & BxdfType::BsdfTransmission as u8) != 0_u8 {
@@ -329,8 +344,9 @@ impl VolPathIntegrator {
                         }
                     }
                 }
-                // add emitted light from the environment
-                if bounces == 0 || specular_bounce {
+                // add emitted light from the environment (unless the
+                // path was aborted, see Scene::set_max_intersections())
+                if (bounces == 0 || specular_bounce) && !scene.path_aborted() {
                     for light in &scene.infinite_lights {
                         l += beta * light.le(&mut ray);
                     }
@@ -342,13 +358,13 @@ impl VolPathIntegrator {
         }
         l
     }
-    pub fn get_camera(&self) -> Arc<Camera> {
+    fn get_camera(&self) -> Arc<Camera> {
         self.camera.clone()
     }
-    pub fn get_sampler(&self) -> &Box<Sampler> {
+    fn get_sampler(&self) -> &Box<Sampler> {
         &self.sampler
     }
-    pub fn get_pixel_bounds(&self) -> Bounds2i {
+    fn get_pixel_bounds(&self) -> Bounds2i {
         self.pixel_bounds
     }
 }
@@ -5,7 +5,7 @@
 //! [here][repo].
 //!
 //! The main render loop for integrators implementing the
-//! `SamplerIntegrator` can be found [here].
+//! `SamplerRender` trait can be found [here].
 //!
 //! There are three more render loops:
 //!
@@ -16,7 +16,7 @@
 //! [rust]: https://www.rust-lang.org
 //! [book]: http://www.pbrt.org
 //! [repo]: https://github.com/wahn/rs_pbrt
-//! [here]: core/integrator/enum.SamplerIntegrator.html#method.render
+//! [here]: core/integrator/trait.SamplerRender.html#method.render_passes
 //! [render_bdpt]: integrators/bdpt/struct.BDPTIntegrator.html#method.render
 //! [render_mlt]: integrators/mlt/struct.MLTIntegrator.html#method.render
 //! [render_sppm]: integrators/sppm/struct.SPPMIntegrator.html#method.render
@@ -85,7 +85,7 @@ impl DiffuseAreaLight {
             p_error: iref.p_error,
             wo: iref.wo,
             n: iref.n,
-            medium_interface: None,
+            medium_interface: iref.medium_interface.clone(),
         };
         vis.p1 = InteractionCommon {
             p: p_shape.p,
@@ -107,9 +107,7 @@ impl DiffuseAreaLight {
         }
         self.l_emit * factor * self.area * PI
     }
-    pub fn preprocess(&self, _scene: &Scene) {
-        // TODO?
-    }
+    pub fn preprocess(&self, _scene: &Scene) {}
     pub fn le(&self, _ray: &mut Ray) -> Spectrum {
         Spectrum::default()
     }
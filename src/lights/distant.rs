@@ -4,7 +4,7 @@ use std::f32::consts::PI;
 use std::sync::RwLock;
 // pbrt
 use crate::core::geometry::vec3_coordinate_system;
-use crate::core::geometry::{Bounds3f, Normal3f, Point2f, Point3f, Ray, Vector3f};
+use crate::core::geometry::{Normal3f, Point2f, Point3f, Ray, Vector3f};
 use crate::core::interaction::{Interaction, InteractionCommon};
 use crate::core::light::{LightFlags, VisibilityTester};
 use crate::core::medium::MediumInterface;
@@ -64,7 +64,7 @@ impl DistantLight {
                 p_error: iref.p_error,
                 wo: iref.wo,
                 n: iref.n,
-                medium_interface: None,
+                medium_interface: iref.medium_interface.clone(),
             },
             p1: InteractionCommon {
                 p: p_outside,
@@ -89,13 +89,9 @@ impl DistantLight {
     /// method to get the bound. This method is called at the end of
     /// the **Scene** constructor.
     pub fn preprocess(&self, scene: &Scene) {
-        let mut world_center_ref = self.world_center.write().unwrap();
-        let mut world_radius_ref = self.world_radius.write().unwrap();
-        Bounds3f::bounding_sphere(
-            &scene.world_bound(),
-            &mut world_center_ref,
-            &mut world_radius_ref,
-        );
+        let (world_center, world_radius) = scene.bounding_sphere();
+        *self.world_center.write().unwrap() = world_center;
+        *self.world_radius.write().unwrap() = world_radius;
     }
     /// Default implementation returns no emitted radiance for a ray
     /// that escapes the scene bounds.
@@ -0,0 +1,455 @@
+// std
+use std;
+use std::f32::consts::PI;
+use std::path::Path;
+use std::sync::Arc;
+// pbrt
+use crate::core::api::find_light_tint;
+use crate::core::geometry::{pnt3_distance_squared, spherical_phi, spherical_theta};
+use crate::core::geometry::{vec3_coordinate_system, Normal3f, Point2f, Point3f, Ray, Vector3f};
+use crate::core::interaction::{Interaction, InteractionCommon};
+use crate::core::light::{LightFlags, VisibilityTester};
+use crate::core::medium::{Medium, MediumInterface};
+use crate::core::paramset::ParamSet;
+use crate::core::pbrt::{degrees, find_interval};
+use crate::core::pbrt::{Float, Spectrum};
+use crate::core::sampling::{uniform_sample_sphere, uniform_sphere_pdf};
+use crate::core::scene::Scene;
+use crate::core::spectrum::SpectrumType;
+use crate::core::transform::Transform;
+
+/// The candela distribution of an IESNA LM-63 photometric data file
+/// (type C photometry). The vertical angle is measured from the main
+/// direction of the luminaire (nadir), the horizontal angle around
+/// it. Both are stored in degrees.
+#[derive(Debug, Clone, Default)]
+pub struct IESProfile {
+    pub vertical_angles: Vec<Float>,
+    pub horizontal_angles: Vec<Float>,
+    /// candela values (already multiplied by the candela
+    /// multiplier), all vertical angles for the first horizontal
+    /// angle, then for the second, and so on
+    pub candela: Vec<Float>,
+}
+
+impl IESProfile {
+    /// Reads an IES file, panics if it can't be read or parsed.
+    pub fn load(path: &Path) -> IESProfile {
+        let text: String = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => panic!("Unable to read IES file {:?}: {}", path, e),
+        };
+        match IESProfile::parse(&text) {
+            Ok(profile) => profile,
+            Err(e) => panic!("Unable to parse IES file {:?}: {}", path, e),
+        }
+    }
+    /// Parses the contents of an IES file.
+    ///
+    /// ```rust
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::lights::ies::IESProfile;
+    ///
+    /// let text = "IESNA:LM-63-2002\n\
+    ///             [TEST] minimal\n\
+    ///             TILT=NONE\n\
+    ///             1 1000 2 3 1 1 2 0 0 0\n\
+    ///             1 1 100\n\
+    ///             0 45 90\n\
+    ///             0\n\
+    ///             500 250 0\n";
+    /// let profile = IESProfile::parse(text).unwrap();
+    /// assert_eq!(profile.vertical_angles, vec![0.0, 45.0, 90.0]);
+    /// assert_eq!(profile.horizontal_angles, vec![0.0]);
+    /// // candela multiplier 2
+    /// assert_eq!(profile.lookup(0.0, 0.0), 1000.0 as Float);
+    /// // interpolated, and rotationally symmetric
+    /// assert_eq!(profile.lookup(22.5, 0.0), 750.0 as Float);
+    /// assert_eq!(profile.lookup(22.5, 123.0), 750.0 as Float);
+    /// // no light above the horizon
+    /// assert_eq!(profile.lookup(135.0, 0.0), 0.0 as Float);
+    /// ```
+    pub fn parse(text: &str) -> Result<IESProfile, String> {
+        // skip the header and keywords up to the TILT line
+        let mut lines = text.lines();
+        let tilt: String = loop {
+            match lines.next() {
+                Some(line) => {
+                    let line = line.trim();
+                    if line.starts_with("TILT=") {
+                        break String::from(&line[5..]);
+                    }
+                }
+                None => return Err(String::from("missing TILT line")),
+            }
+        };
+        let rest: Vec<&str> = lines.collect();
+        let mut tokens = rest.iter().flat_map(|line| {
+            line.split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|token| !token.is_empty())
+        });
+        let mut next = || -> Result<Float, String> {
+            match tokens.next() {
+                Some(token) => token
+                    .parse::<Float>()
+                    .map_err(|_| format!("{:?} is not a number", token)),
+                None => Err(String::from("unexpected end of file")),
+            }
+        };
+        if tilt == "INCLUDE" {
+            // lamp-to-luminaire geometry, then the tilt angles and
+            // multiplying factors, which are ignored
+            next()?;
+            let n_tilt: usize = next()? as usize;
+            for _ in 0..(2 * n_tilt) {
+                next()?;
+            }
+        } else if tilt != "NONE" {
+            println!("WARNING: IES tilt file {:?} ignored", tilt);
+        }
+        let _n_lamps: Float = next()?;
+        let _lumens_per_lamp: Float = next()?;
+        let multiplier: Float = next()?;
+        let n_vertical: usize = next()? as usize;
+        let n_horizontal: usize = next()? as usize;
+        let photometric_type: i32 = next()? as i32;
+        // units type, width, length, height, ballast factor, future
+        // use, and input watts
+        for _ in 0..7 {
+            next()?;
+        }
+        if photometric_type != 1 {
+            println!(
+                "WARNING: IES photometric type {} treated as type C",
+                photometric_type
+            );
+        }
+        if n_vertical == 0 || n_horizontal == 0 {
+            return Err(String::from("no angles"));
+        }
+        let mut vertical_angles: Vec<Float> = Vec::with_capacity(n_vertical);
+        for _ in 0..n_vertical {
+            vertical_angles.push(next()?);
+        }
+        let mut horizontal_angles: Vec<Float> = Vec::with_capacity(n_horizontal);
+        for _ in 0..n_horizontal {
+            horizontal_angles.push(next()?);
+        }
+        let mut candela: Vec<Float> = Vec::with_capacity(n_vertical * n_horizontal);
+        for _ in 0..(n_vertical * n_horizontal) {
+            candela.push(next()? * multiplier);
+        }
+        Ok(IESProfile {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+        })
+    }
+    /// Interpolates the candela table bilinearly at the vertical
+    /// angle _theta_ and the horizontal angle _phi_ (in degrees).
+    pub fn lookup(&self, theta: Float, phi: Float) -> Float {
+        let nv: usize = self.vertical_angles.len();
+        let nh: usize = self.horizontal_angles.len();
+        if theta < self.vertical_angles[0] || theta > self.vertical_angles[nv - 1] {
+            return 0.0 as Float;
+        }
+        let (iv, tv) = interval(&self.vertical_angles, theta);
+        if nh == 1 {
+            // rotationally symmetric
+            return self.lerp_vertical(0, iv, tv);
+        }
+        // use the symmetry implied by the last horizontal angle
+        let mut phi: Float = phi % 360.0 as Float;
+        if phi < 0.0 as Float {
+            phi += 360.0 as Float;
+        }
+        let last: Float = self.horizontal_angles[nh - 1];
+        if last <= 180.0 as Float && phi > 180.0 as Float {
+            phi = 360.0 as Float - phi;
+        }
+        if last <= 90.0 as Float && phi > 90.0 as Float {
+            phi = 180.0 as Float - phi;
+        }
+        let (ih, th) = interval(&self.horizontal_angles, phi);
+        (1.0 as Float - th) * self.lerp_vertical(ih, iv, tv)
+            + th * self.lerp_vertical(ih + 1, iv, tv)
+    }
+    /// Returns the candela value in the direction _w_ (in the
+    /// coordinate system of the luminaire, with the main direction
+    /// along +z).
+    pub fn intensity(&self, w: &Vector3f) -> Float {
+        self.lookup(degrees(spherical_theta(w)), degrees(spherical_phi(w)))
+    }
+    /// Integrates the candela values over the sphere of directions.
+    pub fn flux(&self) -> Float {
+        let n_theta: usize = 180;
+        let n_phi: usize = 360;
+        let d_theta: Float = PI / n_theta as Float;
+        let d_phi: Float = 2.0 as Float * PI / n_phi as Float;
+        let mut sum: Float = 0.0 as Float;
+        for it in 0..n_theta {
+            let theta: Float = (it as Float + 0.5 as Float) * d_theta;
+            for ip in 0..n_phi {
+                let phi: Float = (ip as Float + 0.5 as Float) * d_phi;
+                sum += self.lookup(degrees(theta), degrees(phi)) * theta.sin();
+            }
+        }
+        sum * d_theta * d_phi
+    }
+    fn lerp_vertical(&self, ih: usize, iv: usize, tv: Float) -> Float {
+        let nv: usize = self.vertical_angles.len();
+        let row: &[Float] = &self.candela[ih * nv..(ih + 1) * nv];
+        if tv == 0.0 as Float {
+            row[iv]
+        } else {
+            (1.0 as Float - tv) * row[iv] + tv * row[iv + 1]
+        }
+    }
+}
+
+/// Finds the interval of _angles_ containing _angle_ and the offset
+/// within it (clamped to the first and last angle).
+fn interval(angles: &[Float], angle: Float) -> (usize, Float) {
+    if angles.len() == 1 {
+        return (0, 0.0 as Float);
+    }
+    let i: usize = find_interval(angles.len() as i32, |i| angles[i as usize] <= angle) as usize;
+    let width: Float = angles[i + 1] - angles[i];
+    let t: Float = if width > 0.0 as Float {
+        ((angle - angles[i]) / width)
+            .max(0.0 as Float)
+            .min(1.0 as Float)
+    } else {
+        0.0 as Float
+    };
+    (i, t)
+}
+
+/// A point light whose emission is given by an **IESProfile**.
+#[derive(Clone)]
+pub struct IESLight {
+    pub p_light: Point3f,
+    pub i: Spectrum,
+    pub profile: Arc<IESProfile>,
+    pub flux: Float,
+    // inherited from class Light (see light.h)
+    pub flags: u8,
+    pub n_samples: i32,
+    pub medium_interface: MediumInterface,
+    pub light_to_world: Transform,
+    pub world_to_light: Transform,
+}
+
+impl IESLight {
+    pub fn new(
+        light_to_world: &Transform,
+        medium_interface: &MediumInterface,
+        i: &Spectrum,
+        profile: Arc<IESProfile>,
+    ) -> Self {
+        let mut inside: Option<Arc<Medium>> = None;
+        let mut outside: Option<Arc<Medium>> = None;
+        if let Some(ref mi_inside) = medium_interface.inside {
+            inside = Some(mi_inside.clone());
+        }
+        if let Some(ref mi_outside) = medium_interface.outside {
+            outside = Some(mi_outside.clone());
+        }
+        let flux: Float = profile.flux();
+        IESLight {
+            p_light: light_to_world.transform_point(&Point3f::default()),
+            i: *i,
+            profile,
+            flux,
+            flags: LightFlags::DeltaPosition as u8,
+            n_samples: 1_i32,
+            medium_interface: MediumInterface { inside, outside },
+            light_to_world: *light_to_world,
+            world_to_light: Transform::inverse(light_to_world),
+        }
+    }
+    /// Creates an **IESLight** from the parameters _filename_, _I_,
+    /// _scale_, _from_, and _to_ (the main direction of the
+    /// luminaire). The emitted intensity is the candela value of the
+    /// profile times _I_. A relative _filename_ is looked up in the
+    /// _search_directory_.
+    pub fn create(
+        params: &ParamSet,
+        light_to_world: &Transform,
+        medium_interface: &MediumInterface,
+        search_directory: Option<&Path>,
+    ) -> Option<IESLight> {
+        let filename: String = params.find_one_filename("filename", String::from(""));
+        if filename == "" {
+            println!("ERROR: \"ies\" light needs a \"filename\"");
+            return None;
+        }
+        let mut path: std::path::PathBuf = std::path::PathBuf::from(&filename);
+        if path.is_relative() {
+            if let Some(search_directory) = search_directory {
+                path = search_directory.join(&filename);
+            }
+        }
+        let i: Spectrum = params.find_one_illuminant(
+            "I",
+            Spectrum::from_rgb_type(&[1.0 as Float; 3], SpectrumType::Illuminant),
+        );
+        let sc: Spectrum = params.find_one_spectrum("scale", Spectrum::new(1.0 as Float))
+            * find_light_tint(params);
+        let from: Point3f = params.find_one_point3f("from", Point3f::default());
+        let to: Point3f = params.find_one_point3f(
+            "to",
+            Point3f {
+                x: 0.0,
+                y: 0.0,
+                z: -1.0,
+            },
+        );
+        // the main direction of the luminaire becomes +z
+        let dir: Vector3f = (to - from).normalize();
+        let mut du: Vector3f = Vector3f::default();
+        let mut dv: Vector3f = Vector3f::default();
+        vec3_coordinate_system(&dir, &mut du, &mut dv);
+        let dir_to_z: Transform = Transform::new(
+            du.x, du.y, du.z, 0.0, dv.x, dv.y, dv.z, 0.0, dir.x, dir.y, dir.z, 0.0, 0.0, 0.0, 0.0,
+            1.0,
+        );
+        let light2world: Transform = *light_to_world
+            * Transform::translate(&Vector3f {
+                x: from.x,
+                y: from.y,
+                z: from.z,
+            })
+            * Transform::inverse(&dir_to_z);
+        let profile: Arc<IESProfile> = Arc::new(IESProfile::load(&path));
+        Some(IESLight::new(
+            &light2world,
+            medium_interface,
+            &(i * sc),
+            profile,
+        ))
+    }
+    pub fn scale(&self, w: &Vector3f) -> Float {
+        let wl: Vector3f = self.world_to_light.transform_vector(w).normalize();
+        self.profile.intensity(&wl)
+    }
+    // Light
+    /// Interpolates the candela table in the direction from the
+    /// light to the shading point.
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Point2f, Point3f, Vector3f};
+    /// use pbrt::core::interaction::InteractionCommon;
+    /// use pbrt::core::light::VisibilityTester;
+    /// use pbrt::core::medium::MediumInterface;
+    /// use pbrt::core::pbrt::{Float, Spectrum};
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::lights::ies::{IESLight, IESProfile};
+    /// use std::sync::Arc;
+    ///
+    /// let text = "IESNA:LM-63-2002\n\
+    ///             TILT=NONE\n\
+    ///             1 1000 1 3 1 1 2 0 0 0\n\
+    ///             1 1 100\n\
+    ///             0 45 90\n\
+    ///             0\n\
+    ///             1000 500 0\n";
+    /// let profile = Arc::new(IESProfile::parse(text).unwrap());
+    /// // pointing along +z
+    /// let light = IESLight::new(
+    ///     &Transform::default(),
+    ///     &MediumInterface::default(),
+    ///     &Spectrum::new(1.0 as Float),
+    ///     profile,
+    /// );
+    /// let mut iref = InteractionCommon::default();
+    /// let mut wi = Vector3f::default();
+    /// let mut pdf: Float = 0.0;
+    /// let mut vis = VisibilityTester::default();
+    /// // 45 degrees off the main direction, at distance sqrt(2)
+    /// iref.p = Point3f { x: 1.0, y: 0.0, z: 1.0 };
+    /// let li = light.sample_li(&iref, &Point2f::default(), &mut wi, &mut pdf, &mut vis);
+    /// assert!((li.y() / Spectrum::new(1.0 as Float).y() - 250.0).abs() < 1e-2);
+    /// ```
+    pub fn sample_li(
+        &self,
+        iref: &InteractionCommon,
+        _u: &Point2f,
+        wi: &mut Vector3f,
+        pdf: &mut Float,
+        vis: &mut VisibilityTester,
+    ) -> Spectrum {
+        *wi = (self.p_light - iref.p).normalize();
+        *pdf = 1.0 as Float;
+        *vis = VisibilityTester {
+            p0: InteractionCommon {
+                p: iref.p,
+                time: iref.time,
+                p_error: iref.p_error,
+                wo: iref.wo,
+                n: iref.n,
+                medium_interface: iref.medium_interface.clone(),
+            },
+            p1: InteractionCommon {
+                p: self.p_light,
+                time: iref.time,
+                p_error: Vector3f::default(),
+                wo: Vector3f::default(),
+                n: Normal3f::default(),
+                medium_interface: None,
+            },
+        };
+        self.i * self.scale(&-*wi) / pnt3_distance_squared(&self.p_light, &iref.p)
+    }
+    pub fn power(&self) -> Spectrum {
+        self.i * self.flux
+    }
+    pub fn preprocess(&self, _scene: &Scene) {}
+    /// Default implementation returns no emitted radiance for a ray
+    /// that escapes the scene bounds.
+    pub fn le(&self, _ray: &mut Ray) -> Spectrum {
+        Spectrum::new(0.0 as Float)
+    }
+    pub fn pdf_li(&self, _iref: &dyn Interaction, _wi: Vector3f) -> Float {
+        0.0 as Float
+    }
+    pub fn sample_le(
+        &self,
+        u1: &Point2f,
+        _u2: &Point2f,
+        time: Float,
+        ray: &mut Ray,
+        n_light: &mut Normal3f,
+        pdf_pos: &mut Float,
+        pdf_dir: &mut Float,
+    ) -> Spectrum {
+        *ray = Ray {
+            o: self.p_light,
+            d: uniform_sample_sphere(u1),
+            t_max: std::f32::INFINITY,
+            time,
+            differential: None,
+            medium: None,
+        };
+        *n_light = Normal3f::from(ray.d);
+        *pdf_pos = 1.0 as Float;
+        *pdf_dir = uniform_sphere_pdf();
+        self.i * self.scale(&ray.d)
+    }
+    pub fn get_flags(&self) -> u8 {
+        self.flags
+    }
+    pub fn get_n_samples(&self) -> i32 {
+        self.n_samples
+    }
+    pub fn pdf_le(
+        &self,
+        _ray: &Ray,
+        _n_light: &Normal3f,
+        pdf_pos: &mut Float,
+        pdf_dir: &mut Float,
+    ) {
+        *pdf_pos = 0.0 as Float;
+        *pdf_dir = uniform_sphere_pdf();
+    }
+}
@@ -2,47 +2,23 @@
 use std::f32::consts::PI;
 use std::io::BufReader;
 use std::sync::{Arc, RwLock};
-// others
-#[cfg(feature = "openexr")]
-use half::f16;
-#[cfg(feature = "openexr")]
-use openexr::{FrameBufferMut, InputFile, PixelType};
 // pbrt
 use crate::core::geometry::{spherical_phi, spherical_theta, vec3_coordinate_system};
-use crate::core::geometry::{Bounds3f, Normal3f, Point2f, Point2i, Point3f, Ray, Vector3f};
+use crate::core::geometry::{Normal3f, Point2f, Point2i, Point3f, Ray, Vector3f};
+use crate::core::imageio::read_exr_rgb;
 use crate::core::interaction::{Interaction, InteractionCommon};
 use crate::core::light::{LightFlags, VisibilityTester};
 use crate::core::medium::MediumInterface;
 use crate::core::mipmap::{ImageWrap, MipMap};
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::pbrt::{INV_2_PI, INV_PI};
-use crate::core::sampling::concentric_sample_disk;
 use crate::core::sampling::Distribution2D;
+use crate::core::sampling::{concentric_sample_disk, uniform_sample_sphere, uniform_sphere_pdf};
 use crate::core::scene::Scene;
+use crate::core::spectrum::SpectrumType;
+use crate::core::stats::{Prof, ProfilePhase};
 use crate::core::transform::Transform;
 
-// see https://stackoverflow.com/questions/36008434/how-can-i-decode-f16-to-f32-using-only-the-stable-standard-library
-#[inline]
-#[cfg(feature = "openexr")]
-fn decode_f16(half: u16) -> f32 {
-    let exp: u16 = half >> 10 & 0x1f;
-    let mant: u16 = half & 0x3ff;
-    let val: f32 = if exp == 0 {
-        (mant as f32) * (2.0f32).powi(-24)
-    } else if exp != 31 {
-        (mant as f32 + 1024f32) * (2.0f32).powi(exp as i32 - 25)
-    } else if mant == 0 {
-        ::std::f32::INFINITY
-    } else {
-        ::std::f32::NAN
-    };
-    if half & 0x8000 != 0 {
-        -val
-    } else {
-        val
-    }
-}
-
 // see infinte.h
 
 pub struct InfiniteAreaLight {
@@ -60,115 +36,70 @@ pub struct InfiniteAreaLight {
 }
 
 impl InfiniteAreaLight {
-    #[cfg(not(feature = "openexr"))]
-    pub fn new(light_to_world: &Transform, l: &Spectrum, n_samples: i32, texmap: String) -> Self {
-        InfiniteAreaLight::new_hdr(light_to_world, l, n_samples, texmap)
-    }
-    #[cfg(feature = "openexr")]
     pub fn new(light_to_world: &Transform, l: &Spectrum, n_samples: i32, texmap: String) -> Self {
+        if !texmap.to_lowercase().ends_with(".exr") {
+            return InfiniteAreaLight::new_hdr(light_to_world, l, n_samples, texmap);
+        }
         // read texel data from _texmap_ and initialize _Lmap_
-        if texmap != String::from("") {
-            // https://cessen.github.io/openexr-rs/openexr/index.html
-            let mut resolution: Point2i = Point2i::default();
-            let mut names_and_fills: Vec<(&str, f64)> = Vec::new();
-            // header
-            let file_result = std::fs::File::open(texmap.clone());
-            if file_result.is_ok() {
-                let mut file = file_result.unwrap();
-                let input_file_result = InputFile::new(&mut file);
-                if input_file_result.is_ok() {
-                    let input_file = input_file_result.unwrap();
-                    // get resolution
-                    let (width, height) = input_file.header().data_dimensions();
-                    resolution.x = width as i32;
-                    resolution.y = height as i32;
-                    // make sure the image properties are the same (see incremental_io.rs in github/openexr-rs)
-                    for channel_name in ["R", "G", "B"].iter() {
-                        let channel = input_file
-                            .header()
-                            .get_channel(channel_name)
-                            .expect(&format!("Didn't find channel {}.", channel_name));
-                        assert!(channel.pixel_type == PixelType::HALF);
-                        names_and_fills.push((channel_name, 0.0_f64));
-                    }
-                    let mut pixel_data =
-                        vec![
-                            (f16::from_f32(0.0), f16::from_f32(0.0), f16::from_f32(0.0));
-                            (resolution.x * resolution.y) as usize
-                        ];
-                    {
-                        // read pixels
-                        let mut file = std::fs::File::open(texmap.clone()).unwrap();
-                        let mut input_file = InputFile::new(&mut file).unwrap();
-                        let mut fb = FrameBufferMut::new(resolution.x as u32, resolution.y as u32);
-                        fb.insert_channels(&names_and_fills[..], &mut pixel_data);
-                        input_file.read_pixels(&mut fb).unwrap();
-                    }
-                    // convert pixel data into Vec<Spectrum> (and on the way multiply by _l_)
-                    let mut texels: Vec<Spectrum> = Vec::new();
-                    for i in 0..(resolution.x * resolution.y) {
-                        let (r, g, b) = pixel_data[i as usize];
-                        texels.push(
-                            Spectrum::rgb(
-                                decode_f16(r.to_bits()),
-                                decode_f16(g.to_bits()),
-                                decode_f16(b.to_bits()),
-                            ) * *l,
-                        );
-                    }
-                    // create _MipMap_ from converted texels (see above)
-                    let do_trilinear: bool = false;
-                    let max_aniso: Float = 8.0 as Float;
-                    let wrap_mode: ImageWrap = ImageWrap::Repeat;
-                    let lmap = Arc::new(MipMap::new(
-                        &resolution,
-                        &texels[..],
-                        do_trilinear,
-                        max_aniso,
-                        wrap_mode,
-                    ));
+        let _phase = ProfilePhase::new(Prof::TextureLoading);
+        match read_exr_rgb(&texmap) {
+            Ok((resolution, rgb)) => {
+                // convert pixel data into Vec<Spectrum> (and on the way multiply by _l_)
+                let texels: Vec<Spectrum> = rgb
+                    .iter()
+                    .map(|rgb| Spectrum::from_rgb_type(rgb, SpectrumType::Illuminant) * *l)
+                    .collect();
+                // create _MipMap_ from converted texels (see above)
+                let do_trilinear: bool = false;
+                let max_aniso: Float = 8.0 as Float;
+                let wrap_mode: ImageWrap = ImageWrap::Repeat;
+                let lmap = Arc::new(MipMap::new(
+                    &resolution,
+                    &texels[..],
+                    do_trilinear,
+                    max_aniso,
+                    wrap_mode,
+                ));
 
-                    // initialize sampling PDFs for infinite area light
+                // initialize sampling PDFs for infinite area light
 
-                    // compute scalar-valued image _img_ from environment map
-                    let width: i32 = 2_i32 * lmap.width();
-                    let height: i32 = 2_i32 * lmap.height();
-                    let mut img: Vec<Float> = Vec::new();
-                    let fwidth: Float = 0.5 as Float / (width as Float).min(height as Float);
-                    // TODO: ParallelFor(...) {...}
-                    for v in 0..height {
-                        let vp: Float = (v as Float + 0.5 as Float) / height as Float;
-                        let sin_theta: Float =
-                            (PI * (v as Float + 0.5 as Float) / height as Float).sin();
-                        for u in 0..width {
-                            let up: Float = (u as Float + 0.5 as Float) / width as Float;
-                            let st: Point2f = Point2f { x: up, y: vp };
-                            img.push(lmap.lookup_pnt_flt(&st, fwidth).y() * sin_theta);
-                        }
+                // compute scalar-valued image _img_ from environment map
+                let width: i32 = 2_i32 * lmap.width();
+                let height: i32 = 2_i32 * lmap.height();
+                let mut img: Vec<Float> = Vec::new();
+                let fwidth: Float = 0.5 as Float / (width as Float).min(height as Float);
+                // TODO: ParallelFor(...) {...}
+                for v in 0..height {
+                    let vp: Float = (v as Float + 0.5 as Float) / height as Float;
+                    let sin_theta: Float =
+                        (PI * (v as Float + 0.5 as Float) / height as Float).sin();
+                    for u in 0..width {
+                        let up: Float = (u as Float + 0.5 as Float) / width as Float;
+                        let st: Point2f = Point2f { x: up, y: vp };
+                        img.push(lmap.lookup_pnt_flt(&st, fwidth).y() * sin_theta);
                     }
-                    let distribution: Arc<Distribution2D> =
-                        Arc::new(Distribution2D::new(img, width, height));
-                    InfiniteAreaLight {
-                        lmap,
-                        world_center: RwLock::new(Point3f::default()),
-                        world_radius: RwLock::new(0.0),
-                        distribution,
-                        flags: LightFlags::Infinite as u8,
-                        n_samples: std::cmp::max(1_i32, n_samples),
-                        medium_interface: MediumInterface::default(),
-                        light_to_world: *light_to_world,
-                        world_to_light: Transform::inverse(&*light_to_world),
-                    }
-                } else {
-                    // try to open an HDR image instead (TODO: check extension upfront)
-                    InfiniteAreaLight::new_hdr(light_to_world, l, n_samples, texmap)
                 }
-            } else {
-                // try to open an HDR image instead (TODO: check extension upfront)
-                InfiniteAreaLight::new_hdr(light_to_world, l, n_samples, texmap)
+                let distribution: Arc<Distribution2D> =
+                    Arc::new(Distribution2D::new(img, width, height));
+                InfiniteAreaLight {
+                    lmap,
+                    world_center: RwLock::new(Point3f::default()),
+                    world_radius: RwLock::new(0.0),
+                    distribution,
+                    flags: LightFlags::Infinite as u8,
+                    n_samples: std::cmp::max(1_i32, n_samples),
+                    medium_interface: MediumInterface::default(),
+                    light_to_world: *light_to_world,
+                    world_to_light: Transform::inverse(&*light_to_world),
+                }
+            }
+            Err(e) => {
+                println!(
+                    "WARNING: InfiniteAreaLight::new() ... unable to read {:?}: {}",
+                    texmap, e
+                );
+                InfiniteAreaLight::default(n_samples, l)
             }
-        } else {
-            InfiniteAreaLight::default(n_samples, l)
         }
     }
     pub fn new_hdr(
@@ -178,6 +109,7 @@ impl InfiniteAreaLight {
         texmap: String,
     ) -> Self {
         // read texel data from _texmap_ and initialize _Lmap_
+        let _phase = ProfilePhase::new(Prof::TextureLoading);
         if texmap != String::from("") {
             let file = std::fs::File::open(texmap.clone()).unwrap();
             let reader = BufReader::new(file);
@@ -194,7 +126,10 @@ impl InfiniteAreaLight {
                     let img_result = hdr.read_image_transform(
                         |p| {
                             let rgb = p.to_hdr();
-                            Spectrum::rgb(rgb[0], rgb[1], rgb[2]) * *l
+                            Spectrum::from_rgb_type(
+                                &[rgb[0], rgb[1], rgb[2]],
+                                SpectrumType::Illuminant,
+                            ) * *l
                         },
                         &mut texels,
                     );
@@ -245,7 +180,10 @@ impl InfiniteAreaLight {
                     }
                 }
             } else {
-                println!("WARNING: InfiniteAreaLight::new() ... no OpenEXR support !!!");
+                println!(
+                    "WARNING: InfiniteAreaLight::new() ... unable to read {:?}",
+                    texmap
+                );
             }
         }
         InfiniteAreaLight::default(n_samples, l)
@@ -352,7 +290,6 @@ impl InfiniteAreaLight {
                 medium_interface: Some(Arc::new(MediumInterface::default())),
             },
         };
-        // TODO: SpectrumType::Illuminant
         self.lmap.lookup_pnt_flt(&uv, 0.0 as Float)
     }
     /// Like directional lights, the total power from the infinite
@@ -361,7 +298,6 @@ impl InfiniteAreaLight {
     pub fn power(&self) -> Spectrum {
         let p: Point2f = Point2f { x: 0.5, y: 0.5 };
         let world_radius: Float = *self.world_radius.read().unwrap();
-        // TODO: SpectrumType::Illuminant
         self.lmap.lookup_pnt_flt(&p, 0.5 as Float) * Spectrum::new(PI * world_radius * world_radius)
     }
     /// Like **DistanceLights**, **InfiniteAreaLights** also need the
@@ -369,13 +305,9 @@ impl InfiniteAreaLight {
     /// the scene bounds after all of the scene geometry has been
     /// created.
     pub fn preprocess(&self, scene: &Scene) {
-        let mut world_center_ref = self.world_center.write().unwrap();
-        let mut world_radius_ref = self.world_radius.write().unwrap();
-        Bounds3f::bounding_sphere(
-            &scene.world_bound(),
-            &mut world_center_ref,
-            &mut world_radius_ref,
-        );
+        let (world_center, world_radius) = scene.bounding_sphere();
+        *self.world_center.write().unwrap() = world_center;
+        *self.world_radius.write().unwrap() = world_radius;
     }
     /// Because infinte area lights need to be able to contribute
     /// radiance to rays that don't hit any geometry in the scene,
@@ -389,7 +321,6 @@ impl InfiniteAreaLight {
             x: spherical_phi(&w) * INV_2_PI,
             y: spherical_theta(&w) * INV_PI,
         };
-        // TODO: SpectrumType::Illuminant
         self.lmap.lookup_pnt_flt(&st, 0.0 as Float)
     }
     pub fn pdf_li(&self, _iref: &dyn Interaction, w: Vector3f) -> Float {
@@ -460,7 +391,6 @@ impl InfiniteAreaLight {
             *pdf_dir = map_pdf / (2.0 as Float * PI * PI * sin_theta);
         }
         *pdf_pos = 1.0 as Float / (PI * world_radius * world_radius);
-        // TODO: return Spectrum(Lmap->Lookup(uv), SpectrumType::Illuminant);
         self.lmap.lookup_pnt_flt(&uv, 0.0 as Float)
     }
     pub fn pdf_le(&self, ray: &Ray, _n_light: &Normal3f, pdf_pos: &mut Float, pdf_dir: &mut Float) {
@@ -483,3 +413,142 @@ impl InfiniteAreaLight {
         self.n_samples
     }
 }
+
+/// An infinite area light with the same radiance _l_ from all
+/// directions, e.g. an overcast sky. Unlike an **InfiniteAreaLight**
+/// without an environment map, it needs no (constant) image and
+/// sampling distribution, directions get sampled uniformly over the
+/// sphere of directions.
+///
+/// Rays escaping the scene see just _l_:
+///
+/// ```rust
+/// use pbrt::core::geometry::Ray;
+/// use pbrt::core::pbrt::{Float, Spectrum};
+/// use pbrt::lights::infinite::ConstantInfiniteLight;
+///
+/// let l: Spectrum = Spectrum::rgb(0.2, 0.4, 0.6);
+/// let light = ConstantInfiniteLight::new(&l, 1);
+/// let mut ray: Ray = Ray::default();
+/// let mut rgb: [Float; 3] = [0.0; 3];
+/// light.le(&mut ray).to_rgb(&mut rgb);
+/// assert!((rgb[0] - 0.2).abs() < 1e-3);
+/// assert!((rgb[1] - 0.4).abs() < 1e-3);
+/// assert!((rgb[2] - 0.6).abs() < 1e-3);
+/// ```
+pub struct ConstantInfiniteLight {
+    pub l: Spectrum,
+    pub world_center: RwLock<Point3f>,
+    pub world_radius: RwLock<Float>,
+    // inherited from class Light (see light.h)
+    pub flags: u8,
+    pub n_samples: i32,
+}
+
+impl ConstantInfiniteLight {
+    pub fn new(l: &Spectrum, n_samples: i32) -> Self {
+        ConstantInfiniteLight {
+            l: *l,
+            world_center: RwLock::new(Point3f::default()),
+            world_radius: RwLock::new(0.0),
+            flags: LightFlags::Infinite as u8,
+            n_samples: std::cmp::max(1_i32, n_samples),
+        }
+    }
+    // Light
+    pub fn sample_li(
+        &self,
+        iref: &InteractionCommon,
+        u: &Point2f,
+        wi: &mut Vector3f,
+        pdf: &mut Float,
+        vis: &mut VisibilityTester,
+    ) -> Spectrum {
+        *wi = uniform_sample_sphere(u);
+        *pdf = uniform_sphere_pdf();
+        let world_radius: Float = *self.world_radius.read().unwrap();
+        *vis = VisibilityTester {
+            p0: InteractionCommon {
+                p: iref.p,
+                time: iref.time,
+                p_error: iref.p_error,
+                wo: iref.wo,
+                n: iref.n,
+                medium_interface: iref.medium_interface.clone(),
+            },
+            p1: InteractionCommon {
+                p: iref.p + *wi * (2.0 as Float * world_radius),
+                time: iref.time,
+                p_error: Vector3f::default(),
+                wo: Vector3f::default(),
+                n: Normal3f::default(),
+                medium_interface: Some(Arc::new(MediumInterface::default())),
+            },
+        };
+        self.l
+    }
+    pub fn power(&self) -> Spectrum {
+        let world_radius: Float = *self.world_radius.read().unwrap();
+        self.l * Spectrum::new(PI * world_radius * world_radius)
+    }
+    pub fn preprocess(&self, scene: &Scene) {
+        let (world_center, world_radius) = scene.bounding_sphere();
+        *self.world_center.write().unwrap() = world_center;
+        *self.world_radius.write().unwrap() = world_radius;
+    }
+    pub fn le(&self, _ray: &mut Ray) -> Spectrum {
+        self.l
+    }
+    pub fn pdf_li(&self, _iref: &dyn Interaction, _wi: Vector3f) -> Float {
+        uniform_sphere_pdf()
+    }
+    pub fn sample_le(
+        &self,
+        u1: &Point2f,
+        u2: &Point2f,
+        time: Float,
+        ray: &mut Ray,
+        n_light: &mut Normal3f,
+        pdf_pos: &mut Float,
+        pdf_dir: &mut Float,
+    ) -> Spectrum {
+        let d: Vector3f = -uniform_sample_sphere(u1);
+        *n_light = Normal3f::from(d);
+        // compute origin for infinite light sample ray
+        let mut v1: Vector3f = Vector3f::default();
+        let mut v2: Vector3f = Vector3f::default();
+        vec3_coordinate_system(&-d, &mut v1, &mut v2);
+        let cd: Point2f = concentric_sample_disk(u2);
+        let world_center: Point3f = *self.world_center.read().unwrap();
+        let world_radius: Float = *self.world_radius.read().unwrap();
+        let p_disk: Point3f = world_center + (v1 * cd.x + v2 * cd.y) * world_radius;
+        *ray = Ray {
+            o: p_disk + -d * world_radius,
+            d,
+            t_max: std::f32::INFINITY,
+            time,
+            differential: None,
+            medium: None,
+        };
+        *pdf_dir = uniform_sphere_pdf();
+        *pdf_pos = 1.0 as Float / (PI * world_radius * world_radius);
+        self.l
+    }
+    pub fn pdf_le(
+        &self,
+        _ray: &Ray,
+        _n_light: &Normal3f,
+        pdf_pos: &mut Float,
+        pdf_dir: &mut Float,
+    ) {
+        let world_radius: Float = *self.world_radius.read().unwrap();
+        *pdf_dir = uniform_sphere_pdf();
+        *pdf_pos = 1.0 as Float / (PI * world_radius * world_radius);
+    }
+    pub fn get_flags(&self) -> u8 {
+        self.flags
+    }
+    pub fn get_n_samples(&self) -> i32 {
+        self.n_samples
+    }
+}
@@ -2,9 +2,11 @@
 //! source of illumination so that some light is reflected from them
 //! to the camera sensor.
 //!
+//! - ConstantInfiniteLight
 //! - DiffuseAreaLight
 //! - DistantLight
 //! - GonioPhotometricLight
+//! - IESLight
 //! - InfiniteAreaLight
 //! - PointLight
 //! - ProjectionLight
@@ -32,6 +34,11 @@
 //!
 //! TODO
 //!
+//! ## IES Lights
+//!
+//! A point light whose directional distribution of intensity is read
+//! from an IESNA LM-63 (.ies) photometric data file.
+//!
 //! ## Infinite Area Lights
 //!
 //! Area lights are light sources defined by one or more **Shapes**
@@ -42,6 +49,9 @@
 //! scene. One way to visualize this light is as an enormous sphere
 //! that casts light into the scene from every direction.
 //!
+//! Without an environment map, a **ConstantInfiniteLight** is used
+//! instead, which emits the same radiance in all directions.
+//!
 //! ## Point Lights
 //!
 //! Isotropic point light source that emits the same amount of light
@@ -59,6 +69,7 @@
 pub mod diffuse;
 pub mod distant;
 pub mod goniometric;
+pub mod ies;
 pub mod infinite;
 pub mod point;
 pub mod projection;
@@ -67,7 +67,7 @@ impl PointLight {
                 p_error: iref.p_error,
                 wo: iref.wo,
                 n: iref.n,
-                medium_interface: None,
+                medium_interface: iref.medium_interface.clone(),
             },
             p1: InteractionCommon {
                 p: self.p_light,
@@ -14,6 +14,12 @@ use crate::core::reflection::{Bsdf, Bxdf, BxdfType};
 use crate::core::texture::Texture;
 use crate::textures::constant::ConstantTexture;
 
+/// Hair and fur, scattering light like a rough dielectric cylinder
+/// (see **HairBSDF**). The absorption inside the fiber is given either
+/// directly (_sigma_a_), by the color of the hair (_color_), or by the
+/// concentrations of the pigments _eumelanin_ and _pheomelanin_. The
+/// hair is expected to be rendered with curves, using the _v_
+/// coordinate across the curve as offset from the fiber's center.
 pub struct HairMaterial {
     pub sigma_a: Option<Arc<dyn Texture<Spectrum> + Sync + Send>>,
     pub color: Option<Arc<dyn Texture<Spectrum> + Sync + Send>>,
@@ -141,7 +147,8 @@ impl HairMaterial {
         }
         let bm: Float = self.beta_m.evaluate(si);
         let bn: Float = self.beta_n.evaluate(si);
-        let a: Float = radians(self.alpha.evaluate(si));
+        // in degrees, converted by HairBSDF::new()
+        let a: Float = self.alpha.evaluate(si);
         let e: Float = self.eta.evaluate(si);
         let sig_a: Spectrum;
         if let Some(ref sigma_a) = self.sigma_a {
@@ -182,6 +189,48 @@ impl HairMaterial {
 pub const P_MAX: u8 = 3_u8;
 pub const SQRT_PI_OVER_8: Float = 0.626657069 as Float;
 
+/// The hair scattering model by Chiang et al. (based on the ones by
+/// Marschner et al. and d'Eon et al.): light gets reflected at the
+/// surface of the fiber (R), transmitted through it (TT), or reflected
+/// once inside it (TRT), the remaining paths are lumped together. Each
+/// lobe is the product of an attenuation, a longitudinal (_beta_m_)
+/// and an azimuthal (_beta_n_) roughness term, the scales on the
+/// fiber's surface tilt the lobes by _alpha_ (in degrees).
+///
+/// Without absorption all the light is scattered (the "white
+/// furnace" test), a white-ish fiber scatters a bit less:
+///
+/// ```rust
+/// use pbrt::core::geometry::Point2f;
+/// use pbrt::core::pbrt::{Float, Spectrum};
+/// use pbrt::core::reflection::abs_cos_theta;
+/// use pbrt::core::rng::Rng;
+/// use pbrt::core::sampling::{uniform_sample_sphere, uniform_sphere_pdf};
+/// use pbrt::materials::hair::HairBSDF;
+///
+/// let mut rng: Rng = Rng::new();
+/// let mut u = || Point2f {
+///     x: rng.uniform_float(),
+///     y: rng.uniform_float(),
+/// };
+/// let wo = uniform_sample_sphere(&u());
+/// let scattered = |sigma_a: Spectrum, u: &mut dyn FnMut() -> Point2f| -> Float {
+///     let count: usize = 20000;
+///     let mut sum: Spectrum = Spectrum::default();
+///     for _i in 0..count {
+///         let h: Float = -1.0 + 2.0 * u().x;
+///         let hair = HairBSDF::new(h, 1.55, sigma_a, 0.3, 0.3, 0.0, None);
+///         let wi = uniform_sample_sphere(&u());
+///         sum += hair.f(&wo, &wi) * abs_cos_theta(&wi);
+///     }
+///     sum.y() / (count as Float * uniform_sphere_pdf())
+/// };
+/// let white: Float = scattered(Spectrum::default(), &mut u);
+/// assert!(white > 0.95 && white < 1.05, "{}", white);
+/// let sigma_a: Spectrum = HairBSDF::sigma_a_from_reflectance(Spectrum::new(0.8), 0.3);
+/// let white_ish: Float = scattered(sigma_a, &mut u);
+/// assert!(white_ish < 1.0 && white_ish < white, "{}", white_ish);
+/// ```
 #[derive(Default, Copy, Clone)]
 pub struct HairBSDF {
     pub h: Float,
@@ -300,6 +349,9 @@ impl HairBSDF {
         }
         ap_pdf
     }
+    /// Absorption coefficient for the given concentrations of
+    /// eumelanin (_ce_, making hair brown or black) and pheomelanin
+    /// (_cp_, making it red).
     pub fn sigma_a_from_concentration(ce: Float, cp: Float) -> Spectrum {
         let mut sigma_a: [Float; 3] = [0.0 as Float; 3];
         let eumelanin_sigma_a: [Float; 3] = [0.419 as Float, 0.697 as Float, 1.37 as Float];
@@ -309,9 +361,12 @@ impl HairBSDF {
         }
         Spectrum::from_rgb(&sigma_a)
     }
+    /// Absorption coefficient which makes a fiber with azimuthal
+    /// roughness _beta_n_ appear to have the color _c_ (after multiple
+    /// scattering).
     pub fn sigma_a_from_reflectance(c: Spectrum, beta_n: Float) -> Spectrum {
         let mut sigma_a: Spectrum = Spectrum::default();
-        for i in 0..3 {
+        for i in 0..Spectrum::N_SAMPLES {
             let sqr: Float = beta_n * beta_n;
             let pow3: Float = sqr * beta_n;
             let pow4: Float = pow3 * beta_n;
@@ -437,13 +492,13 @@ impl HairBSDF {
         let mut u: [Point2f; 2] = [demux_float(sample[0]), demux_float(sample[1])];
         // determine which term $p$ to sample for hair scattering
         let ap_pdf: [Float; (P_MAX + 1) as usize] = self.compute_ap_pdf(cos_theta_o);
-        let mut p: usize = 0;
-        for i in 0..P_MAX {
-            p = i as usize; // store index in p for later
-            if u[0][0] < ap_pdf[p] {
+        let mut p: usize = P_MAX as usize;
+        for i in 0..P_MAX as usize {
+            if u[0][0] < ap_pdf[i] {
+                p = i;
                 break;
             }
-            u[0][0] -= ap_pdf[p];
+            u[0][0] -= ap_pdf[i];
         }
         // sample $M_p$ to compute $\thetai$
         u[1][0] = u[1][0].max(1e-5 as Float);
@@ -542,11 +597,8 @@ impl HairBSDF {
                 self.v[P_MAX as usize],
             )
             * (1.0 as Float / (2.0 as Float * PI));
-        if let Some(sc) = self.sc_opt {
-            sc * self.f(wo, &*wi)
-        } else {
-            self.f(wo, &*wi)
-        }
+        // f() applies the scale
+        self.f(wo, &*wi)
     }
     pub fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> Float {
         // compute hair coordinate system terms related to _wo_
@@ -28,6 +28,7 @@ pub mod disney;
 pub mod fourier;
 pub mod glass;
 pub mod hair;
+pub mod kdsubsurface;
 pub mod matte;
 pub mod metal;
 pub mod mirror;
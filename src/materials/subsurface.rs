@@ -21,6 +21,10 @@ use crate::core::texture::Texture;
 
 // see subsurface.h
 
+/// A dielectric boundary (smooth or rough, depending on the
+/// roughness) with light scattering below the surface, described by a
+/// tabulated BSSRDF for the given absorption and scattering
+/// coefficients.
 pub struct SubsurfaceMaterial {
     pub scale: Float,                                 // default: 1.0
     pub kr: Arc<dyn Texture<Spectrum> + Sync + Send>, // default: 1.0
@@ -65,6 +69,45 @@ impl SubsurfaceMaterial {
             table: Arc::new(table),
         }
     }
+    /// Creates the material from its parameters. A "name" selects
+    /// measured scattering coefficients (see
+    /// **get_medium_scattering_properties()**), _Kr_ and _Kt_ scale
+    /// the reflection and transmission of the boundary:
+    ///
+    /// ```rust
+    /// use pbrt::core::interaction::SurfaceInteraction;
+    /// use pbrt::core::material::Material;
+    /// use pbrt::core::medium::get_medium_scattering_properties;
+    /// use pbrt::core::paramset::{ParamSet, TextureParams};
+    /// use pbrt::core::pbrt::Spectrum;
+    /// use pbrt::core::texture::Texture;
+    /// use pbrt::materials::subsurface::SubsurfaceMaterial;
+    ///
+    /// let mut params: ParamSet = ParamSet::default();
+    /// params.add_string(String::from("name"), String::from("Skin1"));
+    /// params.add_rgb(String::from("Kr"), [0.25; 3]);
+    /// params.add_rgb(String::from("Kt"), [0.75; 3]);
+    /// let mut mp: TextureParams = TextureParams {
+    ///     material_params: params,
+    ///     ..Default::default()
+    /// };
+    /// let material = SubsurfaceMaterial::create(&mut mp);
+    /// if let Material::Subsurface(ref subsurface) = *material {
+    ///     let si: SurfaceInteraction = SurfaceInteraction::default();
+    ///     let kr: Spectrum = subsurface.kr.evaluate(&si);
+    ///     let kt: Spectrum = subsurface.kt.evaluate(&si);
+    ///     assert_eq!(kr, Spectrum::from_rgb(&[0.25; 3]));
+    ///     assert_eq!(kt, Spectrum::from_rgb(&[0.75; 3]));
+    ///     let mut sigma_a: Spectrum = Spectrum::default();
+    ///     let mut sigma_s: Spectrum = Spectrum::default();
+    ///     let name: String = String::from("Skin1");
+    ///     assert!(get_medium_scattering_properties(&name, &mut sigma_a, &mut sigma_s));
+    ///     assert_eq!(subsurface.sigma_a.evaluate(&si), sigma_a);
+    ///     assert_eq!(subsurface.sigma_s.evaluate(&si), sigma_s);
+    /// } else {
+    ///     panic!("not a subsurface material");
+    /// }
+    /// ```
     pub fn create(mp: &mut TextureParams) -> Arc<Material> {
         let sig_a_rgb: [Float; 3] = [0.0011, 0.0024, 0.014];
         let sig_s_rgb: [Float; 3] = [2.55, 3.21, 3.77];
@@ -94,7 +137,7 @@ impl SubsurfaceMaterial {
         let kr: Arc<dyn Texture<Spectrum> + Sync + Send> =
             mp.get_spectrum_texture("Kr", Spectrum::new(1.0));
         let kt: Arc<dyn Texture<Spectrum> + Sync + Send> =
-            mp.get_spectrum_texture("Kr", Spectrum::new(1.0));
+            mp.get_spectrum_texture("Kt", Spectrum::new(1.0));
         let roughu: Arc<dyn Texture<Float> + Sync + Send> =
             mp.get_float_texture("uroughness", 0.0 as Float);
         let roughv: Arc<dyn Texture<Float> + Sync + Send> =
@@ -38,7 +38,8 @@ impl HomogeneousMedium {
     ) -> (Spectrum, Option<MediumInteraction>) {
         // TODO: ProfilePhase _(Prof::MediumSample);
         // sample a channel and distance along the ray
-        let channel: usize = ((sampler.get_1d() * 3.0 as Float) as usize).min(2_usize);
+        let channel: usize = ((sampler.get_1d() * Spectrum::N_SAMPLES as Float) as usize)
+            .min(Spectrum::N_SAMPLES - 1);
         let dist: Float = -((1.0 as Float - sampler.get_1d()).ln()) / self.sigma_t[channel];
         let t: Float = (dist / ray.d.length()).min(ray.t_max);
         let sampled_medium: bool = t < ray.t_max;
@@ -66,11 +67,10 @@ impl HomogeneousMedium {
             density = tr;
         }
         let mut pdf: Float = 0.0 as Float;
-        for i in 0..3 {
-            // TODO: Spectrum::nSamples
+        for i in 0..Spectrum::N_SAMPLES {
             pdf += density[i];
         }
-        pdf *= 1.0 as Float / 3.0 as Float; // TODO: Spectrum::nSamples
+        pdf *= 1.0 as Float / Spectrum::N_SAMPLES as Float;
         if pdf == 0.0 as Float {
             assert!(tr.is_black());
             pdf = 1.0 as Float;
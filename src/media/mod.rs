@@ -5,6 +5,9 @@
 //! - GridDensityMedium
 //! - HomogeneousMedium
 //!
+//! With the `vdb` feature, density grids can be read from NanoVDB
+//! files (see **NanoVdbGrid**).
+//!
 //! ## Grid Density Medium
 //!
 //! ![Smoke from a CFD Simulation](/doc/img/smoke_plume_pbrt_rust_volpath.png)
@@ -15,3 +18,5 @@
 
 pub mod grid;
 pub mod homogeneous;
+#[cfg(feature = "vdb")]
+pub mod nanovdb;
@@ -0,0 +1,345 @@
+//! Reads density grids from NanoVDB files (`.nvdb`, as written by
+//! e.g. `nanovdb_convert`) into a **GridDensityMedium**.
+//!
+//! Only uncompressed files with float grids (NanoVDB version 32) are
+//! supported. OpenVDB files (`.vdb`) have to be converted first.
+
+// std
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Arc;
+// pbrt
+use crate::core::geometry::{Bounds3i, Point3i, Vector3f};
+use crate::core::pbrt::{Float, Spectrum};
+use crate::core::transform::Transform;
+use crate::media::grid::GridDensityMedium;
+
+// see NanoVDB.h (sizes and offsets of the data structures)
+
+const FILE_HEADER_SIZE: usize = 16;
+const FILE_META_DATA_SIZE: usize = 176;
+const GRID_DATA_SIZE: usize = 672;
+const ROOT_DATA_SIZE: usize = 64;
+const ROOT_TILE_SIZE: usize = 32;
+const UPPER_TABLE_OFFSET: usize = 8256;
+const LOWER_TABLE_OFFSET: usize = 1088;
+const LEAF_VALUES_OFFSET: usize = 96;
+const GRID_TYPE_FLOAT: u32 = 1;
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// One float grid of a NanoVDB file.
+pub struct NanoVdbGrid {
+    pub name: String,
+    /// from index space (voxel centers at integer coordinates) to
+    /// world space
+    pub index_to_world: Transform,
+    buffer: Vec<u8>,
+}
+
+impl NanoVdbGrid {
+    /// Reads all grids of the NanoVDB file at _path_.
+    ///
+    /// A tiny grid ("density") with 2x2x2 voxels from index (1, 1, 1)
+    /// to (2, 2, 2), a voxel size of 0.5, and the index space origin at
+    /// (1, 2, 3) in world space:
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Point3f, Point3i};
+    /// use pbrt::core::pbrt::Spectrum;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::media::nanovdb::NanoVdbGrid;
+    ///
+    /// fn put(buffer: &mut Vec<u8>, offset: usize, bytes: &[u8]) {
+    ///     buffer[offset..offset + bytes.len()].copy_from_slice(bytes);
+    /// }
+    /// let version: u32 = (32 << 21) | (3 << 10);
+    /// // grid data, tree data, root (with one tile), upper, lower, and leaf node
+    /// let (root, upper, lower, leaf) = (736, 832, 832 + 270_400, 832 + 270_400 + 33_856);
+    /// let mut grid = vec![0_u8; leaf + 2144];
+    /// put(&mut grid, 0, b"NanoVDB0");
+    /// put(&mut grid, 16, &version.to_le_bytes());
+    /// put(&mut grid, 40, b"density");
+    /// for i in 0..3 {
+    ///     // diagonal of the index to world matrix, and translation
+    ///     put(&mut grid, 384 + 32 * i, &0.5_f64.to_le_bytes());
+    ///     put(&mut grid, 528 + 8 * i, &((i + 1) as f64).to_le_bytes());
+    /// }
+    /// put(&mut grid, 636, &1_u32.to_le_bytes()); // float grid
+    /// put(&mut grid, 672 + 24, &64_u64.to_le_bytes()); // root offset
+    /// for (i, c) in [1_i32, 1, 1, 2, 2, 2].iter().enumerate() {
+    ///     put(&mut grid, root + 4 * i, &c.to_le_bytes());
+    /// }
+    /// put(&mut grid, root + 24, &1_u32.to_le_bytes());
+    /// put(&mut grid, root + 64 + 8, &((upper - root) as i64).to_le_bytes());
+    /// put(&mut grid, upper + 32 + 4096, &[1]); // child mask
+    /// put(&mut grid, upper + 8256, &((lower - upper) as i64).to_le_bytes());
+    /// put(&mut grid, lower + 32 + 512, &[1]); // child mask
+    /// put(&mut grid, lower + 1088, &((leaf - lower) as i64).to_le_bytes());
+    /// for x in 1..3 {
+    ///     for y in 1..3 {
+    ///         for z in 1..3 {
+    ///             let value = (x * 100 + y * 10 + z) as f32;
+    ///             let n = (x << 6) | (y << 3) | z;
+    ///             put(&mut grid, leaf + 96 + 4 * n, &value.to_le_bytes());
+    ///         }
+    ///     }
+    /// }
+    /// // file header, meta data, grid name, and the grid
+    /// let mut file: Vec<u8> = Vec::new();
+    /// file.extend_from_slice(b"NanoVDB0");
+    /// file.extend_from_slice(&version.to_le_bytes());
+    /// file.extend_from_slice(&[1, 0, 0, 0]); // one grid, uncompressed
+    /// let mut meta = vec![0_u8; 176];
+    /// put(&mut meta, 0, &(grid.len() as u64).to_le_bytes());
+    /// put(&mut meta, 8, &(grid.len() as u64).to_le_bytes());
+    /// put(&mut meta, 136, &8_u32.to_le_bytes());
+    /// file.extend_from_slice(&meta);
+    /// file.extend_from_slice(b"density\0");
+    /// file.extend_from_slice(&grid);
+    /// let path = std::env::temp_dir().join("pbrt_tiny.nvdb");
+    /// std::fs::write(&path, &file).unwrap();
+    /// let grids = NanoVdbGrid::read(&path).unwrap();
+    /// std::fs::remove_file(&path).unwrap();
+    /// assert_eq!(grids.len(), 1);
+    /// let grid = &grids[0];
+    /// assert_eq!(grid.name, "density");
+    /// assert_eq!(grid.value(&Point3i { x: 2, y: 1, z: 2 }), 212.0);
+    /// assert_eq!(grid.value(&Point3i { x: 5000, y: 0, z: 0 }), 0.0);
+    /// // the voxel (2, 1, 2) is centered at (1, 2, 3) + 0.5 * (2, 1, 2)
+    /// let sigma = Spectrum::new(1.0);
+    /// let medium = grid.to_medium(&sigma, &sigma, 0.0, &Transform::default());
+    /// assert_eq!((medium.nx, medium.ny, medium.nz), (2, 2, 2));
+    /// let p = medium
+    ///     .world_to_medium
+    ///     .transform_point(&Point3f { x: 2.0, y: 2.5, z: 4.0 });
+    /// assert!((medium.density(&p) - 212.0).abs() < 1e-3);
+    /// ```
+    pub fn read(path: &Path) -> io::Result<Vec<NanoVdbGrid>> {
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "vdb")
+        {
+            return Err(invalid_data(format!(
+                "{:?}: OpenVDB files are not supported, convert to NanoVDB (.nvdb) first",
+                path
+            )));
+        }
+        let mut bytes: Vec<u8> = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        if bytes.len() < FILE_HEADER_SIZE || &bytes[0..7] != b"NanoVDB" {
+            return Err(invalid_data(format!("{:?}: not a NanoVDB file", path)));
+        }
+        let grid_count: usize = read_u16(&bytes, 12) as usize;
+        let mut grids: Vec<NanoVdbGrid> = Vec::with_capacity(grid_count);
+        let mut offset: usize = FILE_HEADER_SIZE;
+        for _ in 0..grid_count {
+            if bytes.len() < offset + FILE_META_DATA_SIZE {
+                return Err(invalid_data(format!("{:?}: truncated file", path)));
+            }
+            let file_size: usize = read_u64(&bytes, offset + 8) as usize;
+            let name_size: usize = read_u32(&bytes, offset + 136) as usize;
+            let codec: u16 = read_u16(&bytes, offset + 168);
+            offset += FILE_META_DATA_SIZE + name_size;
+            if bytes.len() < offset + file_size {
+                return Err(invalid_data(format!("{:?}: truncated file", path)));
+            }
+            if codec != 0 {
+                return Err(invalid_data(format!(
+                    "{:?}: compressed grids are not supported",
+                    path
+                )));
+            }
+            grids.push(NanoVdbGrid::from_buffer(
+                bytes[offset..offset + file_size].to_vec(),
+            )?);
+            offset += file_size;
+        }
+        Ok(grids)
+    }
+    /// A grid from the (uncompressed) grid buffer of a NanoVDB file.
+    pub fn from_buffer(buffer: Vec<u8>) -> io::Result<NanoVdbGrid> {
+        if buffer.len() < GRID_DATA_SIZE + 64 + ROOT_DATA_SIZE || &buffer[0..7] != b"NanoVDB" {
+            return Err(invalid_data(String::from("not a NanoVDB grid")));
+        }
+        let major_version: u32 = read_u32(&buffer, 16) >> 21;
+        if major_version != 32 {
+            return Err(invalid_data(format!(
+                "NanoVDB version {} is not supported",
+                major_version
+            )));
+        }
+        let name: String = String::from_utf8_lossy(&buffer[40..296])
+            .trim_end_matches('\0')
+            .to_string();
+        if read_u32(&buffer, 636) != GRID_TYPE_FLOAT {
+            return Err(invalid_data(format!("grid {:?} is not a float grid", name)));
+        }
+        // Map: the double precision matrix and translation
+        let mat: Vec<Float> = (0..9)
+            .map(|i| read_f64(&buffer, 384 + 8 * i) as Float)
+            .collect();
+        let vec: Vec<Float> = (0..3)
+            .map(|i| read_f64(&buffer, 528 + 8 * i) as Float)
+            .collect();
+        let grid = NanoVdbGrid {
+            name,
+            index_to_world: Transform::new(
+                mat[0], mat[1], mat[2], vec[0], mat[3], mat[4], mat[5], vec[1], mat[6], mat[7],
+                mat[8], vec[2], 0.0, 0.0, 0.0, 1.0,
+            ),
+            buffer,
+        };
+        if grid.root() + ROOT_DATA_SIZE > grid.buffer.len() {
+            return Err(invalid_data(format!("grid {:?} is truncated", grid.name)));
+        }
+        Ok(grid)
+    }
+    fn root(&self) -> usize {
+        // the tree follows the grid data, the root offset is relative
+        // to the tree
+        GRID_DATA_SIZE + read_u64(&self.buffer, GRID_DATA_SIZE + 24) as usize
+    }
+    /// The (inclusive) bounds of the active voxels in index space.
+    pub fn index_bounds(&self) -> Bounds3i {
+        let root: usize = self.root();
+        let c = |i: usize| read_i32(&self.buffer, root + 4 * i);
+        Bounds3i {
+            p_min: Point3i {
+                x: c(0),
+                y: c(1),
+                z: c(2),
+            },
+            p_max: Point3i {
+                x: c(3),
+                y: c(4),
+                z: c(5),
+            },
+        }
+    }
+    /// The value of the voxel at _ijk_ (in index space), the
+    /// background value outside of the grid.
+    pub fn value(&self, ijk: &Point3i) -> Float {
+        let (x, y, z) = (ijk.x as u32, ijk.y as u32, ijk.z as u32);
+        let root: usize = self.root();
+        let table_size: usize = read_u32(&self.buffer, root + 24) as usize;
+        let background: Float = read_f32(&self.buffer, root + 28);
+        let key: u64 = u64::from(z >> 12) | (u64::from(y >> 12) << 21) | (u64::from(x >> 12) << 42);
+        let tile: Option<usize> = (0..table_size)
+            .map(|i| root + ROOT_DATA_SIZE + i * ROOT_TILE_SIZE)
+            .find(|tile| read_u64(&self.buffer, *tile) == key);
+        let tile: usize = match tile {
+            Some(tile) => tile,
+            None => return background,
+        };
+        let child: i64 = read_i64(&self.buffer, tile + 8);
+        if child == 0 {
+            return read_f32(&self.buffer, tile + 20);
+        }
+        // upper internal node (32^3 children)
+        let upper: usize = (root as i64 + child) as usize;
+        let n: usize =
+            ((((x & 4095) >> 7) << 10) | (((y & 4095) >> 7) << 5) | ((z & 4095) >> 7)) as usize;
+        let entry: usize = upper + UPPER_TABLE_OFFSET + 8 * n;
+        if !self.is_on(upper + 32 + 4096, n) {
+            return read_f32(&self.buffer, entry);
+        }
+        // lower internal node (16^3 children)
+        let lower: usize = (upper as i64 + read_i64(&self.buffer, entry)) as usize;
+        let n: usize =
+            ((((x & 127) >> 3) << 8) | (((y & 127) >> 3) << 4) | ((z & 127) >> 3)) as usize;
+        let entry: usize = lower + LOWER_TABLE_OFFSET + 8 * n;
+        if !self.is_on(lower + 32 + 512, n) {
+            return read_f32(&self.buffer, entry);
+        }
+        // leaf node (8^3 voxels)
+        let leaf: usize = (lower as i64 + read_i64(&self.buffer, entry)) as usize;
+        let n: usize = (((x & 7) << 6) | ((y & 7) << 3) | (z & 7)) as usize;
+        read_f32(&self.buffer, leaf + LEAF_VALUES_OFFSET + 4 * n)
+    }
+    /// Is bit _n_ of the mask at _offset_ set?
+    fn is_on(&self, offset: usize, n: usize) -> bool {
+        read_u64(&self.buffer, offset + 8 * (n >> 6)) & (1_u64 << (n & 63)) != 0
+    }
+    /// Samples the voxels within **index_bounds()** into a
+    /// **GridDensityMedium**, placed by the grid's index to world
+    /// transformation (followed by _medium_to_world_).
+    pub fn to_medium(
+        &self,
+        sigma_a: &Spectrum,
+        sigma_s: &Spectrum,
+        g: Float,
+        medium_to_world: &Transform,
+    ) -> GridDensityMedium {
+        let bounds: Bounds3i = self.index_bounds();
+        let nx: i32 = bounds.p_max.x - bounds.p_min.x + 1;
+        let ny: i32 = bounds.p_max.y - bounds.p_min.y + 1;
+        let nz: i32 = bounds.p_max.z - bounds.p_min.z + 1;
+        let mut density: Vec<Float> = Vec::with_capacity((nx * ny * nz) as usize);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    density.push(self.value(&Point3i {
+                        x: bounds.p_min.x + x,
+                        y: bounds.p_min.y + y,
+                        z: bounds.p_min.z + z,
+                    }));
+                }
+            }
+        }
+        // the density sample (x, y, z) is the voxel centered at
+        // p_min + (x, y, z) in index space
+        let grid_to_index: Transform = Transform::translate(&Vector3f {
+            x: bounds.p_min.x as Float - 0.5,
+            y: bounds.p_min.y as Float - 0.5,
+            z: bounds.p_min.z as Float - 0.5,
+        }) * Transform::scale(nx as Float, ny as Float, nz as Float);
+        GridDensityMedium::new(
+            sigma_a,
+            sigma_s,
+            g,
+            nx,
+            ny,
+            nz,
+            &(*medium_to_world * self.index_to_world * grid_to_index),
+            Arc::new(density),
+        )
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    let mut b: [u8; 2] = [0; 2];
+    b.copy_from_slice(&bytes[offset..offset + 2]);
+    u16::from_le_bytes(b)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut b: [u8; 4] = [0; 4];
+    b.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(b)
+}
+
+fn read_i32(bytes: &[u8], offset: usize) -> i32 {
+    read_u32(bytes, offset) as i32
+}
+
+fn read_f32(bytes: &[u8], offset: usize) -> Float {
+    f32::from_bits(read_u32(bytes, offset)) as Float
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut b: [u8; 8] = [0; 8];
+    b.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(b)
+}
+
+fn read_i64(bytes: &[u8], offset: usize) -> i64 {
+    read_u64(bytes, offset) as i64
+}
+
+fn read_f64(bytes: &[u8], offset: usize) -> f64 {
+    f64::from_bits(read_u64(bytes, offset))
+}
@@ -6,7 +6,7 @@ use crate::core::paramset::ParamSet;
 use crate::core::pbrt::Float;
 use crate::core::pbrt::{is_power_of_2, log_2_int_i64, round_up_pow2_32, round_up_pow2_64};
 use crate::core::rng::Rng;
-use crate::core::sampler::Sampler;
+use crate::core::sampler::{mix_seed, pixel_seed, sample_seed, Sampler};
 use crate::core::sampling::shuffle;
 
 pub struct MaxMinDistSampler {
@@ -18,6 +18,11 @@ pub struct MaxMinDistSampler {
     pub current_1d_dimension: i32,
     pub current_2d_dimension: i32,
     pub rng: Rng,
+    /// Render seed, mixed into the seeds passed to reseed()
+    pub seed: u64,
+    /// Seed last passed to reseed(), combined with the pixel in
+    /// start_pixel()
+    pub sequence: u64,
     // inherited from class Sampler (see sampler.h)
     pub current_pixel: Point2i,
     pub current_pixel_sample_index: i64,
@@ -33,15 +38,12 @@ impl MaxMinDistSampler {
     pub fn new(samples_per_pixel: i64, n_sampled_dimensions: i64) -> Self {
         let mut samples_per_pixel: i64 = samples_per_pixel;
         let c_index: i32 = log_2_int_i64(samples_per_pixel) as i32;
-        if c_index as usize >= 17_usize {
-            panic!("No more than {} samples per pixel are supported with MaxMinDistSampler.");
-            //     Warning(
-            //         "No more than %d samples per pixel are supported with "
-            //             "MaxMinDistSampler. Rounding down.",
-            //         (1 << int(sizeof(CMaxMinDist) / sizeof(CMaxMinDist[0]))) -
-            //             1);
-            //     spp =
-            //         (1 << (sizeof(CMaxMinDist) / sizeof(CMaxMinDist[0]))) - 1;
+        if c_index as usize >= C_MAX_MIN_DIST.len() {
+            samples_per_pixel = 1_i64 << (C_MAX_MIN_DIST.len() - 1);
+            println!(
+                "WARNING: No more than {} samples per pixel are supported with MaxMinDistSampler. Rounding down.",
+                samples_per_pixel
+            );
         }
         if !is_power_of_2(samples_per_pixel) {
             samples_per_pixel = round_up_pow2_64(samples_per_pixel);
@@ -60,6 +62,8 @@ impl MaxMinDistSampler {
             current_1d_dimension: 0_i32,
             current_2d_dimension: 0_i32,
             rng: Rng::default(),
+            seed: 0_u64,
+            sequence: 0_u64,
             current_pixel: Point2i::default(),
             current_pixel_sample_index: 0_i64,
             samples_1d_array_sizes: Vec::new(),
@@ -87,6 +91,8 @@ impl MaxMinDistSampler {
             current_1d_dimension: self.current_1d_dimension,
             current_2d_dimension: self.current_2d_dimension,
             rng: self.rng.clone(),
+            seed: self.seed,
+            sequence: self.sequence,
             current_pixel: self.current_pixel,
             current_pixel_sample_index: self.current_pixel_sample_index,
             samples_1d_array_sizes: self.samples_1d_array_sizes.iter().cloned().collect(),
@@ -112,6 +118,8 @@ impl MaxMinDistSampler {
     // Sampler
     pub fn start_pixel(&mut self, p: &Point2i) {
         // TODO: ProfilePhase _(Prof::StartPixel);
+        self.rng
+            .set_sequence(mix_seed(self.seed, pixel_seed(self.sequence, p)));
         let inv_spp: Float = 1.0 as Float / self.samples_per_pixel as Float;
         for i in 0..self.samples_per_pixel as usize {
             self.samples_2d[0_usize][i] = Point2f {
@@ -166,6 +174,10 @@ impl MaxMinDistSampler {
         // PixelSampler::StartPixel(p);
         self.current_pixel = *p;
         self.current_pixel_sample_index = 0_i64;
+        // every pixel consumes dimensions in the same order, even if
+        // the previous pixel (of a reused tile sampler) stopped early
+        self.current_1d_dimension = 0_i32;
+        self.current_2d_dimension = 0_i32;
         // reset array offsets for next pixel sample
         self.array_1d_offset = 0_usize;
         self.array_2d_offset = 0_usize;
@@ -292,10 +304,12 @@ impl MaxMinDistSampler {
         self.array_1d_offset = 0_usize;
         self.array_2d_offset = 0_usize;
         self.current_pixel_sample_index += 1_i64;
+        self.start_sample_sequence();
         self.current_pixel_sample_index < self.samples_per_pixel
     }
     pub fn reseed(&mut self, seed: u64) {
-        self.rng.set_sequence(seed);
+        self.sequence = seed;
+        self.rng.set_sequence(mix_seed(self.seed, seed));
     }
     pub fn get_current_pixel(&self) -> Point2i {
         self.current_pixel
@@ -306,4 +320,23 @@ impl MaxMinDistSampler {
     pub fn get_samples_per_pixel(&self) -> i64 {
         self.samples_per_pixel
     }
+    // PixelSampler
+    pub fn set_sample_number(&mut self, sample_num: i64) -> bool {
+        // PixelSampler::SetSampleNumber(...)
+        self.current_1d_dimension = 0_i32;
+        self.current_2d_dimension = 0_i32;
+        // reset array offsets for next pixel sample
+        self.array_1d_offset = 0_usize;
+        self.array_2d_offset = 0_usize;
+        self.current_pixel_sample_index = sample_num;
+        self.start_sample_sequence();
+        self.current_pixel_sample_index < self.samples_per_pixel
+    }
+    /// Continues with the random numbers of the current sample (see
+    /// sample_seed()).
+    fn start_sample_sequence(&mut self) {
+        let seed: u64 = mix_seed(self.seed, pixel_seed(self.sequence, &self.current_pixel));
+        self.rng
+            .set_sequence(sample_seed(seed, self.current_pixel_sample_index));
+    }
 }
@@ -5,6 +5,7 @@
 //! - HaltonSampler
 //! - MaxMinDistSampler
 //! - RandomSampler
+//! - RecordingSampler
 //! - SobolSampler
 //! - StratifiedSampler
 //! - ZeroTwoSequenceSampler
@@ -52,6 +53,7 @@
 pub mod halton;
 pub mod maxmin;
 pub mod random;
+pub mod recording;
 pub mod sobol;
 pub mod stratified;
 pub mod zerotwosequence;
@@ -3,13 +3,18 @@ use crate::core::geometry::{Point2f, Point2i};
 use crate::core::paramset::ParamSet;
 use crate::core::pbrt::Float;
 use crate::core::rng::Rng;
-use crate::core::sampler::Sampler;
+use crate::core::sampler::{mix_seed, pixel_seed, sample_seed, Sampler};
 
 // see random.h
 
 pub struct RandomSampler {
     pub samples_per_pixel: i64,
     pub rng: Rng,
+    /// Render seed, mixed into the seeds passed to reseed()
+    pub seed: u64,
+    /// Seed last passed to reseed(), combined with the pixel in
+    /// start_pixel()
+    pub sequence: u64,
     // inherited from class Sampler (see sampler.h)
     pub current_pixel: Point2i,
     pub current_pixel_sample_index: i64,
@@ -26,6 +31,8 @@ impl RandomSampler {
         RandomSampler {
             samples_per_pixel,
             rng: Rng::default(),
+            seed: 0_u64,
+            sequence: 0_u64,
             current_pixel: Point2i::default(),
             current_pixel_sample_index: 0_i64,
             samples_1d_array_sizes: Vec::new(),
@@ -38,7 +45,8 @@ impl RandomSampler {
     }
     pub fn clone_with_seed(&self, seed: u64) -> Box<Sampler> {
         let mut random_sampler = RandomSampler::new(self.samples_per_pixel);
-        random_sampler.rng.set_sequence(seed);
+        random_sampler.seed = self.seed;
+        random_sampler.reseed(seed);
         // manually copy remaining bits
         random_sampler.current_pixel = self.current_pixel;
         random_sampler.current_pixel_sample_index = self.current_pixel_sample_index;
@@ -59,6 +67,8 @@ impl RandomSampler {
     // Sampler
     pub fn start_pixel(&mut self, p: &Point2i) {
         // TODO: ProfilePhase _(Prof::StartPixel);
+        self.rng
+            .set_sequence(mix_seed(self.seed, pixel_seed(self.sequence, p)));
         for i in 0..self.sample_array_1d.len() {
             for j in 0..self.sample_array_1d[i].len() {
                 self.sample_array_1d[i][j] = self.rng.uniform_float();
@@ -93,7 +103,8 @@ impl RandomSampler {
         Point2f { x, y }
     }
     pub fn reseed(&mut self, seed: u64) {
-        self.rng.set_sequence(seed);
+        self.sequence = seed;
+        self.rng.set_sequence(mix_seed(self.seed, seed));
     }
     pub fn request_2d_array(&mut self, n: i32) {
         assert_eq!(self.round_count(n), n);
@@ -177,6 +188,7 @@ impl RandomSampler {
         self.array_1d_offset = 0_usize;
         self.array_2d_offset = 0_usize;
         self.current_pixel_sample_index += 1_i64;
+        self.start_sample_sequence();
         self.current_pixel_sample_index < self.samples_per_pixel
     }
     pub fn get_current_pixel(&self) -> Point2i {
@@ -188,4 +200,20 @@ impl RandomSampler {
     pub fn get_samples_per_pixel(&self) -> i64 {
         self.samples_per_pixel
     }
+    pub fn set_sample_number(&mut self, sample_num: i64) -> bool {
+        // Sampler::SetSampleNumber(...)
+        // reset array offsets for next pixel sample
+        self.array_1d_offset = 0_usize;
+        self.array_2d_offset = 0_usize;
+        self.current_pixel_sample_index = sample_num;
+        self.start_sample_sequence();
+        self.current_pixel_sample_index < self.samples_per_pixel
+    }
+    /// Continues with the random numbers of the current sample (see
+    /// sample_seed()).
+    fn start_sample_sequence(&mut self) {
+        let seed: u64 = mix_seed(self.seed, pixel_seed(self.sequence, &self.current_pixel));
+        self.rng
+            .set_sequence(sample_seed(seed, self.current_pixel_sample_index));
+    }
 }
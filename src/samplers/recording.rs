@@ -0,0 +1,81 @@
+//! A sampler wrapping another **Sampler**, which records the sequence
+//! of get_1d() and get_2d() calls for the current pixel. Used by the
+//! tests to check that an integrator consumes the sample dimensions
+//! in the same order for every pixel.
+
+// pbrt
+use crate::core::geometry::{Point2f, Point2i};
+use crate::core::pbrt::Float;
+use crate::core::sampler::Sampler;
+
+/// A single sample requested from a **RecordingSampler**.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleCall {
+    Get1D,
+    Get2D,
+}
+
+pub struct RecordingSampler {
+    pub sampler: Box<Sampler>,
+    /// The calls since the last start_pixel()
+    pub calls: Vec<SampleCall>,
+}
+
+impl RecordingSampler {
+    pub fn new(sampler: Box<Sampler>) -> Self {
+        RecordingSampler {
+            sampler,
+            calls: Vec::new(),
+        }
+    }
+    pub fn clone_with_seed(&self, seed: u64) -> Box<Sampler> {
+        let recording_sampler = RecordingSampler::new(self.sampler.clone_with_seed(seed));
+        Box::new(Sampler::Recording(recording_sampler))
+    }
+    // Sampler
+    pub fn start_pixel(&mut self, p: &Point2i) {
+        self.calls.clear();
+        self.sampler.start_pixel(p);
+    }
+    pub fn get_1d(&mut self) -> Float {
+        self.calls.push(SampleCall::Get1D);
+        self.sampler.get_1d()
+    }
+    pub fn get_2d(&mut self) -> Point2f {
+        self.calls.push(SampleCall::Get2D);
+        self.sampler.get_2d()
+    }
+    pub fn request_2d_array(&mut self, n: i32) {
+        self.sampler.request_2d_array(n)
+    }
+    pub fn round_count(&self, count: i32) -> i32 {
+        self.sampler.round_count(count)
+    }
+    pub fn get_2d_array(&mut self, n: i32) -> Option<&[Point2f]> {
+        self.sampler.get_2d_array(n)
+    }
+    pub fn get_2d_arrays(&mut self, n: i32) -> (Option<&[Point2f]>, Option<&[Point2f]>) {
+        self.sampler.get_2d_arrays(n)
+    }
+    pub fn get_2d_array_vec(&mut self, n: i32) -> Vec<Point2f> {
+        self.sampler.get_2d_array_vec(n)
+    }
+    pub fn start_next_sample(&mut self) -> bool {
+        self.sampler.start_next_sample()
+    }
+    pub fn reseed(&mut self, seed: u64) {
+        self.sampler.reseed(seed)
+    }
+    pub fn get_current_pixel(&self) -> Point2i {
+        self.sampler.get_current_pixel()
+    }
+    pub fn get_current_sample_number(&self) -> i64 {
+        self.sampler.get_current_sample_number()
+    }
+    pub fn get_samples_per_pixel(&self) -> i64 {
+        self.sampler.get_samples_per_pixel()
+    }
+    pub fn set_sample_number(&mut self, sample_num: i64) -> bool {
+        self.sampler.set_sample_number(sample_num)
+    }
+}
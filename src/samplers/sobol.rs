@@ -1,11 +1,9 @@
 // pbrt
-use crate::core::geometry::{Bounds2i, Point2f, Point2i, Vector2i};
-use crate::core::lowdiscrepancy::{sobol_interval_to_index, sobol_sample};
+use crate::core::geometry::{Bounds2i, Point2f, Point2i};
+use crate::core::lowdiscrepancy::{sobol_sample, SampleBounds};
 use crate::core::paramset::ParamSet;
 use crate::core::pbrt::Float;
-use crate::core::pbrt::{
-    clamp_t, is_power_of_2, log_2_int_u32, round_up_pow2_32, round_up_pow2_64,
-};
+use crate::core::pbrt::{clamp_t, is_power_of_2, round_up_pow2_64};
 use crate::core::rng::FLOAT_ONE_MINUS_EPSILON;
 use crate::core::sampler::Sampler;
 use crate::core::sobolmatrices::NUM_SOBOL_DIMENSIONS;
@@ -14,9 +12,7 @@ use crate::core::sobolmatrices::NUM_SOBOL_DIMENSIONS;
 
 pub struct SobolSampler {
     pub samples_per_pixel: i64,
-    pub sample_bounds: Bounds2i,
-    pub resolution: i32,
-    pub log_2_resolution: i32,
+    pub sample_bounds: SampleBounds,
     // inherited from class GlobalSampler (see sampler.h)
     pub dimension: i64,
     pub interval_sample_index: u64,
@@ -43,26 +39,9 @@ impl SobolSampler {
                 samples_per_pixel
             );
         }
-        let resolution: i32 =
-            round_up_pow2_32(sample_bounds.diagonal().x.max(sample_bounds.diagonal().y));
-        let log_2_resolution: i32 = log_2_int_u32(resolution as u32);
-        if resolution > 0_i32 {
-            assert!(1_i32 << log_2_resolution == resolution);
-        }
         SobolSampler {
             samples_per_pixel,
-            sample_bounds: Bounds2i {
-                p_min: Point2i {
-                    x: sample_bounds.p_min.x,
-                    y: sample_bounds.p_min.y,
-                },
-                p_max: Point2i {
-                    x: sample_bounds.p_max.x,
-                    y: sample_bounds.p_max.y,
-                },
-            },
-            resolution,
-            log_2_resolution,
+            sample_bounds: SampleBounds::new(sample_bounds),
             dimension: 0_i64,
             interval_sample_index: 0_u64,
             array_start_dim: 5_i64, // static const int arrayStartDim = 5;
@@ -81,8 +60,6 @@ impl SobolSampler {
         let sobol_sampler = SobolSampler {
             samples_per_pixel: self.samples_per_pixel,
             sample_bounds: self.sample_bounds,
-            resolution: self.resolution,
-            log_2_resolution: self.log_2_resolution,
             dimension: self.dimension,
             interval_sample_index: self.interval_sample_index,
             array_start_dim: self.array_start_dim,
@@ -108,12 +85,8 @@ impl SobolSampler {
         )))
     }
     pub fn get_index_for_sample(&self, sample_num: u64) -> u64 {
-        let v: Vector2i = self.current_pixel - self.sample_bounds.p_min;
-        sobol_interval_to_index(
-            self.log_2_resolution as u32,
-            sample_num,
-            &Point2i { x: v.x, y: v.y },
-        )
+        self.sample_bounds
+            .index_for_sample(&self.current_pixel, sample_num)
     }
     pub fn sample_dimension(&self, index: u64, dim: i64) -> Float {
         if dim >= NUM_SOBOL_DIMENSIONS as i64 {
@@ -125,7 +98,8 @@ impl SobolSampler {
         let mut s: Float = sobol_sample(index as i64, dim as i32, 0_u64);
         // remap Sobol$'$ dimensions used for pixel samples
         if dim == 0 || dim == 1 {
-            s = s * self.resolution as Float + self.sample_bounds.p_min[dim as u8] as Float;
+            s = s * self.sample_bounds.resolution as Float
+                + self.sample_bounds.bounds.p_min[dim as u8] as Float;
             s = clamp_t(
                 s - self.current_pixel[dim as u8] as Float,
                 0.0 as Float,
@@ -135,6 +109,27 @@ impl SobolSampler {
         s
     }
     // Sampler
+    /// The sample indices of a pixel depend only on the pixel (see
+    /// **SampleBounds**), so tiles can be rendered in any order and
+    /// with any number of threads. The same holds for the Halton
+    /// sampler:
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Bounds2i, Point2f, Point2i};
+    /// use pbrt::core::sampler::Sampler;
+    /// use pbrt::samplers::sobol::SobolSampler;
+    ///
+    /// // not a power of two, so the sample bounds get padded
+    /// let bounds = Bounds2i::new(Point2i { x: 0, y: 0 }, Point2i { x: 40, y: 24 });
+    /// let mut sampler = Sampler::Sobol(SobolSampler::new(4, &bounds));
+    /// let p: Point2i = Point2i { x: 3, y: 2 };
+    /// sampler.start_pixel(&p);
+    /// let first: Point2f = sampler.get_2d();
+    /// sampler.start_pixel(&Point2i { x: 37, y: 21 });
+    /// sampler.get_2d();
+    /// sampler.start_pixel(&p);
+    /// assert_eq!(sampler.get_2d(), first);
+    /// ```
     pub fn start_pixel(&mut self, p: &Point2i) {
         // TODO: ProfilePhase _(Prof::StartPixel);
         // Sampler::StartPixel(p);
@@ -3,7 +3,7 @@ use crate::core::geometry::{Point2f, Point2i};
 use crate::core::paramset::ParamSet;
 use crate::core::pbrt::Float;
 use crate::core::rng::Rng;
-use crate::core::sampler::Sampler;
+use crate::core::sampler::{mix_seed, pixel_seed, sample_seed, Sampler};
 use crate::core::sampling::{latin_hypercube, shuffle, stratified_sample_1d, stratified_sample_2d};
 
 pub struct StratifiedSampler {
@@ -17,6 +17,11 @@ pub struct StratifiedSampler {
     pub current_1d_dimension: i32,
     pub current_2d_dimension: i32,
     pub rng: Rng,
+    /// Render seed, mixed into the seeds passed to reseed()
+    pub seed: u64,
+    /// Seed last passed to reseed(), combined with the pixel in
+    /// start_pixel()
+    pub sequence: u64,
     // inherited from class Sampler (see sampler.h)
     pub current_pixel: Point2i,
     pub current_pixel_sample_index: i64,
@@ -45,6 +50,8 @@ impl StratifiedSampler {
             current_1d_dimension: 0_i32,
             current_2d_dimension: 0_i32,
             rng: Rng::default(),
+            seed: 0_u64,
+            sequence: 0_u64,
             current_pixel: Point2i::default(),
             current_pixel_sample_index: 0_i64,
             samples_1d_array_sizes: Vec::new(),
@@ -74,6 +81,8 @@ impl StratifiedSampler {
             current_1d_dimension: self.current_1d_dimension,
             current_2d_dimension: self.current_2d_dimension,
             rng: self.rng.clone(),
+            seed: self.seed,
+            sequence: self.sequence,
             current_pixel: self.current_pixel,
             current_pixel_sample_index: self.current_pixel_sample_index,
             samples_1d_array_sizes: self.samples_1d_array_sizes.iter().cloned().collect(),
@@ -98,8 +107,46 @@ impl StratifiedSampler {
         )))
     }
     // Sampler
+
+    /// Generates the samples of pixel _p_ and starts with its first
+    /// dimension, also if the previous pixel wasn't sampled to the
+    /// end. The same holds for the other pixel samplers:
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::{Point2f, Point2i};
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::sampler::Sampler;
+    /// use pbrt::samplers::maxmin::MaxMinDistSampler;
+    /// use pbrt::samplers::stratified::StratifiedSampler;
+    /// use pbrt::samplers::zerotwosequence::ZeroTwoSequenceSampler;
+    ///
+    /// fn first_samples(sampler: &mut Sampler, p: &Point2i) -> (Float, Point2f, Float) {
+    ///     sampler.start_pixel(p);
+    ///     (sampler.get_1d(), sampler.get_2d(), sampler.get_1d())
+    /// }
+    ///
+    /// let make: Vec<fn() -> Sampler> = vec![
+    ///     || Sampler::Stratified(StratifiedSampler::new(2, 2, true, 4)),
+    ///     || Sampler::MaxMinDist(MaxMinDistSampler::new(4, 4)),
+    ///     || Sampler::ZeroTwoSequence(ZeroTwoSequenceSampler::new(4, 4)),
+    /// ];
+    /// let p: Point2i = Point2i { x: 5, y: 7 };
+    /// for new_sampler in make {
+    ///     let mut fresh: Sampler = new_sampler();
+    ///     let expected = first_samples(&mut fresh, &p);
+    ///     // stop in the middle of another pixel
+    ///     let mut reused: Sampler = new_sampler();
+    ///     reused.start_pixel(&Point2i { x: 4, y: 7 });
+    ///     reused.get_1d();
+    ///     reused.get_2d();
+    ///     reused.get_2d();
+    ///     assert_eq!(first_samples(&mut reused, &p), expected);
+    /// }
+    /// ```
     pub fn start_pixel(&mut self, p: &Point2i) {
         // TODO: ProfilePhase _(Prof::StartPixel);
+        self.rng
+            .set_sequence(mix_seed(self.seed, pixel_seed(self.sequence, p)));
         // generate single stratified samples for the pixel
         for i in 0..self.samples_1d.len() {
             let samples: &mut [Float] = self.samples_1d[i].as_mut_slice();
@@ -148,7 +195,6 @@ impl StratifiedSampler {
                 latin_hypercube(
                     &mut self.sample_array_2d[i as usize][(j as usize * count as usize)..],
                     count,
-                    // 2,
                     &mut self.rng,
                 );
             }
@@ -156,6 +202,10 @@ impl StratifiedSampler {
         // PixelSampler::StartPixel(p);
         self.current_pixel = *p;
         self.current_pixel_sample_index = 0_i64;
+        // every pixel consumes dimensions in the same order, even if
+        // the previous pixel (of a reused tile sampler) stopped early
+        self.current_1d_dimension = 0_i32;
+        self.current_2d_dimension = 0_i32;
         // reset array offsets for next pixel sample
         self.array_1d_offset = 0_usize;
         self.array_2d_offset = 0_usize;
@@ -282,10 +332,12 @@ impl StratifiedSampler {
         self.array_1d_offset = 0_usize;
         self.array_2d_offset = 0_usize;
         self.current_pixel_sample_index += 1_i64;
+        self.start_sample_sequence();
         self.current_pixel_sample_index < self.samples_per_pixel
     }
     pub fn reseed(&mut self, seed: u64) {
-        self.rng.set_sequence(seed);
+        self.sequence = seed;
+        self.rng.set_sequence(mix_seed(self.seed, seed));
     }
     pub fn get_current_pixel(&self) -> Point2i {
         self.current_pixel
@@ -296,4 +348,23 @@ impl StratifiedSampler {
     pub fn get_samples_per_pixel(&self) -> i64 {
         self.samples_per_pixel
     }
+    // PixelSampler
+    pub fn set_sample_number(&mut self, sample_num: i64) -> bool {
+        // PixelSampler::SetSampleNumber(...)
+        self.current_1d_dimension = 0_i32;
+        self.current_2d_dimension = 0_i32;
+        // reset array offsets for next pixel sample
+        self.array_1d_offset = 0_usize;
+        self.array_2d_offset = 0_usize;
+        self.current_pixel_sample_index = sample_num;
+        self.start_sample_sequence();
+        self.current_pixel_sample_index < self.samples_per_pixel
+    }
+    /// Continues with the random numbers of the current sample (see
+    /// sample_seed()).
+    fn start_sample_sequence(&mut self) {
+        let seed: u64 = mix_seed(self.seed, pixel_seed(self.sequence, &self.current_pixel));
+        self.rng
+            .set_sequence(sample_seed(seed, self.current_pixel_sample_index));
+    }
 }
@@ -5,7 +5,7 @@ use crate::core::paramset::ParamSet;
 use crate::core::pbrt::round_up_pow2_32;
 use crate::core::pbrt::Float;
 use crate::core::rng::Rng;
-use crate::core::sampler::Sampler;
+use crate::core::sampler::{mix_seed, pixel_seed, sample_seed, Sampler};
 
 // see zerotwosequence.h
 
@@ -18,6 +18,11 @@ pub struct ZeroTwoSequenceSampler {
     pub current_1d_dimension: i32,
     pub current_2d_dimension: i32,
     pub rng: Rng,
+    /// Render seed, mixed into the seeds passed to reseed()
+    pub seed: u64,
+    /// Seed last passed to reseed(), combined with the pixel in
+    /// start_pixel()
+    pub sequence: u64,
     // inherited from class Sampler (see sampler.h)
     pub current_pixel: Point2i,
     pub current_pixel_sample_index: i64,
@@ -39,6 +44,8 @@ impl Default for ZeroTwoSequenceSampler {
             current_1d_dimension: 0_i32,
             current_2d_dimension: 0_i32,
             rng: Rng::default(),
+            seed: 0_u64,
+            sequence: 0_u64,
             current_pixel: Point2i::default(),
             current_pixel_sample_index: 0_i64,
             samples_1d_array_sizes: Vec::new(),
@@ -69,6 +76,8 @@ impl ZeroTwoSequenceSampler {
             current_1d_dimension: 0_i32,
             current_2d_dimension: 0_i32,
             rng: Rng::default(),
+            seed: 0_u64,
+            sequence: 0_u64,
             current_pixel: Point2i::default(),
             current_pixel_sample_index: 0_i64,
             samples_1d_array_sizes: Vec::new(),
@@ -96,6 +105,8 @@ impl ZeroTwoSequenceSampler {
             current_1d_dimension: self.current_1d_dimension,
             current_2d_dimension: self.current_2d_dimension,
             rng: self.rng.clone(),
+            seed: self.seed,
+            sequence: self.sequence,
             current_pixel: self.current_pixel,
             current_pixel_sample_index: self.current_pixel_sample_index,
             samples_1d_array_sizes: self.samples_1d_array_sizes.iter().cloned().collect(),
@@ -121,6 +132,8 @@ impl ZeroTwoSequenceSampler {
     // Sampler
     pub fn start_pixel(&mut self, p: &Point2i) {
         // TODO: ProfilePhase _(Prof::StartPixel);
+        self.rng
+            .set_sequence(mix_seed(self.seed, pixel_seed(self.sequence, p)));
         // generate 1D and 2D pixel sample components using $(0,2)$-sequence
         for samples in &mut self.samples_1d {
             van_der_corput(1, self.samples_per_pixel as i32, samples, &mut self.rng);
@@ -150,6 +163,10 @@ impl ZeroTwoSequenceSampler {
         // PixelSampler::StartPixel(p);
         self.current_pixel = *p;
         self.current_pixel_sample_index = 0_i64;
+        // every pixel consumes dimensions in the same order, even if
+        // the previous pixel (of a reused tile sampler) stopped early
+        self.current_1d_dimension = 0_i32;
+        self.current_2d_dimension = 0_i32;
         // reset array offsets for next pixel sample
         self.array_1d_offset = 0_usize;
         self.array_2d_offset = 0_usize;
@@ -276,10 +293,12 @@ impl ZeroTwoSequenceSampler {
         self.array_1d_offset = 0_usize;
         self.array_2d_offset = 0_usize;
         self.current_pixel_sample_index += 1_i64;
+        self.start_sample_sequence();
         self.current_pixel_sample_index < self.samples_per_pixel
     }
     pub fn reseed(&mut self, seed: u64) {
-        self.rng.set_sequence(seed);
+        self.sequence = seed;
+        self.rng.set_sequence(mix_seed(self.seed, seed));
     }
     pub fn get_current_pixel(&self) -> Point2i {
         self.current_pixel
@@ -290,4 +309,23 @@ impl ZeroTwoSequenceSampler {
     pub fn get_samples_per_pixel(&self) -> i64 {
         self.samples_per_pixel
     }
+    // PixelSampler
+    pub fn set_sample_number(&mut self, sample_num: i64) -> bool {
+        // PixelSampler::SetSampleNumber(...)
+        self.current_1d_dimension = 0_i32;
+        self.current_2d_dimension = 0_i32;
+        // reset array offsets for next pixel sample
+        self.array_1d_offset = 0_usize;
+        self.array_2d_offset = 0_usize;
+        self.current_pixel_sample_index = sample_num;
+        self.start_sample_sequence();
+        self.current_pixel_sample_index < self.samples_per_pixel
+    }
+    /// Continues with the random numbers of the current sample (see
+    /// sample_seed()).
+    fn start_sample_sequence(&mut self) {
+        let seed: u64 = mix_seed(self.seed, pixel_seed(self.sequence, &self.current_pixel));
+        self.rng
+            .set_sequence(sample_seed(seed, self.current_pixel_sample_index));
+    }
 }
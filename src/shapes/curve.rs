@@ -73,9 +73,9 @@ pub struct Curve {
     pub common: Arc<CurveCommon>,
     pub u_min: Float,
     pub u_max: Float,
-    // inherited from class Shape (see shape.h)
-    pub object_to_world: Transform,
-    pub world_to_object: Transform,
+    // inherited from class Shape (see shape.h), shared by all segments
+    pub object_to_world: Arc<Transform>,
+    pub world_to_object: Arc<Transform>,
     pub reverse_orientation: bool,
     pub transform_swaps_handedness: bool,
     pub material: Option<Arc<Material>>,
@@ -83,8 +83,8 @@ pub struct Curve {
 
 impl Curve {
     pub fn new(
-        object_to_world: Transform,
-        world_to_object: Transform,
+        object_to_world: Arc<Transform>,
+        world_to_object: Arc<Transform>,
         reverse_orientation: bool,
         common: Arc<CurveCommon>,
         u_min: Float,
@@ -96,16 +96,16 @@ impl Curve {
             u_min,
             u_max,
             // Shape
+            transform_swaps_handedness: object_to_world.swaps_handedness(),
             object_to_world,
             world_to_object,
             reverse_orientation,
-            transform_swaps_handedness: object_to_world.swaps_handedness(),
             material: None,
         }
     }
     pub fn create(
-        o2w: Transform,
-        w2o: Transform,
+        o2w: &Arc<Transform>,
+        w2o: &Arc<Transform>,
         reverse_orientation: bool,
         c: &[Point3f; 4],
         w0: Float,
@@ -123,8 +123,8 @@ impl Curve {
             // segments.push_back(std::make_shared<Curve>(o2w, w2o, reverseOrientation,
             //                                            common, u_min, u_max));
             let curve: Arc<Shape> = Arc::new(Shape::Crv(Curve::new(
-                o2w,
-                w2o,
+                o2w.clone(),
+                w2o.clone(),
                 reverse_orientation,
                 common.clone(),
                 u_min,
@@ -489,7 +489,7 @@ impl Curve {
         self.transform_swaps_handedness
     }
     pub fn get_object_to_world(&self) -> Transform {
-        self.object_to_world
+        *self.object_to_world
     }
     pub fn area(&self) -> Float {
         // compute object-space control points for curve segment, _cp_obj_
@@ -553,8 +553,8 @@ impl Curve {
 }
 
 pub fn create_curve_shape(
-    o2w: &Transform,
-    w2o: &Transform,
+    o2w: &Arc<Transform>,
+    w2o: &Arc<Transform>,
     reverse_orientation: bool,
     params: &ParamSet,
 ) -> Vec<Arc<Shape>> {
@@ -600,8 +600,8 @@ pub fn create_curve_shape(
     }
     if n.is_empty() {
         Curve::create(
-            *o2w,
-            *w2o,
+            o2w,
+            w2o,
             reverse_orientation,
             &[cp[0], cp[1], cp[2], cp[3]],
             width0,
@@ -612,8 +612,8 @@ pub fn create_curve_shape(
         )
     } else {
         Curve::create(
-            *o2w,
-            *w2o,
+            o2w,
+            w2o,
             reverse_orientation,
             &[cp[0], cp[1], cp[2], cp[3]],
             width0,
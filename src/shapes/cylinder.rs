@@ -23,8 +23,8 @@ pub struct Cylinder {
     pub z_max: Float,
     pub phi_max: Float,
     // inherited from class Shape (see shape.h)
-    pub object_to_world: Transform,
-    pub world_to_object: Transform,
+    pub object_to_world: Arc<Transform>,
+    pub world_to_object: Arc<Transform>,
     pub reverse_orientation: bool,
     pub transform_swaps_handedness: bool,
     pub material: Option<Arc<Material>>,
@@ -32,13 +32,13 @@ pub struct Cylinder {
 
 impl Default for Cylinder {
     fn default() -> Self {
-        let object_to_world: Transform = Transform::default();
+        let object_to_world: Arc<Transform> = Arc::new(Transform::default());
         Cylinder {
             // Shape
+            transform_swaps_handedness: object_to_world.swaps_handedness(),
+            world_to_object: object_to_world.clone(),
             object_to_world,
-            world_to_object: Transform::default(),
             reverse_orientation: false,
-            transform_swaps_handedness: object_to_world.swaps_handedness(),
             // Cylinder
             radius: 1.0,
             z_min: -1.0,
@@ -51,8 +51,8 @@ impl Default for Cylinder {
 
 impl Cylinder {
     pub fn new(
-        object_to_world: Transform,
-        world_to_object: Transform,
+        object_to_world: Arc<Transform>,
+        world_to_object: Arc<Transform>,
         reverse_orientation: bool,
         radius: Float,
         z_min: Float,
@@ -61,10 +61,10 @@ impl Cylinder {
     ) -> Self {
         Cylinder {
             // Shape
+            transform_swaps_handedness: object_to_world.swaps_handedness(),
             object_to_world,
             world_to_object,
             reverse_orientation,
-            transform_swaps_handedness: object_to_world.swaps_handedness(),
             // Cylinder
             radius,
             z_min: z_min.min(z_max),
@@ -334,7 +334,7 @@ impl Cylinder {
         self.transform_swaps_handedness
     }
     pub fn get_object_to_world(&self) -> Transform {
-        self.object_to_world
+        *self.object_to_world
     }
     pub fn area(&self) -> Float {
         (self.z_max - self.z_min) * self.radius * self.phi_max
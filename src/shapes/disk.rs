@@ -20,8 +20,8 @@ pub struct Disk {
     pub inner_radius: Float,
     pub phi_max: Float,
     // inherited from class Shape (see shape.h)
-    pub object_to_world: Transform,
-    pub world_to_object: Transform,
+    pub object_to_world: Arc<Transform>,
+    pub world_to_object: Arc<Transform>,
     pub reverse_orientation: bool,
     pub transform_swaps_handedness: bool,
     pub material: Option<Arc<Material>>,
@@ -29,13 +29,13 @@ pub struct Disk {
 
 impl Default for Disk {
     fn default() -> Self {
-        let object_to_world: Transform = Transform::default();
+        let object_to_world: Arc<Transform> = Arc::new(Transform::default());
         Disk {
             // Shape
+            transform_swaps_handedness: object_to_world.swaps_handedness(),
+            world_to_object: object_to_world.clone(),
             object_to_world,
-            world_to_object: Transform::default(),
             reverse_orientation: false,
-            transform_swaps_handedness: object_to_world.swaps_handedness(),
             // Disk
             height: 0.0,
             radius: 1.0,
@@ -48,8 +48,8 @@ impl Default for Disk {
 
 impl Disk {
     pub fn new(
-        object_to_world: Transform,
-        world_to_object: Transform,
+        object_to_world: Arc<Transform>,
+        world_to_object: Arc<Transform>,
         reverse_orientation: bool,
         height: Float,
         radius: Float,
@@ -58,10 +58,10 @@ impl Disk {
     ) -> Self {
         Disk {
             // Shape
+            transform_swaps_handedness: object_to_world.swaps_handedness(),
             object_to_world,
             world_to_object,
             reverse_orientation,
-            transform_swaps_handedness: object_to_world.swaps_handedness(),
             // Disk
             height,
             radius,
@@ -210,7 +210,7 @@ impl Disk {
         self.transform_swaps_handedness
     }
     pub fn get_object_to_world(&self) -> Transform {
-        self.object_to_world
+        *self.object_to_world
     }
     pub fn area(&self) -> Float {
         self.phi_max
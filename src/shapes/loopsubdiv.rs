@@ -275,6 +275,7 @@ pub fn loop_subdivide(
     n_levels: i32,
     vertex_indices: &Vec<i32>,
     p: &Vec<Point3f>,
+    name: Option<String>,
 ) -> Arc<TriangleMesh> {
     // allocate _LoopSubdiv_ vertices and faces
     let mut verts: Vec<Arc<SDVertex>> = Vec::with_capacity(p.len());
@@ -650,7 +651,7 @@ pub fn loop_subdivide(
     for i in 0..n_normals {
         n_ws.push(object_to_world.transform_normal(&ns[i]));
     }
-    Arc::new(TriangleMesh::new(
+    let mut mesh: TriangleMesh = TriangleMesh::new(
         *object_to_world,
         *world_to_object,
         reverse_orientation,
@@ -663,7 +664,9 @@ pub fn loop_subdivide(
         Vec::new(),
         None,
         None,
-    ))
+    );
+    mesh.set_name(name);
+    Arc::new(mesh)
 }
 
 fn weight_one_ring(
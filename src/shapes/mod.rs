@@ -39,6 +39,9 @@
 //! positions where each individual triangle just stores three offsets
 //! into this array for its three vertices.
 //!
+//! Triangle meshes can also be read from PLY (see **create_ply_mesh()**)
+//! or Wavefront OBJ files (see **load_obj()**).
+//!
 //! ## Disks
 //!
 //! The disk is an interesting quadric since it has a particularly
@@ -64,6 +67,7 @@ pub mod cylinder;
 pub mod disk;
 pub mod loopsubdiv;
 pub mod nurbs;
+pub mod objmesh;
 pub mod plymesh;
 pub mod sphere;
 pub mod triangle;
@@ -0,0 +1,609 @@
+//! Wavefront OBJ files (and their companion MTL files) as a source of
+//! triangle meshes.
+//!
+//! Faces with more than three vertices get triangulated by ear
+//! clipping (falling back to a triangle fan for degenerate polygons),
+//! negative (relative) indices are supported, and one
+//! **TriangleMesh** is created for each material used by the faces
+//! (see *usemtl*). Materials defined in the MTL file(s) are
+//! approximated by a **MatteMaterial** (or a **PlasticMaterial** if
+//! they have a specular color), with a diffuse texture map (*map_Kd*)
+//! becoming an **ImageTexture**.
+
+// std
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+// pbrt
+use crate::core::geometry::{vec3_cross_vec3, Normal3f, Point2f, Point3f, Vector3f};
+use crate::core::material::Material;
+use crate::core::mipmap::ImageWrap;
+use crate::core::pbrt::{Float, Spectrum};
+use crate::core::shape::Shape;
+use crate::core::texture::{Texture, TextureMapping2D, UVMapping2D};
+use crate::core::transform::Transform;
+use crate::materials::matte::MatteMaterial;
+use crate::materials::plastic::PlasticMaterial;
+use crate::shapes::triangle::{Triangle, TriangleMesh};
+use crate::textures::constant::ConstantTexture;
+use crate::textures::imagemap::{convert_to_spectrum, ImageEncoding, ImageTexture};
+
+/// A triangle mesh read from an OBJ file, the triangles referencing
+/// it, and the material it was assigned in the OBJ file (if any).
+pub type ObjMesh = (Arc<TriangleMesh>, Vec<Arc<Shape>>, Option<Arc<Material>>);
+
+/// Material properties read from a MTL file.
+#[derive(Debug, Clone)]
+struct MtlMaterial {
+    kd: [Float; 3],
+    ks: [Float; 3],
+    ns: Float,
+    map_kd: Option<PathBuf>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            kd: [0.5 as Float; 3],
+            ks: [0.0 as Float; 3],
+            ns: 0.0 as Float,
+            map_kd: None,
+        }
+    }
+}
+
+impl MtlMaterial {
+    fn to_material(&self) -> Arc<Material> {
+        let kd: Arc<dyn Texture<Spectrum> + Send + Sync> = match self.map_kd {
+            Some(ref filename) => Arc::new(ImageTexture::new(
+                Box::new(TextureMapping2D::UV(UVMapping2D::new(1.0, 1.0, 0.0, 0.0))),
+                String::from(filename.to_str().unwrap()),
+                false,
+                8.0 as Float,
+                ImageWrap::Repeat,
+                1.0 as Float,
+                ImageEncoding::Srgb,
+                convert_to_spectrum,
+            )),
+            None => Arc::new(ConstantTexture::new(Spectrum::from_rgb(&self.kd))),
+        };
+        if self.ks.iter().all(|c| *c <= 0.0 as Float) {
+            let sigma = Arc::new(ConstantTexture::new(0.0 as Float));
+            Arc::new(Material::Matte(MatteMaterial::new(kd, sigma, None)))
+        } else {
+            // the (Blinn-Phong) specular exponent roughly corresponds
+            // to a Beckmann roughness of sqrt(2 / (ns + 2))
+            let roughness: Float = (2.0 as Float / (self.ns.max(0.0 as Float) + 2.0)).sqrt();
+            let ks = Arc::new(ConstantTexture::new(Spectrum::from_rgb(&self.ks)));
+            let roughness = Arc::new(ConstantTexture::new(roughness));
+            Arc::new(Material::Plastic(PlasticMaterial::new(
+                kd, ks, roughness, None, false,
+            )))
+        }
+    }
+}
+
+/// Faces (vertex triples) of one material, vertices are indices of
+/// (position, uv, normal), with -1 for a missing uv or normal.
+#[derive(Default)]
+struct FaceGroup {
+    material: String,
+    vertices: Vec<(usize, i64, i64)>,
+}
+
+fn invalid_data(path: &Path, line: usize, message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}:{}: {}", path.display(), line, message),
+    )
+}
+
+fn parse_floats(path: &Path, line: usize, tokens: &[&str]) -> io::Result<Vec<Float>> {
+    tokens
+        .iter()
+        .map(|token| {
+            token.parse::<Float>().map_err(|_| {
+                invalid_data(path, line, format!("number expected, found {:?}", token))
+            })
+        })
+        .collect()
+}
+
+/// Converts a (1-based or negative, i.e. relative) OBJ index into a
+/// 0-based index into _count_ elements read so far.
+fn resolve_index(path: &Path, line: usize, token: &str, count: usize) -> io::Result<usize> {
+    let index: i64 = token
+        .parse::<i64>()
+        .map_err(|_| invalid_data(path, line, format!("index expected, found {:?}", token)))?;
+    let resolved: i64 = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(invalid_data(
+            path,
+            line,
+            format!("index {} out of range ({} elements)", index, count),
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+fn read_mtl(path: &Path, materials: &mut HashMap<String, MtlMaterial>) -> io::Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut current: Option<String> = None;
+    for (line_number, line) in reader.lines().enumerate() {
+        let line: String = line?;
+        let tokens: Vec<&str> = line.split('#').next().unwrap().split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        let line_number: usize = line_number + 1;
+        match tokens[0] {
+            "newmtl" => {
+                let name: String = tokens[1..].join(" ");
+                materials.insert(name.clone(), MtlMaterial::default());
+                current = Some(name);
+            }
+            "Kd" | "Ks" | "Ns" => {
+                let values: Vec<Float> = parse_floats(path, line_number, &tokens[1..])?;
+                let mtl: &mut MtlMaterial = match current {
+                    Some(ref name) => materials.get_mut(name).unwrap(),
+                    None => {
+                        return Err(invalid_data(
+                            path,
+                            line_number,
+                            format!("{} before newmtl", tokens[0]),
+                        ))
+                    }
+                };
+                if tokens[0] == "Ns" {
+                    if let Some(ns) = values.first() {
+                        mtl.ns = *ns;
+                    }
+                } else if values.len() >= 3 {
+                    let rgb: [Float; 3] = [values[0], values[1], values[2]];
+                    if tokens[0] == "Kd" {
+                        mtl.kd = rgb;
+                    } else {
+                        mtl.ks = rgb;
+                    }
+                }
+            }
+            "map_Kd" => {
+                // options (like -s or -o) are ignored, the file name
+                // comes last
+                let mtl: &mut MtlMaterial = match current {
+                    Some(ref name) => materials.get_mut(name).unwrap(),
+                    None => {
+                        return Err(invalid_data(
+                            path,
+                            line_number,
+                            String::from("map_Kd before newmtl"),
+                        ))
+                    }
+                };
+                if tokens.len() < 2 {
+                    return Err(invalid_data(
+                        path,
+                        line_number,
+                        String::from("map_Kd needs a file name"),
+                    ));
+                }
+                let directory: &Path = path.parent().unwrap_or_else(|| Path::new(""));
+                let filename: PathBuf = directory.join(tokens[tokens.len() - 1]);
+                if filename.exists() {
+                    mtl.map_kd = Some(filename);
+                } else {
+                    println!("WARNING: texture map {:?} not found, using Kd", filename);
+                }
+            }
+            // everything else (e.g. other texture maps) is ignored
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Splits a (planar) polygon, given by indices into _p_, into
+/// triangles by clipping ears. Falls back to a triangle fan if the
+/// polygon is degenerate.
+fn triangulate(p: &[Point3f], polygon: &[usize]) -> Vec<[usize; 3]> {
+    let n: usize = polygon.len();
+    let fan = || -> Vec<[usize; 3]> { (1..n - 1).map(|i| [0, i, i + 1]).collect() };
+    if n == 3 {
+        return fan();
+    }
+    // polygon normal (Newell's method)
+    let mut normal: Vector3f = Vector3f::default();
+    for i in 0..n {
+        let a: Point3f = p[polygon[i]];
+        let b: Point3f = p[polygon[(i + 1) % n]];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    // project onto the plane of the two other axes
+    let (ax, ay): (usize, usize) =
+        if normal.x.abs() > normal.y.abs() && normal.x.abs() > normal.z.abs() {
+            (1, 2)
+        } else if normal.y.abs() > normal.z.abs() {
+            (2, 0)
+        } else {
+            (0, 1)
+        };
+    let component = |q: &Point3f, axis: usize| -> Float {
+        match axis {
+            0 => q.x,
+            1 => q.y,
+            _ => q.z,
+        }
+    };
+    let q: Vec<Point2f> = polygon
+        .iter()
+        .map(|i| Point2f {
+            x: component(&p[*i], ax),
+            y: component(&p[*i], ay),
+        })
+        .collect();
+    let cross = |a: usize, b: usize, c: usize| -> Float {
+        (q[b].x - q[a].x) * (q[c].y - q[a].y) - (q[b].y - q[a].y) * (q[c].x - q[a].x)
+    };
+    let mut area: Float = 0.0;
+    for i in 0..n {
+        let j: usize = (i + 1) % n;
+        area += q[i].x * q[j].y - q[j].x * q[i].y;
+    }
+    if area == 0.0 as Float {
+        return fan();
+    }
+    let orientation: Float = area.signum();
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles: Vec<[usize; 3]> = Vec::with_capacity(n - 2);
+    while remaining.len() > 3 {
+        let m: usize = remaining.len();
+        let mut clipped: bool = false;
+        for i in 0..m {
+            let (a, b, c) = (
+                remaining[(i + m - 1) % m],
+                remaining[i],
+                remaining[(i + 1) % m],
+            );
+            // convex corner?
+            if cross(a, b, c) * orientation <= 0.0 as Float {
+                continue;
+            }
+            // no other vertex inside the ear?
+            let inside = remaining.iter().any(|v| {
+                *v != a
+                    && *v != b
+                    && *v != c
+                    && cross(a, b, *v) * orientation >= 0.0 as Float
+                    && cross(b, c, *v) * orientation >= 0.0 as Float
+                    && cross(c, a, *v) * orientation >= 0.0 as Float
+            });
+            if inside {
+                continue;
+            }
+            triangles.push([a, b, c]);
+            remaining.remove(i);
+            clipped = true;
+            break;
+        }
+        if !clipped {
+            return fan();
+        }
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+/// Reads the OBJ file at _path_ and creates a triangle mesh (in world
+/// space) for each material used, together with the triangles of the
+/// mesh and the material (if a MTL file referenced by *mtllib*
+/// defines it). Meshes without normals get smooth normals, averaged
+/// over the adjacent faces (weighted by their area).
+///
+/// A unit cube with texture coordinates, five faces using a diffuse
+/// material and the top face a glossy one:
+///
+/// ```rust
+/// use std::path::Path;
+/// use pbrt::core::geometry::{vec3_dot_vec3, Vector3f};
+/// use pbrt::core::material::Material;
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::transform::Transform;
+/// use pbrt::shapes::objmesh::load_obj;
+///
+/// let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/obj/cube.obj");
+/// let meshes = load_obj(&path, &Transform::default(), false).unwrap();
+/// assert_eq!(meshes.len(), 2);
+/// let (sides, side_triangles, side_material) = &meshes[0];
+/// let (top, top_triangles, top_material) = &meshes[1];
+/// assert_eq!((side_triangles.len(), top_triangles.len()), (10, 2));
+/// assert_eq!(sides.n_triangles, 10);
+/// match (side_material.as_ref().map(|m| &**m), top_material.as_ref().map(|m| &**m)) {
+///     (Some(Material::Matte(_)), Some(Material::Plastic(_))) => {}
+///     _ => panic!("unexpected materials"),
+/// }
+/// for mesh in [sides, top].iter() {
+///     assert_eq!(mesh.uv.len(), mesh.p.len());
+///     assert_eq!(mesh.n.len(), mesh.p.len());
+///     for (p, n) in mesh.p.iter().zip(mesh.n.iter()) {
+///         assert!(p.x.abs() == 0.5 && p.y.abs() == 0.5 && p.z.abs() == 0.5);
+///         // smooth normals point away from the center
+///         let n = Vector3f::from(*n);
+///         assert!((n.length() - 1.0).abs() < 1e-5);
+///         assert!(vec3_dot_vec3(&n, &Vector3f { x: p.x, y: p.y, z: p.z }) > 0.0);
+///     }
+///     for uv in mesh.uv.iter() {
+///         assert!(uv.x == 0.0 || uv.x == 1.0);
+///         assert!(uv.y == 0.0 || uv.y == 1.0);
+///     }
+/// }
+/// // the top face (with negative indices) is the square at y = 0.5
+/// assert!(top.p.iter().all(|p| p.y == 0.5));
+/// let area: Float = top_triangles.iter().map(|t| t.area()).sum();
+/// assert!((area - 1.0).abs() < 1e-5);
+/// ```
+///
+/// Polygons don't have to be convex:
+///
+/// ```rust
+/// use pbrt::core::pbrt::Float;
+/// use pbrt::core::transform::Transform;
+/// use pbrt::shapes::objmesh::load_obj;
+///
+/// // an L-shaped hexagon (area 3), a triangle fan around its first
+/// // vertex would cover area outside of it
+/// let obj = "v 1 2 0\nv 0 2 0\nv 0 0 0\nv 2 0 0\nv 2 1 0\nv 1 1 0\nf 1 2 3 4 5 6\n";
+/// let path = std::env::temp_dir().join("pbrt_objmesh_concave.obj");
+/// std::fs::write(&path, obj).unwrap();
+/// let meshes = load_obj(&path, &Transform::default(), false).unwrap();
+/// std::fs::remove_file(&path).unwrap();
+/// assert_eq!(meshes.len(), 1);
+/// let (_mesh, triangles, material) = &meshes[0];
+/// assert!(material.is_none());
+/// assert_eq!(triangles.len(), 4);
+/// let area: Float = triangles.iter().map(|t| t.area()).sum();
+/// assert!((area - 3.0).abs() < 1e-5);
+/// ```
+pub fn load_obj(
+    path: &Path,
+    object_to_world: &Transform,
+    reverse_orientation: bool,
+) -> io::Result<Vec<ObjMesh>> {
+    let reader = BufReader::new(File::open(path)?);
+    let directory: PathBuf = match path.parent() {
+        Some(parent) => parent.to_path_buf(),
+        None => PathBuf::new(),
+    };
+    let mut p: Vec<Point3f> = Vec::new();
+    let mut uvs: Vec<Point2f> = Vec::new();
+    let mut normals: Vec<Normal3f> = Vec::new();
+    let mut mtl_materials: HashMap<String, MtlMaterial> = HashMap::new();
+    // one group per material, in order of first use
+    let mut groups: Vec<FaceGroup> = Vec::new();
+    let mut group_indices: HashMap<String, usize> = HashMap::new();
+    let mut current_group: Option<usize> = None;
+    for (line_number, line) in reader.lines().enumerate() {
+        let line: String = line?;
+        let tokens: Vec<&str> = line.split('#').next().unwrap().split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        let line_number: usize = line_number + 1;
+        match tokens[0] {
+            "v" => {
+                let v: Vec<Float> = parse_floats(path, line_number, &tokens[1..])?;
+                if v.len() < 3 {
+                    return Err(invalid_data(
+                        path,
+                        line_number,
+                        String::from("vertex needs x y z"),
+                    ));
+                }
+                p.push(Point3f {
+                    x: v[0],
+                    y: v[1],
+                    z: v[2],
+                });
+            }
+            "vt" => {
+                let v: Vec<Float> = parse_floats(path, line_number, &tokens[1..])?;
+                if v.is_empty() {
+                    return Err(invalid_data(
+                        path,
+                        line_number,
+                        String::from("uv needs u [v]"),
+                    ));
+                }
+                uvs.push(Point2f {
+                    x: v[0],
+                    y: v.get(1).cloned().unwrap_or(0.0 as Float),
+                });
+            }
+            "vn" => {
+                let v: Vec<Float> = parse_floats(path, line_number, &tokens[1..])?;
+                if v.len() < 3 {
+                    return Err(invalid_data(
+                        path,
+                        line_number,
+                        String::from("normal needs x y z"),
+                    ));
+                }
+                normals.push(Normal3f {
+                    x: v[0],
+                    y: v[1],
+                    z: v[2],
+                });
+            }
+            "f" => {
+                if tokens.len() < 4 {
+                    return Err(invalid_data(
+                        path,
+                        line_number,
+                        String::from("face needs at least three vertices"),
+                    ));
+                }
+                let mut polygon: Vec<(usize, i64, i64)> = Vec::new();
+                for token in &tokens[1..] {
+                    // v, v/vt, v//vn, or v/vt/vn
+                    let parts: Vec<&str> = token.split('/').collect();
+                    let vi: usize = resolve_index(path, line_number, parts[0], p.len())?;
+                    let mut ti: i64 = -1;
+                    if parts.len() > 1 && parts[1] != "" {
+                        ti = resolve_index(path, line_number, parts[1], uvs.len())? as i64;
+                    }
+                    let mut ni: i64 = -1;
+                    if parts.len() > 2 && parts[2] != "" {
+                        ni = resolve_index(path, line_number, parts[2], normals.len())? as i64;
+                    }
+                    polygon.push((vi, ti, ni));
+                }
+                let group_index: usize = match current_group {
+                    Some(group_index) => group_index,
+                    None => {
+                        // faces before any usemtl
+                        groups.push(FaceGroup::default());
+                        group_indices.insert(String::new(), groups.len() - 1);
+                        current_group = Some(groups.len() - 1);
+                        groups.len() - 1
+                    }
+                };
+                let corners: Vec<usize> = polygon.iter().map(|v| v.0).collect();
+                for triangle in triangulate(&p, &corners) {
+                    for corner in triangle.iter() {
+                        groups[group_index].vertices.push(polygon[*corner]);
+                    }
+                }
+            }
+            "usemtl" => {
+                let name: String = tokens[1..].join(" ");
+                current_group = Some(match group_indices.get(&name) {
+                    Some(group_index) => *group_index,
+                    None => {
+                        groups.push(FaceGroup {
+                            material: name.clone(),
+                            vertices: Vec::new(),
+                        });
+                        group_indices.insert(name, groups.len() - 1);
+                        groups.len() - 1
+                    }
+                });
+            }
+            "mtllib" => {
+                for filename in &tokens[1..] {
+                    let mtl_path: PathBuf = directory.join(filename);
+                    if let Err(e) = read_mtl(&mtl_path, &mut mtl_materials) {
+                        println!(
+                            "WARNING: Unable to read material library {:?}: {}",
+                            mtl_path, e
+                        );
+                    }
+                }
+            }
+            // objects, groups, smoothing groups, etc. are ignored
+            _ => {}
+        }
+    }
+    let mut meshes: Vec<ObjMesh> = Vec::new();
+    for group in groups {
+        if group.vertices.is_empty() {
+            continue;
+        }
+        // one mesh vertex for each (position, uv, normal) combination
+        let mut mesh_vertices: Vec<(usize, i64, i64)> = Vec::new();
+        let mut vertex_map: HashMap<(usize, i64, i64), u32> = HashMap::new();
+        let mut vertex_indices: Vec<u32> = Vec::with_capacity(group.vertices.len());
+        for vertex in group.vertices.iter() {
+            let index: u32 = *vertex_map.entry(*vertex).or_insert_with(|| {
+                mesh_vertices.push(*vertex);
+                (mesh_vertices.len() - 1) as u32
+            });
+            vertex_indices.push(index);
+        }
+        let has_uvs: bool = mesh_vertices.iter().all(|v| v.1 >= 0);
+        let has_normals: bool = mesh_vertices.iter().all(|v| v.2 >= 0);
+        let mesh_p: Vec<Point3f> = mesh_vertices.iter().map(|v| p[v.0]).collect();
+        let mut mesh_uv: Vec<Point2f> = Vec::new();
+        if has_uvs {
+            mesh_uv = mesh_vertices.iter().map(|v| uvs[v.1 as usize]).collect();
+        }
+        let mesh_n: Vec<Normal3f> = if has_normals {
+            mesh_vertices
+                .iter()
+                .map(|v| normals[v.2 as usize])
+                .collect()
+        } else {
+            // area weighted average of the face normals around each
+            // position (the cross product's length is twice the area)
+            let mut sums: HashMap<usize, Vector3f> = HashMap::new();
+            for triangle in group.vertices.chunks(3) {
+                let (p0, p1, p2) = (p[triangle[0].0], p[triangle[1].0], p[triangle[2].0]);
+                let face_normal: Vector3f = vec3_cross_vec3(&(p1 - p0), &(p2 - p0));
+                for vertex in triangle {
+                    let sum: &mut Vector3f = sums.entry(vertex.0).or_insert_with(Vector3f::default);
+                    *sum = *sum + face_normal;
+                }
+            }
+            mesh_vertices
+                .iter()
+                .map(|v| {
+                    let sum: Vector3f = sums[&v.0];
+                    if sum.length() > 0.0 as Float {
+                        Normal3f::from(sum.normalize())
+                    } else {
+                        Normal3f::from(sum)
+                    }
+                })
+                .collect()
+        };
+        // transform mesh vertices and normals to world space
+        let p_ws: Vec<Point3f> = mesh_p
+            .iter()
+            .map(|p| object_to_world.transform_point(p))
+            .collect();
+        let n_ws: Vec<Normal3f> = mesh_n
+            .iter()
+            .map(|n| object_to_world.transform_normal(n))
+            .collect();
+        let n_vertices: u32 = p_ws.len() as u32;
+        let mut mesh: TriangleMesh = TriangleMesh::new(
+            *object_to_world,
+            Transform::inverse(object_to_world),
+            reverse_orientation,
+            (vertex_indices.len() / 3) as u32, // n_triangles
+            vertex_indices,
+            n_vertices,
+            p_ws,       // in world space
+            Vec::new(), // no tangents
+            n_ws,       // in world space
+            mesh_uv,
+            None,
+            None,
+        );
+        // named after the file and the material, for statistics
+        mesh.set_name(Some(format!(
+            "{}:{}",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            group.material
+        )));
+        let mesh = Arc::new(mesh);
+        let mut triangles: Vec<Arc<Shape>> = Vec::new();
+        for id in 0..mesh.n_triangles {
+            if mesh.is_degenerate_triangle(id) {
+                continue;
+            }
+            triangles.push(Arc::new(Shape::Trngl(Triangle::new(mesh.clone(), id))));
+        }
+        let material: Option<Arc<Material>> = mtl_materials
+            .get(&group.material)
+            .map(|mtl| mtl.to_material());
+        meshes.push((mesh, triangles, material));
+    }
+    Ok(meshes)
+}
@@ -20,22 +20,30 @@ use crate::core::transform::Transform;
 use crate::shapes::triangle::{Triangle, TriangleMesh};
 use crate::textures::constant::ConstantTexture;
 
-pub fn create_ply_mesh(
-    o2w: &Transform,
-    w2o: &Transform,
-    reverse_orientation: bool,
-    params: &ParamSet,
-    float_textures: Arc<HashMap<String, Arc<dyn Texture<Float> + Send + Sync>>>,
-    search_directory: Option<&Box<PathBuf>>,
-) -> Vec<Arc<Shape>> {
+/// The (object space) vertices and triangles of a PLY file, quads
+/// are split into two triangles.
+pub struct PlyMesh {
+    pub p: Vec<Point3f>,
+    pub n: Vec<Normal3f>,
+    pub uvs: Vec<Point2f>,
+    pub vertex_indices: Vec<u32>,
+}
+
+/// The path of the PLY file given by the "filename" parameter.
+pub fn ply_file_name(params: &ParamSet, search_directory: Option<&PathBuf>) -> String {
     let mut filename: String = params.find_one_string("filename", String::new());
-    if let Some(ref search_directory) = search_directory {
+    if let Some(search_directory) = search_directory {
         let mut path_buf: PathBuf = PathBuf::from("/");
-        path_buf.push(search_directory.as_ref());
+        path_buf.push(search_directory);
         path_buf.push(filename);
         filename = String::from(path_buf.to_str().unwrap());
     }
-    let result = File::open(&filename);
+    filename
+}
+
+/// Reads the PLY file _filename_ (and panics if it can't).
+pub fn read_ply(filename: &str) -> PlyMesh {
+    let result = File::open(filename);
     if result.is_err() {
         panic!("Couldn't open PLY file {:?}", filename);
     }
@@ -194,6 +202,100 @@ pub fn create_ply_mesh(
             _ => unreachable!(),
         }
     }
+    PlyMesh {
+        p,
+        n,
+        uvs,
+        vertex_indices: tm_vertex_indices,
+    }
+}
+
+/// Replaces the "filename" parameter of a plymesh by the contents
+/// of the PLY file (as "P", "N", "uv", and "indices" parameters), so
+/// a recorded scene description doesn't need the file anymore.
+///
+/// ```rust
+/// use pbrt::core::api::SceneBuilder;
+/// use pbrt::core::paramset::ParamSet;
+///
+/// let directory = std::env::temp_dir();
+/// let ply = "ply\nformat ascii 1.0\nelement vertex 4\n\
+///     property float x\nproperty float y\nproperty float z\n\
+///     element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+///     -1 -1 2\n1 -1 2\n1 1 3\n-1 1 3\n4 0 1 2 3\n";
+/// std::fs::write(directory.join("inline_ply_mesh.ply"), ply).unwrap();
+/// let mut params = ParamSet::default();
+/// params.name = String::from("plymesh");
+/// params.add_string(String::from("filename"), String::from("inline_ply_mesh.ply"));
+/// let mut builder = SceneBuilder::new(1);
+/// builder.start_recording();
+/// builder
+///     .search_directory(directory.clone())
+///     .world_begin()
+///     .shape(params);
+/// let recording = builder.take_recording().unwrap();
+/// // the PLY file is a source of the recording ...
+/// assert_eq!(recording.sources.len(), 1);
+/// std::fs::remove_file(directory.join("inline_ply_mesh.ply")).unwrap();
+/// // ... but isn't needed for replaying it
+/// let mut replayed = SceneBuilder::new(1);
+/// recording.replay(&mut replayed);
+/// let bounds = replayed.build().0.world_bound();
+/// assert_eq!((bounds.p_min.z, bounds.p_max.z), (2.0, 3.0));
+/// ```
+pub fn inline_ply_mesh(params: &ParamSet, filename: &str) -> ParamSet {
+    let mesh: PlyMesh = read_ply(filename);
+    let mut inlined: ParamSet = params.clone();
+    inlined.erase_string("filename");
+    inlined.add_point3fs(
+        String::from("P"),
+        mesh.p.iter().flat_map(|p| vec![p.x, p.y, p.z]).collect(),
+    );
+    if !mesh.n.is_empty() {
+        inlined.add_normal3fs(
+            String::from("N"),
+            mesh.n.iter().flat_map(|n| vec![n.x, n.y, n.z]).collect(),
+        );
+    }
+    if !mesh.uvs.is_empty() {
+        inlined.add_point2fs(
+            String::from("uv"),
+            mesh.uvs.iter().flat_map(|uv| vec![uv.x, uv.y]).collect(),
+        );
+    }
+    inlined.add_ints(
+        String::from("indices"),
+        mesh.vertex_indices.iter().map(|i| *i as i32).collect(),
+    );
+    inlined
+}
+
+pub fn create_ply_mesh(
+    o2w: &Transform,
+    w2o: &Transform,
+    reverse_orientation: bool,
+    params: &ParamSet,
+    float_textures: Arc<HashMap<String, Arc<dyn Texture<Float> + Send + Sync>>>,
+    search_directory: Option<&Box<PathBuf>>,
+    name: Option<String>,
+) -> Vec<Arc<Shape>> {
+    // a plymesh might have been inlined, see **inline_ply_mesh()**
+    let p: Vec<Point3f> = params.find_point3f("P");
+    let (p, n, uvs, tm_vertex_indices) = if p.is_empty() {
+        let mesh: PlyMesh = read_ply(&ply_file_name(params, search_directory.map(|d| d.as_ref())));
+        (mesh.p, mesh.n, mesh.uvs, mesh.vertex_indices)
+    } else {
+        (
+            p,
+            params.find_normal3f("N"),
+            params.find_point2f("uv"),
+            params
+                .find_int("indices")
+                .iter()
+                .map(|i| *i as u32)
+                .collect(),
+        )
+    };
     // for i in 0..p.len() {
     //     println!("{:?}: {:?}", i, p[i]);
     // }
@@ -251,7 +353,7 @@ pub fn create_ply_mesh(
             shadow_alpha_tex = Some(Arc::new(ConstantTexture::new(0.0 as Float)));
         }
     }
-    let mesh = Arc::new(TriangleMesh::new(
+    let mut mesh: TriangleMesh = TriangleMesh::new(
         *o2w,
         *w2o,
         reverse_orientation,
@@ -264,13 +366,15 @@ pub fn create_ply_mesh(
         uvs,
         alpha_tex,
         shadow_alpha_tex,
-    ));
+    );
+    mesh.set_name(name);
+    let mesh = Arc::new(mesh);
     let mut shapes: Vec<Arc<Shape>> = Vec::new();
     for id in 0..mesh.n_triangles {
+        if mesh.is_degenerate_triangle(id) {
+            continue;
+        }
         let triangle = Arc::new(Shape::Trngl(Triangle::new(
-            mesh.object_to_world,
-            mesh.world_to_object,
-            mesh.transform_swaps_handedness,
             mesh.clone(),
             id.try_into().unwrap(),
         )));
@@ -15,11 +15,19 @@ use crate::core::material::Material;
 use crate::core::pbrt::gamma;
 use crate::core::pbrt::Float;
 use crate::core::sampling::uniform_sample_triangle;
+use crate::core::stats::{named_stat_add, stat_name_id, NamedStat};
 use crate::core::texture::Texture;
-use crate::core::transform::Transform;
+use crate::core::transform::{AnimatedTransform, Transform};
 
 // see triangle.h
 
+crate::stat_ratio!(
+    "Intersections/Ray-triangle intersection tests",
+    TRIANGLE_HITS,
+    TRIANGLE_TESTS
+);
+crate::stat_memory!("Memory/Triangle meshes", TRI_MESH_BYTES);
+
 #[derive(Clone)]
 pub struct TriangleMesh {
     /// the total number of triangles in the mesh
@@ -43,9 +51,94 @@ pub struct TriangleMesh {
     pub world_to_object: Transform, // TODO: not pub?
     pub reverse_orientation: bool,
     pub transform_swaps_handedness: bool, // TODO: not pub?
+    /// for moving meshes (see new_animated()); the world space data
+    /// above is then valid at the start of the shutter interval
+    pub animated_object_to_world: Option<AnimatedTransform>,
+    /// an optional name (see set_name())
+    pub name: Option<String>,
+    /// the id of _name_ for per-mesh statistics
+    pub stats_id: u32,
 }
 
 impl TriangleMesh {
+    /// Creates a mesh from vertex data in world space. The vertex
+    /// indices are kept as given, so triangle _id_ of the mesh is
+    /// always the _id_-th triangle of the input. Degenerate triangles
+    /// (with coincident or collinear vertices) are reported and should
+    /// be skipped when creating the shapes (see
+    /// **is_degenerate_triangle()**).
+    ///
+    /// Meshes with many coincident vertices neither panic nor produce
+    /// NaNs when intersected:
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::geometry::{Point3f, Ray, Vector3f};
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::rng::Rng;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::shapes::triangle::{Triangle, TriangleMesh};
+    ///
+    /// // a flat, a collinear, and a valid triangle
+    /// let p = vec![
+    ///     Point3f { x: 0.0, y: 0.0, z: 0.0 },
+    ///     Point3f { x: 1.0, y: 0.0, z: 0.0 },
+    ///     Point3f { x: 2.0, y: 0.0, z: 0.0 },
+    ///     Point3f { x: 0.0, y: 1.0, z: 0.0 },
+    /// ];
+    /// let mesh = TriangleMesh::new(
+    ///     Transform::default(), Transform::default(), false,
+    ///     3, vec![0, 1, 1, 0, 1, 2, 0, 1, 3], 4, p,
+    ///     vec![], vec![], vec![], None, None,
+    /// );
+    /// assert_eq!(mesh.n_triangles, 3);
+    /// assert_eq!(mesh.vertex_indices.len(), 9);
+    /// assert!(mesh.is_degenerate_triangle(0));
+    /// assert!(mesh.is_degenerate_triangle(1));
+    /// assert!(!mesh.is_degenerate_triangle(2));
+    /// // random triangles between a few (partly coincident) points
+    /// let mut rng = Rng::new();
+    /// let p: Vec<Point3f> = (0..8)
+    ///     .map(|_| Point3f {
+    ///         x: rng.uniform_uint32_bounded(3) as Float - 1.0,
+    ///         y: rng.uniform_uint32_bounded(3) as Float - 1.0,
+    ///         z: rng.uniform_uint32_bounded(3) as Float - 1.0,
+    ///     })
+    ///     .collect();
+    /// let vertex_indices: Vec<u32> = (0..300).map(|_| rng.uniform_uint32_bounded(8)).collect();
+    /// let mesh = Arc::new(TriangleMesh::new(
+    ///     Transform::default(), Transform::default(), false,
+    ///     100, vertex_indices, 8, p,
+    ///     vec![], vec![], vec![], None, None,
+    /// ));
+    /// assert_eq!(mesh.n_triangles, 100);
+    /// assert!((0..100).any(|id| mesh.is_degenerate_triangle(id)));
+    /// // even the degenerate ones are safe to intersect
+    /// for id in 0..mesh.n_triangles {
+    ///     let triangle = Triangle::new(mesh.clone(), id);
+    ///     for _ in 0..50 {
+    ///         let mut random = || 4.0 * rng.uniform_float() - 2.0;
+    ///         let o = Point3f { x: random(), y: random(), z: random() };
+    ///         let target = Point3f { x: random(), y: random(), z: random() } * 0.5;
+    ///         let ray = Ray {
+    ///             o,
+    ///             d: target - o,
+    ///             t_max: Float::INFINITY,
+    ///             time: 0.0,
+    ///             medium: None,
+    ///             differential: None,
+    ///         };
+    ///         if let Some((si, t_hit)) = triangle.intersect(&ray) {
+    ///             assert!(!mesh.is_degenerate_triangle(id));
+    ///             assert!(t_hit.is_finite());
+    ///             assert!(!Vector3f::from(si.n).has_nans());
+    ///             assert!(!Vector3f::from(si.shading.n).has_nans());
+    ///             assert!((si.n.length() - 1.0).abs() < 1e-3);
+    ///         }
+    ///         triangle.intersect_p(&ray);
+    ///     }
+    /// }
+    /// ```
     pub fn new(
         object_to_world: Transform,
         world_to_object: Transform,
@@ -60,7 +153,7 @@ impl TriangleMesh {
         alpha_mask: Option<Arc<dyn Texture<Float> + Send + Sync>>,
         shadow_alpha_mask: Option<Arc<dyn Texture<Float> + Send + Sync>>,
     ) -> Self {
-        TriangleMesh {
+        let mesh = TriangleMesh {
             // Shape
             object_to_world,
             world_to_object,
@@ -76,37 +169,186 @@ impl TriangleMesh {
             uv,
             alpha_mask,
             shadow_alpha_mask,
+            animated_object_to_world: None,
+            name: None,
+            stats_id: 0_u32,
+        };
+        let n_degenerate: usize = (0..mesh.n_triangles)
+            .filter(|id| mesh.is_degenerate_triangle(*id))
+            .count();
+        if n_degenerate > 0 {
+            println!(
+                "WARNING: {} degenerate triangle(s) in mesh will be skipped",
+                n_degenerate
+            );
+        }
+        crate::stat_add!(
+            TRI_MESH_BYTES,
+            std::mem::size_of::<TriangleMesh>()
+                + mesh.vertex_indices.len() * std::mem::size_of::<u32>()
+                + mesh.p.len() * std::mem::size_of::<Point3f>()
+                + mesh.n.len() * std::mem::size_of::<Normal3f>()
+                + mesh.s.len() * std::mem::size_of::<Vector3f>()
+                + mesh.uv.len() * std::mem::size_of::<Point2f>()
+        );
+        mesh
+    }
+    /// Does triangle _id_ have coincident (or collinear) vertices?
+    /// Such a triangle can't be hit and has no normal, so no shape
+    /// should be created for it. Triangles with invalid vertex indices
+    /// are left alone.
+    pub fn is_degenerate_triangle(&self, id: u32) -> bool {
+        let v: &[u32] = match self.vertex_indices.get(3 * id as usize..3 * id as usize + 3) {
+            Some(v) => v,
+            None => return false,
+        };
+        match (
+            self.p.get(v[0] as usize),
+            self.p.get(v[1] as usize),
+            self.p.get(v[2] as usize),
+        ) {
+            (Some(p0), Some(p1), Some(p2)) => {
+                v[0] == v[1]
+                    || v[1] == v[2]
+                    || v[2] == v[0]
+                    || is_degenerate(&vec3_cross_vec3(&(*p0 - *p2), &(*p1 - *p2)))
+                    || is_degenerate(&vec3_cross_vec3(&(*p2 - *p0), &(*p1 - *p0)))
+            }
+            _ => false,
+        }
+    }
+    /// Names the mesh, e.g. after the "name" parameter of its shape,
+    /// to report ray intersection statistics per mesh (see
+    /// **named_stat_add()**).
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.stats_id = stat_name_id(name.as_deref());
+        self.name = name;
+    }
+    /// Creates a mesh moving with _animated_object_to_world_. Unlike
+    /// new(), vertex positions, tangents, and normals are given in
+    /// object space. Triangles of the mesh are intersected at the
+    /// transformation interpolated for the ray's time:
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::geometry::{Point3f, Ray, Vector3f};
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::transform::{AnimatedTransform, Transform};
+    /// use pbrt::shapes::triangle::{Triangle, TriangleMesh};
+    ///
+    /// fn main() {
+    ///     // move by 2 units along x while the shutter is open
+    ///     let start = Transform::translate(&Vector3f { x: 0.0, y: 0.0, z: 0.0 });
+    ///     let end = Transform::translate(&Vector3f { x: 2.0, y: 0.0, z: 0.0 });
+    ///     let anim = AnimatedTransform::new(&start, 0.0, &end, 1.0);
+    ///     let p = vec![
+    ///         Point3f { x: -0.5, y: -0.5, z: 0.0 },
+    ///         Point3f { x: 0.5, y: -0.5, z: 0.0 },
+    ///         Point3f { x: 0.0, y: 0.5, z: 0.0 },
+    ///     ];
+    ///     let mesh = Arc::new(TriangleMesh::new_animated(
+    ///         anim, false, 1, vec![0, 1, 2], 3, p, vec![], vec![], vec![], None, None,
+    ///     ));
+    ///     let triangle = Triangle::new(mesh.clone(), 0);
+    ///     let ray = |x: Float, time: Float| Ray {
+    ///         o: Point3f { x, y: 0.0, z: -1.0 },
+    ///         d: Vector3f { x: 0.0, y: 0.0, z: 1.0 },
+    ///         t_max: Float::INFINITY,
+    ///         time,
+    ///         medium: None,
+    ///         differential: None,
+    ///     };
+    ///     // mid-shutter, the triangle is centered at x = 1
+    ///     let (si, t_hit) = triangle.intersect(&ray(1.0, 0.5)).unwrap();
+    ///     assert!((si.p.x - 1.0).abs() < 1e-4 && (t_hit - 1.0).abs() < 1e-4);
+    ///     assert!(triangle.intersect_p(&ray(1.0, 0.5)));
+    ///     assert!(!triangle.intersect_p(&ray(0.0, 0.5)));
+    ///     assert!(triangle.intersect_p(&ray(0.0, 0.0)));
+    ///     // the world bound covers the whole motion
+    ///     assert!(triangle.world_bound().p_max.x >= 2.5);
+    /// }
+    /// ```
+    pub fn new_animated(
+        animated_object_to_world: AnimatedTransform,
+        reverse_orientation: bool,
+        n_triangles: u32,
+        vertex_indices: Vec<u32>,
+        n_vertices: u32,
+        p: Vec<Point3f>,
+        s: Vec<Vector3f>,
+        n: Vec<Normal3f>,
+        uv: Vec<Point2f>,
+        alpha_mask: Option<Arc<dyn Texture<Float> + Send + Sync>>,
+        shadow_alpha_mask: Option<Arc<dyn Texture<Float> + Send + Sync>>,
+    ) -> Self {
+        // transform mesh data to world space at the start time
+        let mut object_to_world: Transform = Transform::default();
+        animated_object_to_world.interpolate(Float::NEG_INFINITY, &mut object_to_world);
+        let p_ws: Vec<Point3f> = p
+            .iter()
+            .map(|p| object_to_world.transform_point(p))
+            .collect();
+        let s_ws: Vec<Vector3f> = s
+            .iter()
+            .map(|s| object_to_world.transform_vector(s))
+            .collect();
+        let n_ws: Vec<Normal3f> = n
+            .iter()
+            .map(|n| object_to_world.transform_normal(n))
+            .collect();
+        let mut mesh: TriangleMesh = TriangleMesh::new(
+            object_to_world,
+            Transform::inverse(&object_to_world),
+            reverse_orientation,
+            n_triangles,
+            vertex_indices,
+            n_vertices,
+            p_ws,
+            s_ws,
+            n_ws,
+            uv,
+            alpha_mask,
+            shadow_alpha_mask,
+        );
+        mesh.animated_object_to_world = Some(animated_object_to_world);
+        mesh
+    }
+    /// For animated meshes, the transformation from the (world space)
+    /// mesh data to world space at _time_.
+    fn start_to_time(&self, time: Float) -> Option<Transform> {
+        if let Some(ref animated_object_to_world) = self.animated_object_to_world {
+            let mut object_to_world: Transform = Transform::default();
+            animated_object_to_world.interpolate(time, &mut object_to_world);
+            Some(object_to_world * self.world_to_object)
+        } else {
+            None
         }
     }
 }
 
+/// Is the triangle with the (unnormalized) geometric normal _ng_
+/// degenerate, i.e. does it have zero area (or non-finite vertices)?
+fn is_degenerate(ng: &Vector3f) -> bool {
+    let length_squared: Float = ng.length_squared();
+    length_squared == 0.0 as Float || !length_squared.is_finite()
+}
+
+/// A single triangle of a **TriangleMesh**. All triangles of a mesh
+/// share its transformations and orientation, so a triangle only
+/// stores a reference to the mesh and its index there.
 #[derive(Clone)]
 pub struct Triangle {
     mesh: Arc<TriangleMesh>,
     pub id: u32,
-    // inherited from class Shape (see shape.h)
-    pub object_to_world: Transform,
-    pub world_to_object: Transform,
-    pub reverse_orientation: bool,
-    pub transform_swaps_handedness: bool,
     pub material: Option<Arc<Material>>,
 }
 
 impl Triangle {
-    pub fn new(
-        object_to_world: Transform,
-        world_to_object: Transform,
-        reverse_orientation: bool,
-        mesh: Arc<TriangleMesh>,
-        tri_number: u32,
-    ) -> Self {
+    pub fn new(mesh: Arc<TriangleMesh>, tri_number: u32) -> Self {
+        crate::stat_add!(TRI_MESH_BYTES, std::mem::size_of::<Triangle>());
         Triangle {
             mesh,
             id: tri_number,
-            object_to_world,
-            world_to_object,
-            reverse_orientation,
-            transform_swaps_handedness: false,
             material: None,
         }
     }
@@ -135,13 +377,16 @@ impl Triangle {
             self.mesh.p[self.mesh.vertex_indices[(self.id * 3) as usize + 2] as usize];
         bnd3_union_pnt3(
             &Bounds3f::new(
-                self.world_to_object.transform_point(&p0),
-                self.world_to_object.transform_point(&p1),
+                self.mesh.world_to_object.transform_point(&p0),
+                self.mesh.world_to_object.transform_point(&p1),
             ),
-            &self.world_to_object.transform_point(&p2),
+            &self.mesh.world_to_object.transform_point(&p2),
         )
     }
     pub fn world_bound(&self) -> Bounds3f {
+        if let Some(ref animated_object_to_world) = self.mesh.animated_object_to_world {
+            return animated_object_to_world.motion_bounds(&self.object_bound());
+        }
         let p0: Point3f =
             self.mesh.p[self.mesh.vertex_indices[(self.id * 3) as usize + 0] as usize];
         let p1: Point3f =
@@ -151,6 +396,23 @@ impl Triangle {
         bnd3_union_pnt3(&Bounds3f::new(p0, p1), &p2)
     }
     pub fn intersect(&self, ray: &Ray) -> Option<(SurfaceInteraction, Float)> {
+        named_stat_add(self.mesh.stats_id, NamedStat::RayTests, 1);
+        let hit: Option<(SurfaceInteraction, Float)> =
+            if let Some(start_to_time) = self.mesh.start_to_time(ray.time) {
+                // intersect the mesh where it is at the ray's time
+                let r: Ray = Transform::inverse(&start_to_time).transform_ray(ray);
+                self.intersect_static(&r)
+                    .map(|(si, t_hit)| (start_to_time.transform_surface_interaction(&si), t_hit))
+            } else {
+                self.intersect_static(ray)
+            };
+        if hit.is_some() {
+            named_stat_add(self.mesh.stats_id, NamedStat::RayHits, 1);
+        }
+        hit
+    }
+    fn intersect_static(&self, ray: &Ray) -> Option<(SurfaceInteraction, Float)> {
+        crate::stat_inc!(TRIANGLE_TESTS);
         // get triangle vertices in _p0_, _p1_, and _p2_
         let p0: &Point3f =
             &self.mesh.p[self.mesh.vertex_indices[(self.id * 3) as usize + 0] as usize];
@@ -291,6 +553,28 @@ impl Triangle {
         if t <= delta_t {
             return None;
         }
+        let si: SurfaceInteraction = self.interaction_at(ray, b0, b1, b2)?;
+        crate::stat_inc!(TRIANGLE_HITS);
+        Some((si, t as Float))
+    }
+    /// Fills in the surface interaction of a ray hitting the triangle
+    /// at the barycentric coordinates _b0_, _b1_ and _b2_ (of the
+    /// vertices as stored in the mesh), which can also come from
+    /// another ray tracing kernel (e.g. Embree). Returns _None_ for
+    /// degenerate triangles and for hits the alpha mask cuts away.
+    pub fn interaction_at(
+        &self,
+        ray: &Ray,
+        b0: Float,
+        b1: Float,
+        b2: Float,
+    ) -> Option<SurfaceInteraction<'_>> {
+        let p0: &Point3f =
+            &self.mesh.p[self.mesh.vertex_indices[(self.id * 3) as usize + 0] as usize];
+        let p1: &Point3f =
+            &self.mesh.p[self.mesh.vertex_indices[(self.id * 3) as usize + 1] as usize];
+        let p2: &Point3f =
+            &self.mesh.p[self.mesh.vertex_indices[(self.id * 3) as usize + 2] as usize];
         // compute triangle partial derivatives
         let uv: [Point2f; 3] = self.get_uvs();
         // compute deltas for triangle partial derivatives
@@ -298,6 +582,12 @@ impl Triangle {
         let duv12: Vector2f = uv[1] - uv[2];
         let dp02: Vector3f = *p0 - *p2;
         let dp12: Vector3f = *p1 - *p2;
+        let ng: Vector3f = vec3_cross_vec3(&dp02, &dp12);
+        if is_degenerate(&ng) {
+            // the triangle is actually degenerate (it has no normal);
+            // the intersection is bogus
+            return None;
+        }
         let determinant: Float = duv02.x * duv12.y - duv02.y * duv12.x;
         let degenerate_uv: bool = determinant.abs() < 1e-8 as Float;
         // Vector3f dpdu, dpdv;
@@ -310,11 +600,11 @@ impl Triangle {
         }
         if degenerate_uv || vec3_cross_vec3(&dpdu, &dpdv).length_squared() == 0.0 {
             // handle zero determinant for triangle partial derivative matrix
-            vec3_coordinate_system(
-                &vec3_cross_vec3(&(*p2 - *p0), &(*p1 - *p0)).normalize(),
-                &mut dpdu,
-                &mut dpdv,
-            );
+            let ng_from_p0: Vector3f = vec3_cross_vec3(&(*p2 - *p0), &(*p1 - *p0));
+            if is_degenerate(&ng_from_p0) {
+                return None;
+            }
+            vec3_coordinate_system(&ng_from_p0.normalize(), &mut dpdu, &mut dpdv);
         }
         // compute error bounds for triangle intersection
         let x_abs_sum: Float = (b0 * p0.x).abs() + (b1 * p1.x).abs() + (b2 * p2.x).abs();
@@ -356,7 +646,7 @@ impl Triangle {
             &p_hit, &p_error, &uv_hit, &wo, &dpdu, &dpdv, &dndu, &dndv, ray.time, None,
         );
         // override surface normal in _isect_ for triangle
-        let surface_normal: Normal3f = Normal3f::from(vec3_cross_vec3(&dp02, &dp12).normalize());
+        let surface_normal: Normal3f = Normal3f::from(ng.normalize());
         si.n = surface_normal;
         si.shading.n = surface_normal;
         if !self.mesh.n.is_empty() || !self.mesh.s.is_empty() {
@@ -433,18 +723,34 @@ impl Triangle {
             }
             si.set_shading_geometry(&ss, &ts, &dndu, &dndv, true);
         }
-        // ensure correct orientation of the geometric normal
+        // ensure correct orientation of the geometric normal (the
+        // vertex normals, if present, define the outside)
         if !self.mesh.n.is_empty() {
             si.n = nrm_faceforward_nrm(&si.n, &si.shading.n);
-        } else if self.reverse_orientation ^ self.transform_swaps_handedness {
+            if self.mesh.reverse_orientation {
+                si.shading.n = -si.shading.n;
+                si.n = -si.n;
+            }
+        } else if self.mesh.reverse_orientation ^ self.mesh.transform_swaps_handedness {
             si.shading.n = -si.n;
             si.n = -si.n;
         }
-        Some((si, t as Float))
+        Some(si)
     }
     pub fn intersect_p(&self, ray: &Ray) -> bool {
-        // TODO: ProfilePhase p(Prof::TriIntersectP);
-        // TODO: ++nTests;
+        named_stat_add(self.mesh.stats_id, NamedStat::RayTests, 1);
+        let hit: bool = if let Some(start_to_time) = self.mesh.start_to_time(ray.time) {
+            self.intersect_p_static(&Transform::inverse(&start_to_time).transform_ray(ray))
+        } else {
+            self.intersect_p_static(ray)
+        };
+        if hit {
+            named_stat_add(self.mesh.stats_id, NamedStat::RayHits, 1);
+        }
+        hit
+    }
+    fn intersect_p_static(&self, ray: &Ray) -> bool {
+        crate::stat_inc!(TRIANGLE_TESTS);
         // get triangle vertices in _p0_, _p1_, and _p2_
         let p0: &Point3f =
             &self.mesh.p[self.mesh.vertex_indices[(self.id * 3) as usize + 0] as usize];
@@ -606,16 +912,12 @@ impl Triangle {
             if degenerate_uv || vec3_cross_vec3(&dpdu, &dpdv).length_squared() == 0.0 {
                 // handle zero determinant for triangle partial derivative matrix
                 let ng = vec3_cross_vec3(&(*p2 - *p0), &(*p1 - *p0));
-                if ng.length_squared() == 0.0 as Float {
+                if is_degenerate(&ng) {
                     // the triangle is actually degenerate; the
                     // intersection is bogus
                     return false;
                 }
-                vec3_coordinate_system(
-                    &vec3_cross_vec3(&(*p2 - *p0), &(*p1 - *p0)).normalize(),
-                    &mut dpdu,
-                    &mut dpdv,
-                );
+                vec3_coordinate_system(&ng.normalize(), &mut dpdu, &mut dpdv);
             }
             // interpolate $(u,v)$ parametric coordinates and hit point
             let p_hit: Point3f = *p0 * b0 + *p1 * b1 + *p2 * b2;
@@ -644,17 +946,20 @@ impl Triangle {
                 }
             }
         }
-        // TODO: ++nHits;
+        crate::stat_inc!(TRIANGLE_HITS);
         true
     }
     pub fn get_reverse_orientation(&self) -> bool {
-        self.reverse_orientation
+        self.mesh.reverse_orientation
     }
     pub fn get_transform_swaps_handedness(&self) -> bool {
-        self.transform_swaps_handedness
+        self.mesh.transform_swaps_handedness
     }
     pub fn get_object_to_world(&self) -> Transform {
-        self.object_to_world
+        self.mesh.object_to_world
+    }
+    pub fn get_mesh(&self) -> Arc<TriangleMesh> {
+        self.mesh.clone()
     }
     pub fn area(&self) -> Float {
         // get triangle vertices in _p0_, _p1_, and _p2_
@@ -690,7 +995,10 @@ impl Triangle {
                         * (1.0 as Float - b[0] - b[1]),
             );
             it.n = nrm_faceforward_nrm(&it.n, &ns);
-        } else if self.reverse_orientation ^ self.transform_swaps_handedness {
+            if self.mesh.reverse_orientation {
+                it.n *= -1.0 as Float;
+            }
+        } else if self.mesh.reverse_orientation ^ self.mesh.transform_swaps_handedness {
             it.n *= -1.0 as Float;
         }
         // compute error bounds for sampled point on triangle
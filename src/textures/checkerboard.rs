@@ -1,40 +1,217 @@
 // std
+use std::ops::{Add, Mul};
 use std::sync::Arc;
 // pbrt
-use crate::core::geometry::{Point2f, Vector2f};
+use crate::core::geometry::{Point2f, Point3f, Vector2f, Vector3f};
 use crate::core::interaction::SurfaceInteraction;
-use crate::core::texture::{Texture, TextureMapping2D};
+use crate::core::pbrt::Float;
+use crate::core::texture::{Texture, TextureMapping2D, TextureMapping3D};
 
 // checkerboard.h
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AAMethod {
+    None,
+    ClosedForm,
+}
+
 pub struct Checkerboard2DTexture<T> {
     pub tex1: Arc<dyn Texture<T> + Send + Sync>,
     pub tex2: Arc<dyn Texture<T> + Send + Sync>,
     pub mapping: Box<TextureMapping2D>,
-    // TODO: const AAMethod aaMethod;
+    pub aa_method: AAMethod,
 }
 
 impl<T: Copy> Checkerboard2DTexture<T> {
+    /// A checkerboard in texture space. With **AAMethod::ClosedForm**
+    /// it returns the average over the filter footprint given by the
+    /// ray differentials, so far away checks blend to the mean instead
+    /// of aliasing:
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::geometry::Point2f;
+    /// use pbrt::core::interaction::SurfaceInteraction;
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::texture::{Texture, TextureMapping2D, UVMapping2D};
+    /// use pbrt::textures::checkerboard::{AAMethod, Checkerboard2DTexture};
+    /// use pbrt::textures::constant::ConstantTexture;
+    ///
+    /// let checks = |aa_method: AAMethod| -> Checkerboard2DTexture<Float> {
+    ///     Checkerboard2DTexture::new(
+    ///         Box::new(TextureMapping2D::UV(UVMapping2D::new(1.0, 1.0, 0.0, 0.0))),
+    ///         Arc::new(ConstantTexture::new(1.0 as Float)),
+    ///         Arc::new(ConstantTexture::new(0.0 as Float)),
+    ///         aa_method,
+    ///     )
+    /// };
+    /// let (filtered, point_sampled) = (checks(AAMethod::ClosedForm), checks(AAMethod::None));
+    /// // a footprint of +-_width_ checks around (_u_, _v_)
+    /// let si = |u: Float, v: Float, width: Float| -> SurfaceInteraction {
+    ///     let si: SurfaceInteraction = SurfaceInteraction::default();
+    ///     let mut si = SurfaceInteraction { uv: Point2f { x: u, y: v }, ..si };
+    ///     *si.dudx.get_mut().unwrap() = width;
+    ///     *si.dvdy.get_mut().unwrap() = width;
+    ///     si
+    /// };
+    /// for i in 0..10 {
+    ///     let (u, v): (Float, Float) = (0.37 * i as Float, 1.3 - 0.71 * i as Float);
+    ///     // far away: many checks per pixel average to one half ...
+    ///     assert_eq!(filtered.evaluate(&si(u, v, 8.0)), 0.5);
+    ///     // ... while point sampling picks one of them
+    ///     let point: Float = point_sampled.evaluate(&si(u, v, 8.0));
+    ///     assert!(point == 0.0 || point == 1.0);
+    ///     // in between: the box filtered value is the covered fraction
+    ///     let width: Float = 0.9;
+    ///     let n: usize = 200;
+    ///     let mut even: usize = 0;
+    ///     for j in 0..n * n {
+    ///         let s: Float = u + width * (2.0 * ((j % n) as Float + 0.5) / n as Float - 1.0);
+    ///         let t: Float = v + width * (2.0 * ((j / n) as Float + 0.5) / n as Float - 1.0);
+    ///         if (s.floor() as i32 + t.floor() as i32).rem_euclid(2) == 0 {
+    ///             even += 1;
+    ///         }
+    ///     }
+    ///     let expected: Float = even as Float / (n * n) as Float;
+    ///     assert!((filtered.evaluate(&si(u, v, width)) - expected).abs() < 0.01);
+    ///     // without differentials it point samples
+    ///     assert_eq!(filtered.evaluate(&si(u, v, 0.0)), point);
+    /// }
+    /// ```
     pub fn new(
         mapping: Box<TextureMapping2D>,
         tex1: Arc<dyn Texture<T> + Send + Sync>,
-        tex2: Arc<dyn Texture<T> + Send + Sync>, // , TODO: aaMethod
+        tex2: Arc<dyn Texture<T> + Send + Sync>,
+        aa_method: AAMethod,
     ) -> Self {
         Checkerboard2DTexture {
             tex1,
             tex2,
             mapping,
+            aa_method,
         }
     }
 }
 
-impl<T: Copy> Texture<T> for Checkerboard2DTexture<T> {
+impl<T: Copy> Texture<T> for Checkerboard2DTexture<T>
+where
+    T: Add<Output = T>,
+    T: Mul<Output = T>,
+    T: From<Float>,
+{
     fn evaluate(&self, si: &SurfaceInteraction) -> T {
         let mut dstdx: Vector2f = Vector2f::default();
         let mut dstdy: Vector2f = Vector2f::default();
         let st: Point2f = self.mapping.map(si, &mut dstdx, &mut dstdy);
-        // TODO: if (aaMethod == AAMethod::None) {
-        if (st.x.floor() as u32 + st.y.floor() as u32) % 2 == 0 {
+        if self.aa_method == AAMethod::ClosedForm {
+            // compute the corners of the filter region
+            let ds: Float = dstdx.x.abs().max(dstdy.x.abs());
+            let dt: Float = dstdx.y.abs().max(dstdy.y.abs());
+            let s0: Float = st.x - ds;
+            let s1: Float = st.x + ds;
+            let t0: Float = st.y - dt;
+            let t1: Float = st.y + dt;
+            // point sample if the filter is entirely inside one check
+            if s0.floor() != s1.floor() || t0.floor() != t1.floor() {
+                // apply box filter to checkerboard region
+                let sint: Float = box_filtered_odd_fraction(st.x, ds);
+                let tint: Float = box_filtered_odd_fraction(st.y, dt);
+                let mut area2: Float = sint + tint - 2.0 as Float * sint * tint;
+                if ds > 1.0 as Float || dt > 1.0 as Float {
+                    area2 = 0.5 as Float;
+                }
+                return self.tex1.evaluate(si) * T::from(1.0 as Float - area2)
+                    + self.tex2.evaluate(si) * T::from(area2);
+            }
+        }
+        if (st.x.floor() as i32 + st.y.floor() as i32) % 2 == 0 {
+            self.tex1.evaluate(si)
+        } else {
+            self.tex2.evaluate(si)
+        }
+    }
+}
+
+/// Integral of the step function which is one for odd checks,
+/// i.e. $\int_0^x (\lfloor t \rfloor \bmod 2) dt$.
+fn bump_int(x: Float) -> Float {
+    let half: Float = x / 2.0 as Float;
+    half.floor() + 2.0 as Float * (half - half.floor() - 0.5 as Float).max(0.0 as Float)
+}
+
+/// Fraction of the interval $[x - d, x + d]$ covered by odd checks. A
+/// zero width (no ray differentials) falls back to point sampling.
+fn box_filtered_odd_fraction(x: Float, d: Float) -> Float {
+    if d == 0.0 as Float {
+        (x.floor() as i32).rem_euclid(2) as Float
+    } else {
+        (bump_int(x + d) - bump_int(x - d)) / (2.0 as Float * d)
+    }
+}
+
+pub struct Checkerboard3DTexture<T> {
+    pub tex1: Arc<dyn Texture<T> + Send + Sync>,
+    pub tex2: Arc<dyn Texture<T> + Send + Sync>,
+    pub mapping: Box<TextureMapping3D>,
+}
+
+impl<T: Copy> Checkerboard3DTexture<T> {
+    /// A solid checkerboard of unit cubes, alternating between _tex1_
+    /// and _tex2_ in all three directions (also for negative
+    /// coordinates):
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use pbrt::core::geometry::Point3f;
+    /// use pbrt::core::interaction::SurfaceInteraction;
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::texture::{IdentityMapping3D, Texture, TextureMapping3D};
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::textures::checkerboard::Checkerboard3DTexture;
+    /// use pbrt::textures::constant::ConstantTexture;
+    ///
+    /// let mapping = Box::new(TextureMapping3D::Identity(IdentityMapping3D::new(
+    ///     Transform::default(),
+    /// )));
+    /// let checks: Checkerboard3DTexture<Float> = Checkerboard3DTexture::new(
+    ///     mapping,
+    ///     Arc::new(ConstantTexture::new(1.0 as Float)),
+    ///     Arc::new(ConstantTexture::new(0.0 as Float)),
+    /// );
+    /// let at = |x: Float, y: Float, z: Float| -> Float {
+    ///     let mut si: SurfaceInteraction = SurfaceInteraction::default();
+    ///     si.p = Point3f { x, y, z };
+    ///     checks.evaluate(&si)
+    /// };
+    /// assert_eq!(at(0.5, 0.5, 0.5), 1.0);
+    /// // a step along any axis changes the check
+    /// assert_eq!(at(1.5, 0.5, 0.5), 0.0);
+    /// assert_eq!(at(0.5, 1.5, 0.5), 0.0);
+    /// assert_eq!(at(0.5, 0.5, 1.5), 0.0);
+    /// assert_eq!(at(1.5, 1.5, 0.5), 1.0);
+    /// assert_eq!(at(-0.5, 0.5, 0.5), 0.0);
+    /// assert_eq!(at(-0.5, -0.5, -0.5), 0.0);
+    /// assert_eq!(at(-1.5, -0.5, -0.5), 1.0);
+    /// ```
+    pub fn new(
+        mapping: Box<TextureMapping3D>,
+        tex1: Arc<dyn Texture<T> + Send + Sync>,
+        tex2: Arc<dyn Texture<T> + Send + Sync>,
+    ) -> Self {
+        Checkerboard3DTexture {
+            tex1,
+            tex2,
+            mapping,
+        }
+    }
+}
+
+impl<T: Copy> Texture<T> for Checkerboard3DTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        let mut dpdx: Vector3f = Vector3f::default();
+        let mut dpdy: Vector3f = Vector3f::default();
+        let p: Point3f = self.mapping.map(si, &mut dpdx, &mut dpdy);
+        if (p.x.floor() as i32 + p.y.floor() as i32 + p.z.floor() as i32) % 2 == 0 {
             self.tex1.evaluate(si)
         } else {
             self.tex2.evaluate(si)
@@ -1,9 +1,27 @@
 // pbrt
 use crate::core::interaction::SurfaceInteraction;
+use crate::core::pbrt::{Float, Spectrum};
 use crate::core::texture::Texture;
 
 // see constant.h
 
+/// A texture returning the same value everywhere. Useful to build
+/// materials directly, without going through a **ParamSet**:
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use pbrt::core::pbrt::{Float, Spectrum};
+/// use pbrt::materials::metal::MetalMaterial;
+/// use pbrt::textures::constant::ConstantTexture;
+///
+/// fn main() {
+///     let eta = Arc::new(ConstantTexture::from(Spectrum::new(0.2)));
+///     let k = Arc::new(ConstantTexture::from(Spectrum::new(3.9)));
+///     let roughness = Arc::new(ConstantTexture::from(0.01 as Float));
+///     let metal = MetalMaterial::new(eta, k, roughness, None, None, None, true);
+///     assert!(metal.remap_roughness);
+/// }
+/// ```
 pub struct ConstantTexture<T> {
     pub value: T,
 }
@@ -14,6 +32,18 @@ impl<T: Copy> ConstantTexture<T> {
     }
 }
 
+impl From<Float> for ConstantTexture<Float> {
+    fn from(value: Float) -> Self {
+        ConstantTexture::new(value)
+    }
+}
+
+impl From<Spectrum> for ConstantTexture<Spectrum> {
+    fn from(value: Spectrum) -> Self {
+        ConstantTexture::new(value)
+    }
+}
+
 impl<T: Copy> Texture<T> for ConstantTexture<T> {
     fn evaluate(&self, _si: &SurfaceInteraction) -> T {
         self.value
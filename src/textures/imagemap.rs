@@ -9,14 +9,91 @@ use num;
 use crate::core::geometry::{Point2f, Point2i, Vector2f};
 use crate::core::interaction::SurfaceInteraction;
 use crate::core::mipmap::{Clampable, ImageWrap, MipMap};
+use crate::core::paramset::TextureParams;
 use crate::core::pbrt::{Float, Spectrum};
+use crate::core::spectrum::{inverse_gamma_convert_float, RGBSpectrum};
+use crate::core::stats::{Prof, ProfilePhase};
 use crate::core::texture::{Texture, TextureMapping2D};
 
 // see imagemap.h
 
+/// How the (8-bit) values stored in an image file map to linear
+/// values.
+///
+/// ```rust
+/// use pbrt::textures::imagemap::ImageEncoding;
+///
+/// fn main() {
+///     // mid-gray sRGB texel
+///     let linear = ImageEncoding::Srgb.to_linear(0.5);
+///     assert!((linear - 0.214).abs() < 1e-3);
+///     assert_eq!(ImageEncoding::Linear.to_linear(0.5), 0.5);
+///     assert_eq!(ImageEncoding::Gamma(2.0).to_linear(0.5), 0.25);
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ImageEncoding {
+    Srgb,
+    Linear,
+    Gamma(Float),
+}
+
+impl ImageEncoding {
+    /// Parses "sRGB", "linear", or "gamma <value>".
+    pub fn parse(name: &str) -> Option<ImageEncoding> {
+        let name: String = name.trim().to_lowercase();
+        if name == "srgb" {
+            Some(ImageEncoding::Srgb)
+        } else if name == "linear" {
+            Some(ImageEncoding::Linear)
+        } else if name.starts_with("gamma ") {
+            match name["gamma ".len()..].trim().parse::<Float>() {
+                Ok(gamma) if gamma > 0.0 as Float => Some(ImageEncoding::Gamma(gamma)),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+    /// Reads the "encoding" parameter. Without it, the older "gamma"
+    /// flag selects between sRGB and linear, and if that is missing as
+    /// well, _default_ is used.
+    pub fn create(tp: &mut TextureParams, default: ImageEncoding) -> ImageEncoding {
+        let default: ImageEncoding = if tp.find_bool("gamma", default == ImageEncoding::Srgb) {
+            ImageEncoding::Srgb
+        } else {
+            ImageEncoding::Linear
+        };
+        let name: String = tp.find_string("encoding", String::new());
+        if name.is_empty() {
+            return default;
+        }
+        match ImageEncoding::parse(&name) {
+            Some(encoding) => encoding,
+            None => {
+                println!(
+                    "WARNING: Image encoding \"{}\" unknown. Using {:?}.",
+                    name, default
+                );
+                default
+            }
+        }
+    }
+    pub fn to_linear(&self, v: Float) -> Float {
+        match self {
+            ImageEncoding::Srgb => inverse_gamma_convert_float(v),
+            ImageEncoding::Linear => v,
+            ImageEncoding::Gamma(gamma) => v.max(0.0 as Float).powf(*gamma),
+        }
+    }
+}
+
 pub struct ImageTexture<T> {
     pub mapping: Box<TextureMapping2D>,
     pub mipmap: Arc<MipMap<T>>,
+    /// Encoding of the texels in the image file (they are stored as
+    /// linear values in the _mipmap_)
+    pub encoding: ImageEncoding,
 }
 
 impl<T> ImageTexture<T>
@@ -39,9 +116,10 @@ where
         max_aniso: Float,
         wrap_mode: ImageWrap,
         scale: Float,
-        gamma: bool,
+        encoding: ImageEncoding,
         convert: F,
     ) -> ImageTexture<T> {
+        let _phase = ProfilePhase::new(Prof::TextureLoading);
         let path = Path::new(&filename);
         let img_result: ImageResult<DynamicImage> = image::open(path);
         if !img_result.is_ok() {
@@ -53,13 +131,13 @@ where
             x: rgb.width() as i32,
             y: rgb.height() as i32,
         };
-        let mut texels: Vec<Spectrum> = rgb
+        let mut texels: Vec<RGBSpectrum> = rgb
             .pixels()
             .map(|p| {
                 let r = Float::from(p[0]) / 255.0;
                 let g = Float::from(p[1]) / 255.0;
                 let b = Float::from(p[2]) / 255.0;
-                Spectrum::rgb(r, g, b)
+                RGBSpectrum::rgb(r, g, b)
             })
             .collect();
         // flip image in y; texture coordinate space has (0,0) at the
@@ -75,11 +153,11 @@ where
         let converted_texels: Vec<T> = texels
             .iter()
             .map(|p| {
-                let s = if gamma {
-                    p.inverse_gamma_correct() * scale
-                } else {
-                    *p * scale
-                };
+                let s = Spectrum::rgb(
+                    encoding.to_linear(p.c[0]),
+                    encoding.to_linear(p.c[1]),
+                    encoding.to_linear(p.c[2]),
+                ) * scale;
                 convert(&s)
             })
             .collect();
@@ -91,7 +169,11 @@ where
             max_aniso,
             wrap_mode,
         ));
-        ImageTexture { mapping, mipmap }
+        ImageTexture {
+            mapping,
+            mipmap,
+            encoding,
+        }
     }
 }
 
@@ -156,3 +238,53 @@ pub fn convert_to_spectrum(from: &Spectrum) -> Spectrum {
 pub fn convert_to_float(from: &Spectrum) -> Float {
     from.y()
 }
+
+/// Picks the conversion for the "channel" parameter of float image
+/// textures: "y" (luminance, the default) or a single channel "r",
+/// "g" or "b", e.g. for roughness and metalness packed into one image.
+///
+/// ```rust
+/// use pbrt::core::pbrt::{Float, Spectrum};
+/// use pbrt::textures::imagemap::channel_to_float;
+///
+/// fn main() {
+///     let texel = Spectrum::rgb(0.25, 0.5, 0.75);
+///     let mut rgb: [Float; 3] = [0.0; 3];
+///     texel.to_rgb(&mut rgb);
+///     assert_eq!(channel_to_float("g").unwrap()(&texel), rgb[1]);
+///     assert_eq!(channel_to_float("b").unwrap()(&texel), rgb[2]);
+///     assert_eq!(channel_to_float("y").unwrap()(&texel), texel.y());
+///     // with sampled spectra, RGB only approximately survives the round
+///     // trip
+///     assert!((channel_to_float("g").unwrap()(&texel) - 0.5).abs() < 0.1);
+///     assert!((channel_to_float("b").unwrap()(&texel) - 0.75).abs() < 0.1);
+///     assert!(channel_to_float("a").is_none());
+/// }
+/// ```
+pub fn channel_to_float(channel: &str) -> Option<fn(&Spectrum) -> Float> {
+    match channel {
+        "y" => Some(convert_to_float),
+        "r" => Some(convert_red_to_float),
+        "g" => Some(convert_green_to_float),
+        "b" => Some(convert_blue_to_float),
+        _ => None,
+    }
+}
+
+fn convert_channel_to_float(from: &Spectrum, channel: usize) -> Float {
+    let mut rgb: [Float; 3] = [0.0 as Float; 3];
+    from.to_rgb(&mut rgb);
+    rgb[channel]
+}
+
+fn convert_red_to_float(from: &Spectrum) -> Float {
+    convert_channel_to_float(from, 0)
+}
+
+fn convert_green_to_float(from: &Spectrum) -> Float {
+    convert_channel_to_float(from, 1)
+}
+
+fn convert_blue_to_float(from: &Spectrum) -> Float {
+    convert_channel_to_float(from, 2)
+}
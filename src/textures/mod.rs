@@ -6,6 +6,7 @@
 //!
 //! - BilerpTexture
 //! - Checkerboard2DTexture
+//! - Checkerboard3DTexture
 //! - ConstantTexture
 //! - DotsTexture
 //! - FBmTexture
@@ -14,6 +15,7 @@
 //! - MixTexture
 //! - PtexTexture
 //! - ScaleTexture
+//! - ScaleTextureSF
 //! - UVTexture
 //! - WindyTexture
 //! - WrinkledTexture
@@ -3,6 +3,7 @@ use std::ops::Mul;
 use std::sync::Arc;
 // pbrt
 use crate::core::interaction::SurfaceInteraction;
+use crate::core::pbrt::{Float, Spectrum};
 use crate::core::texture::Texture;
 
 pub struct ScaleTexture<T> {
@@ -27,3 +28,25 @@ where
         self.tex1.evaluate(si) * self.tex2.evaluate(si)
     }
 }
+
+/// Scales a spectrum texture by a float texture, e.g. to tint a
+/// grayscale mask.
+pub struct ScaleTextureSF {
+    pub tex1: Arc<dyn Texture<Spectrum> + Send + Sync>,
+    pub tex2: Arc<dyn Texture<Float> + Send + Sync>,
+}
+
+impl ScaleTextureSF {
+    pub fn new(
+        tex1: Arc<dyn Texture<Spectrum> + Send + Sync>,
+        tex2: Arc<dyn Texture<Float> + Send + Sync>,
+    ) -> Self {
+        ScaleTextureSF { tex1, tex2 }
+    }
+}
+
+impl Texture<Spectrum> for ScaleTextureSF {
+    fn evaluate(&self, si: &SurfaceInteraction) -> Spectrum {
+        self.tex1.evaluate(si) * self.tex2.evaluate(si)
+    }
+}
@@ -1,19 +1,66 @@
 // pbrt
 use crate::core::geometry::{Point3f, Vector3f};
 use crate::core::interaction::SurfaceInteraction;
+use crate::core::paramset::TextureParams;
 use crate::core::pbrt::Float;
 use crate::core::texture::fbm;
-use crate::core::texture::{Texture, TextureMapping3D};
+use crate::core::texture::{IdentityMapping3D, Texture, TextureMapping3D};
+use crate::core::transform::Transform;
 
 // see windy.h
 
 pub struct WindyTexture {
     pub mapping: Box<TextureMapping3D>,
+    pub octaves: i32, // default: 6
+    pub omega: Float, // default: 0.5
 }
 
 impl WindyTexture {
-    pub fn new(mapping: Box<TextureMapping3D>) -> Self {
-        WindyTexture { mapping }
+    pub fn new(mapping: Box<TextureMapping3D>, octaves: i32, omega: Float) -> Self {
+        WindyTexture {
+            mapping,
+            octaves,
+            omega,
+        }
+    }
+    /// The number of _octaves_ and the _roughness_ apply to the wave
+    /// height, the wind strength always uses three octaves.
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::Point3f;
+    /// use pbrt::core::interaction::SurfaceInteraction;
+    /// use pbrt::core::paramset::{ParamSet, TextureParams};
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::texture::Texture;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::textures::windy::WindyTexture;
+    ///
+    /// let mut params: ParamSet = ParamSet::default();
+    /// params.add_int(String::from("octaves"), 0);
+    /// let mut tp: TextureParams = TextureParams {
+    ///     geom_params: params,
+    ///     ..Default::default()
+    /// };
+    /// let texture: WindyTexture = WindyTexture::create(&Transform::default(), &mut tp);
+    /// assert_eq!(texture.octaves, 0);
+    /// let at = |x: Float, y: Float, z: Float| -> Float {
+    ///     let mut si: SurfaceInteraction = SurfaceInteraction::default();
+    ///     si.p = Point3f { x, y, z };
+    ///     texture.evaluate(&si)
+    /// };
+    /// // without octaves there is no wave, also away from
+    /// // the origin, so it's safe as a bump map
+    /// assert_eq!(at(0.0, 0.0, 0.0), 0.0);
+    /// assert_eq!(at(0.0, 0.0, 0.0), at(0.0, 0.0, 0.0));
+    /// assert_eq!(at(0.3, -1.7, 2.2), 0.0);
+    /// ```
+    pub fn create(tex_2_world: &Transform, tp: &mut TextureParams) -> Self {
+        let map: Box<TextureMapping3D> = Box::new(TextureMapping3D::Identity(
+            IdentityMapping3D::new(*tex_2_world),
+        ));
+        let octaves: i32 = tp.find_int("octaves", 6_i32).max(0_i32);
+        let roughness: Float = tp.find_float("roughness", 0.5 as Float);
+        WindyTexture::new(map, octaves, roughness)
     }
 }
 
@@ -32,7 +79,7 @@ where
             0.5 as Float,
             3_i32,
         );
-        let wave_height: Float = fbm(&p, &dpdx, &dpdy, 0.5 as Float, 6_i32);
+        let wave_height: Float = fbm(&p, &dpdx, &dpdy, self.omega, self.octaves);
         T::from(wind_strength.abs() * wave_height)
     }
 }
@@ -1,9 +1,11 @@
 // pbrt
 use crate::core::geometry::{Point3f, Vector3f};
 use crate::core::interaction::SurfaceInteraction;
+use crate::core::paramset::TextureParams;
 use crate::core::pbrt::Float;
 use crate::core::texture::turbulence;
-use crate::core::texture::{Texture, TextureMapping3D};
+use crate::core::texture::{IdentityMapping3D, Texture, TextureMapping3D};
+use crate::core::transform::Transform;
 
 // see wrinkled.h
 
@@ -25,6 +27,45 @@ impl WrinkledTexture {
             octaves,
         }
     }
+    /// Reads the number of _octaves_ and the _roughness_ of the
+    /// turbulence.
+    ///
+    /// ```rust
+    /// use pbrt::core::geometry::Point3f;
+    /// use pbrt::core::interaction::SurfaceInteraction;
+    /// use pbrt::core::paramset::{ParamSet, TextureParams};
+    /// use pbrt::core::pbrt::Float;
+    /// use pbrt::core::texture::Texture;
+    /// use pbrt::core::transform::Transform;
+    /// use pbrt::textures::wrinkled::WrinkledTexture;
+    ///
+    /// let mut params: ParamSet = ParamSet::default();
+    /// params.add_int(String::from("octaves"), 0);
+    /// let mut tp: TextureParams = TextureParams {
+    ///     geom_params: params,
+    ///     ..Default::default()
+    /// };
+    /// let texture: WrinkledTexture = WrinkledTexture::create(&Transform::default(), &mut tp);
+    /// assert_eq!(texture.octaves, 0);
+    /// let at = |x: Float, y: Float, z: Float| -> Float {
+    ///     let mut si: SurfaceInteraction = SurfaceInteraction::default();
+    ///     si.p = Point3f { x, y, z };
+    ///     texture.evaluate(&si)
+    /// };
+    /// // without octaves only the average turbulence of a clamped
+    /// // octave remains, a constant which is safe as a bump map
+    /// assert_eq!(at(0.0, 0.0, 0.0), 0.2);
+    /// assert_eq!(at(0.0, 0.0, 0.0), at(0.0, 0.0, 0.0));
+    /// assert_eq!(at(0.3, -1.7, 2.2), 0.2);
+    /// ```
+    pub fn create(tex_2_world: &Transform, tp: &mut TextureParams) -> Self {
+        let map: Box<TextureMapping3D> = Box::new(TextureMapping3D::Identity(
+            IdentityMapping3D::new(*tex_2_world),
+        ));
+        let octaves: i32 = tp.find_int("octaves", 8_i32).max(0_i32);
+        let roughness: Float = tp.find_float("roughness", 0.5 as Float);
+        WrinkledTexture::new(map, octaves, roughness)
+    }
 }
 
 impl<T> Texture<T> for WrinkledTexture
@@ -0,0 +1,90 @@
+//! Renders a floor lit by a spherical area light with adaptive
+//! sampling: the empty sky converges after the first pass, the noisy
+//! floor takes more samples.
+
+use std::path::PathBuf;
+
+mod common;
+
+use common::{build, glow, params, perspective, quad, sampler, sphere};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::geometry::Point2i;
+
+const RESOLUTION: i32 = 16;
+
+/// Renders the scene into _image_ and returns the number of samples
+/// the film recorded for each pixel.
+fn render(image: &PathBuf, adaptive: bool) -> Vec<u32> {
+    let mut film = params("image");
+    film.add_int(String::from("xresolution"), RESOLUTION);
+    film.add_int(String::from("yresolution"), RESOLUTION);
+    film.add_string(
+        String::from("filename"),
+        image.to_str().unwrap().to_string(),
+    );
+    if adaptive {
+        film.add_int(String::from("minsamples"), 4);
+        film.add_int(String::from("maxsamples"), 64);
+        film.add_float(String::from("errorthreshold"), 0.01);
+    }
+    film.add_bool(String::from("sampleheatmap"), true);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 1.0, -4.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0)
+        .camera(perspective(60.0))
+        .film(film)
+        .sampler(sampler("random", 64))
+        .integrator(params("path"))
+        .world_begin()
+        .attribute_begin()
+        .translate(0.0, 2.0, 0.0)
+        .area_light_source(glow(4.0, false))
+        .shape(sphere(0.5))
+        .attribute_end()
+        .material(params("matte"))
+        .shape(quad([
+            -10.0, 0.0, -10.0, 10.0, 0.0, -10.0, 10.0, 0.0, 10.0, -10.0, 0.0, 10.0,
+        ]));
+    let (mut integrator, scene) = build(builder);
+    integrator.render(&scene, 1);
+    let film = integrator.get_camera().get_film();
+    let mut counts: Vec<u32> = Vec::new();
+    for y in 0..RESOLUTION {
+        for x in 0..RESOLUTION {
+            counts.push(film.get_pixel_sample_count(&Point2i { x, y }));
+        }
+    }
+    counts
+}
+
+#[test]
+fn adaptive_sampling_writes_heatmap_named_after_image() {
+    let dir: PathBuf = std::env::temp_dir().join("pbrt_adaptive_sampling_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let image: PathBuf = dir.join("floor.png");
+    let counts: Vec<u32> = render(&image, true);
+    // the sky (top row) converges after the first pass of
+    // _minsamples_, the floor (bottom row) is noisy
+    assert!(counts[0..RESOLUTION as usize].iter().all(|n| *n == 4));
+    let bottom: usize = ((RESOLUTION - 1) * RESOLUTION) as usize;
+    assert!(counts[bottom..].iter().any(|n| *n > 4));
+    assert!(counts.iter().all(|n| *n <= 64));
+    let heatmap = image::open(dir.join("floor_spp.png")).unwrap().to_luma();
+    assert_eq!(heatmap.dimensions(), (RESOLUTION as u32, RESOLUTION as u32));
+    // 4 of 64 samples
+    assert_eq!(heatmap.get_pixel(0, 0)[0], 16);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn non_adaptive_film_keeps_no_sample_statistics() {
+    let dir: PathBuf = std::env::temp_dir().join("pbrt_non_adaptive_sampling_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let image: PathBuf = dir.join("floor.png");
+    let counts: Vec<u32> = render(&image, false);
+    assert!(counts.iter().all(|n| *n == 0));
+    // the heatmap is only written for adaptive sampling
+    assert!(!dir.join("floor_spp.png").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,52 @@
+//! Renders ambient occlusion with a sample count which isn't a power
+//! of two: samplers which need powers of two round it up, and the
+//! integrator requests and uses arrays of the rounded size.
+
+mod common;
+
+use common::{build, film, floor, params, sampler};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::integrator::{Integrator, SamplerIntegrator};
+use pbrt::core::pbrt::{Float, Spectrum};
+
+/// Renders an unoccluded floor with _sampler_ and 13 AO samples.
+/// Returns the number of samples the integrator took and the image.
+fn render(sampler_name: &str) -> (i32, Vec<Float>) {
+    let (film, path) = film(&format!("pbrt_ao_samples_{}.png", sampler_name), 4, 4);
+    let mut ao = params("ambientocclusion");
+    ao.add_int(String::from("nsamples"), 13);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(sampler(sampler_name, 4))
+        .integrator(ao)
+        .world_begin()
+        .shape(floor(0.0, 10.0));
+    let (mut integrator, scene) = build(builder);
+    let n_samples: i32 = match *integrator {
+        Integrator::Sampler(SamplerIntegrator::AO(ref ao)) => ao.n_samples,
+        _ => unreachable!(),
+    };
+    integrator.render(&scene, 1);
+    let rgb: Vec<Float> = integrator.get_camera().get_film().get_rgb(1.0);
+    std::fs::remove_file(&path).unwrap();
+    (n_samples, rgb)
+}
+
+#[test]
+fn ao_sample_count_is_rounded() {
+    // with cosine weighted samples each unoccluded one contributes pi,
+    // so every pixel is exactly that if all samples were used
+    let mut expected_rgb: [Float; 3] = [0.0; 3];
+    Spectrum::new(std::f32::consts::PI as Float).to_rgb(&mut expected_rgb);
+    for (sampler, expected) in &[("maxmindist", 16), ("02sequence", 16), ("random", 13)] {
+        let (n_samples, rgb) = render(sampler);
+        assert_eq!(n_samples, *expected, "{}", sampler);
+        for (i, v) in rgb.iter().enumerate() {
+            assert!((v - expected_rgb[i % 3]).abs() < 1e-3, "{}: {}", sampler, v);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! A film with "aovs" enabled writes the albedo, normal and depth of
+//! the first surface seen next to the PNG image (see
+//! **Film::write_aov_images()**), and as additional layers into
+//! OpenEXR files (see **Film::write_exr()**). Pixels without a hit
+//! stay black (normal and depth zero).
+
+use exr::prelude::*;
+
+mod common;
+
+use common::{build, film, matte, params, perspective, quad, sampler, sky};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::film::Aov;
+use pbrt::core::pbrt::{gamma_correct, Float};
+
+/// Maps [0, 1] to 8 bits like the film does.
+fn to_u8(v: Float) -> u8 {
+    (255.0 as Float * v + 0.5) as u8
+}
+
+#[test]
+fn aov_images_next_to_png() {
+    let (mut film, path) = film("pbrt_aov_images.png", 16, 16);
+    film.add_bool(String::from("aovs"), true);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0)
+        .camera(perspective(10.0))
+        .film(film)
+        .sampler(sampler("halton", 4))
+        .integrator(params("path"))
+        .world_begin()
+        .light_source(sky(1.0))
+        .material(matte(0.5))
+        // a wall at distance 2, covering one half of the image
+        .shape(quad([
+            0.0, -10.0, 2.0, 10.0, -10.0, 2.0, 10.0, 10.0, 2.0, 0.0, 10.0, 2.0,
+        ]));
+    let (mut integrator, scene) = build(builder);
+    integrator.render(&scene, 1);
+    let film = integrator.get_camera().get_film();
+    let dir = std::env::temp_dir();
+    let exr_path = dir.join("pbrt_aov_images.exr");
+    film.write_exr(
+        exr_path.to_str().unwrap(),
+        &[Aov::Albedo, Aov::Normal, Aov::Depth],
+    )
+    .unwrap();
+    let mut images = Vec::new();
+    for suffix in &["_albedo", "_normal", "_depth"] {
+        let aov_path = dir.join(format!("pbrt_aov_images{}.png", suffix));
+        images.push(image::open(&aov_path).unwrap());
+        std::fs::remove_file(&aov_path).unwrap();
+    }
+    std::fs::remove_file(&path).unwrap();
+    let albedo = images[0].to_rgb();
+    let normal = images[1].to_rgb();
+    let depth = images[2].to_luma();
+    assert_eq!(albedo.dimensions(), (16, 16));
+    assert_eq!(normal.dimensions(), (16, 16));
+    assert_eq!(depth.dimensions(), (16, 16));
+
+    let gray: u8 = to_u8(gamma_correct(0.5));
+    let mut n_hits: usize = 0;
+    for y in 0..16 {
+        for x in 0..16 {
+            let a = albedo.get_pixel(x, y).0;
+            let n = normal.get_pixel(x, y).0;
+            let d = depth.get_pixel(x, y).0[0];
+            if d == 0 {
+                // missed the wall
+                assert_eq!(a, [0, 0, 0], "albedo of ({}, {})", x, y);
+                assert_eq!(n, [128, 128, 128], "normal of ({}, {})", x, y);
+            } else {
+                n_hits += 1;
+                assert_eq!(a, [gray; 3], "albedo of ({}, {})", x, y);
+                assert_eq!((n[0], n[1]), (128, 128), "normal of ({}, {})", x, y);
+                assert!(n[2] == 0 || n[2] == 255, "normal of ({}, {})", x, y);
+                // all rays are within 8 degrees of the wall's normal
+                assert!(d >= 252, "depth of ({}, {}): {}", x, y, d);
+            }
+        }
+        // the wall covers whole columns
+        let row_hits: Vec<bool> = (0..16).map(|x| depth.get_pixel(x, y).0[0] > 0).collect();
+        assert_eq!(row_hits[0], !row_hits[15]);
+        assert_eq!(row_hits.iter().filter(|hit| **hit).count(), 8);
+    }
+    assert_eq!(n_hits, 8 * 16);
+
+    // the same AOVs as EXR layers, with linear values
+    let image = read_first_flat_layer_from_file(&exr_path).unwrap();
+    std::fs::remove_file(&exr_path).unwrap();
+    let channels = &image.layer_data.channel_data.list;
+    let channel = |name: &str| -> Vec<Float> {
+        channels
+            .iter()
+            .find(|channel| channel.name.to_string() == name)
+            .unwrap_or_else(|| panic!("no channel {:?}", name))
+            .sample_data
+            .values_as_f32()
+            .collect()
+    };
+    let albedo_g: Vec<Float> = channel("albedo.G");
+    let normal_z: Vec<Float> = channel("N.Z");
+    let z: Vec<Float> = channel("Z");
+    assert_eq!(z.len(), 16 * 16);
+    for i in 0..z.len() {
+        if z[i] == 0.0 {
+            assert_eq!((albedo_g[i], normal_z[i]), (0.0, 0.0));
+        } else {
+            assert!((albedo_g[i] - 0.5).abs() < 1e-4, "{}", albedo_g[i]);
+            assert!((normal_z[i].abs() - 1.0).abs() < 1e-4, "{}", normal_z[i]);
+            assert!(z[i] >= 2.0 && z[i] < 2.04, "{}", z[i]);
+        }
+    }
+}
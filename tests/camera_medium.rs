@@ -0,0 +1,51 @@
+//! A camera inside an absorbing medium (selected by
+//! **MediumInterface** before **Camera**) sees an emitter at distance
+//! _d_ attenuated by exp(-sigma_a d).
+
+mod common;
+
+use common::{film, glow, params, perspective, quad, render, sampler};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::pbrt::{Float, Spectrum};
+
+/// Renders an emitter 2 units in front of the camera, which is in the
+/// medium _camera_medium_ (empty for none). Returns the mean of the
+/// green channel.
+fn render_mean(camera_medium: &str) -> Float {
+    let (film, path) = film(&format!("pbrt_camera_medium_{}.png", camera_medium), 16, 16);
+    let mut fog = params("fog");
+    fog.add_string(String::from("type"), String::from("homogeneous"));
+    fog.add_rgb(String::from("sigma_a"), [0.5; 3]);
+    fog.add_rgb(String::from("sigma_s"), [0.0; 3]);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .make_named_medium(fog)
+        .look_at(0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0)
+        .medium_interface(camera_medium, camera_medium)
+        .camera(perspective(10.0))
+        .film(film)
+        .sampler(sampler("halton", 16))
+        .integrator(params("volpath"))
+        .world_begin()
+        .area_light_source(glow(1.0, true))
+        .shape(quad([
+            -10.0, -10.0, 2.0, 10.0, -10.0, 2.0, 10.0, 10.0, 2.0, -10.0, 10.0, 2.0,
+        ]));
+    let rgb: Vec<Float> = render(builder, &path);
+    let green: Vec<Float> = rgb.chunks(3).map(|pixel| pixel[1]).collect();
+    green.iter().sum::<Float>() / green.len() as Float
+}
+
+#[test]
+fn camera_ray_starts_in_camera_medium() {
+    let mut white: [Float; 3] = [0.0; 3];
+    Spectrum::new(1.0).to_rgb(&mut white);
+    let clear: Float = render_mean("");
+    assert!((clear - white[1]).abs() < 1e-3, "{}", clear);
+    // the camera rays are within 5 degrees of the axis, so they travel
+    // at most 2.01 through the medium
+    let foggy: Float = render_mean("fog");
+    let expected: Float = white[1] * (-0.5 as Float * 2.0).exp();
+    assert!((foggy - expected).abs() < 0.03, "{} != {}", foggy, expected);
+}
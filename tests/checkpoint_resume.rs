@@ -0,0 +1,62 @@
+//! A render resumed from a checkpoint ends with the same image as an
+//! uninterrupted one.
+
+mod common;
+
+use common::{build, film, params, sampler, sky};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::integrator::{render_with_checkpoints, Integrator, RenderCheckpoint};
+use pbrt::core::pbrt::Float;
+use pbrt::core::scene::Scene;
+
+/// A sphere under a white sky, rendered with _samples_ samples per
+/// pixel of the sobol sampler.
+fn setup(name: &str, samples: i32) -> (Box<Integrator>, Scene, std::path::PathBuf) {
+    let (film, path) = film(&format!("pbrt_checkpoint_resume_{}.png", name), 16, 16);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 2.0, -4.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(sampler("sobol", samples))
+        .integrator(params("path"))
+        .world_begin()
+        .light_source(sky(1.0))
+        .shape(params("sphere"));
+    let (integrator, scene) = build(builder);
+    (integrator, scene, path)
+}
+
+#[test]
+fn resumed_render_matches_uninterrupted_one() {
+    let (mut integrator, scene, path) = setup("uninterrupted", 8);
+    integrator.render(&scene, 1);
+    std::fs::remove_file(&path).unwrap();
+    let expected: Vec<Float> = integrator.get_camera().get_film().get_rgb(1.0);
+
+    // "interrupt" the render after 3 of 8 passes by only asking for 3
+    // samples per pixel, the passes taken so far are the same
+    let checkpoint_path = std::env::temp_dir().join("pbrt_checkpoint_resume.ckpt");
+    let mut checkpoint = RenderCheckpoint {
+        filename: checkpoint_path.to_str().unwrap().to_string(),
+        interval: 0.0,
+        scene_hash: 42,
+        resume: false,
+    };
+    let (mut integrator, scene, path) = setup("interrupted", 3);
+    render_with_checkpoints(&scene, &mut integrator, 1, &checkpoint);
+    std::fs::remove_file(&path).unwrap();
+    let interrupted: Vec<Float> = integrator.get_camera().get_film().get_rgb(1.0);
+    assert_ne!(interrupted, expected);
+
+    checkpoint.resume = true;
+    let (mut integrator, scene, path) = setup("resumed", 8);
+    render_with_checkpoints(&scene, &mut integrator, 1, &checkpoint);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&checkpoint_path).unwrap();
+    let resumed: Vec<Float> = integrator.get_camera().get_film().get_rgb(1.0);
+    for (v, e) in resumed.iter().zip(expected.iter()) {
+        assert!((v - e).abs() < 1e-5, "{} != {}", v, e);
+    }
+}
@@ -0,0 +1,120 @@
+//! Scene setup shared by the integration tests. The scenes are built
+//! with **SceneBuilder**, these helpers make the parameter lists most
+//! tests need and render the result.
+
+// not every test uses every helper
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::integrator::Integrator;
+use pbrt::core::paramset::ParamSet;
+use pbrt::core::pbrt::Float;
+use pbrt::core::scene::Scene;
+
+/// An empty parameter list for the type _name_ (e.g. "matte").
+pub fn params(name: &str) -> ParamSet {
+    let mut params = ParamSet::default();
+    params.name = String::from(name);
+    params
+}
+
+/// An "image" film of _xres_ x _yres_ pixels, written to the file
+/// _filename_ in the temporary directory. Returns the film and the
+/// path of the file.
+pub fn film(filename: &str, xres: i32, yres: i32) -> (ParamSet, PathBuf) {
+    let path: PathBuf = std::env::temp_dir().join(filename);
+    let mut film = params("image");
+    film.add_int(String::from("xresolution"), xres);
+    film.add_int(String::from("yresolution"), yres);
+    film.add_string(String::from("filename"), path.to_str().unwrap().to_string());
+    (film, path)
+}
+
+/// The sampler _name_ taking _samples_ samples per pixel.
+pub fn sampler(name: &str, samples: i32) -> ParamSet {
+    let mut sampler = params(name);
+    sampler.add_int(String::from("pixelsamples"), samples);
+    sampler
+}
+
+/// A perspective camera with a field of view of _fov_ degrees.
+pub fn perspective(fov: Float) -> ParamSet {
+    let mut camera = params("perspective");
+    camera.add_float(String::from("fov"), fov);
+    camera
+}
+
+/// The integrator _name_ with a maximum path length of _max_depth_.
+pub fn integrator(name: &str, max_depth: i32) -> ParamSet {
+    let mut integrator = params(name);
+    integrator.add_int(String::from("maxdepth"), max_depth);
+    integrator
+}
+
+/// A white sky, an infinite light of radiance _l_.
+pub fn sky(l: Float) -> ParamSet {
+    let mut sky = params("infinite");
+    sky.add_rgb(String::from("L"), [l; 3]);
+    sky
+}
+
+/// A diffuse area light emitting _l_ (on both sides if _two_sided_).
+pub fn glow(l: Float, two_sided: bool) -> ParamSet {
+    let mut glow = params("diffuse");
+    glow.add_rgb(String::from("L"), [l; 3]);
+    if two_sided {
+        glow.add_bool(String::from("twosided"), true);
+    }
+    glow
+}
+
+/// A matte material with the gray reflectance _kd_.
+pub fn matte(kd: Float) -> ParamSet {
+    let mut matte = params("matte");
+    matte.add_rgb(String::from("Kd"), [kd; 3]);
+    matte
+}
+
+/// A sphere of the given _radius_.
+pub fn sphere(radius: Float) -> ParamSet {
+    let mut sphere = params("sphere");
+    sphere.add_float(String::from("radius"), radius);
+    sphere
+}
+
+/// A triangle mesh of the two triangles (0, 1, 2) and (0, 2, 3) of the
+/// corners _p_.
+pub fn quad(p: [Float; 12]) -> ParamSet {
+    let mut quad = params("trianglemesh");
+    quad.add_ints(String::from("indices"), vec![0, 1, 2, 0, 2, 3]);
+    quad.add_point3fs(String::from("P"), p.to_vec());
+    quad
+}
+
+/// A square floor from -_half_size_ to _half_size_ (in x and z) at
+/// height _y_.
+pub fn floor(y: Float, half_size: Float) -> ParamSet {
+    let (a, b): (Float, Float) = (-half_size, half_size);
+    quad([a, y, a, a, y, b, b, y, b, b, y, a])
+}
+
+/// Makes the integrator (with camera, film and sampler) and the scene.
+pub fn build(builder: SceneBuilder) -> (Box<Integrator>, Scene) {
+    let search_directory = builder.api_state.search_directory.clone();
+    let (scene, render_options) = builder.build();
+    let integrator: Box<Integrator> = render_options
+        .make_integrator(search_directory.as_ref())
+        .unwrap();
+    (integrator, scene)
+}
+
+/// Renders the scene with one thread and returns the RGB values of the
+/// film. The image written to _path_ gets removed.
+pub fn render(builder: SceneBuilder, path: &Path) -> Vec<Float> {
+    let (mut integrator, scene) = build(builder);
+    integrator.render(&scene, 1);
+    std::fs::remove_file(path).unwrap();
+    integrator.get_camera().get_film().get_rgb(1.0)
+}
@@ -0,0 +1,35 @@
+//! An infinite light without an environment map is a
+//! **ConstantInfiniteLight**: rays escaping an otherwise empty scene
+//! see just its radiance.
+
+mod common;
+
+use common::{build, film, params, sampler};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::pbrt::Float;
+
+#[test]
+fn empty_scene_shows_radiance() {
+    let (film, path) = film("pbrt_constant_infinite.exr", 8, 8);
+    let mut light = params("infinite");
+    light.add_rgb(String::from("L"), [0.2, 0.4, 0.6]);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .film(film)
+        .sampler(sampler("random", 4))
+        .integrator(params("path"))
+        .world_begin()
+        .light_source(light);
+    let (mut integrator, scene) = build(builder);
+    assert_eq!(scene.infinite_lights.len(), 1);
+    integrator.render(&scene, 1);
+    std::fs::remove_file(&path).unwrap();
+    let rgb: Vec<Float> = integrator.get_camera().get_film().get_rgb(1.0);
+    assert_eq!(rgb.len(), 3 * 8 * 8);
+    for pixel in rgb.chunks(3) {
+        assert!((pixel[0] - 0.2).abs() < 1e-3);
+        assert!((pixel[1] - 0.4).abs() < 1e-3);
+        assert!((pixel[2] - 0.6).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,99 @@
+//! Renders a small region of an image through a crop window and
+//! compares it with the same region of a full render.
+
+mod common;
+
+use common::{film, params, perspective, quad, render, sampler, sphere};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::geometry::Point3f;
+use pbrt::core::pbrt::Float;
+
+const RESOLUTION: i32 = 256;
+
+/// A matte sphere on a floor, lit by a point light, rendered with the
+/// given crop window. Returns the RGB values of the cropped image.
+fn render_cropped(crop_window: Option<[Float; 4]>) -> Vec<Float> {
+    let (mut film, path) = film(
+        &format!(
+            "pbrt_crop_window_{}.png",
+            if crop_window.is_some() {
+                "crop"
+            } else {
+                "full"
+            }
+        ),
+        RESOLUTION,
+        RESOLUTION,
+    );
+    if let Some(crop_window) = crop_window {
+        film.add_floats(String::from("cropwindow"), crop_window.to_vec());
+    }
+    let mut light = params("point");
+    light.add_point3f(
+        String::from("from"),
+        Point3f {
+            x: 2.0,
+            y: 4.0,
+            z: -2.0,
+        },
+    );
+    light.add_rgb(String::from("I"), [20.0, 20.0, 20.0]);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 1.0, -4.0, 0.0, 0.5, 0.0, 0.0, 1.0, 0.0)
+        .camera(perspective(40.0))
+        .film(film)
+        // the filter reaches two pixels into the neighbors of the region
+        .pixel_filter(params("gaussian"))
+        // Halton samples only depend on the pixel, not on the crop window
+        .sampler(sampler("halton", 4))
+        .integrator(params("directlighting"))
+        .world_begin()
+        .light_source(light)
+        .material(params("matte"))
+        .shape(sphere(0.5))
+        .shape(quad([
+            -10.0, -0.5, -10.0, 10.0, -0.5, -10.0, 10.0, -0.5, 10.0, -10.0, -0.5, 10.0,
+        ]));
+    render(builder, &path)
+}
+
+#[test]
+fn cropped_render_matches_full_render() {
+    // pixels [112, 144) x [128, 160), on the silhouette of the sphere
+    let (x0, y0, size): (usize, usize, usize) = (112, 128, 32);
+    let crop_window: [Float; 4] = [
+        x0 as Float / RESOLUTION as Float,
+        (x0 + size) as Float / RESOLUTION as Float,
+        y0 as Float / RESOLUTION as Float,
+        (y0 + size) as Float / RESOLUTION as Float,
+    ];
+    let crop: Vec<Float> = render_cropped(Some(crop_window));
+    assert_eq!(crop.len(), 3 * size * size);
+    let full: Vec<Float> = render_cropped(None);
+    assert_eq!(full.len(), 3 * (RESOLUTION * RESOLUTION) as usize);
+    let mut n_lit: usize = 0;
+    for y in 0..size {
+        for x in 0..size {
+            for c in 0..3 {
+                let cropped: Float = crop[3 * (y * size + x) + c];
+                let uncropped: Float = full[3 * ((y0 + y) * RESOLUTION as usize + x0 + x) + c];
+                // only the order of the filter sums may differ
+                assert!(
+                    (cropped - uncropped).abs() <= 1e-4 * uncropped.max(1.0),
+                    "pixel ({}, {}): {} != {}",
+                    x0 + x,
+                    y0 + y,
+                    cropped,
+                    uncropped
+                );
+                if cropped > 0.0 {
+                    n_lit += 1;
+                }
+            }
+        }
+    }
+    // the region isn't trivially black
+    assert!(n_lit > 3 * size);
+}
@@ -0,0 +1,67 @@
+//! Denoising with Intel Open Image Denoise (see README.md for the
+//! setup): a noisy render of a sphere on a floor gets much closer to
+//! a converged one. Skipped if the library can't be loaded.
+
+#![cfg(feature = "denoise")]
+
+mod common;
+
+use common::{build, film, floor, params, sampler, sky};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::film::{denoise, Aov, AovBuffer, Film};
+use pbrt::core::oidn;
+use pbrt::core::pbrt::Float;
+
+/// Renders a sphere on a floor under a white sky with _samples_
+/// samples per pixel of the random sampler (recording the AOVs) and
+/// returns the film.
+fn render(samples: i32) -> std::sync::Arc<Film> {
+    let (mut film, path) = film(&format!("pbrt_denoise_{}.png", samples), 64, 64);
+    film.add_bool(String::from("aovs"), true);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 2.0, -4.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(sampler("random", samples))
+        .integrator(params("path"))
+        .world_begin()
+        .light_source(sky(1.0))
+        .shape(floor(-1.0, 10.0))
+        .shape(params("sphere"));
+    let (mut integrator, scene) = build(builder);
+    integrator.render(&scene, 1);
+    std::fs::remove_file(&path).unwrap();
+    integrator.get_camera().get_film()
+}
+
+fn mean_squared_error(a: &[Float], b: &[Float]) -> Float {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum::<Float>()
+        / a.len() as Float
+}
+
+#[test]
+fn denoised_image_is_closer_to_reference() {
+    if !oidn::is_available() {
+        println!("Intel Open Image Denoise not found, skipping");
+        return;
+    }
+    let reference: Vec<Float> = render(1024).get_rgb(1.0);
+    let film: std::sync::Arc<Film> = render(2);
+    let noisy: Vec<Float> = film.get_rgb(1.0);
+    let albedo: AovBuffer = film.get_aov_buffer(Aov::Albedo);
+    let normal: AovBuffer = film.get_aov_buffer(Aov::Normal);
+    let denoised: Vec<Float> = denoise(&film, Some(&albedo), Some(&normal)).concat();
+    let noisy_error: Float = mean_squared_error(&noisy, &reference);
+    let denoised_error: Float = mean_squared_error(&denoised, &reference);
+    assert!(
+        denoised_error < 0.25 * noisy_error,
+        "{} !< 0.25 * {}",
+        denoised_error,
+        noisy_error
+    );
+}
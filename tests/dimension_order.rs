@@ -0,0 +1,86 @@
+//! The path tracer has to consume the sample dimensions in the same
+//! order for every pixel (and every copy of the sampler), otherwise
+//! the dimensions of the deterministic samplers get mixed up between
+//! pixels. A **RecordingSampler** logs the get_1d() and get_2d() calls
+//! while a few pixels of a scene get rendered, whose paths all have the
+//! same length (a floor under a sky).
+
+mod common;
+
+use common::{build, film, floor, integrator, matte, params, sampler, sky};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::camera::CameraSample;
+use pbrt::core::geometry::{Point2i, Ray};
+use pbrt::core::integrator::{Integrator, SamplerRender};
+use pbrt::core::sampler::Sampler;
+use pbrt::samplers::recording::{RecordingSampler, SampleCall};
+
+/// Renders the pixels _pixels_ with the sampler _name_, the first half
+/// with one copy of the sampler, the rest with another one (like two
+/// tiles), and returns the calls recorded for each pixel.
+fn record(name: &str, pixels: &[Point2i]) -> Vec<Vec<SampleCall>> {
+    let filename: String = format!("pbrt_dimension_order_{}.png", name);
+    let (film, _path) = film(&filename, 16, 12);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 3.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(sampler(name, 4))
+        .integrator(integrator("path", 3))
+        .world_begin()
+        .light_source(sky(1.0))
+        .material(matte(0.5))
+        .shape(floor(0.0, 100.0));
+    let (mut integrator, scene) = build(builder);
+    let integrator = match *integrator {
+        Integrator::Sampler(ref mut integrator) => integrator,
+        _ => unreachable!(),
+    };
+    integrator.preprocess(&scene);
+    let camera = integrator.get_camera();
+    let mut calls: Vec<Vec<SampleCall>> = Vec::new();
+    for (tile, pixels) in pixels.chunks((pixels.len() + 1) / 2).enumerate() {
+        let mut sampler: Box<Sampler> = Box::new(Sampler::Recording(RecordingSampler::new(
+            integrator.get_sampler().clone_with_seed(tile as u64),
+        )));
+        for pixel in pixels {
+            sampler.start_pixel(pixel);
+            loop {
+                let camera_sample: CameraSample = sampler.get_camera_sample(pixel);
+                let mut ray: Ray = Ray::default();
+                camera.generate_ray_differential(&camera_sample, &mut ray);
+                integrator.li(&mut ray, &scene, &mut sampler, 0);
+                if !sampler.start_next_sample() {
+                    break;
+                }
+            }
+            match *sampler {
+                Sampler::Recording(ref recording) => calls.push(recording.calls.clone()),
+                _ => unreachable!(),
+            }
+        }
+    }
+    calls
+}
+
+#[test]
+fn path_consumes_same_dimensions_for_every_pixel() {
+    let pixels: Vec<Point2i> = vec![
+        Point2i { x: 0, y: 0 },
+        Point2i { x: 7, y: 5 },
+        Point2i { x: 15, y: 0 },
+        Point2i { x: 3, y: 11 },
+        Point2i { x: 15, y: 11 },
+    ];
+    for name in ["halton", "sobol", "stratified", "02sequence"].iter() {
+        let calls: Vec<Vec<SampleCall>> = record(name, &pixels);
+        // camera sample and at least one bounce for each of the 4
+        // samples
+        assert!(calls[0].len() > 4 * 5, "{}: {:?}", name, calls[0]);
+        for (pixel, pixel_calls) in pixels.iter().zip(calls.iter()) {
+            assert_eq!(pixel_calls, &calls[0], "{} at {:?}", name, pixel);
+        }
+    }
+}
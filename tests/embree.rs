@@ -0,0 +1,76 @@
+//! Embree finds the same hits as the BVH: a scene with a triangle
+//! mesh (native Embree geometry) and spheres (user geometry) renders
+//! the same image with both accelerators.
+
+#![cfg(feature = "embree")]
+
+mod common;
+
+use common::{film, params, render, sampler, sky};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::paramset::ParamSet;
+use pbrt::core::pbrt::Float;
+
+/// A floor of 8x8 quads, so many rays hit shared edges.
+fn tessellated_floor() -> ParamSet {
+    let n: i32 = 8;
+    let mut p: Vec<Float> = Vec::new();
+    for z in 0..=n {
+        for x in 0..=n {
+            p.extend_from_slice(&[x as Float - 4.0, -1.0, z as Float - 4.0]);
+        }
+    }
+    let mut indices: Vec<i32> = Vec::new();
+    for z in 0..n {
+        for x in 0..n {
+            let v: i32 = z * (n + 1) + x;
+            indices.extend_from_slice(&[v, v + n + 1, v + n + 2, v, v + n + 2, v + 1]);
+        }
+    }
+    let mut floor = params("trianglemesh");
+    floor.add_ints(String::from("indices"), indices);
+    floor.add_point3fs(String::from("P"), p);
+    floor
+}
+
+/// Renders a tessellated floor with a row of spheres under a white
+/// sky, using the accelerator _accelerator_.
+fn render_with(accelerator: &str) -> Vec<Float> {
+    let (film, path) = film(&format!("pbrt_embree_{}.png", accelerator), 32, 32);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 2.0, -4.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(sampler("halton", 4))
+        .integrator(params("path"))
+        .accelerator(params(accelerator))
+        .world_begin()
+        .light_source(sky(1.0))
+        .shape(tessellated_floor());
+    for x in &[-2.0, 0.0, 2.0] {
+        builder
+            .attribute_begin()
+            .translate(*x, 0.0, 0.0)
+            .shape(params("sphere"))
+            .attribute_end();
+    }
+    render(builder, &path)
+}
+
+#[test]
+fn embree_matches_bvh() {
+    let bvh: Vec<Float> = render_with("bvh");
+    let embree: Vec<Float> = render_with("embree");
+    assert_eq!(embree.len(), bvh.len());
+    // Embree's hit points differ in the last bits, which can change
+    // the paths a little
+    let difference: Float = embree
+        .iter()
+        .zip(bvh.iter())
+        .map(|(e, b)| (e - b).abs())
+        .sum::<Float>()
+        / bvh.len() as Float;
+    assert!(difference < 1e-3, "mean difference {}", difference);
+}
@@ -0,0 +1,108 @@
+//! Writes an HDR environment map (Radiance .hdr), renders it with an
+//! environment camera from the center of the scene, and compares the
+//! panorama with the source image.
+
+mod common;
+
+use common::{film, params, render};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::pbrt::Float;
+use pbrt::core::transform::Transform;
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 16;
+
+/// A smooth image with values from about 0.1 to 20 (in each channel).
+fn source_pixel(x: usize, y: usize) -> [f32; 3] {
+    let s: f32 = (x as f32 + 0.5) / WIDTH as f32;
+    let t: f32 = (y as f32 + 0.5) / HEIGHT as f32;
+    let phi: f32 = 2.0 * std::f32::consts::PI * s;
+    let theta: f32 = std::f32::consts::PI * t;
+    [
+        0.1 + 10.0 * (1.0 + phi.cos()) * theta.sin(),
+        0.5 + 2.0 * t,
+        0.2 + 4.0 * (1.0 - t) * (1.0 + phi.sin()),
+    ]
+}
+
+/// A texel as seen through the pixel of the panorama: the bilinear
+/// lookups of samples spread uniformly over the pixel average to
+/// weights of 1/8, 3/4 and 1/8 for the texels around it (in both
+/// directions).
+fn filtered_texel(texels: &[image::Rgb<f32>], x: usize, y: usize) -> [f32; 3] {
+    let weights: [(isize, f32); 3] = [(-1, 0.125), (0, 0.75), (1, 0.125)];
+    let mut rgb: [f32; 3] = [0.0; 3];
+    for (dy, wy) in weights.iter() {
+        for (dx, wx) in weights.iter() {
+            let sx: usize = (x as isize + dx).rem_euclid(WIDTH as isize) as usize;
+            let sy: usize = (y as isize + dy) as usize;
+            let texel: image::Rgb<f32> = texels[sy * WIDTH + sx];
+            for c in 0..3 {
+                rgb[c] += wx * wy * texel[c];
+            }
+        }
+    }
+    rgb
+}
+
+#[test]
+fn environment_camera_renders_hdr_map() {
+    // write the source image
+    let path = std::env::temp_dir().join("pbrt_environment_camera.hdr");
+    let mut data: Vec<image::Rgb<f32>> = Vec::new();
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            data.push(image::Rgb(source_pixel(x, y)));
+        }
+    }
+    let file = std::fs::File::create(&path).unwrap();
+    image::hdr::HDREncoder::new(std::io::BufWriter::new(file))
+        .encode(&data, WIDTH, HEIGHT)
+        .unwrap();
+    // RGBE values are less precise than the floats written
+    let file = std::fs::File::open(&path).unwrap();
+    let texels: Vec<image::Rgb<f32>> = image::hdr::HDRDecoder::new(std::io::BufReader::new(file))
+        .unwrap()
+        .read_image_hdr()
+        .unwrap();
+    assert_eq!(texels.len(), WIDTH * HEIGHT);
+    // render it
+    let (film, image_path) = film("pbrt_environment_camera.png", WIDTH as i32, HEIGHT as i32);
+    let mut sampler = params("stratified");
+    sampler.add_int(String::from("xsamples"), 8);
+    sampler.add_int(String::from("ysamples"), 8);
+    let mut light = params("infinite");
+    light.add_string(String::from("mapname"), path.to_str().unwrap().to_string());
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .camera(params("environment"))
+        .film(film)
+        .sampler(sampler)
+        .integrator(params("path"))
+        .world_begin()
+        // the camera's pole is +y, the one of the environment map +z
+        .transform(&Transform::new(
+            1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+        ))
+        .light_source(light);
+    let rgb: Vec<Float> = render(builder, &image_path);
+    std::fs::remove_file(&path).unwrap();
+    // each pixel of the panorama shows the texel of the same pixel of
+    // the source, blurred by the samples within the pixel (the rows at
+    // the poles are left out, there the lookups wrap around to the
+    // other pole)
+    assert_eq!(rgb.len(), 3 * WIDTH * HEIGHT);
+    let mut max_error: Float = 0.0;
+    for y in 1..HEIGHT - 1 {
+        for x in 0..WIDTH {
+            let expected: [f32; 3] = filtered_texel(&texels, x, y);
+            for c in 0..3 {
+                let rendered: Float = rgb[3 * (y * WIDTH + x) + c];
+                let error: Float = (rendered - expected[c]).abs() / expected[c].max(1.0);
+                max_error = max_error.max(error);
+            }
+        }
+    }
+    assert!(max_error < 0.02, "maximum relative error {}", max_error);
+}
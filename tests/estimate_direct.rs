@@ -0,0 +1,183 @@
+//! Compares **estimate_direct()** with brute-force estimates of the
+//! light reflected by a diffuse point under a square area light.
+
+mod common;
+
+use common::{floor, glow, matte, params, quad};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::geometry::{Point2f, Point3f, Ray, Vector3f};
+use pbrt::core::integrator::estimate_direct;
+use pbrt::core::material::TransportMode;
+use pbrt::core::paramset::ParamSet;
+use pbrt::core::pbrt::{Float, Spectrum, INV_PI};
+use pbrt::core::rng::Rng;
+use pbrt::core::sampler::Sampler;
+use pbrt::core::scene::Scene;
+use pbrt::samplers::random::RandomSampler;
+
+const ALBEDO: Float = 0.5;
+/// Height of the light above the floor
+const HEIGHT: Float = 1.0;
+/// The diffuse point on the floor
+const P: Point3f = Point3f {
+    x: 0.3,
+    y: 0.0,
+    z: 0.2,
+};
+
+/// A floor made of _material_ and a unit square light above it
+/// (facing down), optionally both surrounded by a homogeneous medium
+/// with _sigma_t_.
+fn make_scene(sigma_t: Option<Float>, material: ParamSet) -> Scene {
+    let mut builder = SceneBuilder::new(1);
+    builder.world_begin();
+    if let Some(sigma_t) = sigma_t {
+        let mut fog = params("fog");
+        fog.add_string(String::from("type"), String::from("homogeneous"));
+        fog.add_rgb(String::from("sigma_a"), [0.5 * sigma_t; 3]);
+        fog.add_rgb(String::from("sigma_s"), [0.5 * sigma_t; 3]);
+        builder
+            .make_named_medium(fog)
+            // the floor faces up, the light down, into the medium
+            .medium_interface("", "fog");
+    }
+    builder
+        .material(material)
+        .shape(floor(0.0, 5.0))
+        .attribute_begin()
+        .area_light_source(glow(1.0, false))
+        .shape(quad([
+            -0.5, HEIGHT, -0.5, 0.5, HEIGHT, -0.5, 0.5, HEIGHT, 0.5, -0.5, HEIGHT, 0.5,
+        ]))
+        .attribute_end();
+    builder.build().0
+}
+
+fn mean_and_variance(values: &[Float]) -> (Float, Float) {
+    let n: Float = values.len() as Float;
+    let mean: Float = values.iter().sum::<Float>() / n;
+    let variance: Float = values
+        .iter()
+        .map(|v| (v - mean) * (v - mean))
+        .sum::<Float>();
+    (mean, variance / (n - 1.0))
+}
+
+/// Estimates of the reflected light at _P_ (towards the zenith) by
+/// estimate_direct(), summed over all lights (one per triangle).
+fn estimate(scene: &Scene, handle_media: bool, n: usize) -> Vec<Float> {
+    let mut ray = Ray {
+        o: Point3f {
+            x: P.x,
+            y: 0.5 * HEIGHT,
+            z: P.z,
+        },
+        d: Vector3f {
+            x: 0.0,
+            y: -1.0,
+            z: 0.0,
+        },
+        t_max: Float::INFINITY,
+        time: 0.0,
+        differential: None,
+        medium: None,
+    };
+    let mut isect = scene.intersect(&mut ray).unwrap();
+    assert!((isect.p.x - P.x).abs() < 1e-4 && (isect.p.z - P.z).abs() < 1e-4);
+    isect.compute_scattering_functions(&ray, true, TransportMode::Radiance);
+    let mut sampler: Box<Sampler> = Box::new(Sampler::Random(RandomSampler::new(1)));
+    let mut rng: Rng = Rng::new();
+    let mut u = || Point2f {
+        x: rng.uniform_float(),
+        y: rng.uniform_float(),
+    };
+    (0..n)
+        .map(|_| {
+            scene
+                .lights
+                .iter()
+                .map(|light| {
+                    let (u_scattering, u_light) = (u(), u());
+                    let ld: Spectrum = estimate_direct(
+                        &isect,
+                        &u_scattering,
+                        light.clone(),
+                        &u_light,
+                        scene,
+                        &mut sampler,
+                        handle_media,
+                        false,
+                    );
+                    ld.y()
+                })
+                .sum::<Float>()
+        })
+        .collect()
+}
+
+/// Integrates over the area of the light by sampling points on it
+/// uniformly, attenuated by a medium with _sigma_t_.
+fn brute_force(sigma_t: Float, n: usize) -> Vec<Float> {
+    let mut rng: Rng = Rng::new();
+    rng.set_sequence(7);
+    (0..n)
+        .map(|_| {
+            let p_light = Point3f {
+                x: rng.uniform_float() - 0.5,
+                y: HEIGHT,
+                z: rng.uniform_float() - 0.5,
+            };
+            let d: Vector3f = p_light - P;
+            let r: Float = d.length();
+            // cosines at both ends are the same for parallel planes
+            let cos_theta: Float = HEIGHT / r;
+            ALBEDO * INV_PI * cos_theta * cos_theta / (r * r) * (-sigma_t * r).exp()
+        })
+        .collect()
+}
+
+fn check(estimates: &[Float], expected: &[Float]) {
+    let n: Float = estimates.len() as Float;
+    let n_expected: Float = expected.len() as Float;
+    let (mean, variance) = mean_and_variance(estimates);
+    let (expected, expected_variance) = mean_and_variance(expected);
+    let tolerance: Float = 4.0 * (variance / n + expected_variance / n_expected).sqrt();
+    assert!(
+        (mean - expected).abs() < tolerance,
+        "{} vs. {} (+- {})",
+        mean,
+        expected,
+        tolerance
+    );
+}
+
+#[test]
+fn diffuse_point_under_area_light() {
+    let scene: Scene = make_scene(None, matte(ALBEDO));
+    check(&estimate(&scene, false, 20_000), &brute_force(0.0, 200_000));
+    // without a medium, transmittance is one
+    check(&estimate(&scene, true, 20_000), &brute_force(0.0, 200_000));
+}
+
+#[test]
+fn diffuse_point_under_area_light_in_medium() {
+    let sigma_t: Float = 1.0;
+    let scene: Scene = make_scene(Some(sigma_t), matte(ALBEDO));
+    check(
+        &estimate(&scene, true, 20_000),
+        &brute_force(sigma_t, 200_000),
+    );
+    // without handling media, the light isn't attenuated
+    check(&estimate(&scene, false, 20_000), &brute_force(0.0, 200_000));
+}
+
+#[test]
+fn specular_only_bsdf_is_left_to_the_caller() {
+    // the integrators follow specular bounces themselves (or skip
+    // direct lighting), so a mirror gets nothing from estimate_direct()
+    let mut mirror = params("mirror");
+    mirror.add_rgb(String::from("Kr"), [1.0; 3]);
+    let scene: Scene = make_scene(None, mirror);
+    assert!(estimate(&scene, false, 100).iter().all(|v| *v == 0.0));
+}
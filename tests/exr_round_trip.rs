@@ -0,0 +1,141 @@
+//! OpenEXR files of **imageio** as seen by the _exr_ crate directly:
+//! files written by write_exr() keep their data and display window,
+//! channels and precision, and files written elsewhere (e.g.
+//! uncompressed) are read by read_exr_rgb(), e.g. for the environment
+//! map of an infinite light.
+
+use exr::prelude::*;
+// pbrt
+use pbrt::core::geometry::{Bounds2i, Point2i, Point3f, Ray, Vector3f};
+use pbrt::core::imageio::{read_exr_rgb, write_exr};
+use pbrt::core::pbrt::{Float, Spectrum};
+use pbrt::core::spectrum::SpectrumType;
+use pbrt::core::transform::Transform;
+use pbrt::lights::infinite::InfiniteAreaLight;
+
+/// Writes an uncompressed float RGB image with the _exr_ crate.
+fn write_rgb(path: &std::path::Path, width: usize, height: usize, rgb: [&Vec<f32>; 3]) {
+    let channels = AnyChannels::sort(smallvec::smallvec![
+        AnyChannel::new("R", FlatSamples::F32(rgb[0].clone())),
+        AnyChannel::new("G", FlatSamples::F32(rgb[1].clone())),
+        AnyChannel::new("B", FlatSamples::F32(rgb[2].clone())),
+    ]);
+    let layer = Layer::new(
+        (width, height),
+        LayerAttributes::default(),
+        Encoding::UNCOMPRESSED,
+        channels,
+    );
+    Image::from_layer(layer).write().to_file(path).unwrap();
+}
+
+#[test]
+fn write_exr_reads_with_exr_crate() {
+    let data_window = Bounds2i {
+        p_min: Point2i { x: 1, y: 2 },
+        p_max: Point2i { x: 4, y: 4 },
+    };
+    let r: Vec<Float> = vec![0.0, 0.25, 1.5, -2.0, 1024.0, 3.0];
+    let g: Vec<Float> = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+    let b: Vec<Float> = vec![0.5, 0.125, 0.0, 100.0, 7.0, 0.75];
+    let path = std::env::temp_dir().join("pbrt_exr_round_trip_write.exr");
+    for half_float in &[false, true] {
+        write_exr(
+            path.to_str().unwrap(),
+            &[("R", &r), ("G", &g), ("B", &b)],
+            &data_window,
+            &Point2i { x: 8, y: 8 },
+            *half_float,
+        )
+        .unwrap();
+        let image = read_first_flat_layer_from_file(&path).unwrap();
+        assert_eq!(image.attributes.display_window.size, Vec2(8, 8));
+        let layer = &image.layer_data;
+        assert_eq!(layer.size, Vec2(3, 2));
+        assert_eq!(layer.attributes.layer_position, Vec2(1, 2));
+        // channels are stored in alphabetical order
+        let names: Vec<String> = layer
+            .channel_data
+            .list
+            .iter()
+            .map(|channel| channel.name.to_string())
+            .collect();
+        assert_eq!(names, vec!["B", "G", "R"]);
+        for (channel, expected) in layer.channel_data.list.iter().zip(&[&b, &g, &r]) {
+            match (&channel.sample_data, half_float) {
+                (FlatSamples::F32(_), false) | (FlatSamples::F16(_), true) => {}
+                (samples, _) => panic!("unexpected sample type {:?}", samples),
+            }
+            let values: Vec<Float> = channel.sample_data.values_as_f32().collect();
+            assert_eq!(&values, *expected);
+        }
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn exr_crate_file_reads_with_read_exr_rgb() {
+    let (width, height): (usize, usize) = (4, 3);
+    let n_pixels: usize = width * height;
+    let r: Vec<f32> = (0..n_pixels).map(|i| i as f32 * 0.5).collect();
+    let g: Vec<f32> = (0..n_pixels).map(|i| 100.0 - i as f32).collect();
+    let b: Vec<f32> = (0..n_pixels).map(|i| (i * i) as f32 / 16.0).collect();
+    let path = std::env::temp_dir().join("pbrt_exr_round_trip_read.exr");
+    write_rgb(&path, width, height, [&r, &g, &b]);
+    let (resolution, rgb) = read_exr_rgb(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!((resolution.x, resolution.y), (width as i32, height as i32));
+    assert_eq!(rgb.len(), n_pixels);
+    for i in 0..n_pixels {
+        assert_eq!(rgb[i], [r[i], g[i], b[i]]);
+    }
+}
+
+#[test]
+fn infinite_light_from_exr() {
+    let path = std::env::temp_dir().join("pbrt_exr_round_trip_sky.exr");
+    let n_pixels: usize = 8 * 4;
+    write_rgb(
+        &path,
+        8,
+        4,
+        [
+            &vec![0.25; n_pixels],
+            &vec![0.5; n_pixels],
+            &vec![2.0; n_pixels],
+        ],
+    );
+    let scale: Spectrum = Spectrum::new(2.0);
+    let light = InfiniteAreaLight::new(
+        &Transform::default(),
+        &scale,
+        1,
+        path.to_str().unwrap().to_string(),
+    );
+    std::fs::remove_file(&path).unwrap();
+    let mut expected: [Float; 3] = [0.0; 3];
+    (Spectrum::from_rgb_type(&[0.25, 0.5, 2.0], SpectrumType::Illuminant) * scale)
+        .to_rgb(&mut expected);
+    let mut ray = Ray {
+        o: Point3f::default(),
+        d: Vector3f {
+            x: 0.0,
+            y: 0.6,
+            z: 0.8,
+        },
+        t_max: Float::INFINITY,
+        time: 0.0,
+        differential: None,
+        medium: None,
+    };
+    let mut rgb: [Float; 3] = [0.0; 3];
+    light.le(&mut ray).to_rgb(&mut rgb);
+    for c in 0..3 {
+        assert!(
+            (rgb[c] - expected[c]).abs() < 1e-4,
+            "{:?} != {:?}",
+            rgb,
+            expected
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Imports a small glTF file (a lit quad in front of a camera, with
+//! the geometry in an external buffer) and renders it.
+
+#![cfg(feature = "gltf")]
+
+use std::path::{Path, PathBuf};
+
+mod common;
+
+use common::{build, params, sampler};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::gltfimport::GltfImport;
+use pbrt::core::pbrt::Float;
+
+const QUAD_GLTF: &str = r#"{
+  "asset": { "version": "2.0" },
+  "extensionsUsed": ["KHR_lights_punctual"],
+  "extensions": {
+    "KHR_lights_punctual": {
+      "lights": [{ "type": "point", "color": [1.0, 1.0, 1.0], "intensity": 2732.0 }]
+    }
+  },
+  "scene": 0,
+  "scenes": [{ "nodes": [0, 1, 2] }],
+  "nodes": [
+    { "mesh": 0 },
+    { "camera": 0, "translation": [0.0, 0.0, 3.0] },
+    { "translation": [0.0, 0.0, 2.0], "extensions": { "KHR_lights_punctual": { "light": 0 } } }
+  ],
+  "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.5, "znear": 0.1 } }],
+  "materials": [{
+    "pbrMetallicRoughness": {
+      "baseColorFactor": [0.8, 0.8, 0.8, 1.0],
+      "metallicFactor": 0.0,
+      "roughnessFactor": 1.0
+    }
+  }],
+  "meshes": [{
+    "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }]
+  }],
+  "buffers": [{ "uri": "pbrt_quad.bin", "byteLength": 60 }],
+  "bufferViews": [
+    { "buffer": 0, "byteOffset": 0, "byteLength": 48 },
+    { "buffer": 0, "byteOffset": 48, "byteLength": 12 }
+  ],
+  "accessors": [
+    {
+      "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3",
+      "min": [-0.5, -0.5, 0.0], "max": [0.5, 0.5, 0.0]
+    },
+    { "bufferView": 1, "componentType": 5123, "count": 6, "type": "SCALAR" }
+  ]
+}"#;
+
+/// Writes the glTF file and its buffer to _dir_.
+fn write_quad(dir: &Path) -> PathBuf {
+    let mut buffer: Vec<u8> = Vec::new();
+    let positions: [f32; 12] = [
+        -0.5, -0.5, 0.0, 0.5, -0.5, 0.0, 0.5, 0.5, 0.0, -0.5, 0.5, 0.0,
+    ];
+    for p in positions.iter() {
+        buffer.extend_from_slice(&p.to_le_bytes());
+    }
+    let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+    for i in indices.iter() {
+        buffer.extend_from_slice(&i.to_le_bytes());
+    }
+    std::fs::write(dir.join("pbrt_quad.bin"), buffer).unwrap();
+    let path: PathBuf = dir.join("pbrt_quad.gltf");
+    std::fs::write(&path, QUAD_GLTF).unwrap();
+    path
+}
+
+#[test]
+fn quad_is_imported_and_lit() {
+    let dir: PathBuf = std::env::temp_dir().join("pbrt_gltf_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let gltf: GltfImport = GltfImport::open(&write_quad(&dir)).unwrap();
+    assert_eq!(gltf.buffers.len(), 1);
+    assert_eq!(gltf.buffers[0].0.len(), 60);
+    let mut film = params("image");
+    film.add_int(String::from("xresolution"), 8);
+    film.add_int(String::from("yresolution"), 8);
+    let image_path: PathBuf = dir.join("pbrt_quad.png");
+    film.add_string(
+        String::from("filename"),
+        image_path.to_str().unwrap().to_string(),
+    );
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .film(film)
+        .sampler(sampler("random", 4))
+        .integrator(params("directlighting"));
+    assert!(gltf.make_camera(&mut builder.api_state));
+    builder.world_begin();
+    gltf.make_world(&mut builder.api_state, &mut builder.bsdf_state);
+    let (mut integrator, scene) = build(builder);
+    assert_eq!(scene.lights.len(), 1);
+    integrator.render(&scene, 1);
+    let rgb: Vec<Float> = integrator.get_camera().get_film().get_rgb(1.0);
+    // the quad covers the center of the image, around it there is
+    // nothing
+    let center: usize = 3 * (4 * 8 + 4);
+    assert!(rgb[center] > 0.0);
+    assert!((rgb[center] - rgb[center + 1]).abs() < 1e-3 * rgb[center]);
+    assert!(rgb[0..3].iter().all(|v| *v == 0.0));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,98 @@
+//! Object instances share the geometry and the acceleration structure
+//! of their object: 10,000 instances of a tree of 100,000 triangles
+//! take about the memory of one tree, plus a transformation and a
+//! primitive per instance.
+#![cfg(feature = "stats")]
+
+mod common;
+
+use common::params;
+// pbrt
+use pbrt::accelerators::bvh::LinearBVHNode;
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::paramset::ParamSet;
+use pbrt::core::pbrt::Float;
+use pbrt::core::primitive::Primitive;
+use pbrt::core::scene::Scene;
+use pbrt::core::stats::stat_total;
+use pbrt::core::transform::AnimatedTransform;
+
+/// Quads per side of the grid standing in for the tree.
+const N: usize = 224;
+
+/// A grid of 2 * N * N (about 100,000) triangles, with some height, so
+/// the BVH has work to do.
+fn tree() -> ParamSet {
+    let mut p: Vec<Float> = Vec::with_capacity(3 * (N + 1) * (N + 1));
+    for y in 0..=N {
+        for x in 0..=N {
+            let (u, v): (Float, Float) = (x as Float / N as Float, y as Float / N as Float);
+            p.extend_from_slice(&[u, v, 0.1 * (10.0 * u).sin() * (10.0 * v).cos()]);
+        }
+    }
+    let mut indices: Vec<i32> = Vec::with_capacity(6 * N * N);
+    for y in 0..N {
+        for x in 0..N {
+            let i: i32 = (y * (N + 1) + x) as i32;
+            let row: i32 = (N + 1) as i32;
+            indices.extend_from_slice(&[i, i + 1, i + row + 1, i, i + row + 1, i + row]);
+        }
+    }
+    let mut mesh = params("trianglemesh");
+    mesh.add_ints(String::from("indices"), indices);
+    mesh.add_point3fs(String::from("P"), p);
+    mesh
+}
+
+/// The memory counted while building a scene with _n_instances_ of the
+/// tree, each at a transformation of its own.
+fn scene_memory(n_instances: usize) -> i64 {
+    let titles: [&str; 4] = [
+        "Memory/Triangle meshes",
+        "Memory/Primitives",
+        "Memory/BVH tree",
+        "Memory/Animated transforms",
+    ];
+    let total = || -> i64 { titles.iter().map(|title| stat_total(title)).sum() };
+    let before: i64 = total();
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .world_begin()
+        .object_begin(params("tree"))
+        .shape(tree())
+        .object_end();
+    for i in 0..n_instances {
+        builder
+            .attribute_begin()
+            .translate(2.0 * (i % 100) as Float, 0.0, 2.0 * (i / 100) as Float)
+            .object_instance(params("tree"))
+            .attribute_end();
+    }
+    let scene: Scene = builder.build().0;
+    // the last instance is part of the scene
+    assert!(scene.world_bound().p_max.z > 2.0 * ((n_instances - 1) / 100) as Float);
+    total() - before
+}
+
+#[test]
+fn instances_share_the_tree() {
+    let one: i64 = scene_memory(1);
+    // the tree itself: at least the vertex indices and a primitive per
+    // triangle
+    let n_triangles: i64 = (2 * N * N) as i64;
+    assert!(one > n_triangles * (12 + std::mem::size_of::<Primitive>()) as i64);
+    let many: i64 = scene_memory(10_000);
+    // each further instance adds a primitive, a transformation, and
+    // (less than) two nodes of the top-level BVH
+    let per_instance: i64 = (std::mem::size_of::<Primitive>()
+        + std::mem::size_of::<AnimatedTransform>()
+        + 2 * std::mem::size_of::<LinearBVHNode>()) as i64;
+    assert!(
+        many - one <= 10_000 * per_instance,
+        "{} bytes for one instance, {} bytes for 10,000 instances",
+        one,
+        many
+    );
+    // ... which is only a fraction of another tree
+    assert!(many < 2 * one, "{} vs {}", many, one);
+}
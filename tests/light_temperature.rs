@@ -0,0 +1,64 @@
+//! Renders a white floor lit by a point light with a color
+//! temperature: at 6500K the light is nearly white, at lower
+//! temperatures it's reddish.
+
+mod common;
+
+use common::{film, floor, matte, params, render};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::geometry::Point3f;
+use pbrt::core::pbrt::Float;
+
+/// Returns the RGB value of the center pixel, lit by a point light of
+/// the given _temperature_ (in Kelvin, 0 for none).
+fn render_center(temperature: Float) -> [Float; 3] {
+    let (film, path) = film(&format!("pbrt_light_temperature_{}.png", temperature), 4, 4);
+    let mut light = params("point");
+    light.add_point3f(
+        String::from("from"),
+        Point3f {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+    );
+    if temperature > 0.0 {
+        light.add_float(String::from("temperature"), temperature);
+    }
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(params("random"))
+        .integrator(params("directlighting"))
+        .world_begin()
+        .light_source(light)
+        .material(matte(1.0))
+        .shape(floor(0.0, 10.0));
+    let rgb: Vec<Float> = render(builder, &path);
+    let center: usize = 3 * (2 * 4 + 2);
+    [rgb[center], rgb[center + 1], rgb[center + 2]]
+}
+
+fn luminance(rgb: &[Float; 3]) -> Float {
+    0.212671 * rgb[0] + 0.715160 * rgb[1] + 0.072169 * rgb[2]
+}
+
+#[test]
+fn blackbody_6500k_is_near_white() {
+    let white: [Float; 3] = render_center(0.0);
+    assert!(luminance(&white) > 0.0);
+    let daylight: [Float; 3] = render_center(6500.0);
+    // the tint is normalized, it doesn't change the brightness ...
+    assert!((luminance(&daylight) - luminance(&white)).abs() < 0.01 * luminance(&white));
+    // ... and at 6500K it hardly changes the color
+    for c in 0..3 {
+        let ratio: Float = daylight[c] / white[c];
+        assert!((ratio - 1.0).abs() < 0.05, "{:?} vs {:?}", daylight, white);
+    }
+    let incandescent: [Float; 3] = render_center(2700.0);
+    assert!(incandescent[0] > 1.5 * incandescent[1]);
+    assert!(incandescent[1] > 1.5 * incandescent[2]);
+}
@@ -0,0 +1,70 @@
+//! Renders a closed box of perfect mirrors, where paths never end on
+//! their own, with a limited number of intersections per path. An
+//! environment light outside the box must not be seen by the aborted
+//! paths.
+
+use std::path::PathBuf;
+
+mod common;
+
+use common::{build, film, integrator, params, sampler, sky};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::film::{Aov, AovBuffer, Film};
+use pbrt::core::pbrt::Float;
+
+#[test]
+fn mirror_box_is_aborted() {
+    let (film, path) = film("pbrt_mirror_box.png", 4, 4);
+    // no Russian roulette (the throughput of perfect mirrors rounds to
+    // slightly below one), so only the limit ends the paths
+    let mut integrator = integrator("path", 1_000_000);
+    integrator.add_int(String::from("maxintersections"), 64);
+    integrator.add_float(String::from("rrthreshold"), 0.0);
+    let mut mirror = params("mirror");
+    mirror.add_rgb(String::from("Kr"), [1.0, 1.0, 1.0]);
+    // a watertight box around the camera
+    let mut walls = params("trianglemesh");
+    walls.add_ints(
+        String::from("indices"),
+        vec![
+            0, 1, 2, 0, 2, 3, 4, 6, 5, 4, 7, 6, 0, 3, 7, 0, 7, 4, 1, 5, 6, 1, 6, 2, 0, 4, 5, 0, 5,
+            1, 3, 2, 6, 3, 6, 7,
+        ],
+    );
+    walls.add_point3fs(
+        String::from("P"),
+        vec![
+            -1.0, -1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, -1.0, 1.0, -1.0, -1.0, -1.0, 1.0,
+            1.0, -1.0, 1.0, 1.0, 1.0, 1.0, -1.0, 1.0, 1.0,
+        ],
+    );
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 0.0, 0.0, 0.3, 0.2, 1.0, 0.0, 1.0, 0.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(sampler("random", 2))
+        .integrator(integrator)
+        .world_begin()
+        .light_source(sky(1.0))
+        .material(mirror)
+        .shape(walls);
+    let (mut integrator, scene) = build(builder);
+    assert_eq!(scene.max_intersections, 64);
+    integrator.render(&scene, 1);
+    let film: std::sync::Arc<Film> = integrator.get_camera().get_film();
+    // every camera sample got stuck and is flagged
+    let aborted: AovBuffer = film.get_aov_buffer(Aov::Aborted);
+    assert_eq!(aborted.values.len(), 16);
+    assert!(aborted.values.iter().all(|n| *n == 2.0 as Float));
+    assert_eq!(film.n_aborted_pixels(), 16);
+    // aborted paths didn't escape, so they don't see the sky
+    assert!(film.get_rgb(1.0).iter().all(|v| *v == 0.0));
+    // next to the image there is one showing the aborted pixels
+    let aborted_path: PathBuf = std::env::temp_dir().join("pbrt_mirror_box_aborted.png");
+    let png = image::open(&aborted_path).unwrap().to_luma();
+    assert!(png.pixels().all(|p| p.0 == [255]));
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&aborted_path).unwrap();
+}
@@ -0,0 +1,73 @@
+//! A very fine checkerboard seen in a mirror: with the ray
+//! differentials propagated across the specular bounce, the checks are
+//! filtered to gray. Point sampling would make about half of the
+//! pixels black.
+
+mod common;
+
+use common::{film, integrator, params, perspective, quad, render, sampler};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::geometry::Point3f;
+use pbrt::core::paramset::ParamSet;
+use pbrt::core::pbrt::Float;
+
+fn plane(y: Float) -> ParamSet {
+    quad([
+        -100.0, y, -100.0, 100.0, y, -100.0, 100.0, y, 100.0, -100.0, y, 100.0,
+    ])
+}
+
+/// Renders a mirror floor reflecting a checkered ceiling, which is
+/// lit by a point light, and returns the image.
+fn render_with(name: &str) -> Vec<Float> {
+    let (film, path) = film(&format!("pbrt_mirror_differentials_{}.png", name), 16, 16);
+    let mut light = params("point");
+    light.add_point3f(
+        String::from("from"),
+        Point3f {
+            x: 0.0,
+            y: 4.0,
+            z: 5.0,
+        },
+    );
+    let mut checks = params("checks");
+    checks.tex_type = String::from("spectrum");
+    checks.tex_name = String::from("checkerboard");
+    checks.add_rgb(String::from("tex1"), [1.0; 3]);
+    checks.add_rgb(String::from("tex2"), [0.0; 3]);
+    checks.add_float(String::from("uscale"), 100000.0);
+    checks.add_float(String::from("vscale"), 100000.0);
+    let mut matte = params("matte");
+    matte.add_texture(String::from("Kd"), String::from("checks"));
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 1.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+        .camera(perspective(10.0))
+        .film(film)
+        .sampler(sampler("halton", 1))
+        .integrator(integrator(name, 2))
+        .world_begin()
+        .light_source(light)
+        // mirror floor
+        .attribute_begin()
+        .material(params("mirror"))
+        .shape(plane(0.0))
+        .attribute_end()
+        // checkered ceiling, many checks per pixel
+        .attribute_begin()
+        .texture(checks)
+        .material(matte)
+        .shape(plane(5.0))
+        .attribute_end();
+    render(builder, &path)
+}
+
+#[test]
+fn checks_in_mirror_are_filtered() {
+    for integrator in &["path", "volpath"] {
+        let rgb: Vec<Float> = render_with(integrator);
+        let black: usize = rgb.chunks(3).filter(|pixel| pixel[1] == 0.0).count();
+        assert_eq!(black, 0, "{}", integrator);
+    }
+}
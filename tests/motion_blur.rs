@@ -0,0 +1,94 @@
+//! Renders an instanced sphere which moves across the image while the
+//! shutter is open, and compares it with the sphere standing still.
+
+mod common;
+
+use common::{film, params, perspective, render, sphere};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::pbrt::Float;
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 32;
+const RADIUS: Float = 0.25;
+
+/// Renders the sphere moving from _x0_ to _x1_ (in world space) while
+/// the shutter is open. Returns the red channel of the image.
+fn render_moving(x0: Float, x1: Float) -> Vec<Float> {
+    let (film, path) = film(
+        &format!("pbrt_motion_blur_{}_{}.png", x0, x1),
+        WIDTH as i32,
+        HEIGHT as i32,
+    );
+    let mut sampler = params("stratified");
+    sampler.add_int(String::from("xsamples"), 8);
+    sampler.add_int(String::from("ysamples"), 8);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 0.0, -5.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+        .camera(perspective(40.0))
+        .film(film)
+        .sampler(sampler)
+        // the sphere is convex, so every hit is unoccluded
+        .integrator(params("ambientocclusion"))
+        .world_begin()
+        .object_begin(params("ball"))
+        .shape(sphere(RADIUS))
+        .object_end()
+        .attribute_begin()
+        .active_transform_start_time()
+        .translate(x0, 0.0, 0.0)
+        .active_transform_end_time()
+        .translate(x1, 0.0, 0.0)
+        .active_transform_all()
+        .object_instance(params("ball"))
+        .attribute_end();
+    let rgb: Vec<Float> = render(builder, &path);
+    rgb.iter().step_by(3).copied().collect()
+}
+
+#[test]
+fn translating_instance_is_blurred_along_its_path() {
+    let still: Vec<Float> = render_moving(0.0, 0.0);
+    let moving: Vec<Float> = render_moving(-1.0, 1.0);
+    // the value of a pixel covered by the sphere all the time
+    let center: Float = still[(HEIGHT / 2) * WIDTH + WIDTH / 2];
+    assert!(center > 0.0);
+    // the image height covers 2 * 5 * tan(20 degrees) in world space
+    let pixels_per_unit: Float = HEIGHT as Float / (10.0 * (20.0 as Float).to_radians().tan());
+    let row: &[Float] = &moving[(HEIGHT / 2) * WIDTH..(HEIGHT / 2 + 1) * WIDTH];
+    for x in 0..WIDTH {
+        let world_x: Float = (x as Float + 0.5 - WIDTH as Float / 2.0) / pixels_per_unit;
+        if world_x.abs() < 1.0 - RADIUS {
+            // passed completely, covered for 2 * RADIUS of the 2
+            // units traveled
+            let expected: Float = center * RADIUS;
+            assert!(
+                (row[x] - expected).abs() < 0.2 * expected,
+                "pixel {}: {} (expected {})",
+                x,
+                row[x],
+                expected
+            );
+        } else if world_x.abs() > 1.0 + RADIUS + 1.0 / pixels_per_unit {
+            assert_eq!(row[x], 0.0, "pixel {}", x);
+        }
+    }
+    // the sphere reaches both ends of its path (the bounds of the
+    // instance cover the whole motion)
+    let end: usize = pixels_per_unit.round() as usize;
+    assert!(row[WIDTH / 2 - end] > 0.0);
+    assert!(row[WIDTH / 2 + end - 1] > 0.0);
+    // nothing above or below the path
+    assert!(moving[0..WIDTH].iter().all(|v| *v == 0.0));
+    assert!(moving[(HEIGHT - 1) * WIDTH..].iter().all(|v| *v == 0.0));
+    // moving or not, the same amount of light reaches the film
+    let sum_still: Float = still.iter().sum();
+    let sum_moving: Float = moving.iter().sum();
+    assert!(
+        (sum_moving - sum_still).abs() < 0.05 * sum_still,
+        "{} vs {}",
+        sum_moving,
+        sum_still
+    );
+}
@@ -0,0 +1,41 @@
+//! Filters with negative lobes (like Mitchell-Netravali) can make a
+//! pixel next to a bright sample negative. The film clamps those
+//! pixels to zero.
+
+use pbrt::core::film::{Film, FilmTile};
+use pbrt::core::filter::Filter;
+use pbrt::core::geometry::{Bounds2f, Point2f, Point2i};
+use pbrt::core::pbrt::{Float, Spectrum};
+use pbrt::filters::mitchell::MitchellNetravali;
+
+#[test]
+fn negative_pixels_are_clamped() {
+    let film: Film = Film::new(
+        Point2i { x: 4, y: 4 },
+        Bounds2f {
+            p_min: Point2f { x: 0.0, y: 0.0 },
+            p_max: Point2f { x: 1.0, y: 1.0 },
+        },
+        Box::new(Filter::MitchellNetravali(MitchellNetravali::new(
+            2.0,
+            2.0,
+            1.0 / 3.0,
+            1.0 / 3.0,
+        ))),
+        35.0,
+        String::from("negative_lobes.png"),
+        1.0,
+        std::f32::INFINITY as Float,
+    );
+    let mut tile: FilmTile = film.get_film_tile(&film.get_sample_bounds());
+    // a dark sample in the center of pixel (1, 1) and a bright one 1.5
+    // pixels away, where the filter is negative
+    tile.add_sample(&Point2f { x: 1.5, y: 1.5 }, &mut Spectrum::new(0.0), 1.0);
+    tile.add_sample(&Point2f { x: 3.0, y: 1.5 }, &mut Spectrum::new(10.0), 1.0);
+    film.merge_film_tile(&tile);
+    let rgb: Vec<Float> = film.get_rgb(1.0);
+    assert!(rgb.iter().all(|v| *v >= 0.0));
+    let pixel = |x: usize, y: usize| -> &[Float] { &rgb[3 * (4 * y + x)..3 * (4 * y + x + 1)] };
+    assert_eq!(pixel(1, 1), &[0.0, 0.0, 0.0]);
+    assert!(pixel(2, 1).iter().all(|v| *v > 0.0));
+}
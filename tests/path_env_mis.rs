@@ -0,0 +1,122 @@
+//! The path integrator weights emission of environment lights, found
+//! by BSDF sampled rays escaping the scene, against light sampling. A
+//! glossy plane under an HDR sky, seen from straight above, so every
+//! pixel has the same expected value. The pixels agree with an
+//! estimate that only samples the BSDF, but vary much less than the
+//! same number of BSDF samples would.
+
+mod common;
+
+use common::{build, film, integrator, params, quad, sampler};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::geometry::{Point2f, Point3f, Ray, Vector3f};
+use pbrt::core::microfacet::{MicrofacetDistribution, TrowbridgeReitzDistribution};
+use pbrt::core::pbrt::{Float, Spectrum};
+use pbrt::core::reflection::{Fresnel, FresnelDielectric, MicrofacetReflection};
+use pbrt::core::rng::Rng;
+
+fn mean_and_variance(values: &[Float]) -> (Float, Float) {
+    let n: Float = values.len() as Float;
+    let mean: Float = values.iter().sum::<Float>() / n;
+    let variance: Float = values
+        .iter()
+        .map(|v| (v - mean) * (v - mean))
+        .sum::<Float>();
+    (mean, variance / (n - 1.0))
+}
+
+#[test]
+fn escaped_rays_are_weighted() {
+    let roughness: Float = 0.2;
+    let spp: i32 = 4;
+    let (film, path) = film("pbrt_path_env_mis.exr", 16, 16);
+    let mut sky = params("infinite");
+    let mapname: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/scenes/textures/skylight-day.hdr"
+    );
+    sky.add_string(String::from("mapname"), mapname.to_string());
+    let mut material = params("plastic");
+    material.add_rgb(String::from("Kd"), [0.0, 0.0, 0.0]);
+    material.add_rgb(String::from("Ks"), [1.0, 1.0, 1.0]);
+    material.add_float(String::from("roughness"), roughness);
+    material.add_bool(String::from("remaproughness"), false);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+        .camera(params("orthographic"))
+        .film(film)
+        .sampler(sampler("random", spp))
+        .integrator(integrator("path", 1))
+        .world_begin()
+        .light_source(sky)
+        .material(material)
+        .shape(quad([
+            -100.0, -100.0, 0.0, 100.0, -100.0, 0.0, 100.0, 100.0, 0.0, -100.0, 100.0, 0.0,
+        ]));
+    let (mut integrator, scene) = build(builder);
+    integrator.render(&scene, 1);
+    std::fs::remove_file(&path).unwrap();
+    let rgb: Vec<Float> = integrator.get_camera().get_film().get_rgb(1.0);
+    let pixels: Vec<Float> = rgb
+        .chunks(3)
+        .map(|c| 0.212671 * c[0] + 0.715160 * c[1] + 0.072169 * c[2])
+        .collect();
+    let (mean, variance) = mean_and_variance(&pixels);
+
+    // reflected sky light, estimated by sampling the plastic's BSDF only
+    let bxdf = MicrofacetReflection::new(
+        Spectrum::new(1.0),
+        MicrofacetDistribution::TrowbridgeReitz(TrowbridgeReitzDistribution::new(
+            roughness, roughness, true,
+        )),
+        Fresnel::Dielectric(FresnelDielectric {
+            eta_i: 1.5,
+            eta_t: 1.0,
+        }),
+        None,
+    );
+    let wo = Vector3f {
+        x: 0.0,
+        y: 0.0,
+        z: 1.0,
+    };
+    let mut rng: Rng = Rng::new();
+    let bsdf_samples: Vec<Float> = (0..100_000)
+        .map(|_| {
+            let u = Point2f {
+                x: rng.uniform_float(),
+                y: rng.uniform_float(),
+            };
+            let mut wi = Vector3f::default();
+            let mut pdf: Float = 0.0;
+            let mut sampled_type: u8 = 0;
+            let f: Spectrum = bxdf.sample_f(&wo, &mut wi, &u, &mut pdf, &mut sampled_type);
+            if pdf == 0.0 {
+                return 0.0;
+            }
+            let mut ray = Ray {
+                o: Point3f::default(),
+                d: wi,
+                t_max: std::f32::INFINITY,
+                time: 0.0,
+                differential: None,
+                medium: None,
+            };
+            (f * scene.infinite_lights[0].le(&mut ray) * wi.z.abs() / pdf).y()
+        })
+        .collect();
+    let (expected, bsdf_variance) = mean_and_variance(&bsdf_samples);
+    let tolerance: Float =
+        4.0 * (variance / pixels.len() as Float + bsdf_variance / 100_000.0).sqrt();
+    assert!(
+        (mean - expected).abs() < tolerance,
+        "{} vs. {}",
+        mean,
+        expected
+    );
+    // pixels averaging _spp_ BSDF samples would have a variance of
+    // bsdf_variance / spp
+    assert!(variance < 0.25 * bsdf_variance / spp as Float);
+}
@@ -0,0 +1,168 @@
+//! The path integrator combines light and BSDF sampling with MIS, for
+//! area lights hit after a diffuse bounce as well as for environment
+//! light along escaped rays. A matte floor (reflectance 0.5) under a
+//! uniformly glowing ceiling or a white sky reflects half of the
+//! radiance; dropping the BSDF sampled half loses energy.
+//!
+//! The noise of the path tracer is also compared with the one of the
+//! volumetric path tracer. Without participating media the latter
+//! computes the same image the way the path tracer used to: it traces
+//! a separate BSDF sampled shadow ray per vertex and ignores emission
+//! found by the continuation ray.
+
+mod common;
+
+use common::{
+    film, glow, integrator, matte, params, perspective, quad, render, sampler, sky, sphere,
+};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::paramset::ParamSet;
+use pbrt::core::pbrt::{Float, Spectrum};
+
+fn plane(y: Float) -> ParamSet {
+    quad([
+        -100.0, y, -100.0, 100.0, y, -100.0, 100.0, y, 100.0, -100.0, y, 100.0,
+    ])
+}
+
+/// Renders the floor, lit by a glowing ceiling or (with _sky_) by an
+/// infinite light, and returns the mean of the green channel.
+fn render_mean(name: &str, with_sky: bool) -> Float {
+    let (film, path) = film(&format!("pbrt_path_mis_{}_{}.png", name, with_sky), 8, 8);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0)
+        .camera(perspective(10.0))
+        .film(film)
+        .sampler(sampler("halton", 256))
+        .integrator(integrator(name, 1))
+        .world_begin();
+    if with_sky {
+        builder.light_source(sky(1.0));
+    } else {
+        builder
+            .attribute_begin()
+            .area_light_source(glow(1.0, true))
+            .shape(plane(2.0))
+            .attribute_end();
+    }
+    builder.material(matte(0.5)).shape(plane(0.0));
+    let rgb: Vec<Float> = render(builder, &path);
+    let green: Vec<Float> = rgb.chunks(3).map(|pixel| pixel[1]).collect();
+    green.iter().sum::<Float>() / green.len() as Float
+}
+
+#[test]
+fn bsdf_samples_of_lights_are_weighted() {
+    let mut expected: [Float; 3] = [0.0; 3];
+    Spectrum::new(0.5).to_rgb(&mut expected);
+    for integrator in &["path", "volpath"] {
+        for sky in &[false, true] {
+            let value: Float = render_mean(integrator, *sky);
+            assert!(
+                (value - expected[1]).abs() < 0.01,
+                "{} (sky {}): {} != {}",
+                integrator,
+                sky,
+                value,
+                expected[1]
+            );
+        }
+    }
+}
+
+fn plastic(kd: Float, ks: Float, roughness: Float) -> ParamSet {
+    let mut plastic = params("plastic");
+    plastic.add_rgb(String::from("Kd"), [kd; 3]);
+    plastic.add_rgb(String::from("Ks"), [ks; 3]);
+    plastic.add_float(String::from("roughness"), roughness);
+    plastic
+}
+
+/// A glossy floor and a glossy sphere, lit by a small bright sphere
+/// light and a dim quad light, rendered with _integrator_ and _spp_
+/// samples per pixel (of the random sampler with the given _seed_).
+/// Returns the RGB values of the image.
+fn render_glossy(integrator: &str, spp: i32, seed: i32) -> Vec<Float> {
+    let (film, path) = film(
+        &format!("pbrt_path_mis_{}_{}_{}.png", integrator, spp, seed),
+        32,
+        24,
+    );
+    let mut random = sampler("random", spp);
+    random.add_int(String::from("seed"), seed);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 1.0, -4.0, 0.0, 0.3, 0.0, 0.0, 1.0, 0.0)
+        .camera(perspective(40.0))
+        .film(film)
+        .sampler(random)
+        .integrator(params(integrator))
+        .world_begin()
+        // small bright sphere light
+        .attribute_begin()
+        .translate(-0.8, 1.2, 0.5)
+        .area_light_source(glow(50.0, false))
+        .shape(sphere(0.1))
+        .attribute_end()
+        // dim quad light, facing down
+        .attribute_begin()
+        .area_light_source(glow(2.0, false))
+        .shape(quad([
+            0.5, 2.0, -0.5, 1.5, 2.0, -0.5, 1.5, 2.0, 0.5, 0.5, 2.0, 0.5,
+        ]))
+        .attribute_end()
+        // glossy sphere on a near-mirror floor
+        .attribute_begin()
+        .material(plastic(0.4, 0.5, 0.05))
+        .translate(0.3, 0.4, 0.3)
+        .shape(sphere(0.4))
+        .attribute_end()
+        .material(plastic(0.1, 0.9, 0.01))
+        .shape(quad([
+            -5.0, 0.0, -5.0, -5.0, 0.0, 5.0, 5.0, 0.0, 5.0, 5.0, 0.0, -5.0,
+        ]));
+    render(builder, &path)
+}
+
+fn mean(rgb: &[Float]) -> Float {
+    rgb.iter().sum::<Float>() / rgb.len() as Float
+}
+
+fn mean_absolute_error(rgb: &[Float], reference: &[Float]) -> Float {
+    let sum: Float = rgb
+        .iter()
+        .zip(reference.iter())
+        .map(|(v, r)| (v - r).abs())
+        .sum();
+    sum / rgb.len() as Float
+}
+
+#[test]
+fn mis_path_tracer_is_not_noisier() {
+    // both converge to the same image ...
+    let reference: Vec<Float> = render_glossy("volpath", 512, 1);
+    let converged: Vec<Float> = render_glossy("path", 512, 2);
+    let relative: Float = (mean(&converged) - mean(&reference)).abs() / mean(&reference);
+    assert!(relative < 0.05, "mean differs by {}", relative);
+    // ... and reusing the continuation ray for the BSDF sample adds no
+    // noise compared to a separate shadow ray (both weight it against
+    // light sampling the same way). Fireflies make the error of a
+    // single render vary a lot, so it's summed up over renders with
+    // independent seeds (not shared by the integrators, which would
+    // otherwise take the same random numbers and compute nearly the
+    // same image).
+    let mut path_error: Float = 0.0;
+    let mut volpath_error: Float = 0.0;
+    for seed in 0..8 {
+        path_error += mean_absolute_error(&render_glossy("path", 16, 10 + seed), &reference);
+        volpath_error += mean_absolute_error(&render_glossy("volpath", 16, 20 + seed), &reference);
+    }
+    assert!(
+        path_error < 1.5 * volpath_error,
+        "path {} vs volpath {}",
+        path_error,
+        volpath_error
+    );
+}
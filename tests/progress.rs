@@ -0,0 +1,83 @@
+//! Progress is reported once per tile, and a cancelled render merges
+//! no further tiles but still writes the partial image.
+
+use std::sync::Mutex;
+
+mod common;
+
+use common::{build, film, floor, params, sampler};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::integrator::Integrator;
+use pbrt::core::pbrt::Float;
+use pbrt::core::progressreporter::{CancellationToken, RenderProgress};
+use pbrt::core::scene::Scene;
+
+/// Records all updates and cancels the render after _cancel_after_
+/// tiles.
+struct Recorder {
+    updates: Mutex<Vec<(usize, usize)>>,
+    cancel_after: usize,
+    cancel: CancellationToken,
+}
+
+impl RenderProgress for Recorder {
+    fn on_tile_done(&self, completed: usize, total: usize) {
+        self.updates.lock().unwrap().push((completed, total));
+        if completed >= self.cancel_after {
+            self.cancel.cancel();
+        }
+    }
+}
+
+/// Sets up ambient occlusion of a floor, rendered in 16 tiles of
+/// 16x16 pixels.
+fn setup(name: &str) -> (Box<Integrator>, Scene, std::path::PathBuf) {
+    let (mut film, path) = film(&format!("pbrt_progress_{}.png", name), 64, 64);
+    film.add_int(String::from("tilesize"), 16);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(sampler("02sequence", 1))
+        .integrator(params("ambientocclusion"))
+        .world_begin()
+        .shape(floor(0.0, 10.0));
+    let (integrator, scene) = build(builder);
+    (integrator, scene, path)
+}
+
+#[test]
+fn progress_per_tile() {
+    let (mut integrator, scene, path) = setup("complete");
+    let cancel: CancellationToken = CancellationToken::new();
+    let recorder: Recorder = Recorder {
+        updates: Mutex::new(Vec::new()),
+        cancel_after: usize::MAX,
+        cancel: cancel.clone(),
+    };
+    integrator.render_with_progress(&scene, 1, &recorder, &cancel);
+    std::fs::remove_file(&path).unwrap();
+    let expected: Vec<(usize, usize)> = (1..=16).map(|completed| (completed, 16)).collect();
+    assert_eq!(*recorder.updates.lock().unwrap(), expected);
+}
+
+#[test]
+fn cancel_after_first_tile() {
+    let (mut integrator, scene, path) = setup("cancelled");
+    let cancel: CancellationToken = CancellationToken::new();
+    let recorder: Recorder = Recorder {
+        updates: Mutex::new(Vec::new()),
+        cancel_after: 1,
+        cancel: cancel.clone(),
+    };
+    integrator.render_with_progress(&scene, 1, &recorder, &cancel);
+    assert_eq!(*recorder.updates.lock().unwrap(), vec![(1, 16)]);
+    // the partial image was written and contains exactly one tile
+    assert!(path.exists());
+    std::fs::remove_file(&path).unwrap();
+    let rgb: Vec<Float> = integrator.get_camera().get_film().get_rgb(1.0);
+    let rendered: usize = rgb.chunks(3).filter(|pixel| pixel[1] > 0.0).count();
+    assert_eq!(rendered, 16 * 16);
+}
@@ -0,0 +1,82 @@
+//! Progressive rendering takes one sample per pixel in each pass and
+//! ends (for a deterministic sampler) with the image of a regular
+//! render.
+
+use std::sync::Mutex;
+
+mod common;
+
+use common::{build, film, floor, params, sampler, sky};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::film::Film;
+use pbrt::core::integrator::{render_progressive, Integrator};
+use pbrt::core::pbrt::Float;
+use pbrt::core::scene::Scene;
+
+/// A sphere on a floor under a white sky, rendered with 8 samples per
+/// pixel of the sampler _sampler_name_.
+fn setup(sampler_name: &str, name: &str) -> (Box<Integrator>, Scene, std::path::PathBuf) {
+    let (film, path) = film(&format!("pbrt_progressive_{}.png", name), 16, 16);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 2.0, -4.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+        .camera(params("perspective"))
+        .film(film)
+        .pixel_filter(params("box"))
+        .sampler(sampler(sampler_name, 8))
+        .integrator(params("path"))
+        .world_begin()
+        .light_source(sky(1.0))
+        .shape(floor(-1.0, 10.0))
+        .shape(params("sphere"));
+    let (integrator, scene) = build(builder);
+    (integrator, scene, path)
+}
+
+#[test]
+fn progressive_matches_regular_render() {
+    let (mut integrator, scene, path) = setup("sobol", "regular");
+    integrator.render(&scene, 1);
+    std::fs::remove_file(&path).unwrap();
+    let expected: Vec<Float> = integrator.get_camera().get_film().get_rgb(1.0);
+
+    let (mut integrator, scene, path) = setup("sobol", "progressive");
+    let passes: Mutex<Vec<(usize, Vec<Float>)>> = Mutex::new(Vec::new());
+    render_progressive(&scene, &mut integrator, 1, |film: &Film, pass: usize| {
+        passes.lock().unwrap().push((pass, film.get_rgb(1.0)));
+    });
+    std::fs::remove_file(&path).unwrap();
+    let passes: Vec<(usize, Vec<Float>)> = passes.into_inner().unwrap();
+    let numbers: Vec<usize> = passes.iter().map(|(pass, _rgb)| *pass).collect();
+    assert_eq!(numbers, (1..=8).collect::<Vec<usize>>());
+    // the image changes from pass to pass ...
+    for pair in passes.windows(2) {
+        assert_ne!(pair[0].1, pair[1].1);
+    }
+    // ... and ends where a regular render does
+    let last: &Vec<Float> = &passes[7].1;
+    for (v, e) in last.iter().zip(expected.iter()) {
+        assert!((v - e).abs() < 1e-5, "{} != {}", v, e);
+    }
+}
+
+#[test]
+fn progressive_keeps_stratification() {
+    // the pixel samplers build their (shuffled) pattern for all
+    // samples of a pixel, each pass has to continue the same pattern
+    for name in ["stratified", "02sequence"].iter() {
+        let (mut integrator, scene, path) = setup(name, &format!("{}_regular", name));
+        integrator.render(&scene, 1);
+        let expected: Vec<u8> = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let (mut integrator, scene, path) = setup(name, &format!("{}_progressive", name));
+        render_progressive(&scene, &mut integrator, 1, |_film: &Film, _pass: usize| {});
+        let image: Vec<u8> = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // the film sums up the samples of each pass, only the
+        // rounding of the sums differs from a regular render
+        assert!(image == expected, "{}: images differ", name);
+    }
+}
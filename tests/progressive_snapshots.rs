@@ -0,0 +1,52 @@
+//! With **RenderOptions::progressive** set, **pbrt_cleanup()** hands a
+//! snapshot of the (cropped) film to the callback after every pass,
+//! and the last one is the final image.
+
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+use common::{film, params, sampler, sky};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::film::FilmSnapshot;
+
+#[test]
+fn snapshot_after_each_pass() {
+    let (mut film, path) = film("pbrt_progressive_snapshots.png", 16, 16);
+    film.add_floats(String::from("cropwindow"), vec![0.25, 0.75, 0.5, 0.75]);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 2.0, -4.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(sampler("sobol", 4))
+        .integrator(params("path"))
+        .world_begin()
+        .light_source(sky(1.0))
+        .shape(params("sphere"));
+    let snapshots: Arc<Mutex<Vec<FilmSnapshot>>> = Arc::new(Mutex::new(Vec::new()));
+    let on_pass: Arc<Mutex<Vec<FilmSnapshot>>> = snapshots.clone();
+    let render_options = &mut builder.api_state.render_options;
+    render_options.progressive = true;
+    render_options.on_pass_complete = Some(Box::new(move |snapshot: &FilmSnapshot| {
+        on_pass.lock().unwrap().push(snapshot.clone());
+    }));
+    builder.world_end();
+    let written = image::open(&path).unwrap().to_rgb();
+    std::fs::remove_file(&path).unwrap();
+
+    let snapshots = snapshots.lock().unwrap();
+    let passes: Vec<usize> = snapshots.iter().map(|s| s.samples_per_pixel).collect();
+    assert_eq!(passes, vec![1, 2, 3, 4]);
+    for snapshot in snapshots.iter() {
+        assert_eq!((snapshot.resolution.x, snapshot.resolution.y), (8, 4));
+        assert_eq!(snapshot.rgb.len(), 3 * 8 * 4);
+    }
+    // the last snapshot is what got written
+    let last_path = std::env::temp_dir().join("pbrt_progressive_snapshots_last.png");
+    snapshots[3].write_png(last_path.to_str().unwrap()).unwrap();
+    let last = image::open(&last_path).unwrap().to_rgb();
+    std::fs::remove_file(&last_path).unwrap();
+    assert_eq!(last.into_raw(), written.into_raw());
+}
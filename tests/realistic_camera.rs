@@ -0,0 +1,65 @@
+//! Renders a uniformly bright environment through a lens system
+//! described by a file next to the scene (found via the search
+//! directory), and compares it to the perspective camera.
+
+use std::path::PathBuf;
+
+mod common;
+
+use common::{film, params, render, sampler, sky};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::paramset::ParamSet;
+use pbrt::core::pbrt::Float;
+
+// double Gauss lens from pbrt-v3's scenes, scaled to 50 mm focal length
+const DGAUSS_50MM: &str = "# radius axpos N aperture
+29.475 3.76 1.67 25.2
+84.83 0.12 1 25.2
+19.275 4.025 1.67 23
+40.77 3.275 1.699 23
+12.75 5.705 1 18
+0 4.5 0 17.1
+-14.495 1.18 1.603 17
+40.77 6.065 1.658 20
+-20.385 0.19 1 20
+437.065 3.22 1.717 20
+-39.73 5 1 20
+";
+
+/// Renders a 16x16 image of a white sky with _camera_ and returns the
+/// value of the center pixel (green channel).
+fn render_center(camera: ParamSet, search_directory: Option<PathBuf>) -> Float {
+    let (film, path) = film("pbrt_realistic_camera.png", 16, 16);
+    let mut builder = SceneBuilder::new(1);
+    if let Some(search_directory) = search_directory {
+        builder.search_directory(search_directory);
+    }
+    builder
+        .camera(camera)
+        .film(film)
+        .sampler(sampler("halton", 16))
+        .integrator(params("path"))
+        .world_begin()
+        .light_source(sky(1.0));
+    render(builder, &path)[3 * (8 * 16 + 8) + 1]
+}
+
+#[test]
+fn realistic_camera_with_lens_file() {
+    let directory: PathBuf = std::env::temp_dir().join("pbrt_realistic_camera");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("dgauss.50mm.dat"), DGAUSS_50MM).unwrap();
+    let mut realistic = params("realistic");
+    realistic.add_string(String::from("lensfile"), String::from("dgauss.50mm.dat"));
+    realistic.add_float(String::from("aperturediameter"), 10.0);
+    let through_lens: Float = render_center(realistic, Some(directory.clone()));
+    std::fs::remove_dir_all(&directory).unwrap();
+    let pinhole: Float = render_center(params("perspective"), None);
+    // with simple weighting the center pixel only loses the rays
+    // which are sampled within the bounds of the (round) exit pupil but
+    // miss it, about 1 - pi/4 of them
+    assert!((pinhole - 1.0).abs() < 1e-3, "{}", pinhole);
+    let expected: Float = std::f32::consts::FRAC_PI_4 as Float;
+    assert!((through_lens - expected).abs() < 0.05, "{}", through_lens);
+}
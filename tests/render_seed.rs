@@ -0,0 +1,39 @@
+//! Renders with the same render seed are bit-identical, independent of
+//! the number of threads, a different seed gives different noise.
+
+mod common;
+
+use common::{build, film, params, sampler, sphere};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::pbrt::Float;
+
+/// Renders a matte sphere lit by a point light with the random
+/// sampler and returns the film's RGB values.
+fn render(num_threads: u8, seed: u64) -> Vec<Float> {
+    let (film, path) = film(&format!("pbrt_seed_{}_{}.exr", num_threads, seed), 16, 16);
+    let mut builder = SceneBuilder::new(num_threads);
+    builder
+        .film(film)
+        .sampler(sampler("random", 4))
+        .integrator(params("path"));
+    builder.api_state.render_options.seed = seed;
+    builder
+        .world_begin()
+        .light_source(params("point"))
+        .material(params("matte"))
+        .translate(0.0, 0.0, 3.0)
+        .shape(sphere(1.0));
+    let (mut integrator, scene) = build(builder);
+    integrator.render(&scene, num_threads);
+    std::fs::remove_file(&path).unwrap();
+    integrator.get_camera().get_film().get_rgb(1.0)
+}
+
+#[test]
+fn same_seed_renders_same_image() {
+    let image: Vec<Float> = render(1, 7);
+    assert!(image.iter().any(|v| *v > 0.0));
+    assert!(render(3, 7) == image);
+    assert!(render(1, 8) != image);
+}
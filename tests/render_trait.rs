@@ -0,0 +1,100 @@
+//! The tile based render loop is provided by **SamplerRender**, so an
+//! integrator rendered on its own (through **Render**) produces the
+//! same image, bit for bit, as the one selected by the scene
+//! description. Both have to match the image of the render loop before
+//! it moved into **SamplerRender**, stored as a checksum of the RGB
+//! values (the RGB spectrum only, sampled spectra give other values).
+
+mod common;
+
+use common::{build, film, floor, params, sampler, sky};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::integrator::{Integrator, Render, SamplerIntegrator, SamplerRender};
+use pbrt::core::pbrt::Float;
+use pbrt::core::progressreporter::{CancellationToken, NoProgress};
+use pbrt::core::scene::Scene;
+
+/// Checksums of the images rendered by the loop of Integrator::render()
+/// before it moved into **SamplerRender**.
+const PATH_CHECKSUM: u64 = 0xd875_1a13_fb18_fe63;
+const AO_CHECKSUM: u64 = 0xcc1c_5dd1_4720_a835;
+
+/// The FNV-1a hash of the bits of the RGB values.
+fn checksum(rgb: &[Float]) -> u64 {
+    rgb.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, v| {
+        (hash ^ v.to_bits() as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// A sphere on a floor under an environment light, to be rendered
+/// with the integrator _name_.
+fn setup(name: &str, filename: &str) -> (Box<Integrator>, Scene) {
+    let (film, _path) = film(filename, 24, 16);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 2.0, -5.0, 0.0, 0.5, 0.0, 0.0, 1.0, 0.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(sampler("halton", 4))
+        .integrator(params(name))
+        .world_begin()
+        .light_source(sky(1.0))
+        .shape(floor(0.0, 5.0))
+        .shape(params("sphere"));
+    build(builder)
+}
+
+/// Renders the scene with the integrator _name_ twice, once through
+/// **Integrator** and once through the trait implementation of the
+/// integrator itself (picked by _render_), and returns both images.
+fn render_both(
+    name: &str,
+    render: &dyn Fn(SamplerIntegrator, &Scene) -> Vec<Float>,
+) -> (Vec<Float>, Vec<Float>) {
+    let filename: String = format!("pbrt_render_trait_{}.png", name);
+    let (mut integrator, scene) = setup(name, &filename);
+    integrator.render(&scene, 1);
+    let expected: Vec<Float> = integrator.get_camera().get_film().get_rgb(1.0);
+    let (integrator, scene) = setup(name, &filename);
+    let rgb: Vec<Float> = match *integrator {
+        Integrator::Sampler(integrator) => render(integrator, &scene),
+        _ => unreachable!(),
+    };
+    std::fs::remove_file(std::env::temp_dir().join(&filename)).unwrap();
+    (expected, rgb)
+}
+
+/// Renders with the default render() of **SamplerRender**.
+fn render<T: SamplerRender>(mut integrator: T, scene: &Scene) -> Vec<Float> {
+    let progress: NoProgress = NoProgress::default();
+    let cancel: CancellationToken = CancellationToken::new();
+    integrator.render(scene, 1, &progress, &cancel);
+    integrator.get_camera().get_film().get_rgb(1.0)
+}
+
+#[test]
+fn path_renders_on_its_own() {
+    let (expected, rgb) = render_both("path", &|integrator, scene| match integrator {
+        SamplerIntegrator::Path(path) => render(path, scene),
+        _ => unreachable!(),
+    });
+    assert!(expected.iter().any(|v| *v > 0.0));
+    assert!(rgb == expected);
+    if !cfg!(feature = "sampled-spectrum") {
+        assert_eq!(checksum(&rgb), PATH_CHECKSUM);
+    }
+}
+
+#[test]
+fn ao_renders_on_its_own() {
+    let (expected, rgb) = render_both("ambientocclusion", &|integrator, scene| match integrator {
+        SamplerIntegrator::AO(ao) => render(ao, scene),
+        _ => unreachable!(),
+    });
+    assert!(expected.iter().any(|v| *v > 0.0));
+    assert!(rgb == expected);
+    if !cfg!(feature = "sampled-spectrum") {
+        assert_eq!(checksum(&rgb), AO_CHECKSUM);
+    }
+}
@@ -0,0 +1,92 @@
+//! Renders a floor lit by a grid of small spherical area lights with
+//! the ReSTIR integrator and compares it with a converged rendering
+//! of the path tracer (direct lighting only).
+
+mod common;
+
+use common::{build, film, floor, integrator, params, perspective, sampler, sphere};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::paramset::ParamSet;
+use pbrt::core::pbrt::Float;
+
+/// Renders the scene with _integrator_ and _spp_ samples per pixel and
+/// returns the RGB values of the image.
+fn render(integrator: ParamSet, spp: i32) -> Vec<Float> {
+    let (film, path) = film(
+        &format!("pbrt_restir_{}_{}.png", integrator.name, spp),
+        16,
+        16,
+    );
+    let mut builder = SceneBuilder::new(1);
+    builder
+        // looking down at the floor, the lights are outside of the image
+        .look_at(0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0)
+        .camera(perspective(60.0))
+        .film(film)
+        .sampler(sampler("random", spp))
+        .integrator(integrator)
+        .world_begin();
+    // 16 lights of different brightness and color
+    for i in 0..16 {
+        let (x, z): (Float, Float) = (-1.5 + (i % 4) as Float, -1.5 + (i / 4) as Float);
+        let mut light = params("diffuse");
+        let l: Float = 20.0 + 10.0 * (i % 5) as Float;
+        light.add_rgb(
+            String::from("L"),
+            [l, l * (0.5 + 0.1 * (i % 3) as Float), l * 0.75],
+        );
+        builder
+            .attribute_begin()
+            .translate(x, 0.5, z)
+            .area_light_source(light)
+            .shape(sphere(0.05))
+            .attribute_end();
+    }
+    builder.material(params("matte")).shape(floor(0.0, 10.0));
+    let (mut integrator, scene) = build(builder);
+    assert_eq!(scene.lights.len(), 16);
+    integrator.render(&scene, 1);
+    let rgb: Vec<Float> = integrator.get_camera().get_film().get_rgb(1.0);
+    std::fs::remove_file(&path).unwrap();
+    rgb
+}
+
+fn direct_path() -> ParamSet {
+    integrator("path", 1)
+}
+
+fn mean(rgb: &[Float]) -> Float {
+    rgb.iter().sum::<Float>() / rgb.len() as Float
+}
+
+fn rms_error(rgb: &[Float], reference: &[Float]) -> Float {
+    let sum: Float = rgb
+        .iter()
+        .zip(reference.iter())
+        .map(|(v, r)| (v - r) * (v - r))
+        .sum();
+    (sum / rgb.len() as Float).sqrt()
+}
+
+#[test]
+fn restir_converges_to_path_tracer() {
+    let reference: Vec<Float> = render(direct_path(), 1024);
+    assert!(mean(&reference) > 0.0);
+    let coarse: Vec<Float> = render(params("restir"), 4);
+    let fine: Vec<Float> = render(params("restir"), 64);
+    // the image as a whole agrees (the reuse among neighbors is
+    // slightly biased) ...
+    let relative: Float = (mean(&fine) - mean(&reference)).abs() / mean(&reference);
+    assert!(relative < 0.05, "mean differs by {}", relative);
+    // ... and so do the pixels, more and more with more samples
+    let coarse_error: Float = rms_error(&coarse, &reference);
+    let fine_error: Float = rms_error(&fine, &reference);
+    assert!(
+        fine_error < 0.5 * coarse_error,
+        "error {} with 64 samples, {} with 4 samples",
+        fine_error,
+        coarse_error
+    );
+    assert!(fine_error < 0.1 * mean(&reference));
+}
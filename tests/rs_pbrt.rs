@@ -0,0 +1,91 @@
+//! Runs the **rs_pbrt** executable on tiny scenes.
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const SCENE: &str = r#"
+LookAt 0 0 -5  0 0 0  0 1 0
+Camera "perspective" "float fov" [ 30 ]
+Sampler "random" "integer pixelsamples" [ 64 ]
+Film "image" "integer xresolution" [ 640 ] "integer yresolution" [ 480 ]
+  "string filename" [ "ignored.png" ]
+WorldBegin
+LightSource "point" "rgb I" [ 10 10 10 ] "point from" [ 0 0 -5 ]
+Material "matte"
+Shape "sphere" "float radius" [ 1 ]
+WorldEnd
+"#;
+
+fn write_scene(name: &str, scene: &str) -> PathBuf {
+    let path: PathBuf = std::env::temp_dir().join(name);
+    std::fs::write(&path, scene).unwrap();
+    path
+}
+
+fn rs_pbrt(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rs_pbrt"))
+        .args(args)
+        .output()
+        .expect("failed to run rs_pbrt")
+}
+
+#[test]
+fn overrides() {
+    let scene: PathBuf = write_scene("rs_pbrt_overrides.pbrt", SCENE);
+    let image: PathBuf = std::env::temp_dir().join("rs_pbrt_overrides.png");
+    let output: Output = rs_pbrt(&[
+        "-i",
+        scene.to_str().unwrap(),
+        "--spp",
+        "1",
+        "--resolution",
+        "8x8",
+        "--outfile",
+        image.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let png = image::open(&image).unwrap().to_rgb();
+    assert_eq!(png.dimensions(), (8, 8));
+    std::fs::remove_file(&image).unwrap();
+}
+
+#[test]
+fn stdout_image() {
+    let scene: PathBuf = write_scene("rs_pbrt_stdout.pbrt", SCENE);
+    let output: Output = rs_pbrt(&[
+        "-i",
+        scene.to_str().unwrap(),
+        "--quick",
+        "--resolution",
+        "16x8",
+        "--outfile",
+        "-",
+    ]);
+    assert!(output.status.success());
+    let png = image::load_from_memory(&output.stdout).unwrap().to_rgb();
+    // half the resolution
+    assert_eq!(png.dimensions(), (8, 4));
+}
+
+#[test]
+fn exit_codes() {
+    let scene: PathBuf = write_scene(
+        "rs_pbrt_parse_error.pbrt",
+        &SCENE.replace("\"sphere\"", "\"no_such_shape\""),
+    );
+    let output: Output = rs_pbrt(&["-i", scene.to_str().unwrap(), "--spp", "1"]);
+    assert_eq!(output.status.code(), Some(1));
+    let scene: PathBuf = write_scene(
+        "rs_pbrt_render_error.pbrt",
+        &SCENE.replace("\"random\"", "\"no_such_sampler\""),
+    );
+    let output: Output = rs_pbrt(&["-i", scene.to_str().unwrap(), "--spp", "1"]);
+    assert_eq!(output.status.code(), Some(2));
+    // bad overrides of a valid scene
+    let scene: PathBuf = write_scene("rs_pbrt_bad_override.pbrt", SCENE);
+    for args in [["--spp", "abc"], ["--spp", "0"], ["--resolution", "8by8"]].iter() {
+        let output: Output = rs_pbrt(&["-i", scene.to_str().unwrap(), args[0], args[1]]);
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stdout).contains("ERROR:"));
+    }
+}
@@ -0,0 +1,70 @@
+//! The render loop shared by the sampler integrators produces the
+//! same image, bit for bit, regardless of the number of threads.
+
+mod common;
+
+use common::{build, film, floor, params, sampler, sphere};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::geometry::Point3f;
+use pbrt::core::pbrt::Float;
+
+/// Renders a sphere on a floor with the integrator _name_ and the
+/// sampler _sampler_name_ and returns the film's RGB values.
+fn render(name: &str, sampler_name: &str, num_threads: u8) -> Vec<Float> {
+    let (mut film, path) = film(
+        &format!(
+            "pbrt_sampler_render_{}_{}_{}.exr",
+            name, sampler_name, num_threads
+        ),
+        40,
+        30,
+    );
+    film.add_int(String::from("tilesize"), 8);
+    let mut light = params("point");
+    light.add_point3f(
+        String::from("from"),
+        Point3f {
+            x: 2.0,
+            y: 4.0,
+            z: -2.0,
+        },
+    );
+    light.add_rgb(String::from("I"), [10.0, 10.0, 10.0]);
+    let mut builder = SceneBuilder::new(num_threads);
+    builder
+        .look_at(0.0, 2.0, -5.0, 0.0, 0.5, 0.0, 0.0, 1.0, 0.0)
+        .camera(params("perspective"))
+        // overlapping the neighboring tiles
+        .pixel_filter(params("gaussian"))
+        .film(film)
+        .sampler(sampler(sampler_name, 4))
+        .integrator(params(name))
+        .world_begin()
+        .light_source(light)
+        .material(params("matte"))
+        .shape(floor(0.0, 5.0))
+        .shape(sphere(1.0));
+    let (mut integrator, scene) = build(builder);
+    integrator.render(&scene, num_threads);
+    std::fs::remove_file(&path).unwrap();
+    integrator.get_camera().get_film().get_rgb(1.0)
+}
+
+#[test]
+fn path_is_independent_of_threads() {
+    // the padded sample bounds of the Sobol sampler (the resolution
+    // isn't a power of two) don't depend on the tiles either
+    for sampler_name in ["halton", "sobol"].iter() {
+        let rgb: Vec<Float> = render("path", sampler_name, 1);
+        assert!(rgb.iter().any(|v| *v > 0.0));
+        assert!(render("path", sampler_name, 8) == rgb, "{}", sampler_name);
+    }
+}
+
+#[test]
+fn ao_is_independent_of_threads() {
+    let rgb: Vec<Float> = render("ambientocclusion", "halton", 1);
+    assert!(rgb.iter().any(|v| *v > 0.0));
+    assert!(render("ambientocclusion", "halton", 8) == rgb);
+}
@@ -0,0 +1,82 @@
+//! A "scale" spectrum texture whose _tex2_ is a float texture scales
+//! every channel of _tex1_ by the same value.
+
+mod common;
+
+use common::{film, floor, integrator, params, render, sampler, sky};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::paramset::ParamSet;
+use pbrt::core::pbrt::Float;
+
+fn texture(name: &str, tex_type: &str, tex_name: &str) -> ParamSet {
+    let mut params = params(name);
+    params.tex_type = String::from(tex_type);
+    params.tex_name = String::from(tex_name);
+    params
+}
+
+/// Renders a matte floor under a white sky. _kd_ is called after
+/// **WorldBegin** to define textures and returns the matte parameters.
+fn render_floor(name: &str, kd: &dyn Fn(&mut SceneBuilder) -> ParamSet) -> Vec<Float> {
+    let (film, path) = film(&format!("pbrt_scale_texture_{}.png", name), 4, 4);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(sampler("halton", 16))
+        .integrator(integrator("path", 1))
+        .world_begin()
+        .light_source(sky(1.0));
+    let matte = kd(&mut builder);
+    builder.material(matte).shape(floor(0.0, 10.0));
+    render(builder, &path)
+}
+
+#[test]
+fn scale_spectrum_by_float_texture() {
+    // the samples are the same for all renders, so the images only
+    // differ by the reflectance of the floor
+    let expected: Vec<Float> = render_floor("reference", &|_builder| {
+        let mut matte = params("matte");
+        matte.add_rgb(String::from("Kd"), [0.4, 0.2, 0.1]);
+        matte
+    });
+    let by_value: Vec<Float> = render_floor("value", &|builder| {
+        let mut tinted = texture("tinted", "spectrum", "scale");
+        tinted.add_rgb(String::from("tex1"), [0.8, 0.4, 0.2]);
+        tinted.add_float(String::from("tex2"), 0.5);
+        builder.texture(tinted);
+        let mut matte = params("matte");
+        matte.add_texture(String::from("Kd"), String::from("tinted"));
+        matte
+    });
+    let by_texture: Vec<Float> = render_floor("texture", &|builder| {
+        let mut mask = texture("mask", "float", "constant");
+        mask.add_float(String::from("value"), 0.5);
+        builder.texture(mask);
+        let mut tinted = texture("tinted", "spectrum", "scale");
+        tinted.add_rgb(String::from("tex1"), [0.8, 0.4, 0.2]);
+        tinted.add_texture(String::from("tex2"), String::from("mask"));
+        builder.texture(tinted);
+        let mut matte = params("matte");
+        matte.add_texture(String::from("Kd"), String::from("tinted"));
+        matte
+    });
+    for (i, e) in expected.iter().enumerate() {
+        assert!(*e > 0.0);
+        assert!(
+            (by_value[i] - e).abs() < 1e-4 * e.max(1.0),
+            "{} != {}",
+            by_value[i],
+            e
+        );
+        assert!(
+            (by_texture[i] - e).abs() < 1e-4 * e.max(1.0),
+            "{} != {}",
+            by_texture[i],
+            e
+        );
+    }
+}
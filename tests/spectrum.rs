@@ -0,0 +1,63 @@
+//! Converts RGB values to spectra and back, for the RGB spectra of the
+//! default build and the sampled spectra of the sampled-spectrum
+//! feature.
+
+use pbrt::core::pbrt::{Float, Spectrum};
+use pbrt::core::rng::Rng;
+use pbrt::core::spectrum::SpectrumType;
+
+fn random_colors() -> Vec<[Float; 3]> {
+    let mut rng: Rng = Rng::new();
+    let mut colors: Vec<[Float; 3]> = vec![
+        [0.0, 0.0, 0.0],
+        [1.0, 1.0, 1.0],
+        [0.25, 0.5, 0.75],
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.0, 0.0, 1.0],
+    ];
+    for _ in 0..100 {
+        colors.push([
+            rng.uniform_float(),
+            rng.uniform_float(),
+            rng.uniform_float(),
+        ]);
+    }
+    colors
+}
+
+#[test]
+fn illuminant_round_trip() {
+    for color in random_colors().iter() {
+        let mut rgb: [Float; 3] = [0.0; 3];
+        Spectrum::from_rgb_type(color, SpectrumType::Illuminant).to_rgb(&mut rgb);
+        for c in 0..3 {
+            assert!(
+                (rgb[c] - color[c]).abs() < 2e-3,
+                "{:?} became {:?}",
+                color,
+                rgb
+            );
+        }
+    }
+}
+
+#[test]
+fn reflectance_round_trip() {
+    let white: Spectrum = Spectrum::from_rgb_type(&[1.0; 3], SpectrumType::Illuminant);
+    for color in random_colors().iter() {
+        // lit by white light, the reflected light has the RGB values
+        // (the reflectance on its own is relative to an equal energy
+        // spectrum, not to the white point of sRGB)
+        let mut rgb: [Float; 3] = [0.0; 3];
+        (Spectrum::from_rgb(color) * white).to_rgb(&mut rgb);
+        for c in 0..3 {
+            assert!(
+                (rgb[c] - color[c]).abs() < 2e-3,
+                "{:?} became {:?}",
+                color,
+                rgb
+            );
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Whitted and direct lighting recurse for perfectly specular
+//! surfaces until "maxdepth" is reached, which also ends the
+//! recursion between two parallel mirrors.
+
+mod common;
+
+use common::{film, glow, integrator, params, perspective, quad, render, sampler};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::pbrt::{Float, Spectrum};
+
+/// A glowing mirror (reflectance 0.5, emitting 1) in the plane _z_.
+fn mirror(builder: &mut SceneBuilder, z: Float) {
+    let mut material = params("mirror");
+    material.add_rgb(String::from("Kr"), [0.5; 3]);
+    builder
+        .attribute_begin()
+        .area_light_source(glow(1.0, true))
+        .material(material)
+        .shape(quad([
+            -100.0, -100.0, z, 100.0, -100.0, z, 100.0, 100.0, z, -100.0, 100.0, z,
+        ]))
+        .attribute_end();
+}
+
+/// Renders the center of the corridor between two glowing mirrors
+/// and returns the green channel.
+fn render_center(name: &str, max_depth: i32) -> Float {
+    let (film, path) = film(&format!("pbrt_specular_depth_{}.png", name), 1, 1);
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0)
+        .camera(perspective(1.0))
+        .film(film)
+        .sampler(sampler("halton", 1))
+        .integrator(integrator(name, max_depth))
+        .world_begin();
+    mirror(&mut builder, 1.0);
+    mirror(&mut builder, -1.0);
+    render(builder, &path)[1]
+}
+
+#[test]
+fn parallel_mirrors_stop_at_max_depth() {
+    for integrator in &["whitted", "directlighting"] {
+        for max_depth in &[1, 2, 3, 4, 32] {
+            // each bounce adds the emission of the next mirror, halved
+            // once more
+            let sum: Float = (0..*max_depth).map(|k| (0.5 as Float).powi(k)).sum();
+            let mut expected: [Float; 3] = [0.0; 3];
+            Spectrum::new(sum).to_rgb(&mut expected);
+            let value: Float = render_center(integrator, *max_depth);
+            assert!(
+                (value - expected[1]).abs() < 1e-3,
+                "{} with maxdepth {}: {} != {}",
+                integrator,
+                max_depth,
+                value,
+                expected[1]
+            );
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! The counters of the `stats` feature: a ray hitting a triangle
+//! counts as one test and one hit, and threads hand their counters
+//! over to the totals when they exit, instead of keeping them
+//! allocated.
+#![cfg(feature = "stats")]
+
+use std::sync::{Arc, Mutex};
+
+use pbrt::core::geometry::{Point3f, Ray, Vector3f};
+use pbrt::core::pbrt::Float;
+use pbrt::core::stats::{
+    named_stat_add, named_stat_threads, print_stats, stat_name_id, stat_threads, NamedStat,
+};
+use pbrt::core::transform::Transform;
+use pbrt::shapes::triangle::{Triangle, TriangleMesh};
+
+pbrt::stat_counter!("Test/Thread-local events", N_EVENTS);
+
+lazy_static::lazy_static! {
+    // the tests check which threads hold counters, so they run one
+    // after the other
+    static ref SERIAL: Mutex<()> = Mutex::new(());
+}
+
+fn report() -> String {
+    let mut report: Vec<u8> = Vec::new();
+    print_stats(&mut report).unwrap();
+    String::from_utf8(report).unwrap()
+}
+
+/// The line of the report containing _name_, split at whitespace.
+fn report_line(name: &str) -> Vec<String> {
+    report()
+        .lines()
+        .find(|line| line.contains(name))
+        .unwrap()
+        .split_whitespace()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn ray_triangle_test_and_hit() {
+    let _serial = SERIAL.lock().unwrap();
+    let p = vec![
+        Point3f {
+            x: -1.0,
+            y: -1.0,
+            z: 0.0,
+        },
+        Point3f {
+            x: 1.0,
+            y: -1.0,
+            z: 0.0,
+        },
+        Point3f {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+    ];
+    let mesh = Arc::new(TriangleMesh::new(
+        Transform::default(),
+        Transform::default(),
+        false,
+        1,
+        vec![0, 1, 2],
+        3,
+        p,
+        vec![],
+        vec![],
+        vec![],
+        None,
+        None,
+    ));
+    let ray = Ray {
+        o: Point3f {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        d: Vector3f {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        },
+        t_max: Float::INFINITY,
+        time: 0.0,
+        medium: None,
+        differential: None,
+    };
+    // on a thread of its own, which exits before the next test
+    let thread = std::thread::spawn(move || Triangle::new(mesh, 0).intersect(&ray).is_some());
+    assert!(thread.join().unwrap());
+    // hits / tests
+    let counts: Vec<String> = report_line("Ray-triangle intersection tests");
+    assert_eq!(&counts[3..6], &["1", "/", "1"]);
+}
+
+#[test]
+fn counters_of_exited_threads_are_freed() {
+    let _serial = SERIAL.lock().unwrap();
+    for _ in 0..100 {
+        let threads: Vec<_> = (0..8)
+            .map(|_| std::thread::spawn(|| pbrt::stat_inc!(N_EVENTS)))
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+    assert_eq!(stat_threads("Test/Thread-local events"), 0);
+    let counts: Vec<String> = report_line("Thread-local events");
+    assert_eq!(counts.last().unwrap(), "800");
+}
+
+#[test]
+fn named_counters_of_exited_threads_are_freed() {
+    let _serial = SERIAL.lock().unwrap();
+    let id: u32 = stat_name_id(Some("short-lived threads"));
+    for _ in 0..100 {
+        let threads: Vec<_> = (0..8)
+            .map(|_| std::thread::spawn(move || named_stat_add(id, NamedStat::RayTests, 1)))
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+    assert_eq!(named_stat_threads(), 0);
+    let counts: Vec<String> = report_line("short-lived threads");
+    assert_eq!(&counts[2..4], &["800", "tests"]);
+}
@@ -0,0 +1,156 @@
+//! Streams a film to a mock tev server and checks the packets.
+#![cfg(feature = "tev")]
+
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use pbrt::core::film::Film;
+use pbrt::core::filter::Filter;
+use pbrt::core::geometry::{Bounds2f, Bounds2i, Point2f, Point2i, Vector2f};
+use pbrt::core::pbrt::{Float, Spectrum};
+use pbrt::filters::boxfilter::BoxFilter;
+
+/// One packet: type and payload.
+type Packet = (u8, Vec<u8>);
+
+fn read_packets(mut stream: TcpStream) -> Vec<Packet> {
+    let mut bytes: Vec<u8> = Vec::new();
+    stream.read_to_end(&mut bytes).unwrap();
+    let mut packets: Vec<Packet> = Vec::new();
+    let mut offset: usize = 0;
+    while offset < bytes.len() {
+        assert!(offset + 5 <= bytes.len(), "truncated packet header");
+        let mut length: [u8; 4] = [0; 4];
+        length.copy_from_slice(&bytes[offset..offset + 4]);
+        let length: usize = u32::from_le_bytes(length) as usize;
+        assert!(length >= 5, "packet too short");
+        assert!(offset + length <= bytes.len(), "truncated packet");
+        packets.push((
+            bytes[offset + 4],
+            bytes[offset + 5..offset + length].to_vec(),
+        ));
+        offset += length;
+    }
+    packets
+}
+
+/// Reads the payload of a packet in order.
+struct Payload<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Payload<'a> {
+    fn bool(&mut self) -> bool {
+        let value: bool = self.bytes[0] != 0;
+        self.bytes = &self.bytes[1..];
+        value
+    }
+    fn string(&mut self) -> String {
+        let end: usize = self.bytes.iter().position(|b| *b == 0).unwrap();
+        let value: String = String::from_utf8(self.bytes[..end].to_vec()).unwrap();
+        self.bytes = &self.bytes[end + 1..];
+        value
+    }
+    fn i32(&mut self) -> i32 {
+        let mut value: [u8; 4] = [0; 4];
+        value.copy_from_slice(&self.bytes[..4]);
+        self.bytes = &self.bytes[4..];
+        i32::from_le_bytes(value)
+    }
+    fn f32(&mut self) -> f32 {
+        let mut value: [u8; 4] = [0; 4];
+        value.copy_from_slice(&self.bytes[..4]);
+        self.bytes = &self.bytes[4..];
+        f32::from_le_bytes(value)
+    }
+}
+
+#[test]
+fn tiles_are_streamed() {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port: u16 = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || read_packets(listener.accept().unwrap().0));
+
+    let filter = Box::new(Filter::Bx(BoxFilter {
+        radius: Vector2f { x: 0.5, y: 0.5 },
+        inv_radius: Vector2f { x: 2.0, y: 2.0 },
+    }));
+    let crop = Bounds2f {
+        p_min: Point2f { x: 0.0, y: 0.0 },
+        p_max: Point2f { x: 1.0, y: 1.0 },
+    };
+    let film = Film::new(
+        Point2i { x: 4, y: 2 },
+        crop,
+        filter,
+        35.0,
+        String::new(),
+        1.0,
+        Float::INFINITY,
+    );
+    let display = film.open_tev("127.0.0.1", port, "test").unwrap();
+    // the left and the right half of the image
+    let tiles = (0..2).map(|i| {
+        let sample_bounds = Bounds2i {
+            p_min: Point2i { x: 2 * i, y: 0 },
+            p_max: Point2i { x: 2 * i + 2, y: 2 },
+        };
+        let mut tile = film.get_film_tile(&sample_bounds);
+        for p in &sample_bounds {
+            let p_film = Point2f {
+                x: p.x as Float + 0.5,
+                y: p.y as Float + 0.5,
+            };
+            let mut l = Spectrum::new(0.25 * (i + 1) as Float);
+            tile.add_sample(&p_film, &mut l, 1.0);
+        }
+        (i as usize, tile)
+    });
+    film.merge_film_tiles_in_order(tiles, &mut |_| {});
+    display.close().unwrap();
+
+    let packets: Vec<Packet> = server.join().unwrap();
+    assert_eq!(packets.len(), 3);
+    // create image
+    assert_eq!(packets[0].0, 4);
+    let mut payload = Payload {
+        bytes: &packets[0].1,
+    };
+    assert!(payload.bool());
+    assert_eq!(payload.string(), "test");
+    assert_eq!((payload.i32(), payload.i32()), (4, 2));
+    assert_eq!(payload.i32(), 3);
+    for channel in ["R", "G", "B"].iter() {
+        assert_eq!(payload.string(), *channel);
+    }
+    assert!(payload.bytes.is_empty());
+    // one update per tile, covering the pixels the tile's samples
+    // can contribute to (through the filter), with the merged values
+    let expected: [((i32, i32, i32, i32), [f32; 3]); 2] = [
+        ((0, 0, 3, 2), [0.25, 0.25, 0.0]),
+        ((1, 0, 3, 2), [0.25, 0.5, 0.5]),
+    ];
+    for (packet, (bounds, row)) in packets[1..].iter().zip(expected.iter()) {
+        assert_eq!(packet.0, 5);
+        let mut payload = Payload { bytes: &packet.1 };
+        assert!(!payload.bool());
+        assert_eq!(payload.string(), "test");
+        assert_eq!(payload.i32(), 3);
+        for channel in ["R", "G", "B"].iter() {
+            assert_eq!(payload.string(), *channel);
+        }
+        let (x, y) = (payload.i32(), payload.i32());
+        let (width, height) = (payload.i32(), payload.i32());
+        assert_eq!((x, y, width, height), *bounds);
+        // one plane per channel
+        for _channel in 0..3 {
+            for _y in 0..height {
+                for value in row.iter() {
+                    assert!((payload.f32() - value).abs() < 1e-3);
+                }
+            }
+        }
+        assert!(payload.bytes.is_empty());
+    }
+}
@@ -0,0 +1,45 @@
+//! Samplers based on random numbers seed their generator per pixel,
+//! so the noise in the image doesn't depend on how the image is split
+//! into tiles.
+
+mod common;
+
+use common::{build, film, params, sampler, sphere};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::pbrt::Float;
+
+/// Renders a matte sphere lit by a point light with two threads and
+/// tiles of _tile_size_ pixels and returns the film's RGB values.
+fn render(sampler_name: &str, tile_size: i32) -> Vec<Float> {
+    let (mut film, path) = film(
+        &format!("pbrt_tiles_{}_{}.exr", sampler_name, tile_size),
+        20,
+        12,
+    );
+    film.add_int(String::from("tilesize"), tile_size);
+    let mut builder = SceneBuilder::new(2);
+    builder
+        .film(film)
+        .sampler(sampler(sampler_name, 4))
+        .integrator(params("path"))
+        .world_begin()
+        .light_source(params("point"))
+        .material(params("matte"))
+        .translate(0.0, 0.0, 3.0)
+        .shape(sphere(1.0));
+    let (mut integrator, scene) = build(builder);
+    integrator.render(&scene, 2);
+    std::fs::remove_file(&path).unwrap();
+    integrator.get_camera().get_film().get_rgb(1.0)
+}
+
+#[test]
+fn image_is_independent_of_tile_size() {
+    for sampler_name in ["random", "stratified", "02sequence"].iter() {
+        let image: Vec<Float> = render(sampler_name, 16);
+        assert!(image.iter().any(|v| *v > 0.0));
+        assert!(render(sampler_name, 4) == image, "{}", sampler_name);
+        assert!(render(sampler_name, 7) == image, "{}", sampler_name);
+    }
+}
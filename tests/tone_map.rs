@@ -0,0 +1,55 @@
+//! The exposure of the film scales the linear values before the tone
+//! curve is applied to them for PNG output.
+
+mod common;
+
+use common::{build, film, floor, params, sampler};
+// pbrt
+use pbrt::core::api::SceneBuilder;
+use pbrt::core::film::aces_filmic;
+use pbrt::core::pbrt::{gamma_correct, Float, Spectrum};
+
+/// Renders an unoccluded floor with ambient occlusion (which is pi in
+/// every pixel) and returns the pixels of the written PNG file.
+fn render_png(exposure: Float, tone_map: &str) -> Vec<u8> {
+    let (mut film, path) = film(&format!("pbrt_tone_map_{}.png", tone_map), 4, 4);
+    film.add_float(String::from("exposure"), exposure);
+    film.add_string(String::from("tonemap"), String::from(tone_map));
+    let mut builder = SceneBuilder::new(1);
+    builder
+        .look_at(0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0)
+        .camera(params("perspective"))
+        .film(film)
+        .sampler(sampler("02sequence", 4))
+        .integrator(params("ambientocclusion"))
+        .world_begin()
+        .shape(floor(0.0, 10.0));
+    let (mut integrator, scene) = build(builder);
+    integrator.render(&scene, 1);
+    let png = image::open(&path).unwrap().to_rgb();
+    std::fs::remove_file(&path).unwrap();
+    png.into_raw()
+}
+
+#[test]
+fn exposure_is_applied_before_tone_curve() {
+    let mut rgb: [Float; 3] = [0.0; 3];
+    Spectrum::new(std::f32::consts::PI as Float).to_rgb(&mut rgb);
+    let to_byte = |v: Float| -> i32 { (255.0 as Float * gamma_correct(v) + 0.5) as i32 };
+    // two stops down: pi is scaled to about 0.79, which the curve maps
+    // to about 0.71 (instead of a quarter of its value for pi)
+    let png: Vec<u8> = render_png(-2.0, "aces");
+    for (i, byte) in png.iter().enumerate() {
+        let expected: i32 = to_byte(aces_filmic(0.25 * rgb[i % 3]));
+        assert!(
+            (*byte as i32 - expected).abs() <= 1,
+            "{} != {}",
+            byte,
+            expected
+        );
+        assert!((*byte as i32 - to_byte(0.25 * aces_filmic(rgb[i % 3]))).abs() > 10);
+    }
+    // for comparison: without the exposure the clamp curve saturates
+    let png: Vec<u8> = render_png(0.0, "clamp");
+    assert!(png.iter().all(|byte| *byte == 255));
+}